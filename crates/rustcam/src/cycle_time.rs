@@ -0,0 +1,560 @@
+//! Acceleration-aware cycle time estimation.
+//!
+//! Swiss-cheese layer: **Verification**
+//! Extension point: add sources of `Operation`s (toolpaths, job sections,
+//! interpreted G-code) or refine the per-block kinematics in `plan`.
+//!
+//! Distance over feed rate badly underestimates short-segment 3D paths,
+//! where the machine never gets up to speed. This plans every move the
+//! way GRBL does: each block follows a trapezoidal velocity profile
+//! limited by the slowest axis it uses, corner speeds come from the
+//! junction deviation, and a look-ahead pass makes sure the machine can
+//! always brake in time for what comes next. Each operation starts and
+//! ends at rest, as it does around a tool change.
+
+use serde::Serialize;
+
+use crate::gcode::{GcodeParams, JobSection};
+use crate::gcode_interpreter::Program;
+use crate::geometry::Toolpath;
+use crate::machine::MachineCapabilities;
+
+/// Direction components below this are treated as not moving the axis.
+const AXIS_EPSILON: f64 = 1e-9;
+
+/// Kinematic limits of a machine in mm and seconds.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MotionLimits {
+    /// Per-axis top speed (mm/s).
+    pub max_velocity: [f64; 3],
+    /// Per-axis acceleration (mm/s²). Zero or less means unlimited.
+    pub max_acceleration: [f64; 3],
+    /// Junction deviation (mm).
+    pub junction_deviation: f64,
+}
+
+impl MotionLimits {
+    pub fn from_capabilities(caps: &MachineCapabilities) -> Self {
+        Self {
+            max_velocity: caps.max_velocity.map(|v| v / 60.0),
+            max_acceleration: caps.max_acceleration,
+            junction_deviation: caps.junction_deviation,
+        }
+    }
+}
+
+/// One straight move to plan.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Block {
+    pub to: [f64; 3],
+    pub rapid: bool,
+    /// Programmed feed (mm/min); ignored for rapids.
+    pub feed: f64,
+    /// Seconds spent standing still after the move.
+    pub dwell: f64,
+}
+
+/// A run of blocks planned together, from rest to rest.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Operation {
+    pub name: String,
+    pub start: [f64; 3],
+    pub blocks: Vec<Block>,
+}
+
+/// Time and distance for one operation.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct OperationTime {
+    pub name: String,
+    /// Planned time including acceleration and dwells (s).
+    pub time: f64,
+    /// Distance over feed rate, for comparison (s).
+    pub naive_time: f64,
+    pub feed_time: f64,
+    pub rapid_time: f64,
+    pub dwell_time: f64,
+    pub cut_distance: f64,
+    pub rapid_distance: f64,
+    pub moves: usize,
+}
+
+/// A corner of the piecewise-linear velocity profile.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct VelocitySample {
+    /// Seconds since the start of the program.
+    pub t: f64,
+    /// Path speed (mm/min).
+    pub v: f64,
+    /// Index of the move being executed, counted across operations.
+    #[serde(rename = "move")]
+    pub move_index: usize,
+}
+
+/// Planned run time of a whole program.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct CycleTime {
+    pub total: f64,
+    pub naive_total: f64,
+    pub operations: Vec<OperationTime>,
+    pub profile: Vec<VelocitySample>,
+}
+
+/// Blocks for toolpaths as `emit_gcode` would feed them: plunge rate for
+/// the first feed move below `safe_z` after a rapid, feed rate otherwise.
+pub fn blocks_from_toolpaths(toolpaths: &[Toolpath], params: &GcodeParams) -> Vec<Block> {
+    let mut blocks = Vec::new();
+    for tp in toolpaths {
+        let mut last_rapid = true;
+        let first = blocks.len();
+        for mv in &tp.moves {
+            let feed = if mv.z < params.safe_z - 0.01 && last_rapid {
+                params.plunge_rate
+            } else {
+                params.feed_rate
+            };
+            blocks.push(Block {
+                to: [mv.x, mv.y, mv.z],
+                rapid: mv.rapid,
+                feed,
+                dwell: 0.0,
+            });
+            last_rapid = mv.rapid;
+        }
+        // A drilling cycle dwells at the bottom of its last feed.
+        if let Some(cycle) = tp.drill {
+            if let Some(bottom) = blocks[first..].iter_mut().rev().find(|b| !b.rapid) {
+                bottom.dwell += cycle.dwell;
+            }
+        }
+    }
+    blocks
+}
+
+/// One operation per job section, starting above the origin.
+pub fn operations_from_sections(sections: &[JobSection]) -> Vec<Operation> {
+    let mut start = sections
+        .first()
+        .map_or([0.0; 3], |s| [0.0, 0.0, s.params.safe_z]);
+    sections
+        .iter()
+        .enumerate()
+        .map(|(i, s)| {
+            let blocks = blocks_from_toolpaths(&s.toolpaths, &s.params);
+            let op = Operation {
+                name: s
+                    .comments
+                    .first()
+                    .cloned()
+                    .unwrap_or_else(|| format!("Operation {}", i + 1)),
+                start,
+                blocks,
+            };
+            start = op.blocks.last().map_or(start, |b| b.to);
+            op
+        })
+        .collect()
+}
+
+/// Split an interpreted program into operations at each change of tool.
+pub fn operations_from_program(program: &Program, start: [f64; 3]) -> Vec<Operation> {
+    let mut ops: Vec<Operation> = Vec::new();
+    let mut at = start;
+    let mut tool = None;
+    for m in &program.moves {
+        if tool != Some(m.tool) {
+            tool = Some(m.tool);
+            ops.push(Operation {
+                name: format!("T{}", m.tool),
+                start: at,
+                blocks: Vec::new(),
+            });
+        }
+        let to = [m.x, m.y, m.z];
+        ops.last_mut().unwrap().blocks.push(Block {
+            to,
+            rapid: m.rapid,
+            feed: m.feed,
+            dwell: m.dwell,
+        });
+        at = to;
+    }
+    ops
+}
+
+/// Plan every operation in turn.
+pub fn estimate(operations: &[Operation], limits: &MotionLimits) -> CycleTime {
+    let mut out = CycleTime::default();
+    let mut clock = 0.0;
+    let mut index = 0;
+    for op in operations {
+        let time = plan(op, limits, &mut clock, index, &mut out.profile);
+        out.total += time.time;
+        out.naive_total += time.naive_time;
+        index += op.blocks.len();
+        out.operations.push(time);
+    }
+    out
+}
+
+/// Geometry and limits of one block.
+struct Segment {
+    length: f64,
+    dir: [f64; 3],
+    /// Cruise speed (mm/s).
+    speed: f64,
+    accel: f64,
+    dwell: f64,
+}
+
+/// Plan one operation, appending to `profile` from `clock` onwards.
+fn plan(
+    op: &Operation,
+    limits: &MotionLimits,
+    clock: &mut f64,
+    first_index: usize,
+    profile: &mut Vec<VelocitySample>,
+) -> OperationTime {
+    let mut time = OperationTime {
+        name: op.name.clone(),
+        moves: op.blocks.len(),
+        ..Default::default()
+    };
+    let mut from = op.start;
+    let segments: Vec<Segment> = op
+        .blocks
+        .iter()
+        .map(|b| {
+            let seg = segment(from, b, limits);
+            from = b.to;
+            if b.rapid {
+                time.rapid_distance += seg.length;
+            } else {
+                time.cut_distance += seg.length;
+            }
+            let nominal = if b.rapid || b.feed <= 0.0 {
+                seg.speed
+            } else {
+                b.feed / 60.0
+            };
+            if seg.length > 0.0 && nominal > 0.0 {
+                time.naive_time += seg.length / nominal;
+            }
+            time.naive_time += b.dwell;
+            seg
+        })
+        .collect();
+
+    // Highest speed at the start of each block and at the very end.
+    let n = segments.len();
+    let mut v = vec![0.0; n + 1];
+    let mut prev: Option<&Segment> = None;
+    let mut stopped = true;
+    for (i, seg) in segments.iter().enumerate() {
+        if seg.length == 0.0 {
+            // Zero-length moves take the limit of the move after them.
+            v[i] = f64::INFINITY;
+            stopped |= seg.dwell > 0.0;
+            continue;
+        }
+        v[i] = match prev {
+            Some(p) if !stopped => junction_speed(p, seg, limits.junction_deviation),
+            _ => 0.0,
+        };
+        stopped = seg.dwell > 0.0;
+        prev = Some(seg);
+    }
+    for i in (0..n).rev() {
+        if v[i].is_infinite() {
+            v[i] = v[i + 1];
+        }
+    }
+    v[0] = 0.0;
+    // Look-ahead: brake in time for every later limit ...
+    for i in (0..n).rev() {
+        v[i] = v[i].min(reachable(v[i + 1], &segments[i]));
+    }
+    // ... and never ask for more than can be reached from behind.
+    for i in 0..n {
+        v[i + 1] = v[i + 1].min(reachable(v[i], &segments[i]));
+    }
+
+    let start_clock = *clock;
+    for (i, (seg, block)) in segments.iter().zip(&op.blocks).enumerate() {
+        let index = first_index + i;
+        if seg.length > 0.0 {
+            let phases = trapezoid(seg, v[i], v[i + 1]);
+            let t: f64 = phases.iter().map(|p| p.0).sum();
+            if block.rapid {
+                time.rapid_time += t;
+            } else {
+                time.feed_time += t;
+            }
+            sample(profile, *clock, v[i], index);
+            for (dt, speed) in phases {
+                if dt > 0.0 {
+                    *clock += dt;
+                    sample(profile, *clock, speed, index);
+                }
+            }
+        }
+        if seg.dwell > 0.0 {
+            sample(profile, *clock, 0.0, index);
+            *clock += seg.dwell;
+            sample(profile, *clock, 0.0, index);
+            time.dwell_time += seg.dwell;
+        }
+    }
+    time.time = *clock - start_clock;
+    time
+}
+
+fn segment(from: [f64; 3], block: &Block, limits: &MotionLimits) -> Segment {
+    let d = [
+        block.to[0] - from[0],
+        block.to[1] - from[1],
+        block.to[2] - from[2],
+    ];
+    let length = (d[0] * d[0] + d[1] * d[1] + d[2] * d[2]).sqrt();
+    let dir = if length > 0.0 {
+        d.map(|c| c / length)
+    } else {
+        [0.0; 3]
+    };
+    // The slowest axis sets the pace along the direction of travel.
+    let mut speed = f64::INFINITY;
+    let mut accel = f64::INFINITY;
+    let axes = dir
+        .iter()
+        .zip(limits.max_velocity.iter().zip(&limits.max_acceleration));
+    for (c, (&vmax, &amax)) in axes {
+        let c = c.abs();
+        if c < AXIS_EPSILON {
+            continue;
+        }
+        if vmax > 0.0 {
+            speed = speed.min(vmax / c);
+        }
+        if amax > 0.0 {
+            accel = accel.min(amax / c);
+        }
+    }
+    if !block.rapid && block.feed > 0.0 {
+        speed = speed.min(block.feed / 60.0);
+    }
+    Segment {
+        length,
+        dir,
+        speed,
+        accel,
+        dwell: block.dwell,
+    }
+}
+
+/// GRBL's corner speed: the fastest the tool can take the corner while
+/// staying within `deviation` of it at the lower of the two accelerations.
+fn junction_speed(a: &Segment, b: &Segment, deviation: f64) -> f64 {
+    let cap = a.speed.min(b.speed);
+    let cos = -(a.dir[0] * b.dir[0] + a.dir[1] * b.dir[1] + a.dir[2] * b.dir[2]);
+    if cos < -0.999_999 {
+        // Straight on.
+        return cap;
+    }
+    if cos > 0.999_999 || deviation <= 0.0 {
+        return 0.0;
+    }
+    let sin_half = (0.5 * (1.0 - cos)).sqrt();
+    let accel = a.accel.min(b.accel);
+    if accel.is_infinite() {
+        return cap;
+    }
+    cap.min((accel * deviation * sin_half / (1.0 - sin_half)).sqrt())
+}
+
+/// Fastest speed at one end of `seg` given the speed at the other end.
+fn reachable(v: f64, seg: &Segment) -> f64 {
+    if seg.accel.is_infinite() {
+        return f64::INFINITY;
+    }
+    (v * v + 2.0 * seg.accel * seg.length).sqrt()
+}
+
+/// Accelerate, cruise and decelerate phases as (duration, speed at end).
+fn trapezoid(seg: &Segment, entry: f64, exit: f64) -> [(f64, f64); 3] {
+    let (l, a) = (seg.length, seg.accel);
+    if a.is_infinite() {
+        let cruise = seg.speed.max(entry).max(exit);
+        return [(0.0, cruise), (l / cruise, cruise), (0.0, exit)];
+    }
+    // The peak of a triangle profile, if cruise speed is out of reach.
+    let peak = ((2.0 * a * l + entry * entry + exit * exit) / 2.0).sqrt();
+    let top = seg.speed.min(peak).max(entry).max(exit);
+    let up = (top * top - entry * entry) / (2.0 * a);
+    let down = (top * top - exit * exit) / (2.0 * a);
+    let cruise = (l - up - down).max(0.0);
+    [
+        ((top - entry) / a, top),
+        (cruise / top, top),
+        ((top - exit) / a, exit),
+    ]
+}
+
+fn sample(profile: &mut Vec<VelocitySample>, t: f64, v: f64, move_index: usize) {
+    let v = v * 60.0;
+    if let Some(last) = profile.last_mut() {
+        if last.t == t && last.v == v {
+            // A corner belongs to the move that starts there.
+            last.move_index = move_index;
+            return;
+        }
+    }
+    profile.push(VelocitySample { t, v, move_index });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limits(v: f64, a: f64) -> MotionLimits {
+        MotionLimits {
+            max_velocity: [v; 3],
+            max_acceleration: [a; 3],
+            junction_deviation: 0.01,
+        }
+    }
+
+    fn feed(x: f64, y: f64, f: f64) -> Block {
+        Block {
+            to: [x, y, 0.0],
+            rapid: false,
+            feed: f,
+            dwell: 0.0,
+        }
+    }
+
+    fn op(blocks: Vec<Block>) -> Operation {
+        Operation {
+            name: "test".into(),
+            start: [0.0; 3],
+            blocks,
+        }
+    }
+
+    #[test]
+    fn long_move_is_a_trapezoid() {
+        // 100 mm at 60 mm/s with 100 mm/s²: 0.6 s each way covering 18 mm,
+        // then 64 mm of cruise.
+        let t = estimate(&[op(vec![feed(100.0, 0.0, 3600.0)])], &limits(1e3, 100.0));
+        assert!(
+            (t.total - (0.6 + 0.6 + 64.0 / 60.0)).abs() < 1e-9,
+            "{}",
+            t.total
+        );
+        assert!((t.naive_total - 100.0 / 60.0).abs() < 1e-9);
+        let speeds: Vec<f64> = t.profile.iter().map(|s| s.v).collect();
+        assert_eq!(speeds, [0.0, 3600.0, 3600.0, 0.0]);
+    }
+
+    #[test]
+    fn short_move_never_reaches_feed() {
+        // 1 mm at 100 mm/s²: a triangle peaking at 10 mm/s after 0.1 s.
+        let t = estimate(&[op(vec![feed(1.0, 0.0, 6000.0)])], &limits(1e3, 100.0));
+        assert!((t.total - 0.2).abs() < 1e-9);
+        assert!((t.profile[1].v - 600.0).abs() < 1e-9);
+        assert!(t.total > 10.0 * t.naive_total);
+    }
+
+    #[test]
+    fn the_slowest_axis_limits_a_diagonal() {
+        let mut l = limits(1e3, 1e6);
+        l.max_velocity[2] = 10.0;
+        let plunge = Block {
+            to: [0.0, 0.0, -10.0],
+            rapid: true,
+            feed: 0.0,
+            dwell: 0.0,
+        };
+        let t = estimate(&[op(vec![plunge])], &l);
+        assert!((t.total - 1.0).abs() < 0.01, "{}", t.total);
+    }
+
+    #[test]
+    fn corners_slow_down_but_straight_joins_do_not() {
+        let l = limits(1e3, 100.0);
+        let straight = estimate(
+            &[op(vec![feed(50.0, 0.0, 3600.0), feed(100.0, 0.0, 3600.0)])],
+            &l,
+        );
+        assert!((straight.total - (1.2 + 64.0 / 60.0)).abs() < 1e-9);
+        let corner = estimate(
+            &[op(vec![feed(50.0, 0.0, 3600.0), feed(50.0, 50.0, 3600.0)])],
+            &l,
+        );
+        // A square corner at 0.01 mm deviation: sqrt(100 * 0.01 * 0.707 / 0.293).
+        let junction = corner.profile.iter().find(|s| s.move_index == 1).unwrap();
+        assert!(
+            (junction.v / 60.0 - 1.553_773_974).abs() < 1e-6,
+            "{:?}",
+            junction
+        );
+        assert!(corner.total > straight.total);
+        let reverse = estimate(
+            &[op(vec![feed(50.0, 0.0, 3600.0), feed(0.0, 0.0, 3600.0)])],
+            &l,
+        );
+        assert_eq!(
+            reverse
+                .profile
+                .iter()
+                .find(|s| s.move_index == 1)
+                .unwrap()
+                .v,
+            0.0
+        );
+    }
+
+    #[test]
+    fn look_ahead_brakes_before_a_stop() {
+        // Many tiny collinear steps behave like one long move.
+        let steps: Vec<Block> = (1..=100).map(|i| feed(i as f64, 0.0, 3600.0)).collect();
+        let t = estimate(&[op(steps)], &limits(1e3, 100.0));
+        assert!((t.total - (1.2 + 64.0 / 60.0)).abs() < 1e-9);
+        assert!(t.profile.iter().all(|s| s.v <= 3600.0 + 1e-9));
+        assert_eq!(t.profile.last().unwrap().v, 0.0);
+    }
+
+    #[test]
+    fn operations_are_timed_separately_with_dwells() {
+        let mut drill = feed(0.0, 0.0, 600.0);
+        drill.to[2] = -5.0;
+        drill.dwell = 0.5;
+        let ops = [
+            op(vec![feed(100.0, 0.0, 3600.0)]),
+            Operation {
+                name: "drill".into(),
+                start: [0.0; 3],
+                blocks: vec![drill],
+            },
+        ];
+        let t = estimate(&ops, &limits(1e3, 100.0));
+        assert_eq!(t.operations.len(), 2);
+        assert_eq!(t.operations[1].dwell_time, 0.5);
+        assert!((t.total - t.operations.iter().map(|o| o.time).sum::<f64>()).abs() < 1e-9);
+        let last = t.profile.last().unwrap();
+        assert_eq!((last.move_index, last.v), (1, 0.0));
+        assert!((last.t - t.total).abs() < 1e-9);
+    }
+
+    #[test]
+    fn program_operations_split_at_tool_changes() {
+        use crate::gcode_interpreter::{interpret, InterpreterConfig};
+        let program = interpret(
+            "T1 M6\nG0 X10\nG1 X20 F600\nT2 M6\nG1 Y10\nG4 P2\n",
+            &InterpreterConfig::default(),
+        );
+        let ops = operations_from_program(&program, [0.0; 3]);
+        let names: Vec<&str> = ops.iter().map(|o| o.name.as_str()).collect();
+        assert_eq!(names, ["T1", "T2"]);
+        assert_eq!(ops[1].start, [20.0, 0.0, 0.0]);
+        assert_eq!(ops[1].blocks[0].dwell, 2.0);
+    }
+}
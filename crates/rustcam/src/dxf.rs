@@ -0,0 +1,914 @@
+//! DXF parser — ASCII drawings, R12 through R2018 entities.
+//!
+//! Swiss-cheese layer: **Geometry Input (2-D)**
+//! Extension point: add an entity type as a match arm in `Drawing::emit`.
+//!
+//! A DXF file is a flat list of group-code / value line pairs. Only the
+//! parts that carry 2-D geometry are read: `$INSUNITS` from the header,
+//! block definitions, and the model-space entities. Every curve is
+//! flattened to points within `FLATTEN_TOLERANCE`, block references are
+//! expanded through their insertion transform, and loose pieces whose
+//! endpoints meet are joined into contours, since most drawings outline a
+//! part with separate LINEs and ARCs. Coordinates come out in millimetres.
+
+use std::collections::HashMap;
+use std::f64::consts::TAU;
+
+use crate::geometry::affine::Affine2;
+use crate::geometry::flatten::{arc, arc_segments, chord_gap};
+use crate::geometry::{Polyline, Vec2};
+
+/// Chord tolerance (mm) for arcs, ellipses and splines.
+pub const FLATTEN_TOLERANCE: f64 = 0.01;
+/// Endpoints closer than this (mm) are joined into one contour.
+pub const JOIN_TOLERANCE: f64 = 0.01;
+/// Blocks may insert blocks; this bounds cycles in a malformed file.
+const MAX_BLOCK_DEPTH: usize = 16;
+/// Most block instances one drawing may expand to, arrays included.
+const MAX_INSTANCES: usize = 100_000;
+/// Bisections of one spline knot span.
+const MAX_SPLINE_DEPTH: usize = 12;
+
+/// Whether `text` looks like an ASCII DXF file rather than an SVG.
+pub fn is_dxf(text: &str) -> bool {
+    let mut lines = text.lines().map(str::trim).filter(|l| !l.is_empty());
+    loop {
+        match (lines.next(), lines.next()) {
+            (Some("999"), Some(_)) => continue, // comment
+            (Some("0"), Some(value)) => return value.eq_ignore_ascii_case("SECTION"),
+            _ => return false,
+        }
+    }
+}
+
+/// Parse a DXF file and return the contours on `layers` (all layers when
+/// empty) as polylines in millimetres.
+pub fn parse_dxf(text: &str, layers: &[String]) -> Result<Vec<Polyline>, String> {
+    Drawing::parse(text)?.polylines(layers)
+}
+
+/// One group: an integer code and its value.
+#[derive(Debug, Clone)]
+struct Group {
+    code: i32,
+    value: String,
+}
+
+/// An entity record with its groups; a POLYLINE also owns its VERTEXes.
+#[derive(Debug, Clone)]
+struct Entity {
+    kind: String,
+    groups: Vec<Group>,
+    vertices: Vec<Entity>,
+}
+
+impl Entity {
+    fn text(&self, code: i32) -> Option<&str> {
+        self.groups
+            .iter()
+            .find(|g| g.code == code)
+            .map(|g| g.value.as_str())
+    }
+
+    fn num(&self, code: i32) -> Option<f64> {
+        self.text(code).and_then(|v| v.parse().ok())
+    }
+
+    fn num_or(&self, code: i32, default: f64) -> f64 {
+        self.num(code).unwrap_or(default)
+    }
+
+    fn flags(&self) -> i64 {
+        self.text(70).and_then(|v| v.parse().ok()).unwrap_or(0)
+    }
+
+    fn point(&self, x: i32) -> Vec2 {
+        Vec2::new(self.num_or(x, 0.0), self.num_or(x + 10, 0.0))
+    }
+
+    /// All points of a repeated `x`/`x + 10` group pair, in order.
+    fn points(&self, x: i32) -> Vec<Vec2> {
+        let mut out: Vec<Vec2> = Vec::new();
+        for g in &self.groups {
+            let v = g.value.parse().unwrap_or(0.0);
+            if g.code == x {
+                out.push(Vec2::new(v, 0.0));
+            } else if g.code == x + 10 {
+                if let Some(p) = out.last_mut() {
+                    p.y = v;
+                }
+            }
+        }
+        out
+    }
+
+    /// All values of a repeated numeric group, in order.
+    fn nums(&self, code: i32) -> Vec<f64> {
+        self.groups
+            .iter()
+            .filter(|g| g.code == code)
+            .filter_map(|g| g.value.parse().ok())
+            .collect()
+    }
+
+    /// Entities drawn in an object coordinate system whose Z points down
+    /// are mirrored in X (the DXF arbitrary-axis rule for Z = -1).
+    fn mirrored(&self) -> bool {
+        self.num_or(230, 1.0) < 0.0
+    }
+}
+
+struct Block {
+    base: Vec2,
+    entities: Vec<Entity>,
+}
+
+/// A flattened entity on its layer, before joining.
+struct Piece {
+    layer: String,
+    points: Vec<Vec2>,
+    closed: bool,
+}
+
+/// Pieces flattened so far and the block instances placed for them.
+#[derive(Default)]
+struct Expansion {
+    pieces: Vec<Piece>,
+    instances: usize,
+}
+
+/// A parsed DXF drawing.
+pub struct Drawing {
+    /// Drawing units to millimetres, from `$INSUNITS`.
+    scale: f64,
+    blocks: HashMap<String, Block>,
+    entities: Vec<Entity>,
+}
+
+impl Drawing {
+    /// Parse the header, blocks and entities of an ASCII DXF file.
+    pub fn parse(text: &str) -> Result<Self, String> {
+        if text.starts_with("AutoCAD Binary DXF") {
+            return Err("Binary DXF is not supported; save as ASCII DXF".into());
+        }
+        let records = read_records(text)?;
+
+        let mut scale = 1.0;
+        let mut blocks = HashMap::new();
+        let mut entities = Vec::new();
+        let mut section = String::new();
+        let mut block: Option<(String, Block)> = None;
+        let mut iter = records.into_iter().peekable();
+        while let Some(mut record) = iter.next() {
+            match record.kind.as_str() {
+                "SECTION" => {
+                    section = record.text(2).unwrap_or("").to_ascii_uppercase();
+                    if section == "HEADER" {
+                        scale = header_scale(&record.groups)?;
+                    }
+                    continue;
+                }
+                "ENDSEC" => {
+                    section.clear();
+                    continue;
+                }
+                _ => {}
+            }
+            if record.kind == "POLYLINE" {
+                while iter.peek().is_some_and(|r| r.kind == "VERTEX") {
+                    record.vertices.extend(iter.next());
+                }
+            }
+            match section.as_str() {
+                "BLOCKS" => match record.kind.as_str() {
+                    "BLOCK" => {
+                        let name = record.text(2).unwrap_or("").to_string();
+                        let base = record.point(10);
+                        block = Some((
+                            name,
+                            Block {
+                                base,
+                                entities: Vec::new(),
+                            },
+                        ));
+                    }
+                    "ENDBLK" => {
+                        if let Some((name, b)) = block.take() {
+                            blocks.insert(name, b);
+                        }
+                    }
+                    _ => {
+                        if let Some((_, b)) = block.as_mut() {
+                            b.entities.push(record);
+                        }
+                    }
+                },
+                "ENTITIES" => entities.push(record),
+                _ => {}
+            }
+        }
+        Ok(Self {
+            scale,
+            blocks,
+            entities,
+        })
+    }
+
+    /// Names of the layers that carry geometry, sorted.
+    pub fn layers(&self) -> Result<Vec<String>, String> {
+        let mut names: Vec<String> = self.pieces()?.into_iter().map(|p| p.layer).collect();
+        names.sort();
+        names.dedup();
+        Ok(names)
+    }
+
+    /// The contours on `layers` (all layers when empty), in millimetres,
+    /// with touching open pieces joined.
+    pub fn polylines(&self, layers: &[String]) -> Result<Vec<Polyline>, String> {
+        let wanted = |layer: &str| {
+            layers.is_empty() || layers.iter().any(|l| l.trim().eq_ignore_ascii_case(layer))
+        };
+        let mut closed = Vec::new();
+        let mut open = Vec::new();
+        for piece in self.pieces()?.into_iter().filter(|p| wanted(&p.layer)) {
+            let mut points: Vec<Vec2> = piece
+                .points
+                .iter()
+                .map(|p| Vec2::new(p.x * self.scale, p.y * self.scale))
+                .collect();
+            points.dedup_by(|a, b| Vec2::dist(*a, *b) < 1e-9);
+            if piece.closed {
+                if points.len() > 1 && Vec2::dist(points[0], points[points.len() - 1]) < 1e-9 {
+                    points.pop();
+                }
+                if points.len() >= 2 {
+                    closed.push(Polyline::new(points, true));
+                }
+            } else if points.len() >= 2 {
+                open.push(points);
+            }
+        }
+        closed.extend(join(open, JOIN_TOLERANCE));
+        if closed.is_empty() {
+            return Err(if layers.is_empty() {
+                "No geometry found in DXF".into()
+            } else {
+                format!("No geometry found in DXF on layers {}", layers.join(", "))
+            });
+        }
+        Ok(closed)
+    }
+
+    /// Every model-space entity flattened, in drawing units.
+    fn pieces(&self) -> Result<Vec<Piece>, String> {
+        let mut out = Expansion::default();
+        // Tolerance in drawing units.
+        let tol = FLATTEN_TOLERANCE / self.scale;
+        self.emit(&self.entities, &Affine2::IDENTITY, None, 0, tol, &mut out)?;
+        Ok(out.pieces)
+    }
+
+    fn emit(
+        &self,
+        entities: &[Entity],
+        xf: &Affine2,
+        parent_layer: Option<&str>,
+        depth: usize,
+        tol: f64,
+        out: &mut Expansion,
+    ) -> Result<(), String> {
+        // Curves are flattened before the transform, so tighten for it.
+        let local_tol = tol / xf.max_scale().max(1e-9);
+        for e in entities {
+            if e.num_or(67, 0.0) == 1.0 {
+                continue; // paper space
+            }
+            let own = e.text(8).unwrap_or("0");
+            // Block contents on layer 0 take the layer of the reference.
+            let layer = match parent_layer {
+                Some(parent) if own == "0" => parent,
+                _ => own,
+            };
+            let ocs = if e.mirrored() {
+                Affine2::scale(-1.0, 1.0)
+            } else {
+                Affine2::IDENTITY
+            };
+            let shape: Option<(Vec<Vec2>, bool)> = match e.kind.as_str() {
+                "LINE" => Some((vec![e.point(10), e.point(11)], false)),
+                "ARC" => {
+                    let start = e.num_or(50, 0.0).to_radians();
+                    let mut sweep = e.num_or(51, 360.0).to_radians() - start;
+                    if sweep <= 0.0 {
+                        sweep += TAU;
+                    }
+                    let pts = arc(e.point(10), e.num_or(40, 0.0), start, sweep, local_tol);
+                    Some((ocs_apply(&ocs, pts), false))
+                }
+                "CIRCLE" => {
+                    let mut pts = arc(e.point(10), e.num_or(40, 0.0), 0.0, TAU, local_tol);
+                    pts.pop();
+                    Some((ocs_apply(&ocs, pts), true))
+                }
+                "LWPOLYLINE" => {
+                    let vertices = lwpolyline_vertices(e);
+                    let closed = e.flags() & 1 != 0;
+                    Some((
+                        ocs_apply(&ocs, with_bulges(&vertices, closed, local_tol)),
+                        closed,
+                    ))
+                }
+                "POLYLINE" => {
+                    let flags = e.flags();
+                    // 16: polygon mesh, 64: polyface mesh — surfaces, not outlines.
+                    if flags & (16 | 64) != 0 {
+                        None
+                    } else {
+                        let vertices: Vec<(Vec2, f64)> = e
+                            .vertices
+                            .iter()
+                            .filter(|v| v.flags() & 16 == 0) // spline frame
+                            .map(|v| (v.point(10), v.num_or(42, 0.0)))
+                            .collect();
+                        let closed = flags & 1 != 0;
+                        let pts = with_bulges(&vertices, closed, local_tol);
+                        // 3-D polylines are in world coordinates.
+                        let pts = if flags & 8 != 0 {
+                            pts
+                        } else {
+                            ocs_apply(&ocs, pts)
+                        };
+                        Some((pts, closed))
+                    }
+                }
+                "ELLIPSE" => Some(ellipse(e, local_tol)),
+                "SPLINE" => spline(e, local_tol),
+                "INSERT" => {
+                    self.insert(e, xf, layer, depth, tol, out)?;
+                    None
+                }
+                _ => None,
+            };
+            if let Some((points, closed)) = shape {
+                out.pieces.push(Piece {
+                    layer: layer.to_string(),
+                    points: points.into_iter().map(|p| xf.apply(p)).collect(),
+                    closed,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Expand a block reference, including column/row arrays.
+    fn insert(
+        &self,
+        e: &Entity,
+        xf: &Affine2,
+        layer: &str,
+        depth: usize,
+        tol: f64,
+        out: &mut Expansion,
+    ) -> Result<(), String> {
+        let Some(block) = e.text(2).and_then(|name| self.blocks.get(name)) else {
+            return Ok(());
+        };
+        if depth >= MAX_BLOCK_DEPTH {
+            return Ok(());
+        }
+        let at = e.point(10);
+        let (sx, sy) = (e.num_or(41, 1.0), e.num_or(42, 1.0));
+        let rotation = e.num_or(50, 0.0).to_radians();
+        let columns = e.num_or(70, 1.0).max(1.0) as usize;
+        let rows = e.num_or(71, 1.0).max(1.0) as usize;
+        out.instances = out.instances.saturating_add(columns.saturating_mul(rows));
+        if out.instances > MAX_INSTANCES {
+            return Err(format!(
+                "DXF: block inserts expand to more than {} instances",
+                MAX_INSTANCES
+            ));
+        }
+        let (dx, dy) = (e.num_or(44, 0.0), e.num_or(45, 0.0));
+        let ocs = if e.mirrored() {
+            Affine2::scale(-1.0, 1.0)
+        } else {
+            Affine2::IDENTITY
+        };
+        for row in 0..rows {
+            for col in 0..columns {
+                let local = Affine2::translate(at.x, at.y)
+                    .after(&Affine2::rotate(rotation))
+                    .after(&Affine2::translate(col as f64 * dx, row as f64 * dy))
+                    .after(&Affine2::scale(sx, sy))
+                    .after(&Affine2::translate(-block.base.x, -block.base.y));
+                let placed = xf.after(&ocs).after(&local);
+                self.emit(&block.entities, &placed, Some(layer), depth + 1, tol, out)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Split the file into records, each starting at a code-0 group.
+fn read_records(text: &str) -> Result<Vec<Entity>, String> {
+    let mut records: Vec<Entity> = Vec::new();
+    let mut lines = text.lines().enumerate();
+    while let Some((number, code)) = lines.next() {
+        let code = code.trim();
+        if code.is_empty() {
+            continue;
+        }
+        let code: i32 = code.parse().map_err(|_| {
+            format!(
+                "DXF line {}: expected a group code, got '{}'",
+                number + 1,
+                code
+            )
+        })?;
+        let value = lines
+            .next()
+            .map(|(_, v)| v.trim().to_string())
+            .ok_or_else(|| format!("DXF line {}: group code without a value", number + 1))?;
+        if code == 0 {
+            if value == "EOF" {
+                break;
+            }
+            records.push(Entity {
+                kind: value.to_ascii_uppercase(),
+                groups: Vec::new(),
+                vertices: Vec::new(),
+            });
+        } else if let Some(record) = records.last_mut() {
+            record.groups.push(Group { code, value });
+        }
+    }
+    if records.is_empty() {
+        return Err("DXF file contains no sections".into());
+    }
+    Ok(records)
+}
+
+/// Drawing units to millimetres from the header's `$INSUNITS`.
+fn header_scale(groups: &[Group]) -> Result<f64, String> {
+    let Some(at) = groups
+        .iter()
+        .position(|g| g.code == 9 && g.value == "$INSUNITS")
+    else {
+        return Ok(1.0);
+    };
+    let units: i64 = groups
+        .get(at + 1)
+        .and_then(|g| g.value.parse().ok())
+        .ok_or("DXF: bad $INSUNITS value")?;
+    Ok(match units {
+        0 | 4 => 1.0, // unitless, millimetres
+        1 => 25.4,
+        2 => 304.8,
+        3 => 1_609_344.0,
+        5 => 10.0,
+        6 => 1000.0,
+        7 => 1_000_000.0,
+        8 => 25.4e-6,
+        9 => 0.0254,
+        10 => 914.4,
+        11 => 1e-7,
+        12 => 1e-6,
+        13 => 1e-3,
+        14 => 100.0,
+        15 => 10_000.0,
+        16 => 100_000.0,
+        17 => 1e12,
+        18 => 1.495_978_707e14,
+        19 => 9.460_730_472_580_8e18,
+        20 => 3.085_677_581_49e19,
+        21 => 304.800_609_601_219_2, // US survey foot
+        other => return Err(format!("DXF: unknown $INSUNITS {}", other)),
+    })
+}
+
+fn ocs_apply(ocs: &Affine2, points: Vec<Vec2>) -> Vec<Vec2> {
+    points.into_iter().map(|p| ocs.apply(p)).collect()
+}
+
+/// Vertices and bulges of an LWPOLYLINE, in group order.
+fn lwpolyline_vertices(e: &Entity) -> Vec<(Vec2, f64)> {
+    let mut out: Vec<(Vec2, f64)> = Vec::new();
+    for g in &e.groups {
+        let v = g.value.parse().unwrap_or(0.0);
+        match g.code {
+            10 => out.push((Vec2::new(v, 0.0), 0.0)),
+            20 => {
+                if let Some(last) = out.last_mut() {
+                    last.0.y = v;
+                }
+            }
+            42 => {
+                if let Some(last) = out.last_mut() {
+                    last.1 = v;
+                }
+            }
+            _ => {}
+        }
+    }
+    out
+}
+
+/// Points of a polyline whose segments may bulge into arcs. A vertex's
+/// bulge is the tangent of a quarter of the arc's sweep to the next
+/// vertex, positive counter-clockwise.
+fn with_bulges(vertices: &[(Vec2, f64)], closed: bool, tol: f64) -> Vec<Vec2> {
+    let Some(&(first, _)) = vertices.first() else {
+        return Vec::new();
+    };
+    let mut out = vec![first];
+    let segments = if closed {
+        vertices.len()
+    } else {
+        vertices.len() - 1
+    };
+    for i in 0..segments {
+        let (p0, bulge) = vertices[i];
+        let p1 = vertices[(i + 1) % vertices.len()].0;
+        if bulge.abs() < 1e-12 || Vec2::dist(p0, p1) < 1e-12 {
+            out.push(p1);
+            continue;
+        }
+        let sweep = 4.0 * bulge.atan();
+        let (dx, dy) = (p1.x - p0.x, p1.y - p0.y);
+        let k = (1.0 - bulge * bulge) / (4.0 * bulge);
+        let center = Vec2::new((p0.x + p1.x) / 2.0 - k * dy, (p0.y + p1.y) / 2.0 + k * dx);
+        let radius = Vec2::dist(center, p0);
+        let start = (p0.y - center.y).atan2(p0.x - center.x);
+        let mut pts = arc(center, radius, start, sweep, tol);
+        if let Some(last) = pts.last_mut() {
+            *last = p1; // exact end, not a rounded one
+        }
+        out.extend(pts.into_iter().skip(1));
+    }
+    if closed {
+        out.pop(); // back at the first vertex
+    }
+    out
+}
+
+/// An ELLIPSE: centre, major-axis end relative to it, minor/major ratio,
+/// and start/end parameters in radians.
+fn ellipse(e: &Entity, tol: f64) -> (Vec<Vec2>, bool) {
+    let c = e.point(10);
+    let major = e.point(11);
+    let ratio = e.num_or(40, 1.0);
+    // The minor axis is the major turned a quarter about the extrusion.
+    let turn = if e.mirrored() { -1.0 } else { 1.0 };
+    let minor = Vec2::new(-major.y * ratio * turn, major.x * ratio * turn);
+    let start = e.num_or(41, 0.0);
+    let mut sweep = e.num_or(42, TAU) - start;
+    if sweep <= 0.0 {
+        sweep += TAU;
+    }
+    let full = (sweep - TAU).abs() < 1e-9;
+    let radius = major.x.hypot(major.y);
+    let steps = arc_segments(radius, sweep, tol);
+    let mut pts: Vec<Vec2> = (0..=steps)
+        .map(|i| {
+            let (s, co) = (start + sweep * i as f64 / steps as f64).sin_cos();
+            Vec2::new(
+                c.x + major.x * co + minor.x * s,
+                c.y + major.y * co + minor.y * s,
+            )
+        })
+        .collect();
+    if full {
+        pts.pop();
+    }
+    (pts, full)
+}
+
+/// A SPLINE, evaluated as a NURBS curve when it has control points and a
+/// matching knot vector, else drawn through its fit points.
+fn spline(e: &Entity, tol: f64) -> Option<(Vec<Vec2>, bool)> {
+    let closed = e.flags() & 1 != 0;
+    let degree = e.num_or(71, 3.0).max(1.0) as usize;
+    let control = e.points(10);
+    let knots = e.nums(40);
+    let mut weights = e.nums(41);
+    if weights.len() != control.len() {
+        weights = vec![1.0; control.len()];
+    }
+    if control.len() > degree && knots.len() == control.len() + degree + 1 {
+        let curve = Nurbs {
+            degree,
+            control: &control,
+            weights: &weights,
+            knots: &knots,
+        };
+        return Some((curve.flatten(tol), closed));
+    }
+    let fit = e.points(11);
+    let pts = if fit.len() >= 2 { fit } else { control };
+    (pts.len() >= 2).then_some((pts, closed))
+}
+
+struct Nurbs<'a> {
+    degree: usize,
+    control: &'a [Vec2],
+    weights: &'a [f64],
+    knots: &'a [f64],
+}
+
+impl Nurbs<'_> {
+    /// De Boor's algorithm in homogeneous coordinates.
+    fn at(&self, t: f64) -> Vec2 {
+        let p = self.degree;
+        let n = self.control.len();
+        let span = (p..n)
+            .rev()
+            .find(|&k| self.knots[k] <= t && (t < self.knots[k + 1] || k == n - 1))
+            .unwrap_or(p);
+        let mut d: Vec<[f64; 3]> = (0..=p)
+            .map(|j| {
+                let i = span + j - p;
+                let w = self.weights[i];
+                [self.control[i].x * w, self.control[i].y * w, w]
+            })
+            .collect();
+        for r in 1..=p {
+            for j in (r..=p).rev() {
+                let i = span + j - p;
+                let denom = self.knots[i + p + 1 - r] - self.knots[i];
+                let alpha = if denom.abs() < 1e-15 {
+                    0.0
+                } else {
+                    (t - self.knots[i]) / denom
+                };
+                let prev = d[j - 1];
+                for (v, before) in d[j].iter_mut().zip(prev) {
+                    *v = (1.0 - alpha) * before + alpha * *v;
+                }
+            }
+        }
+        let [x, y, w] = d[p];
+        Vec2::new(x / w, y / w)
+    }
+
+    /// Points with chords within `tol`, bisecting each knot span.
+    fn flatten(&self, tol: f64) -> Vec<Vec2> {
+        let (lo, hi) = (self.knots[self.degree], self.knots[self.control.len()]);
+        let mut breaks: Vec<f64> = self
+            .knots
+            .iter()
+            .copied()
+            .filter(|&k| k > lo && k < hi)
+            .collect();
+        breaks.insert(0, lo);
+        breaks.push(hi);
+        breaks.dedup();
+        let mut out = vec![self.at(lo)];
+        for w in breaks.windows(2) {
+            let (a, b) = (w[0], w[1]);
+            let pa = self.at(a);
+            let pb = self.at(b);
+            self.refine(a, pa, b, pb, tol, 0, &mut out);
+        }
+        out
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn refine(
+        &self,
+        t0: f64,
+        p0: Vec2,
+        t1: f64,
+        p1: Vec2,
+        tol: f64,
+        depth: usize,
+        out: &mut Vec<Vec2>,
+    ) {
+        let tm = (t0 + t1) / 2.0;
+        let pm = self.at(tm);
+        // Always split twice so a span that doubles back is not mistaken
+        // for a straight one.
+        if depth < MAX_SPLINE_DEPTH && (depth < 2 || chord_gap(p0, p1, pm) > tol) {
+            self.refine(t0, p0, tm, pm, tol, depth + 1, out);
+            self.refine(tm, pm, t1, p1, tol, depth + 1, out);
+        } else {
+            out.push(p1);
+        }
+    }
+}
+
+/// Chain open pieces whose endpoints meet within `tol`; chains that end
+/// where they began become closed contours.
+fn join(pieces: Vec<Vec<Vec2>>, tol: f64) -> Vec<Polyline> {
+    let cell = |p: Vec2| ((p.x / tol).floor() as i64, (p.y / tol).floor() as i64);
+    let mut ends: HashMap<(i64, i64), Vec<usize>> = HashMap::new();
+    for (i, piece) in pieces.iter().enumerate() {
+        for p in [piece[0], piece[piece.len() - 1]] {
+            ends.entry(cell(p)).or_default().push(i);
+        }
+    }
+    let mut used = vec![false; pieces.len()];
+    // An unused piece with an end within `tol` of `p`, and whether that
+    // end is its start.
+    let find = |p: Vec2, used: &[bool]| -> Option<(usize, bool)> {
+        let (cx, cy) = cell(p);
+        for dx in -1..=1 {
+            for dy in -1..=1 {
+                for &i in ends.get(&(cx + dx, cy + dy)).into_iter().flatten() {
+                    if used[i] {
+                        continue;
+                    }
+                    let piece = &pieces[i];
+                    if Vec2::dist(piece[0], p) <= tol {
+                        return Some((i, true));
+                    }
+                    if Vec2::dist(piece[piece.len() - 1], p) <= tol {
+                        return Some((i, false));
+                    }
+                }
+            }
+        }
+        None
+    };
+
+    let mut out = Vec::new();
+    for first in 0..pieces.len() {
+        if used[first] {
+            continue;
+        }
+        used[first] = true;
+        let mut chain = pieces[first].clone();
+        while let Some((i, at_start)) = find(chain[chain.len() - 1], &used) {
+            used[i] = true;
+            if at_start {
+                chain.extend(pieces[i].iter().skip(1));
+            } else {
+                chain.extend(pieces[i].iter().rev().skip(1));
+            }
+        }
+        while let Some((i, at_start)) = find(chain[0], &used) {
+            used[i] = true;
+            let mut head: Vec<Vec2> = if at_start {
+                pieces[i].iter().rev().copied().collect()
+            } else {
+                pieces[i].clone()
+            };
+            head.pop();
+            head.extend(chain);
+            chain = head;
+        }
+        let closed = chain.len() > 2 && Vec2::dist(chain[0], chain[chain.len() - 1]) <= tol;
+        if closed {
+            chain.pop();
+        }
+        out.push(Polyline::new(chain, closed));
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f64::consts::PI;
+
+    /// A DXF file from sections of whitespace-separated code/value pairs.
+    fn dxf(header: &str, blocks: &str, entities: &str) -> String {
+        let mut out = String::new();
+        for (name, groups) in [
+            ("HEADER", header),
+            ("BLOCKS", blocks),
+            ("ENTITIES", entities),
+        ] {
+            out.push_str(&format!("  0\nSECTION\n  2\n{}\n", name));
+            let words: Vec<&str> = groups.split_whitespace().collect();
+            for pair in words.chunks(2) {
+                out.push_str(&format!("{:>3}\n{}\n", pair[0], pair[1]));
+            }
+            out.push_str("  0\nENDSEC\n");
+        }
+        out.push_str("  0\nEOF\n");
+        out
+    }
+
+    fn area(pl: &Polyline) -> f64 {
+        let n = pl.points.len();
+        (0..n)
+            .map(|i| {
+                let (a, b) = (pl.points[i], pl.points[(i + 1) % n]);
+                a.x * b.y - b.x * a.y
+            })
+            .sum::<f64>()
+            / 2.0
+    }
+
+    #[test]
+    fn test_detect_dxf() {
+        assert!(is_dxf("999\nmade by hand\n  0\nSECTION\n  2\nHEADER\n"));
+        assert!(!is_dxf("<svg xmlns=\"http://www.w3.org/2000/svg\"/>"));
+        assert!(!is_dxf(""));
+    }
+
+    #[test]
+    fn test_lines_and_arcs_join_into_a_closed_slot() {
+        // A 20 x 10 slot: two lines and two half-circle arcs, out of order
+        // and with one line drawn backwards, plus a hole on its own layer.
+        let e = dxf(
+            "",
+            "",
+            "0 LINE 8 CUT 10 0 20 0 11 20 21 0
+             0 ARC 8 CUT 10 0 20 5 40 5 50 90 51 270
+             0 LINE 8 CUT 10 20 20 10 11 0 21 10
+             0 ARC 8 CUT 10 20 20 5 40 5 50 270 51 90
+             0 CIRCLE 8 HOLES 10 10 20 5 40 2",
+        );
+        let all = parse_dxf(&e, &[]).unwrap();
+        assert_eq!(all.len(), 2);
+        assert!(all.iter().all(|p| p.closed));
+        let slot = parse_dxf(&e, &["cut".to_string()]).unwrap();
+        assert_eq!(slot.len(), 1);
+        // Chords cut inside the arcs by up to the tolerance.
+        let expected = 20.0 * 10.0 + PI * 25.0;
+        assert!((area(&slot[0]).abs() - expected).abs() < 2.0 * PI * 5.0 * FLATTEN_TOLERANCE);
+        assert!(parse_dxf(&e, &["none".to_string()])
+            .unwrap_err()
+            .contains("none"));
+    }
+
+    #[test]
+    fn test_lwpolyline_bulge_and_inch_units() {
+        // A 1 x 1 inch square whose top edge bulges out into a half circle.
+        let e = dxf(
+            "9 $INSUNITS 70 1",
+            "",
+            "0 LWPOLYLINE 8 0 90 4 70 1 10 0 20 0 10 1 20 0 10 1 20 1 42 1 10 0 20 1",
+        );
+        let polys = parse_dxf(&e, &[]).unwrap();
+        assert_eq!(polys.len(), 1);
+        let top = polys[0].points.iter().map(|p| p.y).fold(f64::MIN, f64::max);
+        assert!((top - 1.5 * 25.4).abs() < 0.01, "{}", top);
+        let expected = (1.0 + PI / 8.0) * 25.4 * 25.4;
+        assert!((area(&polys[0]) - expected).abs() < 1.0);
+    }
+
+    #[test]
+    fn test_insert_places_block_with_rotation_scale_and_array() {
+        let e = dxf(
+            "",
+            "0 BLOCK 2 TRI 8 0 10 1 20 0
+             0 POLYLINE 8 0 66 1 70 1
+             0 VERTEX 10 1 20 0 0 VERTEX 10 2 20 0 0 VERTEX 10 1 20 1 0 SEQEND
+             0 ENDBLK",
+            "0 INSERT 8 PARTS 2 TRI 10 10 20 10 41 2 42 2 50 90 70 2 44 5",
+        );
+        let drawing = Drawing::parse(&e).unwrap();
+        assert_eq!(drawing.layers().unwrap(), vec!["PARTS".to_string()]);
+        let polys = drawing.polylines(&[]).unwrap();
+        assert_eq!(polys.len(), 2);
+        let close = |a: Vec2, x: f64, y: f64| (a.x - x).abs() < 1e-9 && (a.y - y).abs() < 1e-9;
+        // The base point lands on the insertion point; x turns into +y.
+        assert!(close(polys[0].points[0], 10.0, 10.0));
+        assert!(close(polys[0].points[1], 10.0, 12.0));
+        assert!(close(polys[0].points[2], 8.0, 10.0));
+        // The second column is 5 units along the rotated x axis.
+        assert!(close(polys[1].points[0], 10.0, 15.0));
+
+        // Arrays nested in arrays multiply; the total is capped.
+        let e = dxf(
+            "",
+            "0 BLOCK 2 TRI 8 0 10 0 20 0
+             0 LINE 8 0 10 0 20 0 11 1 21 0
+             0 ENDBLK
+             0 BLOCK 2 GRID 8 0 10 0 20 0
+             0 INSERT 8 0 2 TRI 70 1000 71 1000 44 2 45 2
+             0 ENDBLK",
+            "0 INSERT 8 0 2 GRID 70 1000000 71 1000000",
+        );
+        let err = parse_dxf(&e, &[]).unwrap_err();
+        assert!(err.contains("instances"), "{}", err);
+    }
+
+    #[test]
+    fn test_spline_and_ellipse_are_flattened_to_tolerance() {
+        // A quarter circle as a rational quadratic B-spline, and a full
+        // 40 x 20 ellipse.
+        let e = dxf(
+            "",
+            "",
+            "0 SPLINE 8 0 70 8 71 2 72 6 73 3
+             40 0 40 0 40 0 40 1 40 1 40 1
+             41 1 41 0.7071067811865476 41 1
+             10 10 20 0 10 10 20 10 10 0 20 10
+             0 ELLIPSE 8 0 10 50 20 0 11 20 21 0 40 0.5 41 0 42 6.283185307179586",
+        );
+        let polys = parse_dxf(&e, &[]).unwrap();
+        let arc = polys.iter().find(|p| !p.closed).unwrap();
+        assert!(arc.points.len() > 8);
+        for w in arc.points.windows(2) {
+            let mid = Vec2::new((w[0].x + w[1].x) / 2.0, (w[0].y + w[1].y) / 2.0);
+            assert!((w[0].x.hypot(w[0].y) - 10.0).abs() < 1e-9);
+            assert!(10.0 - mid.x.hypot(mid.y) < FLATTEN_TOLERANCE);
+        }
+        let ellipse = polys.iter().find(|p| p.closed).unwrap();
+        let expected = PI * 20.0 * 10.0;
+        assert!((area(ellipse) - expected).abs() / expected < 0.01);
+    }
+}
@@ -4,10 +4,16 @@
 /// Extension point: implement alternative output formats (HPGL, DXF toolpath,
 /// Marlin flavour, GRBL flavour, etc.) by consuming `Vec<Toolpath>`.
 use crate::gcode_parser::{parse_line, validate_command, ParseError, ValidationConfig};
-use crate::geometry::Toolpath;
+use crate::geometry::{Toolpath, ToolpathMove};
 use crate::machine::{MachineProfile, MachineType};
 use serde::{Deserialize, Serialize};
 
+mod arcs;
+mod post;
+
+pub use self::arcs::{fit_arcs, Motion};
+pub use self::post::{CommentStyle, PostFormat, PostProcessor};
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GcodeParams {
     pub feed_rate: f64,
@@ -15,6 +21,10 @@ pub struct GcodeParams {
     pub spindle_speed: f64,
     pub safe_z: f64,
     pub unit_mm: bool,
+    /// Chord tolerance (mm) for replacing runs of feed moves with G2/G3
+    /// arcs. Zero emits G1 moves only.
+    #[serde(default)]
+    pub arc_tolerance: f64,
 }
 
 impl Default for GcodeParams {
@@ -25,6 +35,7 @@ impl Default for GcodeParams {
             spindle_speed: 12000.0,
             safe_z: 5.0,
             unit_mm: true,
+            arc_tolerance: 0.0,
         }
     }
 }
@@ -46,25 +57,7 @@ pub fn emit_gcode(toolpaths: &[Toolpath], params: &GcodeParams) -> String {
 
     for (idx, tp) in toolpaths.iter().enumerate() {
         out.push_str(&format!("(Toolpath {})\n", idx + 1));
-        let mut last_rapid = true; // track state to avoid redundant F words
-
-        for mv in &tp.moves {
-            if mv.rapid {
-                out.push_str(&format!("G0 X{:.4} Y{:.4} Z{:.4}\n", mv.x, mv.y, mv.z));
-                last_rapid = true;
-            } else {
-                let feed = if mv.z < params.safe_z - 0.01 && last_rapid {
-                    params.plunge_rate
-                } else {
-                    params.feed_rate
-                };
-                out.push_str(&format!(
-                    "G1 X{:.4} Y{:.4} Z{:.4} F{:.0}\n",
-                    mv.x, mv.y, mv.z, feed
-                ));
-                last_rapid = false;
-            }
-        }
+        emit_moves(&mut out, &tp.moves, params);
         out.push('\n');
     }
 
@@ -77,6 +70,64 @@ pub fn emit_gcode(toolpaths: &[Toolpath], params: &GcodeParams) -> String {
     out
 }
 
+/// Emit the moves of one CNC toolpath, fitting arcs when
+/// `params.arc_tolerance` is set.
+fn emit_moves(out: &mut String, moves: &[ToolpathMove], params: &GcodeParams) {
+    let mut last_rapid = true; // track state to avoid redundant F words
+
+    for motion in fit_arcs(moves, params.arc_tolerance) {
+        let mv = &moves[motion_end(motion)];
+        if mv.rapid {
+            out.push_str(&format!("G0 X{:.4} Y{:.4} Z{:.4}\n", mv.x, mv.y, mv.z));
+            last_rapid = true;
+            continue;
+        }
+        let feed = if mv.z < params.safe_z - 0.01 && last_rapid {
+            params.plunge_rate
+        } else {
+            params.feed_rate
+        };
+        match arc_words(moves, motion) {
+            Some((g, ij)) => out.push_str(&format!(
+                "{} X{:.4} Y{:.4} {} F{:.0}\n",
+                g, mv.x, mv.y, ij, feed
+            )),
+            None => out.push_str(&format!(
+                "G1 X{:.4} Y{:.4} Z{:.4} F{:.0}\n",
+                mv.x, mv.y, mv.z, feed
+            )),
+        }
+        last_rapid = false;
+    }
+}
+
+/// Index of the move whose end point `motion` finishes at.
+fn motion_end(motion: Motion) -> usize {
+    match motion {
+        Motion::Line(i) => i,
+        Motion::Arc { end, .. } => end,
+    }
+}
+
+/// `G2`/`G3` and the `I.. J..` words of an arc; `None` for a line.
+fn arc_words(moves: &[ToolpathMove], motion: Motion) -> Option<(&'static str, String)> {
+    match motion {
+        Motion::Line(_) => None,
+        Motion::Arc {
+            start,
+            center,
+            clockwise,
+            ..
+        } => {
+            let from = &moves[start - 1];
+            Some((
+                if clockwise { "G2" } else { "G3" },
+                format!("I{:.4} J{:.4}", center.x - from.x, center.y - from.y),
+            ))
+        }
+    }
+}
+
 /// Extended params for profile-aware emission.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LaserParams {
@@ -96,129 +147,58 @@ impl Default for LaserParams {
     }
 }
 
-/// Emit G-code using a machine profile for CNC/laser-specific output.
+/// Emit G-code using a machine profile for CNC/laser-specific output,
+/// formatted by the profile's post-processor.
 pub fn emit_gcode_with_profile(
     toolpaths: &[Toolpath],
     params: &GcodeParams,
     profile: &MachineProfile,
     laser_params: Option<&LaserParams>,
 ) -> String {
-    match profile.machine_type {
-        MachineType::CncMill => emit_gcode_cnc(toolpaths, params, profile),
-        MachineType::LaserCutter => emit_gcode_laser(
-            toolpaths,
-            params,
-            profile,
-            laser_params.unwrap_or(&LaserParams::default()),
-        ),
-    }
+    emit_gcode_with_post(
+        toolpaths,
+        params,
+        profile,
+        laser_params,
+        &profile.output_config.post,
+    )
 }
 
-fn emit_gcode_cnc(
-    toolpaths: &[Toolpath],
-    params: &GcodeParams,
-    profile: &MachineProfile,
-) -> String {
-    let mut out = String::with_capacity(4096);
-
-    out.push_str("(RustCAM — generated G-code)\n");
-    for line in &profile.output_config.preamble {
-        out.push_str(line);
-        out.push('\n');
-    }
-    out.push_str(&format!("G0 Z{:.3}\n", params.safe_z));
-    out.push_str(&format!("M3 S{:.0} (spindle on)\n", params.spindle_speed));
-    out.push('\n');
-
-    for (idx, tp) in toolpaths.iter().enumerate() {
-        out.push_str(&format!("(Toolpath {})\n", idx + 1));
-        let mut last_rapid = true;
-
-        for mv in &tp.moves {
-            if mv.rapid {
-                out.push_str(&format!("G0 X{:.4} Y{:.4} Z{:.4}\n", mv.x, mv.y, mv.z));
-                last_rapid = true;
-            } else {
-                let feed = if mv.z < params.safe_z - 0.01 && last_rapid {
-                    params.plunge_rate
-                } else {
-                    params.feed_rate
-                };
-                out.push_str(&format!(
-                    "G1 X{:.4} Y{:.4} Z{:.4} F{:.0}\n",
-                    mv.x, mv.y, mv.z, feed
-                ));
-                last_rapid = false;
-            }
-        }
-        out.push('\n');
-    }
-
-    out.push_str(&format!("G0 Z{:.3}\n", params.safe_z));
-    for line in &profile.output_config.postamble {
-        out.push_str(line);
-        out.push('\n');
-    }
+/// One operation of a multi-tool job.
+#[derive(Debug, Clone)]
+pub struct JobSection {
+    /// Comment lines introducing the operation.
+    pub comments: Vec<String>,
+    /// Tool number, also used as the length-offset register.
+    pub tool: u32,
+    pub params: GcodeParams,
+    pub toolpaths: Vec<Toolpath>,
+}
 
-    out
+/// Emit one CNC program for a multi-tool job, with tool changes between
+/// sections, formatted by the profile's post-processor.
+pub fn emit_job_gcode(sections: &[JobSection], profile: &MachineProfile) -> String {
+    profile.output_config.post.emit_job(sections, profile)
 }
 
-fn emit_gcode_laser(
+/// Emit G-code for `profile` through any post-processor.
+pub fn emit_gcode_with_post(
     toolpaths: &[Toolpath],
     params: &GcodeParams,
     profile: &MachineProfile,
-    laser_params: &LaserParams,
+    laser_params: Option<&LaserParams>,
+    post: &dyn PostProcessor,
 ) -> String {
-    let mut out = String::with_capacity(4096);
-
-    out.push_str("(RustCAM — generated G-code)\n");
-    for line in &profile.output_config.preamble {
-        out.push_str(line);
-        out.push('\n');
-    }
-    if laser_params.air_assist {
-        out.push_str("M8 (air assist on)\n");
-    }
-    out.push('\n');
-
-    for pass in 0..laser_params.passes {
-        if laser_params.passes > 1 {
-            out.push_str(&format!("(Pass {} of {})\n", pass + 1, laser_params.passes));
-        }
-
-        for (idx, tp) in toolpaths.iter().enumerate() {
-            out.push_str(&format!("(Toolpath {})\n", idx + 1));
-
-            for mv in &tp.moves {
-                if mv.rapid {
-                    // Laser off during rapids
-                    out.push_str(&format!("G0 X{:.4} Y{:.4} S0\n", mv.x, mv.y));
-                } else {
-                    // Use move-specific power if available, otherwise default
-                    let power = mv.power.unwrap_or(laser_params.power);
-                    out.push_str(&format!(
-                        "G1 X{:.4} Y{:.4} F{:.0} S{:.0}\n",
-                        mv.x, mv.y, params.feed_rate, power
-                    ));
-                }
-            }
-            out.push('\n');
-        }
-    }
-
-    if laser_params.air_assist {
-        out.push_str("M9 (air assist off)\n");
-    }
-    for line in &profile.output_config.postamble {
-        out.push_str(line);
-        out.push('\n');
-    }
-
-    out
+    post.emit(
+        toolpaths,
+        params,
+        profile,
+        laser_params.unwrap_or(&LaserParams::default()),
+    )
 }
 
 /// A warning produced during G-code validation.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Serialize)]
 pub struct GcodeWarning {
     pub line_number: usize,
     pub message: String,
@@ -366,6 +346,104 @@ mod tests {
         );
     }
 
+    // ── Arc output tests ──────────────────────────────────────────────
+
+    /// Plunge at (10, 0) and cut a full circle of radius 10 in 72 chords.
+    fn circle_toolpath() -> Toolpath {
+        let mut tp = Toolpath::new();
+        tp.rapid(10.0, 0.0, 5.0);
+        tp.cut(10.0, 0.0, -1.0);
+        for i in 1..=72 {
+            let a = (i as f64 * 5.0).to_radians();
+            tp.cut(10.0 * a.cos(), 10.0 * a.sin(), -1.0);
+        }
+        tp.rapid(10.0, 0.0, 5.0);
+        tp
+    }
+
+    #[test]
+    fn test_no_arcs_by_default() {
+        let code = emit_gcode(&[circle_toolpath()], &GcodeParams::default());
+        assert!(!code.contains("G2 ") && !code.contains("G3 "));
+        assert_eq!(code.matches("G1 ").count(), 73);
+    }
+
+    #[test]
+    fn test_arcs_round_trip_through_parser() {
+        use crate::gcode_parser::GCodeCommand;
+        let params = GcodeParams {
+            arc_tolerance: 0.01,
+            ..GcodeParams::default()
+        };
+        let profile = MachineProfile::cnc_mill();
+        let code = emit_gcode_with_profile(&[circle_toolpath()], &params, &profile, None);
+        assert!(validate_gcode(&code, &profile).is_empty());
+
+        let (mut x, mut y) = (0.0, 0.0);
+        let mut arcs = 0;
+        for line in code.lines() {
+            match parse_line(line) {
+                Ok(GCodeCommand::ArcMove {
+                    clockwise,
+                    x: Some(ex),
+                    y: Some(ey),
+                    z: None,
+                    i: Some(i),
+                    j: Some(j),
+                    r: None,
+                    feed,
+                }) => {
+                    assert!(!clockwise);
+                    assert_eq!(feed, Some(params.feed_rate));
+                    // Centre on the origin, both ends on the circle.
+                    let (cx, cy) = (x + i, y + j);
+                    assert!(cx.abs() < 1e-3 && cy.abs() < 1e-3, "{}", line);
+                    assert!(((ex - cx).hypot(ey - cy) - 10.0).abs() < 1e-3);
+                    assert!(((x - cx).hypot(y - cy) - 10.0).abs() < 1e-3);
+                    (x, y) = (ex, ey);
+                    arcs += 1;
+                }
+                Ok(GCodeCommand::RapidMove {
+                    x: Some(nx),
+                    y: Some(ny),
+                    ..
+                })
+                | Ok(GCodeCommand::LinearMove {
+                    x: Some(nx),
+                    y: Some(ny),
+                    ..
+                }) => {
+                    (x, y) = (nx, ny);
+                }
+                _ => {}
+            }
+        }
+        assert_eq!(arcs, 2);
+        assert_eq!(code.matches("G1 ").count(), 1, "only the plunge");
+    }
+
+    #[test]
+    fn test_laser_arcs_keep_power() {
+        let profile = MachineProfile::laser_cutter();
+        let mut tp = Toolpath::new();
+        tp.rapid(0.0, -5.0, 0.0);
+        for i in 1..=36 {
+            let a = (i as f64 * 5.0 - 90.0).to_radians();
+            tp.cut_with_power(5.0 * a.cos(), 5.0 * a.sin(), 0.0, 60.0);
+        }
+        let params = GcodeParams {
+            arc_tolerance: 0.01,
+            ..GcodeParams::default()
+        };
+        let code = emit_gcode_with_profile(&[tp], &params, &profile, None);
+        assert!(
+            code.contains("G3 X0.0000 Y5.0000 I0.0000 J5.0000 F800 S60"),
+            "{}",
+            code
+        );
+        assert!(validate_gcode(&code, &profile).is_empty());
+    }
+
     // ── Validation tests ──────────────────────────────────────────────
 
     #[test]
@@ -0,0 +1,368 @@
+//! Arc fitting for G2/G3 output.
+//!
+//! Swiss-cheese layer: **Output format**
+//! Extension point: relax `MAX_SWEEP` or fit helices once the parser's
+//! `ArcMove` carries a Z word.
+//!
+//! Curves reach the emitter as runs of short G1 chords. `fit_arcs` walks the
+//! moves of a toolpath and replaces every run whose points lie on a circle,
+//! within a tolerance, by a single arc in the XY plane. Only feed moves at a
+//! constant Z (and constant laser power and cutter compensation) are
+//! fitted; everything else passes through as straight moves, so
+//! compensation always changes on a line.
+
+use crate::geometry::{ToolpathMove, Vec2};
+
+/// Fewest chords worth replacing with an arc.
+const MIN_CHORDS: usize = 3;
+
+/// Largest angle one chord may span. Coarser runs are polygons (a square's
+/// corners lie on a circle too), not tessellated curves.
+const MAX_CHORD_ANGLE: f64 = 15.0 * std::f64::consts::PI / 180.0;
+
+/// Largest sweep of a single arc. Half a turn keeps I/J well away from the
+/// full-circle case, where controllers disagree on what start == end means.
+const MAX_SWEEP: f64 = std::f64::consts::PI;
+
+/// One emitted motion.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Motion {
+    /// `moves[index]` as a straight move.
+    Line(usize),
+    /// An arc from the end of `moves[start - 1]` to the end of `moves[end]`
+    /// around `center`, standing in for moves `start..=end`.
+    Arc {
+        start: usize,
+        end: usize,
+        center: Vec2,
+        clockwise: bool,
+    },
+}
+
+/// Group `moves` into lines and arcs. Every point an arc replaces lies
+/// within `tolerance` of it, and the arc strays no further than that from
+/// the chords between them; a tolerance of zero or less turns fitting off.
+pub fn fit_arcs(moves: &[ToolpathMove], tolerance: f64) -> Vec<Motion> {
+    let mut out = Vec::with_capacity(moves.len());
+    let mut i = 0;
+    while i < moves.len() {
+        match fit_from(moves, i, tolerance) {
+            Fit::Arc {
+                end,
+                center,
+                clockwise,
+            } => {
+                out.push(Motion::Arc {
+                    start: i,
+                    end,
+                    center,
+                    clockwise,
+                });
+                i = end + 1;
+            }
+            Fit::Lines(n) => {
+                out.extend((i..i + n).map(Motion::Line));
+                i += n;
+            }
+        }
+    }
+    out
+}
+
+/// What `fit_from` found at one start index.
+enum Fit {
+    /// An arc through `moves[end]`.
+    Arc {
+        end: usize,
+        center: Vec2,
+        clockwise: bool,
+    },
+    /// No arc: emit this many moves (at least one) as lines.
+    Lines(usize),
+}
+
+/// Longest arc starting at `moves[start]`, or how many moves to pass on
+/// as lines before trying again.
+fn fit_from(moves: &[ToolpathMove], start: usize, tolerance: f64) -> Fit {
+    match fit_run(moves, start, tolerance) {
+        Some(fit) => fit,
+        None => Fit::Lines(1),
+    }
+}
+
+fn fit_run(moves: &[ToolpathMove], start: usize, tolerance: f64) -> Option<Fit> {
+    if tolerance <= 0.0 || start == 0 {
+        return None;
+    }
+    // The run: feed moves in the plane of the move before it.
+    let from = &moves[start - 1];
+    let first = &moves[start];
+    if first.comp != from.comp {
+        return None;
+    }
+    let run = moves[start..]
+        .iter()
+        .take_while(|m| !m.rapid && m.z == from.z && m.power == first.power && m.comp == first.comp)
+        .count();
+    if run < MIN_CHORDS {
+        return None;
+    }
+    let pts: Vec<Vec2> = moves[start - 1..start + run]
+        .iter()
+        .map(|m| Vec2::new(m.x, m.y))
+        .collect();
+
+    // Longest prefix that still fits: double the chord count until a fit
+    // fails, then bisect between the last fit and the first miss. Each fit
+    // checks every point, so this costs O(run log run) rather than refitting
+    // every prefix in turn.
+    let mut good = MIN_CHORDS;
+    let mut found = fit(&pts[..=good], tolerance)?;
+    let mut bad = None;
+    while good < run {
+        let k = (good * 2).min(run);
+        match fit(&pts[..=k], tolerance) {
+            Some(f) => (good, found) = (k, f),
+            None => {
+                bad = Some(k);
+                break;
+            }
+        }
+    }
+    if let Some(mut bad) = bad {
+        while bad - good > 1 {
+            let k = (good + bad) / 2;
+            match fit(&pts[..=k], tolerance) {
+                Some(f) => (good, found) = (k, f),
+                None => bad = k,
+            }
+        }
+    }
+
+    let (center, clockwise, bulge) = found;
+    if bulge > tolerance {
+        return Some(Fit::Arc {
+            end: start + good - 1,
+            center,
+            clockwise,
+        });
+    }
+    // Nearly straight the whole way: lines are as accurate as any arc on
+    // it. Pass on the first half as lines instead of refitting the same
+    // run from every one of its points; an arc that starts further in
+    // still gets its chance from the midpoint.
+    Some(Fit::Lines((good / 2).max(1)))
+}
+
+/// Circle through the first, middle and last of `pts`, if every point lies
+/// within `tolerance` of it, the points turn steadily one way in short
+/// steps and no chord is so long that the arc bulges more than `tolerance`
+/// off it. Returns the centre, the direction and how far the arc bulges from
+/// its chord.
+fn fit(pts: &[Vec2], tolerance: f64) -> Option<(Vec2, bool, f64)> {
+    let k = pts.len() - 1;
+    let center = circumcenter(pts[0], pts[k / 2], pts[k])?;
+    let r = Vec2::dist(center, pts[0]);
+    let (ux, uy) = (pts[0].x - center.x, pts[0].y - center.y);
+    let (vx, vy) = (pts[1].x - pts[0].x, pts[1].y - pts[0].y);
+    let clockwise = ux * vy - uy * vx < 0.0;
+
+    let mut sweep = 0.0;
+    for w in pts.windows(2) {
+        if (Vec2::dist(center, w[1]) - r).abs() > tolerance {
+            return None;
+        }
+        let a = (w[0].y - center.y).atan2(w[0].x - center.x);
+        let b = (w[1].y - center.y).atan2(w[1].x - center.x);
+        let mut d = b - a;
+        if d > std::f64::consts::PI {
+            d -= 2.0 * std::f64::consts::PI;
+        } else if d < -std::f64::consts::PI {
+            d += 2.0 * std::f64::consts::PI;
+        }
+        let d = if clockwise { -d } else { d };
+        if d <= 0.0 || d > MAX_CHORD_ANGLE || r * (1.0 - (d / 2.0).cos()) > tolerance {
+            return None;
+        }
+        sweep += d;
+    }
+    if sweep > MAX_SWEEP + 1e-9 {
+        return None;
+    }
+    Some((center, clockwise, r * (1.0 - (sweep / 2.0).cos())))
+}
+
+fn circumcenter(a: Vec2, b: Vec2, c: Vec2) -> Option<Vec2> {
+    let (bx, by) = (b.x - a.x, b.y - a.y);
+    let (cx, cy) = (c.x - a.x, c.y - a.y);
+    let d = 2.0 * (bx * cy - by * cx);
+    if d.abs() < 1e-12 {
+        return None;
+    }
+    let b2 = bx * bx + by * by;
+    let c2 = cx * cx + cy * cy;
+    Some(Vec2::new(
+        a.x + (cy * b2 - by * c2) / d,
+        a.y + (bx * c2 - cx * b2) / d,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geometry::Toolpath;
+
+    #[test]
+    fn long_gentle_run_fits_quickly() {
+        // 20 000 chords along a circle so large that no stretch of it bulges
+        // past the tolerance. Refitting every prefix from every start index
+        // took minutes here.
+        let tp = arc_path(1e7, 0.0, 0.002, 20_000, 0.0);
+        let motions = fit_arcs(&tp.moves, 0.01);
+        assert_eq!(motions.len(), tp.moves.len());
+        assert!(motions.iter().all(|m| matches!(m, Motion::Line(_))));
+    }
+
+    /// Feed moves along a circle of radius `r` about the origin, from
+    /// angle `a0` to `a1` (degrees) in `n` chords, after a rapid to the start.
+    fn arc_path(r: f64, a0: f64, a1: f64, n: usize, z: f64) -> Toolpath {
+        let mut tp = Toolpath::new();
+        let at = |t: f64| {
+            let a = (a0 + (a1 - a0) * t).to_radians();
+            (r * a.cos(), r * a.sin())
+        };
+        let (x, y) = at(0.0);
+        tp.rapid(x, y, z);
+        for i in 1..=n {
+            let (x, y) = at(i as f64 / n as f64);
+            tp.cut(x, y, z);
+        }
+        tp
+    }
+
+    #[test]
+    fn fits_quarter_circle_as_one_ccw_arc() {
+        let tp = arc_path(10.0, 0.0, 90.0, 30, -1.0);
+        let motions = fit_arcs(&tp.moves, 0.01);
+        assert_eq!(motions.len(), 2);
+        assert_eq!(motions[0], Motion::Line(0));
+        match motions[1] {
+            Motion::Arc {
+                start,
+                end,
+                center,
+                clockwise,
+            } => {
+                assert_eq!((start, end), (1, 30));
+                assert!(!clockwise);
+                assert!(center.x.abs() < 1e-9 && center.y.abs() < 1e-9);
+            }
+            other => panic!("expected arc, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn coarse_polygon_stays_as_lines() {
+        // A 24-gon of radius 100: its vertices lie on a circle, but the
+        // circle runs 0.86 mm outside the flats.
+        let tp = arc_path(100.0, 0.0, 180.0, 12, -1.0);
+        let motions = fit_arcs(&tp.moves, 0.01);
+        assert!(motions.iter().all(|m| matches!(m, Motion::Line(_))));
+        // Finely divided, the same circle is an arc.
+        let fine = arc_path(100.0, 0.0, 180.0, 180, -1.0);
+        assert!(fit_arcs(&fine.moves, 0.01)
+            .iter()
+            .any(|m| matches!(m, Motion::Arc { .. })));
+    }
+
+    #[test]
+    fn reversed_direction_is_clockwise() {
+        let tp = arc_path(5.0, 90.0, 0.0, 20, 0.0);
+        let arcs: Vec<_> = fit_arcs(&tp.moves, 0.01)
+            .into_iter()
+            .filter_map(|m| match m {
+                Motion::Arc { clockwise, .. } => Some(clockwise),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(arcs, vec![true]);
+    }
+
+    #[test]
+    fn full_circle_splits_into_half_turns() {
+        let tp = arc_path(10.0, 0.0, 360.0, 72, 0.0);
+        let motions = fit_arcs(&tp.moves, 0.01);
+        let arcs = motions
+            .iter()
+            .filter(|m| matches!(m, Motion::Arc { .. }))
+            .count();
+        assert_eq!(arcs, 2, "{:?}", motions);
+        assert_eq!(motions.len(), 3);
+    }
+
+    #[test]
+    fn straight_lines_stay_lines() {
+        let mut tp = Toolpath::new();
+        tp.rapid(0.0, 0.0, 0.0);
+        for i in 1..=10 {
+            tp.cut(i as f64, 0.0, 0.0);
+        }
+        let motions = fit_arcs(&tp.moves, 0.01);
+        assert!(motions.iter().all(|m| matches!(m, Motion::Line(_))));
+        assert_eq!(motions.len(), 11);
+    }
+
+    #[test]
+    fn polygon_corners_stay_lines() {
+        // An octagon's corners lie exactly on a circle, 45 degrees apart.
+        let tp = arc_path(10.0, 0.0, 360.0, 8, 0.0);
+        assert!(fit_arcs(&tp.moves, 1.0)
+            .iter()
+            .all(|m| matches!(m, Motion::Line(_))));
+    }
+
+    #[test]
+    fn points_off_the_circle_exceed_tolerance() {
+        let mut tp = arc_path(10.0, 0.0, 90.0, 30, 0.0);
+        // Push one point 0.05 mm outward.
+        let m = &mut tp.moves[15];
+        let s = 10.05 / 10.0;
+        (m.x, m.y) = (m.x * s, m.y * s);
+        let motions = fit_arcs(&tp.moves, 0.02);
+        assert!(motions.len() > 2, "{:?}", motions);
+        assert!(fit_arcs(&tp.moves, 0.1).len() <= 3);
+    }
+
+    #[test]
+    fn z_change_or_zero_tolerance_disables_fitting() {
+        let tp = arc_path(10.0, 0.0, 90.0, 30, -1.0);
+        assert_eq!(fit_arcs(&tp.moves, 0.0).len(), tp.moves.len());
+
+        // Descending along the curve (a helix) is not a planar arc.
+        let mut helix = Toolpath::new();
+        helix.rapid(10.0, 0.0, 0.0);
+        for i in 1..=30 {
+            let a = (i as f64 * 3.0).to_radians();
+            helix.cut(10.0 * a.cos(), 10.0 * a.sin(), -0.01 * i as f64);
+        }
+        assert_eq!(fit_arcs(&helix.moves, 0.01).len(), helix.moves.len());
+    }
+
+    #[test]
+    fn s_curve_splits_at_inflection() {
+        // Two quarter turns bending opposite ways.
+        let mut tp = arc_path(10.0, 180.0, 90.0, 20, 0.0);
+        let second = arc_path(10.0, 270.0, 360.0, 20, 0.0);
+        for m in &second.moves[1..] {
+            tp.cut(m.x, m.y + 20.0, 0.0);
+        }
+        let arcs: Vec<_> = fit_arcs(&tp.moves, 0.01)
+            .into_iter()
+            .filter_map(|m| match m {
+                Motion::Arc { clockwise, .. } => Some(clockwise),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(arcs, vec![true, false]);
+    }
+}
@@ -0,0 +1,915 @@
+//! Post-processors: controller dialects for G-code output.
+//!
+//! Swiss-cheese layer: **Output format**
+//! Extension point: implement `PostProcessor` for output a `PostFormat`
+//! cannot describe, or add a dialect to `PostFormat::builtin`.
+//!
+//! A `PostFormat` is a declarative description of a dialect: number
+//! precision, comment syntax, arc and drilling-cycle support, line numbering
+//! and the words used to start and stop the spindle or laser and to end the
+//! program. The built-in GRBL, LinuxCNC, Mach3 and Marlin dialects are plain
+//! values of it, and users can supply their own as JSON or TOML. Word
+//! templates may carry a trailing `(comment)`, which is rewritten in the
+//! dialect's comment style.
+
+use super::{fit_arcs, GcodeParams, JobSection, LaserParams, Motion};
+use crate::geometry::{DrillCycle, Toolpath, ToolpathMove};
+use crate::machine::{MachineProfile, MachineType};
+use serde::{Deserialize, Serialize};
+
+/// Turns toolpaths into a G-code program for one controller.
+pub trait PostProcessor {
+    fn emit(
+        &self,
+        toolpaths: &[Toolpath],
+        params: &GcodeParams,
+        profile: &MachineProfile,
+        laser_params: &LaserParams,
+    ) -> String;
+
+    /// A multi-tool CNC program: each section runs after a tool change
+    /// (skipped when the tool is already loaded).
+    fn emit_job(&self, sections: &[JobSection], profile: &MachineProfile) -> String;
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CommentStyle {
+    /// `G0 X1 (comment)`
+    #[default]
+    Parens,
+    /// `G0 X1 ; comment`
+    Semicolon,
+    /// Comments are dropped.
+    None,
+}
+
+/// Declarative controller dialect. Every field has a default (the generic
+/// RustCAM output), so a user format only lists what differs.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PostFormat {
+    pub name: String,
+    /// Decimal places for coordinates and arc offsets.
+    pub decimals: usize,
+    /// Decimal places for feed rates.
+    pub feed_decimals: usize,
+    pub comments: CommentStyle,
+    /// Whether the controller accepts G2/G3. When false, curves stay G1.
+    pub arcs: bool,
+    /// Whether the controller has G81/G82/G83 drilling cycles. When false,
+    /// holes are drilled with plain moves.
+    pub canned_cycles: bool,
+    /// Whether the controller has G41/G42 cutter radius compensation,
+    /// with the radius in D register `{tool}`.
+    pub cutter_comp: bool,
+    /// Prefix every command line with `N<n>`.
+    pub line_numbers: bool,
+    pub line_number_step: u32,
+    /// Lines written verbatim before anything else (e.g. `%`).
+    pub header: Vec<String>,
+    /// Spindle start; `{speed}` is replaced by the RPM.
+    pub spindle_on: String,
+    pub spindle_off: String,
+    /// Tool change in multi-tool jobs; `{tool}` is replaced by the T number.
+    pub tool_change: String,
+    /// Tool length offset after a tool change. Empty to omit.
+    pub tool_length_offset: String,
+    /// Laser enable before the first move.
+    pub laser_on: String,
+    pub laser_off: String,
+    /// S word for 100% laser power.
+    pub laser_power_max: f64,
+    /// Pause at the bottom of a hole drilled with plain moves; `{seconds}`
+    /// or `{ms}` is replaced by the dwell time.
+    pub dwell: String,
+    /// Last command of the program. Empty to omit.
+    pub program_end: String,
+    /// Lines written verbatim after everything else.
+    pub footer: Vec<String>,
+}
+
+impl Default for PostFormat {
+    fn default() -> Self {
+        Self {
+            name: "Generic".into(),
+            decimals: 4,
+            feed_decimals: 0,
+            comments: CommentStyle::Parens,
+            arcs: true,
+            canned_cycles: true,
+            cutter_comp: true,
+            line_numbers: false,
+            line_number_step: 10,
+            header: Vec::new(),
+            spindle_on: "M3 S{speed} (spindle on)".into(),
+            spindle_off: "M5 (spindle off)".into(),
+            tool_change: "T{tool} M6 (tool change)".into(),
+            tool_length_offset: "G43 H{tool} (tool length offset)".into(),
+            laser_on: "M4 S0 (dynamic laser mode)".into(),
+            laser_off: "M5 (laser off)".into(),
+            laser_power_max: 100.0,
+            dwell: "G4 P{seconds} (dwell)".into(),
+            program_end: "M2 (program end)".into(),
+            footer: Vec::new(),
+        }
+    }
+}
+
+impl PostFormat {
+    /// Names accepted by `builtin`.
+    pub const BUILTIN_NAMES: [&'static str; 5] = ["generic", "grbl", "linuxcnc", "mach3", "marlin"];
+
+    /// A built-in dialect by (case-insensitive) name.
+    pub fn builtin(name: &str) -> Option<Self> {
+        let base = Self::default();
+        match name.to_ascii_lowercase().as_str() {
+            "generic" => Some(base),
+            // GRBL 1.1: laser mode ($32=1) with dynamic power, S0-1000. No
+            // tool changer or tool table: pause for a manual change instead.
+            "grbl" => Some(Self {
+                name: "GRBL".into(),
+                decimals: 3,
+                canned_cycles: false,
+                cutter_comp: false,
+                tool_change: "M0 (change to T{tool})".into(),
+                tool_length_offset: String::new(),
+                laser_power_max: 1000.0,
+                ..base
+            }),
+            "linuxcnc" => Some(Self {
+                name: "LinuxCNC".into(),
+                header: vec!["%".into()],
+                laser_on: "M3 S0 (laser enable)".into(),
+                footer: vec!["%".into()],
+                ..base
+            }),
+            "mach3" => Some(Self {
+                name: "Mach3".into(),
+                line_numbers: true,
+                laser_on: "M3 S0 (laser enable)".into(),
+                program_end: "M30 (program end)".into(),
+                ..base
+            }),
+            // Marlin: `;` comments only, inline laser power S0-255, G4 P in
+            // milliseconds (S takes seconds), and no M2 -- release the
+            // steppers instead.
+            "marlin" => Some(Self {
+                name: "Marlin".into(),
+                decimals: 3,
+                comments: CommentStyle::Semicolon,
+                canned_cycles: false,
+                cutter_comp: false,
+                tool_change: "M0 (change to T{tool})".into(),
+                tool_length_offset: String::new(),
+                laser_on: "M3 I S0 (inline laser mode)".into(),
+                laser_power_max: 255.0,
+                dwell: "G4 S{seconds} (dwell)".into(),
+                program_end: "M84 (motors off)".into(),
+                ..base
+            }),
+            _ => None,
+        }
+    }
+
+    /// Parse a user-defined format from JSON or TOML.
+    pub fn parse(text: &str) -> Result<Self, String> {
+        if text.trim_start().starts_with('{') {
+            serde_json::from_str(text).map_err(|e| format!("Invalid post-processor JSON: {e}"))
+        } else {
+            toml::from_str(text).map_err(|e| format!("Invalid post-processor TOML: {e}"))
+        }
+    }
+}
+
+impl PostProcessor for PostFormat {
+    fn emit(
+        &self,
+        toolpaths: &[Toolpath],
+        params: &GcodeParams,
+        profile: &MachineProfile,
+        laser_params: &LaserParams,
+    ) -> String {
+        let mut w = Writer::new(self);
+        w.start(profile);
+
+        match profile.machine_type {
+            MachineType::CncMill => {
+                let safe_z = w.safe_z(params);
+                w.line(&safe_z);
+                w.spindle_on(params);
+                w.blank();
+                w.cnc_toolpaths(toolpaths, params);
+                w.line(&safe_z);
+                w.line(&self.spindle_off);
+            }
+            MachineType::LaserCutter => {
+                w.line(&self.laser_on);
+                if laser_params.air_assist {
+                    w.line("M8 (air assist on)");
+                }
+                w.blank();
+                for pass in 0..laser_params.passes {
+                    if laser_params.passes > 1 {
+                        w.comment(&format!("Pass {} of {}", pass + 1, laser_params.passes));
+                    }
+                    for (idx, tp) in toolpaths.iter().enumerate() {
+                        w.comment(&format!("Toolpath {}", idx + 1));
+                        w.laser_moves(&tp.moves, params, laser_params);
+                        w.blank();
+                    }
+                }
+                if laser_params.air_assist {
+                    w.line("M9 (air assist off)");
+                }
+                w.line(&self.laser_off);
+            }
+        }
+
+        w.finish(profile);
+        w.out
+    }
+
+    fn emit_job(&self, sections: &[JobSection], profile: &MachineProfile) -> String {
+        let mut w = Writer::new(self);
+        w.start(profile);
+
+        let mut current_tool = None;
+        for section in sections {
+            let params = &section.params;
+            w.blank();
+            for line in &section.comments {
+                w.comment(line);
+            }
+            let safe_z = w.safe_z(params);
+            w.line(&safe_z);
+            if current_tool != Some(section.tool) {
+                if current_tool.is_some() {
+                    w.line(&self.spindle_off);
+                }
+                let tool = section.tool.to_string();
+                w.line(&self.tool_change.replace("{tool}", &tool));
+                w.line(&self.tool_length_offset.replace("{tool}", &tool));
+                current_tool = Some(section.tool);
+                w.tool = section.tool;
+            }
+            w.spindle_on(params);
+            w.blank();
+            w.cnc_toolpaths(&section.toolpaths, params);
+            w.line(&safe_z);
+        }
+        if current_tool.is_some() {
+            w.line(&self.spindle_off);
+        }
+
+        w.finish(profile);
+        w.out
+    }
+}
+
+/// Formats lines in a dialect, numbering them if it asks for it.
+struct Writer<'a> {
+    post: &'a PostFormat,
+    out: String,
+    line_number: u32,
+    /// Loaded tool, whose D register holds the compensation radius.
+    tool: u32,
+}
+
+impl<'a> Writer<'a> {
+    fn new(post: &'a PostFormat) -> Self {
+        Self {
+            post,
+            out: String::with_capacity(4096),
+            line_number: 0,
+            tool: 1,
+        }
+    }
+
+    fn num(&self, v: f64) -> String {
+        // Round tiny values to a plain zero rather than printing "-0.000".
+        let v = if v.abs() < 0.5 * 10f64.powi(-(self.post.decimals as i32)) {
+            0.0
+        } else {
+            v
+        };
+        format!("{:.*}", self.post.decimals, v)
+    }
+
+    fn feed(&self, v: f64) -> String {
+        format!("F{:.*}", self.post.feed_decimals, v)
+    }
+
+    /// A command with an optional trailing `(comment)` or `; comment`.
+    fn line(&mut self, text: &str) {
+        let (code, comment) = split_comment(text);
+        if code.is_empty() {
+            if let Some(c) = comment {
+                self.comment(c);
+            }
+            return;
+        }
+        if self.post.line_numbers {
+            self.line_number += self.post.line_number_step;
+            self.out.push_str(&format!("N{} ", self.line_number));
+        }
+        self.out.push_str(code);
+        match (comment, self.post.comments) {
+            (Some(c), CommentStyle::Parens) => self.out.push_str(&format!(" ({c})")),
+            (Some(c), CommentStyle::Semicolon) => self.out.push_str(&format!(" ; {c}")),
+            _ => {}
+        }
+        self.out.push('\n');
+    }
+
+    fn comment(&mut self, text: &str) {
+        // Parentheses would end a paren comment early.
+        let text = text.replace('(', "[").replace(')', "]");
+        match self.post.comments {
+            CommentStyle::Parens => self.out.push_str(&format!("({text})\n")),
+            CommentStyle::Semicolon => self.out.push_str(&format!("; {text}\n")),
+            CommentStyle::None => {}
+        }
+    }
+
+    /// Header lines, units and the profile preamble.
+    fn start(&mut self, profile: &MachineProfile) {
+        for line in &self.post.header {
+            self.verbatim(line);
+        }
+        self.comment("RustCAM — generated G-code");
+        self.line("G21 (metric)");
+        self.line("G90 (absolute positioning)");
+        for line in &profile.output_config.preamble {
+            self.line(line);
+        }
+    }
+
+    /// The profile postamble, program end and footer lines.
+    fn finish(&mut self, profile: &MachineProfile) {
+        for line in &profile.output_config.postamble {
+            self.line(line);
+        }
+        self.line(&self.post.program_end);
+        for line in &self.post.footer {
+            self.verbatim(line);
+        }
+    }
+
+    fn safe_z(&self, params: &GcodeParams) -> String {
+        format!("G0 Z{}", self.num(params.safe_z))
+    }
+
+    fn spindle_on(&mut self, params: &GcodeParams) {
+        let speed = format!("{:.0}", params.spindle_speed);
+        self.line(&self.post.spindle_on.replace("{speed}", &speed));
+    }
+
+    fn verbatim(&mut self, text: &str) {
+        self.out.push_str(text);
+        self.out.push('\n');
+    }
+
+    fn blank(&mut self) {
+        self.out.push('\n');
+    }
+
+    fn motions(&self, moves: &[ToolpathMove], params: &GcodeParams) -> Vec<Motion> {
+        let tolerance = if self.post.arcs {
+            params.arc_tolerance
+        } else {
+            0.0
+        };
+        fit_arcs(moves, tolerance)
+    }
+
+    /// `X.. Y..` of a move, preceded by the G word and followed by `I.. J..`
+    /// for arcs.
+    fn xy(&self, moves: &[ToolpathMove], motion: Motion, line: &str) -> String {
+        match motion {
+            Motion::Line(i) => format!(
+                "{} X{} Y{}",
+                line,
+                self.num(moves[i].x),
+                self.num(moves[i].y)
+            ),
+            Motion::Arc {
+                start,
+                end,
+                center,
+                clockwise,
+            } => {
+                let from = &moves[start - 1];
+                format!(
+                    "{} X{} Y{} I{} J{}",
+                    if clockwise { "G2" } else { "G3" },
+                    self.num(moves[end].x),
+                    self.num(moves[end].y),
+                    self.num(center.x - from.x),
+                    self.num(center.y - from.y)
+                )
+            }
+        }
+    }
+
+    /// Numbered CNC toolpaths. Runs of holes with the same cycle become one
+    /// modal canned cycle when the dialect has them.
+    fn cnc_toolpaths(&mut self, toolpaths: &[Toolpath], params: &GcodeParams) {
+        let mut active: Option<DrillCycle> = None;
+        for (idx, tp) in toolpaths.iter().enumerate() {
+            let cycle = tp.drill.filter(|_| self.post.canned_cycles);
+            if let Some(prev) = active {
+                if !cycle.is_some_and(|c| c.same_cycle(&prev)) {
+                    self.line("G80 (cancel cycle)");
+                    self.blank();
+                    active = None;
+                }
+            }
+            self.comment(&format!("Toolpath {}", idx + 1));
+            match (cycle, tp.drill) {
+                (Some(c), _) => {
+                    if active.is_some() {
+                        self.line(&format!("X{} Y{}", self.num(c.x), self.num(c.y)));
+                    } else {
+                        // The cycle retracts to the Z it starts from (G98).
+                        self.cnc_moves(&tp.moves[..1], params);
+                        self.canned_cycle(&c, params);
+                        active = Some(c);
+                    }
+                    continue;
+                }
+                (None, Some(c)) if c.dwell > 0.0 => {
+                    // Dwell once the drill reaches the bottom, after the
+                    // last peck when pecking.
+                    let bottom = tp.moves.iter().position(|m| m.z <= c.z).unwrap_or(0);
+                    self.cnc_moves(&tp.moves[..=bottom], params);
+                    let dwell = self
+                        .post
+                        .dwell
+                        .replace("{seconds}", &self.num(c.dwell))
+                        .replace("{ms}", &format!("{:.0}", c.dwell * 1000.0));
+                    self.line(&dwell);
+                    self.cnc_moves(&tp.moves[bottom + 1..], params);
+                }
+                _ => self.cnc_moves(&tp.moves, params),
+            }
+            self.blank();
+        }
+        if active.is_some() {
+            self.line("G80 (cancel cycle)");
+            self.blank();
+        }
+    }
+
+    fn canned_cycle(&mut self, c: &DrillCycle, params: &GcodeParams) {
+        let mut words = format!(
+            "G98 {} X{} Y{} Z{} R{}",
+            c.code(),
+            self.num(c.x),
+            self.num(c.y),
+            self.num(c.z),
+            self.num(c.retract_z)
+        );
+        match c.code() {
+            "G83" => words.push_str(&format!(" Q{}", self.num(c.peck))),
+            "G82" => words.push_str(&format!(" P{}", self.num(c.dwell))),
+            _ => {}
+        }
+        self.line(&format!("{} {}", words, self.feed(params.plunge_rate)));
+    }
+
+    fn cnc_moves(&mut self, moves: &[ToolpathMove], params: &GcodeParams) {
+        let mut last_rapid = true;
+        let mut comp = None;
+        for motion in self.motions(moves, params) {
+            let mv = &moves[super::motion_end(motion)];
+            if mv.rapid {
+                let words = self.xy(moves, motion, "G0");
+                self.line(&format!("{} Z{}", words, self.num(mv.z)));
+                last_rapid = true;
+                continue;
+            }
+            let feed = if mv.z < params.safe_z - 0.01 && last_rapid {
+                params.plunge_rate
+            } else {
+                params.feed_rate
+            };
+            let mut words = match motion {
+                Motion::Line(_) => format!("{} Z{}", self.xy(moves, motion, "G1"), self.num(mv.z)),
+                Motion::Arc { .. } => self.xy(moves, motion, "G1"),
+            };
+            if mv.comp != comp {
+                // Switched on or off over a straight move (see `fit_arcs`).
+                let code = match mv.comp {
+                    Some(side) => format!("{} D{} ", side.code(), self.tool),
+                    None => "G40 ".into(),
+                };
+                words.insert_str(0, &code);
+                comp = mv.comp;
+            }
+            self.line(&format!("{} {}", words, self.feed(feed)));
+            last_rapid = false;
+        }
+    }
+
+    fn laser_moves(
+        &mut self,
+        moves: &[ToolpathMove],
+        params: &GcodeParams,
+        laser_params: &LaserParams,
+    ) {
+        for motion in self.motions(moves, params) {
+            let mv = &moves[super::motion_end(motion)];
+            if mv.rapid {
+                // Laser off during rapids
+                let words = self.xy(moves, motion, "G0");
+                self.line(&format!("{} S0", words));
+            } else {
+                // Use move-specific power if available, otherwise default
+                let power = mv.power.unwrap_or(laser_params.power);
+                let s = power / 100.0 * self.post.laser_power_max;
+                let words = self.xy(moves, motion, "G1");
+                self.line(&format!(
+                    "{} {} S{:.0}",
+                    words,
+                    self.feed(params.feed_rate),
+                    s
+                ));
+            }
+        }
+    }
+}
+
+/// Split `"M5 (spindle off)"` or `"M5 ; spindle off"` into code and comment.
+fn split_comment(text: &str) -> (&str, Option<&str>) {
+    let text = text.trim();
+    if let Some(open) = text.find('(') {
+        if text.ends_with(')') {
+            return (
+                text[..open].trim_end(),
+                Some(&text[open + 1..text.len() - 1]),
+            );
+        }
+    }
+    if let Some(semi) = text.find(';') {
+        return (text[..semi].trim_end(), Some(text[semi + 1..].trim()));
+    }
+    (text, None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geometry::CutterComp;
+
+    fn square() -> Toolpath {
+        let mut tp = Toolpath::new();
+        tp.rapid(0.0, 0.0, 5.0);
+        tp.cut(0.0, 0.0, -1.0);
+        tp.cut(10.0, 0.0, -1.0);
+        tp.cut(10.0, 10.0, -1.0);
+        tp.rapid(10.0, 10.0, 5.0);
+        tp
+    }
+
+    fn emit(post: &PostFormat, profile: &MachineProfile) -> String {
+        post.emit(
+            &[square()],
+            &GcodeParams::default(),
+            profile,
+            &LaserParams::default(),
+        )
+    }
+
+    #[test]
+    fn all_builtins_resolve() {
+        for name in PostFormat::BUILTIN_NAMES {
+            assert!(PostFormat::builtin(name).is_some(), "{name}");
+        }
+        assert_eq!(PostFormat::builtin("GRBL").unwrap().name, "GRBL");
+        assert!(PostFormat::builtin("fanuc").is_none());
+    }
+
+    #[test]
+    fn grbl_uses_three_decimals() {
+        let code = emit(
+            &PostFormat::builtin("grbl").unwrap(),
+            &MachineProfile::cnc_mill(),
+        );
+        assert!(code.contains("G1 X10.000 Y0.000 Z-1.000 F800\n"), "{code}");
+        assert!(code.contains("M2 (program end)"));
+    }
+
+    #[test]
+    fn marlin_uses_semicolon_comments() {
+        let code = emit(
+            &PostFormat::builtin("marlin").unwrap(),
+            &MachineProfile::cnc_mill(),
+        );
+        assert!(!code.contains('('), "{code}");
+        assert!(code.starts_with("; RustCAM"));
+        assert!(code.contains("M5 ; spindle off\n"));
+        assert!(code.trim_end().ends_with("M84 ; motors off"));
+    }
+
+    #[test]
+    fn mach3_numbers_lines_and_ends_with_m30() {
+        let code = emit(
+            &PostFormat::builtin("mach3").unwrap(),
+            &MachineProfile::cnc_mill(),
+        );
+        let numbered: Vec<&str> = code.lines().filter(|l| l.starts_with('N')).collect();
+        assert_eq!(numbered[0], "N10 G21 (metric)");
+        assert_eq!(numbered[1], "N20 G90 (absolute positioning)");
+        assert!(numbered.last().unwrap().ends_with("M30 (program end)"));
+        // Comments and blank lines are not numbered.
+        assert!(code.lines().any(|l| l == "(Toolpath 1)"));
+    }
+
+    #[test]
+    fn linuxcnc_wraps_program_in_percent() {
+        let code = emit(
+            &PostFormat::builtin("linuxcnc").unwrap(),
+            &MachineProfile::cnc_mill(),
+        );
+        assert!(code.starts_with("%\n"));
+        assert!(code.ends_with("M2 (program end)\n%\n"));
+    }
+
+    #[test]
+    fn laser_power_scales_to_dialect_range() {
+        let mut tp = Toolpath::new();
+        tp.rapid(0.0, 0.0, 0.0);
+        tp.cut_with_power(10.0, 0.0, 0.0, 50.0);
+        let profile = MachineProfile::laser_cutter();
+        let params = GcodeParams::default();
+        let laser = LaserParams::default();
+        let grbl =
+            PostFormat::builtin("grbl")
+                .unwrap()
+                .emit(&[tp.clone()], &params, &profile, &laser);
+        assert!(grbl.contains("G1 X10.000 Y0.000 F800 S500\n"), "{grbl}");
+        let marlin = PostFormat::builtin("marlin")
+            .unwrap()
+            .emit(&[tp], &params, &profile, &laser);
+        assert!(marlin.contains("M3 I S0 ; inline laser mode"));
+        assert!(marlin.contains("S128\n"), "{marlin}");
+    }
+
+    #[test]
+    fn cutter_comp_changes_on_straight_moves() {
+        let mut tp = Toolpath::new();
+        tp.rapid(20.0, 0.0, 5.0);
+        tp.cut(20.0, 0.0, -1.0);
+        tp.cut(10.0, 0.0, -1.0);
+        for i in 1..=18 {
+            let a = (i as f64 * 5.0).to_radians();
+            tp.cut(10.0 * a.cos(), 10.0 * a.sin(), -1.0);
+        }
+        tp.cut(0.0, 20.0, -1.0);
+        tp.rapid(0.0, 20.0, 5.0);
+        let last = tp.moves.len() - 2;
+        for mv in &mut tp.moves[2..last] {
+            mv.comp = Some(CutterComp::Right);
+        }
+        let params = GcodeParams {
+            arc_tolerance: 0.01,
+            ..GcodeParams::default()
+        };
+        let profile = MachineProfile::cnc_mill();
+        let gcode = PostFormat::default().emit(&[tp], &params, &profile, &LaserParams::default());
+        let on = gcode
+            .find("G42 D1 G1 X10.0000 Y0.0000 Z-1.0000 F800\n")
+            .expect(&gcode);
+        let arc = gcode
+            .find("G3 X0.0000 Y10.0000 I-10.0000 J0.0000")
+            .expect(&gcode);
+        let off = gcode.find("G40 G1 X0.0000 Y20.0000").expect(&gcode);
+        assert!(on < arc && arc < off);
+        assert_eq!(gcode.matches("G42").count(), 1);
+    }
+
+    #[test]
+    fn arcs_off_keeps_curves_as_lines() {
+        let mut tp = Toolpath::new();
+        tp.rapid(10.0, 0.0, 5.0);
+        tp.cut(10.0, 0.0, -1.0);
+        for i in 1..=18 {
+            let a = (i as f64 * 5.0).to_radians();
+            tp.cut(10.0 * a.cos(), 10.0 * a.sin(), -1.0);
+        }
+        let params = GcodeParams {
+            arc_tolerance: 0.01,
+            ..GcodeParams::default()
+        };
+        let profile = MachineProfile::cnc_mill();
+        let laser = LaserParams::default();
+        let with_arcs = PostFormat::default().emit(&[tp.clone()], &params, &profile, &laser);
+        assert!(
+            with_arcs.contains("G3 X0.0000 Y10.0000 I-10.0000 J0.0000 F800"),
+            "{with_arcs}"
+        );
+        let no_arcs = PostFormat {
+            arcs: false,
+            ..PostFormat::default()
+        };
+        let code = no_arcs.emit(&[tp], &params, &profile, &laser);
+        assert!(!code.contains("G3"));
+        assert_eq!(code.matches("G1 ").count(), 19);
+    }
+
+    #[test]
+    fn parses_user_format_from_json_and_toml() {
+        let json = r#"{"name": "Shop", "decimals": 2, "comments": "none"}"#;
+        let post = PostFormat::parse(json).unwrap();
+        assert_eq!(post.decimals, 2);
+        assert_eq!(post.comments, CommentStyle::None);
+        assert_eq!(post.program_end, PostFormat::default().program_end);
+
+        let toml = r#"
+            name = "Shop"
+            line_numbers = true
+            line_number_step = 5
+            program_end = "M30"
+            header = ["%"]
+        "#;
+        let post = PostFormat::parse(toml).unwrap();
+        assert!(post.line_numbers);
+        assert_eq!(post.line_number_step, 5);
+        assert_eq!(post.header, vec!["%".to_string()]);
+
+        let code = emit(&post, &MachineProfile::cnc_mill());
+        assert!(code.contains("N5 G21 (metric)\n"));
+        assert!(code.trim_end().ends_with("M30"));
+
+        assert!(PostFormat::parse("decimals = \"two\"").is_err());
+        assert!(PostFormat::parse("{\"decimals\": -1}").is_err());
+    }
+
+    #[test]
+    fn comments_none_drops_comment_lines() {
+        let post = PostFormat {
+            comments: CommentStyle::None,
+            ..PostFormat::default()
+        };
+        let code = emit(&post, &MachineProfile::cnc_mill());
+        assert!(!code.contains('('));
+        assert!(code.starts_with("G21\nG90\n"));
+    }
+
+    fn job(tools: &[u32]) -> Vec<JobSection> {
+        tools
+            .iter()
+            .enumerate()
+            .map(|(i, &tool)| JobSection {
+                comments: vec![format!("Operation {} (T{tool})", i + 1)],
+                tool,
+                params: GcodeParams {
+                    spindle_speed: 10000.0 + 1000.0 * i as f64,
+                    ..GcodeParams::default()
+                },
+                toolpaths: vec![square()],
+            })
+            .collect()
+    }
+
+    #[test]
+    fn job_changes_tools_between_operations() {
+        let code = PostFormat::default().emit_job(&job(&[1, 1, 3]), &MachineProfile::cnc_mill());
+        assert_eq!(code.matches(" M6").count(), 2, "{code}");
+        let t1 = code.find("T1 M6").unwrap();
+        let h1 = code.find("G43 H1").unwrap();
+        let t3 = code.find("T3 M6").unwrap();
+        let h3 = code.find("G43 H3").unwrap();
+        assert!(t1 < h1 && h1 < t3 && t3 < h3);
+        // The spindle stops before the change and restarts at each
+        // operation's own speed.
+        assert!(code[h1..t3].contains("M3 S11000"));
+        assert!(code[..t3].trim_end().ends_with("M5 (spindle off)"));
+        assert!(code[h3..].contains("M3 S12000"));
+        assert!(code.contains("(Operation 1 [T1])"));
+        assert!(code.trim_end().ends_with("M2 (program end)"));
+    }
+
+    #[test]
+    fn grbl_job_pauses_for_manual_tool_change() {
+        let code = PostFormat::builtin("grbl")
+            .unwrap()
+            .emit_job(&job(&[2, 5]), &MachineProfile::cnc_mill());
+        assert!(code.contains("M0 (change to T2)"));
+        assert!(code.contains("M0 (change to T5)"));
+        assert!(!code.contains("M6") && !code.contains("G43"));
+    }
+
+    fn hole(x: f64, z: f64) -> Toolpath {
+        let mut tp = Toolpath::new();
+        tp.rapid(x, 0.0, 5.0);
+        tp.rapid(x, 0.0, 1.0);
+        tp.cut(x, 0.0, z);
+        tp.rapid(x, 0.0, 5.0);
+        tp.drill = Some(DrillCycle {
+            x,
+            y: 0.0,
+            z,
+            retract_z: 1.0,
+            peck: 0.0,
+            dwell: 0.0,
+        });
+        tp
+    }
+
+    #[test]
+    fn canned_cycles_stay_modal_until_the_cycle_changes() {
+        let toolpaths = [
+            hole(10.0, -3.0),
+            hole(20.0, -3.0),
+            hole(30.0, -5.0),
+            square(),
+        ];
+        let code = PostFormat::default().emit(
+            &toolpaths,
+            &GcodeParams::default(),
+            &MachineProfile::cnc_mill(),
+            &LaserParams::default(),
+        );
+        let cycle_lines: Vec<&str> = code
+            .lines()
+            .filter(|l| l.starts_with("G98") || l.starts_with('X') || l.starts_with("G80"))
+            .collect();
+        assert_eq!(
+            cycle_lines,
+            [
+                "G98 G81 X10.0000 Y0.0000 Z-3.0000 R1.0000 F300",
+                "X20.0000 Y0.0000",
+                "G80 (cancel cycle)",
+                "G98 G81 X30.0000 Y0.0000 Z-5.0000 R1.0000 F300",
+                "G80 (cancel cycle)",
+            ]
+        );
+        // Each run starts from safe Z, where G98 brings the drill back.
+        assert!(code.contains("G0 X30.0000 Y0.0000 Z5.0000\nG98"));
+    }
+
+    #[test]
+    fn expanded_peck_cycle_dwells_at_the_bottom() {
+        // Two pecks to -4 with a half-second dwell, on a dialect without
+        // canned cycles.
+        let mut tp = Toolpath::new();
+        tp.rapid(10.0, 0.0, 5.0);
+        tp.rapid(10.0, 0.0, 1.0);
+        tp.cut(10.0, 0.0, -2.0);
+        tp.rapid(10.0, 0.0, 1.0);
+        tp.rapid(10.0, 0.0, -1.5);
+        tp.cut(10.0, 0.0, -4.0);
+        tp.rapid(10.0, 0.0, 5.0);
+        tp.drill = Some(DrillCycle {
+            x: 10.0,
+            y: 0.0,
+            z: -4.0,
+            retract_z: 1.0,
+            peck: 2.0,
+            dwell: 0.5,
+        });
+        // GRBL reads G4 P in seconds, Marlin in milliseconds.
+        let in_ms = PostFormat {
+            dwell: "G4 P{ms}".into(),
+            ..PostFormat::builtin("grbl").unwrap()
+        };
+        for (post, expected) in [
+            (PostFormat::builtin("grbl").unwrap(), "G4 P0.500 (dwell)"),
+            (PostFormat::builtin("marlin").unwrap(), "G4 S0.500 ; dwell"),
+            (in_ms, "G4 P500"),
+        ] {
+            let code = post.emit(
+                std::slice::from_ref(&tp),
+                &GcodeParams::default(),
+                &MachineProfile::cnc_mill(),
+                &LaserParams::default(),
+            );
+            assert!(!code.contains("G83"));
+            let lines: Vec<&str> = code.lines().collect();
+            let dwells: Vec<usize> = (0..lines.len())
+                .filter(|&i| lines[i].starts_with("G4"))
+                .collect();
+            assert_eq!(dwells.len(), 1, "{code}");
+            let dwell = dwells[0];
+            assert_eq!(lines[dwell], expected, "{code}");
+            assert!(lines[dwell - 1].contains("Z-4.000"), "{code}");
+            assert!(lines[dwell + 1].contains("Z5.000"), "{code}");
+        }
+    }
+
+    #[test]
+    fn split_comment_forms() {
+        assert_eq!(
+            split_comment("M5 (spindle off)"),
+            ("M5", Some("spindle off"))
+        );
+        assert_eq!(
+            split_comment("M5 ; spindle off"),
+            ("M5", Some("spindle off"))
+        );
+        assert_eq!(split_comment("(note)"), ("", Some("note")));
+        assert_eq!(split_comment("G0 X0 Y0"), ("G0 X0 Y0", None));
+    }
+}
@@ -0,0 +1,754 @@
+//! Modal G-code interpreter and back-plotter.
+//!
+//! Swiss-cheese layer: **Verification**
+//! Extension point: teach `Interpreter::execute` more cycles or arc planes,
+//! or `gcode_parser::parse_block` more controller codes.
+//!
+//! Runs a program block by block the way a controller does, carrying the
+//! modal state (motion mode, units, G90/G91, work offset, plane, spindle,
+//! coolant, feed, tool) from line to line. Every motion ends up in machine
+//! coordinates in millimetres: arcs are split into chords within a
+//! tolerance and drilling cycles into their plunges and retracts. The flat
+//! move list back-plots programs from other CAM systems as well as ours,
+//! and the totals give a quick sanity check.
+
+use serde::{Deserialize, Serialize};
+
+use crate::gcode::GcodeWarning;
+use crate::gcode_parser::{
+    parse_block, CoolantControl, CycleReturn, DistanceMode, GCodeCommand, MotionMode, Plane,
+    SpindleControl, UnitMode, WorkOffset,
+};
+
+const MM_PER_INCH: f64 = 25.4;
+
+/// How far above the last peck a G83 rapids back down, and how far a G73
+/// backs off to break the chip (LinuxCNC uses 0.010 in for both).
+const PECK_CLEARANCE: f64 = 0.254;
+
+/// Arc endpoints further than this from the circle draw a warning (mm).
+const ARC_RADIUS_TOLERANCE: f64 = 0.01;
+
+/// Most chords one arc is split into.
+const MAX_ARC_SEGMENTS: usize = 10_000;
+
+/// Machine setup the program runs on.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct InterpreterConfig {
+    /// G54..G59 origins in machine coordinates (mm).
+    pub work_offsets: [[f64; 3]; 6],
+    /// Largest distance between an arc and its chords (mm).
+    pub arc_tolerance: f64,
+    /// Machine position at the start and the target of G28 (mm).
+    pub home: [f64; 3],
+}
+
+impl Default for InterpreterConfig {
+    fn default() -> Self {
+        Self {
+            work_offsets: [[0.0; 3]; 6],
+            arc_tolerance: 0.01,
+            home: [0.0; 3],
+        }
+    }
+}
+
+/// A drilling cycle in effect, with its depths in machine Z.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ActiveCycle {
+    pub code: u8,
+    pub z: f64,
+    pub r: f64,
+    /// Peck depth in mm (0 = none).
+    pub q: f64,
+    /// Dwell in seconds.
+    pub p: f64,
+}
+
+/// Modal state carried from block to block.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MachineState {
+    pub motion: MotionMode,
+    /// Set while a canned cycle is the modal motion.
+    pub cycle: Option<ActiveCycle>,
+    pub units: UnitMode,
+    pub distance: DistanceMode,
+    pub arc_distance: DistanceMode,
+    pub plane: Plane,
+    pub work_offset: WorkOffset,
+    pub cycle_return: CycleReturn,
+    pub spindle: SpindleControl,
+    pub coolant: CoolantControl,
+    /// Feed rate in mm/min.
+    pub feed: f64,
+    /// Tool in the spindle.
+    pub tool: u32,
+    /// Tool the next M6 loads.
+    pub next_tool: u32,
+    /// Machine position (mm).
+    pub position: [f64; 3],
+    /// M2 or M30 has run.
+    pub ended: bool,
+}
+
+/// One straight move of the back-plot, in machine coordinates.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct PlotMove {
+    pub x: f64,
+    pub y: f64,
+    pub z: f64,
+    pub rapid: bool,
+    /// Feed rate in mm/min; 0 for rapids.
+    pub feed: f64,
+    /// Source line (1-based).
+    pub line: usize,
+    pub tool: u32,
+    /// Seconds spent dwelling at the end of the move.
+    pub dwell: f64,
+}
+
+/// An M6 in the program.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct ToolChange {
+    pub line: usize,
+    pub tool: u32,
+}
+
+/// Totals for a program.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct ProgramStats {
+    /// Lowest and highest machine coordinates the tool reaches.
+    pub bounds: Option<[[f64; 3]; 2]>,
+    /// The same for feed moves only.
+    pub cut_bounds: Option<[[f64; 3]; 2]>,
+    /// Feed distance (mm).
+    pub cut_distance: f64,
+    /// Rapid distance (mm).
+    pub rapid_distance: f64,
+    /// Time at the programmed feed rates, ignoring acceleration (s).
+    pub feed_time: f64,
+    /// Total dwell (s).
+    pub dwell_time: f64,
+    pub tool_changes: Vec<ToolChange>,
+    pub lines: usize,
+}
+
+/// Everything the interpreter makes of a program.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct Program {
+    pub moves: Vec<PlotMove>,
+    pub stats: ProgramStats,
+    /// Parse errors, codes that were ignored and suspicious arcs.
+    pub warnings: Vec<GcodeWarning>,
+}
+
+/// Interpret a whole program.
+pub fn interpret(program: &str, config: &InterpreterConfig) -> Program {
+    let mut interpreter = Interpreter::new(config.clone());
+    interpreter.run(program);
+    interpreter.finish()
+}
+
+/// Executes commands against a `MachineState`, recording the moves.
+pub struct Interpreter {
+    config: InterpreterConfig,
+    state: MachineState,
+    program: Program,
+    line: usize,
+}
+
+impl Interpreter {
+    pub fn new(config: InterpreterConfig) -> Self {
+        let state = MachineState {
+            motion: MotionMode::Rapid,
+            cycle: None,
+            units: UnitMode::Millimeters,
+            distance: DistanceMode::Absolute,
+            arc_distance: DistanceMode::Incremental,
+            plane: Plane::XY,
+            work_offset: WorkOffset::G54,
+            cycle_return: CycleReturn::Initial,
+            spindle: SpindleControl::Off,
+            coolant: CoolantControl::Off,
+            feed: 0.0,
+            tool: 0,
+            next_tool: 0,
+            position: config.home,
+            ended: false,
+        };
+        Self {
+            config,
+            state,
+            program: Program::default(),
+            line: 0,
+        }
+    }
+
+    pub fn state(&self) -> &MachineState {
+        &self.state
+    }
+
+    /// Run every line of `program` until it ends.
+    pub fn run(&mut self, program: &str) {
+        for text in program.lines() {
+            if self.state.ended {
+                break;
+            }
+            self.line += 1;
+            self.program.stats.lines = self.line;
+            match parse_block(text) {
+                Ok(commands) => {
+                    for cmd in &commands {
+                        self.execute(cmd);
+                    }
+                }
+                Err(e) => self.warn(format!("Parse error: {e}")),
+            }
+        }
+    }
+
+    pub fn finish(self) -> Program {
+        self.program
+    }
+
+    /// Apply one command at the current line.
+    pub fn execute(&mut self, cmd: &GCodeCommand) {
+        match cmd {
+            GCodeCommand::RapidMove { x, y, z } => {
+                self.state.motion = MotionMode::Rapid;
+                self.state.cycle = None;
+                let to = self.target(*x, *y, *z);
+                self.push(to, true);
+            }
+            GCodeCommand::LinearMove { x, y, z, feed } => {
+                self.state.motion = MotionMode::Linear;
+                self.state.cycle = None;
+                self.set_feed(*feed);
+                let to = self.target(*x, *y, *z);
+                self.push(to, false);
+            }
+            GCodeCommand::ArcMove {
+                clockwise,
+                x,
+                y,
+                z,
+                i,
+                j,
+                r,
+                feed,
+            } => {
+                self.state.motion = if *clockwise {
+                    MotionMode::ClockwiseArc
+                } else {
+                    MotionMode::CounterClockwiseArc
+                };
+                self.state.cycle = None;
+                self.set_feed(*feed);
+                self.arc(*clockwise, [*x, *y, *z], *i, *j, *r);
+            }
+            GCodeCommand::CannedCycle {
+                code,
+                x,
+                y,
+                z,
+                r,
+                q,
+                p,
+                feed,
+            } => {
+                self.set_feed(*feed);
+                self.start_cycle(*code, *z, *r, *q, *p);
+                self.drill(*x, *y);
+            }
+            GCodeCommand::ModalMotion {
+                x,
+                y,
+                z,
+                i,
+                j,
+                r,
+                feed,
+            } => {
+                self.set_feed(*feed);
+                if let Some(cycle) = self.state.cycle {
+                    if z.is_some() || r.is_some() {
+                        self.start_cycle(cycle.code, *z, *r, None, None);
+                    }
+                    self.drill(*x, *y);
+                    return;
+                }
+                let to = self.target(*x, *y, *z);
+                match self.state.motion {
+                    MotionMode::Rapid => self.push(to, true),
+                    MotionMode::Linear => self.push(to, false),
+                    mode => self.arc(mode == MotionMode::ClockwiseArc, [*x, *y, *z], *i, *j, *r),
+                }
+            }
+            GCodeCommand::CancelCycle => self.state.cycle = None,
+            GCodeCommand::SetFeedRate(f) => self.set_feed(Some(*f)),
+            GCodeCommand::SetPlane(plane) => self.state.plane = *plane,
+            GCodeCommand::SetArcDistanceMode(mode) => self.state.arc_distance = *mode,
+            GCodeCommand::SetCycleReturn(ret) => self.state.cycle_return = *ret,
+            GCodeCommand::SelectTool(t) => self.state.next_tool = *t,
+            GCodeCommand::ToolChange => {
+                self.state.tool = self.state.next_tool;
+                self.program.stats.tool_changes.push(ToolChange {
+                    line: self.line,
+                    tool: self.state.tool,
+                });
+            }
+            GCodeCommand::SetUnits(units) => self.state.units = *units,
+            GCodeCommand::SetDistanceMode(mode) => self.state.distance = *mode,
+            GCodeCommand::SetWorkOffset(offset) => self.state.work_offset = *offset,
+            GCodeCommand::SetSpindle(ctrl) => self.state.spindle = *ctrl,
+            GCodeCommand::SetCoolant(ctrl) => self.state.coolant = *ctrl,
+            GCodeCommand::Dwell { seconds } => self.dwell(*seconds),
+            GCodeCommand::ProgramEnd => self.state.ended = true,
+            GCodeCommand::ProgramPause | GCodeCommand::Comment(_) => {}
+            GCodeCommand::Home { axes } => {
+                // The intermediate point is not kept by the parser, so the
+                // move goes straight home.
+                let all = !axes.any();
+                let mut to = self.state.position;
+                for (k, on) in [axes.x, axes.y, axes.z].into_iter().enumerate() {
+                    if all || on {
+                        to[k] = self.config.home[k];
+                    }
+                }
+                self.push(to, true);
+            }
+            GCodeCommand::ProbeToward { z, feed } => {
+                // Without a workpiece the probe travels the whole way.
+                self.set_feed(Some(*feed));
+                let to = self.target(None, None, Some(*z));
+                self.push(to, false);
+            }
+            GCodeCommand::Raw(code) => self.warn(format!("Ignored {code}")),
+        }
+    }
+
+    fn warn(&mut self, message: String) {
+        self.program.warnings.push(GcodeWarning {
+            line_number: self.line,
+            message,
+        });
+    }
+
+    fn scale(&self) -> f64 {
+        match self.state.units {
+            UnitMode::Inches => MM_PER_INCH,
+            UnitMode::Millimeters => 1.0,
+        }
+    }
+
+    fn offset(&self) -> [f64; 3] {
+        self.config.work_offsets[self.state.work_offset as usize]
+    }
+
+    fn set_feed(&mut self, feed: Option<f64>) {
+        if let Some(f) = feed {
+            self.state.feed = f * self.scale();
+        }
+    }
+
+    /// Machine position for the given program words.
+    fn target(&self, x: Option<f64>, y: Option<f64>, z: Option<f64>) -> [f64; 3] {
+        let (scale, offset) = (self.scale(), self.offset());
+        let mut to = self.state.position;
+        for (k, word) in [x, y, z].into_iter().enumerate() {
+            if let Some(v) = word {
+                to[k] = match self.state.distance {
+                    DistanceMode::Absolute => v * scale + offset[k],
+                    DistanceMode::Incremental => to[k] + v * scale,
+                };
+            }
+        }
+        to
+    }
+
+    /// Straight move to `to`.
+    fn push(&mut self, to: [f64; 3], rapid: bool) {
+        let from = self.state.position;
+        let dist =
+            ((to[0] - from[0]).powi(2) + (to[1] - from[1]).powi(2) + (to[2] - from[2]).powi(2))
+                .sqrt();
+        let stats = &mut self.program.stats;
+        grow(&mut stats.bounds, from);
+        grow(&mut stats.bounds, to);
+        if rapid {
+            stats.rapid_distance += dist;
+        } else {
+            grow(&mut stats.cut_bounds, from);
+            grow(&mut stats.cut_bounds, to);
+            stats.cut_distance += dist;
+            if self.state.feed > 0.0 {
+                stats.feed_time += dist / self.state.feed * 60.0;
+            }
+        }
+        self.state.position = to;
+        if dist == 0.0 {
+            return;
+        }
+        self.program.moves.push(PlotMove {
+            x: to[0],
+            y: to[1],
+            z: to[2],
+            rapid,
+            feed: if rapid { 0.0 } else { self.state.feed },
+            line: self.line,
+            tool: self.state.tool,
+            dwell: 0.0,
+        });
+    }
+
+    /// Dwell where the tool is, charged to the last move.
+    fn dwell(&mut self, seconds: f64) {
+        let seconds = seconds.max(0.0);
+        self.program.stats.dwell_time += seconds;
+        if let Some(last) = self.program.moves.last_mut() {
+            last.dwell += seconds;
+        }
+    }
+
+    /// G2/G3 from the current position, split into chords.
+    fn arc(
+        &mut self,
+        clockwise: bool,
+        words: [Option<f64>; 3],
+        i: Option<f64>,
+        j: Option<f64>,
+        r: Option<f64>,
+    ) {
+        let s = self.state.position;
+        let e = self.target(words[0], words[1], words[2]);
+        if self.state.plane != Plane::XY {
+            self.warn(format!(
+                "Arc in the G{} plane drawn as a straight move",
+                self.state.plane as u8
+            ));
+            self.push(e, false);
+            return;
+        }
+        let scale = self.scale();
+        let centre = if let Some(r) = r {
+            match centre_from_radius(s, e, r * scale, clockwise) {
+                Some(c) => c,
+                None => {
+                    self.warn("Arc radius is too small to reach the end point".into());
+                    [(s[0] + e[0]) / 2.0, (s[1] + e[1]) / 2.0]
+                }
+            }
+        } else {
+            let (i, j) = (i.unwrap_or(0.0) * scale, j.unwrap_or(0.0) * scale);
+            match self.state.arc_distance {
+                DistanceMode::Incremental => [s[0] + i, s[1] + j],
+                DistanceMode::Absolute => {
+                    let o = self.offset();
+                    [i + o[0], j + o[1]]
+                }
+            }
+        };
+        let radius = (s[0] - centre[0]).hypot(s[1] - centre[1]);
+        let end_radius = (e[0] - centre[0]).hypot(e[1] - centre[1]);
+        if (radius - end_radius).abs() > ARC_RADIUS_TOLERANCE {
+            self.warn(format!(
+                "Arc end is {:.4} mm off the circle",
+                (radius - end_radius).abs()
+            ));
+        }
+        if radius < 1e-9 {
+            self.push(e, false);
+            return;
+        }
+
+        let a0 = (s[1] - centre[1]).atan2(s[0] - centre[0]);
+        let a1 = (e[1] - centre[1]).atan2(e[0] - centre[0]);
+        let tau = std::f64::consts::TAU;
+        // Sweep in (0, 2π]; the same start and end make a full circle.
+        let mut sweep = if clockwise { a0 - a1 } else { a1 - a0 }.rem_euclid(tau);
+        if sweep < 1e-9 {
+            sweep = tau;
+        }
+        let tol = self.config.arc_tolerance.clamp(1e-6, radius);
+        let max_step = 2.0 * (1.0 - tol / radius).acos();
+        let n = ((sweep / max_step.max(1e-6)).ceil() as usize).clamp(1, MAX_ARC_SEGMENTS);
+        let dir = if clockwise { -1.0 } else { 1.0 };
+        for k in 1..=n {
+            let t = k as f64 / n as f64;
+            let p = if k == n {
+                e
+            } else {
+                let a = a0 + dir * sweep * t;
+                [
+                    centre[0] + radius * a.cos(),
+                    centre[1] + radius * a.sin(),
+                    s[2] + (e[2] - s[2]) * t,
+                ]
+            };
+            self.push(p, false);
+        }
+    }
+
+    /// Set up the modal cycle from its words, keeping earlier depths
+    /// for words that are left out.
+    fn start_cycle(
+        &mut self,
+        code: u8,
+        z: Option<f64>,
+        r: Option<f64>,
+        q: Option<f64>,
+        p: Option<f64>,
+    ) {
+        let scale = self.scale();
+        let base = self.offset()[2];
+        let initial = self.state.position[2];
+        let prev = self.state.cycle.filter(|c| c.code == code);
+        let incremental = self.state.distance == DistanceMode::Incremental;
+        let r_plane = match r {
+            Some(v) if incremental => initial + v * scale,
+            Some(v) => v * scale + base,
+            None => prev.map_or(initial, |c| c.r),
+        };
+        let bottom = match z {
+            Some(v) if incremental => r_plane + v * scale,
+            Some(v) => v * scale + base,
+            None => match prev {
+                Some(c) => c.z,
+                None => {
+                    self.warn(format!("G{code} without a Z depth"));
+                    r_plane
+                }
+            },
+        };
+        self.state.cycle = Some(ActiveCycle {
+            code,
+            z: bottom,
+            r: r_plane,
+            q: q.map_or(prev.map_or(0.0, |c| c.q), |v| v.abs() * scale),
+            p: p.unwrap_or(prev.map_or(0.0, |c| c.p)),
+        });
+    }
+
+    /// One hole of the active cycle at the given XY words.
+    fn drill(&mut self, x: Option<f64>, y: Option<f64>) {
+        let Some(cycle) = self.state.cycle else {
+            return;
+        };
+        let initial = self.state.position[2];
+        let hole = self.target(x, y, None);
+        let clear = match self.state.cycle_return {
+            CycleReturn::Initial => initial.max(cycle.r),
+            CycleReturn::RPlane => cycle.r,
+        };
+        let (hx, hy) = (hole[0], hole[1]);
+        self.push([hx, hy, initial], true);
+        self.push([hx, hy, cycle.r], true);
+        let pecking = matches!(cycle.code, 73 | 83) && cycle.q > 0.0;
+        let mut depth = cycle.r;
+        while depth > cycle.z + 1e-9 {
+            let next = if pecking {
+                (depth - cycle.q).max(cycle.z)
+            } else {
+                cycle.z
+            };
+            if depth < cycle.r {
+                // G83 comes back down from the R plane; G73 is already
+                // just above the last peck.
+                self.push([hx, hy, depth + PECK_CLEARANCE], true);
+            }
+            self.push([hx, hy, next], false);
+            depth = next;
+            if depth > cycle.z + 1e-9 {
+                let up = if cycle.code == 83 {
+                    cycle.r
+                } else {
+                    depth + PECK_CLEARANCE
+                };
+                self.push([hx, hy, up], true);
+            }
+        }
+        if cycle.code == 82 || cycle.p > 0.0 {
+            self.dwell(cycle.p);
+        }
+        self.push([hx, hy, clear], true);
+    }
+}
+
+/// Centre of the arc from `s` to `e` with radius `r` (negative for more
+/// than half a turn), or `None` if the ends are too far apart.
+fn centre_from_radius(s: [f64; 3], e: [f64; 3], r: f64, clockwise: bool) -> Option<[f64; 2]> {
+    let (dx, dy) = (e[0] - s[0], e[1] - s[1]);
+    let chord = dx.hypot(dy);
+    if chord < 1e-12 || chord > 2.0 * r.abs() + 1e-9 {
+        return None;
+    }
+    let h = (r * r - chord * chord / 4.0).max(0.0).sqrt();
+    // Left of the chord for a short counter-clockwise arc.
+    let mut side = if clockwise { -1.0 } else { 1.0 };
+    if r < 0.0 {
+        side = -side;
+    }
+    let (mx, my) = ((s[0] + e[0]) / 2.0, (s[1] + e[1]) / 2.0);
+    Some([mx - dy / chord * h * side, my + dx / chord * h * side])
+}
+
+fn grow(bounds: &mut Option<[[f64; 3]; 2]>, p: [f64; 3]) {
+    let b = bounds.get_or_insert([p, p]);
+    for k in 0..3 {
+        b[0][k] = b[0][k].min(p[k]);
+        b[1][k] = b[1][k].max(p[k]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(program: &str) -> Program {
+        interpret(program, &InterpreterConfig::default())
+    }
+
+    fn ends(p: &Program) -> Vec<[f64; 3]> {
+        p.moves.iter().map(|m| [m.x, m.y, m.z]).collect()
+    }
+
+    #[test]
+    fn motion_and_distance_modes_are_modal() {
+        let p = run("G21 G90\nG0 X10 Y10\nG1 Z-1 F300\nX20\nG91\nY5 Z1\nG0 X-5\n");
+        assert_eq!(
+            ends(&p),
+            [
+                [10.0, 10.0, 0.0],
+                [10.0, 10.0, -1.0],
+                [20.0, 10.0, -1.0],
+                [20.0, 15.0, 0.0],
+                [15.0, 15.0, 0.0],
+            ]
+        );
+        let rapid: Vec<bool> = p.moves.iter().map(|m| m.rapid).collect();
+        assert_eq!(rapid, [true, false, false, false, true]);
+        assert_eq!(p.moves[2].feed, 300.0);
+        assert_eq!(p.moves[2].line, 4);
+        assert!(p.warnings.is_empty(), "{:?}", p.warnings);
+        let s = &p.stats;
+        assert!((s.cut_distance - (11.0 + 26f64.sqrt())).abs() < 1e-9);
+        assert_eq!(s.bounds, Some([[0.0, 0.0, -1.0], [20.0, 15.0, 0.0]]));
+        assert_eq!(s.cut_bounds, Some([[10.0, 10.0, -1.0], [20.0, 15.0, 0.0]]));
+    }
+
+    #[test]
+    fn inches_and_work_offsets_become_machine_mm() {
+        let mut config = InterpreterConfig::default();
+        config.work_offsets[1] = [100.0, 50.0, -20.0];
+        let p = interpret("G20 G55\nG0 X1 Y2 Z0.5\nG1 X2 F10\n", &config);
+        let want = [[125.4, 100.8, -7.3], [150.8, 100.8, -7.3]];
+        for (got, want) in ends(&p).iter().zip(want) {
+            for k in 0..3 {
+                assert!((got[k] - want[k]).abs() < 1e-9, "{got:?} != {want:?}");
+            }
+        }
+        assert!((p.moves[1].feed - 254.0).abs() < 1e-9);
+        assert!((p.stats.feed_time - 6.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn full_circle_from_ij() {
+        let p = run("G0 X10 Y0\nG2 X10 Y0 I-10 J0 F600\n");
+        let arc = &p.moves[1..];
+        assert!(arc.len() > 60);
+        for m in arc {
+            assert!((m.x.hypot(m.y) - 10.0).abs() < 1e-9);
+        }
+        // Clockwise from (10, 0) heads to negative Y first.
+        assert!(arc[0].y < 0.0);
+        let tau = std::f64::consts::TAU;
+        assert!((p.stats.cut_distance - tau * 10.0).abs() < 0.1);
+        // Chords stay within the tolerance of the circle.
+        let chord = ((arc[0].x - 10.0).powi(2) + arc[0].y.powi(2)).sqrt();
+        let sagitta = 10.0 - (100.0 - chord * chord / 4.0).sqrt();
+        assert!(sagitta <= 0.01 + 1e-12);
+    }
+
+    #[test]
+    fn radius_arcs_and_helices() {
+        // Half circle counter-clockwise from (0, 0) to (10, 0) around (5, 0).
+        let p = run("G0 X0 Y0\nG3 X10 Y0 R5 F100\n");
+        let mid = p.moves[p.moves.len() / 2];
+        assert!(
+            (mid.x - 5.0).abs() < 0.5 && (mid.y + 5.0).abs() < 0.01,
+            "{:?}",
+            mid
+        );
+        assert!(p.warnings.is_empty());
+        // A negative radius takes the long way round.
+        let long = run("G0 X0 Y0\nG2 X10 Y0 R-10 F100\n");
+        let short = run("G0 X0 Y0\nG2 X10 Y0 R10 F100\n");
+        assert!(long.stats.cut_distance > 3.0 * short.stats.cut_distance);
+
+        let helix = run("G0 X5 Y0 Z0\nG3 X5 Y0 Z-2 I-5 J0 F100\n");
+        let zs: Vec<f64> = helix.moves[1..].iter().map(|m| m.z).collect();
+        assert!(zs.windows(2).all(|w| w[1] < w[0]));
+        assert_eq!(*zs.last().unwrap(), -2.0);
+    }
+
+    #[test]
+    fn canned_cycles_expand_per_hole() {
+        let p = run("G0 Z5\nG98 G81 X10 Y10 Z-3 R1 F200\nX20\nG99 X30 Y0\nG80\nG0 X0\n");
+        let feeds: Vec<[f64; 3]> = p
+            .moves
+            .iter()
+            .filter(|m| !m.rapid)
+            .map(|m| [m.x, m.y, m.z])
+            .collect();
+        assert_eq!(
+            feeds,
+            [[10.0, 10.0, -3.0], [20.0, 10.0, -3.0], [30.0, 0.0, -3.0]]
+        );
+        // G98 retracts to the start height, G99 to the R plane.
+        let z_after = |line: usize| p.moves.iter().rfind(|m| m.line == line).unwrap().z;
+        assert_eq!(z_after(2), 5.0);
+        assert_eq!(z_after(4), 1.0);
+        // After G80 coordinates are plain rapids again.
+        assert_eq!(ends(&p).last().unwrap(), &[0.0, 0.0, 1.0]);
+
+        let peck = run("G0 Z5\nG83 X0 Y0 Z-6 R1 Q2 F100\n");
+        let depths: Vec<f64> = peck
+            .moves
+            .iter()
+            .filter(|m| !m.rapid)
+            .map(|m| m.z)
+            .collect();
+        assert_eq!(depths, [-1.0, -3.0, -5.0, -6.0]);
+        let dwell = run("G82 X0 Y0 Z-1 R1 P0.5\nX1\n");
+        assert_eq!(dwell.stats.dwell_time, 1.0);
+        let bottom = dwell.moves.iter().find(|m| !m.rapid).unwrap();
+        assert_eq!((bottom.z, bottom.dwell), (-1.0, 0.5));
+    }
+
+    #[test]
+    fn tool_changes_and_program_end() {
+        let p = run("T1 M6\nG0 X1\nT2\nM6\nG1 X2 F100\nM30\nG0 X50\n");
+        assert_eq!(
+            p.stats.tool_changes,
+            [
+                ToolChange { line: 1, tool: 1 },
+                ToolChange { line: 4, tool: 2 }
+            ]
+        );
+        assert_eq!(p.moves[0].tool, 1);
+        assert_eq!(p.moves[1].tool, 2);
+        // Nothing runs after M30.
+        assert_eq!(p.moves.len(), 2);
+        assert_eq!(p.stats.lines, 6);
+    }
+
+    #[test]
+    fn warns_about_what_it_cannot_show() {
+        let p = run("G41 D1\nG1 X1.2.3\nG18\nG2 X10 Z5 I5\nG17\nG0 X0 Y0\nG2 X10 Y0 I4 J0\n");
+        let lines: Vec<usize> = p.warnings.iter().map(|w| w.line_number).collect();
+        assert_eq!(lines, [1, 2, 4, 7], "{:?}", p.warnings);
+        assert!(p.warnings[0].message.contains("G41"));
+        assert!(p.warnings[3].message.contains("off the circle"));
+    }
+}
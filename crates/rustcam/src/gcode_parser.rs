@@ -136,7 +136,9 @@ impl AxisMask {
     }
 }
 
-#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+#[derive(
+    Copy, Clone, Debug, Default, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize,
+)]
 #[repr(u8)]
 pub enum WorkOffset {
     #[default]
@@ -181,6 +183,27 @@ impl std::fmt::Display for WorkOffset {
     }
 }
 
+/// Plane for arcs (G17/G18/G19).
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+#[repr(u8)]
+pub enum Plane {
+    #[default]
+    XY = 17,
+    XZ = 18,
+    YZ = 19,
+}
+
+/// Where a canned cycle retracts to after each hole.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+#[repr(u8)]
+pub enum CycleReturn {
+    /// G98: back to the Z the cycle started from.
+    #[default]
+    Initial = 98,
+    /// G99: back to the R plane.
+    RPlane = 99,
+}
+
 /// A parsed G-code command.
 #[derive(Clone, Debug, PartialEq)]
 pub enum GCodeCommand {
@@ -199,10 +222,51 @@ pub enum GCodeCommand {
         clockwise: bool,
         x: Option<f64>,
         y: Option<f64>,
+        /// End Z of a helix.
+        z: Option<f64>,
         i: Option<f64>,
         j: Option<f64>,
+        /// Radius format instead of I/J; negative for more than half a turn.
+        r: Option<f64>,
         feed: Option<f64>,
     },
+    /// A drilling cycle (G73, G81, G82, G83) at one hole. Later blocks with
+    /// only coordinates drill more holes with it until G80.
+    CannedCycle {
+        code: u8,
+        x: Option<f64>,
+        y: Option<f64>,
+        z: Option<f64>,
+        r: Option<f64>,
+        /// Peck depth.
+        q: Option<f64>,
+        /// Dwell in seconds.
+        p: Option<f64>,
+        feed: Option<f64>,
+    },
+    /// G80.
+    CancelCycle,
+    /// Coordinates without a motion code: repeat the modal motion (a move,
+    /// an arc or a canned cycle). Only `parse_block` produces this.
+    ModalMotion {
+        x: Option<f64>,
+        y: Option<f64>,
+        z: Option<f64>,
+        i: Option<f64>,
+        j: Option<f64>,
+        r: Option<f64>,
+        feed: Option<f64>,
+    },
+    SetFeedRate(f64),
+    SetPlane(Plane),
+    /// G90.1/G91.1: whether arc centres (I/J) are absolute or relative to
+    /// the start point.
+    SetArcDistanceMode(DistanceMode),
+    SetCycleReturn(CycleReturn),
+    /// T word: the tool the next M6 loads.
+    SelectTool(u32),
+    /// M6.
+    ToolChange,
     SetUnits(UnitMode),
     SetDistanceMode(DistanceMode),
     SetWorkOffset(WorkOffset),
@@ -229,7 +293,11 @@ impl GCodeCommand {
     pub const fn is_motion(&self) -> bool {
         matches!(
             self,
-            Self::RapidMove { .. } | Self::LinearMove { .. } | Self::ArcMove { .. }
+            Self::RapidMove { .. }
+                | Self::LinearMove { .. }
+                | Self::ArcMove { .. }
+                | Self::CannedCycle { .. }
+                | Self::ModalMotion { .. }
         )
     }
 
@@ -242,6 +310,10 @@ impl GCodeCommand {
                 | Self::SetWorkOffset(_)
                 | Self::SetSpindle(_)
                 | Self::SetCoolant(_)
+                | Self::SetFeedRate(_)
+                | Self::SetPlane(_)
+                | Self::SetArcDistanceMode(_)
+                | Self::SetCycleReturn(_)
         )
     }
 
@@ -297,28 +369,61 @@ impl std::fmt::Display for GCodeCommand {
                 clockwise,
                 x,
                 y,
+                z,
                 i,
                 j,
+                r,
                 feed,
             } => {
                 write!(f, "{}", if *clockwise { "G02" } else { "G03" })?;
-                if let Some(v) = x {
-                    write!(f, " X{v:.4}")?;
-                }
-                if let Some(v) = y {
-                    write!(f, " Y{v:.4}")?;
-                }
-                if let Some(v) = i {
-                    write!(f, " I{v:.4}")?;
+                write_words(f, &[('X', *x), ('Y', *y), ('Z', *z)])?;
+                write_words(f, &[('I', *i), ('J', *j), ('R', *r)])?;
+                if let Some(v) = feed {
+                    write!(f, " F{v:.0}")?;
                 }
-                if let Some(v) = j {
-                    write!(f, " J{v:.4}")?;
+                Ok(())
+            }
+            Self::CannedCycle {
+                code,
+                x,
+                y,
+                z,
+                r,
+                q,
+                p,
+                feed,
+            } => {
+                write!(f, "G{code}")?;
+                write_words(f, &[('X', *x), ('Y', *y), ('Z', *z), ('R', *r)])?;
+                write_words(f, &[('Q', *q), ('P', *p)])?;
+                if let Some(v) = feed {
+                    write!(f, " F{v:.0}")?;
                 }
+                Ok(())
+            }
+            Self::CancelCycle => write!(f, "G80"),
+            Self::ModalMotion {
+                x,
+                y,
+                z,
+                i,
+                j,
+                r,
+                feed,
+            } => {
+                write_words(f, &[('X', *x), ('Y', *y), ('Z', *z)])?;
+                write_words(f, &[('I', *i), ('J', *j), ('R', *r)])?;
                 if let Some(v) = feed {
                     write!(f, " F{v:.0}")?;
                 }
                 Ok(())
             }
+            Self::SetFeedRate(v) => write!(f, "F{v:.0}"),
+            Self::SetPlane(plane) => write!(f, "G{}", *plane as u8),
+            Self::SetArcDistanceMode(mode) => write!(f, "{mode}.1"),
+            Self::SetCycleReturn(ret) => write!(f, "G{}", *ret as u8),
+            Self::SelectTool(t) => write!(f, "T{t}"),
+            Self::ToolChange => write!(f, "M06"),
             Self::SetUnits(mode) => write!(f, "{mode}"),
             Self::SetDistanceMode(mode) => write!(f, "{mode}"),
             Self::SetWorkOffset(offset) => write!(f, "{offset}"),
@@ -355,6 +460,16 @@ impl std::fmt::Display for GCodeCommand {
     }
 }
 
+/// Write the words that are present as ` X1.0000`, separated by spaces.
+fn write_words(f: &mut std::fmt::Formatter<'_>, words: &[(char, Option<f64>)]) -> std::fmt::Result {
+    for (letter, value) in words {
+        if let Some(v) = value {
+            write!(f, " {letter}{v:.4}")?;
+        }
+    }
+    Ok(())
+}
+
 // ── Parser ───────────────────────────────────────────────────────────
 
 #[derive(Clone, Debug, PartialEq, Eq)]
@@ -481,26 +596,23 @@ fn parse_g_code(code: u8, words: &[(char, f64)]) -> Result<GCodeCommand, ParseEr
             z: get_word('Z'),
             feed: get_word('F'),
         }),
-        2 => Ok(GCodeCommand::ArcMove {
-            clockwise: true,
-            x: get_word('X'),
-            y: get_word('Y'),
-            i: get_word('I'),
-            j: get_word('J'),
-            feed: get_word('F'),
-        }),
-        3 => Ok(GCodeCommand::ArcMove {
-            clockwise: false,
+        2 | 3 => Ok(GCodeCommand::ArcMove {
+            clockwise: code == 2,
             x: get_word('X'),
             y: get_word('Y'),
+            z: get_word('Z'),
             i: get_word('I'),
             j: get_word('J'),
+            r: get_word('R'),
             feed: get_word('F'),
         }),
         4 => {
             let seconds = get_word('P').unwrap_or(0.0);
             Ok(GCodeCommand::Dwell { seconds })
         }
+        17 => Ok(GCodeCommand::SetPlane(Plane::XY)),
+        18 => Ok(GCodeCommand::SetPlane(Plane::XZ)),
+        19 => Ok(GCodeCommand::SetPlane(Plane::YZ)),
         20 => Ok(GCodeCommand::SetUnits(UnitMode::Inches)),
         21 => Ok(GCodeCommand::SetUnits(UnitMode::Millimeters)),
         28 => {
@@ -522,8 +634,21 @@ fn parse_g_code(code: u8, words: &[(char, f64)]) -> Result<GCodeCommand, ParseEr
         57 => Ok(GCodeCommand::SetWorkOffset(WorkOffset::G57)),
         58 => Ok(GCodeCommand::SetWorkOffset(WorkOffset::G58)),
         59 => Ok(GCodeCommand::SetWorkOffset(WorkOffset::G59)),
+        73 | 81 | 82 | 83 => Ok(GCodeCommand::CannedCycle {
+            code,
+            x: get_word('X'),
+            y: get_word('Y'),
+            z: get_word('Z'),
+            r: get_word('R'),
+            q: get_word('Q'),
+            p: get_word('P'),
+            feed: get_word('F'),
+        }),
+        80 => Ok(GCodeCommand::CancelCycle),
         90 => Ok(GCodeCommand::SetDistanceMode(DistanceMode::Absolute)),
         91 => Ok(GCodeCommand::SetDistanceMode(DistanceMode::Incremental)),
+        98 => Ok(GCodeCommand::SetCycleReturn(CycleReturn::Initial)),
+        99 => Ok(GCodeCommand::SetCycleReturn(CycleReturn::RPlane)),
         _ => Err(ParseError::UnknownGCode(code)),
     }
 }
@@ -548,6 +673,7 @@ fn parse_m_code(code: u8, words: &[(char, f64)]) -> Result<GCodeCommand, ParseEr
             )))
         }
         5 => Ok(GCodeCommand::SetSpindle(SpindleControl::Off)),
+        6 => Ok(GCodeCommand::ToolChange),
         7 => Ok(GCodeCommand::SetCoolant(CoolantControl::Mist)),
         8 => Ok(GCodeCommand::SetCoolant(CoolantControl::Flood)),
         9 => Ok(GCodeCommand::SetCoolant(CoolantControl::Off)),
@@ -555,6 +681,95 @@ fn parse_m_code(code: u8, words: &[(char, f64)]) -> Result<GCodeCommand, ParseEr
     }
 }
 
+/// G-codes that change nothing a back-plot can show: cutter radius
+/// compensation off, tool length offsets, feed and path modes.
+const PASSIVE_G_CODES: &[f64] = &[40.0, 43.0, 49.0, 61.0, 64.0, 94.0];
+
+/// Parses a whole block: every word on the line, in the order a
+/// controller executes them (feed, tool, spindle, coolant, modes in line
+/// order, dwell, home, motion, stop). Coordinates without a motion code become
+/// `ModalMotion`. Codes this parser does not model come back as `Raw` so
+/// the caller can report them; comments and blank lines give no commands.
+pub fn parse_block(line: &str) -> Result<Vec<GCodeCommand>, ParseError> {
+    let text = strip_comments(line).trim().to_uppercase();
+    if text.is_empty() || text.starts_with('(') || text.starts_with(';') {
+        return Ok(Vec::new());
+    }
+    let words = parse_words(&text)?;
+    let get_word =
+        |letter: char| -> Option<f64> { words.iter().find(|(c, _)| *c == letter).map(|(_, v)| *v) };
+    let has_axes = words.iter().any(|(c, _)| matches!(c, 'X' | 'Y' | 'Z'));
+
+    // (execution order, command)
+    let mut out: Vec<(u8, GCodeCommand)> = Vec::new();
+    let mut motion = None;
+    let mut axes_used = false;
+    for &(letter, value) in &words {
+        match letter {
+            'G' => {
+                // Tenths pick out G38.2, G90.1 and the like.
+                let tenths = (value * 10.0).round() as u32;
+                let (code, fraction) = ((tenths / 10) as u8, tenths % 10);
+                match (code, fraction) {
+                    (0..=3 | 73 | 81..=83, 0) => motion = Some(code),
+                    (80, 0) => out.push((8, GCodeCommand::CancelCycle)),
+                    (90 | 91, 1) => out.push((
+                        5,
+                        GCodeCommand::SetArcDistanceMode(if code == 90 {
+                            DistanceMode::Absolute
+                        } else {
+                            DistanceMode::Incremental
+                        }),
+                    )),
+                    (4, 0) => out.push((6, parse_g_code(code, &words)?)),
+                    (28 | 38, _) => {
+                        axes_used = true;
+                        out.push((7, parse_g_code(code, &words)?));
+                    }
+                    (17..=21 | 54..=59 | 90 | 91 | 98 | 99, 0) => {
+                        out.push((5, parse_g_code(code, &words)?))
+                    }
+                    _ if PASSIVE_G_CODES.contains(&(tenths as f64 / 10.0)) => {}
+                    _ => out.push((9, GCodeCommand::Raw(format!("G{value}")))),
+                }
+            }
+            'M' => match value as u8 {
+                0 | 2 | 30 => out.push((10, parse_m_code(value as u8, &words)?)),
+                1 => {}
+                3..=5 => out.push((3, parse_m_code(value as u8, &words)?)),
+                6 => out.push((2, GCodeCommand::ToolChange)),
+                7..=9 => out.push((4, parse_m_code(value as u8, &words)?)),
+                _ => out.push((9, GCodeCommand::Raw(format!("M{value}")))),
+            },
+            'T' => out.push((1, GCodeCommand::SelectTool(value as u32))),
+            _ => {}
+        }
+    }
+    let feed = get_word('F');
+    match motion {
+        Some(code) => out.push((8, parse_g_code(code, &words)?)),
+        None if has_axes && !axes_used => out.push((
+            8,
+            GCodeCommand::ModalMotion {
+                x: get_word('X'),
+                y: get_word('Y'),
+                z: get_word('Z'),
+                i: get_word('I'),
+                j: get_word('J'),
+                r: get_word('R'),
+                feed,
+            },
+        )),
+        None => {
+            if let Some(f) = feed {
+                out.push((0, GCodeCommand::SetFeedRate(f)));
+            }
+        }
+    }
+    out.sort_by_key(|(order, _)| *order);
+    Ok(out.into_iter().map(|(_, cmd)| cmd).collect())
+}
+
 // ── Validator ────────────────────────────────────────────────────────
 
 #[derive(Clone, Debug, PartialEq)]
@@ -906,6 +1121,84 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[test]
+    fn parse_block_orders_words_like_a_controller() {
+        let cmds = parse_block("N10 G0 G90 G54 X1 Y2 M3 S1200 T4 M6 (rough)").unwrap();
+        assert_eq!(
+            cmds,
+            [
+                GCodeCommand::SelectTool(4),
+                GCodeCommand::ToolChange,
+                GCodeCommand::SetSpindle(SpindleControl::Clockwise(SpindleSpeed::new(1200))),
+                GCodeCommand::SetDistanceMode(DistanceMode::Absolute),
+                GCodeCommand::SetWorkOffset(WorkOffset::G54),
+                GCodeCommand::RapidMove {
+                    x: Some(1.0),
+                    y: Some(2.0),
+                    z: None
+                },
+            ]
+        );
+        assert!(parse_block("  (just a comment)").unwrap().is_empty());
+        assert!(parse_block("").unwrap().is_empty());
+    }
+
+    #[test]
+    fn parse_block_modal_words() {
+        assert_eq!(
+            parse_block("X5 Y6 F300").unwrap(),
+            [GCodeCommand::ModalMotion {
+                x: Some(5.0),
+                y: Some(6.0),
+                z: None,
+                i: None,
+                j: None,
+                r: None,
+                feed: Some(300.0),
+            }]
+        );
+        assert_eq!(
+            parse_block("F250").unwrap(),
+            [GCodeCommand::SetFeedRate(250.0)]
+        );
+        assert_eq!(
+            parse_block("G90.1 G17 G40 G49").unwrap(),
+            [
+                GCodeCommand::SetArcDistanceMode(DistanceMode::Absolute),
+                GCodeCommand::SetPlane(Plane::XY),
+            ]
+        );
+        // G28 uses its axis words itself.
+        assert_eq!(parse_block("G28 Z0").unwrap().len(), 1);
+        assert_eq!(
+            parse_block("G64 P0.01 M48").unwrap(),
+            [GCodeCommand::Raw("M48".into())]
+        );
+    }
+
+    #[test]
+    fn parse_block_cycles_and_helices() {
+        let cmds = parse_block("G98 G83 X10 Y10 Z-6 R1 Q2 F300").unwrap();
+        assert_eq!(cmds[0], GCodeCommand::SetCycleReturn(CycleReturn::Initial));
+        assert!(matches!(
+            cmds[1],
+            GCodeCommand::CannedCycle {
+                code: 83,
+                q: Some(2.0),
+                r: Some(1.0),
+                ..
+            }
+        ));
+        assert_eq!(
+            cmds[1].to_string(),
+            "G83 X10.0000 Y10.0000 Z-6.0000 R1.0000 Q2.0000 F300"
+        );
+        let helix = parse_line("G3 X1 Y0 Z-1 I-1 J0").unwrap();
+        assert!(matches!(helix, GCodeCommand::ArcMove { z: Some(z), .. } if z == -1.0));
+        assert_eq!(parse_line("G80").unwrap(), GCodeCommand::CancelCycle);
+        assert_eq!(parse_line("M6").unwrap(), GCodeCommand::ToolChange);
+    }
+
     #[test]
     fn spindle_control_is_running() {
         assert!(SpindleControl::Clockwise(SpindleSpeed::new(1000)).is_running());
@@ -3,6 +3,17 @@
 /// Swiss-cheese layer: **Geometry representation**
 /// Extension point: add new geometry primitives by implementing Into<Polyline> or Into<Mesh>.
 use serde::{Deserialize, Serialize};
+use std::sync::OnceLock;
+
+use self::mesh_index::MeshIndex;
+
+pub mod affine;
+pub mod boolean;
+pub mod flatten;
+pub mod height_grid;
+pub mod mesh_index;
+pub mod nesting;
+pub mod offset;
 
 // ── 3-D ──────────────────────────────────────────────────────────────
 
@@ -87,12 +98,51 @@ impl BoundingBox {
 pub struct Mesh {
     pub triangles: Vec<Triangle>,
     pub bounds: Option<BoundingBox>,
+    /// Built on the first height or slice query; edits to `triangles`
+    /// after that are not seen by it.
+    #[serde(skip)]
+    index: OnceLock<MeshIndex>,
 }
 
 impl Mesh {
     pub fn new(triangles: Vec<Triangle>) -> Self {
         let bounds = BoundingBox::from_triangles(&triangles);
-        Self { triangles, bounds }
+        Self {
+            triangles,
+            bounds,
+            index: OnceLock::new(),
+        }
+    }
+
+    /// The spatial index over `triangles`.
+    pub fn index(&self) -> &MeshIndex {
+        self.index.get_or_init(|| MeshIndex::build(&self.triangles))
+    }
+
+    /// Triangles whose XY projection might contain `(x, y)`, in order.
+    pub fn triangles_at_xy(&self, x: f64, y: f64) -> impl Iterator<Item = &Triangle> {
+        let index = self.index().at_xy(x, y);
+        index.iter().map(|&i| &self.triangles[i as usize])
+    }
+
+    /// Triangles whose XY projection might come within `radius` of
+    /// `(x, y)`, in order.
+    pub fn triangles_near_xy(
+        &self,
+        x: f64,
+        y: f64,
+        radius: f64,
+    ) -> impl Iterator<Item = &Triangle> {
+        let index = self
+            .index()
+            .in_xy_box(x - radius, y - radius, x + radius, y + radius);
+        index.into_iter().map(|i| &self.triangles[i as usize])
+    }
+
+    /// Triangles whose Z range might contain `z`, in order.
+    pub fn triangles_at_z(&self, z: f64) -> impl Iterator<Item = &Triangle> {
+        let index = self.index().at_z(z);
+        index.iter().map(|&i| &self.triangles[i as usize])
     }
 }
 
@@ -178,16 +228,108 @@ pub struct ToolpathMove {
     #[serde(skip_serializing_if = "Option::is_none")]
     #[serde(default)]
     pub power: Option<f64>,
+    /// True while the move passes over a holding tab (lifted, or with the
+    /// laser off).
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    #[serde(default)]
+    pub tab: bool,
+    /// Controller cutter compensation in force for this move. The emitter
+    /// switches it on and off (G41/G42, G40) where it changes.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub comp: Option<CutterComp>,
+}
+
+/// Side of the programmed path the controller offsets the tool to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CutterComp {
+    /// Left of the direction of travel (G41).
+    Left,
+    /// Right of it (G42).
+    Right,
+}
+
+impl CutterComp {
+    pub fn code(self) -> &'static str {
+        match self {
+            Self::Left => "G41",
+            Self::Right => "G42",
+        }
+    }
+}
+
+/// A holding tab left on a profile so the part stays attached to the
+/// sheet.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Tab {
+    /// Centre of the tab on the tool path.
+    pub x: f64,
+    pub y: f64,
+    /// Length of the tab along the profile.
+    pub width: f64,
+    /// Material left standing above the cut floor (0 for laser).
+    pub height: f64,
+}
+
+/// A drilled hole as a canned cycle. The toolpath's moves spell out the
+/// same cycle for controllers without G81-G83.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct DrillCycle {
+    pub x: f64,
+    pub y: f64,
+    /// Bottom of the hole.
+    pub z: f64,
+    /// Plane the tool rapids down to before feeding (the R word).
+    pub retract_z: f64,
+    /// Depth per peck; 0 drills in one feed.
+    pub peck: f64,
+    /// Dwell at the bottom in seconds.
+    pub dwell: f64,
+}
+
+impl DrillCycle {
+    /// The canned cycle word: G83 when pecking, G82 when dwelling,
+    /// otherwise G81.
+    pub fn code(&self) -> &'static str {
+        if self.peck > 0.0 {
+            "G83"
+        } else if self.dwell > 0.0 {
+            "G82"
+        } else {
+            "G81"
+        }
+    }
+
+    /// Whether `other` runs the same cycle (only the position differs).
+    pub fn same_cycle(&self, other: &DrillCycle) -> bool {
+        self.z == other.z
+            && self.retract_z == other.retract_z
+            && self.peck == other.peck
+            && self.dwell == other.dwell
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Toolpath {
     pub moves: Vec<ToolpathMove>,
+    /// Tabs this toolpath leaves standing.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    #[serde(default)]
+    pub tabs: Vec<Tab>,
+    /// Set when the toolpath drills one hole.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub drill: Option<DrillCycle>,
 }
 
 impl Toolpath {
     pub fn new() -> Self {
-        Self { moves: Vec::new() }
+        Self {
+            moves: Vec::new(),
+            tabs: Vec::new(),
+            drill: None,
+        }
     }
     pub fn rapid(&mut self, x: f64, y: f64, z: f64) {
         self.moves.push(ToolpathMove {
@@ -196,6 +338,8 @@ impl Toolpath {
             z,
             rapid: true,
             power: None,
+            tab: false,
+            comp: None,
         });
     }
     pub fn cut(&mut self, x: f64, y: f64, z: f64) {
@@ -205,6 +349,8 @@ impl Toolpath {
             z,
             rapid: false,
             power: None,
+            tab: false,
+            comp: None,
         });
     }
     /// Add a cutting move with laser power metadata.
@@ -215,6 +361,8 @@ impl Toolpath {
             z,
             rapid: false,
             power: Some(power),
+            tab: false,
+            comp: None,
         });
     }
 }
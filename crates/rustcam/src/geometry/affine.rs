@@ -0,0 +1,99 @@
+//! 2-D affine transforms for placing imported geometry.
+//!
+//! Swiss-cheese layer: **Geometry representation**
+//! Extension point: add named constructors for other transform kinds.
+//!
+//! Importers compose nested placements (SVG `transform` on groups, DXF
+//! block references) into one `Affine2` per element and map its points
+//! through it. The matrix layout follows SVG's `matrix(a b c d e f)`:
+//! `x' = a·x + c·y + e`, `y' = b·x + d·y + f`.
+
+use super::Vec2;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Affine2 {
+    pub a: f64,
+    pub b: f64,
+    pub c: f64,
+    pub d: f64,
+    pub e: f64,
+    pub f: f64,
+}
+
+impl Affine2 {
+    pub const IDENTITY: Self = Self::new(1.0, 0.0, 0.0, 1.0, 0.0, 0.0);
+
+    pub const fn new(a: f64, b: f64, c: f64, d: f64, e: f64, f: f64) -> Self {
+        Self { a, b, c, d, e, f }
+    }
+
+    pub fn translate(x: f64, y: f64) -> Self {
+        Self::new(1.0, 0.0, 0.0, 1.0, x, y)
+    }
+
+    pub fn scale(sx: f64, sy: f64) -> Self {
+        Self::new(sx, 0.0, 0.0, sy, 0.0, 0.0)
+    }
+
+    /// Counter-clockwise by `angle` radians (clockwise on screen when Y
+    /// points down, as in SVG).
+    pub fn rotate(angle: f64) -> Self {
+        let (sin, cos) = angle.sin_cos();
+        Self::new(cos, sin, -sin, cos, 0.0, 0.0)
+    }
+
+    pub fn skew_x(angle: f64) -> Self {
+        Self::new(1.0, 0.0, angle.tan(), 1.0, 0.0, 0.0)
+    }
+
+    pub fn skew_y(angle: f64) -> Self {
+        Self::new(1.0, angle.tan(), 0.0, 1.0, 0.0, 0.0)
+    }
+
+    pub fn apply(&self, p: Vec2) -> Vec2 {
+        Vec2::new(
+            self.a * p.x + self.c * p.y + self.e,
+            self.b * p.x + self.d * p.y + self.f,
+        )
+    }
+
+    /// `inner` first, then `self` — the order of an SVG transform list.
+    pub fn after(&self, inner: &Self) -> Self {
+        Self::new(
+            self.a * inner.a + self.c * inner.b,
+            self.b * inner.a + self.d * inner.b,
+            self.a * inner.c + self.c * inner.d,
+            self.b * inner.c + self.d * inner.d,
+            self.a * inner.e + self.c * inner.f + self.e,
+            self.b * inner.e + self.d * inner.f + self.f,
+        )
+    }
+
+    /// Largest factor any length is stretched by. Curves flattened before
+    /// the transform divide their tolerance by this.
+    pub fn max_scale(&self) -> f64 {
+        self.a.hypot(self.b).max(self.c.hypot(self.d))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_after_applies_inner_first() {
+        // Scale, then turn a quarter, then shift.
+        let xf = Affine2::translate(10.0, 0.0)
+            .after(&Affine2::rotate(std::f64::consts::FRAC_PI_2))
+            .after(&Affine2::scale(2.0, 3.0));
+        let p = xf.apply(Vec2::new(1.0, 1.0));
+        assert!(
+            (p.x - 7.0).abs() < 1e-12 && (p.y - 2.0).abs() < 1e-12,
+            "{:?}",
+            p
+        );
+        assert!((xf.max_scale() - 3.0).abs() < 1e-12);
+        let skew = Affine2::skew_x(std::f64::consts::FRAC_PI_4).apply(Vec2::new(0.0, 2.0));
+        assert!((skew.x - 2.0).abs() < 1e-12);
+    }
+}
@@ -0,0 +1,420 @@
+//! Boolean operations on polygon regions.
+//!
+//! Swiss-cheese layer: **Geometry representation**
+//! Extension point: new set operations are a predicate over per-operand
+//! winding numbers passed to `boundary`.
+//!
+//! Regions are sets of closed rings with the area on their left (outer
+//! boundaries counter-clockwise, holes clockwise), the same convention as
+//! `offset::offset_region` and `nesting::Region`. All operations split
+//! edges at their intersections and keep the pieces that separate inside
+//! from outside, so overlapping and self-intersecting input is fine.
+
+use std::collections::HashMap;
+
+use super::offset::signed_area;
+use super::{nesting, Polyline, Vec2};
+
+/// Union of all rings (non-zero rule).
+pub fn union(rings: &[Polyline]) -> Vec<Polyline> {
+    let a = to_rings(rings);
+    to_polylines(boundary(&[&a], |w| w[0] > 0))
+}
+
+/// Area inside both `a` and `b`.
+pub fn intersection(a: &[Polyline], b: &[Polyline]) -> Vec<Polyline> {
+    let (a, b) = (to_rings(a), to_rings(b));
+    to_polylines(boundary(&[&a, &b], |w| w[0] > 0 && w[1] > 0))
+}
+
+/// Area inside `a` but not inside `b`.
+pub fn difference(a: &[Polyline], b: &[Polyline]) -> Vec<Polyline> {
+    let (a, b) = (to_rings(a), to_rings(b));
+    to_polylines(boundary(&[&a, &b], |w| w[0] > 0 && w[1] <= 0))
+}
+
+/// Total signed area of a set of rings (holes subtract).
+pub fn area(rings: &[Polyline]) -> f64 {
+    rings.iter().map(|r| signed_area(&r.points)).sum()
+}
+
+fn to_rings(polys: &[Polyline]) -> Vec<Vec<Vec2>> {
+    polys
+        .iter()
+        .filter(|p| p.points.len() >= 3)
+        .map(|p| p.points.clone())
+        .collect()
+}
+
+fn to_polylines(rings: Vec<Vec<Vec2>>) -> Vec<Polyline> {
+    rings.into_iter().map(|r| Polyline::new(r, true)).collect()
+}
+
+// ── Boundary extraction ──────────────────────────────────────────────
+
+/// Boundary of the positive-winding area of `rings` (their union under
+/// the non-zero rule, with self-overlaps resolved).
+pub(super) fn positive(rings: &[Vec<Vec2>]) -> Vec<Vec<Vec2>> {
+    boundary(&[rings], |w| w[0] > 0)
+}
+
+/// Split all edges of all operands at their mutual intersections, keep
+/// the pieces that separate an area where `inside` holds from one where it
+/// does not (oriented with the inside on the left) and chain them back
+/// into rings. `inside` receives the winding number of each operand.
+fn boundary(operands: &[&[Vec<Vec2>]], inside: impl Fn(&[i32]) -> bool) -> Vec<Vec<Vec2>> {
+    let rings: Vec<&Vec<Vec2>> = operands.iter().flat_map(|op| op.iter()).collect();
+    let classify = |p: Vec2| {
+        let w: Vec<i32> = operands.iter().map(|op| winding_number(op, p)).collect();
+        inside(&w)
+    };
+    let mut segs: Vec<(Vec2, Vec2)> = Vec::new();
+    for ring in &rings {
+        let n = ring.len();
+        for i in 0..n {
+            let a = ring[i];
+            let b = ring[(i + 1) % n];
+            if Vec2::dist(a, b) > 0.0 {
+                segs.push((a, b));
+            }
+        }
+    }
+    if segs.is_empty() {
+        return Vec::new();
+    }
+
+    let all: Vec<Vec2> = rings.iter().flat_map(|r| r.iter()).copied().collect();
+    let (min, max) = bounds(&all);
+    let extent = Vec2::dist(min, max).max(1e-3);
+    let weld = extent * 1e-9;
+    let side_eps = extent * 1e-7;
+
+    // Split points per segment as (parameter, point).
+    let mut splits: Vec<Vec<(f64, Vec2)>> = vec![Vec::new(); segs.len()];
+    let mut order: Vec<usize> = (0..segs.len()).collect();
+    order.sort_by(|&i, &j| {
+        let xi = segs[i].0.x.min(segs[i].1.x);
+        let xj = segs[j].0.x.min(segs[j].1.x);
+        xi.partial_cmp(&xj).unwrap()
+    });
+    for (oi, &i) in order.iter().enumerate() {
+        let (a, b) = segs[i];
+        let i_max_x = a.x.max(b.x);
+        for &j in &order[oi + 1..] {
+            let (c, d) = segs[j];
+            if c.x.min(d.x) > i_max_x + weld {
+                break;
+            }
+            if a.y.max(b.y) + weld < c.y.min(d.y) || c.y.max(d.y) + weld < a.y.min(b.y) {
+                continue;
+            }
+            intersect_for_split(i, j, &segs, &mut splits, weld);
+        }
+    }
+
+    // Weld split pieces into a vertex graph.
+    let mut welder = Welder::new(weld);
+    let mut edges: Vec<(usize, usize)> = Vec::new();
+    let mut seen: HashMap<(usize, usize), ()> = HashMap::new();
+    for (k, &(a, b)) in segs.iter().enumerate() {
+        let len = Vec2::dist(a, b);
+        let mut pts: Vec<(f64, Vec2)> = splits[k].clone();
+        pts.push((0.0, a));
+        pts.push((1.0, b));
+        pts.sort_by(|x, y| x.0.partial_cmp(&y.0).unwrap());
+        for w in pts.windows(2) {
+            let (p, q) = (w[0].1, w[1].1);
+            if (w[1].0 - w[0].0) * len <= weld {
+                continue;
+            }
+            let mid = Vec2::new((p.x + q.x) / 2.0, (p.y + q.y) / 2.0);
+            let dir = unit(sub(q, p));
+            let left = add(mid, scale(Vec2::new(-dir.y, dir.x), side_eps));
+            let right = add(mid, scale(Vec2::new(dir.y, -dir.x), side_eps));
+            let inside_left = classify(left);
+            let inside_right = classify(right);
+            if inside_left == inside_right {
+                continue;
+            }
+            let (s, e) = if inside_left { (p, q) } else { (q, p) };
+            let si = welder.id(s);
+            let ei = welder.id(e);
+            if si != ei && seen.insert((si, ei), ()).is_none() {
+                edges.push((si, ei));
+            }
+        }
+    }
+
+    chain_edges(&welder.points, &edges, extent)
+}
+
+/// Record the intersection of segments `i` and `j` as split points on
+/// both. Points that coincide with an existing endpoint reuse that
+/// endpoint's exact coordinates.
+fn intersect_for_split(
+    i: usize,
+    j: usize,
+    segs: &[(Vec2, Vec2)],
+    splits: &mut [Vec<(f64, Vec2)>],
+    weld: f64,
+) {
+    let (a, b) = segs[i];
+    let (c, d) = segs[j];
+    let r = sub(b, a);
+    let s = sub(d, c);
+    let denom = cross(r, s);
+    let len_r = dot(r, r).sqrt();
+    let len_s = dot(s, s).sqrt();
+    let tol_t = weld / len_r;
+    let tol_u = weld / len_s;
+
+    if denom.abs() <= 1e-12 * len_r * len_s {
+        // Parallel: only collinear overlaps need splitting, at the
+        // endpoints of one segment that fall inside the other.
+        if cross(sub(c, a), r).abs() > weld * len_r {
+            return;
+        }
+        for &p in &[c, d] {
+            let t = dot(sub(p, a), r) / (len_r * len_r);
+            if t > tol_t && t < 1.0 - tol_t {
+                splits[i].push((t, p));
+            }
+        }
+        for &p in &[a, b] {
+            let u = dot(sub(p, c), s) / (len_s * len_s);
+            if u > tol_u && u < 1.0 - tol_u {
+                splits[j].push((u, p));
+            }
+        }
+        return;
+    }
+
+    let t = cross(sub(c, a), s) / denom;
+    let u = cross(sub(c, a), r) / denom;
+    if t < -tol_t || t > 1.0 + tol_t || u < -tol_u || u > 1.0 + tol_u {
+        return;
+    }
+    let t_inner = t > tol_t && t < 1.0 - tol_t;
+    let u_inner = u > tol_u && u < 1.0 - tol_u;
+    match (t_inner, u_inner) {
+        (true, true) => {
+            let p = add(a, scale(r, t));
+            splits[i].push((t, p));
+            splits[j].push((u, p));
+        }
+        (true, false) => {
+            let p = if u < 0.5 { c } else { d };
+            splits[i].push((t, p));
+        }
+        (false, true) => {
+            let p = if t < 0.5 { a } else { b };
+            splits[j].push((u, p));
+        }
+        (false, false) => {}
+    }
+}
+
+/// Follow directed boundary edges into closed rings. At vertices with more
+/// than one outgoing edge the sharpest left turn is taken, which keeps
+/// loops that merely touch at a point separate.
+fn chain_edges(points: &[Vec2], edges: &[(usize, usize)], extent: f64) -> Vec<Vec<Vec2>> {
+    let mut outgoing: HashMap<usize, Vec<usize>> = HashMap::new();
+    for (k, &(s, _)) in edges.iter().enumerate() {
+        outgoing.entry(s).or_default().push(k);
+    }
+    let mut used = vec![false; edges.len()];
+    let min_area = (extent * 1e-6).powi(2);
+    let mut rings = Vec::new();
+
+    for start in 0..edges.len() {
+        if used[start] {
+            continue;
+        }
+        let origin = edges[start].0;
+        let mut ring = vec![points[origin]];
+        let mut cur = start;
+        let mut closed = false;
+        loop {
+            used[cur] = true;
+            let (s, e) = edges[cur];
+            if e == origin {
+                closed = true;
+                break;
+            }
+            ring.push(points[e]);
+            let din = unit(sub(points[e], points[s]));
+            let next = outgoing.get(&e).and_then(|cands| {
+                cands
+                    .iter()
+                    .copied()
+                    .filter(|&k| !used[k])
+                    .max_by(|&x, &y| {
+                        let tx = turn(din, unit(sub(points[edges[x].1], points[e])));
+                        let ty = turn(din, unit(sub(points[edges[y].1], points[e])));
+                        tx.partial_cmp(&ty).unwrap()
+                    })
+            });
+            match next {
+                Some(k) => cur = k,
+                None => break,
+            }
+        }
+        if closed && ring.len() >= 3 && signed_area(&ring).abs() > min_area {
+            rings.push(ring);
+        }
+    }
+    rings
+}
+
+/// Merges points closer than the weld distance into a single vertex id.
+struct Welder {
+    cell: f64,
+    grid: HashMap<(i64, i64), Vec<usize>>,
+    points: Vec<Vec2>,
+}
+
+impl Welder {
+    fn new(cell: f64) -> Self {
+        Self {
+            cell,
+            grid: HashMap::new(),
+            points: Vec::new(),
+        }
+    }
+
+    fn id(&mut self, p: Vec2) -> usize {
+        let cx = (p.x / self.cell).floor() as i64;
+        let cy = (p.y / self.cell).floor() as i64;
+        for dx in -1..=1 {
+            for dy in -1..=1 {
+                if let Some(ids) = self.grid.get(&(cx + dx, cy + dy)) {
+                    for &id in ids {
+                        if Vec2::dist(self.points[id], p) <= self.cell {
+                            return id;
+                        }
+                    }
+                }
+            }
+        }
+        let id = self.points.len();
+        self.points.push(p);
+        self.grid.entry((cx, cy)).or_default().push(id);
+        id
+    }
+}
+
+/// Winding number of `p` with respect to all rings of one operand.
+fn winding_number(rings: &[Vec<Vec2>], p: Vec2) -> i32 {
+    rings.iter().map(|r| nesting::winding_number(r, p)).sum()
+}
+
+// ── Small vector helpers ─────────────────────────────────────────────
+
+pub(super) fn add(a: Vec2, b: Vec2) -> Vec2 {
+    Vec2::new(a.x + b.x, a.y + b.y)
+}
+
+pub(super) fn sub(a: Vec2, b: Vec2) -> Vec2 {
+    Vec2::new(a.x - b.x, a.y - b.y)
+}
+
+pub(super) fn scale(a: Vec2, k: f64) -> Vec2 {
+    Vec2::new(a.x * k, a.y * k)
+}
+
+pub(super) fn dot(a: Vec2, b: Vec2) -> f64 {
+    a.x * b.x + a.y * b.y
+}
+
+pub(super) fn cross(a: Vec2, b: Vec2) -> f64 {
+    a.x * b.y - a.y * b.x
+}
+
+pub(super) fn unit(a: Vec2) -> Vec2 {
+    let len = dot(a, a).sqrt();
+    if len < 1e-15 {
+        a
+    } else {
+        scale(a, 1.0 / len)
+    }
+}
+
+/// Signed turn angle from direction `a` to direction `b` (left positive).
+fn turn(a: Vec2, b: Vec2) -> f64 {
+    cross(a, b).atan2(dot(a, b))
+}
+
+fn bounds(points: &[Vec2]) -> (Vec2, Vec2) {
+    let mut min = Vec2::new(f64::MAX, f64::MAX);
+    let mut max = Vec2::new(f64::MIN, f64::MIN);
+    for p in points {
+        min.x = min.x.min(p.x);
+        min.y = min.y.min(p.y);
+        max.x = max.x.max(p.x);
+        max.y = max.y.max(p.y);
+    }
+    (min, max)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rect(x0: f64, y0: f64, x1: f64, y1: f64) -> Polyline {
+        Polyline::new(
+            vec![
+                Vec2::new(x0, y0),
+                Vec2::new(x1, y0),
+                Vec2::new(x1, y1),
+                Vec2::new(x0, y1),
+            ],
+            true,
+        )
+    }
+
+    #[test]
+    fn union_of_overlapping_squares() {
+        let u = union(&[rect(0.0, 0.0, 10.0, 10.0), rect(5.0, 5.0, 15.0, 15.0)]);
+        assert_eq!(u.len(), 1);
+        assert!((area(&u) - 175.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn intersection_of_overlapping_squares() {
+        let i = intersection(&[rect(0.0, 0.0, 10.0, 10.0)], &[rect(5.0, 5.0, 15.0, 15.0)]);
+        assert_eq!(i.len(), 1);
+        assert!((area(&i) - 25.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn difference_can_create_a_hole() {
+        let d = difference(&[rect(0.0, 0.0, 10.0, 10.0)], &[rect(4.0, 4.0, 6.0, 6.0)]);
+        assert_eq!(d.len(), 2);
+        assert!((area(&d) - 96.0).abs() < 1e-9);
+        assert_eq!(
+            d.iter()
+                .filter(|r| area(std::slice::from_ref(r)) < 0.0)
+                .count(),
+            1
+        );
+    }
+
+    #[test]
+    fn difference_of_disjoint_keeps_first() {
+        let d = difference(&[rect(0.0, 0.0, 1.0, 1.0)], &[rect(5.0, 5.0, 6.0, 6.0)]);
+        assert!((area(&d) - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn intersection_of_disjoint_is_empty() {
+        let i = intersection(&[rect(0.0, 0.0, 1.0, 1.0)], &[rect(5.0, 5.0, 6.0, 6.0)]);
+        assert!(i.is_empty());
+    }
+
+    #[test]
+    fn shared_edge_union_merges() {
+        let u = union(&[rect(0.0, 0.0, 10.0, 10.0), rect(10.0, 0.0, 20.0, 10.0)]);
+        assert_eq!(u.len(), 1);
+        assert!((area(&u) - 200.0).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,66 @@
+//! Flattening curves into chords within a tolerance.
+//!
+//! Swiss-cheese layer: **Geometry representation**
+//! Extension point: add other curve kinds next to `arc`.
+//!
+//! Importers turn circular and elliptical arcs into polylines here, so a
+//! drawing comes out equally smooth whichever format it arrived in. The
+//! chord count follows from the sagitta: a chord across angle θ of a
+//! circle of radius r strays `r·(1 − cos θ/2)` from it.
+
+use std::f64::consts::PI;
+
+use super::Vec2;
+
+/// Chords for `sweep` radians of a curve of `radius` staying within
+/// `tol` of it, at least one per eighth of a turn.
+pub fn arc_segments(radius: f64, sweep: f64, tol: f64) -> usize {
+    let step = if tol < radius {
+        2.0 * (1.0 - tol / radius).acos()
+    } else {
+        PI / 4.0
+    };
+    (sweep.abs() / step.min(PI / 4.0))
+        .ceil()
+        .clamp(1.0, 100_000.0) as usize
+}
+
+/// Points along a circular arc from `start` through `sweep` radians
+/// (counter-clockwise when positive), both ends included.
+pub fn arc(center: Vec2, radius: f64, start: f64, sweep: f64, tol: f64) -> Vec<Vec2> {
+    let steps = arc_segments(radius, sweep, tol);
+    (0..=steps)
+        .map(|i| {
+            let a = start + sweep * i as f64 / steps as f64;
+            Vec2::new(center.x + radius * a.cos(), center.y + radius * a.sin())
+        })
+        .collect()
+}
+
+/// Distance from `p` to the chord `a`–`b`.
+pub fn chord_gap(a: Vec2, b: Vec2, p: Vec2) -> f64 {
+    let (dx, dy) = (b.x - a.x, b.y - a.y);
+    let len = dx.hypot(dy);
+    if len < 1e-12 {
+        return Vec2::dist(a, p);
+    }
+    ((p.x - a.x) * dy - (p.y - a.y) * dx).abs() / len
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_arc_chords_stay_within_tolerance() {
+        let center = Vec2::new(1.0, 2.0);
+        let pts = arc(center, 10.0, 0.0, PI, 0.01);
+        assert_eq!(pts.len(), arc_segments(10.0, PI, 0.01) + 1);
+        for w in pts.windows(2) {
+            let mid = Vec2::new((w[0].x + w[1].x) / 2.0, (w[0].y + w[1].y) / 2.0);
+            assert!(10.0 - Vec2::dist(center, mid) <= 0.01 + 1e-12);
+        }
+        // Coarse tolerance still gives one chord per eighth of a turn.
+        assert_eq!(arc_segments(1.0, 2.0 * PI, 5.0), 8);
+    }
+}
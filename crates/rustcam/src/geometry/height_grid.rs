@@ -0,0 +1,217 @@
+//! Sampled height fields and their level contours.
+//!
+//! Swiss-cheese layer: **Geometry representation**
+//! Extension point: add smoother interpolation than bilinear, or trace
+//! open contours that leave the grid.
+//!
+//! A `HeightGrid` holds one Z per node of a regular XY grid: the
+//! tool-centre surface for waterline passes, or the stock top for rest
+//! machining. `level_loops` traces where it crosses a Z with marching
+//! squares, placing each crossing by linear interpolation along the grid
+//! edge and resolving saddles by the cell's average height. Linear
+//! interpolation misplaces a crossing where the surface steps between
+//! nodes, so `level_loops_below` bisects each edge against the exact
+//! surface instead and keeps the crossing on the low side.
+
+use std::collections::BTreeMap;
+
+use super::Vec2;
+
+/// Heights on a regular grid of `nx` × `ny` nodes, row-major from
+/// `origin`.
+#[derive(Debug, Clone)]
+pub struct HeightGrid {
+    pub origin: Vec2,
+    pub spacing: f64,
+    pub nx: usize,
+    pub ny: usize,
+    pub z: Vec<f64>,
+}
+
+impl HeightGrid {
+    pub fn at(&self, i: usize, j: usize) -> f64 {
+        self.z[j * self.nx + i]
+    }
+
+    pub fn node(&self, i: usize, j: usize) -> Vec2 {
+        Vec2::new(
+            self.origin.x + i as f64 * self.spacing,
+            self.origin.y + j as f64 * self.spacing,
+        )
+    }
+
+    /// Closed loops where the surface crosses `z`, with the part above
+    /// `z` on their right. Loops that would run off the grid are left
+    /// open and dropped, so callers keep a border below every level.
+    pub fn level_loops(&self, z: f64) -> Vec<Vec<Vec2>> {
+        self.trace(z, |a, b| self.crossing(a, b, z))
+    }
+
+    /// Like `level_loops`, but each crossing is found by bisecting its
+    /// grid edge with `height`, the surface the grid samples, to within
+    /// `tolerance`, and taken at the low end. Every loop point is then on
+    /// surface no higher than `z`, however steep it is between nodes.
+    pub fn level_loops_below(
+        &self,
+        z: f64,
+        tolerance: f64,
+        height: impl Fn(Vec2) -> f64,
+    ) -> Vec<Vec<Vec2>> {
+        self.trace(z, |a, b| {
+            let (mut lo, mut hi) = (self.node(a.0, a.1), self.node(b.0, b.1));
+            if self.at(a.0, a.1) > z {
+                std::mem::swap(&mut lo, &mut hi);
+            }
+            while Vec2::dist(lo, hi) > tolerance {
+                let mid = Vec2::new((lo.x + hi.x) / 2.0, (lo.y + hi.y) / 2.0);
+                if height(mid) > z {
+                    hi = mid;
+                } else {
+                    lo = mid;
+                }
+            }
+            lo
+        })
+    }
+
+    /// Marching squares at `z`, placing the crossing on the edge between
+    /// two nodes with `crossing`.
+    fn trace(
+        &self,
+        z: f64,
+        crossing: impl Fn((usize, usize), (usize, usize)) -> Vec2,
+    ) -> Vec<Vec<Vec2>> {
+        let above = |i: usize, j: usize| self.at(i, j) > z;
+        // Crossings are keyed by grid edge: 2 * node for the edge to the
+        // +X neighbour, 2 * node + 1 for the edge to the +Y neighbour.
+        let mut points: BTreeMap<usize, Vec2> = BTreeMap::new();
+        let mut next: BTreeMap<usize, usize> = BTreeMap::new();
+        for j in 0..self.ny - 1 {
+            for i in 0..self.nx - 1 {
+                // Corners and edges counter-clockwise from the bottom left.
+                let corners = [(i, j), (i + 1, j), (i + 1, j + 1), (i, j + 1)];
+                let edges = [
+                    2 * (j * self.nx + i),
+                    2 * ((j * self.nx) + i + 1) + 1,
+                    2 * ((j + 1) * self.nx + i),
+                    2 * (j * self.nx + i) + 1,
+                ];
+                let inside = corners.map(|(a, b)| above(a, b));
+                // Crossing k runs from corner k to corner k + 1; `true`
+                // when it steps up through the level.
+                let mut crossings: Vec<(usize, bool)> = Vec::with_capacity(4);
+                for k in 0..4 {
+                    let (a, b) = (inside[k], inside[(k + 1) % 4]);
+                    if a != b {
+                        let (ca, cb) = (corners[k], corners[(k + 1) % 4]);
+                        points.entry(edges[k]).or_insert_with(|| crossing(ca, cb));
+                        crossings.push((edges[k], b));
+                    }
+                }
+                if crossings.is_empty() {
+                    continue;
+                }
+                // Each entry joins the next exit counter-clockwise, which
+                // walks round a high corner; a saddle whose centre is high
+                // joins each entry to the previous exit instead.
+                let n = crossings.len();
+                let centre = corners.iter().map(|&(a, b)| self.at(a, b)).sum::<f64>() / 4.0;
+                let joined = n == 4 && centre > z;
+                for (k, &(key, into)) in crossings.iter().enumerate() {
+                    if !into {
+                        continue;
+                    }
+                    let exit = if joined {
+                        crossings[(k + n - 1) % n].0
+                    } else {
+                        crossings[(k + 1) % n].0
+                    };
+                    next.insert(key, exit);
+                }
+            }
+        }
+
+        let mut loops = Vec::new();
+        while let Some((&start, _)) = next.iter().next() {
+            let mut ring = Vec::new();
+            let mut key = start;
+            while let Some(to) = next.remove(&key) {
+                let p = points[&key];
+                if ring.last().is_none_or(|&q: &Vec2| Vec2::dist(p, q) > 1e-9) {
+                    ring.push(p);
+                }
+                key = to;
+            }
+            if ring.len() > 2 {
+                loops.push(ring);
+            }
+        }
+        loops
+    }
+
+    /// Where the surface crosses `z` between two neighbouring nodes.
+    fn crossing(&self, a: (usize, usize), b: (usize, usize), z: f64) -> Vec2 {
+        let (za, zb) = (self.at(a.0, a.1), self.at(b.0, b.1));
+        let t = (za - z) / (za - zb);
+        let (pa, pb) = (self.node(a.0, a.1), self.node(b.0, b.1));
+        Vec2::new(pa.x + (pb.x - pa.x) * t, pa.y + (pb.y - pa.y) * t)
+    }
+
+    /// Gradient magnitude of the surface at `p`, bilinear in the cell
+    /// around it.
+    pub fn slope(&self, p: Vec2) -> f64 {
+        let fx = ((p.x - self.origin.x) / self.spacing).clamp(0.0, (self.nx - 2) as f64);
+        let fy = ((p.y - self.origin.y) / self.spacing).clamp(0.0, (self.ny - 2) as f64);
+        let (i, j) = (fx.floor() as usize, fy.floor() as usize);
+        let (u, v) = (fx - i as f64, fy - j as f64);
+        let (z00, z10) = (self.at(i, j), self.at(i + 1, j));
+        let (z01, z11) = (self.at(i, j + 1), self.at(i + 1, j + 1));
+        let dx = ((z10 - z00) * (1.0 - v) + (z11 - z01) * v) / self.spacing;
+        let dy = ((z01 - z00) * (1.0 - u) + (z11 - z10) * u) / self.spacing;
+        dx.hypot(dy)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geometry::offset::signed_area;
+
+    /// A square plateau 1 high over nodes 2..=5 of an 8 × 8 grid.
+    fn plateau() -> HeightGrid {
+        let mut z = vec![0.0; 64];
+        for j in 2..=5 {
+            for i in 2..=5 {
+                z[j * 8 + i] = 1.0;
+            }
+        }
+        HeightGrid {
+            origin: Vec2::new(0.0, 0.0),
+            spacing: 1.0,
+            nx: 8,
+            ny: 8,
+            z,
+        }
+    }
+
+    #[test]
+    fn plateau_gives_one_clockwise_loop_halfway_out() {
+        let grid = plateau();
+        let loops = grid.level_loops(0.5);
+        assert_eq!(loops.len(), 1);
+        // High ground on the right: clockwise round the plateau.
+        assert!(signed_area(&loops[0]) < 0.0);
+        for p in &loops[0] {
+            let d = (p.x - 3.5).abs().max((p.y - 3.5).abs());
+            assert!((d - 2.0).abs() < 1e-9, "{:?}", p);
+        }
+        assert!(grid.level_loops(1.5).is_empty());
+    }
+
+    #[test]
+    fn slope_is_bilinear() {
+        let grid = plateau();
+        assert_eq!(grid.slope(Vec2::new(0.5, 0.5)), 0.0);
+        assert!((grid.slope(Vec2::new(1.5, 3.5)) - 1.0).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,300 @@
+//! Spatial index over the triangles of a mesh.
+//!
+//! Swiss-cheese layer: **Geometry representation**
+//! Extension point: swap the uniform grids for a BVH behind the same
+//! `at_xy` / `at_z` queries.
+//!
+//! Height probes and slicing used to test every triangle per query, and
+//! the tool-disc sampling multiplies that by 25 per sample point. The
+//! index buckets triangles once: by their XY bounding box into a uniform
+//! grid, and by their Z range into uniform bands. A query returns the
+//! triangles of one bucket in their original order, a superset of those
+//! that can match, so callers running the exact tests on the candidates
+//! get the same answers as the brute-force loop, ties included.
+
+use super::Triangle;
+
+/// Most cells along one side of the XY grid.
+const MAX_GRID_SIDE: usize = 1024;
+
+/// Most Z bands.
+const MAX_BANDS: usize = 1024;
+
+/// Triangles per XY cell the grid aims for.
+const TRIANGLES_PER_CELL: f64 = 2.0;
+
+/// Buckets of triangle indices stored back to back.
+#[derive(Clone, Default)]
+struct Buckets {
+    /// `items[start[i]..start[i + 1]]` is bucket `i`.
+    start: Vec<u32>,
+    items: Vec<u32>,
+}
+
+impl Buckets {
+    /// Fill `count` buckets from `(triangle, first bucket, last bucket)`
+    /// ranges given twice by `ranges`, once to count and once to place.
+    fn build<I>(count: usize, ranges: impl Fn() -> I) -> Self
+    where
+        I: Iterator<Item = (u32, std::ops::RangeInclusive<usize>)>,
+    {
+        let mut start = vec![0u32; count + 1];
+        for (_, cells) in ranges() {
+            for c in cells {
+                start[c + 1] += 1;
+            }
+        }
+        for i in 0..count {
+            start[i + 1] += start[i];
+        }
+        let mut fill = start.clone();
+        let mut items = vec![0u32; start[count] as usize];
+        // Triangles arrive in index order, so every bucket stays sorted.
+        for (t, cells) in ranges() {
+            for c in cells {
+                items[fill[c] as usize] = t;
+                fill[c] += 1;
+            }
+        }
+        Self { start, items }
+    }
+
+    fn get(&self, i: usize) -> &[u32] {
+        &self.items[self.start[i] as usize..self.start[i + 1] as usize]
+    }
+}
+
+/// Uniform bucketing of one coordinate.
+#[derive(Clone, Copy, Debug)]
+struct Axis {
+    min: f64,
+    max: f64,
+    cell: f64,
+    count: usize,
+}
+
+impl Axis {
+    fn new(min: f64, max: f64, count: usize) -> Self {
+        let extent = max - min;
+        let cell = if extent > 0.0 {
+            extent / count as f64
+        } else {
+            1.0
+        };
+        Self {
+            min,
+            max,
+            cell,
+            count,
+        }
+    }
+
+    /// Bucket of `v`, clamped into range. Monotonic in `v`, so a value
+    /// inside an interval lands between the interval's end buckets.
+    fn clamped(&self, v: f64) -> usize {
+        let i = ((v - self.min) / self.cell).floor();
+        if i > 0.0 {
+            (i as usize).min(self.count - 1)
+        } else {
+            0
+        }
+    }
+
+    /// Bucket of `v`, or `None` if it lies outside every bucket.
+    fn find(&self, v: f64) -> Option<usize> {
+        (self.min..=self.max).contains(&v).then(|| self.clamped(v))
+    }
+}
+
+/// XY grid and Z bands over a triangle list.
+#[derive(Clone)]
+pub struct MeshIndex {
+    x: Axis,
+    y: Axis,
+    xy: Buckets,
+    z: Axis,
+    bands: Buckets,
+}
+
+impl MeshIndex {
+    pub fn build(triangles: &[Triangle]) -> Self {
+        let n = triangles.len();
+        let boxes: Vec<[f64; 4]> = triangles.iter().map(xy_box).collect();
+        let (mut lo, mut hi) = ([f64::MAX; 2], [f64::MIN; 2]);
+        for b in &boxes {
+            lo = [lo[0].min(b[0]), lo[1].min(b[1])];
+            hi = [hi[0].max(b[2]), hi[1].max(b[3])];
+        }
+        let (mut z_lo, mut z_hi) = (f64::MAX, f64::MIN);
+        for t in triangles {
+            z_lo = z_lo.min(t.min_z());
+            z_hi = z_hi.max(t.max_z());
+        }
+        if n == 0 {
+            (lo, hi, z_lo, z_hi) = ([0.0; 2], [0.0; 2], 0.0, 0.0);
+        }
+
+        // Roughly square cells, about `TRIANGLES_PER_CELL` triangles each.
+        let (w, h) = (hi[0] - lo[0], hi[1] - lo[1]);
+        let cells = (n as f64 / TRIANGLES_PER_CELL).max(1.0);
+        let side = if w > 0.0 && h > 0.0 {
+            (w * h / cells).sqrt()
+        } else {
+            w.max(h) / cells
+        };
+        let count = |extent: f64| {
+            if side > 0.0 {
+                ((extent / side).ceil() as usize).clamp(1, MAX_GRID_SIDE)
+            } else {
+                1
+            }
+        };
+        let x = Axis::new(lo[0], hi[0], count(w));
+        let y = Axis::new(lo[1], hi[1], count(h));
+        let xy = Buckets::build(x.count * y.count, || {
+            boxes.iter().enumerate().flat_map(move |(t, b)| {
+                let (x0, x1) = (x.clamped(b[0]), x.clamped(b[2]));
+                let (y0, y1) = (y.clamped(b[1]), y.clamped(b[3]));
+                (y0..=y1).map(move |row| (t as u32, row * x.count + x0..=row * x.count + x1))
+            })
+        });
+
+        let z = Axis::new(z_lo, z_hi, n.clamp(1, MAX_BANDS));
+        let bands = Buckets::build(z.count, || {
+            triangles
+                .iter()
+                .enumerate()
+                .map(move |(t, tri)| (t as u32, z.clamped(tri.min_z())..=z.clamped(tri.max_z())))
+        });
+        Self { x, y, xy, z, bands }
+    }
+
+    /// Indices of the triangles whose XY projection might contain
+    /// `(x, y)`, in ascending order.
+    pub fn at_xy(&self, x: f64, y: f64) -> &[u32] {
+        match (self.x.find(x), self.y.find(y)) {
+            (Some(i), Some(j)) => self.xy.get(j * self.x.count + i),
+            _ => &[],
+        }
+    }
+
+    /// Indices of the triangles whose XY projection might overlap the box
+    /// from (`x0`, `y0`) to (`x1`, `y1`), in ascending order.
+    pub fn in_xy_box(&self, x0: f64, y0: f64, x1: f64, y1: f64) -> Vec<u32> {
+        if x1 < self.x.min || x0 > self.x.max || y1 < self.y.min || y0 > self.y.max {
+            return Vec::new();
+        }
+        let (i0, i1) = (self.x.clamped(x0), self.x.clamped(x1));
+        let (j0, j1) = (self.y.clamped(y0), self.y.clamped(y1));
+        let mut out: Vec<u32> = (j0..=j1)
+            .flat_map(|j| (i0..=i1).flat_map(move |i| self.xy.get(j * self.x.count + i)))
+            .copied()
+            .collect();
+        out.sort_unstable();
+        out.dedup();
+        out
+    }
+
+    /// Indices of the triangles whose Z range might contain `z`, in
+    /// ascending order.
+    pub fn at_z(&self, z: f64) -> &[u32] {
+        match self.z.find(z) {
+            Some(i) => self.bands.get(i),
+            None => &[],
+        }
+    }
+}
+
+impl std::fmt::Debug for MeshIndex {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MeshIndex")
+            .field("grid", &(self.x.count, self.y.count))
+            .field("bands", &self.z.count)
+            .field("entries", &(self.xy.items.len() + self.bands.items.len()))
+            .finish()
+    }
+}
+
+/// XY bounding box `[min x, min y, max x, max y]`, padded by the slack
+/// `slicer::triangle_z_at_xy` allows past a triangle's edges.
+fn xy_box(t: &Triangle) -> [f64; 4] {
+    let xs = [t.v0.x, t.v1.x, t.v2.x];
+    let ys = [t.v0.y, t.v1.y, t.v2.y];
+    let (x0, x1) = (
+        xs.iter().copied().fold(f64::MAX, f64::min),
+        xs.iter().copied().fold(f64::MIN, f64::max),
+    );
+    let (y0, y1) = (
+        ys.iter().copied().fold(f64::MAX, f64::min),
+        ys.iter().copied().fold(f64::MIN, f64::max),
+    );
+    let pad = (x1 - x0 + y1 - y0) * 1e-6 + 1e-9;
+    [x0 - pad, y0 - pad, x1 + pad, y1 + pad]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geometry::Vec3;
+
+    fn tri(a: [f64; 3], b: [f64; 3], c: [f64; 3]) -> Triangle {
+        Triangle {
+            normal: Vec3::new(0.0, 0.0, 1.0),
+            v0: Vec3::new(a[0], a[1], a[2]),
+            v1: Vec3::new(b[0], b[1], b[2]),
+            v2: Vec3::new(c[0], c[1], c[2]),
+        }
+    }
+
+    /// A strip of triangles rising in Z along X.
+    fn strip(n: usize) -> Vec<Triangle> {
+        (0..n)
+            .map(|i| {
+                let x = i as f64;
+                tri([x, 0.0, x], [x + 1.0, 0.0, x + 1.0], [x, 1.0, x])
+            })
+            .collect()
+    }
+
+    #[test]
+    fn xy_buckets_hold_every_overlapping_triangle_in_order() {
+        let tris = strip(100);
+        let index = MeshIndex::build(&tris);
+        let hits = index.at_xy(10.5, 0.25);
+        assert!(hits.contains(&10));
+        assert!(hits.windows(2).all(|w| w[0] < w[1]));
+        assert!(hits.len() < 10, "{}", hits.len());
+        // Shared edges are found from both sides.
+        assert!(index.at_xy(11.0, 0.0).contains(&10));
+        assert!(index.at_xy(11.0, 0.0).contains(&11));
+        assert!(index.at_xy(-1.0, 0.5).is_empty());
+        assert!(index.at_xy(50.0, 2.0).is_empty());
+    }
+
+    #[test]
+    fn z_bands_cover_triangle_ranges() {
+        let tris = strip(100);
+        let index = MeshIndex::build(&tris);
+        for z in [0.0, 0.5, 37.25, 99.0, 100.0] {
+            let hits = index.at_z(z);
+            for (i, t) in tris.iter().enumerate() {
+                if t.min_z() <= z && z <= t.max_z() {
+                    assert!(hits.contains(&(i as u32)), "z {z} misses {i}");
+                }
+            }
+            assert!(hits.windows(2).all(|w| w[0] < w[1]));
+        }
+        assert!(index.at_z(101.0).is_empty());
+    }
+
+    #[test]
+    fn flat_and_empty_meshes() {
+        let flat = [tri([0.0, 0.0, 2.0], [1.0, 0.0, 2.0], [0.0, 1.0, 2.0])];
+        let index = MeshIndex::build(&flat);
+        assert_eq!(index.at_z(2.0), [0]);
+        assert_eq!(index.at_xy(0.2, 0.2), [0]);
+        let empty = MeshIndex::build(&[]);
+        assert!(empty.at_xy(0.0, 0.0).is_empty());
+        assert!(empty.at_z(0.0).is_empty());
+    }
+}
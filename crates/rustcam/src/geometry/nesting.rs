@@ -0,0 +1,338 @@
+//! Contour nesting: group closed polylines into regions with holes.
+//!
+//! Swiss-cheese layer: **Geometry representation**
+//! Extension point: add further `FillRule` variants and classify them in
+//! `classify`.
+//!
+//! Importers (`parse_svg`, `slice_at_z`) return a flat list of loops with
+//! no notion of which loop is a boundary and which is an island. This
+//! module recovers that structure so area-clearing strategies can leave
+//! islands standing.
+
+use super::offset::signed_area;
+use super::{Polyline, Vec2};
+
+/// How overlapping loops decide what is "inside".
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum FillRule {
+    /// Inside when enclosed by an odd number of loops. Independent of
+    /// loop direction, so it suits slicer output and most SVG artwork.
+    #[default]
+    EvenOdd,
+    /// Inside when the winding number is non-zero (SVG's default
+    /// `fill-rule`). Loop direction matters.
+    NonZero,
+}
+
+/// A connected area to machine: one outer boundary and the islands
+/// inside it.
+///
+/// `outer` is counter-clockwise and every hole is clockwise, i.e. the
+/// region is always on the left of its rings, which is the convention
+/// `offset::offset_region` expects.
+#[derive(Debug, Clone)]
+pub struct Region {
+    pub outer: Polyline,
+    pub holes: Vec<Polyline>,
+}
+
+impl Region {
+    /// Outer boundary followed by holes.
+    pub fn rings(&self) -> Vec<Polyline> {
+        let mut rings = Vec::with_capacity(self.holes.len() + 1);
+        rings.push(self.outer.clone());
+        rings.extend(self.holes.iter().cloned());
+        rings
+    }
+
+    /// True when `p` lies inside the outer boundary but in none of the holes.
+    pub fn contains(&self, p: Vec2) -> bool {
+        winding_number(&self.outer.points, p) != 0
+            && self.holes.iter().all(|h| winding_number(&h.points, p) == 0)
+    }
+}
+
+/// Winding number of `p` with respect to a single closed ring.
+pub fn winding_number(ring: &[Vec2], p: Vec2) -> i32 {
+    let n = ring.len();
+    let mut wn = 0;
+    for i in 0..n {
+        let a = ring[i];
+        let b = ring[(i + 1) % n];
+        let side = (b.x - a.x) * (p.y - a.y) - (p.x - a.x) * (b.y - a.y);
+        if a.y <= p.y {
+            if b.y > p.y && side > 0.0 {
+                wn += 1;
+            }
+        } else if b.y <= p.y && side < 0.0 {
+            wn -= 1;
+        }
+    }
+    wn
+}
+
+/// True when the segment `a`→`b` stays inside the area bounded by
+/// `rings` (non-zero rule), i.e. it crosses no ring and its midpoint is
+/// inside. Touching a ring at an endpoint is allowed.
+pub fn segment_inside(rings: &[Polyline], a: Vec2, b: Vec2) -> bool {
+    let mid = Vec2::new((a.x + b.x) / 2.0, (a.y + b.y) / 2.0);
+    let wn: i32 = rings.iter().map(|r| winding_number(&r.points, mid)).sum();
+    if wn == 0 {
+        return false;
+    }
+    let cross = |o: Vec2, p: Vec2, q: Vec2| (p.x - o.x) * (q.y - o.y) - (p.y - o.y) * (q.x - o.x);
+    rings.iter().all(|r| {
+        let n = r.points.len();
+        (0..n).all(|i| {
+            let c = r.points[i];
+            let d = r.points[(i + 1) % n];
+            let d1 = cross(a, b, c);
+            let d2 = cross(a, b, d);
+            let d3 = cross(c, d, a);
+            let d4 = cross(c, d, b);
+            !(d1 * d2 < 0.0 && d3 * d4 < 0.0)
+        })
+    })
+}
+
+/// Group closed contours into regions under `rule`.
+///
+/// Open polylines and loops with fewer than three points are ignored.
+/// Loops nested inside an island start a new region of their own (the
+/// counter of a letter "O" inside a pocket, for instance).
+pub fn build_regions(contours: &[Polyline], rule: FillRule) -> Vec<Region> {
+    let loops: Vec<&Polyline> = contours
+        .iter()
+        .filter(|c| c.closed && c.points.len() >= 3 && signed_area(&c.points).abs() > 1e-12)
+        .collect();
+    let kinds = classify(&loops, rule);
+
+    // Outer boundaries become regions; each hole attaches to the smallest
+    // outer boundary that contains it.
+    let mut regions: Vec<(usize, Region)> = Vec::new();
+    for (i, kind) in kinds.iter().enumerate() {
+        if *kind == LoopKind::Outer {
+            regions.push((
+                i,
+                Region {
+                    outer: oriented(loops[i], true),
+                    holes: Vec::new(),
+                },
+            ));
+        }
+    }
+    for (i, kind) in kinds.iter().enumerate() {
+        if *kind != LoopKind::Hole {
+            continue;
+        }
+        let probe = loops[i].points[0];
+        let owner = regions
+            .iter_mut()
+            .filter(|(o, _)| winding_number(&loops[*o].points, probe) != 0)
+            .min_by(|(a, _), (b, _)| {
+                let aa = signed_area(&loops[*a].points).abs();
+                let ab = signed_area(&loops[*b].points).abs();
+                aa.partial_cmp(&ab).unwrap()
+            });
+        if let Some((_, region)) = owner {
+            region.holes.push(oriented(loops[i], false));
+        }
+    }
+    regions.into_iter().map(|(_, r)| r).collect()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum LoopKind {
+    Outer,
+    Hole,
+    /// Separates two filled areas (non-zero rule only); not a boundary.
+    Interior,
+}
+
+/// Decide for every loop whether the filled area lies inside it (outer
+/// boundary), outside it (hole) or on both sides.
+fn classify(loops: &[&Polyline], rule: FillRule) -> Vec<LoopKind> {
+    loops
+        .iter()
+        .enumerate()
+        .map(|(i, l)| {
+            let probe = l.points[0];
+            let enclosing = loops
+                .iter()
+                .enumerate()
+                .filter(|(j, _)| *j != i)
+                .map(|(_, o)| winding_number(&o.points, probe));
+            match rule {
+                FillRule::EvenOdd => {
+                    let depth = enclosing.filter(|w| *w != 0).count();
+                    if depth % 2 == 0 {
+                        LoopKind::Outer
+                    } else {
+                        LoopKind::Hole
+                    }
+                }
+                FillRule::NonZero => {
+                    let outside: i32 = enclosing.sum();
+                    let inside = outside + if signed_area(&l.points) > 0.0 { 1 } else { -1 };
+                    match (outside != 0, inside != 0) {
+                        (false, true) => LoopKind::Outer,
+                        (true, false) => LoopKind::Hole,
+                        _ => LoopKind::Interior,
+                    }
+                }
+            }
+        })
+        .collect()
+}
+
+/// Copy of `poly` wound counter-clockwise (`ccw`) or clockwise.
+fn oriented(poly: &Polyline, ccw: bool) -> Polyline {
+    let mut points = poly.points.clone();
+    if (signed_area(&points) > 0.0) != ccw {
+        points.reverse();
+    }
+    Polyline::new(points, true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rect(x0: f64, y0: f64, x1: f64, y1: f64) -> Polyline {
+        Polyline::new(
+            vec![
+                Vec2::new(x0, y0),
+                Vec2::new(x1, y0),
+                Vec2::new(x1, y1),
+                Vec2::new(x0, y1),
+            ],
+            true,
+        )
+    }
+
+    fn reversed(mut p: Polyline) -> Polyline {
+        p.points.reverse();
+        p
+    }
+
+    #[test]
+    fn single_loop_is_one_region() {
+        let regions = build_regions(&[rect(0.0, 0.0, 10.0, 10.0)], FillRule::EvenOdd);
+        assert_eq!(regions.len(), 1);
+        assert!(regions[0].holes.is_empty());
+        assert!(signed_area(&regions[0].outer.points) > 0.0);
+    }
+
+    #[test]
+    fn nested_loop_becomes_hole_regardless_of_order() {
+        let contours = vec![rect(3.0, 3.0, 7.0, 7.0), rect(0.0, 0.0, 10.0, 10.0)];
+        let regions = build_regions(&contours, FillRule::EvenOdd);
+        assert_eq!(regions.len(), 1);
+        assert_eq!(regions[0].holes.len(), 1);
+        assert!(signed_area(&regions[0].holes[0].points) < 0.0);
+        assert!(!regions[0].contains(Vec2::new(5.0, 5.0)));
+        assert!(regions[0].contains(Vec2::new(1.0, 5.0)));
+    }
+
+    #[test]
+    fn island_inside_hole_starts_new_region() {
+        let contours = vec![
+            rect(0.0, 0.0, 30.0, 30.0),
+            rect(5.0, 5.0, 25.0, 25.0),
+            rect(10.0, 10.0, 20.0, 20.0),
+        ];
+        let regions = build_regions(&contours, FillRule::EvenOdd);
+        assert_eq!(regions.len(), 2);
+        let inner = regions
+            .iter()
+            .find(|r| r.contains(Vec2::new(15.0, 15.0)))
+            .unwrap();
+        assert!(inner.holes.is_empty());
+    }
+
+    #[test]
+    fn separate_loops_are_separate_regions() {
+        let contours = vec![rect(0.0, 0.0, 5.0, 5.0), rect(10.0, 0.0, 15.0, 5.0)];
+        assert_eq!(build_regions(&contours, FillRule::EvenOdd).len(), 2);
+    }
+
+    #[test]
+    fn hole_attaches_to_innermost_outer() {
+        let contours = vec![
+            rect(0.0, 0.0, 50.0, 50.0),
+            rect(5.0, 5.0, 45.0, 45.0),
+            rect(10.0, 10.0, 40.0, 40.0),
+            rect(20.0, 20.0, 30.0, 30.0),
+        ];
+        let regions = build_regions(&contours, FillRule::EvenOdd);
+        assert_eq!(regions.len(), 2);
+        for r in &regions {
+            assert_eq!(r.holes.len(), 1);
+        }
+    }
+
+    #[test]
+    fn nonzero_same_direction_nested_loop_is_not_a_hole() {
+        let contours = vec![rect(0.0, 0.0, 10.0, 10.0), rect(3.0, 3.0, 7.0, 7.0)];
+        let regions = build_regions(&contours, FillRule::NonZero);
+        assert_eq!(regions.len(), 1);
+        assert!(regions[0].holes.is_empty());
+        assert!(regions[0].contains(Vec2::new(5.0, 5.0)));
+    }
+
+    #[test]
+    fn nonzero_opposite_direction_nested_loop_is_a_hole() {
+        let contours = vec![
+            rect(0.0, 0.0, 10.0, 10.0),
+            reversed(rect(3.0, 3.0, 7.0, 7.0)),
+        ];
+        let regions = build_regions(&contours, FillRule::NonZero);
+        assert_eq!(regions.len(), 1);
+        assert_eq!(regions[0].holes.len(), 1);
+    }
+
+    #[test]
+    fn open_polylines_are_ignored() {
+        let open = Polyline::new(vec![Vec2::new(0.0, 0.0), Vec2::new(5.0, 5.0)], false);
+        assert!(build_regions(&[open], FillRule::EvenOdd).is_empty());
+    }
+
+    #[test]
+    fn segment_inside_rejects_crossing_a_hole() {
+        let rings = vec![
+            rect(0.0, 0.0, 10.0, 10.0),
+            reversed(rect(4.0, 4.0, 6.0, 6.0)),
+        ];
+        assert!(segment_inside(
+            &rings,
+            Vec2::new(1.0, 1.0),
+            Vec2::new(9.0, 1.0)
+        ));
+        assert!(!segment_inside(
+            &rings,
+            Vec2::new(1.0, 5.0),
+            Vec2::new(9.0, 5.0)
+        ));
+        assert!(!segment_inside(
+            &rings,
+            Vec2::new(5.0, 5.0),
+            Vec2::new(5.0, 5.5)
+        ));
+        assert!(!segment_inside(
+            &rings,
+            Vec2::new(1.0, 1.0),
+            Vec2::new(12.0, 1.0)
+        ));
+    }
+
+    #[test]
+    fn winding_number_follows_direction() {
+        let sq = rect(0.0, 0.0, 10.0, 10.0);
+        assert_eq!(winding_number(&sq.points, Vec2::new(5.0, 5.0)), 1);
+        assert_eq!(
+            winding_number(&reversed(sq.clone()).points, Vec2::new(5.0, 5.0)),
+            -1
+        );
+        assert_eq!(winding_number(&sq.points, Vec2::new(15.0, 5.0)), 0);
+    }
+}
@@ -0,0 +1,472 @@
+//! Polygon offsetting for tool-radius compensation.
+//!
+//! Swiss-cheese layer: **Geometry representation**
+//! Extension point: add new corner treatments as `JoinType` variants and
+//! handle them in `push_join`.
+//!
+//! Each edge is displaced along its normal, corners are closed with the
+//! selected join, and the resulting (possibly self-intersecting) raw loops
+//! are cleaned up by keeping only the boundary of the region with positive
+//! winding number. This removes the "swallow-tail" loops that appear when
+//! a pocket wall is narrower than the tool, and splits the result into
+//! several loops when an inward offset pinches a shape apart.
+
+use super::boolean::{self, add, cross, dot, scale, sub, unit};
+use super::{Polyline, Vec2};
+
+/// Maximum chord deviation (mm) used when approximating round joins.
+pub const ARC_TOLERANCE: f64 = 0.01;
+
+/// How convex corners are closed when offsetting outward.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum JoinType {
+    /// Circular arc of radius `|delta|` about the original vertex, the
+    /// locus of a cylindrical tool rolling around the corner. The arc is
+    /// approximated from outside, within `ARC_TOLERANCE`.
+    #[default]
+    Round,
+    /// Extend both edges until they meet. Corners whose miter point lies
+    /// more than `limit × |delta|` from the vertex are bevelled instead.
+    Miter { limit: f64 },
+}
+
+/// Signed area of a closed ring (positive for counter-clockwise).
+pub fn signed_area(points: &[Vec2]) -> f64 {
+    let n = points.len();
+    if n < 3 {
+        return 0.0;
+    }
+    let mut sum = 0.0;
+    for i in 0..n {
+        let a = points[i];
+        let b = points[(i + 1) % n];
+        sum += a.x * b.y - b.x * a.y;
+    }
+    sum / 2.0
+}
+
+/// Offset a single closed polygon.
+///
+/// Positive `delta` grows the polygon, negative `delta` shrinks it,
+/// independent of the input winding. The result may contain zero loops
+/// (the shape vanished), one loop, or several loops (the shape pinched
+/// apart). Output loops keep the winding direction of the input so that
+/// climb/conventional direction choices made by callers are preserved.
+/// Open polylines and rings with fewer than three points yield nothing.
+pub fn offset_polygon(poly: &Polyline, delta: f64, join: JoinType) -> Vec<Polyline> {
+    if !poly.closed {
+        return Vec::new();
+    }
+    let mut ring = clean_ring(&poly.points);
+    if ring.len() < 3 {
+        return Vec::new();
+    }
+    let ccw = signed_area(&ring) > 0.0;
+    if !ccw {
+        ring.reverse();
+    }
+    let mut loops = offset_rings(&[ring], delta, join);
+    if !ccw {
+        for l in &mut loops {
+            l.reverse();
+        }
+    }
+    loops
+        .into_iter()
+        .map(|points| Polyline::new(points, true))
+        .collect()
+}
+
+/// Offset a region described by several closed rings.
+///
+/// Rings must be oriented with the region on their left: outer boundaries
+/// counter-clockwise, holes clockwise. Positive `delta` grows the region
+/// (outer boundaries move out, holes shrink); negative `delta` shrinks it.
+/// The returned rings follow the same orientation convention, so holes that
+/// survive the offset come back clockwise.
+pub fn offset_region(rings: &[Polyline], delta: f64, join: JoinType) -> Vec<Polyline> {
+    let cleaned: Vec<Vec<Vec2>> = rings
+        .iter()
+        .map(|r| clean_ring(&r.points))
+        .filter(|r| r.len() >= 3)
+        .collect();
+    offset_rings(&cleaned, delta, join)
+        .into_iter()
+        .map(|points| Polyline::new(points, true))
+        .collect()
+}
+
+// ── Raw offset ───────────────────────────────────────────────────────
+
+fn offset_rings(rings: &[Vec<Vec2>], delta: f64, join: JoinType) -> Vec<Vec<Vec2>> {
+    if rings.is_empty() {
+        return Vec::new();
+    }
+    if delta.abs() < 1e-12 {
+        return rings.to_vec();
+    }
+    let raw: Vec<Vec<Vec2>> = rings
+        .iter()
+        .map(|r| raw_offset(r, delta, join))
+        .filter(|r| r.len() >= 3)
+        .collect();
+    boolean::positive(&raw)
+}
+
+/// Drop consecutive duplicate points (including the explicit closing point
+/// some importers emit).
+fn clean_ring(points: &[Vec2]) -> Vec<Vec2> {
+    let mut out: Vec<Vec2> = Vec::with_capacity(points.len());
+    for &p in points {
+        if out.last().is_none_or(|&q| Vec2::dist(p, q) > 1e-9) {
+            out.push(p);
+        }
+    }
+    while out.len() > 1 && Vec2::dist(out[0], *out.last().unwrap()) <= 1e-9 {
+        out.pop();
+    }
+    out
+}
+
+/// Displace every edge of a ring to its right by `delta` and stitch the
+/// corners. The result may self-intersect.
+fn raw_offset(ring: &[Vec2], delta: f64, join: JoinType) -> Vec<Vec2> {
+    let n = ring.len();
+    let mut out = Vec::with_capacity(n * 2);
+    for i in 0..n {
+        let prev = ring[(i + n - 1) % n];
+        let v = ring[i];
+        let next = ring[(i + 1) % n];
+        let d0 = unit(sub(v, prev));
+        let d1 = unit(sub(next, v));
+        let a = add(v, scale(right_normal(d0), delta));
+        let b = add(v, scale(right_normal(d1), delta));
+        let cross = cross(d0, d1);
+        let dot = dot(d0, d1);
+
+        if cross.abs() < 1e-12 && dot > 0.0 {
+            // Collinear continuation.
+            out.push(a);
+        } else if cross.abs() < 1e-12 {
+            // Full reversal: cap around the tip in the offset direction.
+            push_join(
+                &mut out,
+                v,
+                a,
+                b,
+                std::f64::consts::PI * delta.signum(),
+                delta,
+                join,
+            );
+        } else if cross * delta > 0.0 {
+            // The offset side is on the outside of this turn: fill the gap.
+            push_join(&mut out, v, a, b, cross.atan2(dot), delta, join);
+        } else {
+            // Inside of the turn: route through the vertex so the loop the
+            // overlap forms has the right winding and gets cleaned up.
+            out.push(a);
+            out.push(v);
+            out.push(b);
+        }
+    }
+    out
+}
+
+/// Emit the corner geometry between offset points `a` and `b` around
+/// vertex `v`, where `sweep` is the signed turn angle.
+fn push_join(
+    out: &mut Vec<Vec2>,
+    v: Vec2,
+    a: Vec2,
+    b: Vec2,
+    sweep: f64,
+    delta: f64,
+    join: JoinType,
+) {
+    match join {
+        JoinType::Round => {
+            // Circumscribe the arc: every segment is tangent to it, so the
+            // path never comes closer than |delta| to the vertex and the
+            // bulge stays within ARC_TOLERANCE. The first and last tangent
+            // points lie on the adjacent offset edges, so `a` and `b` are
+            // not needed.
+            let r = delta.abs();
+            let max_step =
+                (2.0 * (r / (r + ARC_TOLERANCE)).acos()).min(std::f64::consts::FRAC_PI_2);
+            let steps = ((sweep.abs() / max_step).ceil() as usize).max(1);
+            let step = sweep / steps as f64;
+            let reach = r / (step / 2.0).cos();
+            let start = (a.y - v.y).atan2(a.x - v.x);
+            for k in 0..steps {
+                let ang = start + step * (k as f64 + 0.5);
+                out.push(Vec2::new(v.x + reach * ang.cos(), v.y + reach * ang.sin()));
+            }
+        }
+        JoinType::Miter { limit } => {
+            let half = sweep.abs() / 2.0;
+            let dist = if half.cos() > 1e-9 {
+                1.0 / half.cos()
+            } else {
+                f64::INFINITY
+            };
+            if dist <= limit.max(1.0) {
+                let mid = unit(add(sub(a, v), sub(b, v)));
+                out.push(add(v, scale(mid, delta.abs() * dist)));
+            } else {
+                out.push(a);
+                out.push(b);
+            }
+        }
+    }
+}
+
+fn right_normal(d: Vec2) -> Vec2 {
+    Vec2::new(d.y, -d.x)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rect(x0: f64, y0: f64, x1: f64, y1: f64) -> Polyline {
+        Polyline::new(
+            vec![
+                Vec2::new(x0, y0),
+                Vec2::new(x1, y0),
+                Vec2::new(x1, y1),
+                Vec2::new(x0, y1),
+            ],
+            true,
+        )
+    }
+
+    fn area(p: &Polyline) -> f64 {
+        signed_area(&p.points)
+    }
+
+    fn dist_to_boundary(ring: &[Vec2], p: Vec2) -> f64 {
+        let n = ring.len();
+        (0..n)
+            .map(|i| {
+                let (a, b) = (ring[i], ring[(i + 1) % n]);
+                let ab = sub(b, a);
+                let t = (dot(sub(p, a), ab) / dot(ab, ab)).clamp(0.0, 1.0);
+                Vec2::dist(p, add(a, scale(ab, t)))
+            })
+            .fold(f64::INFINITY, f64::min)
+    }
+
+    #[test]
+    fn signed_area_of_ccw_square_is_positive() {
+        assert!((area(&rect(0.0, 0.0, 10.0, 10.0)) - 100.0).abs() < 1e-9);
+        let mut cw = rect(0.0, 0.0, 10.0, 10.0);
+        cw.points.reverse();
+        assert!((area(&cw) + 100.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn inward_offset_of_square_is_smaller_square() {
+        let out = offset_polygon(&rect(0.0, 0.0, 10.0, 10.0), -1.0, JoinType::Round);
+        assert_eq!(out.len(), 1);
+        assert!((area(&out[0]) - 64.0).abs() < 1e-6);
+        let b = out[0].bounds().unwrap();
+        assert!((b.min.x - 1.0).abs() < 1e-9 && (b.max.y - 9.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn outward_round_offset_adds_corner_arcs() {
+        let out = offset_polygon(&rect(0.0, 0.0, 10.0, 10.0), 1.0, JoinType::Round);
+        assert_eq!(out.len(), 1);
+        // 10x10 + four 10x1 strips + a full unit circle.
+        let expected = 100.0 + 40.0 + std::f64::consts::PI;
+        assert!((area(&out[0]) - expected).abs() < 0.05);
+        for p in &out[0].points {
+            let dx = (p.x - p.x.clamp(0.0, 10.0)).abs();
+            let dy = (p.y - p.y.clamp(0.0, 10.0)).abs();
+            let d = (dx * dx + dy * dy).sqrt();
+            assert!(
+                (1.0 - 1e-9..=1.0 + ARC_TOLERANCE + 1e-9).contains(&d),
+                "{}",
+                d
+            );
+        }
+    }
+
+    #[test]
+    fn outward_miter_offset_keeps_sharp_corners() {
+        let out = offset_polygon(
+            &rect(0.0, 0.0, 10.0, 10.0),
+            1.0,
+            JoinType::Miter { limit: 2.0 },
+        );
+        assert_eq!(out.len(), 1);
+        assert!((area(&out[0]) - 144.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn miter_limit_bevels_sharp_spikes() {
+        let spike = Polyline::new(
+            vec![
+                Vec2::new(0.0, 0.0),
+                Vec2::new(20.0, 0.0),
+                Vec2::new(0.0, 1.0),
+            ],
+            true,
+        );
+        let out = offset_polygon(&spike, 1.0, JoinType::Miter { limit: 2.0 });
+        assert_eq!(out.len(), 1);
+        let b = out[0].bounds().unwrap();
+        assert!(b.max.x < 23.0, "spike tip should be bevelled: {}", b.max.x);
+    }
+
+    #[test]
+    fn output_preserves_input_winding() {
+        let mut cw = rect(0.0, 0.0, 10.0, 10.0);
+        cw.points.reverse();
+        let out = offset_polygon(&cw, -1.0, JoinType::Round);
+        assert_eq!(out.len(), 1);
+        assert!(area(&out[0]) < 0.0);
+        let grown = offset_polygon(&cw, 1.0, JoinType::Round);
+        assert!(area(&grown[0]) < -140.0);
+    }
+
+    #[test]
+    fn collapsing_offset_returns_nothing() {
+        let out = offset_polygon(&rect(0.0, 0.0, 10.0, 4.0), -2.5, JoinType::Round);
+        assert!(out.is_empty());
+    }
+
+    #[test]
+    fn dumbbell_pinches_into_two_loops() {
+        // Two 10x10 lobes joined by a 2mm-wide neck.
+        let dumbbell = Polyline::new(
+            vec![
+                Vec2::new(0.0, 0.0),
+                Vec2::new(10.0, 0.0),
+                Vec2::new(10.0, 4.0),
+                Vec2::new(20.0, 4.0),
+                Vec2::new(20.0, 0.0),
+                Vec2::new(30.0, 0.0),
+                Vec2::new(30.0, 10.0),
+                Vec2::new(20.0, 10.0),
+                Vec2::new(20.0, 6.0),
+                Vec2::new(10.0, 6.0),
+                Vec2::new(10.0, 10.0),
+                Vec2::new(0.0, 10.0),
+            ],
+            true,
+        );
+        let out = offset_polygon(&dumbbell, -1.5, JoinType::Round);
+        assert_eq!(out.len(), 2, "neck should pinch off: {:?}", out.len());
+        for l in &out {
+            // 7x7 lobe plus the sliver between the two neck-corner arcs.
+            assert!(area(l) > 49.0 && area(l) < 49.5, "{}", area(l));
+        }
+    }
+
+    #[test]
+    fn narrow_slot_removes_swallow_tail() {
+        // L-shape with a concave corner: the inward offset would fold over
+        // itself without cleanup.
+        let l_shape = Polyline::new(
+            vec![
+                Vec2::new(0.0, 0.0),
+                Vec2::new(20.0, 0.0),
+                Vec2::new(20.0, 3.0),
+                Vec2::new(3.0, 3.0),
+                Vec2::new(3.0, 20.0),
+                Vec2::new(0.0, 20.0),
+            ],
+            true,
+        );
+        let out = offset_polygon(&l_shape, -1.0, JoinType::Round);
+        assert_eq!(out.len(), 1);
+        for p in &out[0].points {
+            let d = dist_to_boundary(&l_shape.points, *p);
+            assert!(
+                (1.0 - 1e-9..=1.0 + ARC_TOLERANCE + 1e-9).contains(&d),
+                "point {:?} is {} from the wall",
+                p,
+                d
+            );
+        }
+        assert!(area(&out[0]) > 0.0);
+    }
+
+    #[test]
+    fn concave_outward_offset_is_simple() {
+        // Outward offset of an L-shape: the inner corner must not leave
+        // a dangling loop.
+        let l_shape = Polyline::new(
+            vec![
+                Vec2::new(0.0, 0.0),
+                Vec2::new(20.0, 0.0),
+                Vec2::new(20.0, 3.0),
+                Vec2::new(3.0, 3.0),
+                Vec2::new(3.0, 20.0),
+                Vec2::new(0.0, 20.0),
+            ],
+            true,
+        );
+        let out = offset_polygon(&l_shape, 1.0, JoinType::Round);
+        assert_eq!(out.len(), 1);
+        assert!(out[0]
+            .points
+            .iter()
+            .any(|p| (p.x - 4.0).abs() < 1e-9 && (p.y - 4.0).abs() < 1e-9));
+    }
+
+    #[test]
+    fn region_offset_shrinks_hole_when_growing() {
+        let outer = rect(0.0, 0.0, 20.0, 20.0);
+        let mut hole = rect(5.0, 5.0, 15.0, 15.0);
+        hole.points.reverse();
+        let out = offset_region(&[outer, hole], -1.0, JoinType::Round);
+        assert_eq!(out.len(), 2);
+        let total: f64 = out.iter().map(area).sum();
+        // Outer 18x18, hole grows to 12x12 plus rounded corners.
+        let expected = 324.0 - (100.0 + 40.0 + std::f64::consts::PI);
+        assert!((total - expected).abs() < 0.05, "{}", total);
+    }
+
+    #[test]
+    fn region_offset_merges_touching_islands() {
+        let a = rect(0.0, 0.0, 10.0, 10.0);
+        let b = rect(11.0, 0.0, 21.0, 10.0);
+        let out = offset_region(&[a, b], 1.0, JoinType::Miter { limit: 2.0 });
+        assert_eq!(out.len(), 1);
+        assert!((area(&out[0]) - 23.0 * 12.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn open_polyline_is_not_offset() {
+        let open = Polyline::new(vec![Vec2::new(0.0, 0.0), Vec2::new(10.0, 0.0)], false);
+        assert!(offset_polygon(&open, 1.0, JoinType::Round).is_empty());
+    }
+
+    #[test]
+    fn duplicate_closing_point_is_ignored() {
+        let mut sq = rect(0.0, 0.0, 10.0, 10.0);
+        sq.points.push(Vec2::new(0.0, 0.0));
+        let out = offset_polygon(&sq, -1.0, JoinType::Round);
+        assert_eq!(out.len(), 1);
+        assert!((area(&out[0]) - 64.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn circle_offset_tracks_radius() {
+        let pts: Vec<Vec2> = (0..64)
+            .map(|i| {
+                let a = std::f64::consts::TAU * i as f64 / 64.0;
+                Vec2::new(5.0 * a.cos(), 5.0 * a.sin())
+            })
+            .collect();
+        let circle = Polyline::new(pts, true);
+        let out = offset_polygon(&circle, -2.0, JoinType::Round);
+        assert_eq!(out.len(), 1);
+        for p in &out[0].points {
+            let r = (p.x * p.x + p.y * p.y).sqrt();
+            assert!(r > 2.9 && r < 3.01, "radius {}", r);
+        }
+    }
+}
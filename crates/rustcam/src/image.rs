@@ -0,0 +1,572 @@
+//! Bitmap import: PNG and BMP decoded to grayscale for raster engraving.
+//!
+//! Swiss-cheese layer: **Geometry Input (raster)**
+//! Extension point: add a decoder to `parse_image` keyed on the file's
+//! magic bytes; it only has to produce luminance per pixel.
+//!
+//! Images come out as a `GrayImage`: luminance from 0 (black) to 1
+//! (white), row-major with the top row first, as the file shows it.
+//! Color is reduced with the Rec. 601 luma weights and transparent pixels
+//! are composited onto white, so they are not engraved. The PNG decoder
+//! takes every color type and bit depth, interlaced or not, but does not
+//! check chunk CRCs; the BMP decoder takes uncompressed 1/4/8/16/24/32-bit
+//! files and bit-field masks, not RLE.
+
+/// Refuse to inflate image data beyond this.
+const MAX_IMAGE_BYTES: usize = 1 << 28;
+/// Refuse images with more pixels than this.
+const MAX_PIXELS: usize = 1 << 26;
+
+/// A grayscale bitmap, 0 = black to 1 = white.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GrayImage {
+    pub width: usize,
+    pub height: usize,
+    /// Row-major, top row first.
+    pub pixels: Vec<f64>,
+}
+
+impl GrayImage {
+    /// Luminance of the pixel in column `x` of row `y` (top row is 0).
+    pub fn get(&self, x: usize, y: usize) -> f64 {
+        self.pixels[y * self.width + x]
+    }
+
+    /// Resample to `width` x `height` with a box filter: every target
+    /// pixel averages the source area it covers, so downsampling a photo
+    /// keeps its tone and upsampling repeats pixels.
+    pub fn resample(&self, width: usize, height: usize) -> GrayImage {
+        let width = width.max(1);
+        let height = height.max(1);
+        let mut rows = Vec::with_capacity(self.height * width);
+        for row in self.pixels.chunks(self.width) {
+            rows.extend(resample_line(row, width));
+        }
+        let mut pixels = vec![0.0; width * height];
+        let mut column = vec![0.0; self.height];
+        for x in 0..width {
+            for (y, v) in column.iter_mut().enumerate() {
+                *v = rows[y * width + x];
+            }
+            for (y, v) in resample_line(&column, height).into_iter().enumerate() {
+                pixels[y * width + x] = v;
+            }
+        }
+        GrayImage {
+            width,
+            height,
+            pixels,
+        }
+    }
+}
+
+/// Box-filter `src` to `n` samples.
+fn resample_line(src: &[f64], n: usize) -> Vec<f64> {
+    let scale = src.len() as f64 / n as f64;
+    (0..n)
+        .map(|i| {
+            let (a, b) = (i as f64 * scale, (i + 1) as f64 * scale);
+            let mut sum = 0.0;
+            let mut k = a.floor() as usize;
+            while (k as f64) < b && k < src.len() {
+                let cover = (b.min(k as f64 + 1.0) - a.max(k as f64)).max(0.0);
+                sum += src[k] * cover;
+                k += 1;
+            }
+            sum / (b - a)
+        })
+        .collect()
+}
+
+/// Decode a PNG or BMP file, detected from its content.
+pub fn parse_image(data: &[u8]) -> Result<GrayImage, String> {
+    if data.starts_with(PNG_SIGNATURE) {
+        parse_png(data)
+    } else if data.starts_with(b"BM") {
+        parse_bmp(data)
+    } else {
+        Err("Unsupported image format: expected PNG or BMP".into())
+    }
+}
+
+fn check_size(width: usize, height: usize) -> Result<(), String> {
+    if width == 0 || height == 0 {
+        return Err("Image has no pixels".into());
+    }
+    if width.saturating_mul(height) > MAX_PIXELS {
+        return Err(format!("Image is too large ({}x{})", width, height));
+    }
+    Ok(())
+}
+
+/// Luma of an RGB color composited with `alpha` onto white, all in 0..1.
+fn luma(r: f64, g: f64, b: f64, alpha: f64) -> f64 {
+    // In thousandths, so that white stays exactly 1.
+    let y = (299.0 * r + 587.0 * g + 114.0 * b) / 1000.0;
+    y * alpha + (1.0 - alpha)
+}
+
+fn be_u32(data: &[u8], at: usize) -> u32 {
+    u32::from_be_bytes([data[at], data[at + 1], data[at + 2], data[at + 3]])
+}
+
+fn le_u16(data: &[u8], at: usize) -> u16 {
+    u16::from_le_bytes([data[at], data[at + 1]])
+}
+
+fn le_u32(data: &[u8], at: usize) -> u32 {
+    u32::from_le_bytes([data[at], data[at + 1], data[at + 2], data[at + 3]])
+}
+
+// ── PNG ─────────────────────────────────────────────────────────────
+
+const PNG_SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+
+/// Adam7 passes: first column, first row, column step, row step.
+const ADAM7: [(usize, usize, usize, usize); 7] = [
+    (0, 0, 8, 8),
+    (4, 0, 8, 8),
+    (0, 4, 4, 8),
+    (2, 0, 4, 4),
+    (0, 2, 2, 4),
+    (1, 0, 2, 2),
+    (0, 1, 1, 2),
+];
+
+/// What IHDR, PLTE and tRNS say about the samples.
+struct PngFormat {
+    color_type: u8,
+    depth: usize,
+    palette: Vec<[u8; 3]>,
+    /// tRNS: per palette entry alpha, or the one transparent gray/RGB.
+    transparency: Vec<u8>,
+}
+
+impl PngFormat {
+    fn channels(&self) -> usize {
+        match self.color_type {
+            0 | 3 => 1,
+            2 => 3,
+            4 => 2,
+            _ => 4,
+        }
+    }
+
+    /// Luminance of pixel `x` of an unfiltered scanline.
+    fn gray(&self, line: &[u8], x: usize) -> Result<f64, String> {
+        let n = self.channels();
+        let max = ((1u32 << self.depth) - 1) as f64;
+        let s = |c: usize| png_sample(line, x * n + c, self.depth);
+        let key = |c: usize| {
+            self.transparency
+                .get(2 * c..2 * c + 2)
+                .map(|b| u16::from_be_bytes([b[0], b[1]]))
+        };
+        Ok(match self.color_type {
+            0 => {
+                let v = s(0);
+                let alpha = if key(0) == Some(v) { 0.0 } else { 1.0 };
+                luma(v as f64 / max, v as f64 / max, v as f64 / max, alpha)
+            }
+            2 => {
+                let (r, g, b) = (s(0), s(1), s(2));
+                let keyed = key(0) == Some(r) && key(1) == Some(g) && key(2) == Some(b);
+                let alpha = if keyed { 0.0 } else { 1.0 };
+                luma(r as f64 / max, g as f64 / max, b as f64 / max, alpha)
+            }
+            3 => {
+                let i = s(0) as usize;
+                let [r, g, b] = *self
+                    .palette
+                    .get(i)
+                    .ok_or_else(|| format!("PNG: palette index {} out of range", i))?;
+                let alpha = self.transparency.get(i).copied().unwrap_or(255);
+                luma(
+                    r as f64 / 255.0,
+                    g as f64 / 255.0,
+                    b as f64 / 255.0,
+                    alpha as f64 / 255.0,
+                )
+            }
+            4 => {
+                let v = s(0) as f64 / max;
+                luma(v, v, v, s(1) as f64 / max)
+            }
+            _ => luma(
+                s(0) as f64 / max,
+                s(1) as f64 / max,
+                s(2) as f64 / max,
+                s(3) as f64 / max,
+            ),
+        })
+    }
+}
+
+/// Sample `index` of a scanline packed at `depth` bits.
+fn png_sample(line: &[u8], index: usize, depth: usize) -> u16 {
+    match depth {
+        16 => u16::from_be_bytes([line[2 * index], line[2 * index + 1]]),
+        8 => line[index] as u16,
+        _ => {
+            let bit = index * depth;
+            let shift = 8 - depth - bit % 8;
+            ((line[bit / 8] >> shift) as u16) & ((1 << depth) - 1)
+        }
+    }
+}
+
+fn parse_png(data: &[u8]) -> Result<GrayImage, String> {
+    let mut pos = PNG_SIGNATURE.len();
+    let mut header = None;
+    let mut palette = Vec::new();
+    let mut transparency = Vec::new();
+    let mut compressed = Vec::new();
+    while pos + 8 <= data.len() {
+        let len = be_u32(data, pos) as usize;
+        let kind = &data[pos + 4..pos + 8];
+        // A forged length must not wrap around on 32-bit targets.
+        let end = (pos + 8).checked_add(len).ok_or("PNG: truncated chunk")?;
+        let body = data.get(pos + 8..end).ok_or("PNG: truncated chunk")?;
+        match kind {
+            b"IHDR" if body.len() >= 13 => header = Some(body.to_vec()),
+            b"PLTE" => palette = body.chunks_exact(3).map(|c| [c[0], c[1], c[2]]).collect(),
+            b"tRNS" => transparency = body.to_vec(),
+            b"IDAT" => compressed.extend_from_slice(body),
+            b"IEND" => break,
+            _ => {}
+        }
+        // Length, type, body and CRC.
+        pos = end + 4;
+    }
+    let header = header.ok_or("PNG: missing IHDR")?;
+    let width = be_u32(&header, 0) as usize;
+    let height = be_u32(&header, 4) as usize;
+    check_size(width, height)?;
+    let format = PngFormat {
+        color_type: header[9],
+        depth: header[8] as usize,
+        palette,
+        transparency,
+    };
+    let depth_ok = match format.color_type {
+        0 => matches!(format.depth, 1 | 2 | 4 | 8 | 16),
+        3 => matches!(format.depth, 1 | 2 | 4 | 8),
+        2 | 4 | 6 => matches!(format.depth, 8 | 16),
+        _ => false,
+    };
+    if !depth_ok {
+        return Err(format!(
+            "PNG: unsupported color type {} at {} bits",
+            format.color_type, format.depth
+        ));
+    }
+    let raw = miniz_oxide::inflate::decompress_to_vec_zlib_with_limit(&compressed, MAX_IMAGE_BYTES)
+        .map_err(|e| format!("PNG: bad image data ({:?})", e.status))?;
+
+    let bits = format.channels() * format.depth;
+    // Filters work on whole bytes: the byte `bpp` back is the same
+    // channel of the previous pixel.
+    let bpp = bits.div_ceil(8);
+    let passes: &[(usize, usize, usize, usize)] = match header[12] {
+        0 => &[(0, 0, 1, 1)],
+        1 => &ADAM7,
+        m => return Err(format!("PNG: unknown interlace method {}", m)),
+    };
+    let mut pixels = vec![1.0; width * height];
+    let mut pos = 0;
+    for &(x0, y0, dx, dy) in passes {
+        if x0 >= width || y0 >= height {
+            continue;
+        }
+        let (w, h) = ((width - x0).div_ceil(dx), (height - y0).div_ceil(dy));
+        let stride = (w * bits).div_ceil(8);
+        let mut prev = vec![0u8; stride];
+        for row in 0..h {
+            let filter = *raw.get(pos).ok_or("PNG: image data is short")?;
+            let mut line = raw
+                .get(pos + 1..pos + 1 + stride)
+                .ok_or("PNG: image data is short")?
+                .to_vec();
+            unfilter(filter, &mut line, &prev, bpp)?;
+            for col in 0..w {
+                pixels[(y0 + row * dy) * width + x0 + col * dx] = format.gray(&line, col)?;
+            }
+            prev = line;
+            pos += 1 + stride;
+        }
+    }
+    Ok(GrayImage {
+        width,
+        height,
+        pixels,
+    })
+}
+
+/// Undo a PNG scanline filter in place, given the previous unfiltered
+/// line of the same pass.
+fn unfilter(filter: u8, line: &mut [u8], prev: &[u8], bpp: usize) -> Result<(), String> {
+    for i in 0..line.len() {
+        let a = if i >= bpp { line[i - bpp] } else { 0 };
+        let b = prev[i];
+        let c = if i >= bpp { prev[i - bpp] } else { 0 };
+        let predict = match filter {
+            0 => 0,
+            1 => a,
+            2 => b,
+            3 => ((a as u16 + b as u16) / 2) as u8,
+            4 => paeth(a, b, c),
+            f => return Err(format!("PNG: unknown filter type {}", f)),
+        };
+        line[i] = line[i].wrapping_add(predict);
+    }
+    Ok(())
+}
+
+fn paeth(a: u8, b: u8, c: u8) -> u8 {
+    let p = a as i16 + b as i16 - c as i16;
+    let (pa, pb, pc) = (
+        (p - a as i16).abs(),
+        (p - b as i16).abs(),
+        (p - c as i16).abs(),
+    );
+    if pa <= pb && pa <= pc {
+        a
+    } else if pb <= pc {
+        b
+    } else {
+        c
+    }
+}
+
+// ── BMP ─────────────────────────────────────────────────────────────
+
+/// One channel of a bit-field pixel: mask and the shift to its low bit.
+#[derive(Clone, Copy)]
+struct Field {
+    mask: u32,
+    shift: u32,
+}
+
+impl Field {
+    fn new(mask: u32) -> Self {
+        Self {
+            mask,
+            shift: if mask == 0 { 0 } else { mask.trailing_zeros() },
+        }
+    }
+
+    /// The channel of `pixel` scaled to 0..1, or `None` without a mask.
+    fn get(self, pixel: u32) -> Option<f64> {
+        if self.mask == 0 {
+            return None;
+        }
+        let max = self.mask >> self.shift;
+        Some(((pixel & self.mask) >> self.shift) as f64 / max as f64)
+    }
+}
+
+fn parse_bmp(data: &[u8]) -> Result<GrayImage, String> {
+    if data.len() < 54 {
+        return Err("BMP: truncated header".into());
+    }
+    let offset = le_u32(data, 10) as usize;
+    let dib = le_u32(data, 14) as usize;
+    if dib < 40 {
+        return Err("BMP: OS/2 bitmaps are not supported".into());
+    }
+    let width = le_u32(data, 18) as i32;
+    let height = le_u32(data, 22) as i32;
+    let bpp = le_u16(data, 28) as usize;
+    let compression = le_u32(data, 30);
+    let top_down = height < 0;
+    let (width, height) = (
+        width.unsigned_abs() as usize,
+        height.unsigned_abs() as usize,
+    );
+    check_size(width, height)?;
+
+    // Bit-field masks follow a 40-byte header, or sit inside a larger one.
+    let fields = match (compression, bpp) {
+        (3, 16 | 32) | (6, 16 | 32) => {
+            let mask = |i: usize| {
+                data.get(54 + 4 * i..58 + 4 * i)
+                    .map_or(0, |_| le_u32(data, 54 + 4 * i))
+            };
+            let alpha = if dib >= 56 || compression == 6 {
+                mask(3)
+            } else {
+                0
+            };
+            [mask(0), mask(1), mask(2), alpha].map(Field::new)
+        }
+        (0, 16) => [0x7c00, 0x03e0, 0x001f, 0].map(Field::new),
+        (0, 32) => [0x00ff_0000, 0x0000_ff00, 0x0000_00ff, 0].map(Field::new),
+        (0, 1 | 4 | 8 | 24) => [0; 4].map(Field::new),
+        _ => {
+            return Err(format!(
+                "BMP: unsupported {}-bit bitmap with compression {}",
+                bpp, compression
+            ))
+        }
+    };
+    let palette: Vec<f64> = if bpp <= 8 {
+        let used = le_u32(data, 46) as usize;
+        let count = if used == 0 {
+            1 << bpp
+        } else {
+            used.min(1 << bpp)
+        };
+        let start = dib.saturating_add(14);
+        data.get(start..start.saturating_add(4 * count))
+            .ok_or("BMP: truncated palette")?
+            .chunks_exact(4)
+            .map(|c| {
+                luma(
+                    c[2] as f64 / 255.0,
+                    c[1] as f64 / 255.0,
+                    c[0] as f64 / 255.0,
+                    1.0,
+                )
+            })
+            .collect()
+    } else {
+        Vec::new()
+    };
+
+    let stride = (width * bpp).div_ceil(32) * 4;
+    let mut pixels = vec![1.0; width * height];
+    for row in 0..height {
+        let line = row
+            .checked_mul(stride)
+            .and_then(|at| at.checked_add(offset))
+            .and_then(|start| data.get(start..start.checked_add(stride)?))
+            .ok_or("BMP: pixel data is short")?;
+        let y = if top_down { row } else { height - 1 - row };
+        for x in 0..width {
+            pixels[y * width + x] = match bpp {
+                24 => luma(
+                    line[3 * x + 2] as f64 / 255.0,
+                    line[3 * x + 1] as f64 / 255.0,
+                    line[3 * x] as f64 / 255.0,
+                    1.0,
+                ),
+                16 | 32 => {
+                    let pixel = if bpp == 16 {
+                        le_u16(line, 2 * x) as u32
+                    } else {
+                        le_u32(line, 4 * x)
+                    };
+                    let [r, g, b, a] = fields.map(|f| f.get(pixel));
+                    let c = |v: Option<f64>| v.unwrap_or(0.0);
+                    luma(c(r), c(g), c(b), a.unwrap_or(1.0))
+                }
+                _ => {
+                    let i = png_sample(line, x, bpp) as usize;
+                    *palette
+                        .get(i)
+                        .ok_or_else(|| format!("BMP: palette index {} out of range", i))?
+                }
+            };
+        }
+    }
+    Ok(GrayImage {
+        width,
+        height,
+        pixels,
+    })
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// A PNG with the given IHDR fields and filtered scanlines.
+    pub(crate) fn png(width: u32, height: u32, depth: u8, color: u8, lines: &[u8]) -> Vec<u8> {
+        let chunk = |out: &mut Vec<u8>, kind: &[u8], body: &[u8]| {
+            out.extend((body.len() as u32).to_be_bytes());
+            out.extend(kind);
+            out.extend(body);
+            out.extend([0; 4]);
+        };
+        let mut ihdr = Vec::new();
+        ihdr.extend(width.to_be_bytes());
+        ihdr.extend(height.to_be_bytes());
+        ihdr.extend([depth, color, 0, 0, 0]);
+        let mut out = PNG_SIGNATURE.to_vec();
+        chunk(&mut out, b"IHDR", &ihdr);
+        chunk(
+            &mut out,
+            b"IDAT",
+            &miniz_oxide::deflate::compress_to_vec_zlib(lines, 6),
+        );
+        chunk(&mut out, b"IEND", &[]);
+        out
+    }
+
+    #[test]
+    fn png_filters_and_color_types_decode() {
+        // 8-bit gray, Sub then Up filtered: 0 255 / 0 255.
+        let gray = parse_image(&png(2, 2, 8, 0, &[1, 0, 255, 2, 0, 0])).unwrap();
+        assert_eq!(gray.pixels, vec![0.0, 1.0, 0.0, 1.0]);
+
+        // RGBA: opaque red, then fully transparent black over white.
+        let rgba = parse_image(&png(2, 1, 8, 6, &[0, 255, 0, 0, 255, 0, 0, 0, 0])).unwrap();
+        assert!((rgba.pixels[0] - 0.299).abs() < 1e-9);
+        assert_eq!(rgba.pixels[1], 1.0);
+
+        // 1-bit gray packs eight pixels per byte, most significant first.
+        let bits = parse_image(&png(10, 1, 1, 0, &[0, 0b1010_0000, 0b0100_0000])).unwrap();
+        let on: Vec<usize> = (0..10).filter(|&x| bits.get(x, 0) == 1.0).collect();
+        assert_eq!(on, vec![0, 2, 9]);
+    }
+
+    #[test]
+    fn bmp_rows_run_bottom_up() {
+        // 2x2 24-bit: bottom row white, top row black then blue.
+        let mut bmp = b"BM".to_vec();
+        bmp.extend((54u32 + 16).to_le_bytes());
+        bmp.extend([0; 4]);
+        bmp.extend(54u32.to_le_bytes());
+        bmp.extend(40u32.to_le_bytes());
+        bmp.extend(2i32.to_le_bytes());
+        bmp.extend(2i32.to_le_bytes());
+        bmp.extend(1u16.to_le_bytes());
+        bmp.extend(24u16.to_le_bytes());
+        bmp.extend([0; 24]);
+        bmp.extend([255, 255, 255, 255, 255, 255, 0, 0]);
+        bmp.extend([0, 0, 0, 255, 0, 0, 0, 0]);
+        let img = parse_image(&bmp).unwrap();
+        assert_eq!((img.width, img.height), (2, 2));
+        assert_eq!(img.get(0, 0), 0.0);
+        assert!((img.get(1, 0) - 0.114).abs() < 1e-9);
+        assert_eq!(img.get(0, 1), 1.0);
+        assert!(parse_image(b"GIF89a").is_err());
+
+        // Pixel data claimed to start near the end of the address space.
+        bmp[10..14].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert_eq!(parse_image(&bmp).unwrap_err(), "BMP: pixel data is short");
+    }
+
+    #[test]
+    fn forged_png_chunk_length_is_truncated() {
+        let mut data = png(1, 1, 8, 0, &[0, 0]);
+        // The IDAT length, right after the 25-byte IHDR chunk.
+        let at = PNG_SIGNATURE.len() + 25;
+        data[at..at + 4].copy_from_slice(&u32::MAX.to_be_bytes());
+        assert_eq!(parse_image(&data).unwrap_err(), "PNG: truncated chunk");
+    }
+
+    #[test]
+    fn resample_keeps_mean_tone() {
+        let img = GrayImage {
+            width: 4,
+            height: 1,
+            pixels: vec![0.0, 1.0, 0.0, 1.0],
+        };
+        let half = img.resample(2, 1);
+        assert_eq!(half.pixels, vec![0.5, 0.5]);
+        let up = img.resample(8, 2);
+        assert_eq!((up.width, up.height), (8, 2));
+        assert_eq!(&up.pixels[..4], &[0.0, 0.0, 1.0, 1.0]);
+    }
+}
@@ -0,0 +1,264 @@
+//! Multi-operation jobs and the tool library.
+//!
+//! Swiss-cheese layer: **Job planning**
+//! Extension point: add per-tool defaults (feeds, speeds) to `ToolEntry`,
+//! or operation kinds that are not `CamConfig` strategies.
+//!
+//! A `Job` is an ordered list of operations run on one input. Each
+//! operation names its tool by T number in the job's `ToolLibrary` and
+//! carries its own strategy and cut parameters as a flattened `CamConfig`.
+//! The library is plain JSON, so the frontend can keep it between sessions.
+
+use std::collections::BTreeMap;
+
+use crate::setup::Setup;
+use crate::tool::{Tool, ToolType};
+use crate::CamConfig;
+use serde::{Deserialize, Serialize};
+
+/// A tool in the library.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ToolEntry {
+    #[serde(default)]
+    pub name: String,
+    pub tool: Tool,
+}
+
+/// Tools keyed by T number (which is also the length-offset register).
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct ToolLibrary {
+    pub tools: BTreeMap<u32, ToolEntry>,
+}
+
+impl ToolLibrary {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add or replace tool `number`.
+    pub fn insert(&mut self, number: u32, name: &str, tool: Tool) {
+        self.tools.insert(
+            number,
+            ToolEntry {
+                name: name.into(),
+                tool,
+            },
+        );
+    }
+
+    pub fn get(&self, number: u32) -> Option<&ToolEntry> {
+        self.tools.get(&number)
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, String> {
+        serde_json::from_str(json).map_err(|e| e.to_string())
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(&self).unwrap_or_else(|_| "{}".into())
+    }
+}
+
+/// One step of a job. Everything except `name` and `tool` is read as a
+/// `CamConfig`; its tool fields are replaced by the library tool.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Operation {
+    #[serde(default)]
+    pub name: String,
+    /// T number in the job's tool library.
+    pub tool: u32,
+    /// Cut only where the earlier operations left material.
+    #[serde(default)]
+    pub rest_machining: bool,
+    #[serde(flatten)]
+    pub config: CamConfig,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Job {
+    #[serde(default)]
+    pub name: String,
+    #[serde(default)]
+    pub tools: ToolLibrary,
+    pub operations: Vec<Operation>,
+    /// Built-in controller dialect (see `CamConfig::post_processor`).
+    #[serde(default)]
+    pub post_processor: Option<String>,
+    /// User-defined post-processor as JSON or TOML.
+    #[serde(default)]
+    pub custom_post: Option<String>,
+    /// Stock and work origin shared by every operation.
+    #[serde(default)]
+    pub setup: Option<Setup>,
+}
+
+impl Job {
+    pub fn from_json(json: &str) -> Result<Self, String> {
+        serde_json::from_str(json).map_err(|e| e.to_string())
+    }
+
+    /// Check that the job has operations and every tool they name exists.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.operations.is_empty() {
+            return Err("Job has no operations".into());
+        }
+        for (i, op) in self.operations.iter().enumerate() {
+            if op.tool == 0 {
+                return Err(format!("Operation {}: tool numbers start at T1", i + 1));
+            }
+            if self.tools.get(op.tool).is_none() {
+                return Err(format!(
+                    "Operation {}: tool T{} is not in the tool library",
+                    i + 1,
+                    op.tool
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// The config operation `index` runs with: its own settings, the
+    /// library tool and the job's machine and post-processor.
+    pub fn operation_config(&self, index: usize) -> Result<CamConfig, String> {
+        let op = &self.operations[index];
+        let entry = self
+            .tools
+            .get(op.tool)
+            .ok_or_else(|| format!("Tool T{} is not in the tool library", op.tool))?;
+        let tool = &entry.tool;
+        let mut config = op.config.clone();
+        config.machine_type = "cnc_mill".into();
+        config.tool_diameter = tool.diameter;
+        config.corner_radius = tool.corner_radius;
+        config.effective_diameter = None;
+        config.tool_type = match &tool.tool_type {
+            ToolType::EndMill => "end_mill".into(),
+            ToolType::BallEnd => "ball_end".into(),
+            ToolType::FaceMill { effective_diameter } => {
+                config.effective_diameter = Some(*effective_diameter);
+                "face_mill".into()
+            }
+            ToolType::Drill {
+                tip_angle,
+                tip_compensation,
+            } => {
+                config.tip_angle = *tip_angle;
+                config.tip_compensation = *tip_compensation;
+                "drill".into()
+            }
+            ToolType::VBit {
+                angle,
+                tip_diameter,
+            } => {
+                config.vbit_angle = *angle;
+                config.tip_diameter = *tip_diameter;
+                "vbit".into()
+            }
+        };
+        config.post_processor = self.post_processor.clone();
+        config.custom_post = self.custom_post.clone();
+        config.setup = self.setup.clone();
+        Ok(config)
+    }
+
+    /// Comment lines introducing operation `index` in the program.
+    pub fn operation_header(&self, index: usize) -> Vec<String> {
+        let op = &self.operations[index];
+        let name = if op.name.is_empty() {
+            op.config.strategy.as_str()
+        } else {
+            op.name.as_str()
+        };
+        let mut lines = vec![format!("Operation {}: {}", index + 1, name)];
+        if let Some(entry) = self.tools.get(op.tool) {
+            let label = if entry.name.is_empty() {
+                String::new()
+            } else {
+                format!(" {}", entry.name)
+            };
+            lines.push(format!("T{}{} D{:.3}", op.tool, label, entry.tool.diameter));
+        }
+        lines.push(format!(
+            "Strategy {}, depth {:.3}, feed {:.0}, {:.0} RPM",
+            op.config.strategy, op.config.cut_depth, op.config.feed_rate, op.config.spindle_speed
+        ));
+        if op.rest_machining {
+            lines.push("Rest machining after the earlier operations".into());
+        }
+        lines
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn job_json() -> &'static str {
+        r#"{
+            "name": "Bracket",
+            "tools": {
+                "1": {"name": "6mm flat", "tool": {"tool_type": {"type": "end_mill"}, "diameter": 6.0, "flute_length": 20.0, "corner_radius": 0.0}},
+                "4": {"name": "3mm ball", "tool": {"tool_type": {"type": "ball_end"}, "diameter": 3.0, "flute_length": 12.0, "corner_radius": 1.5}}
+            },
+            "operations": [
+                {"name": "Rough", "tool": 1, "strategy": "pocket", "cut_depth": -3.0},
+                {"name": "Finish", "tool": 4, "strategy": "contour", "spindle_speed": 18000}
+            ]
+        }"#
+    }
+
+    #[test]
+    fn parses_job_and_library() {
+        let job = Job::from_json(job_json()).unwrap();
+        assert_eq!(job.tools.tools.len(), 2);
+        assert_eq!(job.operations.len(), 2);
+        assert_eq!(job.operations[0].config.strategy, "pocket");
+        assert_eq!(job.operations[1].config.spindle_speed, 18000.0);
+        // Unlisted settings take the CamConfig defaults.
+        assert_eq!(job.operations[1].config.cut_depth, -1.0);
+        assert!(job.validate().is_ok());
+    }
+
+    #[test]
+    fn operation_config_uses_library_tool() {
+        let job = Job::from_json(job_json()).unwrap();
+        let rough = job.operation_config(0).unwrap();
+        assert_eq!(rough.tool_diameter, 6.0);
+        assert_eq!(rough.tool_type, "end_mill");
+        let finish = job.operation_config(1).unwrap();
+        assert_eq!(finish.tool_diameter, 3.0);
+        assert_eq!(finish.tool_type, "ball_end");
+        assert_eq!(finish.machine_type, "cnc_mill");
+    }
+
+    #[test]
+    fn validate_rejects_missing_tools() {
+        let mut job = Job::from_json(job_json()).unwrap();
+        job.operations[1].tool = 7;
+        let err = job.validate().unwrap_err();
+        assert!(err.contains("T7"), "{err}");
+
+        job.operations.clear();
+        assert!(job.validate().is_err());
+    }
+
+    #[test]
+    fn library_round_trips_through_json() {
+        let mut lib = ToolLibrary::new();
+        lib.insert(1, "6mm flat", Tool::new(ToolType::EndMill, 6.0, 20.0, 0.0));
+        lib.insert(2, "50mm face", Tool::face_mill(50.0, 40.0, 5.0));
+        let json = lib.to_json();
+        assert!(json.starts_with("{\"1\":"), "{json}");
+        assert_eq!(ToolLibrary::from_json(&json).unwrap(), lib);
+    }
+
+    #[test]
+    fn header_names_operation_and_tool() {
+        let job = Job::from_json(job_json()).unwrap();
+        let header = job.operation_header(1);
+        assert_eq!(header[0], "Operation 2: Finish");
+        assert_eq!(header[1], "T4 3mm ball D3.000");
+        assert!(header[2].contains("18000 RPM"));
+    }
+}
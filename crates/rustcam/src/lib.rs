@@ -19,28 +19,50 @@
 //! Each layer is a trait / module boundary. Add new formats or strategies
 //! without touching existing code.
 
+pub mod cycle_time;
+pub mod dxf;
 pub mod gcode;
+pub mod gcode_interpreter;
 pub mod gcode_parser;
 pub mod geometry;
+pub mod image;
+pub mod job;
 pub mod machine;
+pub mod mesh_import;
+pub mod obj;
+pub mod ply;
+pub mod setup;
+pub mod simulation;
 pub mod sketch_actor;
 pub mod slicer;
 pub mod stl;
 pub mod svg;
+pub mod text;
+pub mod threemf;
 pub mod tool;
 pub mod toolpath;
 pub mod units;
 #[cfg(target_arch = "wasm32")]
 mod wasm_api;
 
-use gcode::{emit_gcode_with_profile, GcodeParams, LaserParams};
+use cycle_time::MotionLimits;
+use gcode::{
+    emit_gcode_with_profile, emit_job_gcode, GcodeParams, JobSection, LaserParams, PostFormat,
+};
+use gcode_interpreter::InterpreterConfig;
 use geometry::Toolpath;
 use machine::{MachineProfile, MachineType};
+use mesh_import::MeshFormat;
 use serde::{Deserialize, Serialize};
+use setup::{Placement, Setup};
+use simulation::{SimParams, Stock};
 use tool::Tool;
 use toolpath::{
-    ContourStrategy, CutParams, LaserCutStrategy, LaserEngraveStrategy, Pattern, PerimeterStrategy,
-    PocketStrategy, ScanDirection, Surface3dStrategy, SurfaceParams, ToolpathStrategy,
+    raster_engrave, AdaptiveStrategy, ContourStrategy, CutParams, DrillParams, DrillStrategy,
+    EntryMode, LaserCutStrategy, LaserEngraveStrategy, Lead, LeadParams, OffsetPocketStrategy,
+    OrderParams, Pattern, PerimeterStrategy, PocketStrategy, PriorCut, RapidReport, RasterParams,
+    RestStock, ScanDirection, Surface3dStrategy, SurfaceParams, TabParams, ToolpathStrategy,
+    VCarveStrategy, WaterlineStrategy,
 };
 
 // ── Public parameter struct (JSON from JS) ───────────────────────────
@@ -75,6 +97,104 @@ pub struct CamConfig {
     pub climb_cut: bool,
     #[serde(default = "default_perimeter_passes")]
     pub perimeter_passes: u32,
+    /// Adaptive clearing: largest radial engagement as a fraction of the
+    /// tool diameter.
+    #[serde(default = "default_max_engagement")]
+    pub max_engagement: f64,
+    /// Waterline: shallowest wall, in degrees from horizontal, that gets
+    /// passes (0 machines every wall).
+    #[serde(default)]
+    pub slope_limit: f64,
+    /// How the tool enters the material: `"plunge"` (default), `"ramp"`
+    /// or `"helix"`.
+    #[serde(default = "default_entry_mode")]
+    pub entry_mode: String,
+    /// Steepest ramp or helix descent, degrees from horizontal.
+    #[serde(default = "default_ramp_angle")]
+    pub ramp_angle: f64,
+    /// Helix diameter (tool-centre path). Defaults to the tool diameter.
+    #[serde(default)]
+    pub helix_diameter: Option<f64>,
+    /// How contour and perimeter cuts join a closed profile: `"none"`
+    /// (default), `"line"` or `"arc"`.
+    #[serde(default = "default_lead")]
+    pub lead_in: String,
+    /// How they leave it, with the same choices.
+    #[serde(default = "default_lead")]
+    pub lead_out: String,
+    /// Lead line length or arc radius. Defaults to the tool diameter.
+    #[serde(default)]
+    pub lead_radius: Option<f64>,
+    /// Who offsets profiles by the tool radius: `"computer"` (default) or
+    /// `"controller"`, which cuts the nominal outline under G41/G42.
+    #[serde(default = "default_compensation")]
+    pub compensation: String,
+    /// Holding tabs per closed profile (0 = use `tab_spacing`).
+    #[serde(default)]
+    pub tab_count: u32,
+    /// Distance between tabs along a profile when `tab_count` is 0
+    /// (0 = no tabs).
+    #[serde(default)]
+    pub tab_spacing: f64,
+    #[serde(default = "default_tab_width")]
+    pub tab_width: f64,
+    /// Tab height above the bottom of the cut.
+    #[serde(default = "default_tab_height")]
+    pub tab_height: f64,
+    /// Drilling: largest circle diameter drilled as a hole.
+    #[serde(default = "default_drill_max_diameter")]
+    pub drill_max_diameter: f64,
+    /// Drilling: Z of the retract (R) plane above each hole.
+    #[serde(default = "default_drill_retract")]
+    pub drill_retract: f64,
+    /// Drilling: depth per peck (0 = no pecking).
+    #[serde(default)]
+    pub peck_depth: f64,
+    /// Drilling: dwell at the bottom of each hole in seconds.
+    #[serde(default)]
+    pub dwell: f64,
+    /// Drill point angle in degrees (`tool_type` `"drill"`).
+    #[serde(default = "default_tip_angle")]
+    pub tip_angle: f64,
+    /// Drill deeper by the point length so the full diameter reaches
+    /// `cut_depth`.
+    #[serde(default)]
+    pub tip_compensation: bool,
+    /// V-bit included angle in degrees (`tool_type` `"vbit"`).
+    #[serde(default = "default_vbit_angle")]
+    pub vbit_angle: f64,
+    /// V-bit flat tip diameter (0 = sharp point).
+    #[serde(default)]
+    pub tip_diameter: f64,
+    /// V-carve: pocket the flat bottom where a region is wider than the
+    /// bit cuts at `cut_depth`.
+    #[serde(default)]
+    pub flat_bottom: bool,
+    /// Chord tolerance (mm) for emitting curves as G2/G3 arcs
+    /// (0 = G1 only).
+    #[serde(default = "default_arc_tolerance")]
+    pub arc_tolerance: f64,
+    /// Re-sequence the toolpaths of each pass to shorten the rapids,
+    /// cutting anything inside a closed profile before the profile.
+    #[serde(default)]
+    pub optimize_order: bool,
+    /// With `optimize_order`, start closed contour and laser-cut loops at
+    /// the point nearest the tool.
+    #[serde(default)]
+    pub optimize_start_points: bool,
+    /// Stock, model placement and work origin. Without one, the input's
+    /// own coordinates are the work frame.
+    #[serde(default)]
+    pub setup: Option<Setup>,
+    /// Stock simulation: heightmap cell size (mm).
+    #[serde(default = "default_sim_resolution")]
+    pub sim_resolution: f64,
+    /// Stock simulation: cuts this far below the model are not gouges.
+    #[serde(default = "default_gouge_tolerance")]
+    pub gouge_tolerance: f64,
+    /// Stock simulation: stock this far above the model is not leftover.
+    #[serde(default = "default_leftover_tolerance")]
+    pub leftover_tolerance: f64,
     #[serde(default = "default_scan_direction")]
     pub scan_direction: String,
     /// Surface-strategy traversal pattern: `"zigzag"` (default), `"one_way"`,
@@ -89,6 +209,35 @@ pub struct CamConfig {
     pub passes: Option<u32>,
     #[serde(default)]
     pub air_assist: Option<bool>,
+    /// Built-in controller dialect: `"generic"`, `"grbl"`, `"linuxcnc"`,
+    /// `"mach3"` or `"marlin"`. Defaults to the machine profile's.
+    #[serde(default)]
+    pub post_processor: Option<String>,
+    /// User-defined post-processor as JSON or TOML. Overrides
+    /// `post_processor`.
+    #[serde(default)]
+    pub custom_post: Option<String>,
+    /// Cycle time: per-axis top speed (mm/min) instead of the profile's.
+    #[serde(default)]
+    pub max_velocity: Option<[f64; 3]>,
+    /// Cycle time: per-axis acceleration (mm/s²) instead of the profile's.
+    #[serde(default)]
+    pub max_acceleration: Option<[f64; 3]>,
+    /// Cycle time: junction deviation (mm) instead of the profile's.
+    #[serde(default)]
+    pub junction_deviation: Option<f64>,
+    /// DXF input: layers to import (any case). Empty imports every layer.
+    #[serde(default)]
+    pub layers: Vec<String>,
+    /// SVG input: stroke or fill colors to import (any CSS form). Empty
+    /// imports every shape.
+    #[serde(default)]
+    pub colors: Vec<String>,
+    /// SVG input: what a unitless root size or a viewBox-only root
+    /// measures, `"px"` (CSS pixels, the SVG standard's reading and the
+    /// default) or `"mm"` (one user unit per millimetre).
+    #[serde(default = "default_svg_units")]
+    pub svg_units: String,
 }
 
 fn default_tool_diameter() -> f64 {
@@ -100,6 +249,54 @@ fn default_tool_type() -> String {
 fn default_perimeter_passes() -> u32 {
     1
 }
+fn default_max_engagement() -> f64 {
+    0.2
+}
+fn default_entry_mode() -> String {
+    "plunge".into()
+}
+fn default_lead() -> String {
+    "none".into()
+}
+fn default_compensation() -> String {
+    "computer".into()
+}
+fn default_ramp_angle() -> f64 {
+    3.0
+}
+fn default_tab_width() -> f64 {
+    4.0
+}
+fn default_tab_height() -> f64 {
+    1.0
+}
+fn default_drill_max_diameter() -> f64 {
+    10.0
+}
+fn default_drill_retract() -> f64 {
+    1.0
+}
+fn default_tip_angle() -> f64 {
+    118.0
+}
+fn default_vbit_angle() -> f64 {
+    60.0
+}
+fn default_arc_tolerance() -> f64 {
+    0.01
+}
+fn default_svg_units() -> String {
+    "px".into()
+}
+fn default_sim_resolution() -> f64 {
+    0.5
+}
+fn default_gouge_tolerance() -> f64 {
+    0.01
+}
+fn default_leftover_tolerance() -> f64 {
+    0.1
+}
 fn default_step_over() -> f64 {
     1.5
 }
@@ -151,12 +348,49 @@ impl Default for CamConfig {
             strategy: default_strategy(),
             climb_cut: false,
             perimeter_passes: default_perimeter_passes(),
+            max_engagement: default_max_engagement(),
+            slope_limit: 0.0,
+            entry_mode: default_entry_mode(),
+            ramp_angle: default_ramp_angle(),
+            helix_diameter: None,
+            lead_in: default_lead(),
+            lead_out: default_lead(),
+            lead_radius: None,
+            compensation: default_compensation(),
+            tab_count: 0,
+            tab_spacing: 0.0,
+            tab_width: default_tab_width(),
+            tab_height: default_tab_height(),
+            drill_max_diameter: default_drill_max_diameter(),
+            drill_retract: default_drill_retract(),
+            peck_depth: 0.0,
+            dwell: 0.0,
+            tip_angle: default_tip_angle(),
+            tip_compensation: false,
+            vbit_angle: default_vbit_angle(),
+            tip_diameter: 0.0,
+            flat_bottom: false,
+            arc_tolerance: default_arc_tolerance(),
+            optimize_order: false,
+            optimize_start_points: false,
+            setup: None,
+            sim_resolution: default_sim_resolution(),
+            gouge_tolerance: default_gouge_tolerance(),
+            leftover_tolerance: default_leftover_tolerance(),
             scan_direction: default_scan_direction(),
             pattern: default_pattern(),
             machine_type: default_machine_type(),
             laser_power: None,
             passes: None,
             air_assist: None,
+            post_processor: None,
+            custom_post: None,
+            max_velocity: None,
+            max_acceleration: None,
+            junction_deviation: None,
+            layers: Vec::new(),
+            colors: Vec::new(),
+            svg_units: default_svg_units(),
         }
     }
 }
@@ -179,6 +413,44 @@ pub(crate) fn pattern_from_config(config: &CamConfig) -> Pattern {
     }
 }
 
+/// Parse the entry mode from config string. Unknown values fall back to
+/// `EntryMode::Plunge`.
+pub(crate) fn entry_mode_from_config(config: &CamConfig) -> EntryMode {
+    match config.entry_mode.as_str() {
+        "ramp" => EntryMode::Ramp,
+        "helix" | "helical" => EntryMode::Helix {
+            diameter: config.helix_diameter.unwrap_or(config.tool_diameter),
+        },
+        _ => EntryMode::Plunge,
+    }
+}
+
+/// Lead moves and compensation from config strings. Unknown leads fall
+/// back to none.
+pub(crate) fn leads_from_config(config: &CamConfig) -> LeadParams {
+    let size = config.lead_radius.unwrap_or(config.tool_diameter);
+    let lead = |name: &str| match name {
+        "line" => Lead::Line { length: size },
+        "arc" => Lead::Arc { radius: size },
+        _ => Lead::None,
+    };
+    LeadParams {
+        lead_in: lead(&config.lead_in),
+        lead_out: lead(&config.lead_out),
+        controller_comp: config.compensation == "controller",
+    }
+}
+
+/// Holding-tab layout from config fields.
+pub(crate) fn tabs_from_config(config: &CamConfig) -> TabParams {
+    TabParams {
+        count: config.tab_count,
+        spacing: config.tab_spacing,
+        width: config.tab_width,
+        height: config.tab_height,
+    }
+}
+
 /// Create a Tool from CamConfig fields.
 pub(crate) fn tool_from_config(config: &CamConfig) -> Tool {
     match config.tool_type.as_str() {
@@ -188,6 +460,18 @@ pub(crate) fn tool_from_config(config: &CamConfig) -> Tool {
             config.effective_diameter.unwrap_or(config.tool_diameter),
             10.0,
         ),
+        "drill" => Tool::drill(
+            config.tool_diameter,
+            config.tip_angle,
+            config.tip_compensation,
+            10.0,
+        ),
+        "vbit" => Tool::vbit(
+            config.tool_diameter,
+            config.vbit_angle,
+            config.tip_diameter,
+            10.0,
+        ),
         _ => Tool::new(
             tool::ToolType::EndMill,
             config.tool_diameter,
@@ -197,6 +481,16 @@ pub(crate) fn tool_from_config(config: &CamConfig) -> Tool {
     }
 }
 
+/// Hole selection and cycle settings for the drilling strategy.
+pub(crate) fn drill_params_from_config(config: &CamConfig) -> DrillParams {
+    DrillParams {
+        max_diameter: config.drill_max_diameter,
+        retract_z: config.drill_retract,
+        peck: config.peck_depth,
+        dwell: config.dwell,
+    }
+}
+
 /// Resolve a MachineProfile from the config's machine_type field.
 fn profile_from_config(config: &CamConfig) -> MachineProfile {
     match config.machine_type.as_str() {
@@ -205,6 +499,55 @@ fn profile_from_config(config: &CamConfig) -> MachineProfile {
     }
 }
 
+/// The machine profile with the configured post-processor, if any.
+pub(crate) fn output_profile_from_config(config: &CamConfig) -> Result<MachineProfile, String> {
+    let mut profile = profile_from_config(config);
+    if let Some(text) = config
+        .custom_post
+        .as_deref()
+        .filter(|t| !t.trim().is_empty())
+    {
+        profile.output_config.post = PostFormat::parse(text)?;
+    } else if let Some(name) = config.post_processor.as_deref() {
+        profile.output_config.post = PostFormat::builtin(name)
+            .ok_or_else(|| format!("Unknown post-processor '{}'", name))?;
+    }
+    let post = &profile.output_config.post;
+    if leads_from_config(config).controller_comp && !post.cutter_comp {
+        return Err(format!(
+            "Controller '{}' has no cutter compensation (G41/G42)",
+            post.name
+        ));
+    }
+    Ok(profile)
+}
+
+pub(crate) fn gcode_params_from_config(config: &CamConfig) -> GcodeParams {
+    GcodeParams {
+        feed_rate: config.feed_rate,
+        plunge_rate: config.plunge_rate,
+        spindle_speed: config.spindle_speed,
+        safe_z: config.safe_z,
+        unit_mm: true,
+        arc_tolerance: config.arc_tolerance,
+    }
+}
+
+/// The profile's motion limits with any overrides from the config.
+pub(crate) fn motion_limits_from_config(config: &CamConfig) -> MotionLimits {
+    let mut caps = profile_from_config(config).capabilities;
+    if let Some(v) = config.max_velocity {
+        caps.max_velocity = v;
+    }
+    if let Some(a) = config.max_acceleration {
+        caps.max_acceleration = a;
+    }
+    if let Some(d) = config.junction_deviation {
+        caps.junction_deviation = d;
+    }
+    MotionLimits::from_capabilities(&caps)
+}
+
 /// Build LaserParams from config, if applicable.
 fn laser_params_from_config(config: &CamConfig) -> Option<LaserParams> {
     if config.machine_type == "laser_cutter" {
@@ -222,6 +565,10 @@ fn laser_params_from_config(config: &CamConfig) -> Option<LaserParams> {
 fn strategy_from_config(config: &CamConfig) -> Box<dyn ToolpathStrategy> {
     match config.strategy.as_str() {
         "pocket" => Box::new(PocketStrategy),
+        "offset_pocket" => Box::new(OffsetPocketStrategy),
+        "adaptive" => Box::new(AdaptiveStrategy::new(config.max_engagement)),
+        "drill" => Box::new(DrillStrategy::new(drill_params_from_config(config))),
+        "vcarve" => Box::new(VCarveStrategy::new(config.flat_bottom)),
         "perimeter" => Box::new(PerimeterStrategy),
         "laser_cut" => Box::new(LaserCutStrategy::new(config.laser_power.unwrap_or(100.0))),
         "laser_engrave" => Box::new(LaserEngraveStrategy::new(
@@ -240,6 +587,20 @@ pub fn available_profiles() -> String {
     serde_json::to_string(&profiles).unwrap_or_else(|_| "[]".into())
 }
 
+/// Return JSON list of the built-in text fonts.
+pub fn available_fonts() -> String {
+    serde_json::to_string(text::BUILTIN_FONTS).unwrap_or_else(|_| "[]".into())
+}
+
+/// Return JSON list of the built-in post-processors.
+pub fn available_post_processors() -> String {
+    let posts: Vec<PostFormat> = PostFormat::BUILTIN_NAMES
+        .iter()
+        .filter_map(|name| PostFormat::builtin(name))
+        .collect();
+    serde_json::to_string(&posts).unwrap_or_else(|_| "[]".into())
+}
+
 /// Return a default config JSON for the given machine type.
 pub fn default_config(machine_type: &str) -> String {
     let config = if machine_type == "laser_cutter" {
@@ -261,218 +622,757 @@ pub fn default_config(machine_type: &str) -> String {
 
 // ── WASM entry points ────────────────────────────────────────────────
 
-/// Process an STL file (testable helper).
+/// Process an STL file (testable helper). Other mesh formats are
+/// detected from the content and accepted too.
 pub fn process_stl_impl(data: &[u8], config_json: &str) -> Result<String, String> {
+    process_mesh_impl(data, config_json, None)
+}
+
+/// Process a Wavefront OBJ file (testable helper).
+pub fn process_obj_impl(data: &[u8], config_json: &str) -> Result<String, String> {
+    process_mesh_impl(data, config_json, Some(MeshFormat::Obj))
+}
+
+/// Process a 3MF package (testable helper).
+pub fn process_3mf_impl(data: &[u8], config_json: &str) -> Result<String, String> {
+    process_mesh_impl(data, config_json, Some(MeshFormat::ThreeMf))
+}
+
+/// Process a PLY file (testable helper).
+pub fn process_ply_impl(data: &[u8], config_json: &str) -> Result<String, String> {
+    process_mesh_impl(data, config_json, Some(MeshFormat::Ply))
+}
+
+/// Process a mesh in `format`, or in the detected format when `None`.
+fn process_mesh_impl(
+    data: &[u8],
+    config_json: &str,
+    format: Option<MeshFormat>,
+) -> Result<String, String> {
     let config: CamConfig = serde_json::from_str(config_json).map_err(|e| e.to_string())?;
 
-    let profile = profile_from_config(&config);
+    let profile = output_profile_from_config(&config)?;
     profile.validate_strategy(&config.strategy)?;
+    require_2d_input(&config.strategy)?;
 
-    let mesh = stl::parse_stl(data)?;
-
-    let cut_params = CutParams {
-        tool: tool_from_config(&config),
-        tool_diameter: config.tool_diameter,
-        step_over: config.step_over,
-        step_down: config.step_down,
-        feed_rate: config.feed_rate,
-        plunge_rate: config.plunge_rate,
-        safe_z: config.safe_z,
-        cut_z: config.cut_depth,
-        climb_cut: config.climb_cut,
-        perimeter_passes: config.perimeter_passes,
+    let mesh = match format {
+        Some(format) => mesh_import::parse_mesh_as(data, format)?,
+        None => mesh_import::parse_mesh(data)?,
     };
+    let (mesh, placement) = place_mesh(mesh, config.setup.as_ref())?;
+    let toolpaths = build_toolpaths_stl(&mesh, &config);
+    Ok(emit_program(
+        toolpaths,
+        &config,
+        &profile,
+        placement.as_ref(),
+    ))
+}
 
-    let gcode_params = GcodeParams {
-        feed_rate: config.feed_rate,
-        plunge_rate: config.plunge_rate,
-        spindle_speed: config.spindle_speed,
-        safe_z: config.safe_z,
-        unit_mm: true,
-    };
+/// Process an SVG string (testable helper).
+pub fn process_svg_impl(svg_text: &str, config_json: &str) -> Result<String, String> {
+    let config: CamConfig = serde_json::from_str(config_json).map_err(|e| e.to_string())?;
 
-    let toolpaths: Vec<Toolpath> = match config.strategy.as_str() {
-        "pocket" => {
-            let layers = slicer::slice_mesh(&mesh, config.step_down);
-            let strategy = PocketStrategy;
-            let mut all = Vec::new();
-            for (z, contours) in &layers {
-                let mut p = cut_params.clone();
-                p.cut_z = *z;
-                all.extend(strategy.generate(contours, &p));
-            }
-            all
+    let profile = output_profile_from_config(&config)?;
+    profile.validate_strategy(&config.strategy)?;
+
+    let (polylines, placement) = load_2d_input(svg_text, &config)?;
+    Ok(emit_2d_program(
+        &polylines,
+        &config,
+        &profile,
+        placement.as_ref(),
+    ))
+}
+
+/// Cut 2D outlines with a single strategy and emit the program.
+fn emit_2d_program(
+    polylines: &[geometry::Polyline],
+    config: &CamConfig,
+    profile: &MachineProfile,
+    placement: Option<&Placement>,
+) -> String {
+    let toolpaths = build_toolpaths_svg(polylines, config);
+    emit_program(toolpaths, config, profile, placement)
+}
+
+/// Emit toolpaths built in the placing frame as one program in the work
+/// frame of `placement`, if any.
+pub(crate) fn emit_program(
+    mut toolpaths: Vec<Toolpath>,
+    config: &CamConfig,
+    profile: &MachineProfile,
+    placement: Option<&Placement>,
+) -> String {
+    let mut gcode_params = gcode_params_from_config(config);
+    let mut profile = profile.clone();
+    if let Some(placement) = placement {
+        placement.to_work(&mut toolpaths);
+        gcode_params.safe_z += placement.top;
+        select_work_offset(&mut profile, placement);
+    }
+    let laser = laser_params_from_config(config);
+    emit_gcode_with_profile(&toolpaths, &gcode_params, &profile, laser.as_ref())
+}
+
+/// Make the setup's G54..G59 the active work offset.
+fn select_work_offset(profile: &mut MachineProfile, placement: &Placement) {
+    profile
+        .output_config
+        .preamble
+        .push(format!("{} (work offset)", placement.work_offset));
+}
+
+/// Engrave laid-out text (testable helper). `text_json` is a
+/// [`text::TextOptions`]; with a serial range, the first part's text.
+pub fn process_text_impl(text_json: &str, config_json: &str) -> Result<String, String> {
+    let options = text::TextOptions::from_json(text_json)?;
+    let config: CamConfig = serde_json::from_str(config_json).map_err(|e| e.to_string())?;
+    let profile = output_profile_from_config(&config)?;
+    profile.validate_strategy(&config.strategy)?;
+    let (polylines, placement) = place_outlines(text::render(&options)?, &config)?;
+    Ok(emit_2d_program(
+        &polylines,
+        &config,
+        &profile,
+        placement.as_ref(),
+    ))
+}
+
+/// JSON shape of each part returned by [`process_text_serials_impl`].
+#[derive(Serialize)]
+struct TextPart {
+    text: String,
+    gcode: String,
+}
+
+/// One program per part of a serial-number run (testable helper).
+///
+/// Returns `[{"text": "SN-0001", "gcode": "..."}, ...]`.
+pub fn process_text_serials_impl(text_json: &str, config_json: &str) -> Result<String, String> {
+    let options = text::TextOptions::from_json(text_json)?;
+    let config: CamConfig = serde_json::from_str(config_json).map_err(|e| e.to_string())?;
+    let profile = output_profile_from_config(&config)?;
+    profile.validate_strategy(&config.strategy)?;
+    let font = options.load_font()?;
+    let parts = options
+        .texts()?
+        .into_iter()
+        .map(|text| {
+            let (polylines, placement) = place_outlines(options.layout(&text, &font)?, &config)?;
+            let gcode = emit_2d_program(&polylines, &config, &profile, placement.as_ref());
+            Ok(TextPart { text, gcode })
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+    serde_json::to_string(&parts).map_err(|e| e.to_string())
+}
+
+/// Outlines of laid-out text for preview or the sketcher (testable
+/// helper). Returns `[{points, closed}, ...]`.
+pub fn text_outlines_impl(text_json: &str) -> Result<String, String> {
+    let options = text::TextOptions::from_json(text_json)?;
+    serde_json::to_string(&text::render(&options)?).map_err(|e| e.to_string())
+}
+
+/// Engrave a PNG or BMP image on a laser (testable helper). `image_json`
+/// is a [`RasterParams`]; the config's laser power burns the darkest dots.
+pub fn process_image_impl(
+    data: &[u8],
+    image_json: &str,
+    config_json: &str,
+) -> Result<String, String> {
+    let config: CamConfig = serde_json::from_str(config_json).map_err(|e| e.to_string())?;
+    let profile = output_profile_from_config(&config)?;
+    if profile.machine_type != MachineType::LaserCutter {
+        return Err("Image engraving needs a laser cutter".into());
+    }
+    let (toolpaths, placement) = image_toolpaths(data, image_json, &config)?;
+    Ok(emit_program(
+        toolpaths,
+        &config,
+        &profile,
+        placement.as_ref(),
+    ))
+}
+
+/// Flattened raster moves of an image for the simulator (testable helper).
+pub fn sim_moves_image_impl(
+    data: &[u8],
+    image_json: &str,
+    config_json: &str,
+) -> Result<String, String> {
+    let config: CamConfig = serde_json::from_str(config_json).map_err(|e| e.to_string())?;
+    let (toolpaths, placement) = image_toolpaths(data, image_json, &config)?;
+    flatten_moves_impl(&work_toolpaths(toolpaths, placement.as_ref()))
+}
+
+/// Raster toolpaths of an image, placed by `config.setup` as the
+/// rectangle it covers.
+fn image_toolpaths(
+    data: &[u8],
+    image_json: &str,
+    config: &CamConfig,
+) -> Result<(Vec<Toolpath>, Option<Placement>), String> {
+    let params = RasterParams::from_json(image_json)?;
+    let image = image::parse_image(data)?;
+    let toolpaths = raster_engrave(&image, &params, config.laser_power.unwrap_or(100.0))?;
+    match &config.setup {
+        Some(setup) => {
+            let (xf, placement) = setup.place_2d(&[params.frame(&image)], config.cut_depth)?;
+            let toolpaths = toolpaths
+                .into_iter()
+                .map(|mut tp| {
+                    for mv in &mut tp.moves {
+                        let p = xf.apply(geometry::Vec2::new(mv.x, mv.y));
+                        (mv.x, mv.y) = (p.x, p.y);
+                    }
+                    tp
+                })
+                .collect();
+            Ok((toolpaths, Some(placement)))
         }
-        "slice" => {
-            let layers = slicer::slice_mesh(&mesh, config.step_down);
-            let strategy = ContourStrategy;
-            let mut all = Vec::new();
-            for (z, contours) in &layers {
-                let mut p = cut_params.clone();
-                p.cut_z = *z;
-                all.extend(strategy.generate(contours, &p));
+        None => Ok((toolpaths, None)),
+    }
+}
+
+/// Run a multi-operation job on an STL file (testable helper).
+pub fn process_job_stl_impl(data: &[u8], job_json: &str) -> Result<String, String> {
+    let (sections, profile) = job_sections_stl(data, job_json)?;
+    Ok(emit_job_gcode(&sections, &profile))
+}
+
+/// Run a multi-operation job on an SVG string (testable helper).
+pub fn process_job_svg_impl(svg_text: &str, job_json: &str) -> Result<String, String> {
+    let (sections, profile) = job_sections_svg(svg_text, job_json)?;
+    Ok(emit_job_gcode(&sections, &profile))
+}
+
+fn job_sections_stl(
+    data: &[u8],
+    job_json: &str,
+) -> Result<(Vec<JobSection>, MachineProfile), String> {
+    let job = job::Job::from_json(job_json)?;
+    for (i, op) in job.operations.iter().enumerate() {
+        require_2d_input(&op.config.strategy).map_err(|e| format!("Operation {}: {}", i + 1, e))?;
+    }
+    let (mesh, placement) = place_mesh(mesh_import::parse_mesh(data)?, job.setup.as_ref())?;
+    job_sections(job_json, placement.as_ref(), |config, prior| {
+        Ok(build_rest_toolpaths_stl(&mesh, config, prior))
+    })
+}
+
+fn job_sections_svg(
+    svg_text: &str,
+    job_json: &str,
+) -> Result<(Vec<JobSection>, MachineProfile), String> {
+    let job = job::Job::from_json(job_json)?;
+    // One stock for the whole job, deep enough for the deepest cut.
+    let depth = job
+        .operations
+        .iter()
+        .map(|op| op.config.cut_depth)
+        .fold(0.0, f64::min);
+    let placed = job
+        .setup
+        .as_ref()
+        .map(|setup| place_2d_input(svg_text, setup, depth))
+        .transpose()?;
+    job_sections(
+        job_json,
+        placed.as_ref().map(|(_, p)| p),
+        |config, prior| {
+            let mut polylines = parse_2d_input(svg_text, config)?;
+            if let Some((xf, _)) = &placed {
+                polylines = setup::transform_polylines(xf, &polylines);
+            }
+            Ok(build_rest_toolpaths_svg(&polylines, config, prior))
+        },
+    )
+}
+
+/// Generate every operation of a job with `build`, returning the
+/// sections and the profile to emit them with. Rest-machining operations
+/// get the earlier operations' tools and toolpaths; the rest get none.
+/// Sections are built in the placing frame and moved into the work frame
+/// of `placement` once all are done.
+fn job_sections(
+    job_json: &str,
+    placement: Option<&Placement>,
+    build: impl Fn(&CamConfig, &[PriorCut]) -> Result<Vec<Toolpath>, String>,
+) -> Result<(Vec<JobSection>, MachineProfile), String> {
+    let job = job::Job::from_json(job_json)?;
+    job.validate()?;
+
+    let mut profile = None;
+    let mut sections: Vec<JobSection> = Vec::with_capacity(job.operations.len());
+    for (i, op) in job.operations.iter().enumerate() {
+        let config = job.operation_config(i)?;
+        let p = output_profile_from_config(&config)?;
+        p.validate_strategy(&config.strategy)
+            .map_err(|e| format!("Operation {}: {}", i + 1, e))?;
+        profile.get_or_insert(p);
+        let mut prior = Vec::new();
+        if op.rest_machining {
+            require_rest_strategy(&config.strategy)
+                .map_err(|e| format!("Operation {}: {}", i + 1, e))?;
+            if i == 0 {
+                return Err("Operation 1: rest machining needs an earlier operation".into());
+            }
+            for (section, earlier) in sections.iter().zip(&job.operations) {
+                let entry = job
+                    .tools
+                    .get(earlier.tool)
+                    .ok_or_else(|| format!("Tool T{} is not in the tool library", earlier.tool))?;
+                prior.push(PriorCut {
+                    tool: &entry.tool,
+                    toolpaths: &section.toolpaths,
+                });
             }
-            all
         }
-        "surface3d" | "zigzag" => {
-            let surface_params = SurfaceParams::new_with_pattern(
-                &mesh,
-                cut_params,
-                scan_direction_from_config(&config),
-                pattern_from_config(&config),
-            );
-            Surface3dStrategy.generate_surface(&surface_params)
+        let toolpaths =
+            build(&config, &prior).map_err(|e| format!("Operation {}: {}", i + 1, e))?;
+        sections.push(JobSection {
+            comments: job.operation_header(i),
+            tool: op.tool,
+            params: gcode_params_from_config(&config),
+            toolpaths,
+        });
+    }
+    let mut profile = profile.ok_or("Job has no operations")?;
+    if let Some(placement) = placement {
+        for section in &mut sections {
+            placement.to_work(&mut section.toolpaths);
+            section.params.safe_z += placement.top;
         }
-        "perimeter" => {
-            let layers = slicer::slice_mesh(&mesh, config.step_down);
-            let strategy = PerimeterStrategy;
-            let mut all = Vec::new();
-            for (z, contours) in &layers {
-                let mut p = cut_params.clone();
-                p.cut_z = *z;
-                all.extend(strategy.generate(contours, &p));
-            }
-            all
+        select_work_offset(&mut profile, placement);
+    }
+    Ok((sections, profile))
+}
+
+/// STL preview (testable helper).
+pub fn preview_stl_impl(data: &[u8], config_json: &str) -> Result<String, String> {
+    let config: CamConfig = serde_json::from_str(config_json).map_err(|e| e.to_string())?;
+    let (mesh, placement) = load_mesh(data, &config)?;
+    let toolpaths = work_toolpaths(build_toolpaths_stl(&mesh, &config), placement.as_ref());
+
+    let mut preview_paths: Vec<Vec<[f64; 3]>> = Vec::new();
+    for tp in &toolpaths {
+        let path: Vec<[f64; 3]> = tp
+            .moves
+            .iter()
+            .filter(|m| !m.rapid)
+            .map(|m| [m.x, m.y, m.z])
+            .collect();
+        if !path.is_empty() {
+            preview_paths.push(path);
         }
-        _ => {
-            let layers = slicer::slice_mesh(&mesh, config.step_down);
-            let strategy = ContourStrategy;
-            let mut all = Vec::new();
-            for (z, contours) in &layers {
-                let mut p = cut_params.clone();
-                p.cut_z = *z;
-                all.extend(strategy.generate(contours, &p));
-            }
-            if all.is_empty() {
-                let contours =
-                    slicer::slice_at_z(&mesh, mesh.bounds.as_ref().map_or(0.0, |b| b.min.z + 0.01));
-                all.extend(strategy.generate(&contours, &cut_params));
+    }
+    serde_json::to_string(&preview_paths).map_err(|e| e.to_string())
+}
+
+/// JSON shape returned by [`import_dxf_impl`].
+#[derive(Serialize)]
+struct DxfImport {
+    layers: Vec<String>,
+    polylines: Vec<geometry::Polyline>,
+}
+
+/// Read a DXF drawing for the sketcher (testable helper). `layers_json`
+/// is a JSON list of layer names to keep; an empty list keeps all.
+///
+/// Returns `{"layers": [name, ...], "polylines": [{points, closed}, ...]}`
+/// where `layers` lists every layer with geometry, for a layer picker.
+pub fn import_dxf_impl(text: &str, layers_json: &str) -> Result<String, String> {
+    let wanted: Vec<String> = serde_json::from_str(layers_json).map_err(|e| e.to_string())?;
+    let drawing = dxf::Drawing::parse(text)?;
+    let import = DxfImport {
+        layers: drawing.layers()?,
+        polylines: drawing.polylines(&wanted)?,
+    };
+    serde_json::to_string(&import).map_err(|e| e.to_string())
+}
+
+/// Stroke and fill colors used in an SVG (testable helper), as a sorted
+/// JSON list of `#rrggbb` strings for a color picker.
+pub fn svg_colors_impl(svg_text: &str) -> Result<String, String> {
+    let mut colors: Vec<String> = svg::parse_svg_shapes(svg_text)?
+        .into_iter()
+        .flat_map(|s| [s.stroke, s.fill])
+        .flatten()
+        .collect();
+    colors.sort();
+    colors.dedup();
+    serde_json::to_string(&colors).map_err(|e| e.to_string())
+}
+
+/// JSON shape returned by [`preview_svg_impl`].
+#[derive(Serialize)]
+struct SvgPreview {
+    paths: Vec<Vec<[f64; 2]>>,
+    tabs: Vec<geometry::Tab>,
+}
+
+/// SVG preview (testable helper).
+///
+/// Returns `{"paths": [[[x, y], ...], ...], "tabs": [{x, y, width, height}, ...]}`;
+/// `tabs` lists the holding tabs the configured strategy would leave.
+pub fn preview_svg_impl(svg_text: &str, config_json: &str) -> Result<String, String> {
+    let config: CamConfig = serde_json::from_str(config_json).map_err(|e| e.to_string())?;
+    let (polylines, _) = load_2d_input(svg_text, &config)?;
+    let paths: Vec<Vec<[f64; 2]>> = polylines
+        .iter()
+        .map(|pl| pl.points.iter().map(|p| [p.x, p.y]).collect())
+        .collect();
+
+    let mut tabs: Vec<geometry::Tab> = Vec::new();
+    if tabs_from_config(&config).enabled() {
+        for tp in build_toolpaths_svg(&polylines, &config) {
+            // Every depth pass repeats the same tabs.
+            for tab in tp.tabs {
+                if !tabs.contains(&tab) {
+                    tabs.push(tab);
+                }
             }
-            all
         }
-    };
+    }
+    serde_json::to_string(&SvgPreview { paths, tabs }).map_err(|e| e.to_string())
+}
+
+// ── Simulation data ──────────────────────────────────────────────────
+
+/// STL sim moves (testable helper).
+pub fn sim_moves_stl_impl(data: &[u8], config_json: &str) -> Result<String, String> {
+    let config: CamConfig = serde_json::from_str(config_json).map_err(|e| e.to_string())?;
+    let (mesh, placement) = load_mesh(data, &config)?;
+    let toolpaths = work_toolpaths(build_toolpaths_stl(&mesh, &config), placement.as_ref());
+    flatten_moves_impl(&toolpaths)
+}
 
-    let laser = laser_params_from_config(&config);
-    Ok(emit_gcode_with_profile(
+/// SVG sim moves (testable helper).
+pub fn sim_moves_svg_impl(svg_text: &str, config_json: &str) -> Result<String, String> {
+    let config: CamConfig = serde_json::from_str(config_json).map_err(|e| e.to_string())?;
+    let (polylines, placement) = load_2d_input(svg_text, &config)?;
+    let toolpaths = work_toolpaths(build_toolpaths_svg(&polylines, &config), placement.as_ref());
+    flatten_moves_impl(&toolpaths)
+}
+
+/// Cut the STL's bounding block, or the setup's stock, with the toolpaths
+/// and check the result against the model (testable helper). Returns a
+/// `SimReport` as JSON.
+pub fn simulate_stock_stl_impl(data: &[u8], config_json: &str) -> Result<String, String> {
+    let config: CamConfig = serde_json::from_str(config_json).map_err(|e| e.to_string())?;
+    let (mesh, placement) = load_mesh(data, &config)?;
+    let toolpaths = work_toolpaths(build_toolpaths_stl(&mesh, &config), placement.as_ref());
+    let params = sim_params_from_config(&config);
+    let (mesh, stock) = match &placement {
+        Some(placement) => (
+            placement.mesh_to_work(&mesh),
+            placement.sim_stock(params.resolution),
+        ),
+        None => {
+            let stock = Stock::for_mesh(&mesh, params.resolution).ok_or("STL has no triangles")?;
+            (mesh, stock)
+        }
+    };
+    let report = simulation::simulate(
+        stock,
         &toolpaths,
-        &gcode_params,
-        &profile,
-        laser.as_ref(),
-    ))
+        &tool_from_config(&config),
+        Some(&mesh),
+        &params,
+    );
+    serde_json::to_string(&report).map_err(|e| e.to_string())
 }
 
-/// Process an SVG string (testable helper).
-pub fn process_svg_impl(svg_text: &str, config_json: &str) -> Result<String, String> {
+/// Cut stock covering the SVG's outlines, with its top at Z 0, or the
+/// setup's stock (testable helper). There is no model, so only rapids and
+/// the image are reported.
+pub fn simulate_stock_svg_impl(svg_text: &str, config_json: &str) -> Result<String, String> {
     let config: CamConfig = serde_json::from_str(config_json).map_err(|e| e.to_string())?;
+    let (polylines, placement) = load_2d_input(svg_text, &config)?;
+    let toolpaths = work_toolpaths(build_toolpaths_svg(&polylines, &config), placement.as_ref());
+    let params = sim_params_from_config(&config);
+    if let Some(placement) = placement {
+        let stock = placement.sim_stock(params.resolution);
+        let tool = tool_from_config(&config);
+        let report = simulation::simulate(stock, &toolpaths, &tool, None, &params);
+        return serde_json::to_string(&report).map_err(|e| e.to_string());
+    }
+    let points: Vec<geometry::Vec2> = polylines
+        .iter()
+        .flat_map(|p| p.points.iter().copied())
+        .collect();
+    let b = geometry::BoundingBox2::from_points(&points).ok_or("SVG has no paths")?;
+    // Room for tools cutting outside the outlines.
+    let pad = config.tool_diameter;
+    let stock = Stock::new(
+        geometry::Vec2::new(b.min.x - pad, b.min.y - pad),
+        geometry::Vec2::new(b.max.x + pad, b.max.y + pad),
+        0.0,
+        params.resolution,
+    );
+    let report = simulation::simulate(stock, &toolpaths, &tool_from_config(&config), None, &params);
+    serde_json::to_string(&report).map_err(|e| e.to_string())
+}
 
-    let profile = profile_from_config(&config);
-    profile.validate_strategy(&config.strategy)?;
+pub(crate) fn sim_params_from_config(config: &CamConfig) -> SimParams {
+    SimParams {
+        resolution: config.sim_resolution,
+        gouge_tolerance: config.gouge_tolerance,
+        leftover_tolerance: config.leftover_tolerance,
+    }
+}
 
-    let polylines = svg::parse_svg(svg_text)?;
+/// Run a G-code program through the interpreter (testable helper).
+/// `config_json` is an `InterpreterConfig`; returns a `Program` as JSON.
+pub fn interpret_gcode_impl(gcode: &str, config_json: &str) -> Result<String, String> {
+    let config: InterpreterConfig = serde_json::from_str(config_json).map_err(|e| e.to_string())?;
+    let program = gcode_interpreter::interpret(gcode, &config);
+    serde_json::to_string(&program).map_err(|e| e.to_string())
+}
 
-    let cut_params = CutParams {
-        tool: tool_from_config(&config),
-        tool_diameter: config.tool_diameter,
-        step_over: config.step_over,
-        step_down: config.step_down,
-        feed_rate: config.feed_rate,
-        plunge_rate: config.plunge_rate,
-        safe_z: config.safe_z,
-        cut_z: config.cut_depth,
-        climb_cut: config.climb_cut,
-        perimeter_passes: config.perimeter_passes,
-    };
+// ── Cycle time ───────────────────────────────────────────────────────
 
-    let gcode_params = GcodeParams {
-        feed_rate: config.feed_rate,
-        plunge_rate: config.plunge_rate,
-        spindle_speed: config.spindle_speed,
-        safe_z: config.safe_z,
-        unit_mm: true,
+/// Planned machining time for an STL (testable helper). Returns a
+/// `CycleTime` as JSON.
+pub fn cycle_time_stl_impl(data: &[u8], config_json: &str) -> Result<String, String> {
+    let config: CamConfig = serde_json::from_str(config_json).map_err(|e| e.to_string())?;
+    let (mesh, _) = load_mesh(data, &config)?;
+    cycle_time_single(&config, &build_toolpaths_stl(&mesh, &config))
+}
+
+/// Planned machining time for an SVG (testable helper).
+pub fn cycle_time_svg_impl(svg_text: &str, config_json: &str) -> Result<String, String> {
+    let config: CamConfig = serde_json::from_str(config_json).map_err(|e| e.to_string())?;
+    let (polylines, _) = load_2d_input(svg_text, &config)?;
+    cycle_time_single(&config, &build_toolpaths_svg(&polylines, &config))
+}
+
+/// Planned machining time for a job on an STL, per operation (testable
+/// helper).
+pub fn cycle_time_job_stl_impl(data: &[u8], job_json: &str) -> Result<String, String> {
+    let (sections, _) = job_sections_stl(data, job_json)?;
+    cycle_time_job(job_json, &sections)
+}
+
+/// Planned machining time for a job on an SVG, per operation (testable
+/// helper).
+pub fn cycle_time_job_svg_impl(svg_text: &str, job_json: &str) -> Result<String, String> {
+    let (sections, _) = job_sections_svg(svg_text, job_json)?;
+    cycle_time_job(job_json, &sections)
+}
+
+/// Planned run time of a G-code program on the machine in `config_json`,
+/// one operation per tool (testable helper).
+pub fn cycle_time_gcode_impl(gcode: &str, config_json: &str) -> Result<String, String> {
+    let config: CamConfig = serde_json::from_str(config_json).map_err(|e| e.to_string())?;
+    let interp = InterpreterConfig::default();
+    let program = gcode_interpreter::interpret(gcode, &interp);
+    let ops = cycle_time::operations_from_program(&program, interp.home);
+    let time = cycle_time::estimate(&ops, &motion_limits_from_config(&config));
+    serde_json::to_string(&time).map_err(|e| e.to_string())
+}
+
+// ── Rapid travel ─────────────────────────────────────────────────────
+
+/// Rapid travel of an STL program as planned and with the config's cut
+/// ordering (testable helper). Returns a `RapidReport` as JSON.
+pub fn rapid_report_stl_impl(data: &[u8], config_json: &str) -> Result<String, String> {
+    let config: CamConfig = serde_json::from_str(config_json).map_err(|e| e.to_string())?;
+    let (mesh, _) = load_mesh(data, &config)?;
+    rapid_report(&config, |c| build_toolpaths_stl(&mesh, c))
+}
+
+/// Rapid travel of an SVG or DXF program (testable helper).
+pub fn rapid_report_svg_impl(svg_text: &str, config_json: &str) -> Result<String, String> {
+    let config: CamConfig = serde_json::from_str(config_json).map_err(|e| e.to_string())?;
+    let (polylines, _) = load_2d_input(svg_text, &config)?;
+    rapid_report(&config, |c| build_toolpaths_svg(&polylines, c))
+}
+
+/// Rapids from the origin, where cycle time starts too.
+fn rapid_report(
+    config: &CamConfig,
+    build: impl Fn(&CamConfig) -> Vec<Toolpath>,
+) -> Result<String, String> {
+    let planned = CamConfig {
+        optimize_order: false,
+        ..config.clone()
+    };
+    let origin = geometry::Vec2::new(0.0, 0.0);
+    let report = RapidReport {
+        before: toolpath::rapid_distance(&build(&planned), origin),
+        after: toolpath::rapid_distance(&build(config), origin),
     };
+    serde_json::to_string(&report).map_err(|e| e.to_string())
+}
 
-    let strategy = strategy_from_config(&config);
+fn cycle_time_single(config: &CamConfig, toolpaths: &[Toolpath]) -> Result<String, String> {
+    let params = gcode_params_from_config(config);
+    let op = cycle_time::Operation {
+        name: config.strategy.clone(),
+        start: [0.0, 0.0, params.safe_z],
+        blocks: cycle_time::blocks_from_toolpaths(toolpaths, &params),
+    };
+    let time = cycle_time::estimate(&[op], &motion_limits_from_config(config));
+    serde_json::to_string(&time).map_err(|e| e.to_string())
+}
 
-    let is_laser = profile.machine_type == MachineType::LaserCutter;
-    let mut all_toolpaths = Vec::new();
+/// The whole job runs on the first operation's machine.
+fn cycle_time_job(job_json: &str, sections: &[JobSection]) -> Result<String, String> {
+    let config = job::Job::from_json(job_json)?.operation_config(0)?;
+    let ops = cycle_time::operations_from_sections(sections);
+    let time = cycle_time::estimate(&ops, &motion_limits_from_config(&config));
+    serde_json::to_string(&time).map_err(|e| e.to_string())
+}
 
-    if is_laser {
-        all_toolpaths.extend(strategy.generate(&polylines, &cut_params));
+/// Strategies that work on closed 2D outlines rather than mesh slices:
+/// drilling needs circles and points, V-carving needs whole regions.
+const STRATEGIES_2D: &[&str] = &["drill", "vcarve"];
+
+/// Outlines of 2D input: a DXF drawing (only `config.layers`, if any are
+/// named) or an SVG (only shapes painted in `config.colors`, if any), told
+/// apart by content.
+pub(crate) fn parse_2d_input(
+    text: &str,
+    config: &CamConfig,
+) -> Result<Vec<geometry::Polyline>, String> {
+    if dxf::is_dxf(text) {
+        dxf::parse_dxf(text, &config.layers)
     } else {
-        let mut z = 0.0;
-        while z > config.cut_depth - 0.001 {
-            z -= config.step_down;
-            if z < config.cut_depth {
-                z = config.cut_depth;
-            }
-            let mut p = cut_params.clone();
-            p.cut_z = z;
-            all_toolpaths.extend(strategy.generate(&polylines, &p));
-            if (z - config.cut_depth).abs() < 0.001 {
-                break;
-            }
-        }
+        let units = svg::SvgUnits::parse(&config.svg_units)?;
+        svg::parse_svg_by_color(text, &config.colors, units)
     }
+}
 
-    let laser = laser_params_from_config(&config);
-    Ok(emit_gcode_with_profile(
-        &all_toolpaths,
-        &gcode_params,
-        &profile,
-        laser.as_ref(),
-    ))
+/// Mesh input placed by `config.setup`, and where the stock went.
+pub(crate) fn load_mesh(
+    data: &[u8],
+    config: &CamConfig,
+) -> Result<(geometry::Mesh, Option<Placement>), String> {
+    place_mesh(mesh_import::parse_mesh(data)?, config.setup.as_ref())
 }
 
-/// STL preview (testable helper).
-pub fn preview_stl_impl(data: &[u8], config_json: &str) -> Result<String, String> {
-    let config: CamConfig = serde_json::from_str(config_json).map_err(|e| e.to_string())?;
-    let mesh = stl::parse_stl(data)?;
-    let toolpaths = build_toolpaths_stl(&mesh, &config);
+/// `mesh` in the placing frame of `setup`, if there is one.
+fn place_mesh(
+    mesh: geometry::Mesh,
+    setup: Option<&Setup>,
+) -> Result<(geometry::Mesh, Option<Placement>), String> {
+    match setup {
+        Some(setup) => {
+            let (mesh, placement) = setup.place_mesh(&mesh)?;
+            Ok((mesh, Some(placement)))
+        }
+        None => Ok((mesh, None)),
+    }
+}
+
+/// 2D input placed by `config.setup`, and where the stock went.
+pub(crate) fn load_2d_input(
+    text: &str,
+    config: &CamConfig,
+) -> Result<(Vec<geometry::Polyline>, Option<Placement>), String> {
+    let polylines = parse_2d_input(text, config)?;
+    match &config.setup {
+        Some(setup) => {
+            let (xf, placement) = place_2d_input(text, setup, config.cut_depth)?;
+            Ok((setup::transform_polylines(&xf, &polylines), Some(placement)))
+        }
+        None => Ok((polylines, None)),
+    }
+}
 
-    let mut preview_paths: Vec<Vec<[f64; 3]>> = Vec::new();
-    for tp in &toolpaths {
-        let path: Vec<[f64; 3]> = tp
-            .moves
-            .iter()
-            .filter(|m| !m.rapid)
-            .map(|m| [m.x, m.y, m.z])
-            .collect();
-        if !path.is_empty() {
-            preview_paths.push(path);
+/// The map placing 2D input by `setup`, and where the stock went. The
+/// stock fits the whole drawing, whatever layers or colors are cut.
+fn place_2d_input(
+    text: &str,
+    setup: &Setup,
+    depth: f64,
+) -> Result<(geometry::affine::Affine2, Placement), String> {
+    setup.place_2d(&parse_2d_input(text, &CamConfig::default())?, depth)
+}
+
+/// Generated outlines, such as text, placed by `config.setup`.
+fn place_outlines(
+    polylines: Vec<geometry::Polyline>,
+    config: &CamConfig,
+) -> Result<(Vec<geometry::Polyline>, Option<Placement>), String> {
+    match &config.setup {
+        Some(setup) => {
+            let (xf, placement) = setup.place_2d(&polylines, config.cut_depth)?;
+            Ok((setup::transform_polylines(&xf, &polylines), Some(placement)))
         }
+        None => Ok((polylines, None)),
     }
-    serde_json::to_string(&preview_paths).map_err(|e| e.to_string())
 }
 
-/// SVG preview (testable helper).
-pub fn preview_svg_impl(svg_text: &str) -> Result<String, String> {
-    let polylines = svg::parse_svg(svg_text)?;
-    let preview_paths: Vec<Vec<[f64; 2]>> = polylines
-        .iter()
-        .map(|pl| pl.points.iter().map(|p| [p.x, p.y]).collect())
-        .collect();
-    serde_json::to_string(&preview_paths).map_err(|e| e.to_string())
+/// Toolpaths built in the placing frame, moved into the work frame.
+pub(crate) fn work_toolpaths(
+    mut toolpaths: Vec<Toolpath>,
+    placement: Option<&Placement>,
+) -> Vec<Toolpath> {
+    if let Some(placement) = placement {
+        placement.to_work(&mut toolpaths);
+    }
+    toolpaths
 }
 
-// ── Simulation data ──────────────────────────────────────────────────
+/// Reject a 2D-only strategy for mesh input.
+pub(crate) fn require_2d_input(strategy: &str) -> Result<(), String> {
+    if STRATEGIES_2D.contains(&strategy) {
+        Err(format!(
+            "Strategy '{}' needs 2D input (SVG, DXF or sketch)",
+            strategy
+        ))
+    } else {
+        Ok(())
+    }
+}
 
-/// STL sim moves (testable helper).
-pub fn sim_moves_stl_impl(data: &[u8], config_json: &str) -> Result<String, String> {
-    let config: CamConfig = serde_json::from_str(config_json).map_err(|e| e.to_string())?;
-    let mesh = stl::parse_stl(data)?;
-    let toolpaths = build_toolpaths_stl(&mesh, &config);
-    flatten_moves_impl(&toolpaths)
+/// Strategies that can follow earlier operations as rest machining:
+/// 2D area clearing and 3D surface finishing.
+const REST_STRATEGIES: &[&str] = &[
+    "pocket",
+    "offset_pocket",
+    "adaptive",
+    "zigzag",
+    "surface3d",
+    "waterline",
+];
+
+/// Reject rest machining for strategies that do not clear an area.
+pub(crate) fn require_rest_strategy(strategy: &str) -> Result<(), String> {
+    if REST_STRATEGIES.contains(&strategy) {
+        Ok(())
+    } else {
+        Err(format!(
+            "Strategy '{}' cannot run as rest machining (use {})",
+            strategy,
+            REST_STRATEGIES.join(", ")
+        ))
+    }
 }
 
-/// SVG sim moves (testable helper).
-pub fn sim_moves_svg_impl(svg_text: &str, config_json: &str) -> Result<String, String> {
-    let config: CamConfig = serde_json::from_str(config_json).map_err(|e| e.to_string())?;
-    let polylines = svg::parse_svg(svg_text)?;
-    let toolpaths = build_toolpaths_svg(&polylines, &config);
-    flatten_moves_impl(&toolpaths)
+/// Strategies that step down on their own (or not at all), so 2D input
+/// runs them once at `cut_depth` instead of once per layer.
+pub(crate) fn full_depth_in_one_pass(strategy: &str) -> bool {
+    STRATEGIES_2D.contains(&strategy)
 }
 
 fn build_toolpaths_stl(mesh: &geometry::Mesh, config: &CamConfig) -> Vec<Toolpath> {
+    build_rest_toolpaths_stl(mesh, config, &[])
+}
+
+/// `build_toolpaths_stl` limited to where the `prior` operations left
+/// material (everywhere when there are none).
+fn build_rest_toolpaths_stl(
+    mesh: &geometry::Mesh,
+    config: &CamConfig,
+    prior: &[PriorCut],
+) -> Vec<Toolpath> {
+    if require_2d_input(&config.strategy).is_err() {
+        return Vec::new();
+    }
     let cut_params = CutParams {
         tool: tool_from_config(config),
         tool_diameter: config.tool_diameter,
@@ -484,41 +1384,83 @@ fn build_toolpaths_stl(mesh: &geometry::Mesh, config: &CamConfig) -> Vec<Toolpat
         cut_z: config.cut_depth,
         climb_cut: config.climb_cut,
         perimeter_passes: config.perimeter_passes,
+        entry: entry_mode_from_config(config),
+        ramp_angle: config.ramp_angle,
+        tabs: tabs_from_config(config),
+        final_z: mesh.bounds.as_ref().map(|b| b.min.z),
+        leads: leads_from_config(config),
     };
+    let tool = cut_params.tool.clone();
+    let rest = (!prior.is_empty()).then(|| RestStock::for_mesh(mesh, &tool, prior));
 
     // Handle the 3D surface strategy separately (accepts both the legacy
     // "zigzag" name and the new "surface3d" name with pattern selection).
-    if config.strategy == "zigzag" || config.strategy == "surface3d" {
-        let surface_params = SurfaceParams::new_with_pattern(
-            mesh,
-            cut_params,
-            scan_direction_from_config(config),
-            pattern_from_config(config),
-        );
-        return Surface3dStrategy.generate_surface(&surface_params);
+    let surface = match config.strategy.as_str() {
+        "zigzag" | "surface3d" => {
+            let surface_params = SurfaceParams::new_with_pattern(
+                mesh,
+                cut_params.clone(),
+                scan_direction_from_config(config),
+                pattern_from_config(config),
+            );
+            Some(Surface3dStrategy.generate_surface(&surface_params))
+        }
+        "waterline" => {
+            let surface_params =
+                SurfaceParams::new(mesh, cut_params.clone(), scan_direction_from_config(config));
+            Some(WaterlineStrategy::new(config.slope_limit).generate_surface(&surface_params))
+        }
+        _ => None,
+    };
+    if let Some(toolpaths) = surface {
+        return match &rest {
+            Some(rest) => rest.trim_surface(&toolpaths, mesh, &tool, config.safe_z),
+            None => toolpaths,
+        };
     }
 
     let layers = slicer::slice_mesh(mesh, config.step_down);
     let strategy: Box<dyn ToolpathStrategy> = match config.strategy.as_str() {
         "pocket" => Box::new(PocketStrategy),
+        "offset_pocket" => Box::new(OffsetPocketStrategy),
+        "adaptive" => Box::new(AdaptiveStrategy::new(config.max_engagement)),
         "perimeter" => Box::new(PerimeterStrategy),
         _ => Box::new(ContourStrategy),
     };
+    let order = order_params_from_config(config);
     let mut all = Vec::new();
     for (z, contours) in &layers {
         let mut p = cut_params.clone();
         p.cut_z = *z;
-        all.extend(strategy.generate(contours, &p));
+        let pass = match &rest {
+            Some(rest) => strategy.generate(&rest.region_at(contours, *z, &tool), &p),
+            None => strategy.generate(contours, &p),
+        };
+        push_pass(&mut all, pass, order.as_ref());
     }
-    if all.is_empty() {
+    if all.is_empty() && rest.is_none() {
         let contours =
             slicer::slice_at_z(mesh, mesh.bounds.as_ref().map_or(0.0, |b| b.min.z + 0.01));
-        all.extend(strategy.generate(&contours, &cut_params));
+        push_pass(
+            &mut all,
+            strategy.generate(&contours, &cut_params),
+            order.as_ref(),
+        );
     }
     all
 }
 
 fn build_toolpaths_svg(polylines: &[geometry::Polyline], config: &CamConfig) -> Vec<Toolpath> {
+    build_rest_toolpaths_svg(polylines, config, &[])
+}
+
+/// `build_toolpaths_svg` limited to where the `prior` operations left
+/// material (everywhere when there are none).
+fn build_rest_toolpaths_svg(
+    polylines: &[geometry::Polyline],
+    config: &CamConfig,
+    prior: &[PriorCut],
+) -> Vec<Toolpath> {
     let cut_params = CutParams {
         tool: tool_from_config(config),
         tool_diameter: config.tool_diameter,
@@ -530,13 +1472,25 @@ fn build_toolpaths_svg(polylines: &[geometry::Polyline], config: &CamConfig) ->
         cut_z: config.cut_depth,
         climb_cut: config.climb_cut,
         perimeter_passes: config.perimeter_passes,
+        entry: entry_mode_from_config(config),
+        ramp_angle: config.ramp_angle,
+        tabs: tabs_from_config(config),
+        final_z: Some(config.cut_depth),
+        leads: leads_from_config(config),
     };
     let strategy = strategy_from_config(config);
     let is_laser = config.machine_type == "laser_cutter";
+    let rest =
+        (!prior.is_empty()).then(|| RestStock::for_contours(polylines, &cut_params.tool, prior));
 
+    let order = order_params_from_config(config);
     let mut all = Vec::new();
-    if is_laser {
-        all.extend(strategy.generate(polylines, &cut_params));
+    if is_laser || full_depth_in_one_pass(&config.strategy) {
+        push_pass(
+            &mut all,
+            strategy.generate(polylines, &cut_params),
+            order.as_ref(),
+        );
     } else {
         let mut z = 0.0;
         while z > config.cut_depth - 0.001 {
@@ -546,7 +1500,14 @@ fn build_toolpaths_svg(polylines: &[geometry::Polyline], config: &CamConfig) ->
             }
             let mut p = cut_params.clone();
             p.cut_z = z;
-            all.extend(strategy.generate(polylines, &p));
+            let pass = match &rest {
+                Some(rest) => {
+                    let contours = rest.region_at(polylines, z, &cut_params.tool);
+                    strategy.generate(&contours, &p)
+                }
+                None => strategy.generate(polylines, &p),
+            };
+            push_pass(&mut all, pass, order.as_ref());
             if (z - config.cut_depth).abs() < 0.001 {
                 break;
             }
@@ -555,6 +1516,44 @@ fn build_toolpaths_svg(polylines: &[geometry::Polyline], config: &CamConfig) ->
     all
 }
 
+/// Strategies whose cuts may run either way and start anywhere: contours
+/// followed on the line or offset around a loop, and laser cuts.
+const FREE_DIRECTION_STRATEGIES: &[&str] = &["contour", "laser_cut"];
+
+/// How to reorder each pass, or `None` to keep the planned order.
+/// Perimeter passes always keep theirs, working inward. Profiles with
+/// leads or compensation are never reversed or restarted, which would
+/// put the leads on the wrong side.
+fn order_params_from_config(config: &CamConfig) -> Option<OrderParams> {
+    if !config.optimize_order || config.strategy == "perimeter" {
+        return None;
+    }
+    let free = FREE_DIRECTION_STRATEGIES.contains(&config.strategy.as_str())
+        && !(config.strategy == "contour" && leads_from_config(config).active());
+    Some(OrderParams {
+        reverse: free,
+        start_points: free && config.optimize_start_points,
+    })
+}
+
+/// Append one pass of toolpaths, reordered from where the last one ended
+/// when `order` is set. Passes never mix, so every layer is finished
+/// before the next one down.
+fn push_pass(all: &mut Vec<Toolpath>, pass: Vec<Toolpath>, order: Option<&OrderParams>) {
+    let Some(params) = order else {
+        all.extend(pass);
+        return;
+    };
+    let start = all
+        .iter()
+        .rev()
+        .find_map(|tp| tp.moves.last())
+        .map_or(geometry::Vec2::new(0.0, 0.0), |m| {
+            geometry::Vec2::new(m.x, m.y)
+        });
+    all.extend(toolpath::order_toolpaths(pass, start, params));
+}
+
 /// Flatten moves to JSON (testable helper).
 fn flatten_moves_impl(toolpaths: &[Toolpath]) -> Result<String, String> {
     let moves: Vec<&geometry::ToolpathMove> =
@@ -691,6 +1690,7 @@ pub fn sketch_snapshot_impl() -> Result<String, String> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use machine::MachineType;
 
     #[test]
     fn test_req_003_config_default_tool_type() {
@@ -794,6 +1794,491 @@ mod tests {
         assert_eq!(profile.machine_type, MachineType::LaserCutter);
     }
 
+    #[test]
+    fn test_output_profile_from_config_post() {
+        let profile = output_profile_from_config(&CamConfig::default()).unwrap();
+        assert_eq!(profile.output_config.post, PostFormat::default());
+
+        let config = CamConfig {
+            post_processor: Some("grbl".into()),
+            ..CamConfig::default()
+        };
+        let profile = output_profile_from_config(&config).unwrap();
+        assert_eq!(profile.output_config.post.name, "GRBL");
+
+        let config = CamConfig {
+            post_processor: Some("grbl".into()),
+            custom_post: Some("name = \"Shop\"\ndecimals = 2".into()),
+            ..CamConfig::default()
+        };
+        let profile = output_profile_from_config(&config).unwrap();
+        assert_eq!(profile.output_config.post.name, "Shop");
+        assert_eq!(profile.output_config.post.decimals, 2);
+
+        let config = CamConfig {
+            post_processor: Some("heidenhain".into()),
+            ..CamConfig::default()
+        };
+        let err = output_profile_from_config(&config).unwrap_err();
+        assert!(err.contains("heidenhain"));
+    }
+
+    #[test]
+    fn test_process_svg_with_post_processor() {
+        let gcode = process_svg_impl(simple_svg(), r#"{"post_processor":"marlin"}"#).unwrap();
+        assert!(!gcode.contains('('), "Marlin output has no paren comments");
+        assert!(gcode.contains("; RustCAM"));
+        assert!(gcode.contains(" Z-1.000 F300\n"), "{}", gcode);
+
+        let custom = r#"{"custom_post": "{\"line_numbers\": true, \"program_end\": \"M30\"}"}"#;
+        let gcode = process_svg_impl(simple_svg(), custom).unwrap();
+        assert!(gcode.contains("N10 G21 (metric)"));
+        assert!(gcode.trim_end().ends_with("M30"));
+
+        let err = process_svg_impl(simple_svg(), r#"{"custom_post": "decimals = ["}"#);
+        assert!(err.unwrap_err().contains("post-processor"));
+    }
+
+    fn two_tool_job(post: &str) -> String {
+        format!(
+            r#"{{
+                "post_processor": {post},
+                "tools": {{
+                    "1": {{"name": "6mm flat", "tool": {{"tool_type": {{"type": "end_mill"}}, "diameter": 6.0, "flute_length": 20.0, "corner_radius": 0.0}}}},
+                    "2": {{"name": "2mm flat", "tool": {{"tool_type": {{"type": "end_mill"}}, "diameter": 2.0, "flute_length": 8.0, "corner_radius": 0.0}}}}
+                }},
+                "operations": [
+                    {{"name": "Clear", "tool": 1, "strategy": "pocket", "cut_depth": -2.0, "step_down": 1.0}},
+                    {{"name": "Finish walls", "tool": 2, "strategy": "contour", "cut_depth": -2.0, "spindle_speed": 20000}}
+                ]
+            }}"#
+        )
+    }
+
+    /// A 40 mm square on OUTLINE and a 10 mm hole in its middle on HOLES.
+    fn layered_dxf() -> &'static str {
+        "  0\nSECTION\n  2\nENTITIES\n\
+         0\nLWPOLYLINE\n  8\nOUTLINE\n 90\n4\n 70\n1\n\
+         10\n0\n 20\n0\n 10\n40\n 20\n0\n 10\n40\n 20\n40\n 10\n0\n 20\n40\n\
+         0\nCIRCLE\n  8\nHoles\n 10\n20\n 20\n20\n 40\n5\n\
+         0\nENDSEC\n  0\nEOF\n"
+    }
+
+    #[test]
+    fn test_dxf_is_2d_input_filtered_by_layer() {
+        let preview = |config: &str| -> Vec<Vec<[f64; 2]>> {
+            let json: serde_json::Value =
+                serde_json::from_str(&preview_svg_impl(layered_dxf(), config).unwrap()).unwrap();
+            serde_json::from_value(json["paths"].clone()).unwrap()
+        };
+        assert_eq!(preview("{}").len(), 2);
+        let holes = preview(r#"{"layers": ["HOLES"]}"#);
+        assert_eq!(holes.len(), 1);
+        assert!(holes[0]
+            .iter()
+            .all(|p| (p[0] - 20.0).hypot(p[1] - 20.0) < 5.0 + 1e-9));
+
+        let gcode = process_svg_impl(layered_dxf(), r#"{"layers": ["outline"]}"#).unwrap();
+        assert!(gcode.contains("G1"));
+
+        // Each job operation picks its own layers.
+        let job = two_tool_job("null")
+            .replace(
+                r#""strategy": "pocket","#,
+                r#""strategy": "pocket", "layers": ["holes"],"#,
+            )
+            .replace(
+                r#""strategy": "contour","#,
+                r#""strategy": "contour", "layers": ["dims"],"#,
+            );
+        let err = process_job_svg_impl(layered_dxf(), &job).unwrap_err();
+        assert!(
+            err.starts_with("Operation 2:") && err.contains("dims"),
+            "{}",
+            err
+        );
+
+        let import: serde_json::Value =
+            serde_json::from_str(&import_dxf_impl(layered_dxf(), "[]").unwrap()).unwrap();
+        assert_eq!(import["layers"], serde_json::json!(["Holes", "OUTLINE"]));
+        assert_eq!(import["polylines"].as_array().unwrap().len(), 2);
+    }
+
+    /// A red-stroked square with a blue-filled hole in its middle.
+    fn colored_svg() -> &'static str {
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="40mm" height="40mm" viewBox="0 0 400 400">
+            <rect width="400" height="400" fill="none" stroke="red"/>
+            <circle cx="200" cy="200" r="50" style="fill:#00f"/>
+        </svg>"#
+    }
+
+    #[test]
+    fn test_svg_is_2d_input_filtered_by_color() {
+        let preview = |config: &str| -> Vec<Vec<[f64; 2]>> {
+            let json: serde_json::Value =
+                serde_json::from_str(&preview_svg_impl(colored_svg(), config).unwrap()).unwrap();
+            serde_json::from_value(json["paths"].clone()).unwrap()
+        };
+        assert_eq!(preview("{}").len(), 2);
+        // viewBox units come out in millimetres.
+        let hole = preview(r#"{"colors": ["blue"]}"#);
+        assert_eq!(hole.len(), 1);
+        assert!(hole[0]
+            .iter()
+            .all(|p| (p[0] - 20.0).hypot(p[1] - 20.0) < 5.0 + 1e-9));
+
+        let job = two_tool_job("null").replace(
+            r#""strategy": "contour","#,
+            r##""strategy": "contour", "colors": ["#00ff00"],"##,
+        );
+        let err = process_job_svg_impl(colored_svg(), &job).unwrap_err();
+        assert!(err.starts_with("Operation 2:"), "{}", err);
+
+        let colors: Vec<String> =
+            serde_json::from_str(&svg_colors_impl(colored_svg()).unwrap()).unwrap();
+        assert_eq!(colors, vec!["#0000ff", "#ff0000"]);
+    }
+
+    #[test]
+    fn test_optimize_order_shortens_rapids_per_pass() {
+        // Holes listed after their plates, plates far-near-far.
+        let svg = r#"<svg xmlns="http://www.w3.org/2000/svg" width="240mm" height="40mm" viewBox="0 0 240 40">
+            <rect x="200" y="0" width="40" height="40"/>
+            <rect x="0" y="0" width="40" height="40"/>
+            <rect x="100" y="0" width="40" height="40"/>
+            <circle cx="220" cy="20" r="5"/>
+            <circle cx="20" cy="20" r="5"/>
+            <circle cx="120" cy="20" r="5"/>
+        </svg>"#;
+        let config = r#"{"cut_depth": -2, "step_down": 1, "optimize_order": true, "optimize_start_points": true}"#;
+        let report: serde_json::Value =
+            serde_json::from_str(&rapid_report_svg_impl(svg, config).unwrap()).unwrap();
+        let (before, after) = (
+            report["before"].as_f64().unwrap(),
+            report["after"].as_f64().unwrap(),
+        );
+        assert!(after < before * 0.7, "{} -> {}", before, after);
+
+        let config: CamConfig = serde_json::from_str(config).unwrap();
+        let polylines = parse_2d_input(svg, &config).unwrap();
+        let toolpaths = build_toolpaths_svg(&polylines, &config);
+        assert_eq!(toolpaths.len(), 12);
+        let depth = |tp: &Toolpath| tp.moves.iter().map(|m| m.z).fold(f64::MAX, f64::min);
+        let width = |tp: &Toolpath| {
+            let xs = tp.moves.iter().map(|m| m.x);
+            xs.clone().fold(f64::MIN, f64::max) - xs.fold(f64::MAX, f64::min)
+        };
+        for (i, tp) in toolpaths.iter().enumerate() {
+            // The first layer is finished before the second starts.
+            assert_eq!(depth(tp), if i < 6 { -1.0 } else { -2.0 });
+        }
+        // Every plate comes right after its hole.
+        for pair in toolpaths.chunks(2) {
+            assert!(width(&pair[0]) < 20.0 && width(&pair[1]) > 40.0);
+            assert!((pair[1].moves[0].x - pair[0].moves[0].x).abs() < 30.0);
+        }
+        // Perimeter passes stay as planned.
+        let perimeter = CamConfig {
+            strategy: "perimeter".into(),
+            ..config.clone()
+        };
+        assert!(order_params_from_config(&perimeter).is_none());
+    }
+
+    #[test]
+    fn test_text_engraves_one_program_per_serial() {
+        let text = r#"{"text": "SN-{serial}", "size": 4, "serial": {"start": 7, "count": 2, "digits": 3}}"#;
+        let config = r#"{"cut_depth": -0.3, "step_down": 0.3}"#;
+        let parts: serde_json::Value =
+            serde_json::from_str(&process_text_serials_impl(text, config).unwrap()).unwrap();
+        let parts = parts.as_array().unwrap();
+        assert_eq!(parts.len(), 2);
+        assert_eq!(parts[1]["text"], "SN-008");
+        assert_ne!(parts[0]["gcode"], parts[1]["gcode"]);
+        // Single strokes are followed on the line, never offset.
+        let gcode = process_text_impl(text, config).unwrap();
+        assert_eq!(gcode, parts[0]["gcode"].as_str().unwrap());
+        assert!(gcode.contains("Z-0.3"), "{}", gcode);
+
+        let outlines: Vec<geometry::Polyline> =
+            serde_json::from_str(&text_outlines_impl(text).unwrap()).unwrap();
+        assert!(outlines.iter().all(|o| !o.closed));
+        assert!(outlines
+            .iter()
+            .flat_map(|o| &o.points)
+            .all(|p| (-1e-9..=4.0 + 1e-9).contains(&p.y)));
+        assert!(process_text_impl(r#"{"text": "x", "font": "gothic"}"#, config).is_err());
+    }
+
+    #[test]
+    fn test_setup_places_input_in_the_work_frame() {
+        // Stock fitted to the whole drawing with 5 mm around it, 10 mm
+        // thick, touched off on the front left corner of its bottom.
+        let setup = r#""setup": {"stock": {"size": [0, 0, 10], "margin": 5}, "origin_z": "bottom", "work_offset": "G55"}"#;
+        let config = format!(
+            r#"{{"strategy": "contour", "cut_depth": -2, "step_down": 2, "layers": ["holes"], {setup}}}"#
+        );
+        let gcode = process_svg_impl(layered_dxf(), &config).unwrap();
+        assert!(gcode.contains("G55 (work offset)"), "{}", gcode);
+        assert!(
+            gcode.contains("Z8.000") && !gcode.contains("Z-"),
+            "{}",
+            gcode
+        );
+
+        let moves: Vec<geometry::ToolpathMove> =
+            serde_json::from_str(&sim_moves_svg_impl(layered_dxf(), &config).unwrap()).unwrap();
+        let cuts: Vec<_> = moves.iter().filter(|m| !m.rapid).collect();
+        assert!(cuts.iter().all(|m| m.z >= 8.0 - 1e-9));
+        // The hole keeps its place in the drawing, 25 mm from the corner.
+        assert!(cuts
+            .iter()
+            .all(|m| ((m.x - 25.0).hypot(m.y - 25.0) - 5.0).abs() < 3.5));
+
+        let plain = process_svg_impl(layered_dxf(), r#"{"layers": ["holes"]}"#).unwrap();
+        assert!(!plain.contains("work offset"));
+
+        // Every operation of a job shares the job's setup.
+        let job = two_tool_job("null").replacen("{", &format!("{{{setup},"), 1);
+        let gcode = process_job_svg_impl(simple_svg(), &job).unwrap();
+        assert_eq!(gcode.matches("G55 (work offset)").count(), 1);
+        assert!(!gcode.contains("Z-"), "{}", gcode);
+        let setup_err = job.replace(r#""margin": 5"#, r#""margin": -1"#);
+        assert!(process_job_svg_impl(simple_svg(), &setup_err).is_err());
+    }
+
+    #[test]
+    fn test_profile_leads_and_controller_comp() {
+        let cuts = |config: &str| {
+            let moves: Vec<geometry::ToolpathMove> =
+                serde_json::from_str(&sim_moves_svg_impl(simple_svg(), config).unwrap()).unwrap();
+            moves.into_iter().filter(|m| !m.rapid).collect::<Vec<_>>()
+        };
+        let plain = cuts(r#"{"strategy": "contour"}"#);
+        let arcs = cuts(r#"{"strategy": "contour", "lead_in": "arc", "lead_out": "arc"}"#);
+        assert!(arcs.len() > plain.len());
+
+        // Under controller compensation the program follows the drawing
+        // itself and the controller applies the tool radius.
+        let config = r#"{"strategy": "contour", "compensation": "controller", "lead_in": "line"}"#;
+        let gcode = process_svg_impl(simple_svg(), config).unwrap();
+        assert!(
+            gcode.contains("G41 D1") || gcode.contains("G42 D1"),
+            "{}",
+            gcode
+        );
+        assert!(gcode.contains("G40 "), "{}", gcode);
+        assert!(cuts(config)
+            .iter()
+            .filter(|m| m.comp.is_some())
+            .any(|m| (m.x - 10.0).abs() < 1e-6));
+
+        let grbl =
+            r#"{"strategy": "contour", "compensation": "controller", "post_processor": "grbl"}"#;
+        assert!(process_svg_impl(simple_svg(), grbl)
+            .unwrap_err()
+            .contains("cutter compensation"));
+    }
+
+    #[test]
+    fn test_process_image_engraves_scanlines() {
+        // 2x2 gray: black, mid gray / white, white (unfiltered lines).
+        let png = image::tests::png(2, 2, 8, 0, &[0, 0, 128, 0, 255, 255]);
+        let laser =
+            r#"{"machine_type": "laser_cutter", "laser_power": 80, "post_processor": "grbl"}"#;
+        let options = r#"{"width": 2, "line_interval": 1, "dither": "grayscale", "overscan": 1}"#;
+        let gcode = process_image_impl(&png, options, laser).unwrap();
+        // GRBL runs S0-1000: full black at 80%, the gray at about 40%.
+        assert!(gcode.contains("G0 X-1.000 Y1.500 S0"), "{}", gcode);
+        assert!(gcode.contains("G1 X1.000 Y1.500 F800 S800"), "{}", gcode);
+        assert!(gcode.contains("G1 X2.000 Y1.500 F800 S398"), "{}", gcode);
+        assert!(gcode.contains("G1 X3.000 Y1.500 F800 S0"), "{}", gcode);
+        assert!(!gcode.contains("Y0.5"), "blank line engraved: {}", gcode);
+
+        let setup = r#""setup": {"stock": {"size": [10, 10, 3]}, "origin": "center"}"#;
+        let placed = laser.replacen('{', &format!("{{{setup}, "), 1);
+        let moves: Vec<geometry::ToolpathMove> =
+            serde_json::from_str(&sim_moves_image_impl(&png, options, &placed).unwrap()).unwrap();
+        // The image is centred on the stock, so the row sits 0.5 mm up.
+        assert!(moves.iter().all(|m| (m.y - 0.5).abs() < 1e-9));
+
+        let err = process_image_impl(&png, options, "{}").unwrap_err();
+        assert!(err.contains("laser"), "{}", err);
+        assert!(process_image_impl(b"GIF89a", options, laser).is_err());
+    }
+
+    #[test]
+    fn test_process_job_svg_changes_tools() {
+        let gcode = process_job_svg_impl(simple_svg(), &two_tool_job("null")).unwrap();
+        let op1 = gcode.find("(Operation 1: Clear)").unwrap();
+        let t1 = gcode.find("T1 M6").unwrap();
+        let op2 = gcode.find("(Operation 2: Finish walls)").unwrap();
+        let t2 = gcode.find("T2 M6").unwrap();
+        assert!(op1 < t1 && t1 < op2 && op2 < t2, "{}", gcode);
+        assert!(gcode.contains("G43 H2"));
+        assert!(gcode.contains("(T2 2mm flat D2.000)"));
+        assert!(gcode[t2..].contains("M3 S20000"));
+        assert_eq!(gcode.matches("G21").count(), 1, "one program header");
+    }
+
+    #[test]
+    fn test_cycle_time_job_per_operation() {
+        let json = cycle_time_job_svg_impl(simple_svg(), &two_tool_job("null")).unwrap();
+        let time: serde_json::Value = serde_json::from_str(&json).unwrap();
+        let ops = time["operations"].as_array().unwrap();
+        let names: Vec<&str> = ops.iter().map(|o| o["name"].as_str().unwrap()).collect();
+        assert_eq!(names, ["Operation 1: Clear", "Operation 2: Finish walls"]);
+        let sum: f64 = ops.iter().map(|o| o["time"].as_f64().unwrap()).sum();
+        assert!((sum - time["total"].as_f64().unwrap()).abs() < 1e-6);
+        assert!(ops.iter().all(|o| o["time"].as_f64().unwrap() > 0.0));
+    }
+
+    #[test]
+    fn test_process_job_uses_job_post_and_checks_tools() {
+        let gcode = process_job_svg_impl(simple_svg(), &two_tool_job("\"grbl\"")).unwrap();
+        assert!(gcode.contains("M0 (change to T2)"));
+
+        let missing = two_tool_job("null").replace("\"tool\": 2,", "\"tool\": 9,");
+        let err = process_job_svg_impl(simple_svg(), &missing).unwrap_err();
+        assert!(err.contains("T9"), "{}", err);
+
+        let laser_only = two_tool_job("null").replace("\"contour\"", "\"laser_cut\"");
+        let err = process_job_svg_impl(simple_svg(), &laser_only).unwrap_err();
+        assert!(err.starts_with("Operation 2:"), "{}", err);
+    }
+
+    #[test]
+    fn test_job_rest_machining_cuts_only_the_corners() {
+        let job = two_tool_job("null").replace(
+            r#""strategy": "contour", "cut_depth": -2.0"#,
+            r#""strategy": "pocket", "rest_machining": true, "cut_depth": -2.0, "step_down": 1.0"#,
+        );
+        let (sections, _) = job_sections_svg(simple_svg(), &job).unwrap();
+        let full = two_tool_job("null").replace(r#""tool": 1, "#, r#""tool": 2, "#);
+        let (full, _) = job_sections_svg(simple_svg(), &full).unwrap();
+        let cut_length = |tps: &[Toolpath]| -> f64 {
+            let moves: Vec<_> = tps.iter().flat_map(|tp| &tp.moves).collect();
+            moves
+                .windows(2)
+                .filter(|w| !w[1].rapid)
+                .map(|w| (w[1].x - w[0].x).hypot(w[1].y - w[0].y))
+                .sum()
+        };
+        let rest = cut_length(&sections[1].toolpaths);
+        assert!(rest > 0.0);
+        assert!(rest < 0.2 * cut_length(&full[0].toolpaths), "{rest}");
+        // Every rest cut stays near a corner of the 10..90 square.
+        for m in sections[1].toolpaths.iter().flat_map(|tp| &tp.moves) {
+            let corner = |v: f64| (v - 10.0).abs().min((v - 90.0).abs());
+            assert!(m.rapid || corner(m.x).max(corner(m.y)) < 8.0, "{:?}", m);
+        }
+        assert!(process_job_svg_impl(simple_svg(), &job)
+            .unwrap()
+            .contains("(Rest machining after the earlier operations)"));
+
+        let first = job.replace(
+            r#""tool": 1, "strategy": "pocket""#,
+            r#""tool": 1, "rest_machining": true, "strategy": "pocket""#,
+        );
+        assert!(process_job_svg_impl(simple_svg(), &first).is_err());
+        let contour = job.replace(
+            r#""strategy": "pocket", "rest_machining""#,
+            r#""strategy": "contour", "rest_machining""#,
+        );
+        let err = process_job_svg_impl(simple_svg(), &contour).unwrap_err();
+        assert!(err.contains("Operation 2"), "{err}");
+    }
+
+    #[test]
+    fn test_process_job_stl() {
+        let gcode = process_job_stl_impl(&minimal_binary_stl(), &two_tool_job("null")).unwrap();
+        assert!(gcode.contains("T1 M6") && gcode.contains("T2 M6"));
+    }
+
+    fn holes_svg() -> &'static str {
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="100mm" height="100mm" viewBox="0 0 100 100">
+            <rect x="0" y="0" width="100" height="60"/>
+            <circle cx="80" cy="30" r="2.5"/>
+            <circle cx="10" cy="10" r="2.5"/>
+            <circle cx="50" cy="30" r="20"/>
+            <path d="M 20 50"/>
+        </svg>"#
+    }
+
+    #[test]
+    fn test_process_svg_drill_emits_canned_cycles() {
+        let config =
+            r#"{"strategy": "drill", "cut_depth": -6.0, "step_down": 1.0, "peck_depth": 2.0}"#;
+        let gcode = process_svg_impl(holes_svg(), config).unwrap();
+        let cycles: Vec<&str> = gcode
+            .lines()
+            .filter(|l| l.starts_with("G98") || l.starts_with('X'))
+            .collect();
+        // Nearest first from the origin; the large circle is not a hole.
+        assert_eq!(
+            cycles,
+            [
+                "G98 G83 X10.0000 Y10.0000 Z-6.0000 R1.0000 Q2.0000 F300",
+                "X20.0000 Y50.0000",
+                "X80.0000 Y30.0000",
+            ],
+            "{}",
+            gcode
+        );
+        assert_eq!(gcode.matches("G80").count(), 1);
+    }
+
+    #[test]
+    fn test_process_svg_drill_expands_for_grbl() {
+        let config = r#"{"strategy": "drill", "cut_depth": -3.0, "dwell": 0.5,
+            "tool_type": "drill", "tip_angle": 90.0, "tip_compensation": true,
+            "tool_diameter": 5.0, "post_processor": "grbl"}"#;
+        let gcode = process_svg_impl(holes_svg(), config).unwrap();
+        assert!(!gcode.contains("G8"), "{}", gcode);
+        assert_eq!(gcode.matches("G4 P0.500").count(), 3);
+        // 90 degree point on a 5 mm drill: 2.5 mm deeper.
+        assert!(
+            gcode.contains("G1 X10.000 Y10.000 Z-5.500 F300"),
+            "{}",
+            gcode
+        );
+    }
+
+    #[test]
+    fn test_drill_rejects_stl() {
+        let err = process_stl_impl(&minimal_binary_stl(), r#"{"strategy": "drill"}"#);
+        assert!(err.unwrap_err().contains("2D"));
+    }
+
+    #[test]
+    fn test_process_svg_vcarve() {
+        let svg = r#"<svg xmlns="http://www.w3.org/2000/svg" width="40mm" height="4mm" viewBox="0 0 40 4">
+            <rect x="0" y="0" width="40" height="4"/>
+        </svg>"#;
+        let config = r#"{"strategy": "vcarve", "tool_type": "vbit", "vbit_angle": 90.0,
+            "tool_diameter": 12.0, "cut_depth": -5.0, "step_down": 5.0}"#;
+        let gcode = process_svg_impl(svg, config).unwrap();
+        // One pass; the 4 mm strip takes a 90 degree bit 2 mm deep.
+        let zs: Vec<f64> = gcode
+            .lines()
+            .filter(|l| l.starts_with("G1"))
+            .filter_map(|l| l.split_whitespace().find(|w| w.starts_with('Z')))
+            .map(|w| w[1..].parse().unwrap())
+            .collect();
+        let deepest = zs.iter().cloned().fold(0.0, f64::min);
+        assert!((deepest + 2.0).abs() < 1e-3, "{}", gcode);
+
+        let err = process_stl_impl(&minimal_binary_stl(), r#"{"strategy": "vcarve"}"#);
+        assert!(err.unwrap_err().contains("2D"));
+    }
+
+    #[test]
+    fn test_available_post_processors() {
+        let posts: Vec<PostFormat> = serde_json::from_str(&available_post_processors()).unwrap();
+        let names: Vec<&str> = posts.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, ["Generic", "GRBL", "LinuxCNC", "Mach3", "Marlin"]);
+    }
+
     #[test]
     fn test_laser_rejects_stl_3d_strategy() {
         let config = CamConfig {
@@ -818,7 +2303,7 @@ mod tests {
 
     #[test]
     fn test_svg_laser_cut_produces_gcode() {
-        let svg = r#"<svg xmlns="http://www.w3.org/2000/svg" width="100" height="100">
+        let svg = r#"<svg xmlns="http://www.w3.org/2000/svg" width="100mm" height="100mm" viewBox="0 0 100 100">
             <rect x="10" y="10" width="80" height="80"/>
         </svg>"#;
         let config_json =
@@ -833,7 +2318,7 @@ mod tests {
 
     #[test]
     fn test_svg_laser_engrave_produces_scanlines() {
-        let svg = r#"<svg xmlns="http://www.w3.org/2000/svg" width="100" height="100">
+        let svg = r#"<svg xmlns="http://www.w3.org/2000/svg" width="100mm" height="100mm" viewBox="0 0 100 100">
             <rect x="10" y="10" width="80" height="80"/>
         </svg>"#;
         let config_json = r#"{"machine_type": "laser_cutter", "strategy": "laser_engrave", "laser_power": 60, "step_over": 2.0}"#;
@@ -845,7 +2330,7 @@ mod tests {
 
     #[test]
     fn test_svg_cnc_mill_still_works() {
-        let svg = r#"<svg xmlns="http://www.w3.org/2000/svg" width="100" height="100">
+        let svg = r#"<svg xmlns="http://www.w3.org/2000/svg" width="100mm" height="100mm" viewBox="0 0 100 100">
             <rect x="10" y="10" width="80" height="80"/>
         </svg>"#;
         let config_json = r#"{"strategy": "contour"}"#;
@@ -958,7 +2443,7 @@ mod tests {
     // ── CAM function coverage tests ─────────────────────────────────
 
     fn simple_svg() -> &'static str {
-        r#"<svg xmlns="http://www.w3.org/2000/svg" width="100" height="100">
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="100mm" height="100mm" viewBox="0 0 100 100">
             <rect x="10" y="10" width="80" height="80"/>
         </svg>"#
     }
@@ -983,13 +2468,45 @@ endsolid test"
 
     #[test]
     fn test_preview_svg() {
-        let result = preview_svg_impl(simple_svg());
+        let result = preview_svg_impl(simple_svg(), "{}");
         assert!(result.is_ok());
         let json = result.unwrap();
-        let paths: Vec<Vec<[f64; 2]>> = serde_json::from_str(&json).unwrap();
+        let preview: serde_json::Value = serde_json::from_str(&json).unwrap();
+        let paths: Vec<Vec<[f64; 2]>> = serde_json::from_value(preview["paths"].clone()).unwrap();
         assert!(!paths.is_empty());
     }
 
+    #[test]
+    fn test_preview_svg_lists_tabs() {
+        let config = r#"{"tab_count": 4, "cut_depth": -3.0}"#;
+        let json = preview_svg_impl(simple_svg(), config).unwrap();
+        let preview: serde_json::Value = serde_json::from_str(&json).unwrap();
+        let tabs = preview["tabs"].as_array().unwrap();
+        assert_eq!(tabs.len(), 4);
+        for tab in tabs {
+            assert_eq!(tab["width"], 4.0);
+            assert_eq!(tab["height"], 1.0);
+        }
+        let none = preview_svg_impl(simple_svg(), "{}").unwrap();
+        let preview: serde_json::Value = serde_json::from_str(&none).unwrap();
+        assert!(preview["tabs"].as_array().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_sim_moves_svg_marks_tab_moves() {
+        let config = r#"{"tab_count": 3, "cut_depth": -3.0, "tab_height": 1.0}"#;
+        let json = sim_moves_svg_impl(simple_svg(), config).unwrap();
+        let moves: Vec<serde_json::Value> = serde_json::from_str(&json).unwrap();
+        let tab_moves: Vec<_> = moves.iter().filter(|m| m["tab"] == true).collect();
+        assert!(!tab_moves.is_empty());
+        // Only the passes below the tab top (-2) lift, and they lift to it.
+        for m in tab_moves {
+            assert!((m["z"].as_f64().unwrap() + 2.0).abs() < 1e-9);
+        }
+        let plain = sim_moves_svg_impl(simple_svg(), r#"{"strategy":"contour"}"#).unwrap();
+        assert!(!plain.contains("\"tab\""));
+    }
+
     #[test]
     fn test_preview_stl() {
         let result = preview_stl_impl(minimal_ascii_stl(), "{}");
@@ -1008,6 +2525,40 @@ endsolid test"
         assert!(result.is_ok());
     }
 
+    #[test]
+    fn test_simulate_stock_svg() {
+        let config = r#"{"strategy": "pocket", "cut_depth": -2.0, "step_down": 1.0,
+            "tool_diameter": 6.0, "step_over": 3.0, "sim_resolution": 1.0}"#;
+        let json = simulate_stock_svg_impl(simple_svg(), config).unwrap();
+        let report: serde_json::Value = serde_json::from_str(&json).unwrap();
+        // The 80 mm square is cleared 2 mm deep inside 6 mm of padding.
+        let volume = report["removed_volume"].as_f64().unwrap();
+        assert!(
+            (volume - 80.0 * 80.0 * 2.0).abs() < 0.05 * volume,
+            "{volume}"
+        );
+        assert_eq!(report["rapid_collisions"].as_array().unwrap().len(), 0);
+        assert_eq!(report["gouges"]["count"], 0);
+        let image = &report["image"];
+        assert_eq!(
+            (image["width"].as_u64(), image["height"].as_u64()),
+            (Some(92), Some(92))
+        );
+        assert_eq!(image["pixels"].as_array().unwrap().len(), 92 * 92);
+        assert_eq!(image["min_z"], -2.0);
+    }
+
+    #[test]
+    fn test_simulate_stock_stl() {
+        let config = r#"{"strategy": "surface3d", "tool_diameter": 0.2, "step_over": 0.1,
+            "sim_resolution": 0.05}"#;
+        let json = simulate_stock_stl_impl(minimal_ascii_stl(), config).unwrap();
+        let report: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(report["gouges"]["count"], 0, "{}", report["gouges"]);
+        assert_eq!(report["rapid_collisions"].as_array().unwrap().len(), 0);
+        assert!(simulate_stock_stl_impl(b"solid empty\nendsolid empty", "{}").is_err());
+    }
+
     #[test]
     fn test_flatten_moves_empty() {
         let result = flatten_moves_impl(&[]);
@@ -1058,6 +2609,28 @@ endsolid test"
         ));
     }
 
+    #[test]
+    fn test_entry_mode_from_config() {
+        let mut config = CamConfig::default();
+        assert_eq!(entry_mode_from_config(&config), EntryMode::Plunge);
+        config.entry_mode = "ramp".into();
+        assert_eq!(entry_mode_from_config(&config), EntryMode::Ramp);
+        config.entry_mode = "helix".into();
+        assert_eq!(
+            entry_mode_from_config(&config),
+            EntryMode::Helix {
+                diameter: config.tool_diameter
+            }
+        );
+        config.helix_diameter = Some(2.5);
+        assert_eq!(
+            entry_mode_from_config(&config),
+            EntryMode::Helix { diameter: 2.5 }
+        );
+        config.entry_mode = "bogus".into();
+        assert_eq!(entry_mode_from_config(&config), EntryMode::Plunge);
+    }
+
     #[test]
     fn test_laser_params_from_config() {
         let config = CamConfig::default();
@@ -1081,6 +2654,8 @@ endsolid test"
         for s in &[
             "contour",
             "pocket",
+            "offset_pocket",
+            "adaptive",
             "perimeter",
             "laser_cut",
             "laser_engrave",
@@ -1110,6 +2685,19 @@ endsolid test"
         assert_eq!(default_machine_type(), "cnc_mill");
         assert_eq!(default_scan_direction(), "x");
         assert_eq!(default_perimeter_passes(), 1);
+        assert_eq!(default_max_engagement(), 0.2);
+        assert_eq!(default_entry_mode(), "plunge");
+        assert_eq!(default_ramp_angle(), 3.0);
+        assert_eq!(default_tab_width(), 4.0);
+        assert_eq!(default_tab_height(), 1.0);
+        assert_eq!(default_arc_tolerance(), 0.01);
+        assert_eq!(default_sim_resolution(), 0.5);
+        assert_eq!(default_gouge_tolerance(), 0.01);
+        assert_eq!(default_leftover_tolerance(), 0.1);
+        assert_eq!(default_drill_max_diameter(), 10.0);
+        assert_eq!(default_drill_retract(), 1.0);
+        assert_eq!(default_tip_angle(), 118.0);
+        assert_eq!(default_vbit_angle(), 60.0);
     }
 
     // ── Additional strategy coverage on STL ─────────────────────────
@@ -1218,7 +2806,7 @@ endsolid test"
 
     /// Helper: SVG with a <path> element instead of <rect>.
     fn svg_with_path() -> &'static str {
-        r#"<svg xmlns="http://www.w3.org/2000/svg" width="100" height="100">
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="100mm" height="100mm" viewBox="0 0 100 100">
             <path d="M 0 0 L 10 0 L 10 10 L 0 10 Z"/>
         </svg>"#
     }
@@ -1281,6 +2869,18 @@ endsolid test"
         assert!(result.is_ok());
     }
 
+    #[test]
+    fn test_process_stl_waterline_strategy() {
+        // A flat facet has no walls, so this only checks the wiring.
+        let config_json = r#"{"strategy": "waterline", "slope_limit": 30}"#;
+        assert!(process_stl_impl(minimal_ascii_stl(), config_json).is_ok());
+        let err = process_stl_impl(
+            minimal_ascii_stl(),
+            r#"{"strategy": "waterline", "machine_type": "laser_cutter"}"#,
+        );
+        assert!(err.is_err());
+    }
+
     // Error-path tests use the _impl helpers (returning Result<T, String>)
     // which are fully testable in native mode.
 
@@ -1327,6 +2927,168 @@ endsolid test"
         assert!(result.is_ok());
     }
 
+    #[test]
+    fn test_process_svg_offset_pocket_strategy() {
+        let config_json = r#"{"strategy": "offset_pocket"}"#;
+        let gcode = process_svg_impl(simple_svg(), config_json).unwrap();
+        // One continuous spiral per layer: a single plunge-to-safe-Z retract each.
+        let retracts = gcode
+            .lines()
+            .filter(|l| l.starts_with("G0") && l.contains("Z5.0"))
+            .count();
+        assert!(retracts <= 4, "expected few retracts, got {}", retracts);
+        assert!(gcode.lines().filter(|l| l.starts_with("G1")).count() > 100);
+    }
+
+    #[test]
+    fn test_process_stl_offset_pocket_strategy() {
+        let config_json = r#"{"strategy": "offset_pocket"}"#;
+        let result = process_stl_impl(minimal_ascii_stl(), config_json);
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn test_process_svg_adaptive_strategy() {
+        let config_json = r#"{"strategy": "adaptive", "max_engagement": 0.3}"#;
+        let gcode = process_svg_impl(simple_svg(), config_json).unwrap();
+        assert!(gcode.lines().filter(|l| l.starts_with("G1")).count() > 100);
+    }
+
+    #[test]
+    fn test_process_stl_adaptive_strategy() {
+        let config_json = r#"{"strategy": "adaptive"}"#;
+        let result = process_stl_impl(minimal_ascii_stl(), config_json);
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn test_process_svg_circle_emits_arcs() {
+        let svg = r#"<svg xmlns="http://www.w3.org/2000/svg" width="100mm" height="100mm" viewBox="0 0 100 100"><circle cx="50" cy="50" r="25"/></svg>"#;
+        let gcode = process_svg_impl(svg, r#"{"strategy":"contour"}"#).unwrap();
+        let arcs = gcode
+            .lines()
+            .filter(|l| l.starts_with("G2 ") || l.starts_with("G3 "))
+            .count();
+        // Two half turns per pass instead of ~130 chords.
+        assert_eq!(arcs, 2, "{}", gcode);
+        assert!(gcode.lines().filter(|l| l.starts_with("G1 ")).count() < 5);
+
+        let lines_only =
+            process_svg_impl(svg, r#"{"strategy":"contour","arc_tolerance":0}"#).unwrap();
+        assert!(!lines_only.contains("G2 ") && !lines_only.contains("G3 "));
+    }
+
+    #[test]
+    fn test_interpret_gcode_reads_back_our_programs() {
+        let svg = r#"<svg xmlns="http://www.w3.org/2000/svg" width="100mm" height="100mm" viewBox="0 0 100 100"><circle cx="50" cy="50" r="25"/></svg>"#;
+        let gcode = process_svg_impl(svg, r#"{"strategy":"contour"}"#).unwrap();
+        let json = interpret_gcode_impl(&gcode, "{}").unwrap();
+        let program: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(program["warnings"].as_array().unwrap().len(), 0, "{json}");
+        // The arcs come back as a circle centred where it was drawn.
+        let b = &program["stats"]["cut_bounds"];
+        let (lo, hi) = (b[0][0].as_f64().unwrap(), b[1][0].as_f64().unwrap());
+        assert!(((lo + hi) / 2.0 - 50.0).abs() < 0.1, "{b}");
+        assert!(hi - lo > 50.0);
+
+        let config =
+            r#"{"strategy": "drill", "cut_depth": -6.0, "step_down": 1.0, "peck_depth": 2.0}"#;
+        let gcode = process_svg_impl(holes_svg(), config).unwrap();
+        let program = gcode_interpreter::interpret(&gcode, &InterpreterConfig::default());
+        assert!(program.warnings.is_empty(), "{:?}", program.warnings);
+        let bottoms = program.moves.iter().filter(|m| m.z == -6.0).count();
+        assert_eq!(bottoms, 3);
+        assert!(interpret_gcode_impl("G0 X1", "not json").is_err());
+    }
+
+    #[test]
+    fn test_cycle_time_svg_accounts_for_acceleration() {
+        let time = |config: &str| -> serde_json::Value {
+            serde_json::from_str(&cycle_time_svg_impl(simple_svg(), config).unwrap()).unwrap()
+        };
+        let slow = time(r#"{"strategy": "pocket", "max_acceleration": [50, 50, 50]}"#);
+        let fast = time(r#"{"strategy": "pocket", "max_acceleration": [5000, 5000, 5000]}"#);
+        let (slow_t, fast_t) = (
+            slow["total"].as_f64().unwrap(),
+            fast["total"].as_f64().unwrap(),
+        );
+        assert!(slow_t > fast_t, "{slow_t} <= {fast_t}");
+        assert!(fast_t > fast["naive_total"].as_f64().unwrap());
+        assert_eq!(slow["operations"][0]["name"], "pocket");
+        let profile = slow["profile"].as_array().unwrap();
+        assert_eq!(profile.first().unwrap()["v"], 0.0);
+        assert!((profile.last().unwrap()["t"].as_f64().unwrap() - slow_t).abs() < 1e-6);
+        // The velocity never exceeds the feed rate on cutting moves.
+        let feed = default_feed_rate();
+        let sim: Vec<serde_json::Value> = serde_json::from_str(
+            &sim_moves_svg_impl(simple_svg(), r#"{"strategy": "pocket"}"#).unwrap(),
+        )
+        .unwrap();
+        for s in profile {
+            let m = &sim[s["move"].as_u64().unwrap() as usize];
+            if m["rapid"] == false {
+                assert!(s["v"].as_f64().unwrap() <= feed + 1e-6);
+            }
+        }
+    }
+
+    #[test]
+    fn test_cycle_time_gcode_matches_toolpaths() {
+        let config = r#"{"strategy": "pocket", "arc_tolerance": 0}"#;
+        let gcode = process_svg_impl(simple_svg(), config).unwrap();
+        let from_gcode: serde_json::Value =
+            serde_json::from_str(&cycle_time_gcode_impl(&gcode, config).unwrap()).unwrap();
+        let from_paths: serde_json::Value =
+            serde_json::from_str(&cycle_time_svg_impl(simple_svg(), config).unwrap()).unwrap();
+        let (a, b) = (
+            from_gcode["total"].as_f64().unwrap(),
+            from_paths["total"].as_f64().unwrap(),
+        );
+        // Only the header and footer moves differ.
+        assert!((a - b).abs() < 0.05 * b, "{a} vs {b}");
+    }
+
+    #[test]
+    fn test_process_svg_ramp_entry_has_no_vertical_plunges() {
+        // Feed moves that descend below the stock top without moving in XY.
+        let plunges = |gcode: &str| {
+            let mut last = [0.0; 3];
+            let mut count = 0;
+            for line in gcode
+                .lines()
+                .filter(|l| l.starts_with("G0") || l.starts_with("G1"))
+            {
+                let mut pos = last;
+                for word in line.split_whitespace() {
+                    let axis = match word.chars().next() {
+                        Some('X') => 0,
+                        Some('Y') => 1,
+                        Some('Z') => 2,
+                        _ => continue,
+                    };
+                    pos[axis] = word[1..].parse().unwrap();
+                }
+                if line.starts_with("G1")
+                    && pos[2] < -1e-6
+                    && pos[2] < last[2]
+                    && pos[..2] == last[..2]
+                {
+                    count += 1;
+                }
+                last = pos;
+            }
+            count
+        };
+        let plunge = process_svg_impl(simple_svg(), r#"{"strategy": "pocket"}"#).unwrap();
+        assert!(plunges(&plunge) > 0);
+        let ramp = process_svg_impl(
+            simple_svg(),
+            r#"{"strategy": "pocket", "entry_mode": "ramp", "ramp_angle": 5.0}"#,
+        )
+        .unwrap();
+        assert_eq!(plunges(&ramp), 0);
+    }
+
     #[test]
     fn test_process_svg_perimeter_strategy() {
         let config_json = r#"{"strategy": "perimeter"}"#;
@@ -1408,10 +3170,11 @@ endsolid test"
 
     #[test]
     fn test_preview_svg_returns_2d_coordinates() {
-        let result = preview_svg_impl(svg_with_path());
+        let result = preview_svg_impl(svg_with_path(), "{}");
         assert!(result.is_ok());
         let json = result.unwrap();
-        let paths: Vec<Vec<[f64; 2]>> = serde_json::from_str(&json).unwrap();
+        let preview: serde_json::Value = serde_json::from_str(&json).unwrap();
+        let paths: Vec<Vec<[f64; 2]>> = serde_json::from_value(preview["paths"].clone()).unwrap();
         assert!(!paths.is_empty(), "should produce at least one path");
         for path in &paths {
             assert!(!path.is_empty(), "each path should have points");
@@ -1423,10 +3186,11 @@ endsolid test"
 
     #[test]
     fn test_preview_svg_rect_coordinates_in_range() {
-        let result = preview_svg_impl(simple_svg());
+        let result = preview_svg_impl(simple_svg(), "{}");
         assert!(result.is_ok());
         let json = result.unwrap();
-        let paths: Vec<Vec<[f64; 2]>> = serde_json::from_str(&json).unwrap();
+        let preview: serde_json::Value = serde_json::from_str(&json).unwrap();
+        let paths: Vec<Vec<[f64; 2]>> = serde_json::from_value(preview["paths"].clone()).unwrap();
         // The rect is at x=10,y=10 width=80 height=80, so coords should be in [10, 90]
         for path in &paths {
             for [x, y] in path {
@@ -1876,12 +3640,44 @@ endsolid test"
         assert_eq!(config.machine_type, default_machine_type());
         assert_eq!(config.scan_direction, default_scan_direction());
         assert_eq!(config.perimeter_passes, default_perimeter_passes());
+        assert_eq!(config.max_engagement, default_max_engagement());
+        assert_eq!(config.slope_limit, 0.0);
+        assert_eq!(config.entry_mode, default_entry_mode());
+        assert_eq!(config.ramp_angle, default_ramp_angle());
+        assert!(config.helix_diameter.is_none());
+        assert_eq!(config.tab_count, 0);
+        assert_eq!(config.tab_spacing, 0.0);
+        assert_eq!(config.tab_width, default_tab_width());
+        assert_eq!(config.tab_height, default_tab_height());
+        assert!(!tabs_from_config(&config).enabled());
+        assert_eq!(config.arc_tolerance, default_arc_tolerance());
+        assert_eq!(sim_params_from_config(&config), SimParams::default());
+        assert_eq!(config.drill_max_diameter, 10.0);
+        assert_eq!(config.drill_retract, 1.0);
+        assert_eq!((config.peck_depth, config.dwell), (0.0, 0.0));
+        assert_eq!(config.tip_angle, 118.0);
+        assert!(!config.tip_compensation);
+        assert_eq!((config.vbit_angle, config.tip_diameter), (60.0, 0.0));
+        assert!(!config.flat_bottom);
+        assert!(config.post_processor.is_none());
+        assert!(config.custom_post.is_none());
+        assert!(config.max_velocity.is_none());
+        assert!(config.max_acceleration.is_none());
+        assert!(config.junction_deviation.is_none());
         assert_eq!(config.corner_radius, 0.0);
         assert!(config.effective_diameter.is_none());
         assert!(!config.climb_cut);
         assert!(config.laser_power.is_none());
         assert!(config.passes.is_none());
         assert!(config.air_assist.is_none());
+        assert!(config.layers.is_empty());
+        assert!(config.colors.is_empty());
+        assert!(!config.optimize_order && !config.optimize_start_points);
+        assert!(config.setup.is_none());
+        assert_eq!(config.lead_in, "none");
+        assert_eq!(config.lead_out, "none");
+        assert!(config.lead_radius.is_none());
+        assert_eq!(config.compensation, "computer");
     }
 
     #[test]
@@ -1918,6 +3714,20 @@ endsolid test"
         assert!(result.is_ok());
     }
 
+    #[test]
+    fn test_process_mesh_formats_share_the_pipeline() {
+        let cube = b"v 0 0 0\nv 10 0 0\nv 10 10 0\nv 0 10 0\n\
+            v 0 0 5\nv 10 0 5\nv 10 10 5\nv 0 10 5\n\
+            f 1 4 3 2\nf 5 6 7 8\nf 1 2 6 5\nf 2 3 7 6\nf 3 4 8 7\nf 4 1 5 8\n";
+        let config = r#"{"strategy": "contour", "step_down": 2.0}"#;
+        let gcode = process_obj_impl(cube, config).unwrap();
+        assert!(gcode.contains("G1"));
+        // Detected from the content when handed to the STL entry point.
+        assert_eq!(process_stl_impl(cube, config).unwrap(), gcode);
+        assert!(process_ply_impl(cube, config).unwrap_err().contains("PLY"));
+        assert!(process_3mf_impl(cube, config).is_err());
+    }
+
     #[test]
     fn test_process_stl_impl_invalid_json() {
         let result = process_stl_impl(minimal_ascii_stl(), "not json");
@@ -1982,13 +3792,13 @@ endsolid test"
 
     #[test]
     fn test_preview_svg_impl_success() {
-        let result = preview_svg_impl(simple_svg());
+        let result = preview_svg_impl(simple_svg(), "{}");
         assert!(result.is_ok());
     }
 
     #[test]
     fn test_preview_svg_impl_invalid_svg() {
-        let result = preview_svg_impl("not svg");
+        let result = preview_svg_impl("not svg", "{}");
         assert!(result.is_err());
     }
 
@@ -2157,7 +3967,7 @@ endsolid test"
 
     #[test]
     fn test_preview_svg_invalid_svg() {
-        let result = preview_svg_impl("<svg></svg>");
+        let result = preview_svg_impl("<svg></svg>", "{}");
         assert!(result.is_err());
     }
 
@@ -1,5 +1,6 @@
 //! Machine profile system for CNC mill and laser cutter support.
 
+use crate::gcode::PostFormat;
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
@@ -19,6 +20,28 @@ pub struct MachineCapabilities {
     pub max_feed_rate: f64,
     pub max_spindle_rpm: Option<f64>,
     pub max_laser_power: Option<f64>,
+    /// Per-axis top speed in mm/min (X, Y, Z), also the rapid rate.
+    #[serde(default = "default_max_velocity")]
+    pub max_velocity: [f64; 3],
+    /// Per-axis acceleration in mm/s² (X, Y, Z).
+    #[serde(default = "default_max_acceleration")]
+    pub max_acceleration: [f64; 3],
+    /// How far the path may stray from a corner when cornering at speed
+    /// (mm), as in GRBL's `$11`.
+    #[serde(default = "default_junction_deviation")]
+    pub junction_deviation: f64,
+}
+
+fn default_max_velocity() -> [f64; 3] {
+    [5000.0, 5000.0, 2000.0]
+}
+
+fn default_max_acceleration() -> [f64; 3] {
+    [500.0, 500.0, 200.0]
+}
+
+fn default_junction_deviation() -> f64 {
+    0.01
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -27,6 +50,9 @@ pub struct OutputConfig {
     pub postamble: Vec<String>,
     pub unit_mode: String,
     pub distance_mode: String,
+    /// Controller dialect used to format the program.
+    #[serde(default)]
+    pub post: PostFormat,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -47,10 +73,15 @@ impl MachineProfile {
                 available_strategies: vec![
                     "contour".into(),
                     "pocket".into(),
+                    "offset_pocket".into(),
+                    "adaptive".into(),
                     "slice".into(),
                     "zigzag".into(),
                     "surface3d".into(),
+                    "waterline".into(),
                     "perimeter".into(),
+                    "drill".into(),
+                    "vcarve".into(),
                 ],
                 has_spindle: true,
                 has_laser_power: false,
@@ -58,17 +89,16 @@ impl MachineProfile {
                 max_feed_rate: 10000.0,
                 max_spindle_rpm: Some(30000.0),
                 max_laser_power: None,
+                max_velocity: default_max_velocity(),
+                max_acceleration: default_max_acceleration(),
+                junction_deviation: default_junction_deviation(),
             },
             output_config: OutputConfig {
-                preamble: vec!["G21 (metric)".into(), "G90 (absolute positioning)".into()],
-                postamble: vec![
-                    "M5 (spindle off)".into(),
-                    "M9 (coolant off)".into(),
-                    "G0 X0 Y0".into(),
-                    "M2 (program end)".into(),
-                ],
+                preamble: Vec::new(),
+                postamble: vec!["M9 (coolant off)".into(), "G0 X0 Y0".into()],
                 unit_mode: "G21".into(),
                 distance_mode: "G90".into(),
+                post: PostFormat::default(),
             },
         }
     }
@@ -92,20 +122,16 @@ impl MachineProfile {
                 max_feed_rate: 20000.0,
                 max_spindle_rpm: None,
                 max_laser_power: Some(100.0),
+                max_velocity: [20000.0, 20000.0, 1000.0],
+                max_acceleration: [3000.0, 3000.0, 100.0],
+                junction_deviation: 0.02,
             },
             output_config: OutputConfig {
-                preamble: vec![
-                    "G21 (metric)".into(),
-                    "G90 (absolute positioning)".into(),
-                    "M4 S0 (dynamic laser mode)".into(),
-                ],
-                postamble: vec![
-                    "M5 (laser off)".into(),
-                    "G0 X0 Y0".into(),
-                    "M2 (program end)".into(),
-                ],
+                preamble: Vec::new(),
+                postamble: vec!["G0 X0 Y0".into()],
                 unit_mode: "G21".into(),
                 distance_mode: "G90".into(),
+                post: PostFormat::default(),
             },
         }
     }
@@ -123,7 +149,7 @@ impl MachineProfile {
     pub fn validate_strategy(&self, strategy: &str) -> Result<(), String> {
         // 3D strategies are not valid for laser cutters
         if self.machine_type == MachineType::LaserCutter
-            && matches!(strategy, "zigzag" | "surface3d" | "slice")
+            && matches!(strategy, "zigzag" | "surface3d" | "waterline" | "slice")
         {
             return Err(format!(
                 "Strategy '{}' requires Z-axis which laser cutter does not have",
@@ -155,9 +181,14 @@ mod tests {
         let profile = MachineProfile::cnc_mill();
         assert!(profile.supports_strategy("contour"));
         assert!(profile.supports_strategy("pocket"));
+        assert!(profile.supports_strategy("offset_pocket"));
+        assert!(profile.supports_strategy("adaptive"));
         assert!(profile.supports_strategy("slice"));
         assert!(profile.supports_strategy("zigzag"));
+        assert!(profile.supports_strategy("waterline"));
         assert!(profile.supports_strategy("perimeter"));
+        assert!(profile.supports_strategy("drill"));
+        assert!(profile.supports_strategy("vcarve"));
     }
 
     #[test]
@@ -165,6 +196,9 @@ mod tests {
         let profile = MachineProfile::laser_cutter();
         assert!(profile.validate_strategy("zigzag").is_err());
         assert!(profile.validate_strategy("slice").is_err());
+        assert!(profile.validate_strategy("waterline").is_err());
+        assert!(profile.validate_strategy("drill").is_err());
+        assert!(profile.validate_strategy("vcarve").is_err());
     }
 
     #[test]
@@ -209,6 +243,19 @@ mod tests {
         assert_eq!(p2.machine_type, MachineType::CncMill);
     }
 
+    #[test]
+    fn motion_limits_default_when_missing() {
+        let mut json = serde_json::to_value(MachineProfile::cnc_mill()).unwrap();
+        let caps = json["capabilities"].as_object_mut().unwrap();
+        caps.remove("max_velocity");
+        caps.remove("max_acceleration");
+        caps.remove("junction_deviation");
+        let p: MachineProfile = serde_json::from_value(json).unwrap();
+        assert_eq!(p.capabilities.max_velocity, default_max_velocity());
+        assert_eq!(p.capabilities.max_acceleration, default_max_acceleration());
+        assert_eq!(p.capabilities.junction_deviation, 0.01);
+    }
+
     #[test]
     fn machine_profile_default() {
         let p = MachineProfile::default();
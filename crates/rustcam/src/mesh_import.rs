@@ -0,0 +1,224 @@
+//! Mesh file formats and their detection.
+//!
+//! Swiss-cheese layer: **Geometry Input**
+//! Extension point: add a `MeshFormat` variant, its signature in `detect`
+//! and its parser in `parse_mesh_as`.
+//!
+//! Every format ends up as the same `Mesh` of triangles in millimetres.
+//! Formats are told apart by content, not by file name: a zip container
+//! is 3MF, a `ply` header is PLY, a binary STL has a size that matches its
+//! triangle count, and text with `v` / `f` records is OBJ. Polygonal faces
+//! (OBJ, PLY) are split into triangles by ear clipping in their own plane,
+//! so concave faces come out right.
+
+use crate::geometry::{Mesh, Triangle, Vec3};
+use crate::{obj, ply, stl, threemf};
+
+/// A mesh file format.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MeshFormat {
+    Stl,
+    Obj,
+    ThreeMf,
+    Ply,
+}
+
+impl MeshFormat {
+    /// Format for a name such as `"obj"` or `"3mf"` (a file extension).
+    pub fn from_name(name: &str) -> Result<Self, String> {
+        match name.trim_start_matches('.').to_ascii_lowercase().as_str() {
+            "stl" => Ok(Self::Stl),
+            "obj" => Ok(Self::Obj),
+            "3mf" => Ok(Self::ThreeMf),
+            "ply" => Ok(Self::Ply),
+            other => Err(format!("Unknown mesh format '{}'", other)),
+        }
+    }
+}
+
+/// Guess the format of `data` from its content.
+pub fn detect(data: &[u8]) -> MeshFormat {
+    if data.starts_with(b"PK\x03\x04") {
+        return MeshFormat::ThreeMf;
+    }
+    if data.starts_with(b"ply\n") || data.starts_with(b"ply\r\n") {
+        return MeshFormat::Ply;
+    }
+    if data.len() >= 84 {
+        let count = u32::from_le_bytes([data[80], data[81], data[82], data[83]]) as usize;
+        if count.checked_mul(50).and_then(|n| n.checked_add(84)) == Some(data.len()) {
+            return MeshFormat::Stl;
+        }
+    }
+    if data.starts_with(b"solid") {
+        return MeshFormat::Stl;
+    }
+    let head = &data[..data.len().min(64 * 1024)];
+    let looks_obj = head
+        .split(|&b| b == b'\n')
+        .map(|line| line.trim_ascii_start())
+        .any(|line| line.starts_with(b"v ") || line.starts_with(b"f ") || line.starts_with(b"v\t"));
+    if looks_obj {
+        MeshFormat::Obj
+    } else {
+        MeshFormat::Stl
+    }
+}
+
+/// Parse a mesh in whichever supported format `data` is in.
+pub fn parse_mesh(data: &[u8]) -> Result<Mesh, String> {
+    parse_mesh_as(data, detect(data))
+}
+
+/// Parse a mesh in a known format.
+pub fn parse_mesh_as(data: &[u8], format: MeshFormat) -> Result<Mesh, String> {
+    match format {
+        MeshFormat::Stl => stl::parse_stl(data),
+        MeshFormat::Obj => obj::parse_obj(data),
+        MeshFormat::ThreeMf => threemf::parse_3mf(data),
+        MeshFormat::Ply => ply::parse_ply(data),
+    }
+}
+
+/// A triangle with its normal from the winding (counter-clockwise seen
+/// from outside).
+pub(crate) fn facet(v0: Vec3, v1: Vec3, v2: Vec3) -> Triangle {
+    let (a, b) = (sub(v1, v0), sub(v2, v0));
+    let normal = Vec3::new(
+        a.y * b.z - a.z * b.y,
+        a.z * b.x - a.x * b.z,
+        a.x * b.y - a.y * b.x,
+    )
+    .normalize();
+    Triangle { normal, v0, v1, v2 }
+}
+
+/// Triangles covering the polygon `face`, keeping its winding. Faces that
+/// are not simple polygons fall back to a fan from the first vertex.
+pub(crate) fn triangulate(face: &[Vec3]) -> Vec<Triangle> {
+    if face.len() < 3 {
+        return Vec::new();
+    }
+    if face.len() == 3 {
+        return vec![facet(face[0], face[1], face[2])];
+    }
+    let corners =
+        ear_clip(face).unwrap_or_else(|| (1..face.len() - 1).map(|i| [0, i, i + 1]).collect());
+    corners
+        .into_iter()
+        .map(|[a, b, c]| facet(face[a], face[b], face[c]))
+        .collect()
+}
+
+/// Ear clipping in the plane of the face's Newell normal.
+fn ear_clip(face: &[Vec3]) -> Option<Vec<[usize; 3]>> {
+    let n = face.len();
+    let mut normal = Vec3::new(0.0, 0.0, 0.0);
+    for i in 0..n {
+        let (p, q) = (face[i], face[(i + 1) % n]);
+        normal.x += (p.y - q.y) * (p.z + q.z);
+        normal.y += (p.z - q.z) * (p.x + q.x);
+        normal.z += (p.x - q.x) * (p.y + q.y);
+    }
+    // Drop the axis the face is most nearly flat against, keeping the
+    // winding counter-clockwise in the remaining two.
+    let (ax, ay, az) = (normal.x.abs(), normal.y.abs(), normal.z.abs());
+    let flat: Vec<(f64, f64)> = if az >= ax && az >= ay {
+        let s = normal.z.signum();
+        face.iter().map(|p| (p.x * s, p.y)).collect()
+    } else if ax >= ay {
+        let s = normal.x.signum();
+        face.iter().map(|p| (p.y * s, p.z)).collect()
+    } else {
+        let s = normal.y.signum();
+        face.iter().map(|p| (p.z * s, p.x)).collect()
+    };
+    if ax.max(ay).max(az) < 1e-12 {
+        return None;
+    }
+
+    let cross = |o: (f64, f64), a: (f64, f64), b: (f64, f64)| {
+        (a.0 - o.0) * (b.1 - o.1) - (a.1 - o.1) * (b.0 - o.0)
+    };
+    let mut left: Vec<usize> = (0..n).collect();
+    let mut out = Vec::with_capacity(n - 2);
+    while left.len() > 3 {
+        let m = left.len();
+        let ear = (0..m).find(|&k| {
+            let (a, b, c) = (left[(k + m - 1) % m], left[k], left[(k + 1) % m]);
+            if cross(flat[a], flat[b], flat[c]) <= 0.0 {
+                return false;
+            }
+            // No other corner inside the candidate ear.
+            left.iter().all(|&p| {
+                p == a
+                    || p == b
+                    || p == c
+                    || cross(flat[a], flat[b], flat[p]) < 0.0
+                    || cross(flat[b], flat[c], flat[p]) < 0.0
+                    || cross(flat[c], flat[a], flat[p]) < 0.0
+            })
+        })?;
+        let (a, b, c) = (left[(ear + m - 1) % m], left[ear], left[(ear + 1) % m]);
+        out.push([a, b, c]);
+        left.remove(ear);
+    }
+    out.push([left[0], left[1], left[2]]);
+    Some(out)
+}
+
+fn sub(a: Vec3, b: Vec3) -> Vec3 {
+    Vec3::new(a.x - b.x, a.y - b.y, a.z - b.z)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_formats_by_content() {
+        assert_eq!(detect(b"PK\x03\x04rest"), MeshFormat::ThreeMf);
+        assert_eq!(detect(b"ply\nformat ascii 1.0\n"), MeshFormat::Ply);
+        assert_eq!(detect(b"solid x\nendsolid x"), MeshFormat::Stl);
+        assert_eq!(detect(b"# blender\nv 0 0 0\nf 1 2 3\n"), MeshFormat::Obj);
+        let mut binary = vec![0u8; 84 + 50];
+        binary[..5].copy_from_slice(b"v 1 2");
+        binary[80] = 1;
+        assert_eq!(detect(&binary), MeshFormat::Stl);
+        assert_eq!(MeshFormat::from_name(".3MF"), Ok(MeshFormat::ThreeMf));
+        assert!(MeshFormat::from_name("step").is_err());
+    }
+
+    #[test]
+    fn test_concave_face_is_ear_clipped() {
+        // An L shape: a fan from vertex 0 would cover the notch.
+        let v = |x: f64, y: f64| Vec3::new(x, y, 1.0);
+        let face = [
+            v(0.0, 0.0),
+            v(2.0, 0.0),
+            v(2.0, 1.0),
+            v(1.0, 1.0),
+            v(1.0, 2.0),
+            v(0.0, 2.0),
+        ];
+        let tris = triangulate(&face);
+        assert_eq!(tris.len(), 4);
+        let area: f64 = tris
+            .iter()
+            .map(|t| {
+                assert!(t.normal.z > 0.99, "winding kept");
+                ((t.v1.x - t.v0.x) * (t.v2.y - t.v0.y) - (t.v1.y - t.v0.y) * (t.v2.x - t.v0.x))
+                    / 2.0
+            })
+            .sum();
+        assert!((area - 3.0).abs() < 1e-12);
+        // Nothing covers the notch.
+        for t in &tris {
+            let c = (
+                (t.v0.x + t.v1.x + t.v2.x) / 3.0,
+                (t.v0.y + t.v1.y + t.v2.y) / 3.0,
+            );
+            assert!(!(c.0 > 1.0 && c.1 > 1.0), "{:?}", t);
+        }
+    }
+}
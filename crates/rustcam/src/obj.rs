@@ -0,0 +1,99 @@
+//! Wavefront OBJ parser.
+//!
+//! Swiss-cheese layer: **Geometry Input**
+//! Extension point: other OBJ records (`o`, `g`, `usemtl`) are skipped in
+//! `parse_obj`; handle them there if groups or materials ever matter.
+//!
+//! Only geometry is read: `v` vertices and `f` faces. Face corners may be
+//! `v`, `v/vt`, `v//vn` or `v/vt/vn`, with negative indices counting back
+//! from the latest vertex. Faces with more than three corners are
+//! triangulated, so quads and concave n-gons both work. OBJ has no units;
+//! coordinates are taken as millimetres.
+
+use crate::geometry::{Mesh, Vec3};
+use crate::mesh_import::triangulate;
+
+/// Parse an OBJ file from raw bytes.
+pub fn parse_obj(data: &[u8]) -> Result<Mesh, String> {
+    let text = String::from_utf8_lossy(data);
+    let mut vertices: Vec<Vec3> = Vec::new();
+    let mut triangles = Vec::new();
+
+    // A backslash at the end of a line continues it.
+    let joined = text.replace("\\\r\n", " ").replace("\\\n", " ");
+    for (number, raw) in joined.lines().enumerate() {
+        let line = raw.split('#').next().unwrap_or("").trim();
+        let mut fields = line.split_whitespace();
+        match fields.next() {
+            Some("v") => {
+                let mut xyz = [0.0; 3];
+                for c in &mut xyz {
+                    *c = fields
+                        .next()
+                        .and_then(|s| s.parse().ok())
+                        .ok_or_else(|| format!("OBJ line {}: bad vertex", number + 1))?;
+                }
+                vertices.push(Vec3::new(xyz[0], xyz[1], xyz[2]));
+            }
+            Some("f") => {
+                let face = fields
+                    .map(|corner| resolve(corner, vertices.len()).map(|i| vertices[i]))
+                    .collect::<Option<Vec<_>>>()
+                    .ok_or_else(|| format!("OBJ line {}: bad face index", number + 1))?;
+                if face.len() < 3 {
+                    return Err(format!(
+                        "OBJ line {}: face needs at least 3 vertices",
+                        number + 1
+                    ));
+                }
+                triangles.extend(triangulate(&face));
+            }
+            _ => {}
+        }
+    }
+
+    if triangles.is_empty() {
+        return Err("OBJ file contains no faces".into());
+    }
+    Ok(Mesh::new(triangles))
+}
+
+/// Zero-based vertex index of a face corner such as `7/2/7` or `-1//3`.
+fn resolve(corner: &str, count: usize) -> Option<usize> {
+    let index: i64 = corner.split('/').next()?.parse().ok()?;
+    let resolved = if index > 0 {
+        index - 1
+    } else {
+        count as i64 + index
+    };
+    (0..count as i64)
+        .contains(&resolved)
+        .then_some(resolved as usize)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_obj_quad_and_negative_indices() {
+        let obj = b"# unit square, then a triangle on top\n\
+            o square\n\
+            v 0 0 0\nv 10 0 0\nv 10 10 0\nv 0 10 0\n\
+            vn 0 0 1\n\
+            f 1//1 2//1 3//1 4//1\n\
+            v 0 0 5\nv 10 0 5\nv 0 10 5\n\
+            f -3/1 -2/1 -1/1\n";
+        let mesh = parse_obj(obj).unwrap();
+        assert_eq!(mesh.triangles.len(), 3);
+        assert!(mesh.triangles.iter().all(|t| t.normal.z > 0.99));
+        assert_eq!(mesh.bounds.unwrap().max.z, 5.0);
+    }
+
+    #[test]
+    fn test_parse_obj_rejects_bad_index() {
+        let err = parse_obj(b"v 0 0 0\nv 1 0 0\nf 1 2 3\n").unwrap_err();
+        assert!(err.contains("line 3"), "{}", err);
+        assert!(parse_obj(b"v 0 0 0\n").is_err());
+    }
+}
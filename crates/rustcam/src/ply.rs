@@ -0,0 +1,352 @@
+//! Stanford PLY parser — ASCII and binary (either byte order).
+//!
+//! Swiss-cheese layer: **Geometry Input**
+//! Extension point: `Scalar` covers the PLY property types; a new element
+//! needs only a match arm in `parse_ply`.
+//!
+//! The header declares elements (`vertex`, `face`, anything else) and
+//! their properties in file order, so every element is read generically
+//! and only the ones we need are kept: `x`/`y`/`z` of each vertex and the
+//! `vertex_indices` (or `vertex_index`) list of each face. Faces with more
+//! than three corners are triangulated. PLY has no units; coordinates are
+//! taken as millimetres.
+
+use crate::geometry::{Mesh, Vec3};
+use crate::mesh_import::triangulate;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Encoding {
+    Ascii,
+    LittleEndian,
+    BigEndian,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Scalar {
+    I8,
+    U8,
+    I16,
+    U16,
+    I32,
+    U32,
+    F32,
+    F64,
+}
+
+impl Scalar {
+    fn from_name(name: &str) -> Result<Self, String> {
+        Ok(match name {
+            "char" | "int8" => Self::I8,
+            "uchar" | "uint8" => Self::U8,
+            "short" | "int16" => Self::I16,
+            "ushort" | "uint16" => Self::U16,
+            "int" | "int32" => Self::I32,
+            "uint" | "uint32" => Self::U32,
+            "float" | "float32" => Self::F32,
+            "double" | "float64" => Self::F64,
+            other => return Err(format!("PLY: unknown property type '{}'", other)),
+        })
+    }
+
+    fn size(self) -> usize {
+        match self {
+            Self::I8 | Self::U8 => 1,
+            Self::I16 | Self::U16 => 2,
+            Self::I32 | Self::U32 | Self::F32 => 4,
+            Self::F64 => 8,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Property {
+    name: String,
+    /// `Some(count type)` for list properties.
+    list: Option<Scalar>,
+    value: Scalar,
+}
+
+#[derive(Debug)]
+struct Element {
+    name: String,
+    count: usize,
+    properties: Vec<Property>,
+}
+
+/// Parse a PLY file from raw bytes.
+pub fn parse_ply(data: &[u8]) -> Result<Mesh, String> {
+    let (encoding, elements, body) = parse_header(data)?;
+    let mut reader = Reader {
+        data: &data[body..],
+        pos: 0,
+        encoding,
+    };
+
+    let mut vertices: Vec<Vec3> = Vec::new();
+    let mut triangles = Vec::new();
+    for element in &elements {
+        let find = |name: &str| element.properties.iter().position(|p| p.name == name);
+        match element.name.as_str() {
+            "vertex" => {
+                let xyz = [find("x"), find("y"), find("z")];
+                if xyz.iter().any(Option::is_none) {
+                    return Err("PLY: vertex element needs x, y and z".into());
+                }
+                // Every vertex takes at least a byte, so a forged count
+                // cannot make us allocate more than the file could hold.
+                vertices.reserve(element.count.min(reader.remaining()));
+                for _ in 0..element.count {
+                    let mut p = [0.0; 3];
+                    for (i, property) in element.properties.iter().enumerate() {
+                        let values = reader.property(property)?;
+                        if let Some(axis) = xyz.iter().position(|&k| k == Some(i)) {
+                            p[axis] = values.first().copied().unwrap_or(0.0);
+                        }
+                    }
+                    vertices.push(Vec3::new(p[0], p[1], p[2]));
+                }
+            }
+            "face" => {
+                let indices = find("vertex_indices")
+                    .or_else(|| find("vertex_index"))
+                    .ok_or("PLY: face element needs vertex_indices")?;
+                for _ in 0..element.count {
+                    for (i, property) in element.properties.iter().enumerate() {
+                        let values = reader.property(property)?;
+                        if i != indices {
+                            continue;
+                        }
+                        let face = values
+                            .iter()
+                            .map(|&v| {
+                                let whole = v >= 0.0 && v.fract() == 0.0;
+                                whole.then(|| vertices.get(v as usize).copied())?
+                            })
+                            .collect::<Option<Vec<_>>>()
+                            .ok_or("PLY: face index out of range")?;
+                        triangles.extend(triangulate(&face));
+                    }
+                }
+            }
+            _ => {
+                for _ in 0..element.count {
+                    for property in &element.properties {
+                        reader.property(property)?;
+                    }
+                }
+            }
+        }
+    }
+
+    if triangles.is_empty() {
+        return Err("PLY file contains no faces".into());
+    }
+    Ok(Mesh::new(triangles))
+}
+
+/// Encoding, elements and the byte offset where the body starts.
+fn parse_header(data: &[u8]) -> Result<(Encoding, Vec<Element>, usize), String> {
+    let end = data
+        .windows(10)
+        .position(|w| w == b"end_header")
+        .ok_or("PLY: missing end_header")?;
+    let mut body = end + 10;
+    if data.get(body) == Some(&b'\r') {
+        body += 1;
+    }
+    if data.get(body) == Some(&b'\n') {
+        body += 1;
+    }
+
+    let header = std::str::from_utf8(&data[..end]).map_err(|_| "PLY: header is not text")?;
+    let mut lines = header.lines().map(str::trim);
+    if lines.next() != Some("ply") {
+        return Err("PLY: missing 'ply' magic".into());
+    }
+    let mut encoding = None;
+    let mut elements: Vec<Element> = Vec::new();
+    for line in lines {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        match fields.as_slice() {
+            ["format", kind, ..] => {
+                encoding = Some(match *kind {
+                    "ascii" => Encoding::Ascii,
+                    "binary_little_endian" => Encoding::LittleEndian,
+                    "binary_big_endian" => Encoding::BigEndian,
+                    other => return Err(format!("PLY: unknown format '{}'", other)),
+                });
+            }
+            ["element", name, count] => elements.push(Element {
+                name: name.to_string(),
+                count: count
+                    .parse()
+                    .map_err(|_| format!("PLY: bad element count '{}'", count))?,
+                properties: Vec::new(),
+            }),
+            ["property", "list", count, value, name] => elements
+                .last_mut()
+                .ok_or("PLY: property before element")?
+                .properties
+                .push(Property {
+                    name: name.to_string(),
+                    list: Some(Scalar::from_name(count)?),
+                    value: Scalar::from_name(value)?,
+                }),
+            ["property", value, name] => elements
+                .last_mut()
+                .ok_or("PLY: property before element")?
+                .properties
+                .push(Property {
+                    name: name.to_string(),
+                    list: None,
+                    value: Scalar::from_name(value)?,
+                }),
+            _ => {} // comment, obj_info, blank
+        }
+    }
+    let encoding = encoding.ok_or("PLY: missing format line")?;
+    Ok((encoding, elements, body))
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+    encoding: Encoding,
+}
+
+impl Reader<'_> {
+    fn remaining(&self) -> usize {
+        self.data.len() - self.pos
+    }
+
+    /// All values of one property (one value unless it is a list).
+    fn property(&mut self, property: &Property) -> Result<Vec<f64>, String> {
+        match property.list {
+            Some(count) => {
+                let n = self.scalar(count)?;
+                if !(0.0..=1e6).contains(&n) {
+                    return Err("PLY: bad list length".into());
+                }
+                (0..n as usize)
+                    .map(|_| self.scalar(property.value))
+                    .collect()
+            }
+            None => Ok(vec![self.scalar(property.value)?]),
+        }
+    }
+
+    fn scalar(&mut self, kind: Scalar) -> Result<f64, String> {
+        if self.encoding == Encoding::Ascii {
+            return self.token();
+        }
+        let bytes = self
+            .data
+            .get(self.pos..self.pos + kind.size())
+            .ok_or("PLY: file is truncated")?;
+        self.pos += kind.size();
+        let mut b = [0u8; 8];
+        b[..bytes.len()].copy_from_slice(bytes);
+        if self.encoding == Encoding::BigEndian {
+            b[..bytes.len()].reverse();
+        }
+        Ok(match kind {
+            Scalar::I8 => b[0] as i8 as f64,
+            Scalar::U8 => b[0] as f64,
+            Scalar::I16 => i16::from_le_bytes([b[0], b[1]]) as f64,
+            Scalar::U16 => u16::from_le_bytes([b[0], b[1]]) as f64,
+            Scalar::I32 => i32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f64,
+            Scalar::U32 => u32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f64,
+            Scalar::F32 => f32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f64,
+            Scalar::F64 => f64::from_le_bytes(b),
+        })
+    }
+
+    /// Next whitespace-separated number of an ASCII body.
+    fn token(&mut self) -> Result<f64, String> {
+        let rest = &self.data[self.pos..];
+        let start = rest
+            .iter()
+            .position(|c| !c.is_ascii_whitespace())
+            .ok_or("PLY: file is truncated")?;
+        let len = rest[start..]
+            .iter()
+            .position(|c| c.is_ascii_whitespace())
+            .unwrap_or(rest.len() - start);
+        self.pos += start + len;
+        std::str::from_utf8(&rest[start..start + len])
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .ok_or_else(|| "PLY: bad number".to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_ascii_ply_with_quad_and_extra_properties() {
+        let ply = b"ply\nformat ascii 1.0\ncomment made by hand\n\
+            element vertex 4\nproperty float x\nproperty float y\nproperty float z\n\
+            property uchar red\n\
+            element face 1\nproperty list uchar int vertex_indices\n\
+            end_header\n\
+            0 0 1 255\n10 0 1 0\n10 10 1 0\n0 10 1 0\n\
+            4 0 1 2 3\n";
+        let mesh = parse_ply(ply).unwrap();
+        assert_eq!(mesh.triangles.len(), 2);
+        assert!(mesh.triangles.iter().all(|t| t.normal.z > 0.99));
+        assert_eq!(mesh.bounds.unwrap().max.x, 10.0);
+    }
+
+    #[test]
+    fn test_parse_binary_big_endian_ply() {
+        let mut ply = b"ply\nformat binary_big_endian 1.0\n\
+            element vertex 3\nproperty double x\nproperty double y\nproperty double z\n\
+            element edge 1\nproperty int vertex1\nproperty int vertex2\n\
+            element face 1\nproperty list uchar uint vertex_index\n\
+            end_header\n"
+            .to_vec();
+        for v in [[0.0f64, 0.0, 2.0], [4.0, 0.0, 2.0], [0.0, 3.0, 2.0]] {
+            for c in v {
+                ply.extend(c.to_be_bytes());
+            }
+        }
+        ply.extend(0i32.to_be_bytes());
+        ply.extend(1i32.to_be_bytes());
+        ply.push(3);
+        for i in [0u32, 1, 2] {
+            ply.extend(i.to_be_bytes());
+        }
+        let mesh = parse_ply(&ply).unwrap();
+        assert_eq!(mesh.triangles.len(), 1);
+        let t = &mesh.triangles[0];
+        assert_eq!((t.v1.x, t.v2.y, t.v0.z), (4.0, 3.0, 2.0));
+
+        ply.truncate(ply.len() - 2);
+        assert!(parse_ply(&ply).unwrap_err().contains("truncated"));
+    }
+
+    #[test]
+    fn test_bad_face_indices_and_forged_counts_are_rejected() {
+        let with_face = |face: &str| {
+            format!(
+                "ply\nformat ascii 1.0\nelement vertex 3\nproperty float x\n\
+                 property float y\nproperty float z\nelement face 1\n\
+                 property list uchar float vertex_indices\nend_header\n\
+                 0 0 0\n1 0 0\n0 1 0\n{}\n",
+                face
+            )
+        };
+        assert!(parse_ply(with_face("3 0 1 2").as_bytes()).is_ok());
+        for face in ["3 0 1 -1", "3 0 0.5 2", "3 0 1 nan", "3 0 1 3"] {
+            let err = parse_ply(with_face(face).as_bytes()).unwrap_err();
+            assert_eq!(err, "PLY: face index out of range", "{}", face);
+        }
+
+        let forged = b"ply\nformat binary_little_endian 1.0\n\
+            element vertex 4000000000000\nproperty float x\nproperty float y\n\
+            property float z\nend_header\n\0\0\0\0";
+        assert!(parse_ply(forged).unwrap_err().contains("truncated"));
+    }
+}
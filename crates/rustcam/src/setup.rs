@@ -0,0 +1,506 @@
+//! Setup: stock, model placement and work origin.
+//!
+//! Swiss-cheese layer: **Job planning**
+//! Extension point: add a `StockShape` (an extruded outline, say) or
+//! another `OriginXY` anchor.
+//!
+//! A setup says how the part sits on the machine. The model is scaled,
+//! mirrored, rotated and moved, in that order; the stock is sized to fit
+//! it or given outright; and the work origin, where G54..G59 is touched
+//! off, is picked on the stock. Input geometry is placed before any
+//! strategy runs, with X and Y in the work frame and Z measured from the
+//! stock top. That is the frame every strategy already assumes (`safe_z`
+//! above the top, 2-D `cut_depth` below it), so `Placement::to_work` only
+//! has to lift the finished toolpaths when the origin is on the stock
+//! bottom.
+
+use serde::{Deserialize, Serialize};
+
+use crate::gcode_parser::WorkOffset;
+use crate::geometry::affine::Affine2;
+use crate::geometry::{BoundingBox, BoundingBox2, Mesh, Polyline, Toolpath, Triangle, Vec2, Vec3};
+use crate::simulation::Stock;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StockShape {
+    #[default]
+    Box,
+    /// Standing on its end, axis along Z.
+    Cylinder,
+}
+
+/// The blank the part is cut from.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StockSetup {
+    pub shape: StockShape,
+    /// Length (X), width (Y) and height (Z) in mm; a cylinder's diameter
+    /// is the X size. A size of 0 fits the model plus `margin`.
+    pub size: [f64; 3],
+    /// Extra stock around the model and above it when fitting.
+    pub margin: f64,
+    /// Corner of the model's bounding box measured from the stock's (both
+    /// at minimum X, Y and Z). `None` centres the model in X and Y with its
+    /// top `margin` below the stock top.
+    pub offset: Option<[f64; 3]>,
+}
+
+impl Default for StockSetup {
+    fn default() -> Self {
+        Self {
+            shape: StockShape::Box,
+            size: [0.0; 3],
+            margin: 0.0,
+            offset: None,
+        }
+    }
+}
+
+/// How the model is fitted to the stock, applied in field order.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ModelTransform {
+    /// Uniform scale factor.
+    pub scale: f64,
+    /// Mirror across X, Y and Z (negate that coordinate).
+    pub mirror: [bool; 3],
+    /// Rotation about X, then Y, then Z, in degrees.
+    pub rotate: [f64; 3],
+    pub translate: [f64; 3],
+}
+
+impl Default for ModelTransform {
+    fn default() -> Self {
+        Self {
+            scale: 1.0,
+            mirror: [false; 3],
+            rotate: [0.0; 3],
+            translate: [0.0; 3],
+        }
+    }
+}
+
+/// Work origin in X and Y, seen from the front of the machine.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OriginXY {
+    #[default]
+    FrontLeft,
+    FrontRight,
+    BackLeft,
+    BackRight,
+    Center,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OriginZ {
+    #[default]
+    Top,
+    Bottom,
+}
+
+/// Stock, model placement and work origin of one setup.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Setup {
+    pub stock: StockSetup,
+    pub model: ModelTransform,
+    pub origin: OriginXY,
+    pub origin_z: OriginZ,
+    pub work_offset: WorkOffset,
+}
+
+/// Where a setup put the stock. Coordinates are in the placing frame:
+/// work X and Y, Z from the stock top.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Placement {
+    pub shape: StockShape,
+    pub stock_min: Vec3,
+    pub stock_max: Vec3,
+    /// Z of the stock top in the work frame: 0, or the stock height with
+    /// the origin on the bottom.
+    pub top: f64,
+    pub work_offset: WorkOffset,
+}
+
+/// Linear part (row-major) and translation of a 3-D affine map.
+struct Affine3 {
+    m: [[f64; 3]; 3],
+    t: [f64; 3],
+}
+
+impl Affine3 {
+    fn apply(&self, p: Vec3) -> Vec3 {
+        let v = self.linear(p);
+        Vec3::new(v.x + self.t[0], v.y + self.t[1], v.z + self.t[2])
+    }
+
+    fn linear(&self, p: Vec3) -> Vec3 {
+        let r = |i: usize| self.m[i][0] * p.x + self.m[i][1] * p.y + self.m[i][2] * p.z;
+        Vec3::new(r(0), r(1), r(2))
+    }
+
+    fn det(&self) -> f64 {
+        let m = &self.m;
+        m[0][0] * (m[1][1] * m[2][2] - m[1][2] * m[2][1])
+            - m[0][1] * (m[1][0] * m[2][2] - m[1][2] * m[2][0])
+            + m[0][2] * (m[1][0] * m[2][1] - m[1][1] * m[2][0])
+    }
+
+    /// A mirrored map turns the winding over, so two corners swap to
+    /// keep the facets facing out.
+    fn triangle(&self, t: &Triangle) -> Triangle {
+        let (v1, v2) = if self.det() < 0.0 {
+            (t.v2, t.v1)
+        } else {
+            (t.v1, t.v2)
+        };
+        Triangle {
+            normal: self.linear(t.normal).normalize(),
+            v0: self.apply(t.v0),
+            v1: self.apply(v1),
+            v2: self.apply(v2),
+        }
+    }
+}
+
+fn mul(a: &[[f64; 3]; 3], b: &[[f64; 3]; 3]) -> [[f64; 3]; 3] {
+    let mut out = [[0.0; 3]; 3];
+    for (i, row) in out.iter_mut().enumerate() {
+        for (j, v) in row.iter_mut().enumerate() {
+            *v = (0..3).map(|k| a[i][k] * b[k][j]).sum();
+        }
+    }
+    out
+}
+
+impl ModelTransform {
+    fn validate(&self) -> Result<(), String> {
+        if self.scale.is_nan() || self.scale <= 0.0 {
+            return Err("Model scale must be positive".into());
+        }
+        Ok(())
+    }
+
+    fn signs(&self) -> [f64; 3] {
+        self.mirror
+            .map(|m| if m { -self.scale } else { self.scale })
+    }
+
+    fn affine3(&self) -> Affine3 {
+        let [sx, sy, sz] = self.signs();
+        let [ax, ay, az] = self.rotate.map(f64::to_radians);
+        let (s, c) = ax.sin_cos();
+        let rx = [[1.0, 0.0, 0.0], [0.0, c, -s], [0.0, s, c]];
+        let (s, c) = ay.sin_cos();
+        let ry = [[c, 0.0, s], [0.0, 1.0, 0.0], [-s, 0.0, c]];
+        let (s, c) = az.sin_cos();
+        let rz = [[c, -s, 0.0], [s, c, 0.0], [0.0, 0.0, 1.0]];
+        let scale = [[sx, 0.0, 0.0], [0.0, sy, 0.0], [0.0, 0.0, sz]];
+        Affine3 {
+            m: mul(&rz, &mul(&ry, &mul(&rx, &scale))),
+            t: self.translate,
+        }
+    }
+
+    /// The plane part for flat drawings: no X/Y rotation or Z mirror.
+    fn affine2(&self) -> Affine2 {
+        let [sx, sy, _] = self.signs();
+        Affine2::translate(self.translate[0], self.translate[1])
+            .after(&Affine2::rotate(self.rotate[2].to_radians()))
+            .after(&Affine2::scale(sx, sy))
+    }
+}
+
+impl Setup {
+    pub fn from_json(json: &str) -> Result<Self, String> {
+        serde_json::from_str(json).map_err(|e| e.to_string())
+    }
+
+    /// `mesh` moved into the placing frame, and where the stock went.
+    pub fn place_mesh(&self, mesh: &Mesh) -> Result<(Mesh, Placement), String> {
+        self.model.validate()?;
+        let xf = self.model.affine3();
+        let tris: Vec<Triangle> = mesh.triangles.iter().map(|t| xf.triangle(t)).collect();
+        let b = BoundingBox::from_triangles(&tris).ok_or("Mesh has no triangles")?;
+        let (shift, placement) = self.place(b.min, b.max, false)?;
+        let moved = Affine3 {
+            m: [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]],
+            t: [shift.x, shift.y, shift.z],
+        };
+        let tris = tris.iter().map(|t| moved.triangle(t)).collect();
+        Ok((Mesh::new(tris), placement))
+    }
+
+    /// The map taking a flat drawing into the placing frame, and where
+    /// the stock went. The drawing lies on the stock top, and the stock is
+    /// at least `depth` (the cut depth, negative) thick. Only the Z
+    /// rotation and the X/Y mirrors apply.
+    ///
+    /// The stock is fitted to all of `polylines`, so a layer or color
+    /// filtered out of one operation still sits where it does in the next.
+    pub fn place_2d(
+        &self,
+        polylines: &[Polyline],
+        depth: f64,
+    ) -> Result<(Affine2, Placement), String> {
+        self.model.validate()?;
+        let xf = self.model.affine2();
+        let pts: Vec<Vec2> = polylines
+            .iter()
+            .flat_map(|p| p.points.iter().map(|&v| xf.apply(v)))
+            .collect();
+        let b = BoundingBox2::from_points(&pts).ok_or("Drawing is empty")?;
+        let (shift, placement) = self.place(
+            Vec3::new(b.min.x, b.min.y, depth.min(0.0)),
+            Vec3::new(b.max.x, b.max.y, 0.0),
+            true,
+        )?;
+        Ok((Affine2::translate(shift.x, shift.y).after(&xf), placement))
+    }
+
+    /// Size and position the stock around a model spanning `min`..`max`.
+    /// Returns the shift into the placing frame and the placement. A
+    /// `flat` model keeps its top on the stock top.
+    fn place(&self, min: Vec3, max: Vec3, flat: bool) -> Result<(Vec3, Placement), String> {
+        let stock = &self.stock;
+        if stock.size.iter().any(|&s| s.is_nan() || s < 0.0) || stock.margin < 0.0 {
+            return Err("Stock size and margin must not be negative".into());
+        }
+        let extent = [max.x - min.x, max.y - min.y, max.z - min.z];
+        let margin = stock.margin;
+        let top_margin = if flat { 0.0 } else { margin };
+        let fit = |given: f64, model: f64, extra: f64| {
+            if given > 0.0 {
+                given
+            } else {
+                model + extra
+            }
+        };
+        let (sx, sy) = match stock.shape {
+            StockShape::Box => (
+                fit(stock.size[0], extent[0], 2.0 * margin),
+                fit(stock.size[1], extent[1], 2.0 * margin),
+            ),
+            StockShape::Cylinder => {
+                let d = fit(stock.size[0], extent[0].hypot(extent[1]), 2.0 * margin);
+                (d, d)
+            }
+        };
+        let sz = fit(stock.size[2], extent[2], top_margin);
+        let offset = match stock.offset {
+            Some(o) if !flat => o,
+            Some(o) => [o[0], o[1], sz - extent[2]],
+            None => [
+                (sx - extent[0]) / 2.0,
+                (sy - extent[1]) / 2.0,
+                sz - extent[2] - top_margin,
+            ],
+        };
+        let lo = Vec3::new(min.x - offset[0], min.y - offset[1], min.z - offset[2]);
+        let hi = Vec3::new(lo.x + sx, lo.y + sy, lo.z + sz);
+        let (ox, oy) = match self.origin {
+            OriginXY::FrontLeft => (lo.x, lo.y),
+            OriginXY::FrontRight => (hi.x, lo.y),
+            OriginXY::BackLeft => (lo.x, hi.y),
+            OriginXY::BackRight => (hi.x, hi.y),
+            OriginXY::Center => ((lo.x + hi.x) / 2.0, (lo.y + hi.y) / 2.0),
+        };
+        let shift = Vec3::new(-ox, -oy, -hi.z);
+        let placement = Placement {
+            shape: stock.shape,
+            stock_min: Vec3::new(lo.x - ox, lo.y - oy, -sz),
+            stock_max: Vec3::new(hi.x - ox, hi.y - oy, 0.0),
+            top: match self.origin_z {
+                OriginZ::Top => 0.0,
+                OriginZ::Bottom => sz,
+            },
+            work_offset: self.work_offset,
+        };
+        Ok((shift, placement))
+    }
+}
+
+/// `polylines` mapped through `xf`.
+pub fn transform_polylines(xf: &Affine2, polylines: &[Polyline]) -> Vec<Polyline> {
+    polylines
+        .iter()
+        .map(|p| Polyline::new(p.points.iter().map(|&v| xf.apply(v)).collect(), p.closed))
+        .collect()
+}
+
+impl Placement {
+    /// Move toolpaths from the placing frame into the work frame.
+    pub fn to_work(&self, toolpaths: &mut [Toolpath]) {
+        if self.top == 0.0 {
+            return;
+        }
+        for tp in toolpaths {
+            for mv in &mut tp.moves {
+                mv.z += self.top;
+            }
+            if let Some(cycle) = &mut tp.drill {
+                cycle.z += self.top;
+                cycle.retract_z += self.top;
+            }
+        }
+    }
+
+    /// A mesh in the placing frame moved into the work frame.
+    pub fn mesh_to_work(&self, mesh: &Mesh) -> Mesh {
+        let lift = |v: Vec3| Vec3::new(v.x, v.y, v.z + self.top);
+        Mesh::new(
+            mesh.triangles
+                .iter()
+                .map(|t| Triangle {
+                    normal: t.normal,
+                    v0: lift(t.v0),
+                    v1: lift(t.v1),
+                    v2: lift(t.v2),
+                })
+                .collect(),
+        )
+    }
+
+    /// Uncut stock in the work frame for the stock simulation. Outside a
+    /// cylinder there is nothing above the stock bottom.
+    pub fn sim_stock(&self, cell: f64) -> Stock {
+        let (lo, hi) = (self.stock_min, self.stock_max);
+        let mut stock = Stock::new(Vec2::new(lo.x, lo.y), Vec2::new(hi.x, hi.y), self.top, cell);
+        if self.shape == StockShape::Cylinder {
+            let centre = Vec2::new((lo.x + hi.x) / 2.0, (lo.y + hi.y) / 2.0);
+            let (r, bottom) = ((hi.x - lo.x) / 2.0, self.top + lo.z);
+            for j in 0..stock.ny {
+                for i in 0..stock.nx {
+                    if Vec2::dist(stock.cell_centre(i, j), centre) > r {
+                        stock.heights[j * stock.nx + i] = bottom;
+                    }
+                }
+            }
+        }
+        stock
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cube(size: f64) -> Mesh {
+        // Two facets are enough to span the box.
+        let t = |a: [f64; 3], b: [f64; 3], c: [f64; 3]| Triangle {
+            normal: Vec3::new(0.0, 0.0, 1.0),
+            v0: Vec3::new(a[0], a[1], a[2]),
+            v1: Vec3::new(b[0], b[1], b[2]),
+            v2: Vec3::new(c[0], c[1], c[2]),
+        };
+        Mesh::new(vec![
+            t([0.0, 0.0, size], [size, 0.0, size], [size, size, size]),
+            t([0.0, 0.0, 0.0], [size, size, 0.0], [0.0, size, 0.0]),
+        ])
+    }
+
+    fn bounds(mesh: &Mesh) -> (Vec3, Vec3) {
+        let b = mesh.bounds.as_ref().unwrap();
+        (b.min, b.max)
+    }
+
+    #[test]
+    fn test_fitted_stock_origin_corners_and_top() {
+        let setup = Setup::from_json(
+            r#"{"stock": {"margin": 2}, "origin": "front_left", "origin_z": "top"}"#,
+        )
+        .unwrap();
+        let (mesh, placement) = setup.place_mesh(&cube(10.0)).unwrap();
+        // 14 x 14 x 12 stock, model centred with 2 mm on top.
+        assert_eq!(placement.stock_min, Vec3::new(0.0, 0.0, -12.0));
+        assert_eq!(placement.stock_max, Vec3::new(14.0, 14.0, 0.0));
+        assert_eq!(
+            bounds(&mesh),
+            (Vec3::new(2.0, 2.0, -12.0), Vec3::new(12.0, 12.0, -2.0))
+        );
+
+        let setup = Setup {
+            origin: OriginXY::Center,
+            origin_z: OriginZ::Bottom,
+            work_offset: WorkOffset::G55,
+            ..setup
+        };
+        let (mesh, placement) = setup.place_mesh(&cube(10.0)).unwrap();
+        assert_eq!(bounds(&mesh).0, Vec3::new(-5.0, -5.0, -12.0));
+        assert_eq!(placement.top, 12.0);
+        let work = placement.mesh_to_work(&mesh);
+        assert_eq!(
+            bounds(&work),
+            (Vec3::new(-5.0, -5.0, 0.0), Vec3::new(5.0, 5.0, 10.0))
+        );
+    }
+
+    #[test]
+    fn test_model_transform_and_given_stock() {
+        let setup = Setup::from_json(
+            r#"{
+                "stock": {"size": [100, 50, 30], "offset": [10, 5, 0]},
+                "model": {"scale": 2, "mirror": [true, false, false], "rotate": [0, 0, 90]},
+                "origin": "back_right"
+            }"#,
+        )
+        .unwrap();
+        let (mesh, placement) = setup.place_mesh(&cube(10.0)).unwrap();
+        // A 20 mm cube sitting 10 mm in from the left and 5 from the front
+        // of the stock, whose back right top corner is the origin.
+        assert_eq!(placement.stock_min, Vec3::new(-100.0, -50.0, -30.0));
+        let (lo, hi) = bounds(&mesh);
+        assert!((lo.x + 90.0).abs() < 1e-9 && (hi.x + 70.0).abs() < 1e-9);
+        assert!((lo.y + 45.0).abs() < 1e-9 && (hi.y + 25.0).abs() < 1e-9);
+        assert!((lo.z + 30.0).abs() < 1e-9 && (hi.z + 10.0).abs() < 1e-9);
+        // Mirroring keeps the top facet facing up.
+        let top = mesh.triangles.iter().find(|t| t.min_z() > -11.0).unwrap();
+        assert!((top.normal.z - 1.0).abs() < 1e-9);
+        let (a, b, c) = (top.v0, top.v1, top.v2);
+        let winding = (b.x - a.x) * (c.y - a.y) - (b.y - a.y) * (c.x - a.x);
+        assert!(winding > 0.0);
+
+        assert!(Setup::from_json(r#"{"model": {"scale": 0}}"#)
+            .unwrap()
+            .place_mesh(&cube(1.0))
+            .is_err());
+    }
+
+    #[test]
+    fn test_drawing_lies_on_stock_top() {
+        let setup = Setup::from_json(
+            r#"{"stock": {"shape": "cylinder", "size": [0, 0, 6], "margin": 1}, "origin": "center", "origin_z": "bottom"}"#,
+        )
+        .unwrap();
+        let square = Polyline::new(
+            vec![
+                Vec2::new(50.0, 50.0),
+                Vec2::new(56.0, 50.0),
+                Vec2::new(56.0, 58.0),
+                Vec2::new(50.0, 58.0),
+            ],
+            true,
+        );
+        let square = [square];
+        let (xf, placement) = setup.place_2d(&square, -2.0).unwrap();
+        let placed = transform_polylines(&xf, &square);
+        assert_eq!(placed[0].points[0].x, -3.0);
+        assert_eq!(placed[0].points[2].y, 4.0);
+        // A 12 mm disc, 6 thick, with the drawing on its top face.
+        assert_eq!(placement.stock_max, Vec3::new(6.0, 6.0, 0.0));
+        assert_eq!((placement.stock_min.z, placement.top), (-6.0, 6.0));
+
+        let mut tp = Toolpath::new();
+        tp.rapid(0.0, 0.0, 5.0);
+        tp.cut(0.0, 0.0, -2.0);
+        placement.to_work(std::slice::from_mut(&mut tp));
+        assert_eq!((tp.moves[0].z, tp.moves[1].z), (11.0, 4.0));
+
+        let stock = placement.sim_stock(0.5);
+        assert_eq!(stock.height_at(0.0, 0.0), Some(6.0));
+        assert_eq!(stock.height_at(5.9, 5.9), Some(0.0));
+    }
+}
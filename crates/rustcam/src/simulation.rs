@@ -0,0 +1,540 @@
+//! Stock material-removal simulation.
+//!
+//! Swiss-cheese layer: **Verification**
+//! Extension point: replace the heightmap with a dexel model to see
+//! undercuts, or add the holder to the rapid collision check.
+//!
+//! The stock is a heightmap: one top-of-material Z per square cell. The
+//! tool's cutting profile is swept along every move at sub-cell spacing,
+//! and each cell under it is lowered to the tool's underside. Rapids that
+//! would lower a cell are reported as collisions (the material still goes,
+//! as it would in the crash). Comparing the cut heightmap with the model's
+//! height at each cell centre then shows gouges (cut below the model) and
+//! leftover stock (above it by more than a tolerance).
+
+use serde::{Deserialize, Serialize};
+
+use crate::geometry::{Mesh, Toolpath, Vec2};
+use crate::slicer::mesh_height_at;
+use crate::tool::{Tool, ToolType};
+
+/// Most cells a stock grid may have; coarser cells are used beyond it.
+const MAX_CELLS: usize = 4_000_000;
+
+/// How many of the worst defects each summary lists.
+const MAX_SAMPLES: usize = 50;
+
+/// Material thinner than this in a rapid's way is not a collision (mm).
+const RAPID_CLEARANCE: f64 = 1e-6;
+
+/// Grid resolution and report tolerances.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct SimParams {
+    /// Cell size of the stock heightmap (mm).
+    pub resolution: f64,
+    /// Cuts this far below the model are not gouges (mm).
+    pub gouge_tolerance: f64,
+    /// Stock this far above the model is not leftover (mm).
+    pub leftover_tolerance: f64,
+}
+
+impl Default for SimParams {
+    fn default() -> Self {
+        Self {
+            resolution: 0.5,
+            gouge_tolerance: 0.01,
+            leftover_tolerance: 0.1,
+        }
+    }
+}
+
+/// Heightmap of the material left on the machine.
+#[derive(Debug, Clone)]
+pub struct Stock {
+    /// Corner of cell (0, 0).
+    pub min: Vec2,
+    pub cell: f64,
+    pub nx: usize,
+    pub ny: usize,
+    /// Z of the uncut stock top.
+    pub top: f64,
+    /// Row-major heights, `ny` rows of `nx`, row 0 at `min.y`.
+    pub heights: Vec<f64>,
+}
+
+/// One cell that is cut too deep or not deep enough.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct Defect {
+    pub x: f64,
+    pub y: f64,
+    /// How far the stock is from the model (mm, positive).
+    pub depth: f64,
+}
+
+/// All defects of one kind: how many cells, the worst, and where the
+/// worst few are.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct DefectSummary {
+    pub count: usize,
+    pub max_depth: f64,
+    /// Deepest first, at most `MAX_SAMPLES`.
+    pub samples: Vec<Defect>,
+}
+
+/// A rapid move that passes through material.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct RapidCollision {
+    /// Index of the move among all moves of all toolpaths, as returned by
+    /// the `sim_moves_*` entry points.
+    pub move_index: usize,
+    /// Tool position where it is deepest in the material.
+    pub x: f64,
+    pub y: f64,
+    pub z: f64,
+    pub depth: f64,
+}
+
+/// The stock heights as 8-bit grayscale: white is the stock top, black
+/// the deepest cut.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DepthImage {
+    pub width: usize,
+    pub height: usize,
+    /// XY of the image's bottom-left corner.
+    pub origin: [f64; 2],
+    pub cell_size: f64,
+    /// Heights mapped to black and white.
+    pub min_z: f64,
+    pub max_z: f64,
+    /// Row-major, first row at the top (largest Y) like a screen image.
+    pub pixels: Vec<u8>,
+}
+
+/// Everything the simulation found.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SimReport {
+    /// Material removed (mm³).
+    pub removed_volume: f64,
+    /// Cells cut below the model; empty without a model.
+    pub gouges: DefectSummary,
+    /// Cells left above the model; empty without a model.
+    pub leftover: DefectSummary,
+    pub rapid_collisions: Vec<RapidCollision>,
+    pub image: DepthImage,
+}
+
+impl Stock {
+    /// Uncut stock covering `min`..`max` in XY with its top at `top`.
+    /// The cell size grows if the grid would be too large.
+    pub fn new(min: Vec2, max: Vec2, top: f64, cell: f64) -> Self {
+        let (w, h) = ((max.x - min.x).max(0.0), (max.y - min.y).max(0.0));
+        let mut cell = cell.max(1e-3);
+        let area_cells = (w * h) / (cell * cell);
+        if area_cells > MAX_CELLS as f64 {
+            cell *= (area_cells / MAX_CELLS as f64).sqrt();
+        }
+        let nx = ((w / cell).ceil() as usize).max(1);
+        let ny = ((h / cell).ceil() as usize).max(1);
+        Self {
+            min,
+            cell,
+            nx,
+            ny,
+            top,
+            heights: vec![top; nx * ny],
+        }
+    }
+
+    /// Stock filling the bounding box of `mesh`.
+    pub fn for_mesh(mesh: &Mesh, cell: f64) -> Option<Self> {
+        let b = mesh.bounds.as_ref()?;
+        Some(Self::new(
+            Vec2::new(b.min.x, b.min.y),
+            Vec2::new(b.max.x, b.max.y),
+            b.max.z,
+            cell,
+        ))
+    }
+
+    /// Centre of cell `(i, j)`.
+    pub fn cell_centre(&self, i: usize, j: usize) -> Vec2 {
+        Vec2::new(
+            self.min.x + (i as f64 + 0.5) * self.cell,
+            self.min.y + (j as f64 + 0.5) * self.cell,
+        )
+    }
+
+    pub fn height(&self, i: usize, j: usize) -> f64 {
+        self.heights[j * self.nx + i]
+    }
+
+    /// Stock height under `(x, y)`, or `None` off the stock.
+    pub fn height_at(&self, x: f64, y: f64) -> Option<f64> {
+        let i = ((x - self.min.x) / self.cell).floor();
+        let j = ((y - self.min.y) / self.cell).floor();
+        if i < 0.0 || j < 0.0 || i >= self.nx as f64 || j >= self.ny as f64 {
+            return None;
+        }
+        Some(self.height(i as usize, j as usize))
+    }
+
+    /// Sweep `tool` along every move of `toolpaths` and remove what it
+    /// cuts. Returns the rapids that went through material.
+    pub fn cut(&mut self, toolpaths: &[Toolpath], tool: &Tool) -> Vec<RapidCollision> {
+        let profile = Profile::of(tool);
+        let mut collisions = Vec::new();
+        let moves = toolpaths.iter().flat_map(|tp| tp.moves.iter());
+        // The tool starts where the first move puts it.
+        let mut at = None;
+        for (index, m) in moves.enumerate() {
+            let to = [m.x, m.y, m.z];
+            let hit = self.sweep(at.unwrap_or(to), to, &profile);
+            if m.rapid {
+                if let Some((depth, [x, y, z])) = hit {
+                    collisions.push(RapidCollision {
+                        move_index: index,
+                        x,
+                        y,
+                        z,
+                        depth,
+                    });
+                }
+            }
+            at = Some(to);
+        }
+        collisions
+    }
+
+    /// Move the tool from `a` to `b`, lowering the cells it passes over.
+    /// Returns the deepest cut made and where the tool was for it.
+    fn sweep(&mut self, a: [f64; 3], b: [f64; 3], profile: &Profile) -> Option<(f64, [f64; 3])> {
+        let len = ((b[0] - a[0]).powi(2) + (b[1] - a[1]).powi(2)).sqrt();
+        let steps = ((len / (self.cell / 2.0)).ceil() as usize).max(1);
+        let mut deepest: Option<(f64, [f64; 3])> = None;
+        for k in 0..=steps {
+            let t = k as f64 / steps as f64;
+            let p = [
+                a[0] + (b[0] - a[0]) * t,
+                a[1] + (b[1] - a[1]) * t,
+                a[2] + (b[2] - a[2]) * t,
+            ];
+            let depth = self.stamp(p, profile);
+            if depth > RAPID_CLEARANCE && deepest.is_none_or(|(d, _)| depth > d) {
+                deepest = Some((depth, p));
+            }
+        }
+        deepest
+    }
+
+    /// Cut with the tool at `p`; returns the most material removed from
+    /// any one cell.
+    fn stamp(&mut self, p: [f64; 3], profile: &Profile) -> f64 {
+        let mut removed: f64 = 0.0;
+        if p[2] - profile.reference >= self.top {
+            return removed;
+        }
+        let r = profile.radius;
+        let span = |lo: f64, hi: f64, origin: f64, n: usize| {
+            let first = ((lo - origin) / self.cell).floor().max(0.0);
+            let last = ((hi - origin) / self.cell).floor().min(n as f64 - 1.0);
+            (first as usize)..(last + 1.0).max(first) as usize
+        };
+        // The cell under the axis is cut even by a tool narrower than it.
+        let home = (
+            ((p[0] - self.min.x) / self.cell).floor(),
+            ((p[1] - self.min.y) / self.cell).floor(),
+        );
+        for j in span(p[1] - r, p[1] + r, self.min.y, self.ny) {
+            for i in span(p[0] - r, p[0] + r, self.min.x, self.nx) {
+                let c = self.cell_centre(i, j);
+                let mut d = ((c.x - p[0]).powi(2) + (c.y - p[1]).powi(2)).sqrt();
+                if (i as f64, j as f64) == home {
+                    d = d.min(r);
+                }
+                let Some(h) = profile.height(d) else {
+                    continue;
+                };
+                let bottom = p[2] + h;
+                let cell = &mut self.heights[j * self.nx + i];
+                if bottom < *cell {
+                    removed = removed.max(*cell - bottom);
+                    *cell = bottom;
+                }
+            }
+        }
+        removed
+    }
+
+    /// Material removed so far (mm³).
+    pub fn removed_volume(&self) -> f64 {
+        let area = self.cell * self.cell;
+        self.heights.iter().map(|h| (self.top - h) * area).sum()
+    }
+
+    /// Gouges and leftover stock compared with `mesh`. Cells off the
+    /// model are not checked.
+    pub fn compare(&self, mesh: &Mesh, params: &SimParams) -> (DefectSummary, DefectSummary) {
+        let mut gouges = Vec::new();
+        let mut leftover = Vec::new();
+        for j in 0..self.ny {
+            for i in 0..self.nx {
+                let c = self.cell_centre(i, j);
+                let Some(model) = mesh_height_at(mesh, c.x, c.y) else {
+                    continue;
+                };
+                let h = self.height(i, j);
+                if h < model - params.gouge_tolerance {
+                    gouges.push(Defect {
+                        x: c.x,
+                        y: c.y,
+                        depth: model - h,
+                    });
+                } else if h > model + params.leftover_tolerance {
+                    leftover.push(Defect {
+                        x: c.x,
+                        y: c.y,
+                        depth: h - model,
+                    });
+                }
+            }
+        }
+        (summarize(gouges), summarize(leftover))
+    }
+
+    /// The heightmap as a grayscale image.
+    pub fn depth_image(&self) -> DepthImage {
+        let min_z = self.heights.iter().cloned().fold(self.top, f64::min);
+        let span = self.top - min_z;
+        let mut pixels = Vec::with_capacity(self.nx * self.ny);
+        for j in (0..self.ny).rev() {
+            for i in 0..self.nx {
+                let v = if span > 0.0 {
+                    (self.height(i, j) - min_z) / span
+                } else {
+                    1.0
+                };
+                pixels.push((v * 255.0).round() as u8);
+            }
+        }
+        DepthImage {
+            width: self.nx,
+            height: self.ny,
+            origin: [self.min.x, self.min.y],
+            cell_size: self.cell,
+            min_z,
+            max_z: self.top,
+            pixels,
+        }
+    }
+}
+
+/// Cut `stock` with `toolpaths` and check it against `model` if given.
+pub fn simulate(
+    mut stock: Stock,
+    toolpaths: &[Toolpath],
+    tool: &Tool,
+    model: Option<&Mesh>,
+    params: &SimParams,
+) -> SimReport {
+    let rapid_collisions = stock.cut(toolpaths, tool);
+    let (gouges, leftover) = model
+        .map(|mesh| stock.compare(mesh, params))
+        .unwrap_or_default();
+    SimReport {
+        removed_volume: stock.removed_volume(),
+        gouges,
+        leftover,
+        rapid_collisions,
+        image: stock.depth_image(),
+    }
+}
+
+fn summarize(mut defects: Vec<Defect>) -> DefectSummary {
+    defects.sort_by(|a, b| b.depth.total_cmp(&a.depth));
+    DefectSummary {
+        count: defects.len(),
+        max_depth: defects.first().map_or(0.0, |d| d.depth),
+        samples: defects.into_iter().take(MAX_SAMPLES).collect(),
+    }
+}
+
+/// The tool's underside relative to the move's Z.
+struct Profile {
+    tool: Tool,
+    radius: f64,
+    /// Height of the move's reference point above the tip. Surface paths
+    /// place a ball end by its centre (see `slicer::project_ball_tool`);
+    /// every other tool is placed by its tip.
+    reference: f64,
+}
+
+impl Profile {
+    fn of(tool: &Tool) -> Self {
+        let radius = tool.diameter / 2.0;
+        let reference = match tool.tool_type {
+            ToolType::BallEnd => tool.corner_radius.clamp(0.0, radius),
+            _ => 0.0,
+        };
+        Self {
+            tool: tool.clone(),
+            radius,
+            reference,
+        }
+    }
+
+    /// Height of the underside at distance `d` from the axis, or `None`
+    /// outside the tool.
+    fn height(&self, d: f64) -> Option<f64> {
+        Some(self.tool.height_at_radius(d)? - self.reference)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geometry::{Triangle, Vec3};
+
+    fn line(points: &[(f64, f64, f64, bool)]) -> Vec<Toolpath> {
+        let mut tp = Toolpath::new();
+        for &(x, y, z, rapid) in points {
+            if rapid {
+                tp.rapid(x, y, z);
+            } else {
+                tp.cut(x, y, z);
+            }
+        }
+        vec![tp]
+    }
+
+    /// A 10 x 10 plane from (0, 0, z0) rising by `slope` per mm in X.
+    fn plane(z0: f64, slope: f64) -> Mesh {
+        let v = |x: f64, y: f64| Vec3::new(x, y, z0 + slope * x);
+        let n = Vec3::new(0.0, 0.0, 1.0);
+        Mesh::new(vec![
+            Triangle {
+                normal: n,
+                v0: v(0.0, 0.0),
+                v1: v(10.0, 0.0),
+                v2: v(10.0, 10.0),
+            },
+            Triangle {
+                normal: n,
+                v0: v(0.0, 0.0),
+                v1: v(10.0, 10.0),
+                v2: v(0.0, 10.0),
+            },
+        ])
+    }
+
+    fn stock() -> Stock {
+        Stock::new(Vec2::new(0.0, 0.0), Vec2::new(20.0, 10.0), 0.0, 0.25)
+    }
+
+    #[test]
+    fn slot_has_tool_width_and_depth() {
+        let mut s = stock();
+        let tool = Tool::new(ToolType::EndMill, 4.0, 10.0, 0.0);
+        let paths = line(&[
+            (2.0, 5.0, 5.0, true),
+            (2.0, 5.0, -1.0, false),
+            (18.0, 5.0, -1.0, false),
+            (18.0, 5.0, 5.0, true),
+        ]);
+        assert!(s.cut(&paths, &tool).is_empty());
+        assert_eq!(s.height_at(10.0, 5.0), Some(-1.0));
+        assert_eq!(s.height_at(10.0, 6.8), Some(-1.0));
+        assert_eq!(s.height_at(10.0, 7.2), Some(0.0));
+        // 16 mm slot plus two half-circle ends, 1 mm deep.
+        let expected = 16.0 * 4.0 + std::f64::consts::PI * 4.0;
+        assert!((s.removed_volume() - expected).abs() < 0.05 * expected);
+    }
+
+    #[test]
+    fn vbit_groove_widens_with_depth() {
+        let mut s = stock();
+        let tool = Tool::vbit(10.0, 90.0, 0.0, 10.0);
+        s.cut(
+            &line(&[(2.0, 5.0, -2.0, false), (18.0, 5.0, -2.0, false)]),
+            &tool,
+        );
+        // 90 degrees: the groove is as wide on each side as it is deep.
+        // Cell centres are 0.125, 1.125 and 2.125 mm off the axis.
+        let at = |y: f64| s.height_at(10.0, y).unwrap();
+        assert!((at(5.1) + 1.875).abs() < 1e-9);
+        assert!((at(6.1) + 0.875).abs() < 1e-9);
+        assert_eq!(at(7.1), 0.0);
+    }
+
+    #[test]
+    fn finds_gouges_and_leftover() {
+        // Model surface z = x / 10 over 0..10; stock top at 1.
+        let mesh = plane(0.0, 0.1);
+        let mut s = Stock::for_mesh(&mesh, 0.25).unwrap();
+        assert_eq!(s.top, 1.0);
+        let tool = Tool::new(ToolType::EndMill, 2.0, 10.0, 0.0);
+        // Flat pass at 0.5 across the middle: gouges where the surface
+        // is above it, leaves stock where it is below.
+        s.cut(
+            &line(&[(0.0, 5.0, 0.5, false), (10.0, 5.0, 0.5, false)]),
+            &tool,
+        );
+        let (gouges, leftover) = s.compare(&mesh, &SimParams::default());
+        assert!(gouges.count > 0);
+        assert!(gouges.samples.iter().all(|g| g.x > 5.0));
+        assert!(
+            (gouges.max_depth - 0.4875).abs() < 1e-6,
+            "{}",
+            gouges.max_depth
+        );
+        assert!(gouges.samples.windows(2).all(|w| w[0].depth >= w[1].depth));
+        // Everywhere else the stock top is left, except right at x = 10.
+        assert!(leftover.count > 300);
+        assert!(leftover.max_depth > 0.9);
+    }
+
+    #[test]
+    fn rapids_through_material_are_collisions() {
+        let mut s = stock();
+        let tool = Tool::new(ToolType::EndMill, 2.0, 10.0, 0.0);
+        let mut paths = line(&[(1.0, 1.0, 5.0, true), (1.0, 1.0, 1.0, true)]);
+        paths.extend(line(&[(1.0, 1.0, -0.5, true), (15.0, 1.0, -0.5, true)]));
+        let hits = s.cut(&paths, &tool);
+        assert_eq!(hits.len(), 2, "{:?}", hits);
+        assert_eq!(hits[0].move_index, 2);
+        assert!((hits[0].depth - 0.5).abs() < 1e-9);
+        // The second rapid starts in the hole the first one made.
+        assert_eq!(hits[1].move_index, 3);
+        assert!(hits[1].x > 1.0);
+    }
+
+    #[test]
+    fn ball_end_is_placed_by_its_centre() {
+        let mut s = stock();
+        let tool = Tool::ball_end(4.0, 10.0);
+        s.cut(&line(&[(10.0, 5.0, 1.0, false)]), &tool);
+        // Centre 1 mm up, radius 2: the tip is 1 mm deep.
+        assert!((s.height_at(10.1, 5.1).unwrap() + 1.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn report_has_a_depth_image() {
+        let mesh = plane(0.0, 0.0);
+        let tool = Tool::new(ToolType::EndMill, 2.0, 10.0, 0.0);
+        let paths = line(&[(5.0, 8.0, -1.0, false)]);
+        let report = simulate(
+            Stock::new(Vec2::new(0.0, 0.0), Vec2::new(10.0, 10.0), 0.0, 1.0),
+            &paths,
+            &tool,
+            Some(&mesh),
+            &SimParams::default(),
+        );
+        let img = &report.image;
+        assert_eq!((img.width, img.height), (10, 10));
+        assert_eq!((img.min_z, img.max_z), (-1.0, 0.0));
+        // The cut near the top of the stock is near the top of the image.
+        assert_eq!(img.pixels[2 * 10 + 5], 0);
+        assert_eq!(img.pixels[8 * 10 + 5], 255);
+        assert!(report.gouges.count > 0);
+        assert!(report.removed_volume > 0.0);
+    }
+}
@@ -37,7 +37,7 @@ pub fn slice_at_z(mesh: &Mesh, z: f64) -> Vec<Polyline> {
 /// line segment.
 fn collect_segments(mesh: &Mesh, z: f64) -> Vec<Segment2> {
     let mut segs = Vec::new();
-    for tri in &mesh.triangles {
+    for tri in mesh.triangles_at_z(z) {
         if tri.min_z() > z || tri.max_z() < z {
             continue;
         }
@@ -83,7 +83,7 @@ fn intersect_triangle_z(a: Vec3, b: Vec3, c: Vec3, z: f64) -> Option<Segment2> {
 pub fn surface_normal_at(mesh: &Mesh, x: f64, y: f64) -> Option<Vec3> {
     let mut best: Option<(f64, Vec3)> = None;
 
-    for tri in &mesh.triangles {
+    for tri in mesh.triangles_at_xy(x, y) {
         if let Some(z) = triangle_z_at_xy(tri.v0, tri.v1, tri.v2, x, y) {
             match best {
                 None => best = Some((z, tri.normal)),
@@ -152,6 +152,142 @@ pub fn project_ball_tool(mesh: &Mesh, x: f64, y: f64, radius: f64) -> Option<f64
     best
 }
 
+/// Exact tool-tip Z for a flat end mill of radius `radius` centred at
+/// `(x, y)`: the highest point of the mesh under the whole disc, not just
+/// at sample points. `None` when the disc misses the mesh.
+///
+/// Height is linear over each triangle, so its highest point under the
+/// disc is a vertex inside it, where an edge leaves it, or on the rim in
+/// the uphill direction of the facet.
+pub fn drop_flat_tool(mesh: &Mesh, x: f64, y: f64, radius: f64) -> Option<f64> {
+    let c = Vec2::new(x, y);
+    let r2 = radius * radius;
+    let mut best: Option<f64> = None;
+    let mut offer = |z: f64| best = Some(best.map_or(z, |b: f64| b.max(z)));
+    for tri in mesh.triangles_near_xy(x, y, radius) {
+        let v = [tri.v0, tri.v1, tri.v2];
+        for k in 0..3 {
+            let (p, q) = (v[k], v[(k + 1) % 3]);
+            if let Some((t0, t1)) = clip_to_disc(p, q, c, r2) {
+                offer(p.z + (q.z - p.z) * t0);
+                offer(p.z + (q.z - p.z) * t1);
+            }
+        }
+        if let Some((gx, gy)) = plane_gradient(tri.v0, tri.v1, tri.v2) {
+            let g = gx.hypot(gy);
+            let (px, py) = if g > 1e-12 {
+                (x + radius * gx / g, y + radius * gy / g)
+            } else {
+                (x, y)
+            };
+            if let Some(z) = triangle_z_at_xy(tri.v0, tri.v1, tri.v2, px, py) {
+                offer(z);
+            }
+        }
+    }
+    best
+}
+
+/// Exact tool-centre Z for a ball-end mill of radius `radius` above
+/// `(x, y)`: the lowest centre at which the ball touches no triangle's
+/// face, edge or vertex from above. `None` when the ball misses the mesh.
+pub fn drop_ball_tool(mesh: &Mesh, x: f64, y: f64, radius: f64) -> Option<f64> {
+    let c = Vec2::new(x, y);
+    let r2 = radius * radius;
+    let mut best: Option<f64> = None;
+    let mut offer = |z: f64| best = Some(best.map_or(z, |b: f64| b.max(z)));
+    for tri in mesh.triangles_near_xy(x, y, radius) {
+        let v = [tri.v0, tri.v1, tri.v2];
+        for &p in &v {
+            let d2 = (p.x - x).powi(2) + (p.y - y).powi(2);
+            if d2 <= r2 {
+                offer(p.z + (r2 - d2).sqrt());
+            }
+        }
+        for k in 0..3 {
+            if let Some(z) = ball_on_edge(v[k], v[(k + 1) % 3], c, r2) {
+                offer(z);
+            }
+        }
+        // Facet contact: the ball touches the plane at the centre minus
+        // the radius along the upward unit normal.
+        if let Some((gx, gy)) = plane_gradient(tri.v0, tri.v1, tri.v2) {
+            let len = (gx * gx + gy * gy + 1.0).sqrt();
+            let (nx, ny, nz) = (-gx / len, -gy / len, 1.0 / len);
+            let (px, py) = (x - radius * nx, y - radius * ny);
+            if let Some(z) = triangle_z_at_xy(tri.v0, tri.v1, tri.v2, px, py) {
+                offer(z + radius * nz);
+            }
+        }
+    }
+    best
+}
+
+/// Parameters along `p`→`q` (in XY) of the part inside the disc of
+/// squared radius `r2` around `c`.
+fn clip_to_disc(p: Vec3, q: Vec3, c: Vec2, r2: f64) -> Option<(f64, f64)> {
+    let (dx, dy) = (q.x - p.x, q.y - p.y);
+    let (fx, fy) = (p.x - c.x, p.y - c.y);
+    let a = dx * dx + dy * dy;
+    let cc = fx * fx + fy * fy - r2;
+    if a < 1e-18 {
+        // A vertical edge: inside or not as a point.
+        return (cc <= 0.0).then_some((0.0, 1.0));
+    }
+    let b = 2.0 * (fx * dx + fy * dy);
+    let disc = b * b - 4.0 * a * cc;
+    if disc < 0.0 {
+        return None;
+    }
+    let s = disc.sqrt();
+    let (t0, t1) = (
+        ((-b - s) / (2.0 * a)).max(0.0),
+        ((-b + s) / (2.0 * a)).min(1.0),
+    );
+    (t0 <= t1).then_some((t0, t1))
+}
+
+/// Centre Z of a ball over `c` resting on the segment `p`→`q`, if it
+/// touches inside the segment.
+fn ball_on_edge(p: Vec3, q: Vec3, c: Vec2, r2: f64) -> Option<f64> {
+    let d = Vec3::new(q.x - p.x, q.y - p.y, q.z - p.z);
+    let dd = d.x * d.x + d.y * d.y + d.z * d.z;
+    let h2 = d.x * d.x + d.y * d.y;
+    if dd < 1e-18 || h2 < 1e-18 {
+        // Points and vertical edges touch at their vertices.
+        return None;
+    }
+    // Distance from the centre (c, zc) to the line, as a quadratic in zc:
+    // |w|^2 - (w.d)^2 / |d|^2 = r^2 with w = centre - p.
+    let (wx, wy) = (c.x - p.x, c.y - p.y);
+    let e = wx * d.x + wy * d.y;
+    // w.d = e + wz * dz and |w|^2 = wx^2 + wy^2 + wz^2, with wz = zc - p.z.
+    let qa = 1.0 - d.z * d.z / dd;
+    let qb = -2.0 * e * d.z / dd;
+    let qc = wx * wx + wy * wy - e * e / dd - r2;
+    let disc = qb * qb - 4.0 * qa * qc;
+    if disc < 0.0 {
+        return None;
+    }
+    let wz = (-qb + disc.sqrt()) / (2.0 * qa);
+    let t = (e + wz * d.z) / dd;
+    (0.0..=1.0).contains(&t).then_some(p.z + wz)
+}
+
+/// Slope (dz/dx, dz/dy) of the triangle's plane, or `None` for a
+/// vertical triangle.
+fn plane_gradient(v0: Vec3, v1: Vec3, v2: Vec3) -> Option<(f64, f64)> {
+    let (ax, ay, az) = (v1.x - v0.x, v1.y - v0.y, v1.z - v0.z);
+    let (bx, by, bz) = (v2.x - v0.x, v2.y - v0.y, v2.z - v0.z);
+    let nz = ax * by - ay * bx;
+    if nz.abs() < 1e-12 {
+        return None;
+    }
+    let nx = ay * bz - az * by;
+    let ny = az * bx - ax * bz;
+    Some((-nx / nz, -ny / nz))
+}
+
 /// Enumerate the `(x, y, d)` triples for the disc sampling grid around
 /// `(cx, cy)`, where `d` is the planar distance from the center. For
 /// `radius == 0` only the center sample is yielded.
@@ -180,7 +316,7 @@ fn disc_samples(cx: f64, cy: f64, radius: f64) -> Vec<(f64, f64, f64)> {
 pub fn mesh_height_at(mesh: &Mesh, x: f64, y: f64) -> Option<f64> {
     let mut max_z: Option<f64> = None;
 
-    for tri in &mesh.triangles {
+    for tri in mesh.triangles_at_xy(x, y) {
         if let Some(z) = triangle_z_at_xy(tri.v0, tri.v1, tri.v2, x, y) {
             max_z = Some(max_z.map_or(z, |current| current.max(z)));
         }
@@ -285,6 +421,126 @@ mod tests {
         Mesh::new(vec![t1, t2])
     }
 
+    /// A wavy heightfield with a raised block floating over part of it,
+    /// so height queries see overlapping triangles.
+    fn make_wavy_mesh(n: usize) -> Mesh {
+        let h = |i: usize, j: usize| (i as f64 * 0.7).sin() * (j as f64 * 0.4).cos() * 3.0;
+        let mut tris = Vec::new();
+        let up = Vec3::new(0.0, 0.0, 1.0);
+        for i in 0..n {
+            for j in 0..n {
+                let p = |a: usize, b: usize| Vec3::new(a as f64, b as f64, h(a, b));
+                let (a, b, c, d) = (p(i, j), p(i + 1, j), p(i + 1, j + 1), p(i, j + 1));
+                tris.push(Triangle {
+                    normal: up,
+                    v0: a,
+                    v1: b,
+                    v2: c,
+                });
+                tris.push(Triangle {
+                    normal: Vec3::new(0.0, 1.0, 0.0),
+                    v0: a,
+                    v1: c,
+                    v2: d,
+                });
+            }
+        }
+        let lid = |x: f64, y: f64| Vec3::new(x, y, 2.0 + x * 0.1);
+        tris.push(Triangle {
+            normal: up,
+            v0: lid(2.5, 2.5),
+            v1: lid(7.0, 2.5),
+            v2: lid(7.0, 6.0),
+        });
+        Mesh::new(tris)
+    }
+
+    #[test]
+    fn test_exact_drops_find_corners_the_disc_samples_miss() {
+        // A 2 mm square top at Z 10 with its corner at the origin.
+        let up = Vec3::new(0.0, 0.0, 1.0);
+        let v = |x: f64, y: f64| Vec3::new(x, y, 10.0);
+        let mesh = Mesh::new(vec![
+            Triangle {
+                normal: up,
+                v0: v(0.0, 0.0),
+                v1: v(2.0, 0.0),
+                v2: v(2.0, 2.0),
+            },
+            Triangle {
+                normal: up,
+                v0: v(0.0, 0.0),
+                v1: v(2.0, 2.0),
+                v2: v(0.0, 2.0),
+            },
+        ]);
+        // The corner is 2.86 mm away, between two rim samples.
+        let (x, y) = (-1.2, -2.6);
+        assert_eq!(project_flat_tool(&mesh, x, y, 3.0), None);
+        assert_eq!(drop_flat_tool(&mesh, x, y, 3.0), Some(10.0));
+        let lift = (9.0 - (1.2f64 * 1.2 + 2.6 * 2.6)).sqrt();
+        let z = drop_ball_tool(&mesh, x, y, 3.0).unwrap();
+        assert!((z - (10.0 + lift)).abs() < 1e-9, "{z}");
+        // Beside an edge the ball rests on it; on the top, on the facet.
+        let z = drop_ball_tool(&mesh, 1.0, -2.5, 3.0).unwrap();
+        assert!((z - (10.0 + 2.75f64.sqrt())).abs() < 1e-9, "{z}");
+        assert_eq!(drop_ball_tool(&mesh, 1.0, 1.0, 3.0), Some(13.0));
+        assert_eq!(drop_flat_tool(&mesh, 6.0, 6.0, 3.0), None);
+    }
+
+    #[test]
+    fn test_indexed_queries_match_brute_force() {
+        let mesh = make_wavy_mesh(12);
+        let brute_height = |x: f64, y: f64| {
+            mesh.triangles
+                .iter()
+                .filter_map(|t| triangle_z_at_xy(t.v0, t.v1, t.v2, x, y))
+                .fold(None, |m: Option<f64>, z| Some(m.map_or(z, |m| m.max(z))))
+        };
+        let brute_normal = |x: f64, y: f64| {
+            let mut best: Option<(f64, Vec3)> = None;
+            for t in &mesh.triangles {
+                if let Some(z) = triangle_z_at_xy(t.v0, t.v1, t.v2, x, y) {
+                    if best.is_none_or(|(m, _)| z > m) {
+                        best = Some((z, t.normal));
+                    }
+                }
+            }
+            best.map(|(_, n)| n)
+        };
+        // Vertices, edges, interiors and points off the mesh.
+        for i in -4..=52 {
+            for j in -4..=52 {
+                let (x, y) = (i as f64 * 0.25, j as f64 * 0.25 + 0.001 * (i % 3) as f64);
+                assert_eq!(
+                    mesh_height_at(&mesh, x, y),
+                    brute_height(x, y),
+                    "({x}, {y})"
+                );
+                assert_eq!(
+                    surface_normal_at(&mesh, x, y),
+                    brute_normal(x, y),
+                    "({x}, {y})"
+                );
+            }
+        }
+        let mut z = -3.5;
+        while z < 3.5 {
+            let brute: Vec<Segment2> = mesh
+                .triangles
+                .iter()
+                .filter(|t| t.min_z() <= z && t.max_z() >= z)
+                .filter_map(|t| intersect_triangle_z(t.v0, t.v1, t.v2, z))
+                .collect();
+            let indexed = collect_segments(&mesh, z);
+            assert_eq!(indexed.len(), brute.len(), "z {z}");
+            for (a, b) in indexed.iter().zip(&brute) {
+                assert_eq!((a.a, a.b), (b.a, b.b), "z {z}");
+            }
+            z += 0.25;
+        }
+    }
+
     #[test]
     fn test_slice_produces_segments() {
         let mesh = make_flat_quad_mesh(5.0);
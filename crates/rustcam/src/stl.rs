@@ -1,7 +1,7 @@
 /// STL file parser — binary and ASCII formats.
 ///
 /// Swiss-cheese layer: **Geometry Input**
-/// Plug a different parser here (STEP, OBJ, 3MF, …) by producing a `Mesh`.
+/// Other mesh formats (OBJ, 3MF, PLY) live beside this one; see `mesh_import`.
 use crate::geometry::{Mesh, Triangle, Vec3};
 
 /// Detect format and parse an STL file from raw bytes.
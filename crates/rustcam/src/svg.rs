@@ -1,254 +1,688 @@
-/// SVG parser — extracts paths and basic shapes into polylines.
+/// SVG importer — paths and basic shapes into polylines in millimetres.
 ///
 /// Swiss-cheese layer: **Geometry Input (2-D)**
-/// Extension point: add more SVG element types or a full XML parser.
+/// Extension point: add element types in `Importer::shape`.
+///
+/// The document is read as XML. Every shape is flattened in its own user
+/// space and mapped to millimetres through the transforms of its
+/// ancestors, nested viewports and the root `viewBox`. The root's
+/// `width`/`height` give the physical size when they carry a unit. A
+/// unitless or missing size is read in the caller's `SvgUnits`: CSS pixels
+/// (96 per inch) by default, as the SVG standard, browsers and Illustrator
+/// have it, or one user unit per millimetre on request. Y stays
+/// pointing down, as drawn.
+use std::collections::HashMap;
+
+use roxmltree::{Document, Node};
+use serde::Serialize;
+
+use crate::geometry::affine::Affine2;
+use crate::geometry::flatten;
 use crate::geometry::{Polyline, Vec2};
 
-/// Parse an SVG string and return all paths as polylines.
-pub fn parse_svg(svg: &str) -> Result<Vec<Polyline>, String> {
-    let mut polylines = Vec::new();
+/// Largest distance (mm) between a flattened arc and its chords.
+const FLATTEN_TOLERANCE: f64 = 0.01;
+/// Fewest chords for a full circle or ellipse. Larger ones get more, at
+/// half the flattening tolerance, so an outward tool offset of the chords
+/// still refits to G2/G3 within the arc tolerance.
+const ELLIPSE_SEGMENTS: usize = 64;
+/// Chords for each Bézier segment.
+const BEZIER_STEPS: usize = 16;
+/// How deep `<use>` references may nest before we assume a cycle.
+const MAX_USE_DEPTH: usize = 16;
+
+const SVG_NS: &str = "http://www.w3.org/2000/svg";
+const XLINK_NS: &str = "http://www.w3.org/1999/xlink";
+
+/// What a unitless root `width`/`height`, or a root with only a
+/// `viewBox`, measures.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SvgUnits {
+    /// One user unit per millimetre.
+    Millimetres,
+    /// CSS pixels, 96 per inch, as the SVG standard has it.
+    #[default]
+    Pixels,
+}
 
-    // Extract <path d="..."/> elements
-    for d_attr in extract_path_d_attrs(svg) {
-        let pl = parse_path_d(&d_attr)?;
-        if !pl.points.is_empty() {
-            polylines.push(pl);
+impl SvgUnits {
+    /// `"mm"` or `"px"`.
+    pub fn parse(name: &str) -> Result<Self, String> {
+        match name {
+            "mm" => Ok(Self::Millimetres),
+            "px" => Ok(Self::Pixels),
+            other => Err(format!("Unknown SVG units '{}' (expected mm or px)", other)),
         }
     }
 
-    // Extract <rect> elements
-    for rect in extract_rects(svg) {
-        polylines.push(rect);
+    fn mm(self) -> f64 {
+        match self {
+            Self::Millimetres => 1.0,
+            Self::Pixels => 25.4 / 96.0,
+        }
     }
+}
 
-    // Extract <circle> elements
-    for circ in extract_circles(svg) {
-        polylines.push(circ);
-    }
+/// One imported outline and the paint it was drawn with.
+#[derive(Debug, Clone, Serialize)]
+pub struct SvgShape {
+    pub polyline: Polyline,
+    /// Stroke color as `#rrggbb` (or a lowercase name we don't know),
+    /// `None` when unstroked.
+    pub stroke: Option<String>,
+    /// Fill color, same form as `stroke`.
+    pub fill: Option<String>,
+}
 
-    // Extract <polygon> and <polyline> elements
-    for pl in extract_poly_elements(svg) {
-        polylines.push(pl);
+impl SvgShape {
+    /// Whether the stroke or fill is one of `colors` (any CSS form).
+    pub fn has_color(&self, colors: &[String]) -> bool {
+        colors.iter().filter_map(|c| parse_color(c)).any(|c| {
+            self.stroke.as_deref() == Some(c.as_str()) || self.fill.as_deref() == Some(c.as_str())
+        })
     }
+}
+
+/// Parse an SVG string and return all paths as polylines.
+pub fn parse_svg(svg: &str) -> Result<Vec<Polyline>, String> {
+    parse_svg_by_color(svg, &[], SvgUnits::default())
+}
 
+/// Parse an SVG and return the outlines stroked or filled with one of
+/// `colors` (all outlines when empty).
+pub fn parse_svg_by_color(
+    svg: &str,
+    colors: &[String],
+    units: SvgUnits,
+) -> Result<Vec<Polyline>, String> {
+    let shapes = parse_svg_shapes_in(svg, units)?;
+    let polylines: Vec<Polyline> = shapes
+        .into_iter()
+        .filter(|s| colors.is_empty() || s.has_color(colors))
+        .map(|s| s.polyline)
+        .collect();
     if polylines.is_empty() {
-        return Err("No paths found in SVG".into());
+        return Err(format!(
+            "No paths found in SVG with colors {}",
+            colors.join(", ")
+        ));
     }
     Ok(polylines)
 }
 
-// ── Path d-attribute parsing ─────────────────────────────────────────
+/// Parse an SVG into shapes with their stroke and fill colors.
+pub fn parse_svg_shapes(svg: &str) -> Result<Vec<SvgShape>, String> {
+    parse_svg_shapes_in(svg, SvgUnits::default())
+}
 
-fn extract_path_d_attrs(svg: &str) -> Vec<String> {
-    let mut results = Vec::new();
-    let lower = svg.to_lowercase();
-    let mut search = svg;
-    while let Some(idx) = search.to_lowercase().find("<path") {
-        let rest = &search[idx..];
-        if let Some(end) = rest.find("/>").or_else(|| rest.find('>')) {
-            let tag = &rest[..end];
-            if let Some(d) = extract_attr(tag, "d") {
-                results.push(d);
+/// [`parse_svg_shapes`] with unitless root sizes read as `units`.
+pub fn parse_svg_shapes_in(svg: &str, units: SvgUnits) -> Result<Vec<SvgShape>, String> {
+    let doc = Document::parse(svg).map_err(|e| format!("SVG: {}", e))?;
+    let root = doc.root_element();
+    if !is_svg(root, "svg") {
+        return Err("SVG: root element is not <svg>".into());
+    }
+    let mut importer = Importer {
+        doc: &doc,
+        rules: stylesheet_rules(&doc),
+        shapes: Vec::new(),
+        use_depth: 0,
+    };
+    let root_style = Style {
+        fill: Some("#000000".into()),
+        stroke: None,
+        color: Some("#000000".into()),
+    };
+    importer.children(root, &root_transform(root, units), &root_style)?;
+    if importer.shapes.is_empty() {
+        return Err("No paths found in SVG".into());
+    }
+    Ok(importer.shapes)
+}
+
+// ── Document walk ────────────────────────────────────────────────────
+
+/// Inherited paint.
+#[derive(Debug, Clone)]
+struct Style {
+    fill: Option<String>,
+    stroke: Option<String>,
+    /// `currentColor`.
+    color: Option<String>,
+}
+
+struct Importer<'d, 'i> {
+    doc: &'d Document<'i>,
+    rules: Vec<Rule>,
+    shapes: Vec<SvgShape>,
+    use_depth: usize,
+}
+
+impl<'d, 'i> Importer<'d, 'i> {
+    fn children(
+        &mut self,
+        parent: Node<'d, 'i>,
+        ctm: &Affine2,
+        style: &Style,
+    ) -> Result<(), String> {
+        for child in parent.children().filter(Node::is_element) {
+            self.element(child, ctm, style)?;
+        }
+        Ok(())
+    }
+
+    fn element(&mut self, node: Node<'d, 'i>, ctm: &Affine2, parent: &Style) -> Result<(), String> {
+        let ns = node.tag_name().namespace();
+        if ns.is_some() && ns != Some(SVG_NS) {
+            return Ok(());
+        }
+        let props = self.properties(node);
+        if props.get("display").map(String::as_str) == Some("none") {
+            return Ok(());
+        }
+        let mut style = parent.clone();
+        if let Some(color) = props.get("color") {
+            style.color = paint(color, parent.color.clone(), &parent.color);
+        }
+        if let Some(fill) = props.get("fill") {
+            style.fill = paint(fill, parent.fill.clone(), &style.color);
+        }
+        if let Some(stroke) = props.get("stroke") {
+            style.stroke = paint(stroke, parent.stroke.clone(), &style.color);
+        }
+        let ctm = ctm.after(&parse_transform(node.attribute("transform").unwrap_or("")));
+
+        match node.tag_name().name() {
+            "g" | "a" | "switch" => self.children(node, &ctm, &style),
+            "svg" => {
+                let (x, y) = (attr_length(node, "x"), attr_length(node, "y"));
+                let viewport = match (length_attr(node, "width"), length_attr(node, "height")) {
+                    (Some(w), Some(h)) => viewbox_transform(node, w, h),
+                    _ => viewbox_transform(node, 0.0, 0.0),
+                };
+                let ctm = ctm.after(&Affine2::translate(x, y)).after(&viewport);
+                self.children(node, &ctm, &style)
+            }
+            "use" => self.reference(node, &ctm, &style),
+            _ => {
+                let tol = FLATTEN_TOLERANCE / ctm.max_scale().max(1e-12);
+                for local in shape(node, tol)? {
+                    let points = local.points.iter().map(|&p| ctm.apply(p)).collect();
+                    self.shapes.push(SvgShape {
+                        polyline: Polyline::new(points, local.closed),
+                        stroke: style.stroke.clone(),
+                        fill: style.fill.clone(),
+                    });
+                }
+                Ok(())
             }
-            search = &search[idx + end + 1..];
-        } else {
-            break;
         }
     }
-    let _ = lower; // used implicitly above
-    results
-}
 
-fn extract_attr(tag: &str, name: &str) -> Option<String> {
-    let pattern = format!("{}=\"", name);
-    if let Some(start) = tag.find(&pattern) {
-        let val_start = start + pattern.len();
-        if let Some(end) = tag[val_start..].find('"') {
-            return Some(tag[val_start..val_start + end].to_string());
+    /// `<use href="#id" x y>`: the referenced element drawn in place. A
+    /// `<symbol>` draws its children like a group.
+    fn reference(
+        &mut self,
+        node: Node<'d, 'i>,
+        ctm: &Affine2,
+        style: &Style,
+    ) -> Result<(), String> {
+        let href = node
+            .attribute("href")
+            .or_else(|| node.attribute((XLINK_NS, "href")))
+            .and_then(|h| h.strip_prefix('#'));
+        let Some(target) = href.and_then(|id| {
+            self.doc
+                .descendants()
+                .find(|n| n.attribute("id") == Some(id))
+        }) else {
+            return Ok(());
+        };
+        if self.use_depth >= MAX_USE_DEPTH {
+            return Err("SVG: <use> references nest too deep (cycle?)".into());
         }
+        let ctm = ctm.after(&Affine2::translate(
+            attr_length(node, "x"),
+            attr_length(node, "y"),
+        ));
+        self.use_depth += 1;
+        let result = if is_svg(target, "symbol") {
+            self.children(target, &ctm, style)
+        } else {
+            self.element(target, &ctm, style)
+        };
+        self.use_depth -= 1;
+        result
     }
-    // Also handle single-quoted attributes
-    let pattern_sq = format!("{}='", name);
-    if let Some(start) = tag.find(&pattern_sq) {
-        let val_start = start + pattern_sq.len();
-        if let Some(end) = tag[val_start..].find('\'') {
-            return Some(tag[val_start..val_start + end].to_string());
+
+    /// `fill`, `stroke`, `color` and `display` of `node`, from its
+    /// presentation attributes, then the stylesheet, then `style=""`.
+    fn properties(&self, node: Node) -> HashMap<&'static str, String> {
+        const NAMES: [&str; 4] = ["fill", "stroke", "color", "display"];
+        let mut props = HashMap::new();
+        for name in NAMES {
+            if let Some(v) = node.attribute(name) {
+                props.insert(name, v.trim().to_string());
+            }
+        }
+        let mut matching: Vec<&Rule> = self.rules.iter().filter(|r| r.matches(node)).collect();
+        matching.sort_by_key(|r| r.specificity);
+        let inline = node
+            .attribute("style")
+            .map(declarations)
+            .unwrap_or_default();
+        let decls = matching
+            .iter()
+            .flat_map(|r| r.declarations.iter())
+            .chain(inline.iter());
+        for (key, value) in decls {
+            if let Some(name) = NAMES.iter().find(|n| **n == key) {
+                props.insert(name, value.clone());
+            }
         }
+        props
+    }
+}
+
+fn is_svg(node: Node, name: &str) -> bool {
+    let ns = node.tag_name().namespace();
+    node.tag_name().name() == name && (ns.is_none() || ns == Some(SVG_NS))
+}
+
+/// Resolve a paint value: `inherit` keeps the parent's, `currentColor`
+/// takes `color`, and `none`/paint servers give no color.
+fn paint(value: &str, inherited: Option<String>, current: &Option<String>) -> Option<String> {
+    match value.trim() {
+        "inherit" => inherited,
+        v if v.eq_ignore_ascii_case("currentcolor") => current.clone(),
+        v => parse_color(v),
+    }
+}
+
+// ── Shapes ───────────────────────────────────────────────────────────
+
+/// The outlines of a basic shape or path in its own user space.
+fn shape(node: Node, tol: f64) -> Result<Vec<Polyline>, String> {
+    let len = |name| attr_length(node, name);
+    Ok(match node.tag_name().name() {
+        "path" => parse_path_d(node.attribute("d").unwrap_or(""), tol)?,
+        "rect" => rect(node, tol).into_iter().collect(),
+        "circle" => ellipse(len("cx"), len("cy"), len("r"), len("r"), tol)
+            .into_iter()
+            .collect(),
+        "ellipse" => ellipse(len("cx"), len("cy"), len("rx"), len("ry"), tol)
+            .into_iter()
+            .collect(),
+        "line" => vec![Polyline::new(
+            vec![
+                Vec2::new(len("x1"), len("y1")),
+                Vec2::new(len("x2"), len("y2")),
+            ],
+            false,
+        )],
+        name @ ("polygon" | "polyline") => node
+            .attribute("points")
+            .and_then(|s| parse_points_attr(s).ok())
+            .filter(|pts| !pts.is_empty())
+            .map(|pts| Polyline::new(pts, name == "polygon"))
+            .into_iter()
+            .collect(),
+        _ => Vec::new(),
+    })
+}
+
+fn rect(node: Node, tol: f64) -> Option<Polyline> {
+    let (x, y) = (attr_length(node, "x"), attr_length(node, "y"));
+    let (w, h) = (attr_length(node, "width"), attr_length(node, "height"));
+    if w <= 0.0 || h <= 0.0 {
+        return None;
+    }
+    // A missing radius copies the other one.
+    let (rx, ry) = match (length_attr(node, "rx"), length_attr(node, "ry")) {
+        (Some(rx), Some(ry)) => (rx, ry),
+        (Some(r), None) | (None, Some(r)) => (r, r),
+        (None, None) => (0.0, 0.0),
+    };
+    let (rx, ry) = (rx.clamp(0.0, w / 2.0), ry.clamp(0.0, h / 2.0));
+    if rx <= 0.0 || ry <= 0.0 {
+        return Some(Polyline::new(
+            vec![
+                Vec2::new(x, y),
+                Vec2::new(x + w, y),
+                Vec2::new(x + w, y + h),
+                Vec2::new(x, y + h),
+            ],
+            true,
+        ));
+    }
+    // Quarter ellipses at the corners, clockwise on screen from top right.
+    let corners = [
+        (x + w - rx, y + ry, -0.5),
+        (x + w - rx, y + h - ry, 0.0),
+        (x + rx, y + h - ry, 0.5),
+        (x + rx, y + ry, 1.0),
+    ];
+    let steps = flatten::arc_segments(rx.max(ry), std::f64::consts::FRAC_PI_2, tol);
+    let mut points: Vec<Vec2> = corners
+        .iter()
+        .flat_map(|&(cx, cy, turn)| {
+            (0..=steps).map(move |i| {
+                let a = std::f64::consts::PI * (turn + 0.5 * i as f64 / steps as f64);
+                Vec2::new(cx + rx * a.cos(), cy + ry * a.sin())
+            })
+        })
+        .collect();
+    points.dedup();
+    Some(Polyline::new(points, true))
+}
+
+fn ellipse(cx: f64, cy: f64, rx: f64, ry: f64, tol: f64) -> Option<Polyline> {
+    if rx <= 0.0 || ry <= 0.0 {
+        return None;
     }
-    None
+    let steps =
+        flatten::arc_segments(rx.max(ry), std::f64::consts::TAU, tol / 2.0).max(ELLIPSE_SEGMENTS);
+    let points = (0..steps)
+        .map(|i| {
+            let angle = std::f64::consts::TAU * i as f64 / steps as f64;
+            Vec2::new(cx + rx * angle.cos(), cy + ry * angle.sin())
+        })
+        .collect();
+    Some(Polyline::new(points, true))
 }
 
-fn parse_path_d(d: &str) -> Result<Polyline, String> {
+fn parse_points_attr(s: &str) -> Result<Vec<Vec2>, String> {
     let mut points = Vec::new();
-    let mut closed = false;
-    let mut cursor = Vec2::new(0.0, 0.0);
-    let mut start = Vec2::new(0.0, 0.0);
+    let nums: Vec<f64> = s
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|s| !s.is_empty())
+        .map(|s| s.parse::<f64>())
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("points parse error: {e}"))?;
+    if !nums.len().is_multiple_of(2) {
+        return Err("Odd number of coordinates in points attribute".into());
+    }
+    for pair in nums.chunks(2) {
+        points.push(Vec2::new(pair[0], pair[1]));
+    }
+    Ok(points)
+}
+
+// ── Path d-attribute parsing ─────────────────────────────────────────
+
+/// Subpaths being collected from path data.
+struct PathBuilder {
+    subpaths: Vec<Polyline>,
+    points: Vec<Vec2>,
+    cursor: Vec2,
+    start: Vec2,
+}
+
+impl PathBuilder {
+    fn new() -> Self {
+        Self {
+            subpaths: Vec::new(),
+            points: Vec::new(),
+            cursor: Vec2::new(0.0, 0.0),
+            start: Vec2::new(0.0, 0.0),
+        }
+    }
 
+    fn move_to(&mut self, p: Vec2) {
+        self.finish(false);
+        self.cursor = p;
+        self.start = p;
+        self.points.push(p);
+    }
+
+    fn line_to(&mut self, p: Vec2) {
+        // Drawing straight after `Z` starts a new subpath at its start.
+        if self.points.is_empty() {
+            self.points.push(self.cursor);
+        }
+        self.points.push(p);
+        self.cursor = p;
+    }
+
+    fn close(&mut self) {
+        if self.points.len() > 1 && self.points.last() == self.points.first() {
+            self.points.pop();
+        }
+        self.finish(true);
+        self.cursor = self.start;
+    }
+
+    fn finish(&mut self, closed: bool) {
+        if !self.points.is_empty() {
+            let points = std::mem::take(&mut self.points);
+            self.subpaths.push(Polyline::new(points, closed));
+        }
+    }
+}
+
+/// Flatten path data into one polyline per subpath; arcs stay within
+/// `tol` of the curve.
+pub(crate) fn parse_path_d(d: &str, tol: f64) -> Result<Vec<Polyline>, String> {
     let tokens = tokenize_d(d);
+    let mut path = PathBuilder::new();
     let mut i = 0;
+    let mut cmd = ' ';
+    // Second control point of the last cubic / control of the last
+    // quadratic, for the smooth S and T commands.
+    let mut cubic_ctrl: Option<Vec2> = None;
+    let mut quad_ctrl: Option<Vec2> = None;
 
     while i < tokens.len() {
-        match tokens[i].as_str() {
-            "M" => {
-                i += 1;
-                let (x, y) = read_pair(&tokens, &mut i)?;
-                cursor = Vec2::new(x, y);
-                start = cursor;
-                points.push(cursor);
-                // Subsequent coordinate pairs after M are implicit L
-                while i < tokens.len() && is_number(&tokens[i]) {
-                    let (x, y) = read_pair(&tokens, &mut i)?;
-                    cursor = Vec2::new(x, y);
-                    points.push(cursor);
-                }
-            }
-            "m" => {
-                i += 1;
-                let (dx, dy) = read_pair(&tokens, &mut i)?;
-                cursor = Vec2::new(cursor.x + dx, cursor.y + dy);
-                start = cursor;
-                points.push(cursor);
-                while i < tokens.len() && is_number(&tokens[i]) {
-                    let (dx, dy) = read_pair(&tokens, &mut i)?;
-                    cursor = Vec2::new(cursor.x + dx, cursor.y + dy);
-                    points.push(cursor);
-                }
-            }
-            "L" => {
-                i += 1;
-                while i < tokens.len() && is_number(&tokens[i]) {
-                    let (x, y) = read_pair(&tokens, &mut i)?;
-                    cursor = Vec2::new(x, y);
-                    points.push(cursor);
-                }
-            }
-            "l" => {
-                i += 1;
-                while i < tokens.len() && is_number(&tokens[i]) {
-                    let (dx, dy) = read_pair(&tokens, &mut i)?;
-                    cursor = Vec2::new(cursor.x + dx, cursor.y + dy);
-                    points.push(cursor);
-                }
-            }
-            "H" => {
-                i += 1;
-                let x = read_one(&tokens, &mut i)?;
-                cursor = Vec2::new(x, cursor.y);
-                points.push(cursor);
-            }
-            "h" => {
-                i += 1;
-                let dx = read_one(&tokens, &mut i)?;
-                cursor = Vec2::new(cursor.x + dx, cursor.y);
-                points.push(cursor);
-            }
-            "V" => {
-                i += 1;
-                let y = read_one(&tokens, &mut i)?;
-                cursor = Vec2::new(cursor.x, y);
-                points.push(cursor);
+        if !is_number(&tokens[i]) {
+            cmd = tokens[i].chars().next().unwrap_or(' ');
+            i += 1;
+            if matches!(cmd, 'Z' | 'z') {
+                path.close();
+                cubic_ctrl = None;
+                quad_ctrl = None;
             }
-            "v" => {
-                i += 1;
-                let dy = read_one(&tokens, &mut i)?;
-                cursor = Vec2::new(cursor.x, cursor.y + dy);
-                points.push(cursor);
+            continue;
+        }
+        // Each pass reads one set of arguments; numbers after a complete
+        // set repeat the command.
+        let origin = if cmd.is_ascii_lowercase() {
+            path.cursor
+        } else {
+            Vec2::new(0.0, 0.0)
+        };
+        let point = |tokens: &[String], i: &mut usize| -> Result<Vec2, String> {
+            let (x, y) = read_pair(tokens, i)?;
+            Ok(Vec2::new(origin.x + x, origin.y + y))
+        };
+        let (mut next_cubic, mut next_quad) = (None, None);
+        match cmd.to_ascii_uppercase() {
+            'M' => {
+                path.move_to(point(&tokens, &mut i)?);
+                // Further pairs are implicit line-tos.
+                cmd = if cmd == 'm' { 'l' } else { 'L' };
             }
-            "C" => {
-                i += 1;
-                while i < tokens.len() && is_number(&tokens[i]) {
-                    let (x1, y1) = read_pair(&tokens, &mut i)?;
-                    let (x2, y2) = read_pair(&tokens, &mut i)?;
-                    let (x, y) = read_pair(&tokens, &mut i)?;
-                    let p0 = cursor;
-                    let p1 = Vec2::new(x1, y1);
-                    let p2 = Vec2::new(x2, y2);
-                    let p3 = Vec2::new(x, y);
-                    subdivide_cubic(&mut points, p0, p1, p2, p3, 16);
-                    cursor = p3;
-                }
+            'L' => path.line_to(point(&tokens, &mut i)?),
+            'H' => {
+                let x = read_one(&tokens, &mut i)? + origin.x;
+                path.line_to(Vec2::new(x, path.cursor.y));
             }
-            "c" => {
-                i += 1;
-                while i < tokens.len() && is_number(&tokens[i]) {
-                    let (dx1, dy1) = read_pair(&tokens, &mut i)?;
-                    let (dx2, dy2) = read_pair(&tokens, &mut i)?;
-                    let (dx, dy) = read_pair(&tokens, &mut i)?;
-                    let p0 = cursor;
-                    let p1 = Vec2::new(cursor.x + dx1, cursor.y + dy1);
-                    let p2 = Vec2::new(cursor.x + dx2, cursor.y + dy2);
-                    let p3 = Vec2::new(cursor.x + dx, cursor.y + dy);
-                    subdivide_cubic(&mut points, p0, p1, p2, p3, 16);
-                    cursor = p3;
-                }
+            'V' => {
+                let y = read_one(&tokens, &mut i)? + origin.y;
+                path.line_to(Vec2::new(path.cursor.x, y));
             }
-            "Q" => {
-                i += 1;
-                while i < tokens.len() && is_number(&tokens[i]) {
-                    let (x1, y1) = read_pair(&tokens, &mut i)?;
-                    let (x, y) = read_pair(&tokens, &mut i)?;
-                    let p0 = cursor;
-                    let p1 = Vec2::new(x1, y1);
-                    let p2 = Vec2::new(x, y);
-                    subdivide_quadratic(&mut points, p0, p1, p2, 16);
-                    cursor = p2;
-                }
+            'C' | 'S' => {
+                let p0 = path.cursor;
+                let p1 = if cmd.eq_ignore_ascii_case(&'S') {
+                    reflect(cubic_ctrl, p0)
+                } else {
+                    point(&tokens, &mut i)?
+                };
+                let p2 = point(&tokens, &mut i)?;
+                let p3 = point(&tokens, &mut i)?;
+                let mut out = Vec::with_capacity(BEZIER_STEPS);
+                subdivide_cubic(&mut out, p0, p1, p2, p3, BEZIER_STEPS);
+                out.into_iter().for_each(|p| path.line_to(p));
+                next_cubic = Some(p2);
             }
-            "q" => {
-                i += 1;
-                while i < tokens.len() && is_number(&tokens[i]) {
-                    let (dx1, dy1) = read_pair(&tokens, &mut i)?;
-                    let (dx, dy) = read_pair(&tokens, &mut i)?;
-                    let p0 = cursor;
-                    let p1 = Vec2::new(cursor.x + dx1, cursor.y + dy1);
-                    let p2 = Vec2::new(cursor.x + dx, cursor.y + dy);
-                    subdivide_quadratic(&mut points, p0, p1, p2, 16);
-                    cursor = p2;
-                }
+            'Q' | 'T' => {
+                let p0 = path.cursor;
+                let p1 = if cmd.eq_ignore_ascii_case(&'T') {
+                    reflect(quad_ctrl, p0)
+                } else {
+                    point(&tokens, &mut i)?
+                };
+                let p2 = point(&tokens, &mut i)?;
+                let mut out = Vec::with_capacity(BEZIER_STEPS);
+                subdivide_quadratic(&mut out, p0, p1, p2, BEZIER_STEPS);
+                out.into_iter().for_each(|p| path.line_to(p));
+                next_quad = Some(p1);
             }
-            "Z" | "z" => {
-                closed = true;
-                cursor = start;
-                i += 1;
+            'A' => {
+                let (rx, ry) = read_pair(&tokens, &mut i)?;
+                let rotation = read_one(&tokens, &mut i)?;
+                let large = read_one(&tokens, &mut i)? != 0.0;
+                let sweep = read_one(&tokens, &mut i)? != 0.0;
+                let end = point(&tokens, &mut i)?;
+                let arc = elliptical_arc(path.cursor, rx, ry, rotation, large, sweep, end, tol);
+                arc.into_iter().for_each(|p| path.line_to(p));
             }
             _ => {
-                // Skip unknown commands
+                // Skip the arguments of unknown commands.
                 i += 1;
             }
         }
+        cubic_ctrl = next_cubic;
+        quad_ctrl = next_quad;
     }
+    path.finish(false);
+    Ok(path.subpaths)
+}
 
-    Ok(Polyline::new(points, closed))
+/// The previous control point mirrored about `p`, or `p` itself.
+fn reflect(ctrl: Option<Vec2>, p: Vec2) -> Vec2 {
+    ctrl.map_or(p, |c| Vec2::new(2.0 * p.x - c.x, 2.0 * p.y - c.y))
 }
 
+/// Points of an SVG arc from `from` to `to`, excluding `from`, using the
+/// endpoint-to-centre conversion of SVG 1.1 appendix F.6.5. Radii too
+/// small to span the endpoints are scaled up.
+#[allow(clippy::too_many_arguments)]
+fn elliptical_arc(
+    from: Vec2,
+    rx: f64,
+    ry: f64,
+    rotation_deg: f64,
+    large: bool,
+    sweep: bool,
+    to: Vec2,
+    tol: f64,
+) -> Vec<Vec2> {
+    if from == to {
+        return Vec::new();
+    }
+    let (mut rx, mut ry) = (rx.abs(), ry.abs());
+    if rx < 1e-12 || ry < 1e-12 {
+        return vec![to];
+    }
+    let phi = rotation_deg.to_radians();
+    let (sin, cos) = phi.sin_cos();
+    let (hx, hy) = ((from.x - to.x) / 2.0, (from.y - to.y) / 2.0);
+    let (x1, y1) = (cos * hx + sin * hy, -sin * hx + cos * hy);
+    let lambda = (x1 / rx).powi(2) + (y1 / ry).powi(2);
+    if lambda > 1.0 {
+        rx *= lambda.sqrt();
+        ry *= lambda.sqrt();
+    }
+    let num = (rx * ry).powi(2) - (rx * y1).powi(2) - (ry * x1).powi(2);
+    let den = (rx * y1).powi(2) + (ry * x1).powi(2);
+    let sign = if large == sweep { -1.0 } else { 1.0 };
+    let k = sign * (num.max(0.0) / den).sqrt();
+    let (cx1, cy1) = (k * rx * y1 / ry, -k * ry * x1 / rx);
+    let center = Vec2::new(
+        cos * cx1 - sin * cy1 + (from.x + to.x) / 2.0,
+        sin * cx1 + cos * cy1 + (from.y + to.y) / 2.0,
+    );
+    let start = ((y1 - cy1) / ry).atan2((x1 - cx1) / rx);
+    let end = ((-y1 - cy1) / ry).atan2((-x1 - cx1) / rx);
+    let mut delta = end - start;
+    if sweep && delta < 0.0 {
+        delta += std::f64::consts::TAU;
+    } else if !sweep && delta > 0.0 {
+        delta -= std::f64::consts::TAU;
+    }
+
+    // Flatten on the unit circle, then stretch and turn into place.
+    let place = Affine2::translate(center.x, center.y)
+        .after(&Affine2::rotate(phi))
+        .after(&Affine2::scale(rx, ry));
+    let unit = flatten::arc(Vec2::new(0.0, 0.0), 1.0, start, delta, tol / rx.max(ry));
+    let mut points: Vec<Vec2> = unit[1..].iter().map(|&p| place.apply(p)).collect();
+    if let Some(last) = points.last_mut() {
+        *last = to;
+    }
+    points
+}
+
+/// Split path data into command letters and numbers. Numbers need no
+/// separator where the next one starts with a sign or a second `.`, and
+/// arc flags may be packed together (`a1 1 0 1010 10`).
 fn tokenize_d(d: &str) -> Vec<String> {
+    let bytes = d.as_bytes();
     let mut tokens = Vec::new();
-    let mut buf = String::new();
+    let mut cmd = b' ';
+    let mut args = 0;
+    let mut i = 0;
+    while i < bytes.len() {
+        let c = bytes[i];
+        if c.is_ascii_alphabetic() {
+            cmd = c.to_ascii_uppercase();
+            args = 0;
+            tokens.push((c as char).to_string());
+            i += 1;
+            continue;
+        }
+        let flag = cmd == b'A' && matches!(args % 7, 3 | 4) && matches!(c, b'0' | b'1');
+        let len = if flag { 1 } else { number_len(&bytes[i..]) };
+        if len == 0 {
+            i += 1; // separator
+            continue;
+        }
+        tokens.push(d[i..i + len].to_string());
+        args += 1;
+        i += len;
+    }
+    tokens
+}
 
-    for ch in d.chars() {
-        if ch.is_ascii_alphabetic() {
-            if !buf.is_empty() {
-                tokens.push(buf.clone());
-                buf.clear();
-            }
-            tokens.push(ch.to_string());
-        } else if ch == ',' || ch.is_whitespace() {
-            if !buf.is_empty() {
-                tokens.push(buf.clone());
-                buf.clear();
-            }
-        } else if ch == '-' && !buf.is_empty() && !buf.ends_with('e') && !buf.ends_with('E') {
-            tokens.push(buf.clone());
-            buf.clear();
-            buf.push(ch);
-        } else {
-            buf.push(ch);
+/// Length of the number at the start of `b` (0 if there is none).
+fn number_len(b: &[u8]) -> usize {
+    fn digits(b: &[u8], i: &mut usize) -> bool {
+        let start = *i;
+        while b.get(*i).is_some_and(u8::is_ascii_digit) {
+            *i += 1;
         }
+        *i > start
     }
-    if !buf.is_empty() {
-        tokens.push(buf);
+    let mut i = usize::from(matches!(b.first(), Some(b'+' | b'-')));
+    let int = digits(b, &mut i);
+    let mut frac = false;
+    if b.get(i) == Some(&b'.') {
+        i += 1;
+        frac = digits(b, &mut i);
     }
-    tokens
+    if !int && !frac {
+        return 0;
+    }
+    if matches!(b.get(i), Some(b'e' | b'E')) {
+        let mut j = i + 1;
+        if matches!(b.get(j), Some(b'+' | b'-')) {
+            j += 1;
+        }
+        if digits(b, &mut j) {
+            i = j;
+        }
+    }
+    i
 }
 
 fn is_number(s: &str) -> bool {
@@ -294,134 +728,336 @@ fn subdivide_quadratic(out: &mut Vec<Vec2>, p0: Vec2, p1: Vec2, p2: Vec2, steps:
     }
 }
 
-// ── <rect> extraction ────────────────────────────────────────────────
-
-fn extract_rects(svg: &str) -> Vec<Polyline> {
-    let mut results = Vec::new();
-    let mut search = svg;
-    while let Some(idx) = search.to_lowercase().find("<rect") {
-        let rest = &search[idx..];
-        if let Some(end) = rest.find("/>").or_else(|| rest.find('>')) {
-            let tag = &rest[..end];
-            let x = extract_attr(tag, "x")
-                .and_then(|s| s.parse().ok())
-                .unwrap_or(0.0);
-            let y = extract_attr(tag, "y")
-                .and_then(|s| s.parse().ok())
-                .unwrap_or(0.0);
-            let w: f64 = extract_attr(tag, "width")
-                .and_then(|s| s.parse().ok())
-                .unwrap_or(0.0);
-            let h: f64 = extract_attr(tag, "height")
-                .and_then(|s| s.parse().ok())
-                .unwrap_or(0.0);
-            if w > 0.0 && h > 0.0 {
-                results.push(Polyline::new(
-                    vec![
-                        Vec2::new(x, y),
-                        Vec2::new(x + w, y),
-                        Vec2::new(x + w, y + h),
-                        Vec2::new(x, y + h),
-                    ],
-                    true,
-                ));
+// ── Transforms, lengths and viewports ────────────────────────────────
+
+/// All numbers in `s`, however separated.
+fn numbers(s: &str) -> Vec<f64> {
+    let bytes = s.as_bytes();
+    let mut out = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        match number_len(&bytes[i..]) {
+            0 => i += 1,
+            len => {
+                out.extend(s[i..i + len].parse::<f64>().ok());
+                i += len;
             }
-            search = &search[idx + end + 1..];
-        } else {
-            break;
         }
     }
-    results
-}
-
-// ── <circle> extraction ──────────────────────────────────────────────
-
-fn extract_circles(svg: &str) -> Vec<Polyline> {
-    let mut results = Vec::new();
-    let mut search = svg;
-    while let Some(idx) = search.to_lowercase().find("<circle") {
-        let rest = &search[idx..];
-        if let Some(end) = rest.find("/>").or_else(|| rest.find('>')) {
-            let tag = &rest[..end];
-            let cx: f64 = extract_attr(tag, "cx")
-                .and_then(|s| s.parse().ok())
-                .unwrap_or(0.0);
-            let cy: f64 = extract_attr(tag, "cy")
-                .and_then(|s| s.parse().ok())
-                .unwrap_or(0.0);
-            let r: f64 = extract_attr(tag, "r")
-                .and_then(|s| s.parse().ok())
-                .unwrap_or(0.0);
-            if r > 0.0 {
-                let segments = 64;
-                let points: Vec<Vec2> = (0..segments)
-                    .map(|i| {
-                        let angle = 2.0 * std::f64::consts::PI * i as f64 / segments as f64;
-                        Vec2::new(cx + r * angle.cos(), cy + r * angle.sin())
-                    })
-                    .collect();
-                results.push(Polyline::new(points, true));
-            }
-            search = &search[idx + end + 1..];
-        } else {
-            break;
+    out
+}
+
+/// A `transform` attribute as one matrix. Unknown or malformed entries
+/// are ignored.
+fn parse_transform(s: &str) -> Affine2 {
+    let mut xf = Affine2::IDENTITY;
+    for item in s.split_inclusive(')') {
+        let Some((name, args)) = item.split_once('(') else {
+            continue;
+        };
+        let v = numbers(args.trim_end_matches(')'));
+        let name = name.trim_matches(|c: char| c.is_whitespace() || c == ',');
+        let next = match (name, v.as_slice()) {
+            ("matrix", &[a, b, c, d, e, f]) => Affine2::new(a, b, c, d, e, f),
+            ("translate", &[x]) => Affine2::translate(x, 0.0),
+            ("translate", &[x, y]) => Affine2::translate(x, y),
+            ("scale", &[s]) => Affine2::scale(s, s),
+            ("scale", &[sx, sy]) => Affine2::scale(sx, sy),
+            ("rotate", &[a]) => Affine2::rotate(a.to_radians()),
+            ("rotate", &[a, cx, cy]) => Affine2::translate(cx, cy)
+                .after(&Affine2::rotate(a.to_radians()))
+                .after(&Affine2::translate(-cx, -cy)),
+            ("skewX", &[a]) => Affine2::skew_x(a.to_radians()),
+            ("skewY", &[a]) => Affine2::skew_y(a.to_radians()),
+            _ => continue,
+        };
+        xf = xf.after(&next);
+    }
+    xf
+}
+
+/// Split a length into its number and lowercase unit.
+fn split_length(s: &str) -> Option<(f64, String)> {
+    let s = s.trim();
+    let len = number_len(s.as_bytes());
+    let value = s[..len].parse::<f64>().ok()?;
+    Some((value, s[len..].trim().to_ascii_lowercase()))
+}
+
+/// CSS pixels per unit. Percentages are not supported.
+fn px_per_unit(unit: &str) -> Option<f64> {
+    Some(match unit {
+        "" | "px" => 1.0,
+        "mm" => 96.0 / 25.4,
+        "cm" => 96.0 / 2.54,
+        "q" => 96.0 / 101.6,
+        "in" => 96.0,
+        "pt" => 96.0 / 72.0,
+        "pc" => 16.0,
+        "em" => 16.0,
+        _ => return None,
+    })
+}
+
+/// A length attribute in user units (CSS pixels).
+fn length_attr(node: Node, name: &str) -> Option<f64> {
+    let (value, unit) = split_length(node.attribute(name)?)?;
+    Some(value * px_per_unit(&unit)?)
+}
+
+fn attr_length(node: Node, name: &str) -> f64 {
+    length_attr(node, name).unwrap_or(0.0)
+}
+
+/// The root's `width`/`height` in millimetres; a unitless size is in
+/// `units`.
+fn root_size_mm(node: Node, name: &str, units: SvgUnits) -> Option<f64> {
+    let (value, unit) = split_length(node.attribute(name)?)?;
+    match unit.as_str() {
+        "" => Some(value * units.mm()),
+        unit => Some(value * px_per_unit(unit)? * 25.4 / 96.0),
+    }
+}
+
+/// Millimetres per user unit of the outermost `<svg>`.
+fn root_transform(svg: Node, units: SvgUnits) -> Affine2 {
+    let physical = |name| {
+        svg.attribute(name)
+            .and_then(split_length)
+            .is_some_and(|(_, unit)| !unit.is_empty())
+    };
+    let viewbox = view_box(svg);
+    let (width, height) = (
+        root_size_mm(svg, "width", units),
+        root_size_mm(svg, "height", units),
+    );
+    let user = Affine2::scale(units.mm(), units.mm());
+    match (viewbox, width, height) {
+        (Some(_), Some(w), Some(h)) => viewbox_transform(svg, w, h),
+        // One side given: the other follows the viewBox's aspect.
+        (Some([_, _, vw, vh]), Some(w), None) => viewbox_transform(svg, w, w * vh / vw),
+        (Some([_, _, vw, vh]), None, Some(h)) => viewbox_transform(svg, h * vw / vh, h),
+        (Some(_), None, None) => user.after(&viewbox_transform(svg, 0.0, 0.0)),
+        (None, ..) if physical("width") || physical("height") => {
+            Affine2::scale(25.4 / 96.0, 25.4 / 96.0)
         }
+        (None, ..) => user,
     }
-    results
-}
-
-// ── <polygon> / <polyline> extraction ────────────────────────────────
-
-fn extract_poly_elements(svg: &str) -> Vec<Polyline> {
-    let mut results = Vec::new();
-    for (tag_name, is_closed) in &[("polygon", true), ("polyline", false)] {
-        let needle = format!("<{}", tag_name);
-        let mut search = svg;
-        while let Some(idx) = search.to_lowercase().find(&needle) {
-            let rest = &search[idx..];
-            if let Some(end) = rest.find("/>").or_else(|| rest.find('>')) {
-                let tag = &rest[..end];
-                if let Some(pts_str) = extract_attr(tag, "points") {
-                    if let Ok(pts) = parse_points_attr(&pts_str) {
-                        if !pts.is_empty() {
-                            results.push(Polyline::new(pts, *is_closed));
-                        }
-                    }
-                }
-                search = &search[idx + end + 1..];
-            } else {
-                break;
-            }
+}
+
+fn view_box(node: Node) -> Option<[f64; 4]> {
+    match numbers(node.attribute("viewBox")?).as_slice() {
+        &[x, y, w, h] if w > 0.0 && h > 0.0 => Some([x, y, w, h]),
+        _ => None,
+    }
+}
+
+/// Map `node`'s viewBox onto a `width` × `height` viewport, following
+/// `preserveAspectRatio`. Without a viewport size (or a viewBox) the
+/// viewBox origin just moves to 0,0.
+fn viewbox_transform(node: Node, width: f64, height: f64) -> Affine2 {
+    let Some([x, y, vw, vh]) = view_box(node) else {
+        return Affine2::IDENTITY;
+    };
+    let origin = Affine2::translate(-x, -y);
+    if width <= 0.0 || height <= 0.0 {
+        return origin;
+    }
+    let (sx, sy) = (width / vw, height / vh);
+    let aspect = node
+        .attribute("preserveAspectRatio")
+        .unwrap_or("xMidYMid meet");
+    let mut words = aspect.split_whitespace();
+    let align = words.next().unwrap_or("xMidYMid");
+    if align == "none" {
+        return Affine2::scale(sx, sy).after(&origin);
+    }
+    let s = if words.next() == Some("slice") {
+        sx.max(sy)
+    } else {
+        sx.min(sy)
+    };
+    let shift = |axis: &str, spare: f64| {
+        if align.contains(&format!("{}Min", axis)) {
+            0.0
+        } else if align.contains(&format!("{}Max", axis)) {
+            spare
+        } else {
+            spare / 2.0
         }
+    };
+    Affine2::translate(shift("x", width - vw * s), shift("y", height - vh * s))
+        .after(&Affine2::scale(s, s))
+        .after(&origin)
+}
+
+// ── Colors and stylesheets ──────────────────────────────────────────
+
+/// A CSS color as `#rrggbb`. Names outside the basic palette are kept
+/// lowercase; `none`, `transparent` and paint servers give `None`.
+pub fn parse_color(s: &str) -> Option<String> {
+    let s = s.trim().to_ascii_lowercase();
+    let hex = |r: u8, g: u8, b: u8| Some(format!("#{:02x}{:02x}{:02x}", r, g, b));
+    if let Some(h) = s.strip_prefix('#') {
+        let v = |i: usize, n: usize| u8::from_str_radix(h.get(i..i + n)?, 16).ok();
+        return match h.len() {
+            3 | 4 => hex(v(0, 1)? * 17, v(1, 1)? * 17, v(2, 1)? * 17),
+            6 | 8 => hex(v(0, 2)?, v(2, 2)?, v(4, 2)?),
+            _ => None,
+        };
+    }
+    if let Some(args) = s
+        .strip_prefix("rgba(")
+        .or_else(|| s.strip_prefix("rgb("))
+        .and_then(|a| a.strip_suffix(')'))
+    {
+        let channel = |part: &str| -> Option<u8> {
+            let part = part.trim();
+            let v = match part.strip_suffix('%') {
+                Some(p) => p.trim().parse::<f64>().ok()? * 2.55,
+                None => part.parse::<f64>().ok()?,
+            };
+            Some(v.round().clamp(0.0, 255.0) as u8)
+        };
+        let parts: Vec<&str> = args
+            .split(|c: char| c == ',' || c == '/' || c.is_whitespace())
+            .filter(|p| !p.is_empty())
+            .collect();
+        return hex(
+            channel(parts.first()?)?,
+            channel(parts.get(1)?)?,
+            channel(parts.get(2)?)?,
+        );
     }
-    results
+    let named = match s.as_str() {
+        "" | "none" | "transparent" => return None,
+        _ if s.starts_with("url(") => return None,
+        "black" => "#000000",
+        "white" => "#ffffff",
+        "red" => "#ff0000",
+        "lime" => "#00ff00",
+        "blue" => "#0000ff",
+        "yellow" => "#ffff00",
+        "cyan" | "aqua" => "#00ffff",
+        "magenta" | "fuchsia" => "#ff00ff",
+        "gray" | "grey" => "#808080",
+        "silver" => "#c0c0c0",
+        "maroon" => "#800000",
+        "olive" => "#808000",
+        "green" => "#008000",
+        "purple" => "#800080",
+        "teal" => "#008080",
+        "navy" => "#000080",
+        "orange" => "#ffa500",
+        _ => return Some(s),
+    };
+    Some(named.to_string())
 }
 
-fn parse_points_attr(s: &str) -> Result<Vec<Vec2>, String> {
-    let mut points = Vec::new();
-    let nums: Vec<f64> = s
-        .split(|c: char| c == ',' || c.is_whitespace())
-        .filter(|s| !s.is_empty())
-        .map(|s| s.parse::<f64>())
-        .collect::<Result<Vec<_>, _>>()
-        .map_err(|e| format!("points parse error: {e}"))?;
-    if !nums.len().is_multiple_of(2) {
-        return Err("Odd number of coordinates in points attribute".into());
+/// `name: value; ...` pairs of a style attribute or CSS block.
+fn declarations(s: &str) -> Vec<(String, String)> {
+    s.split(';')
+        .filter_map(|d| d.split_once(':'))
+        .map(|(k, v)| {
+            let v = v.trim().trim_end_matches("!important").trim();
+            (k.trim().to_ascii_lowercase(), v.to_string())
+        })
+        .collect()
+}
+
+/// A rule of a `<style>` sheet with one simple selector: `*`, a tag,
+/// `.class` or `#id`.
+#[derive(Debug)]
+struct Rule {
+    selector: String,
+    specificity: u8,
+    declarations: Vec<(String, String)>,
+}
+
+impl Rule {
+    fn matches(&self, node: Node) -> bool {
+        if let Some(class) = self.selector.strip_prefix('.') {
+            node.attribute("class")
+                .is_some_and(|c| c.split_whitespace().any(|c| c == class))
+        } else if let Some(id) = self.selector.strip_prefix('#') {
+            node.attribute("id") == Some(id)
+        } else {
+            self.selector == "*" || self.selector == node.tag_name().name()
+        }
     }
-    for pair in nums.chunks(2) {
-        points.push(Vec2::new(pair[0], pair[1]));
+}
+
+/// Rules of every `<style>` element, in document order. Compound
+/// selectors and at-rules are skipped.
+fn stylesheet_rules(doc: &Document) -> Vec<Rule> {
+    let mut rules = Vec::new();
+    for style in doc.descendants().filter(|n| is_svg(*n, "style")) {
+        let css: String = style.children().filter_map(|c| c.text()).collect();
+        let mut css = css.as_str();
+        // Drop comments.
+        let mut clean = String::new();
+        while let Some(start) = css.find("/*") {
+            clean.push_str(&css[..start]);
+            css = css[start..]
+                .find("*/")
+                .map_or("", |end| &css[start + end + 2..]);
+        }
+        clean.push_str(css);
+        for block in clean.split('}') {
+            let Some((selectors, body)) = block.split_once('{') else {
+                continue;
+            };
+            let body = declarations(body);
+            for selector in selectors.split(',').map(str::trim) {
+                let simple = !selector.is_empty()
+                    && !selector.starts_with('@')
+                    && selector
+                        .chars()
+                        .skip(1)
+                        .all(|c| c.is_alphanumeric() || c == '-' || c == '_');
+                if !simple {
+                    continue;
+                }
+                let specificity = match selector.as_bytes()[0] {
+                    b'#' => 3,
+                    b'.' => 2,
+                    b'*' => 0,
+                    _ => 1,
+                };
+                rules.push(Rule {
+                    selector: selector.to_string(),
+                    specificity,
+                    declarations: body.clone(),
+                });
+            }
+        }
     }
-    Ok(points)
+    rules
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    /// Parse with user units read as millimetres, so coordinates come out
+    /// as written.
+    fn parse_mm(svg: &str) -> Result<Vec<Polyline>, String> {
+        parse_svg_by_color(svg, &[], SvgUnits::Millimetres)
+    }
+
+    /// Wrap bare elements in an `<svg>` root.
+    fn shapes(body: &str) -> Vec<Polyline> {
+        parse_mm(&format!("<svg>{}</svg>", body)).unwrap_or_default()
+    }
+
+    fn close(a: Vec2, b: Vec2) -> bool {
+        Vec2::dist(a, b) < 1e-9
+    }
+
     #[test]
     fn test_simple_path() {
         let svg = r#"<svg><path d="M 0 0 L 10 0 L 10 10 L 0 10 Z"/></svg>"#;
-        let paths = parse_svg(svg).unwrap();
+        let paths = parse_mm(svg).unwrap();
         assert_eq!(paths.len(), 1);
         assert!(paths[0].closed);
         assert_eq!(paths[0].points.len(), 4);
@@ -430,7 +1066,7 @@ mod tests {
     #[test]
     fn test_rect() {
         let svg = r#"<svg><rect x="5" y="5" width="20" height="10"/></svg>"#;
-        let paths = parse_svg(svg).unwrap();
+        let paths = parse_mm(svg).unwrap();
         assert_eq!(paths.len(), 1);
         assert!(paths[0].closed);
         assert_eq!(paths[0].points.len(), 4);
@@ -439,34 +1075,33 @@ mod tests {
     #[test]
     fn test_circle() {
         let svg = r#"<svg><circle cx="50" cy="50" r="25"/></svg>"#;
-        let paths = parse_svg(svg).unwrap();
+        let paths = parse_mm(svg).unwrap();
         assert_eq!(paths.len(), 1);
         assert!(paths[0].closed);
-        assert_eq!(paths[0].points.len(), 64);
+        // r = 25 needs more than the minimum to stay within 0.005 mm.
+        assert_eq!(paths[0].points.len(), 158);
     }
 
     #[test]
     fn test_relative_path() {
         let svg = r#"<svg><path d="m 10 10 l 5 0 l 0 5 z"/></svg>"#;
-        let paths = parse_svg(svg).unwrap();
+        let paths = parse_mm(svg).unwrap();
         assert_eq!(paths.len(), 1);
         assert_eq!(paths[0].points[0], Vec2::new(10.0, 10.0));
         assert_eq!(paths[0].points[1], Vec2::new(15.0, 10.0));
     }
 
     #[test]
-    fn test_extract_path_d_attrs() {
-        let svg = r#"<path d="M 0 0 L 1 1"/><path d="M 2 2 L 3 3"/>"#;
-        let attrs = extract_path_d_attrs(svg);
-        assert_eq!(attrs.len(), 2);
-        assert_eq!(attrs[0], "M 0 0 L 1 1");
-        assert_eq!(attrs[1], "M 2 2 L 3 3");
+    fn test_paths_in_document_order() {
+        let paths = shapes(r#"<path d="M 0 0 L 1 1"/><path d="M 2 2 L 3 3"/>"#);
+        assert_eq!(paths.len(), 2);
+        assert_eq!(paths[0].points[1], Vec2::new(1.0, 1.0));
+        assert_eq!(paths[1].points[0], Vec2::new(2.0, 2.0));
     }
 
     #[test]
-    fn test_extract_rects() {
-        let svg = r#"<rect x="1" y="2" width="10" height="5"/>"#;
-        let rects = extract_rects(svg);
+    fn test_rect_corners() {
+        let rects = shapes(r#"<rect x="1" y="2" width="10" height="5"/>"#);
         assert_eq!(rects.len(), 1);
         assert!(rects[0].closed);
         assert_eq!(rects[0].points[0], Vec2::new(1.0, 2.0));
@@ -474,27 +1109,24 @@ mod tests {
     }
 
     #[test]
-    fn test_extract_circles() {
-        let svg = r#"<circle cx="5" cy="5" r="3"/>"#;
-        let circles = extract_circles(svg);
+    fn test_small_circle() {
+        let circles = shapes(r#"<circle cx="5" cy="5" r="3"/>"#);
         assert_eq!(circles.len(), 1);
         assert!(circles[0].closed);
         assert_eq!(circles[0].points.len(), 64);
     }
 
     #[test]
-    fn test_extract_poly_elements_polygon() {
-        let svg = r#"<polygon points="0,0 10,0 10,10"/>"#;
-        let polys = extract_poly_elements(svg);
+    fn test_polygon_element() {
+        let polys = shapes(r#"<polygon points="0,0 10,0 10,10"/>"#);
         assert_eq!(polys.len(), 1);
         assert!(polys[0].closed);
         assert_eq!(polys[0].points.len(), 3);
     }
 
     #[test]
-    fn test_extract_poly_elements_polyline() {
-        let svg = r#"<polyline points="1,1 2,2 3,3"/>"#;
-        let polys = extract_poly_elements(svg);
+    fn test_polyline_element() {
+        let polys = shapes(r#"<polyline points="1,1 2,2 3,3"/>"#);
         assert_eq!(polys.len(), 1);
         assert!(!polys[0].closed);
         assert_eq!(polys[0].points.len(), 3);
@@ -547,7 +1179,7 @@ mod tests {
     #[test]
     fn test_parse_svg_rect() {
         let svg = r#"<svg xmlns="http://www.w3.org/2000/svg"><rect x="10" y="20" width="30" height="40"/></svg>"#;
-        let polylines = parse_svg(svg).unwrap();
+        let polylines = parse_mm(svg).unwrap();
         assert!(!polylines.is_empty());
         assert!(polylines[0].closed);
         assert_eq!(polylines[0].points.len(), 4);
@@ -557,16 +1189,16 @@ mod tests {
     fn test_parse_svg_circle() {
         let svg =
             r#"<svg xmlns="http://www.w3.org/2000/svg"><circle cx="50" cy="50" r="25"/></svg>"#;
-        let polylines = parse_svg(svg).unwrap();
+        let polylines = parse_mm(svg).unwrap();
         assert!(!polylines.is_empty());
         assert!(polylines[0].closed);
-        assert_eq!(polylines[0].points.len(), 64);
+        assert_eq!(polylines[0].points.len(), 158);
     }
 
     #[test]
     fn test_parse_svg_polygon() {
         let svg = r#"<svg xmlns="http://www.w3.org/2000/svg"><polygon points="0,0 100,0 100,100 0,100"/></svg>"#;
-        let polylines = parse_svg(svg).unwrap();
+        let polylines = parse_mm(svg).unwrap();
         assert!(!polylines.is_empty());
         assert!(polylines[0].closed);
         assert_eq!(polylines[0].points.len(), 4);
@@ -576,7 +1208,7 @@ mod tests {
     fn test_parse_svg_polyline() {
         let svg =
             r#"<svg xmlns="http://www.w3.org/2000/svg"><polyline points="0,0 50,50 100,0"/></svg>"#;
-        let polylines = parse_svg(svg).unwrap();
+        let polylines = parse_mm(svg).unwrap();
         assert!(!polylines.is_empty());
         assert!(!polylines[0].closed);
         assert_eq!(polylines[0].points.len(), 3);
@@ -589,14 +1221,14 @@ mod tests {
             <circle cx="50" cy="50" r="5"/>
             <polygon points="20,20 30,20 30,30"/>
         </svg>"#;
-        let polylines = parse_svg(svg).unwrap();
+        let polylines = parse_mm(svg).unwrap();
         assert_eq!(polylines.len(), 3);
     }
 
     #[test]
     fn test_parse_svg_empty_returns_error() {
         let svg = r#"<svg xmlns="http://www.w3.org/2000/svg"></svg>"#;
-        let result = parse_svg(svg);
+        let result = parse_mm(svg);
         assert!(result.is_err());
     }
 
@@ -606,7 +1238,7 @@ mod tests {
     fn test_h_and_h_path_commands() {
         // Absolute H and relative h
         let svg = r#"<svg><path d="M 0 0 H 10 h 5"/></svg>"#;
-        let paths = parse_svg(svg).unwrap();
+        let paths = parse_mm(svg).unwrap();
         assert_eq!(paths.len(), 1);
         // M 0,0 -> H 10 -> cursor at (10,0) -> h 5 -> cursor at (15,0)
         assert_eq!(paths[0].points.len(), 3);
@@ -619,7 +1251,7 @@ mod tests {
     fn test_v_and_v_path_commands() {
         // Absolute V and relative v
         let svg = r#"<svg><path d="M 0 0 V 10 v 5"/></svg>"#;
-        let paths = parse_svg(svg).unwrap();
+        let paths = parse_mm(svg).unwrap();
         assert_eq!(paths.len(), 1);
         // M 0,0 -> V 10 -> cursor at (0,10) -> v 5 -> cursor at (0,15)
         assert_eq!(paths[0].points.len(), 3);
@@ -631,7 +1263,7 @@ mod tests {
     #[test]
     fn test_single_quote_attributes() {
         let svg = r#"<svg><rect x='5' y='10' width='20' height='30'/></svg>"#;
-        let paths = parse_svg(svg).unwrap();
+        let paths = parse_mm(svg).unwrap();
         assert_eq!(paths.len(), 1);
         assert!(paths[0].closed);
         assert_eq!(paths[0].points.len(), 4);
@@ -654,7 +1286,7 @@ mod tests {
     fn test_cubic_bezier_path_commands() {
         // Absolute C
         let svg = r#"<svg><path d="M 0 0 C 10 20 30 20 40 0"/></svg>"#;
-        let paths = parse_svg(svg).unwrap();
+        let paths = parse_mm(svg).unwrap();
         assert_eq!(paths.len(), 1);
         // M produces 1 point, C subdivides into 16 points
         assert_eq!(paths[0].points.len(), 17);
@@ -668,7 +1300,7 @@ mod tests {
     fn test_relative_cubic_bezier_path_commands() {
         // Relative c from starting point (10, 10)
         let svg = r#"<svg><path d="M 10 10 c 10 20 30 20 40 0"/></svg>"#;
-        let paths = parse_svg(svg).unwrap();
+        let paths = parse_mm(svg).unwrap();
         assert_eq!(paths.len(), 1);
         assert_eq!(paths[0].points.len(), 17);
         // Last point should be approximately (10+40, 10+0) = (50, 10)
@@ -681,7 +1313,7 @@ mod tests {
     fn test_quadratic_bezier_path_commands() {
         // Absolute Q
         let svg = r#"<svg><path d="M 0 0 Q 20 40 40 0"/></svg>"#;
-        let paths = parse_svg(svg).unwrap();
+        let paths = parse_mm(svg).unwrap();
         assert_eq!(paths.len(), 1);
         // M produces 1 point, Q subdivides into 16 points
         assert_eq!(paths[0].points.len(), 17);
@@ -694,7 +1326,7 @@ mod tests {
     fn test_relative_quadratic_bezier_path_commands() {
         // Relative q from starting point (5, 5)
         let svg = r#"<svg><path d="M 5 5 q 20 40 40 0"/></svg>"#;
-        let paths = parse_svg(svg).unwrap();
+        let paths = parse_mm(svg).unwrap();
         assert_eq!(paths.len(), 1);
         assert_eq!(paths[0].points.len(), 17);
         // Last point should be approximately (5+40, 5+0) = (45, 5)
@@ -703,6 +1335,17 @@ mod tests {
         assert!((last.y - 5.0).abs() < 1e-10);
     }
 
+    #[test]
+    fn test_smooth_curves_reflect_control_points() {
+        // S mirrors the previous C's second control point, T the Q's.
+        let s = &parse_path_d("M0 0 C0 10 10 10 10 0 S20 -10 20 0", 0.01).unwrap()[0];
+        let c = &parse_path_d("M0 0 C0 10 10 10 10 0 C10 -10 20 -10 20 0", 0.01).unwrap()[0];
+        assert_eq!(s.points.len(), 33);
+        assert!(s.points.iter().zip(&c.points).all(|(a, b)| close(*a, *b)));
+        let t = &parse_path_d("M0 0 Q5 10 10 0 T20 0", 0.01).unwrap()[0];
+        assert!(close(t.points[24], Vec2::new(15.0, -5.0)));
+    }
+
     // ── Additional coverage gap tests ──────────────────────────────
 
     #[test]
@@ -725,19 +1368,18 @@ mod tests {
 
     #[test]
     fn test_unknown_path_command() {
-        // Unknown command 'X' should be skipped
+        // Unknown command 'X' is skipped along with its numbers
         let svg = r#"<svg><path d="M 0 0 L 10 0 X 5 5 L 10 10 Z"/></svg>"#;
-        let paths = parse_svg(svg).unwrap();
+        let paths = parse_mm(svg).unwrap();
         assert_eq!(paths.len(), 1);
-        // M + L + L produces 3 points (X is skipped, but 5 and 5 are consumed as L-like)
-        assert!(!paths[0].points.is_empty());
+        assert_eq!(paths[0].points.len(), 3);
     }
 
     #[test]
     fn test_implicit_lineto_after_m() {
         // After M, subsequent coordinate pairs are implicit L commands
         let svg = r#"<svg><path d="M 0 0 10 10 20 0"/></svg>"#;
-        let paths = parse_svg(svg).unwrap();
+        let paths = parse_mm(svg).unwrap();
         assert_eq!(paths.len(), 1);
         assert_eq!(paths[0].points.len(), 3);
         assert_eq!(paths[0].points[1], Vec2::new(10.0, 10.0));
@@ -748,7 +1390,7 @@ mod tests {
     fn test_implicit_lineto_after_relative_m() {
         // After m, subsequent coordinate pairs are implicit relative l commands
         let svg = r#"<svg><path d="m 0 0 10 10 20 0"/></svg>"#;
-        let paths = parse_svg(svg).unwrap();
+        let paths = parse_mm(svg).unwrap();
         assert_eq!(paths.len(), 1);
         assert_eq!(paths[0].points.len(), 3);
         assert_eq!(paths[0].points[1], Vec2::new(10.0, 10.0));
@@ -778,51 +1420,241 @@ mod tests {
         assert_eq!(tokens, vec!["10", "L", "20"]);
     }
 
+    #[test]
+    fn test_tokenize_d_exponents_dots_and_arc_flags() {
+        assert_eq!(tokenize_d("1e-3.5.5"), vec!["1e-3", ".5", ".5"]);
+        assert_eq!(
+            tokenize_d("a1 1 0 1010 10"),
+            vec!["a", "1", "1", "0", "1", "0", "10", "10"]
+        );
+    }
+
     #[test]
     fn test_multiple_l_commands() {
         let svg = r#"<svg><path d="M 0 0 L 10 0 10 10 0 10"/></svg>"#;
-        let paths = parse_svg(svg).unwrap();
+        let paths = parse_mm(svg).unwrap();
         assert_eq!(paths[0].points.len(), 4);
     }
 
     #[test]
     fn test_multiple_relative_l_commands() {
         let svg = r#"<svg><path d="M 0 0 l 10 0 0 10 -10 0"/></svg>"#;
-        let paths = parse_svg(svg).unwrap();
+        let paths = parse_mm(svg).unwrap();
         assert_eq!(paths[0].points.len(), 4);
         assert_eq!(paths[0].points[3], Vec2::new(0.0, 10.0));
     }
 
     #[test]
     fn test_empty_path_d() {
-        // Path with empty d attribute should produce empty polyline
-        let result = parse_path_d("");
+        // Path with empty d attribute produces no subpaths
+        let result = parse_path_d("", 0.01);
         assert!(result.is_ok());
-        assert!(result.unwrap().points.is_empty());
+        assert!(result.unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_subpaths_become_separate_polylines() {
+        // A letter "O": outer ring, inner ring, then a lone drill point.
+        let paths = parse_path_d("M0 0H10V10H0Z m2 2h6v6h-6z M5 20", 0.01).unwrap();
+        assert_eq!(paths.len(), 3);
+        assert!(paths[0].closed && paths[1].closed && !paths[2].closed);
+        assert_eq!(paths[1].points[0], Vec2::new(2.0, 2.0));
+        assert_eq!(paths[2].points, vec![Vec2::new(5.0, 20.0)]);
     }
 
     #[test]
     fn test_rect_zero_dimensions() {
         // Rect with zero width or height should be skipped
-        let svg = r#"<rect x="0" y="0" width="0" height="10"/>"#;
-        let rects = extract_rects(svg);
-        assert!(rects.is_empty());
+        assert!(shapes(r#"<rect x="0" y="0" width="0" height="10"/>"#).is_empty());
     }
 
     #[test]
     fn test_circle_zero_radius() {
         // Circle with zero radius should be skipped
-        let svg = r#"<circle cx="5" cy="5" r="0"/>"#;
-        let circles = extract_circles(svg);
-        assert!(circles.is_empty());
+        assert!(shapes(r#"<circle cx="5" cy="5" r="0"/>"#).is_empty());
     }
 
     #[test]
     fn test_rect_missing_x_y() {
         // Rect without x/y attributes should default to 0,0
-        let svg = r#"<rect width="10" height="5"/>"#;
-        let rects = extract_rects(svg);
+        let rects = shapes(r#"<rect width="10" height="5"/>"#);
         assert_eq!(rects.len(), 1);
         assert_eq!(rects[0].points[0], Vec2::new(0.0, 0.0));
     }
+
+    // ── Standards coverage ─────────────────────────────────────────
+
+    #[test]
+    fn test_arc_command_follows_the_circle() {
+        // Half circle of radius 10 from (0,0) to (20,0), then back with
+        // radii too small to span the ends (scaled up to fit).
+        let paths = parse_path_d("M0 0 A10 10 0 0 1 20 0 a1 1 0 0 1 -20 0", 0.01).unwrap();
+        let pts = &paths[0].points;
+        let center = Vec2::new(10.0, 0.0);
+        assert!(pts
+            .iter()
+            .all(|&p| (Vec2::dist(p, center) - 10.0).abs() < 1e-9));
+        assert!(pts.len() > 40);
+        // Sweep flag 1 is clockwise on screen: the first half dips to y < 0.
+        let half = pts.len() / 2;
+        assert!(pts[1..half].iter().all(|p| p.y < 0.0));
+        assert!(pts[half + 1..pts.len() - 1].iter().all(|p| p.y > 0.0));
+        assert!(close(pts[half], Vec2::new(20.0, 0.0)));
+    }
+
+    #[test]
+    fn test_ellipse_line_and_rounded_rect() {
+        let paths = shapes(
+            r#"<ellipse cx="10" cy="5" rx="4" ry="2"/><line x1="0" y1="0" x2="3" y2="4"/>
+               <rect width="20" height="10" rx="2"/>"#,
+        );
+        assert_eq!(paths.len(), 3);
+        assert_eq!(paths[0].points.len(), 64);
+        assert_eq!(paths[0].points[0], Vec2::new(14.0, 5.0));
+        assert!(close(paths[0].points[16], Vec2::new(10.0, 7.0)));
+        assert!(!paths[1].closed);
+        assert_eq!(
+            paths[1].points,
+            vec![Vec2::new(0.0, 0.0), Vec2::new(3.0, 4.0)]
+        );
+        let rounded = &paths[2];
+        assert!(rounded.closed && rounded.points.len() > 8);
+        assert!(close(rounded.points[0], Vec2::new(18.0, 0.0)));
+        assert!(rounded
+            .points
+            .iter()
+            .all(|p| p.x >= 0.0 && p.x <= 20.0 + 1e-9));
+        assert!(rounded.points.iter().all(|p| (p.x - 20.0).hypot(p.y) > 0.5));
+    }
+
+    #[test]
+    fn test_nested_transforms_compose() {
+        let paths = shapes(
+            r##"<g transform="translate(100 0)"><g transform="scale(2)">
+                 <path transform="rotate(90, 5, 5)" d="M5 0 L10 5"/>
+               </g></g>
+               <g style="display:none"><rect width="5" height="5"/></g>
+               <defs><rect id="r" width="1" height="1"/></defs>
+               <use href="#r" x="3" y="4" transform="matrix(1 0 0 1 0 0)"/>"##,
+        );
+        assert_eq!(paths.len(), 2);
+        // rotate about (5,5) sends (5,0) to (10,5) and (10,5) to (5,10).
+        assert!(close(paths[0].points[0], Vec2::new(120.0, 10.0)));
+        assert!(close(paths[0].points[1], Vec2::new(110.0, 20.0)));
+        assert_eq!(paths[1].points[0], Vec2::new(3.0, 4.0));
+        assert!(close(
+            parse_transform("skewX(45)").apply(Vec2::new(0.0, 1.0)),
+            Vec2::new(1.0, 1.0)
+        ));
+    }
+
+    #[test]
+    fn test_units_and_viewbox_map_to_millimetres() {
+        let first = |svg: &str| parse_mm(svg).unwrap()[0].points.clone();
+        // 100 user units span 50 mm.
+        let pts = first(
+            r#"<svg width="50mm" height="50mm" viewBox="-10 -10 100 100"><path d="M-10 -10 L90 90"/></svg>"#,
+        );
+        assert!(close(pts[0], Vec2::new(0.0, 0.0)) && close(pts[1], Vec2::new(50.0, 50.0)));
+        // Default xMidYMid meet centres a square viewBox in a wide page.
+        let pts = first(
+            r#"<svg width="2in" height="1in" viewBox="0 0 10 10"><path d="M0 0 L10 10"/></svg>"#,
+        );
+        assert!(close(pts[0], Vec2::new(12.7, 0.0)) && close(pts[1], Vec2::new(38.1, 25.4)));
+        let pts = first(
+            r#"<svg width="20mm" height="10mm" viewBox="0 0 10 10" preserveAspectRatio="none"><path d="M10 10"/></svg>"#,
+        );
+        assert!(close(pts[0], Vec2::new(20.0, 10.0)));
+        // Physical size without a viewBox: user units are CSS pixels.
+        let pts = first(r#"<svg width="96px" height="96px"><path d="M96 48"/></svg>"#);
+        assert!(close(pts[0], Vec2::new(25.4, 12.7)));
+        // Element lengths with units, and a nested viewport.
+        let pts = first(
+            r#"<svg width="100" height="100"><svg x="10" y="10" width="20" height="20" viewBox="0 0 2 2"><circle cx="1" cy="1" r="0.5in"/></svg></svg>"#,
+        );
+        assert!(close(pts[0], Vec2::new(20.0 + 480.0, 20.0)));
+        // Unitless size or none at all: CSS pixels by default.
+        let svg = r#"<svg viewBox="5 5 10 10"><path d="M5 5 L15 5"/></svg>"#;
+        let pts = parse_svg(svg).unwrap()[0].points.clone();
+        assert!(close(pts[0], Vec2::new(0.0, 0.0)));
+        assert!(close(pts[1], Vec2::new(10.0 * 25.4 / 96.0, 0.0)));
+        assert!(close(first(svg)[1], Vec2::new(10.0, 0.0)));
+    }
+
+    #[test]
+    fn test_unitless_roots_in_css_pixels() {
+        // Illustrator export: a viewBox in pixels and no width or height.
+        let illustrator = r#"<?xml version="1.0" encoding="utf-8"?>
+<svg version="1.1" id="Layer_1" xmlns="http://www.w3.org/2000/svg" x="0px" y="0px"
+  viewBox="0 0 192 96" style="enable-background:new 0 0 192 96;" xml:space="preserve">
+<rect x="96" y="0" width="96" height="48"/>
+</svg>"#;
+        let corner =
+            |svg: &str, units| parse_svg_shapes_in(svg, units).unwrap()[0].polyline.points[2];
+        assert!(close(
+            corner(illustrator, SvgUnits::Pixels),
+            Vec2::new(50.8, 12.7)
+        ));
+        assert!(close(
+            corner(illustrator, SvgUnits::Millimetres),
+            Vec2::new(192.0, 48.0)
+        ));
+
+        // Unitless width and height: 2 px per user unit.
+        let sized = r#"<svg width="384" height="192" viewBox="0 0 192 96"><rect width="96" height="48"/></svg>"#;
+        assert!(close(
+            corner(sized, SvgUnits::Pixels),
+            Vec2::new(50.8, 25.4)
+        ));
+        // A physical size ignores the choice.
+        let physical = sized.replace("384", "100mm").replace("192\"", "50mm\"");
+        for units in [SvgUnits::Pixels, SvgUnits::Millimetres] {
+            assert!(close(corner(&physical, units), Vec2::new(50.0, 25.0)));
+        }
+        // No size or viewBox at all.
+        let bare = r#"<svg><rect width="96" height="48"/></svg>"#;
+        assert!(close(corner(bare, SvgUnits::Pixels), Vec2::new(25.4, 12.7)));
+
+        assert_eq!(SvgUnits::parse("px"), Ok(SvgUnits::Pixels));
+        assert!(SvgUnits::parse("pt").unwrap_err().contains("pt"));
+    }
+
+    #[test]
+    fn test_colors_are_normalized_and_inherited() {
+        let svg = r##"<svg xmlns="http://www.w3.org/2000/svg">
+            <style>/* cut */ .cut { stroke: #F00; fill: none }</style>
+            <g fill="blue" color="rgb(0, 128, 0)">
+              <rect class="cut" width="1" height="1"/>
+              <rect width="1" height="1" style="stroke: currentColor"/>
+              <rect width="1" height="1" fill="url(#grad)" stroke="PapayaWhip"/>
+            </g>
+            <circle r="1" stroke="rgb(100%, 0%, 0%)"/>
+        </svg>"##;
+        let shapes = parse_svg_shapes(svg).unwrap();
+        let paint: Vec<(Option<&str>, Option<&str>)> = shapes
+            .iter()
+            .map(|s| (s.stroke.as_deref(), s.fill.as_deref()))
+            .collect();
+        assert_eq!(
+            paint,
+            vec![
+                (Some("#ff0000"), None),
+                (Some("#008000"), Some("#0000ff")),
+                (Some("papayawhip"), None),
+                (Some("#ff0000"), Some("#000000")),
+            ]
+        );
+        let red = parse_svg_by_color(svg, &["red".to_string()], SvgUnits::default()).unwrap();
+        assert_eq!(red.len(), 2);
+        let err = parse_svg_by_color(svg, &["#123".to_string()], SvgUnits::default()).unwrap_err();
+        assert!(err.contains("#123"), "{}", err);
+    }
+
+    #[test]
+    fn test_malformed_xml_is_an_error() {
+        assert!(parse_mm("<svg><path d='M0 0'></svg>")
+            .unwrap_err()
+            .starts_with("SVG:"));
+        assert!(parse_mm("<html/>").is_err());
+    }
 }
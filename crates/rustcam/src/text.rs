@@ -0,0 +1,627 @@
+//! Text engraving: single-stroke and SVG fonts laid out as polylines.
+//!
+//! Swiss-cheese layer: **Geometry Input (2-D)**
+//! Extension point: add a built-in face to `Font::builtin`, or load any
+//! Hershey `.jhf` file or SVG font at run time.
+//!
+//! Engraving wants the cutter to run once down the middle of each stroke
+//! rather than around an outline, so the built-in faces are the public
+//! domain Hershey Roman Simplex and a fixed-pitch cut of it for serial
+//! numbers. Hershey strokes stay open even where they meet themselves, so
+//! contour and laser strategies follow them on the line. SVG fonts give
+//! closed outlines for pocketed or V-carved lettering (or single strokes,
+//! if the font was drawn that way).
+//!
+//! Text comes out in millimetres with Y up: `size` is the cap height,
+//! the first baseline passes through (`x`, `y`) and later lines go down.
+//! With `arc_radius` the baseline bends round a circle centred there.
+
+use std::collections::HashMap;
+
+use roxmltree::Document;
+use serde::{Deserialize, Serialize};
+
+use crate::geometry::{Polyline, Vec2};
+use crate::svg;
+
+/// Names accepted by [`Font::builtin`].
+pub const BUILTIN_FONTS: &[&str] = &["simplex", "simplex_mono"];
+
+const SIMPLEX_JHF: &str = include_str!("text/simplex.jhf");
+
+/// Most parts one serial range may number.
+const MAX_SERIAL_COUNT: u32 = 10_000;
+/// Widest zero padding; a `u64` has at most 20 digits.
+const MAX_SERIAL_DIGITS: usize = 20;
+
+/// A `.jhf` record: left edge, right edge and the pen-down runs, in
+/// Hershey coordinates.
+type JhfRecord = (i32, i32, Vec<Vec<(i32, i32)>>);
+
+/// A character's strokes in font units, origin at its left edge on the
+/// baseline, Y up.
+#[derive(Debug, Clone)]
+struct Glyph {
+    advance: f64,
+    strokes: Vec<Polyline>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Font {
+    glyphs: HashMap<char, Glyph>,
+    /// Drawn for characters the font lacks (SVG `<missing-glyph>`).
+    missing: Option<Glyph>,
+    /// Font units from the baseline to the top of a capital.
+    cap_height: f64,
+}
+
+impl Font {
+    /// One of [`BUILTIN_FONTS`].
+    pub fn builtin(name: &str) -> Result<Self, String> {
+        match name {
+            "simplex" => Self::from_jhf(SIMPLEX_JHF),
+            "simplex_mono" => Ok(Self::from_jhf(SIMPLEX_JHF)?.monospaced()),
+            other => Err(format!(
+                "Unknown font '{}' (expected one of {})",
+                other,
+                BUILTIN_FONTS.join(", ")
+            )),
+        }
+    }
+
+    /// Parse a Hershey font in the `.jhf` format: one glyph per record,
+    /// for the characters from space upwards. A record is a five-column
+    /// glyph number, a three-column vertex count, then that many
+    /// coordinate pairs as letters offset from `R` (the first pair is the
+    /// left and right edge, ` R` lifts the pen). Long records may wrap.
+    pub fn from_jhf(text: &str) -> Result<Self, String> {
+        let mut lines = text.lines().map(|l| l.trim_end_matches('\r'));
+        let mut records: Vec<JhfRecord> = Vec::new();
+        while let Some(line) = lines.next() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let count: usize = line
+                .get(5..8)
+                .and_then(|c| c.trim().parse().ok())
+                .ok_or_else(|| format!("JHF: bad glyph header '{}'", line))?;
+            let mut body = line.get(8..).unwrap_or("").to_string();
+            while body.len() < 2 * count {
+                match lines.next() {
+                    Some(more) => body.push_str(more),
+                    None => break,
+                }
+            }
+            let bytes = body.as_bytes();
+            if count == 0 || bytes.len() < 2 * count {
+                return Err(format!("JHF: glyph {} is truncated", records.len() + 1));
+            }
+            let v = |b: u8| b as i32 - b'R' as i32;
+            let mut strokes = vec![Vec::new()];
+            for pair in bytes[2..2 * count].chunks(2) {
+                if pair == b" R" {
+                    strokes.push(Vec::new());
+                } else {
+                    strokes.last_mut().unwrap().push((v(pair[0]), v(pair[1])));
+                }
+            }
+            records.push((v(bytes[0]), v(bytes[1]), strokes));
+        }
+
+        // Hershey Y points down; find the baseline and cap line from "H".
+        let (top, base) = records
+            .get(usize::from(b'H' - b' '))
+            .and_then(|(_, _, strokes)| {
+                let ys = strokes.iter().flatten().map(|&(_, y)| y);
+                Some((ys.clone().min()?, ys.max()?))
+            })
+            .unwrap_or((-12, 9));
+        let glyphs = records
+            .into_iter()
+            .enumerate()
+            .filter_map(|(i, (left, right, strokes))| {
+                let c = char::from_u32(32 + i as u32)?;
+                let strokes = strokes
+                    .into_iter()
+                    .filter(|s| s.len() > 1)
+                    .map(|s| {
+                        let points = s
+                            .into_iter()
+                            .map(|(x, y)| Vec2::new((x - left) as f64, (base - y) as f64))
+                            .collect();
+                        Polyline::new(points, false)
+                    })
+                    .collect();
+                let advance = (right - left) as f64;
+                Some((c, Glyph { advance, strokes }))
+            })
+            .collect();
+        Ok(Self {
+            glyphs,
+            missing: None,
+            cap_height: (base - top).max(1) as f64,
+        })
+    }
+
+    /// Parse the first `<font>` of an SVG document. Glyph paths are in
+    /// font units with Y up, as the SVG font format defines them.
+    pub fn from_svg_font(text: &str) -> Result<Self, String> {
+        let doc = Document::parse(text).map_err(|e| format!("SVG font: {}", e))?;
+        let font = doc
+            .descendants()
+            .find(|n| n.tag_name().name() == "font")
+            .ok_or("SVG font: no <font> element")?;
+        let number = |node: roxmltree::Node, name: &str| {
+            node.attribute(name)
+                .and_then(|v| v.trim().parse::<f64>().ok())
+        };
+        let default_advance = number(font, "horiz-adv-x").unwrap_or(0.0);
+        let face = font.children().find(|n| n.tag_name().name() == "font-face");
+        let em = face
+            .and_then(|f| number(f, "units-per-em"))
+            .filter(|&em| em > 0.0)
+            .unwrap_or(1000.0);
+
+        let glyph = |node: roxmltree::Node| -> Result<Glyph, String> {
+            let strokes = svg::parse_path_d(node.attribute("d").unwrap_or(""), em * 1e-4)?;
+            Ok(Glyph {
+                advance: number(node, "horiz-adv-x").unwrap_or(default_advance),
+                strokes,
+            })
+        };
+        let mut glyphs = HashMap::new();
+        let mut missing = None;
+        for node in font.children().filter(|n| n.is_element()) {
+            match node.tag_name().name() {
+                "glyph" => {
+                    let mut chars = node.attribute("unicode").unwrap_or("").chars();
+                    // Ligatures (several characters) are not used.
+                    if let (Some(c), None) = (chars.next(), chars.next()) {
+                        glyphs.insert(c, glyph(node)?);
+                    }
+                }
+                "missing-glyph" => missing = Some(glyph(node)?),
+                _ => {}
+            }
+        }
+        if glyphs.is_empty() {
+            return Err("SVG font: no glyphs".into());
+        }
+        let h_top = glyphs.get(&'H').and_then(|g| {
+            g.strokes
+                .iter()
+                .flat_map(|s| s.points.iter().map(|p| p.y))
+                .reduce(f64::max)
+        });
+        let cap_height = face
+            .and_then(|f| number(f, "cap-height"))
+            .or(h_top)
+            .filter(|&h| h > 0.0)
+            .unwrap_or(0.7 * em);
+        Ok(Self {
+            glyphs,
+            missing,
+            cap_height,
+        })
+    }
+
+    /// The same glyphs on a fixed pitch (the widest advance), each
+    /// centred in its cell, so columns of digits line up.
+    pub fn monospaced(mut self) -> Self {
+        let pitch = self.glyphs.values().map(|g| g.advance).fold(0.0, f64::max);
+        for glyph in self.glyphs.values_mut() {
+            let shift = (pitch - glyph.advance) / 2.0;
+            for stroke in &mut glyph.strokes {
+                for p in &mut stroke.points {
+                    p.x += shift;
+                }
+            }
+            glyph.advance = pitch;
+        }
+        self
+    }
+
+    fn glyph(&self, c: char) -> Result<&Glyph, String> {
+        self.glyphs
+            .get(&c)
+            .or(self.missing.as_ref())
+            .ok_or_else(|| format!("Font has no glyph for '{}'", c))
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Align {
+    #[default]
+    Left,
+    Center,
+    Right,
+}
+
+/// A run of part numbers for `{serial}` in the text.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SerialRange {
+    #[serde(default = "default_serial_start")]
+    pub start: u64,
+    #[serde(default = "default_serial_count")]
+    pub count: u32,
+    #[serde(default = "default_serial_step")]
+    pub step: u64,
+    /// Zero-pad to this many digits.
+    #[serde(default)]
+    pub digits: usize,
+}
+
+impl SerialRange {
+    fn check(&self) -> Result<(), String> {
+        if self.count > MAX_SERIAL_COUNT {
+            return Err(format!(
+                "Serial count {} is over the limit of {}",
+                self.count, MAX_SERIAL_COUNT
+            ));
+        }
+        if self.digits > MAX_SERIAL_DIGITS {
+            return Err(format!(
+                "Serial digits {} is over the limit of {}",
+                self.digits, MAX_SERIAL_DIGITS
+            ));
+        }
+        Ok(())
+    }
+
+    /// Part `i`'s number, zero-padded.
+    fn label(&self, i: u64) -> Result<String, String> {
+        let n = i
+            .checked_mul(self.step)
+            .and_then(|offset| self.start.checked_add(offset))
+            .ok_or("Serial range runs past the largest number")?;
+        Ok(format!("{:0w$}", n, w = self.digits))
+    }
+}
+
+fn default_serial_start() -> u64 {
+    1
+}
+fn default_serial_count() -> u32 {
+    1
+}
+fn default_serial_step() -> u64 {
+    1
+}
+
+/// What to write and where.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TextOptions {
+    /// Lines separated by `\n`; `{serial}` takes the part number.
+    pub text: String,
+    /// A built-in font name, unless `svg_font` is given.
+    #[serde(default = "default_font")]
+    pub font: String,
+    /// An SVG document holding a `<font>`, used instead of `font`.
+    #[serde(default)]
+    pub svg_font: Option<String>,
+    /// Cap height (mm).
+    #[serde(default = "default_size")]
+    pub size: f64,
+    /// Extra gap between letters (mm).
+    #[serde(default)]
+    pub spacing: f64,
+    /// Baseline to baseline, in multiples of `size`.
+    #[serde(default = "default_line_spacing")]
+    pub line_spacing: f64,
+    #[serde(default)]
+    pub align: Align,
+    #[serde(default)]
+    pub x: f64,
+    #[serde(default)]
+    pub y: f64,
+    /// Lean of the letters (degrees, positive to the right).
+    #[serde(default)]
+    pub slant: f64,
+    /// Bend the baseline round a circle of this radius centred on
+    /// (`x`, `y`): positive reads clockwise over the top, negative reads
+    /// anticlockwise along the bottom with the letters upright.
+    #[serde(default)]
+    pub arc_radius: Option<f64>,
+    /// Where on the circle the alignment point sits (degrees from +X).
+    /// Defaults to the top (90) for a positive radius, else the bottom.
+    #[serde(default)]
+    pub arc_angle: Option<f64>,
+    #[serde(default)]
+    pub serial: Option<SerialRange>,
+}
+
+fn default_font() -> String {
+    "simplex".into()
+}
+fn default_size() -> f64 {
+    5.0
+}
+fn default_line_spacing() -> f64 {
+    1.6
+}
+
+impl TextOptions {
+    pub fn from_json(json: &str) -> Result<Self, String> {
+        serde_json::from_str(json).map_err(|e| e.to_string())
+    }
+
+    pub fn load_font(&self) -> Result<Font, String> {
+        match &self.svg_font {
+            Some(svg) => Font::from_svg_font(svg),
+            None => Font::builtin(&self.font),
+        }
+    }
+
+    /// The text of every part: one per serial number, or just `text`.
+    pub fn texts(&self) -> Result<Vec<String>, String> {
+        match &self.serial {
+            Some(range) => {
+                range.check()?;
+                (0..range.count as u64)
+                    .map(|i| Ok(self.text.replace("{serial}", &range.label(i)?)))
+                    .collect()
+            }
+            None => Ok(vec![self.text.clone()]),
+        }
+    }
+
+    /// The first part's text (empty when the range numbers no parts).
+    pub fn first_text(&self) -> Result<String, String> {
+        match &self.serial {
+            Some(range) if range.count == 0 => Ok(String::new()),
+            Some(range) => {
+                range.check()?;
+                Ok(self.text.replace("{serial}", &range.label(0)?))
+            }
+            None => Ok(self.text.clone()),
+        }
+    }
+
+    /// Lay out `text` (one of [`Self::texts`]) in `font`.
+    pub fn layout(&self, text: &str, font: &Font) -> Result<Vec<Polyline>, String> {
+        if self.size.is_nan() || self.size <= 0.0 {
+            return Err("Text size must be positive".into());
+        }
+        if text.trim().is_empty() {
+            return Err("Text is empty".into());
+        }
+        let scale = self.size / font.cap_height;
+        let lean = self.slant.to_radians().tan();
+        let mut out = Vec::new();
+        for (row, line) in text.lines().enumerate() {
+            let glyphs = line
+                .chars()
+                .map(|c| font.glyph(c))
+                .collect::<Result<Vec<_>, _>>()?;
+            let width = glyphs.iter().map(|g| g.advance * scale).sum::<f64>()
+                + self.spacing * glyphs.len().saturating_sub(1) as f64;
+            let mut u = match self.align {
+                Align::Left => 0.0,
+                Align::Center => -width / 2.0,
+                Align::Right => -width,
+            };
+            let v = -(row as f64) * self.line_spacing * self.size;
+            for glyph in glyphs {
+                for stroke in &glyph.strokes {
+                    let points = stroke
+                        .points
+                        .iter()
+                        .map(|p| Vec2::new(u + (p.x + p.y * lean) * scale, v + p.y * scale))
+                        .collect();
+                    out.push(Polyline::new(points, stroke.closed));
+                }
+                u += glyph.advance * scale + self.spacing;
+            }
+        }
+        Ok(out.into_iter().map(|pl| self.place(pl)).collect())
+    }
+
+    /// Move a laid-out stroke from baseline coordinates (`u` along,
+    /// `v` up) to its place, bending it round the arc if there is one.
+    fn place(&self, stroke: Polyline) -> Polyline {
+        let origin = Vec2::new(self.x, self.y);
+        let radius = match self.arc_radius {
+            Some(r) if r.abs() > 1e-9 => r,
+            _ => {
+                let points = stroke
+                    .points
+                    .iter()
+                    .map(|p| Vec2::new(origin.x + p.x, origin.y + p.y))
+                    .collect();
+                return Polyline::new(points, stroke.closed);
+            }
+        };
+        // Straight strokes turn into curves: split them finely first.
+        let max_step = self.size / 8.0;
+        let mut fine: Vec<Vec2> = Vec::with_capacity(stroke.points.len());
+        let mut edges: Vec<(Vec2, Vec2)> = stroke.points.windows(2).map(|w| (w[0], w[1])).collect();
+        if stroke.closed && stroke.points.len() > 2 {
+            edges.push((*stroke.points.last().unwrap(), stroke.points[0]));
+        }
+        fine.extend(stroke.points.first());
+        for (a, b) in edges {
+            let n = (Vec2::dist(a, b) / max_step).ceil().max(1.0) as usize;
+            fine.extend((1..=n).map(|i| {
+                let t = i as f64 / n as f64;
+                Vec2::new(a.x + (b.x - a.x) * t, a.y + (b.y - a.y) * t)
+            }));
+        }
+        if stroke.closed && fine.len() > 1 {
+            fine.pop();
+        }
+
+        let over = radius > 0.0;
+        let r = radius.abs();
+        let anchor = self
+            .arc_angle
+            .unwrap_or(if over { 90.0 } else { 270.0 })
+            .to_radians();
+        let points = fine
+            .into_iter()
+            .map(|p| {
+                let (angle, dist) = if over {
+                    (anchor - p.x / r, r + p.y)
+                } else {
+                    (anchor + p.x / r, r - p.y)
+                };
+                Vec2::new(origin.x + dist * angle.cos(), origin.y + dist * angle.sin())
+            })
+            .collect();
+        Polyline::new(points, stroke.closed)
+    }
+}
+
+/// Outlines of the first part's text.
+pub fn render(options: &TextOptions) -> Result<Vec<Polyline>, String> {
+    let font = options.load_font()?;
+    let text = options.first_text()?;
+    options.layout(&text, &font)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options(json: &str) -> TextOptions {
+        TextOptions::from_json(json).unwrap()
+    }
+
+    fn bounds(polylines: &[Polyline]) -> (Vec2, Vec2) {
+        let pts = polylines.iter().flat_map(|p| p.points.iter());
+        let min = pts.clone().fold(Vec2::new(f64::MAX, f64::MAX), |m, p| {
+            Vec2::new(m.x.min(p.x), m.y.min(p.y))
+        });
+        let max = pts.fold(Vec2::new(f64::MIN, f64::MIN), |m, p| {
+            Vec2::new(m.x.max(p.x), m.y.max(p.y))
+        });
+        (min, max)
+    }
+
+    #[test]
+    fn test_simplex_covers_printable_ascii() {
+        let font = Font::builtin("simplex").unwrap();
+        assert_eq!(font.glyphs.len(), 95);
+        assert_eq!(font.cap_height, 21.0);
+        // "H": two uprights and a bar, all open strokes.
+        let h = font.glyph('H').unwrap();
+        assert_eq!(h.strokes.len(), 3);
+        assert!(h.strokes.iter().all(|s| !s.closed));
+        assert_eq!(
+            h.strokes[0].points,
+            vec![Vec2::new(4.0, 21.0), Vec2::new(4.0, 0.0)]
+        );
+        assert!(font.glyph('é').unwrap_err().contains('é'));
+        assert!(Font::builtin("gothic").is_err());
+        // Wrapped records read the same as single-line ones.
+        let wrapped = Font::from_jhf("12345  9I[RFJ[ RRFZ[\n RMTWT\n").unwrap();
+        assert_eq!(wrapped.glyph(' ').unwrap().strokes.len(), 3);
+    }
+
+    #[test]
+    fn test_layout_size_alignment_and_lines() {
+        let text =
+            options(r#"{"text": "HI\nH", "size": 10, "x": 100, "y": 50, "align": "center"}"#);
+        let font = text.load_font().unwrap();
+        let strokes = text.layout(&text.text, &font).unwrap();
+        assert_eq!(strokes.len(), 3 + 1 + 3);
+        let (min, max) = bounds(&strokes[..4]);
+        // Cap height is `size`, centred on x = 100 with the baseline at y = 50.
+        assert!((max.y - 60.0).abs() < 1e-9 && (min.y - 50.0).abs() < 1e-9);
+        let scale = 10.0 / 21.0;
+        let width = (22.0 + 8.0) * scale;
+        assert!((min.x - (100.0 - width / 2.0 + 4.0 * scale)).abs() < 1e-9);
+        // The second line sits 1.6 sizes lower.
+        let (min, _) = bounds(&strokes[4..]);
+        assert!((min.y - 34.0).abs() < 1e-9);
+
+        let spaced = options(r#"{"text": "II", "spacing": 2, "slant": 45}"#);
+        let s = spaced.layout("II", &font).unwrap();
+        let dx = s[1].points[0].x - s[0].points[0].x;
+        assert!((dx - (8.0 * 5.0 / 21.0 + 2.0)).abs() < 1e-9);
+        // 45° slant: the top of the "I" leans right by its height.
+        assert!((s[0].points[0].x - s[0].points[1].x - 5.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_text_on_arc() {
+        let over = options(r#"{"text": "HHHH", "arc_radius": 30, "align": "center"}"#);
+        let strokes = render(&over).unwrap();
+        for s in &strokes {
+            for p in &s.points {
+                let r = p.x.hypot(p.y);
+                assert!((30.0 - 1e-9..=35.0 + 1e-9).contains(&r), "{:?}", p);
+            }
+        }
+        // Centred over the top: symmetric about the Y axis, reading left
+        // to right.
+        let (min, max) = bounds(&strokes);
+        assert!((min.x + max.x).abs() < 1e-6 && min.y > 0.0);
+        assert!(strokes[0].points[0].x < strokes[11].points[0].x);
+
+        // Along the bottom the letters stay upright: tops point inwards.
+        let under = options(r#"{"text": "I", "arc_radius": -30, "align": "center"}"#);
+        let i = &render(&under).unwrap()[0];
+        let (top, foot) = (i.points.first().unwrap(), i.points.last().unwrap());
+        assert!((top.y + 25.0).abs() < 1e-9 && (foot.y + 30.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_serial_numbers_and_mono_digits() {
+        let text = options(
+            r#"{"text": "SN-{serial}", "font": "simplex_mono", "serial": {"start": 98, "count": 3, "digits": 4}}"#,
+        );
+        assert_eq!(text.texts().unwrap(), vec!["SN-0098", "SN-0099", "SN-0100"]);
+        let font = text.load_font().unwrap();
+        let one = font.glyph('1').unwrap();
+        assert_eq!(one.advance, font.glyph('m').unwrap().advance);
+        let widths: Vec<f64> = text
+            .texts()
+            .unwrap()
+            .iter()
+            .map(|t| {
+                let (min, max) = bounds(&text.layout(t, &font).unwrap());
+                max.x - min.x
+            })
+            .collect();
+        assert!(widths[0] > 0.0);
+
+        let overflow = options(
+            r#"{"text": "{serial}", "serial": {"start": 18446744073709551615, "count": 2}}"#,
+        );
+        assert!(overflow.texts().unwrap_err().contains("Serial range"));
+        let overflow =
+            options(r#"{"text": "{serial}", "serial": {"count": 3, "step": 9223372036854775808}}"#);
+        assert!(overflow.texts().is_err());
+
+        // Sizes that would exhaust memory are refused before any work.
+        let wide = options(r#"{"text": "{serial}", "serial": {"digits": 1000000000000}}"#);
+        assert!(wide.texts().unwrap_err().contains("digits"));
+        assert!(render(&wide).unwrap_err().contains("digits"));
+        let many = options(r#"{"text": "{serial}", "serial": {"count": 4000000000}}"#);
+        assert!(many.texts().unwrap_err().contains("count"));
+        let most = options(r#"{"text": "{serial}", "serial": {"count": 10000, "digits": 20}}"#);
+        assert_eq!(most.texts().unwrap().len(), 10_000);
+        assert_eq!(most.first_text().unwrap(), "00000000000000000001");
+    }
+
+    #[test]
+    fn test_svg_font_outlines() {
+        let svg = r#"<svg xmlns="http://www.w3.org/2000/svg"><defs>
+            <font horiz-adv-x="600"><font-face units-per-em="1000" cap-height="700"/>
+              <missing-glyph d="M0 0h500v700h-500z"/>
+              <glyph unicode="O" horiz-adv-x="800" d="M100 0h600v700h-600z M200 100v500h400v-500z"/>
+              <glyph unicode=" "/>
+            </font></defs></svg>"#;
+        let text = options(r#"{"text": "O ?", "size": 7}"#);
+        let font = Font::from_svg_font(svg).unwrap();
+        let outlines = text.layout("O ?", &font).unwrap();
+        assert_eq!(outlines.len(), 3);
+        assert!(outlines.iter().all(|o| o.closed));
+        // 700 units tall = 7 mm; the missing glyph follows 800 + 600 units on.
+        assert_eq!(outlines[0].points[0], Vec2::new(1.0, 0.0));
+        assert_eq!(outlines[0].points[2], Vec2::new(7.0, 7.0));
+        assert_eq!(outlines[2].points[0], Vec2::new(14.0, 0.0));
+        assert!(Font::from_svg_font("<svg/>").is_err());
+    }
+}
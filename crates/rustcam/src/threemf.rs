@@ -0,0 +1,570 @@
+//! 3MF parser — the zipped XML mesh format used by slicers.
+//!
+//! Swiss-cheese layer: **Geometry Input**
+//! Extension point: `unit_to_mm` for model units; `Model::emit` for new
+//! ways an object can hold geometry.
+//!
+//! A 3MF file is a zip package. `_rels/.rels` names the root model part
+//! (usually `3D/3dmodel.model`), an XML document with a list of objects,
+//! each either a triangle mesh or a set of components that place other
+//! objects, and a `<build>` list of the items to print. Geometry is
+//! emitted per build item with the item and component transforms applied
+//! and scaled from the model's `unit` to millimetres. Components may name
+//! another model part with the production extension's `p:path` (slicers
+//! keep each object in its own `3D/Objects/*.model`); those parts are
+//! loaded too and share the root model's unit. The zip reader only
+//! handles what 3MF producers write: stored or deflated entries.
+
+use std::collections::HashMap;
+
+use crate::geometry::{Mesh, Triangle, Vec3};
+use crate::mesh_import::facet;
+
+/// Components may nest; this bounds cycles in a malformed file.
+const MAX_COMPONENT_DEPTH: usize = 32;
+/// Refuse to inflate a single part beyond this.
+const MAX_PART_BYTES: usize = 1 << 30;
+
+/// Parse a 3MF package from raw bytes.
+pub fn parse_3mf(data: &[u8]) -> Result<Mesh, String> {
+    let parts = read_zip(data)?;
+    let model_path = root_model_path(&parts).unwrap_or_else(|| "3D/3dmodel.model".into());
+    let model = Model::read(&parts, model_path.trim_start_matches('/'))?;
+
+    let mut triangles = Vec::new();
+    if model.build.is_empty() {
+        // No build list: every object that is not part of another one.
+        for id in model.roots() {
+            model.emit(id, &model.scale, 0, &mut triangles)?;
+        }
+    } else {
+        for (id, transform) in &model.build {
+            model.emit(id, &transform.then(&model.scale), 0, &mut triangles)?;
+        }
+    }
+    if triangles.is_empty() {
+        return Err("3MF file contains no triangles".into());
+    }
+    Ok(Mesh::new(triangles))
+}
+
+/// A 3MF affine transform: rows `m00 m01 m02 / m10 m11 m12 / m20 m21 m22 /
+/// m30 m31 m32`, applied to row vectors so the last row is translation.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Transform([f64; 12]);
+
+impl Transform {
+    const IDENTITY: Self = Self([1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0]);
+
+    fn scale(s: f64) -> Self {
+        Self([s, 0.0, 0.0, 0.0, s, 0.0, 0.0, 0.0, s, 0.0, 0.0, 0.0])
+    }
+
+    fn parse(attr: Option<&str>) -> Result<Self, String> {
+        let Some(text) = attr else {
+            return Ok(Self::IDENTITY);
+        };
+        let values: Vec<f64> = text
+            .split_whitespace()
+            .map(|s| s.parse::<f64>())
+            .collect::<Result<_, _>>()
+            .map_err(|_| format!("3MF: bad transform '{}'", text))?;
+        let m: [f64; 12] = values
+            .try_into()
+            .map_err(|_| format!("3MF: transform needs 12 numbers: '{}'", text))?;
+        Ok(Self(m))
+    }
+
+    fn apply(&self, p: Vec3) -> Vec3 {
+        let m = &self.0;
+        Vec3::new(
+            p.x * m[0] + p.y * m[3] + p.z * m[6] + m[9],
+            p.x * m[1] + p.y * m[4] + p.z * m[7] + m[10],
+            p.x * m[2] + p.y * m[5] + p.z * m[8] + m[11],
+        )
+    }
+
+    /// `self` followed by `next`.
+    fn then(&self, next: &Self) -> Self {
+        let (a, b) = (&self.0, &next.0);
+        let mut out = [0.0; 12];
+        for row in 0..4 {
+            for col in 0..3 {
+                let mut v: f64 = (0..3).map(|k| a[row * 3 + k] * b[k * 3 + col]).sum();
+                if row == 3 {
+                    v += b[9 + col];
+                }
+                out[row * 3 + col] = v;
+            }
+        }
+        Self(out)
+    }
+
+    /// A mirroring transform turns the winding inside out.
+    fn mirrors(&self) -> bool {
+        let m = &self.0;
+        let det = m[0] * (m[4] * m[8] - m[5] * m[7]) - m[1] * (m[3] * m[8] - m[5] * m[6])
+            + m[2] * (m[3] * m[7] - m[4] * m[6]);
+        det < 0.0
+    }
+}
+
+enum Object {
+    Mesh {
+        vertices: Vec<Vec3>,
+        triangles: Vec<[usize; 3]>,
+    },
+    Components(Vec<(ObjectRef, Transform)>),
+}
+
+/// An object id and the model part that defines it.
+type ObjectRef = (String, String);
+
+struct Model {
+    /// Model unit to millimetres.
+    scale: Transform,
+    objects: HashMap<ObjectRef, Object>,
+    /// Objects of the root part in document order.
+    order: Vec<ObjectRef>,
+    build: Vec<(ObjectRef, Transform)>,
+}
+
+impl Model {
+    /// The root model part `root` and every part it references.
+    fn read(parts: &HashMap<String, Vec<u8>>, root: &str) -> Result<Self, String> {
+        let mut model = Self {
+            scale: Transform::IDENTITY,
+            objects: HashMap::new(),
+            order: Vec::new(),
+            build: Vec::new(),
+        };
+        let mut pending = vec![root.to_string()];
+        let mut seen = pending.clone();
+        while let Some(part) = pending.pop() {
+            let xml = parts
+                .get(&part)
+                .ok_or_else(|| format!("3MF: missing model part '{}'", part))?;
+            let xml = std::str::from_utf8(xml).map_err(|_| "3MF: model is not UTF-8")?;
+            let doc = roxmltree::Document::parse(xml).map_err(|e| format!("3MF: {}", e))?;
+            for next in model.read_part(&doc, &part, part == root)? {
+                if !seen.contains(&next) {
+                    seen.push(next.clone());
+                    pending.push(next);
+                }
+            }
+        }
+        Ok(model)
+    }
+
+    /// Add the objects of one model part; the root part also sets the
+    /// unit and build list. Returns the other parts it references.
+    fn read_part(
+        &mut self,
+        doc: &roxmltree::Document,
+        part: &str,
+        is_root: bool,
+    ) -> Result<Vec<String>, String> {
+        let root = doc.root_element();
+        if root.tag_name().name() != "model" {
+            return Err("3MF: root element is not <model>".into());
+        }
+        if is_root {
+            let unit = root.attribute("unit").unwrap_or("millimeter");
+            self.scale = Transform::scale(unit_to_mm(unit)?);
+        }
+
+        let mut referenced = Vec::new();
+        for node in root.descendants().filter(|n| n.is_element()) {
+            match node.tag_name().name() {
+                "object" => {
+                    let id = node.attribute("id").ok_or("3MF: object without id")?;
+                    if let Some(object) = read_object(node, part)? {
+                        if let Object::Components(list) = &object {
+                            referenced.extend(
+                                list.iter()
+                                    .filter(|((p, _), _)| p != part)
+                                    .map(|((p, _), _)| p.clone()),
+                            );
+                        }
+                        let key = (part.to_string(), id.to_string());
+                        if is_root {
+                            self.order.push(key.clone());
+                        }
+                        self.objects.insert(key, object);
+                    }
+                }
+                "item" if is_root => {
+                    let key = object_ref(node, part).ok_or("3MF: item without objectid")?;
+                    if key.0 != part {
+                        referenced.push(key.0.clone());
+                    }
+                    self.build
+                        .push((key, Transform::parse(node.attribute("transform"))?));
+                }
+                _ => {}
+            }
+        }
+        Ok(referenced)
+    }
+
+    /// Objects not used as a component of another object.
+    fn roots(&self) -> impl Iterator<Item = &ObjectRef> {
+        let used: Vec<&ObjectRef> = self
+            .objects
+            .values()
+            .filter_map(|o| match o {
+                Object::Components(list) => Some(list.iter().map(|(id, _)| id)),
+                Object::Mesh { .. } => None,
+            })
+            .flatten()
+            .collect();
+        self.order.iter().filter(move |id| !used.contains(id))
+    }
+
+    /// Append the triangles of object `id` placed by `transform`.
+    fn emit(
+        &self,
+        id: &ObjectRef,
+        transform: &Transform,
+        depth: usize,
+        out: &mut Vec<Triangle>,
+    ) -> Result<(), String> {
+        if depth > MAX_COMPONENT_DEPTH {
+            return Err("3MF: components nested too deeply".into());
+        }
+        let object = self
+            .objects
+            .get(id)
+            .ok_or_else(|| format!("3MF: unknown object id '{}' in '{}'", id.1, id.0))?;
+        match object {
+            Object::Mesh {
+                vertices,
+                triangles,
+            } => {
+                let placed: Vec<Vec3> = vertices.iter().map(|&v| transform.apply(v)).collect();
+                let flip = transform.mirrors();
+                out.extend(triangles.iter().map(|&[a, b, c]| {
+                    if flip {
+                        facet(placed[a], placed[c], placed[b])
+                    } else {
+                        facet(placed[a], placed[b], placed[c])
+                    }
+                }));
+            }
+            Object::Components(components) => {
+                for (child, local) in components {
+                    self.emit(child, &local.then(transform), depth + 1, out)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+fn read_object(node: roxmltree::Node, part: &str) -> Result<Option<Object>, String> {
+    if let Some(mesh) = child(node, "mesh") {
+        let mut vertices = Vec::new();
+        for v in child(mesh, "vertices").iter().flat_map(|n| n.children()) {
+            if v.is_element() && v.tag_name().name() == "vertex" {
+                vertices.push(Vec3::new(number(v, "x")?, number(v, "y")?, number(v, "z")?));
+            }
+        }
+        let mut triangles = Vec::new();
+        for t in child(mesh, "triangles").iter().flat_map(|n| n.children()) {
+            if t.is_element() && t.tag_name().name() == "triangle" {
+                let mut corner = [0usize; 3];
+                for (c, name) in corner.iter_mut().zip(["v1", "v2", "v3"]) {
+                    let i = number(t, name)?;
+                    if !(0.0..vertices.len() as f64).contains(&i) {
+                        return Err(format!("3MF: triangle index {} out of range", i));
+                    }
+                    *c = i as usize;
+                }
+                triangles.push(corner);
+            }
+        }
+        return Ok(Some(Object::Mesh {
+            vertices,
+            triangles,
+        }));
+    }
+    if let Some(components) = child(node, "components") {
+        let mut list = Vec::new();
+        for c in components.children().filter(|n| n.is_element()) {
+            if c.tag_name().name() == "component" {
+                let id = object_ref(c, part).ok_or("3MF: component without objectid")?;
+                list.push((id, Transform::parse(c.attribute("transform"))?));
+            }
+        }
+        return Ok(Some(Object::Components(list)));
+    }
+    Ok(None)
+}
+
+/// The object a component or item points at: `objectid` in the part named
+/// by its production-extension `p:path`, or in `part` itself.
+fn object_ref(node: roxmltree::Node, part: &str) -> Option<ObjectRef> {
+    let id = node.attribute("objectid")?;
+    let path = node
+        .attributes()
+        .find(|a| a.name() == "path")
+        .map_or(part, |a| a.value().trim_start_matches('/'));
+    Some((path.to_string(), id.to_string()))
+}
+
+fn child<'a, 'i>(parent: roxmltree::Node<'a, 'i>, name: &str) -> Option<roxmltree::Node<'a, 'i>> {
+    parent
+        .children()
+        .find(|n| n.is_element() && n.tag_name().name() == name)
+}
+
+fn number(node: roxmltree::Node, name: &str) -> Result<f64, String> {
+    node.attribute(name)
+        .and_then(|s| s.trim().parse().ok())
+        .ok_or_else(|| format!("3MF: <{}> needs numeric '{}'", node.tag_name().name(), name))
+}
+
+fn unit_to_mm(unit: &str) -> Result<f64, String> {
+    Ok(match unit {
+        "micron" => 0.001,
+        "millimeter" => 1.0,
+        "centimeter" => 10.0,
+        "inch" => 25.4,
+        "foot" => 304.8,
+        "meter" => 1000.0,
+        other => return Err(format!("3MF: unknown unit '{}'", other)),
+    })
+}
+
+/// Target of the package relationship whose type ends in `3dmodel`.
+fn root_model_path(parts: &HashMap<String, Vec<u8>>) -> Option<String> {
+    let rels = std::str::from_utf8(parts.get("_rels/.rels")?).ok()?;
+    let doc = roxmltree::Document::parse(rels).ok()?;
+    doc.descendants()
+        .filter(|n| n.tag_name().name() == "Relationship")
+        .find(|n| n.attribute("Type").is_some_and(|t| t.ends_with("/3dmodel")))
+        .and_then(|n| n.attribute("Target"))
+        .map(str::to_string)
+}
+
+/// Every file in a zip archive, by name.
+fn read_zip(data: &[u8]) -> Result<HashMap<String, Vec<u8>>, String> {
+    let u16_at = |at: usize| -> Result<usize, String> {
+        data.get(at..at + 2)
+            .map(|b| u16::from_le_bytes([b[0], b[1]]) as usize)
+            .ok_or_else(|| "3MF: zip is truncated".to_string())
+    };
+    let u32_at = |at: usize| -> Result<usize, String> {
+        data.get(at..at + 4)
+            .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]) as usize)
+            .ok_or_else(|| "3MF: zip is truncated".to_string())
+    };
+
+    // The end-of-central-directory record sits in the last 64 KiB + 22.
+    let search_from = data.len().saturating_sub(0xFFFF + 22);
+    let eocd = (search_from..data.len().saturating_sub(21))
+        .rev()
+        .find(|&i| data[i..i + 4] == [0x50, 0x4b, 0x05, 0x06])
+        .ok_or("3MF: not a zip package")?;
+    let entries = u16_at(eocd + 10)?;
+    let mut at = u32_at(eocd + 16)?;
+
+    let mut parts = HashMap::new();
+    for _ in 0..entries {
+        if u32_at(at)? != 0x0201_4b50 {
+            return Err("3MF: bad zip central directory".into());
+        }
+        let method = u16_at(at + 10)?;
+        let packed = u32_at(at + 20)?;
+        let name_len = u16_at(at + 28)?;
+        let skip = name_len + u16_at(at + 30)? + u16_at(at + 32)?;
+        let local = u32_at(at + 42)?;
+        let name = data
+            .get(at + 46..at + 46 + name_len)
+            .ok_or("3MF: zip is truncated")?;
+        let name = String::from_utf8_lossy(name).into_owned();
+        at += 46 + skip;
+
+        if u32_at(local)? != 0x0403_4b50 {
+            return Err("3MF: bad zip local header".into());
+        }
+        let start = local + 30 + u16_at(local + 26)? + u16_at(local + 28)?;
+        let raw = data
+            .get(start..start + packed)
+            .ok_or("3MF: zip is truncated")?;
+        let bytes = match method {
+            0 => raw.to_vec(),
+            8 => miniz_oxide::inflate::decompress_to_vec_with_limit(raw, MAX_PART_BYTES)
+                .map_err(|e| format!("3MF: cannot inflate '{}': {:?}", name, e.status))?,
+            other => {
+                return Err(format!(
+                    "3MF: '{}' uses unsupported compression {}",
+                    name, other
+                ))
+            }
+        };
+        parts.insert(name, bytes);
+    }
+    Ok(parts)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A zip archive holding `files`, deflated when `deflate` is set.
+    fn zip(files: &[(&str, &str)], deflate: bool) -> Vec<u8> {
+        let mut out = Vec::new();
+        let mut central = Vec::new();
+        for (name, body) in files {
+            let packed = if deflate {
+                miniz_oxide::deflate::compress_to_vec(body.as_bytes(), 6)
+            } else {
+                body.as_bytes().to_vec()
+            };
+            let method: u16 = if deflate { 8 } else { 0 };
+            let offset = out.len() as u32;
+            out.extend(0x0403_4b50u32.to_le_bytes());
+            out.extend([20, 0, 0, 0]);
+            out.extend(method.to_le_bytes());
+            out.extend([0; 8]); // time, date, crc
+            out.extend((packed.len() as u32).to_le_bytes());
+            out.extend((body.len() as u32).to_le_bytes());
+            out.extend((name.len() as u16).to_le_bytes());
+            out.extend([0, 0]);
+            out.extend(name.as_bytes());
+            out.extend(&packed);
+
+            central.extend(0x0201_4b50u32.to_le_bytes());
+            central.extend([20, 0, 20, 0, 0, 0]);
+            central.extend(method.to_le_bytes());
+            central.extend([0; 8]);
+            central.extend((packed.len() as u32).to_le_bytes());
+            central.extend((body.len() as u32).to_le_bytes());
+            central.extend((name.len() as u16).to_le_bytes());
+            central.extend([0; 12]);
+            central.extend(offset.to_le_bytes());
+            central.extend(name.as_bytes());
+        }
+        let dir_offset = out.len() as u32;
+        out.extend(&central);
+        out.extend(0x0605_4b50u32.to_le_bytes());
+        out.extend([0; 4]);
+        out.extend((files.len() as u16).to_le_bytes());
+        out.extend((files.len() as u16).to_le_bytes());
+        out.extend((central.len() as u32).to_le_bytes());
+        out.extend(dir_offset.to_le_bytes());
+        out.extend([0, 0]);
+        out
+    }
+
+    const RELS: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships">
+  <Relationship Target="/3D/part.model" Id="rel0"
+    Type="http://schemas.microsoft.com/3dmanufacturing/2013/01/3dmodel"/>
+</Relationships>"#;
+
+    /// A 1 x 1 cm right triangle in centimetres, placed through a
+    /// component and a build item.
+    const MODEL: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<model unit="centimeter" xmlns="http://schemas.microsoft.com/3dmanufacturing/core/2015/02">
+  <resources>
+    <object id="1" type="model">
+      <mesh>
+        <vertices>
+          <vertex x="0" y="0" z="0"/><vertex x="1" y="0" z="0"/><vertex x="0" y="1" z="0"/>
+        </vertices>
+        <triangles><triangle v1="0" v2="1" v3="2"/></triangles>
+      </mesh>
+    </object>
+    <object id="2" type="model">
+      <components>
+        <component objectid="1" transform="1 0 0 0 1 0 0 0 1 0 0 2"/>
+      </components>
+    </object>
+  </resources>
+  <build>
+    <item objectid="2" transform="0 1 0 -1 0 0 0 0 1 5 0 0"/>
+  </build>
+</model>"#;
+
+    #[test]
+    fn test_parse_3mf_applies_transforms_and_units() {
+        for deflate in [false, true] {
+            let data = zip(&[("_rels/.rels", RELS), ("3D/part.model", MODEL)], deflate);
+            let mesh = parse_3mf(&data).unwrap();
+            assert_eq!(mesh.triangles.len(), 1);
+            let t = &mesh.triangles[0];
+            // cm -> mm, lifted 2 cm by the component, turned 90° and
+            // shifted 5 cm in x by the item.
+            let close =
+                |a: Vec3, b: Vec3| (a.x - b.x).abs() + (a.y - b.y).abs() + (a.z - b.z).abs() < 1e-9;
+            assert!(close(t.v0, Vec3::new(50.0, 0.0, 20.0)), "{:?}", t.v0);
+            assert!(close(t.v1, Vec3::new(50.0, 10.0, 20.0)), "{:?}", t.v1);
+            assert!(close(t.v2, Vec3::new(40.0, 0.0, 20.0)), "{:?}", t.v2);
+            assert!(t.normal.z > 0.99);
+        }
+    }
+
+    #[test]
+    fn test_parse_3mf_resolves_components_in_other_parts() {
+        // The layout Bambu Studio and OrcaSlicer write: the root model only
+        // assembles objects kept in their own parts.
+        let root = r#"<?xml version="1.0" encoding="UTF-8"?>
+<model unit="millimeter" xmlns="http://schemas.microsoft.com/3dmanufacturing/core/2015/02"
+  xmlns:p="http://schemas.microsoft.com/3dmanufacturing/production/2015/06">
+  <resources>
+    <object id="7" type="model">
+      <components>
+        <component p:path="/3D/Objects/object_1.model" objectid="1"/>
+        <component p:path="/3D/Objects/object_1.model" objectid="1"
+          transform="1 0 0 0 1 0 0 0 1 0 0 5"/>
+        <component p:path="/3D/Objects/object_2.model" objectid="1"/>
+      </components>
+    </object>
+  </resources>
+  <build><item objectid="7"/></build>
+</model>"#;
+        let part = |x: f64| {
+            format!(
+                r#"<model unit="millimeter" xmlns="http://schemas.microsoft.com/3dmanufacturing/core/2015/02">
+  <resources><object id="1" type="model"><mesh>
+    <vertices><vertex x="{x}" y="0" z="0"/><vertex x="{}" y="0" z="0"/><vertex x="{x}" y="1" z="0"/></vertices>
+    <triangles><triangle v1="0" v2="1" v3="2"/></triangles>
+  </mesh></object></resources>
+</model>"#,
+                x + 1.0
+            )
+        };
+        let (one, two) = (part(0.0), part(10.0));
+        let files = [
+            ("_rels/.rels", RELS),
+            ("3D/part.model", root),
+            ("3D/Objects/object_1.model", one.as_str()),
+            ("3D/Objects/object_2.model", two.as_str()),
+        ];
+        let mesh = parse_3mf(&zip(&files, true)).unwrap();
+        assert_eq!(mesh.triangles.len(), 3);
+        let mut corners: Vec<(f64, f64)> =
+            mesh.triangles.iter().map(|t| (t.v0.x, t.v0.z)).collect();
+        corners.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        assert_eq!(corners, [(0.0, 0.0), (0.0, 5.0), (10.0, 0.0)]);
+
+        let err = parse_3mf(&zip(&files[..3], false)).unwrap_err();
+        assert!(err.contains("object_2.model"), "{}", err);
+    }
+
+    #[test]
+    fn test_parse_3mf_errors() {
+        assert!(parse_3mf(b"PK\x03\x04 not really").is_err());
+        let missing = zip(&[("_rels/.rels", RELS)], false);
+        assert!(parse_3mf(&missing).unwrap_err().contains("part.model"));
+        let mirrored = MODEL.replace("0 1 0 -1 0 0 0 0 1 5 0 0", "-1 0 0 0 1 0 0 0 1 0 0 0");
+        let data = zip(&[("3D/3dmodel.model", &mirrored)], false);
+        let mesh = parse_3mf(&data).unwrap();
+        assert!(
+            mesh.triangles[0].normal.z > 0.99,
+            "mirroring keeps outward normals"
+        );
+    }
+}
@@ -1,7 +1,8 @@
 /// Tool definitions for CAM operations.
 ///
 /// Swiss-cheese layer: **Tool geometry**
-/// Extension point: add new tool types (V-bit, drill, etc.) by extending ToolType.
+/// Extension point: add new tool types by extending ToolType and its
+/// cutting profile in `radius_at_height`.
 use serde::{Deserialize, Serialize};
 
 /// Type of cutting tool with type-specific parameters.
@@ -18,6 +19,23 @@ pub enum ToolType {
         /// Effective cutting width (may differ from body diameter).
         effective_diameter: f64,
     },
+    /// Twist drill with a conical point.
+    Drill {
+        /// Included angle of the point in degrees (118 for most HSS drills).
+        tip_angle: f64,
+        /// Drill deeper by the tip length so the full diameter reaches the
+        /// programmed depth (for through holes).
+        #[serde(default)]
+        tip_compensation: bool,
+    },
+    /// V-bit (engraving cutter): a cone with an optional flat tip.
+    VBit {
+        /// Included angle of the cone in degrees.
+        angle: f64,
+        /// Diameter of the flat at the tip (0 for a sharp point).
+        #[serde(default)]
+        tip_diameter: f64,
+    },
 }
 
 /// Cutting tool definition.
@@ -75,6 +93,103 @@ impl Tool {
         }
     }
 
+    /// Create a twist drill.
+    pub fn drill(diameter: f64, tip_angle: f64, tip_compensation: bool, flute_length: f64) -> Self {
+        Self {
+            tool_type: ToolType::Drill {
+                tip_angle,
+                tip_compensation,
+            },
+            diameter,
+            flute_length,
+            corner_radius: 0.0,
+        }
+    }
+
+    /// Create a V-bit. `diameter` is the widest the cone cuts.
+    pub fn vbit(diameter: f64, angle: f64, tip_diameter: f64, flute_length: f64) -> Self {
+        Self {
+            tool_type: ToolType::VBit {
+                angle,
+                tip_diameter,
+            },
+            diameter,
+            flute_length,
+            corner_radius: 0.0,
+        }
+    }
+
+    /// Radius of the cutting profile at height `h` above the tip.
+    pub fn radius_at_height(&self, h: f64) -> f64 {
+        let r = self.diameter / 2.0;
+        if h < 0.0 {
+            return 0.0;
+        }
+        match &self.tool_type {
+            ToolType::Drill { tip_angle, .. } => (h * half_angle_tan(*tip_angle)).min(r),
+            ToolType::VBit {
+                angle,
+                tip_diameter,
+            } => (tip_diameter / 2.0 + h * half_angle_tan(*angle)).min(r),
+            _ => {
+                let cr = self.corner_radius.clamp(0.0, r);
+                if h >= cr {
+                    r
+                } else {
+                    r - cr + (cr * cr - (cr - h) * (cr - h)).sqrt()
+                }
+            }
+        }
+    }
+
+    /// Lowest height above the tip at which the profile is `radius` wide,
+    /// or `None` when the tool is narrower than that.
+    pub fn height_at_radius(&self, radius: f64) -> Option<f64> {
+        let r = self.diameter / 2.0;
+        if radius > r + 1e-12 {
+            return None;
+        }
+        let h = match &self.tool_type {
+            ToolType::Drill { tip_angle, .. } => radius / half_angle_tan(*tip_angle),
+            ToolType::VBit {
+                angle,
+                tip_diameter,
+            } => (radius - tip_diameter / 2.0) / half_angle_tan(*angle),
+            _ => {
+                let cr = self.corner_radius.clamp(0.0, r);
+                let d = radius - (r - cr);
+                if d <= 0.0 {
+                    0.0
+                } else {
+                    cr - (cr * cr - d * d).max(0.0).sqrt()
+                }
+            }
+        };
+        Some(h.max(0.0))
+    }
+
+    /// Length of a drill's conical point (0 for other tools).
+    pub fn tip_length(&self) -> f64 {
+        match &self.tool_type {
+            ToolType::Drill { tip_angle, .. } if *tip_angle > 0.0 && *tip_angle < 180.0 => {
+                self.diameter / 2.0 / (tip_angle.to_radians() / 2.0).tan()
+            }
+            _ => 0.0,
+        }
+    }
+
+    /// Extra depth to add to a drilled hole: the tip length when the drill
+    /// asks for tip compensation, otherwise 0.
+    pub fn tip_compensation(&self) -> f64 {
+        match &self.tool_type {
+            ToolType::Drill {
+                tip_compensation: true,
+                ..
+            } => self.tip_length(),
+            _ => 0.0,
+        }
+    }
+
     /// Get the effective cutting diameter (for face mills, this may differ from body diameter).
     pub fn effective_diameter(&self) -> f64 {
         match &self.tool_type {
@@ -84,6 +199,11 @@ impl Tool {
     }
 }
 
+/// Tangent of half an included angle given in degrees.
+fn half_angle_tan(angle: f64) -> f64 {
+    (angle.clamp(1.0, 179.0).to_radians() / 2.0).tan()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -132,6 +252,44 @@ mod tests {
         assert!((face_mill.effective_diameter() - 40.0).abs() < 0.001);
     }
 
+    #[test]
+    fn test_drill_tip_length() {
+        let drill = Tool::drill(6.0, 90.0, false, 30.0);
+        assert!((drill.tip_length() - 3.0).abs() < 1e-9);
+        assert_eq!(drill.tip_compensation(), 0.0);
+
+        let through = Tool::drill(5.0, 118.0, true, 30.0);
+        assert!((through.tip_compensation() - 1.502).abs() < 0.001);
+        assert_eq!(Tool::default().tip_length(), 0.0);
+    }
+
+    #[test]
+    fn test_vbit_profile() {
+        // 90 degree bit: radius grows one-for-one with height.
+        let vbit = Tool::vbit(12.0, 90.0, 0.0, 10.0);
+        assert!((vbit.radius_at_height(2.0) - 2.0).abs() < 1e-9);
+        assert!((vbit.radius_at_height(20.0) - 6.0).abs() < 1e-9);
+        assert!((vbit.height_at_radius(3.0).unwrap() - 3.0).abs() < 1e-9);
+        assert_eq!(vbit.height_at_radius(7.0), None);
+
+        let flat_tip = Tool::vbit(12.0, 90.0, 1.0, 10.0);
+        assert!((flat_tip.radius_at_height(0.0) - 0.5).abs() < 1e-9);
+        assert_eq!(flat_tip.height_at_radius(0.3), Some(0.0));
+        assert!((flat_tip.height_at_radius(1.5).unwrap() - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_profile_of_round_tools() {
+        let ball = Tool::ball_end(6.0, 10.0);
+        assert!((ball.radius_at_height(3.0) - 3.0).abs() < 1e-9);
+        assert!((ball.radius_at_height(1.0) - 5f64.sqrt()).abs() < 1e-9);
+        assert!((ball.height_at_radius(5f64.sqrt()).unwrap() - 1.0).abs() < 1e-9);
+
+        let flat = Tool::default();
+        assert_eq!(flat.radius_at_height(0.0), flat.diameter / 2.0);
+        assert_eq!(flat.height_at_radius(1.0), Some(0.0));
+    }
+
     #[test]
     fn test_tool_type_default() {
         let tt = ToolType::default();
@@ -3,6 +3,34 @@
 /// Swiss-cheese layer: **Strategy selection**
 /// Extension point: implement `ToolpathStrategy` to add spiral, trochoidal,
 /// adaptive-clearing, or any custom strategy.
+pub mod adaptive;
+pub mod drill;
+mod entry;
+mod lead;
+pub mod order;
+pub mod raster;
+pub mod rest;
+mod tabs;
+pub mod vcarve;
+pub mod waterline;
+
+pub use self::adaptive::AdaptiveStrategy;
+pub use self::drill::{DrillParams, DrillStrategy};
+pub use self::entry::EntryMode;
+pub use self::lead::{Lead, LeadParams};
+pub use self::order::{order_toolpaths, rapid_distance, OrderParams, RapidReport};
+pub use self::raster::{raster_engrave, Dither, RasterParams};
+pub use self::rest::{PriorCut, RestStock};
+pub use self::tabs::TabParams;
+pub use self::vcarve::VCarveStrategy;
+pub use self::waterline::WaterlineStrategy;
+
+use self::entry::{enter, ramp_length, EntryArea};
+use self::lead::Leads;
+use self::tabs::tabbed_loop;
+
+use crate::geometry::nesting::{build_regions, segment_inside, FillRule, Region};
+use crate::geometry::offset::{offset_polygon, offset_region, JoinType};
 use crate::geometry::{Mesh, Polyline, Toolpath, Vec2};
 
 // ── Strategy trait (the "hole") ──────────────────────────────────────
@@ -104,6 +132,18 @@ pub struct CutParams {
     pub climb_cut: bool,
     /// Number of perimeter passes (default 1).
     pub perimeter_passes: u32,
+    /// How the tool descends into the material (default: plunge).
+    pub entry: EntryMode,
+    /// Steepest ramp or helix descent in degrees from horizontal.
+    pub ramp_angle: f64,
+    /// Holding tabs on closed profiles (contour, perimeter, laser cut).
+    pub tabs: TabParams,
+    /// Z of the deepest pass, which tab heights are measured from. `None`
+    /// treats every pass as the last.
+    pub final_z: Option<f64>,
+    /// Lead moves on closed contour and perimeter profiles, and cutter
+    /// compensation by the controller.
+    pub leads: LeadParams,
 }
 
 impl Default for CutParams {
@@ -120,97 +160,171 @@ impl Default for CutParams {
             cut_z: 0.0,
             climb_cut: false,
             perimeter_passes: 1,
+            entry: EntryMode::Plunge,
+            ramp_angle: 3.0,
+            tabs: TabParams::default(),
+            final_z: None,
+            leads: LeadParams::default(),
         }
     }
 }
 
 // ── Contour strategy ─────────────────────────────────────────────────
 
+/// Contour strategy profiles the outside of each closed contour, keeping
+/// the tool edge on the line. Open paths are cut on the line itself.
 pub struct ContourStrategy;
 
 impl ToolpathStrategy for ContourStrategy {
     fn generate(&self, contours: &[Polyline], params: &CutParams) -> Vec<Toolpath> {
         let mut toolpaths = Vec::new();
-        let offset = params.tool_diameter / 2.0;
+        let offset = profile_offset(params);
 
         for contour in contours {
-            let offset_pts = offset_polyline(contour, offset);
-            if offset_pts.is_empty() {
-                continue;
+            let paths = if contour.closed && offset > 0.0 {
+                offset_polygon(contour, offset, JoinType::Round)
+            } else {
+                vec![contour.clone()]
+            };
+            for path in &paths {
+                let area = if path.closed {
+                    EntryArea::Outside(&path.points)
+                } else {
+                    EntryArea::Unknown
+                };
+                if let Some(tp) = follow_path(&path.points, path.closed, area, params) {
+                    toolpaths.push(tp);
+                }
             }
+        }
+        toolpaths
+    }
+}
 
-            let mut tp = Toolpath::new();
-            // Rapid to start above first point
-            tp.rapid(offset_pts[0].x, offset_pts[0].y, params.safe_z);
-            // Plunge to cut depth
-            tp.cut(offset_pts[0].x, offset_pts[0].y, params.cut_z);
-            // Follow contour
-            for pt in &offset_pts[1..] {
-                tp.cut(pt.x, pt.y, params.cut_z);
-            }
-            // Close if needed
-            if contour.closed && !offset_pts.is_empty() {
-                tp.cut(offset_pts[0].x, offset_pts[0].y, params.cut_z);
+/// Distance from the outline to the programmed path of a profile: the
+/// tool radius, or nothing when the controller compensates.
+fn profile_offset(params: &CutParams) -> f64 {
+    if params.leads.controller_comp {
+        0.0
+    } else {
+        params.tool_diameter / 2.0
+    }
+}
+
+/// Enter at the first point, follow `pts` at cut depth (returning to the
+/// start if `closed`) and retract. Closed paths lift over holding tabs on
+/// passes below the tab top, and get the configured leads.
+fn follow_path(
+    pts: &[Vec2],
+    closed: bool,
+    area: EntryArea,
+    params: &CutParams,
+) -> Option<Toolpath> {
+    let mut tp = Toolpath::new();
+    let leads = if closed {
+        Leads::plan(pts, &area, params)
+    } else {
+        None
+    };
+    let pts = leads.as_ref().map_or(pts, |l| &l.path);
+    let first = *pts.first()?;
+    let on = match &leads {
+        Some(leads) => leads.enter(&mut tp, area, params),
+        None => {
+            enter(&mut tp, pts, closed, area, params);
+            0
+        }
+    };
+
+    let tab_top = params.final_z.unwrap_or(params.cut_z) + params.tabs.height;
+    if closed && pts.len() > 2 && params.tabs.enabled() && params.cut_z < tab_top - 1e-9 {
+        let lp = tabbed_loop(pts, &params.tabs, params.tool_diameter, ramp_length(params));
+        let mut prev = first;
+        let mut over = false;
+        for (p, tab) in lp.points {
+            if tab && !over {
+                tp.cut(prev.x, prev.y, tab_top);
+                tp.moves.last_mut().unwrap().tab = true;
+            } else if !tab && over {
+                tp.cut(prev.x, prev.y, params.cut_z);
             }
+            tp.cut(p.x, p.y, if tab { tab_top } else { params.cut_z });
+            tp.moves.last_mut().unwrap().tab = tab;
+            prev = p;
+            over = tab;
+        }
+        tp.tabs = lp.tabs;
+    } else {
+        for pt in &pts[1..] {
+            tp.cut(pt.x, pt.y, params.cut_z);
+        }
+        if closed && pts.len() > 1 {
+            tp.cut(first.x, first.y, params.cut_z);
+        }
+    }
+    match &leads {
+        Some(leads) => leads.exit(&mut tp, on, params),
+        None => {
+            let end = if closed { first } else { *pts.last().unwrap() };
             // Retract
-            tp.rapid(
-                offset_pts.last().unwrap().x,
-                offset_pts.last().unwrap().y,
-                params.safe_z,
-            );
-            toolpaths.push(tp);
+            tp.rapid(end.x, end.y, params.safe_z);
         }
-        toolpaths
     }
+    Some(tp)
 }
 
 // ── Pocket strategy (scanline fill) ──────────────────────────────────
 
+/// Pocket strategy clears the area inside each boundary with a scanline
+/// zigzag. Nested contours are grouped into regions (even-odd), so islands
+/// are left standing and the tool stays a radius clear of every wall.
 pub struct PocketStrategy;
 
 impl ToolpathStrategy for PocketStrategy {
     fn generate(&self, contours: &[Polyline], params: &CutParams) -> Vec<Toolpath> {
         let mut toolpaths = Vec::new();
 
-        for contour in contours {
-            if contour.points.len() < 3 || !contour.closed {
-                continue;
-            }
-
-            let bounds = match contour.bounds() {
+        for region in build_regions(contours, FillRule::EvenOdd) {
+            // Tool-center area: the region shrunk by the tool radius.
+            let rings = offset_region(
+                &region.rings(),
+                -params.tool_diameter / 2.0,
+                JoinType::Round,
+            );
+            let all_pts: Vec<Vec2> = rings
+                .iter()
+                .flat_map(|r| r.points.iter().copied())
+                .collect();
+            let bounds = match crate::geometry::BoundingBox2::from_points(&all_pts) {
                 Some(b) => b,
                 None => continue,
             };
 
-            let offset = params.tool_diameter / 2.0;
-            let y_min = bounds.min.y + offset;
-            let y_max = bounds.max.y - offset;
             let step = params.step_over.max(0.1);
 
             let mut tp = Toolpath::new();
-            let mut y = y_min;
+            let mut y = bounds.min.y;
             let mut forward = true;
 
-            while y <= y_max {
-                let intersections = scanline_intersect(contour, y);
-                let mut xs: Vec<f64> = intersections;
+            while y <= bounds.max.y {
+                let mut xs: Vec<f64> = rings
+                    .iter()
+                    .flat_map(|r| scanline_intersect(r, y))
+                    .collect();
                 xs.sort_by(|a, b| a.partial_cmp(b).unwrap());
 
-                // Inset X by tool radius
                 for pair in xs.chunks(2) {
                     if pair.len() < 2 {
                         continue;
                     }
-                    let x0 = pair[0] + offset;
-                    let x1 = pair[1] - offset;
+                    let (x0, x1) = (pair[0], pair[1]);
                     if x0 >= x1 {
                         continue;
                     }
                     let (start_x, end_x) = if forward { (x0, x1) } else { (x1, x0) };
 
-                    // Rapid to start
-                    tp.rapid(start_x, y, params.safe_z);
-                    tp.cut(start_x, y, params.cut_z);
+                    let scan = [Vec2::new(start_x, y), Vec2::new(end_x, y)];
+                    enter(&mut tp, &scan, false, EntryArea::Inside(&rings), params);
                     tp.cut(end_x, y, params.cut_z);
                     tp.rapid(end_x, y, params.safe_z);
                 }
@@ -226,6 +340,138 @@ impl ToolpathStrategy for PocketStrategy {
     }
 }
 
+// ── Offset pocket strategy (contour-parallel) ─────────────────────────
+
+/// Contour-parallel pocketing: clears each region with successive inward
+/// offsets of its boundary spaced `step_over` apart. Loops are cut from the
+/// centre outward so the last one is a finishing pass along the walls, and
+/// they are linked by short feed moves wherever the link stays inside the
+/// pocket; the tool only retracts to jump between separate branches.
+///
+/// With `climb_cut` the loops keep the region on their left (climb milling
+/// for a clockwise spindle); otherwise they run the other way.
+pub struct OffsetPocketStrategy;
+
+impl ToolpathStrategy for OffsetPocketStrategy {
+    fn generate(&self, contours: &[Polyline], params: &CutParams) -> Vec<Toolpath> {
+        let step = params.step_over.max(0.1);
+        let mut toolpaths = Vec::new();
+
+        for region in build_regions(contours, FillRule::EvenOdd) {
+            let boundary = offset_region(
+                &region.rings(),
+                -params.tool_diameter / 2.0,
+                JoinType::Round,
+            );
+            if boundary.is_empty() {
+                continue;
+            }
+            let loops = offset_loops_inside_out(&boundary, step);
+            let tp = link_loops(&loops, &boundary, params);
+            if !tp.moves.is_empty() {
+                toolpaths.push(tp);
+            }
+        }
+        toolpaths
+    }
+}
+
+/// Successive inward offsets of `boundary`, ordered so that every loop
+/// comes after the loops nested inside it.
+fn offset_loops_inside_out(boundary: &[Polyline], step: f64) -> Vec<Vec<Vec2>> {
+    let mut levels: Vec<Vec<Region>> = Vec::new();
+    loop {
+        let rings = if levels.is_empty() {
+            boundary.to_vec()
+        } else {
+            offset_region(boundary, -(levels.len() as f64) * step, JoinType::Round)
+        };
+        if rings.is_empty() {
+            break;
+        }
+        levels.push(build_regions(&rings, FillRule::NonZero));
+    }
+
+    let mut out = Vec::new();
+    if let Some(roots) = levels.first() {
+        for i in 0..roots.len() {
+            collect_inside_out(&levels, 0, i, &mut out);
+        }
+    }
+    out
+}
+
+/// Post-order walk of the offset tree: children (deeper levels inside
+/// this region) first, then this region's own holes and outer loop.
+fn collect_inside_out(levels: &[Vec<Region>], k: usize, i: usize, out: &mut Vec<Vec<Vec2>>) {
+    let node = &levels[k][i];
+    if let Some(next) = levels.get(k + 1) {
+        for (j, child) in next.iter().enumerate() {
+            if node.contains(child.outer.points[0]) {
+                collect_inside_out(levels, k + 1, j, out);
+            }
+        }
+    }
+    for hole in &node.holes {
+        out.push(hole.points.clone());
+    }
+    out.push(node.outer.points.clone());
+}
+
+/// Join closed loops into a single toolpath. Each loop starts at the
+/// vertex nearest the previous loop's end; the link is a feed move when it
+/// is no longer than the tool diameter and stays inside `boundary`,
+/// otherwise the tool retracts and rapids across.
+fn link_loops(loops: &[Vec<Vec2>], boundary: &[Polyline], params: &CutParams) -> Toolpath {
+    let mut tp = Toolpath::new();
+    let mut pos: Option<Vec2> = None;
+
+    for ring in loops {
+        if ring.is_empty() {
+            continue;
+        }
+        let mut ring = ring.clone();
+        if !params.climb_cut {
+            ring.reverse();
+        }
+        if let Some(p) = pos {
+            let nearest = (0..ring.len())
+                .min_by(|&a, &b| {
+                    Vec2::dist(ring[a], p)
+                        .partial_cmp(&Vec2::dist(ring[b], p))
+                        .unwrap()
+                })
+                .unwrap();
+            ring.rotate_left(nearest);
+        }
+        let start = ring[0];
+
+        match pos {
+            Some(p)
+                if Vec2::dist(p, start) <= params.tool_diameter
+                    && segment_inside(boundary, p, start) =>
+            {
+                tp.cut(start.x, start.y, params.cut_z);
+            }
+            Some(p) => {
+                tp.rapid(p.x, p.y, params.safe_z);
+                enter(&mut tp, &ring, true, EntryArea::Inside(boundary), params);
+            }
+            None => enter(&mut tp, &ring, true, EntryArea::Inside(boundary), params),
+        }
+        for pt in &ring[1..] {
+            tp.cut(pt.x, pt.y, params.cut_z);
+        }
+        tp.cut(start.x, start.y, params.cut_z);
+        pos = Some(start);
+    }
+
+    if let Some(p) = pos {
+        tp.rapid(p.x, p.y, params.safe_z);
+    }
+    tp
+}
+
 // ── Perimeter strategy (boundary follow) ──────────────────────────────
 
 /// Perimeter strategy follows the outer boundary of a contour.
@@ -247,7 +493,7 @@ impl ToolpathStrategy for PerimeterStrategy {
         });
 
         if let Some(contour) = outer {
-            let base_offset = params.tool_diameter / 2.0;
+            let base_offset = profile_offset(params);
             let num_passes = params.perimeter_passes.max(1);
 
             // Generate multiple passes from outside to inside
@@ -256,36 +502,27 @@ impl ToolpathStrategy for PerimeterStrategy {
                 // Pass 0 (outermost) uses full tool offset
                 // Innermost pass uses full tool offset + (num_passes-1) * step_over
                 let pass_offset = base_offset + (pass as f64) * params.step_over;
-                let mut offset_pts = offset_polyline(contour, pass_offset);
-                if offset_pts.is_empty() {
-                    continue;
-                }
-
-                // Climb cut reverses direction (CW for outside = CCW traverse)
-                if params.climb_cut {
-                    offset_pts.reverse();
-                }
+                let paths = if contour.closed && pass_offset > 0.0 {
+                    offset_polygon(contour, -pass_offset, JoinType::Round)
+                } else {
+                    vec![contour.clone()]
+                };
 
-                let mut tp = Toolpath::new();
-                // Rapid to start above first point
-                tp.rapid(offset_pts[0].x, offset_pts[0].y, params.safe_z);
-                // Plunge to cut depth
-                tp.cut(offset_pts[0].x, offset_pts[0].y, params.cut_z);
-                // Follow perimeter
-                for pt in &offset_pts[1..] {
-                    tp.cut(pt.x, pt.y, params.cut_z);
-                }
-                // Close path if contour is closed
-                if contour.closed && !offset_pts.is_empty() {
-                    tp.cut(offset_pts[0].x, offset_pts[0].y, params.cut_z);
+                for path in paths {
+                    let mut pts = path.points.clone();
+                    // Climb cut reverses direction (CW for outside = CCW traverse)
+                    if params.climb_cut {
+                        pts.reverse();
+                    }
+                    let area = if path.closed {
+                        EntryArea::Inside(std::slice::from_ref(&path))
+                    } else {
+                        EntryArea::Unknown
+                    };
+                    if let Some(tp) = follow_path(&pts, path.closed, area, params) {
+                        toolpaths.push(tp);
+                    }
                 }
-                // Retract
-                tp.rapid(
-                    offset_pts.last().unwrap().x,
-                    offset_pts.last().unwrap().y,
-                    params.safe_z,
-                );
-                toolpaths.push(tp);
             }
         }
         toolpaths
@@ -317,9 +554,12 @@ impl Surface3dStrategy {
     ) -> Option<(f64, f64, f64)> {
         let z = match tool_type {
             ToolType::BallEnd => project_ball_tool(mesh, x, y, tool_radius),
-            ToolType::EndMill | ToolType::FaceMill { .. } => {
-                project_flat_tool(mesh, x, y, tool_radius)
-            }
+            // Drill and V-bit points fit inside the flat disc, so the disc
+            // never lets them gouge.
+            ToolType::EndMill
+            | ToolType::FaceMill { .. }
+            | ToolType::Drill { .. }
+            | ToolType::VBit { .. } => project_flat_tool(mesh, x, y, tool_radius),
         }?;
         Some((x, y, z))
     }
@@ -580,7 +820,7 @@ impl LaserCutStrategy {
 }
 
 impl ToolpathStrategy for LaserCutStrategy {
-    fn generate(&self, contours: &[Polyline], _params: &CutParams) -> Vec<Toolpath> {
+    fn generate(&self, contours: &[Polyline], params: &CutParams) -> Vec<Toolpath> {
         let mut toolpaths = Vec::new();
 
         for contour in contours {
@@ -594,6 +834,22 @@ impl ToolpathStrategy for LaserCutStrategy {
             // Rapid to start (no Z movement for laser)
             tp.rapid(first.x, first.y, 0.0);
 
+            // Beam off over holding tabs
+            if contour.closed && contour.points.len() > 2 && params.tabs.enabled() {
+                let tabs = TabParams {
+                    height: 0.0,
+                    ..params.tabs
+                };
+                let lp = tabbed_loop(&contour.points, &tabs, 0.0, 0.0);
+                for (p, tab) in lp.points {
+                    tp.cut_with_power(p.x, p.y, 0.0, if tab { 0.0 } else { self.power });
+                    tp.moves.last_mut().unwrap().tab = tab;
+                }
+                tp.tabs = lp.tabs;
+                toolpaths.push(tp);
+                continue;
+            }
+
             // Cut along contour with power
             for pt in &contour.points[1..] {
                 tp.cut_with_power(pt.x, pt.y, 0.0, self.power);
@@ -706,57 +962,6 @@ fn scanline_intersect(poly: &Polyline, y: f64) -> Vec<f64> {
     xs
 }
 
-// ── Polyline offset (simple normal offset) ───────────────────────────
-
-fn offset_polyline(poly: &Polyline, dist: f64) -> Vec<Vec2> {
-    let pts = &poly.points;
-    let n = pts.len();
-    if n < 2 {
-        return pts.clone();
-    }
-
-    let mut result = Vec::with_capacity(n);
-    for i in 0..n {
-        let prev = if i == 0 {
-            if poly.closed {
-                pts[n - 1]
-            } else {
-                pts[0]
-            }
-        } else {
-            pts[i - 1]
-        };
-        let next = if i == n - 1 {
-            if poly.closed {
-                pts[0]
-            } else {
-                pts[n - 1]
-            }
-        } else {
-            pts[i + 1]
-        };
-
-        // Average normal of adjacent edges
-        let dx1 = pts[i].x - prev.x;
-        let dy1 = pts[i].y - prev.y;
-        let dx2 = next.x - pts[i].x;
-        let dy2 = next.y - pts[i].y;
-
-        let nx = -(dy1 + dy2);
-        let ny = dx1 + dx2;
-        let len = (nx * nx + ny * ny).sqrt();
-        if len < 1e-10 {
-            result.push(pts[i]);
-        } else {
-            result.push(Vec2::new(
-                pts[i].x + dist * nx / len,
-                pts[i].y + dist * ny / len,
-            ));
-        }
-    }
-    result
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -817,6 +1022,321 @@ mod tests {
         assert!(!toolpaths.is_empty());
     }
 
+    /// Steepest descent (degrees) of any cutting move below `top`.
+    fn steepest_descent(toolpaths: &[Toolpath], top: f64) -> f64 {
+        let mut steepest: f64 = 0.0;
+        for tp in toolpaths {
+            for w in tp.moves.windows(2) {
+                let (a, b) = (&w[0], &w[1]);
+                if b.rapid || b.z >= a.z || b.z >= top - 1e-9 {
+                    continue;
+                }
+                let run = (b.x - a.x).hypot(b.y - a.y);
+                let drop = a.z.min(top) - b.z;
+                steepest = steepest.max(drop.atan2(run).to_degrees());
+            }
+        }
+        steepest
+    }
+
+    #[test]
+    fn test_ramp_entry_applies_to_all_2d_strategies() {
+        let contours = vec![Polyline::new(
+            vec![
+                Vec2::new(0.0, 0.0),
+                Vec2::new(30.0, 0.0),
+                Vec2::new(30.0, 20.0),
+                Vec2::new(0.0, 20.0),
+            ],
+            true,
+        )];
+        let params = CutParams {
+            tool_diameter: 4.0,
+            cut_z: -1.0,
+            step_down: 1.0,
+            entry: EntryMode::Ramp,
+            ramp_angle: 5.0,
+            ..CutParams::default()
+        };
+        let strategies: Vec<Box<dyn ToolpathStrategy>> = vec![
+            Box::new(ContourStrategy),
+            Box::new(PerimeterStrategy),
+            Box::new(PocketStrategy),
+            Box::new(OffsetPocketStrategy),
+            Box::new(AdaptiveStrategy::new(0.3)),
+        ];
+        for strategy in strategies {
+            let toolpaths = strategy.generate(&contours, &params);
+            assert!(!toolpaths.is_empty());
+            assert!(steepest_descent(&toolpaths, 0.0) <= 5.0 + 1e-6);
+            // Plunge mode still drops straight down.
+            let plunged = strategy.generate(
+                &contours,
+                &CutParams {
+                    entry: EntryMode::Plunge,
+                    ..params.clone()
+                },
+            );
+            assert!(steepest_descent(&plunged, 0.0) > 89.0);
+        }
+    }
+
+    #[test]
+    fn test_tabs_lift_only_on_final_passes() {
+        let contours = vec![square()];
+        let params = CutParams {
+            tool_diameter: 2.0,
+            cut_z: -1.0,
+            final_z: Some(-3.0),
+            tabs: TabParams {
+                count: 2,
+                spacing: 0.0,
+                width: 3.0,
+                height: 1.5,
+            },
+            ..CutParams::default()
+        };
+        for strategy in [
+            Box::new(ContourStrategy) as Box<dyn ToolpathStrategy>,
+            Box::new(PerimeterStrategy),
+        ] {
+            // Above the tab top (-1.5): no tabs.
+            let upper = strategy.generate(&contours, &params);
+            assert!(upper[0].tabs.is_empty());
+            assert!(upper[0].moves.iter().all(|m| !m.tab));
+
+            let last = CutParams {
+                cut_z: -3.0,
+                ..params.clone()
+            };
+            let tp = &strategy.generate(&contours, &last)[0];
+            assert_eq!(tp.tabs.len(), 2);
+            for m in tp.moves.iter().filter(|m| !m.rapid) {
+                assert!(m.z == -3.0 || m.z == -1.5, "z {}", m.z);
+                assert_eq!(m.tab, m.z == -1.5);
+            }
+            // Each tab is cleared by its width plus the tool diameter.
+            let lifted: f64 = tp
+                .moves
+                .windows(2)
+                .filter(|w| w[0].tab && w[1].tab)
+                .map(|w| (w[1].x - w[0].x).hypot(w[1].y - w[0].y))
+                .sum();
+            assert!((lifted - 2.0 * 5.0).abs() < 1e-9, "lifted {}", lifted);
+        }
+    }
+
+    #[test]
+    fn test_helix_entry_in_offset_pocket() {
+        let contours = vec![square()];
+        let params = CutParams {
+            tool_diameter: 3.0,
+            cut_z: -2.0,
+            step_down: 1.0,
+            entry: EntryMode::Helix { diameter: 2.0 },
+            ramp_angle: 3.0,
+            ..CutParams::default()
+        };
+        let toolpaths = OffsetPocketStrategy.generate(&contours, &params);
+        assert!(steepest_descent(&toolpaths, -1.0) <= 3.0 + 1e-6);
+        // The descent stays on a circle inside the 7 mm tool-centre square.
+        let descent: Vec<_> = toolpaths[0]
+            .moves
+            .iter()
+            .skip(1)
+            .take_while(|m| m.z > -2.0)
+            .collect();
+        assert!(descent.len() > 20);
+        for m in descent {
+            assert!((1.5 - 1e-9..=8.5 + 1e-9).contains(&m.x));
+            assert!((1.5 - 1e-9..=8.5 + 1e-9).contains(&m.y));
+        }
+    }
+
+    #[test]
+    fn test_pocket_leaves_island_standing() {
+        // 30x30 pocket with a 10x10 boss in the middle.
+        let outer = Polyline::new(
+            vec![
+                Vec2::new(0.0, 0.0),
+                Vec2::new(30.0, 0.0),
+                Vec2::new(30.0, 30.0),
+                Vec2::new(0.0, 30.0),
+            ],
+            true,
+        );
+        let island = Polyline::new(
+            vec![
+                Vec2::new(10.0, 10.0),
+                Vec2::new(20.0, 10.0),
+                Vec2::new(20.0, 20.0),
+                Vec2::new(10.0, 20.0),
+            ],
+            true,
+        );
+        let params = CutParams {
+            tool_diameter: 2.0,
+            step_over: 0.5,
+            ..CutParams::default()
+        };
+        let toolpaths = PocketStrategy.generate(&[island, outer], &params);
+        assert_eq!(toolpaths.len(), 1, "boss and wall form one region");
+
+        let cuts: Vec<_> = toolpaths[0].moves.iter().filter(|m| !m.rapid).collect();
+        assert!(!cuts.is_empty());
+        for m in &cuts {
+            assert!(m.x >= 1.0 - 1e-6 && m.x <= 29.0 + 1e-6);
+            assert!(m.y >= 1.0 - 1e-6 && m.y <= 29.0 + 1e-6);
+            // Distance from the tool center to the boss must cover the radius.
+            let dx = (m.x - m.x.clamp(10.0, 20.0)).abs();
+            let dy = (m.y - m.y.clamp(10.0, 20.0)).abs();
+            let clearance = (dx * dx + dy * dy).sqrt();
+            assert!(
+                clearance >= 1.0 - 1e-6,
+                "cut at ({}, {}) gouges the boss",
+                m.x,
+                m.y
+            );
+        }
+        // Rows through the boss are split into two segments.
+        assert!(cuts.iter().any(|m| m.y > 12.0 && m.y < 18.0 && m.x > 25.0));
+    }
+
+    fn offset_pocket_params() -> CutParams {
+        CutParams {
+            tool_diameter: 2.0,
+            step_over: 0.8,
+            ..CutParams::default()
+        }
+    }
+
+    #[test]
+    fn test_offset_pocket_single_region_is_one_continuous_path() {
+        let big = Polyline::new(
+            vec![
+                Vec2::new(0.0, 0.0),
+                Vec2::new(20.0, 0.0),
+                Vec2::new(20.0, 20.0),
+                Vec2::new(0.0, 20.0),
+            ],
+            true,
+        );
+        let toolpaths = OffsetPocketStrategy.generate(&[big], &offset_pocket_params());
+        assert_eq!(toolpaths.len(), 1);
+        let rapids = toolpaths[0].moves.iter().filter(|m| m.rapid).count();
+        assert_eq!(rapids, 2, "only the initial approach and final retract");
+        let cuts: Vec<_> = toolpaths[0].moves.iter().filter(|m| !m.rapid).collect();
+        assert!(cuts.len() > 40);
+    }
+
+    #[test]
+    fn test_offset_pocket_finishes_on_the_wall() {
+        let params = offset_pocket_params();
+        let toolpaths = OffsetPocketStrategy.generate(&[square()], &params);
+        let moves = &toolpaths[0].moves;
+        let first_cut = moves.iter().find(|m| !m.rapid).unwrap();
+        // Starts near the centre, ends on the radius-offset wall loop.
+        assert!((first_cut.x - 5.0).abs() < 2.0 && (first_cut.y - 5.0).abs() < 2.0);
+        let last_cut = moves.iter().rev().find(|m| !m.rapid).unwrap();
+        let on_wall = [
+            last_cut.x - 1.0,
+            9.0 - last_cut.x,
+            last_cut.y - 1.0,
+            9.0 - last_cut.y,
+        ]
+        .iter()
+        .any(|d| d.abs() < 1e-6);
+        assert!(on_wall, "last cut ({}, {})", last_cut.x, last_cut.y);
+    }
+
+    #[test]
+    fn test_offset_pocket_climb_runs_counter_clockwise() {
+        let area_of_last_loop = |climb: bool| {
+            let params = CutParams {
+                climb_cut: climb,
+                ..offset_pocket_params()
+            };
+            let toolpaths = OffsetPocketStrategy.generate(&[square()], &params);
+            let cuts: Vec<Vec2> = toolpaths[0]
+                .moves
+                .iter()
+                .filter(|m| !m.rapid)
+                .map(|m| Vec2::new(m.x, m.y))
+                .collect();
+            // The wall loop is the tail of the path: everything at x or y 1/9.
+            let mut tail: Vec<Vec2> = cuts
+                .iter()
+                .rev()
+                .take_while(|p| {
+                    (p.x - 1.0).abs() < 1e-6
+                        || (p.x - 9.0).abs() < 1e-6
+                        || (p.y - 1.0).abs() < 1e-6
+                        || (p.y - 9.0).abs() < 1e-6
+                })
+                .copied()
+                .collect();
+            tail.reverse();
+            crate::geometry::offset::signed_area(&tail)
+        };
+        assert!(area_of_last_loop(true) > 0.0);
+        assert!(area_of_last_loop(false) < 0.0);
+    }
+
+    #[test]
+    fn test_offset_pocket_respects_island() {
+        let outer = Polyline::new(
+            vec![
+                Vec2::new(0.0, 0.0),
+                Vec2::new(30.0, 0.0),
+                Vec2::new(30.0, 30.0),
+                Vec2::new(0.0, 30.0),
+            ],
+            true,
+        );
+        let island = Polyline::new(
+            vec![
+                Vec2::new(10.0, 10.0),
+                Vec2::new(20.0, 10.0),
+                Vec2::new(20.0, 20.0),
+                Vec2::new(10.0, 20.0),
+            ],
+            true,
+        );
+        let toolpaths = OffsetPocketStrategy.generate(&[outer, island], &offset_pocket_params());
+        assert_eq!(toolpaths.len(), 1);
+        let moves = &toolpaths[0].moves;
+        for (prev, m) in moves.iter().zip(moves.iter().skip(1)) {
+            if m.rapid {
+                continue;
+            }
+            // Check both endpoints and the midpoint of every feed move.
+            for (x, y) in [(m.x, m.y), ((m.x + prev.x) / 2.0, (m.y + prev.y) / 2.0)] {
+                let dx = (x - x.clamp(10.0, 20.0)).abs();
+                let dy = (y - y.clamp(10.0, 20.0)).abs();
+                let clearance = (dx * dx + dy * dy).sqrt();
+                assert!(clearance >= 1.0 - 1e-6, "({}, {}) gouges", x, y);
+                assert!((1.0 - 1e-6..=29.0 + 1e-6).contains(&x));
+                assert!((1.0 - 1e-6..=29.0 + 1e-6).contains(&y));
+            }
+        }
+    }
+
+    #[test]
+    fn test_offset_pocket_skips_region_smaller_than_tool() {
+        let tiny = Polyline::new(
+            vec![
+                Vec2::new(0.0, 0.0),
+                Vec2::new(1.0, 0.0),
+                Vec2::new(1.0, 1.0),
+                Vec2::new(0.0, 1.0),
+            ],
+            true,
+        );
+        assert!(OffsetPocketStrategy
+            .generate(&[tiny], &offset_pocket_params())
+            .is_empty());
+    }
+
     #[test]
     fn test_scanline() {
         let sq = square();
@@ -1672,6 +2192,31 @@ mod tests {
         assert!((last.y - first.y).abs() < 0.01);
     }
 
+    #[test]
+    fn test_laser_cut_tabs_turn_beam_off() {
+        let contours = vec![square()];
+        let params = CutParams {
+            tabs: TabParams {
+                count: 2,
+                width: 2.0,
+                ..TabParams::default()
+            },
+            ..CutParams::default()
+        };
+        let toolpaths = LaserCutStrategy::new(80.0).generate(&contours, &params);
+        let tp = &toolpaths[0];
+        assert_eq!(tp.tabs.len(), 2);
+        let mut off = 0.0;
+        for w in tp.moves.windows(2) {
+            let run = (w[1].x - w[0].x).hypot(w[1].y - w[0].y);
+            assert_eq!(w[1].tab, w[1].power == Some(0.0));
+            if w[1].tab {
+                off += run;
+            }
+        }
+        assert!((off - 4.0).abs() < 1e-9, "beam off for {} mm", off);
+    }
+
     // ── Laser engrave strategy tests ──────────────────────────────────
 
     #[test]
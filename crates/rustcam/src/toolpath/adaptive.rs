@@ -0,0 +1,687 @@
+//! Adaptive clearing with bounded radial engagement.
+//!
+//! Swiss-cheese layer: **Strategy selection**
+//! Extension point: swap `start_point` for a smarter entry location, or
+//! replace `link` to use lower lifts between cuts.
+//!
+//! The cleared area grows pass by pass. Each pass is the boundary of the
+//! previous cleared area offset outward by the engagement step and clipped
+//! to the area the tool centre may reach. Along a straight frontier the
+//! step is the radial engagement, but where the frontier wraps into an
+//! inside corner or closes a channel the tool bites more, so each pass is
+//! measured before it is kept: at points along its new chains, the
+//! material left inside the tool disc is converted to the stepover of a
+//! straight cut that leaves the same area, and the step is shortened until
+//! that stays within the configured maximum. Only the parts of each pass
+//! that touch new material are cut. In channels barely wider than the tool
+//! those parts shrink to short loops across the channel, which gives
+//! trochoidal motion without a separate code path.
+
+use crate::geometry::boolean;
+use crate::geometry::nesting::{build_regions, segment_inside, winding_number, FillRule};
+use crate::geometry::offset::{offset_region, signed_area, JoinType};
+use crate::geometry::{Polyline, Toolpath, Vec2};
+
+use super::entry::{enter, EntryArea};
+use super::{CutParams, ToolpathStrategy};
+
+/// How far one pass may be shortened below the nominal step.
+const MIN_STEP_FRACTION: f64 = 1.0 / 16.0;
+/// Attempts at a shorter step before a pass is taken as it is.
+const MAX_STEP_TRIES: usize = 6;
+/// Directions sampled around the tool when measuring engagement.
+const ENGAGEMENT_RAYS: usize = 64;
+
+/// Adaptive clearing strategy.
+///
+/// `max_engagement` is the largest radial depth of cut as a fraction of
+/// the tool diameter (clamped to 0.02–0.9).
+pub struct AdaptiveStrategy {
+    pub max_engagement: f64,
+}
+
+impl AdaptiveStrategy {
+    pub fn new(max_engagement: f64) -> Self {
+        Self { max_engagement }
+    }
+
+    /// Radial step in mm for the given tool diameter.
+    fn step(&self, tool_diameter: f64) -> f64 {
+        (self.max_engagement.clamp(0.02, 0.9) * tool_diameter).max(0.01)
+    }
+}
+
+impl ToolpathStrategy for AdaptiveStrategy {
+    fn generate(&self, contours: &[Polyline], params: &CutParams) -> Vec<Toolpath> {
+        let step = self.step(params.tool_diameter);
+        let mut toolpaths = Vec::new();
+
+        for region in build_regions(contours, FillRule::EvenOdd) {
+            let reachable = offset_region(
+                &region.rings(),
+                -params.tool_diameter / 2.0,
+                JoinType::Round,
+            );
+            // The tool-centre area may have pinched into separate pieces.
+            for area in build_regions(&reachable, FillRule::NonZero) {
+                let rings = area.rings();
+                let passes = clearing_passes(&rings, step, params.tool_diameter / 2.0);
+                let tp = emit_passes(&passes, &rings, step, params);
+                if !tp.moves.is_empty() {
+                    toolpaths.push(tp);
+                }
+            }
+        }
+        toolpaths
+    }
+}
+
+/// One growth step: the open (or closed) chains that cut new material and
+/// the cleared tool-centre area once they are done.
+struct Pass {
+    chains: Vec<Chain>,
+    cleared: Vec<Polyline>,
+}
+
+struct Chain {
+    points: Vec<Vec2>,
+    closed: bool,
+}
+
+/// Where the tool enters, plus the growth passes from there outward.
+struct Clearing {
+    start: Vec2,
+    passes: Vec<Pass>,
+}
+
+/// Grow the cleared area from the deepest point of `rings` until it fills
+/// them, keeping the engagement of a tool of `radius` within `step` after
+/// the first pass.
+fn clearing_passes(rings: &[Polyline], step: f64, radius: f64) -> Clearing {
+    let start = start_point(rings, step);
+    let mut cleared = vec![circle(start, step * 0.05)];
+    let mut cleared_area = boolean::area(&cleared);
+    let mut passes = Vec::new();
+
+    let extent = rings
+        .iter()
+        .filter_map(|r| r.bounds())
+        .map(|b| Vec2::dist(b.min, b.max))
+        .fold(0.0, f64::max);
+    let min_step = step * MIN_STEP_FRACTION;
+    let max_passes = (extent / min_step).ceil() as usize * 4 + 16;
+
+    let mut d = step;
+    for _ in 0..max_passes {
+        let mut tries = 0;
+        let (next, chains) = loop {
+            let grown = offset_region(&cleared, d, JoinType::Round);
+            let next = boolean::intersection(&grown, rings);
+            let chains = frontier_chains(&next, &cleared, d);
+            // The first pass is the entry circle; it slots by nature.
+            if passes.is_empty() || tries == MAX_STEP_TRIES || d <= min_step {
+                break (next, chains);
+            }
+            let worst = chains
+                .iter()
+                .flat_map(|c| sample_chain(c, step * 0.5))
+                .map(|p| engagement(p, &cleared, radius))
+                .fold(0.0, f64::max);
+            if worst <= step {
+                break (next, chains);
+            }
+            // Engagement shrinks about in proportion to the step.
+            d = (d * (step / worst).clamp(0.3, 0.9)).max(min_step);
+            tries += 1;
+        };
+        // Let the step recover once the tight spot is passed.
+        d = (d * 1.5).min(step);
+        let next_area = boolean::area(&next);
+        if next.is_empty() || next_area <= cleared_area + 1e-9 {
+            break;
+        }
+        cleared = next;
+        cleared_area = next_area;
+        if chains.is_empty() {
+            break;
+        }
+        passes.push(Pass {
+            chains,
+            cleared: cleared.clone(),
+        });
+    }
+    Clearing { start, passes }
+}
+
+/// Split the boundary of `next` into the stretches that lie outside
+/// `cleared`; stretches along walls that were already cut are dropped.
+fn frontier_chains(next: &[Polyline], cleared: &[Polyline], step: f64) -> Vec<Chain> {
+    let probe = step * 1e-3;
+    let is_cleared = |p: Vec2| -> bool {
+        cleared
+            .iter()
+            .map(|r| winding_number(&r.points, p))
+            .sum::<i32>()
+            > 0
+    };
+
+    let mut chains = Vec::new();
+    for ring in next {
+        // Long edges along a wall can have a cleared midpoint but uncut
+        // corners at their ends, so test short pieces instead.
+        let pts = &subdivide(&ring.points, step * 0.25);
+        let n = pts.len();
+        if n < 2 {
+            continue;
+        }
+        // An edge is new if a point just inside it (region on the left)
+        // has not been cleared yet.
+        let needed: Vec<bool> = (0..n)
+            .map(|i| {
+                let (a, b) = (pts[i], pts[(i + 1) % n]);
+                let len = Vec2::dist(a, b).max(1e-12);
+                let mid = Vec2::new((a.x + b.x) / 2.0, (a.y + b.y) / 2.0);
+                let inner = Vec2::new(
+                    mid.x - (b.y - a.y) / len * probe,
+                    mid.y + (b.x - a.x) / len * probe,
+                );
+                !is_cleared(inner)
+            })
+            .collect();
+
+        if needed.iter().all(|&x| x) {
+            chains.push(Chain {
+                points: ring.points.clone(),
+                closed: true,
+            });
+            continue;
+        }
+        let Some(first_gap) = needed.iter().position(|&x| !x) else {
+            continue;
+        };
+        // Walk once around the ring starting just after a gap, so every
+        // run of needed edges is contiguous.
+        let mut run: Vec<Vec2> = Vec::new();
+        for k in 1..=n {
+            let i = (first_gap + k) % n;
+            if needed[i] {
+                if run.is_empty() {
+                    run.push(pts[i]);
+                }
+                run.push(pts[(i + 1) % n]);
+            } else if !run.is_empty() {
+                chains.push(Chain {
+                    points: drop_collinear(std::mem::take(&mut run)),
+                    closed: false,
+                });
+            }
+        }
+        if !run.is_empty() {
+            chains.push(Chain {
+                points: drop_collinear(run),
+                closed: false,
+            });
+        }
+    }
+    chains
+}
+
+/// Points along a chain no more than `spacing` apart, vertices included.
+fn sample_chain(chain: &Chain, spacing: f64) -> Vec<Vec2> {
+    let pts = &chain.points;
+    let mut out = Vec::new();
+    let edges = if chain.closed {
+        pts.len()
+    } else {
+        pts.len().saturating_sub(1)
+    };
+    for i in 0..edges {
+        let (a, b) = (pts[i], pts[(i + 1) % pts.len()]);
+        let pieces = (Vec2::dist(a, b) / spacing).ceil().max(1.0) as usize;
+        for k in 0..pieces {
+            let t = k as f64 / pieces as f64;
+            out.push(Vec2::new(a.x + (b.x - a.x) * t, a.y + (b.y - a.y) * t));
+        }
+    }
+    if !chain.closed {
+        out.extend(pts.last());
+    }
+    out
+}
+
+/// Radial engagement of a tool of `radius` centred at `p` when the tool
+/// centre has already swept `cleared`: the stepover of a straight cut that
+/// leaves as much uncut material inside the tool disc.
+fn engagement(p: Vec2, cleared: &[Polyline], radius: f64) -> f64 {
+    let edges: Vec<(Vec2, Vec2)> = cleared
+        .iter()
+        .flat_map(|r| {
+            let n = r.points.len();
+            (0..n).map(move |i| (r.points[i], r.points[(i + 1) % n]))
+        })
+        .filter(|&(a, b)| segment_dist(p, a, b) <= 2.0 * radius)
+        .collect();
+    // A point within one radius of the swept centres has been cut; inside
+    // `cleared` counts too, since the way there from `p` crosses an edge.
+    let cut = |q: Vec2| edges.iter().any(|&(a, b)| segment_dist(q, a, b) <= radius);
+    let along = |u: Vec2, r: f64| Vec2::new(p.x + u.x * r, p.y + u.y * r);
+
+    // Uncut area in polar form. Each ray changes state once: uncut out to
+    // the rim from where it leaves the cut area, or the other way round
+    // when the centre itself is still uncut.
+    let centre_cut = cut(p);
+    let mut uncut = 0.0;
+    for i in 0..ENGAGEMENT_RAYS {
+        let a = std::f64::consts::TAU * (i as f64 + 0.5) / ENGAGEMENT_RAYS as f64;
+        let u = Vec2::new(a.cos(), a.sin());
+        let rim_cut = cut(along(u, radius));
+        if rim_cut == centre_cut {
+            if !rim_cut {
+                uncut += radius * radius / 2.0;
+            }
+            continue;
+        }
+        let (mut lo, mut hi) = (0.0, radius);
+        for _ in 0..12 {
+            let mid = (lo + hi) / 2.0;
+            if cut(along(u, mid)) == centre_cut {
+                lo = mid;
+            } else {
+                hi = mid;
+            }
+        }
+        let r = (lo + hi) / 2.0;
+        uncut += if centre_cut {
+            (radius * radius - r * r) / 2.0
+        } else {
+            r * r / 2.0
+        };
+    }
+    uncut *= std::f64::consts::TAU / ENGAGEMENT_RAYS as f64;
+
+    // Area of the circular segment a straight cut of stepover `a` leaves.
+    let segment = |a: f64| {
+        let h = radius - a;
+        radius * radius * (h / radius).clamp(-1.0, 1.0).acos()
+            - h * (radius * radius - h * h).max(0.0).sqrt()
+    };
+    let (mut lo, mut hi) = (0.0, 2.0 * radius);
+    for _ in 0..40 {
+        let mid = (lo + hi) / 2.0;
+        if segment(mid) < uncut {
+            lo = mid;
+        } else {
+            hi = mid;
+        }
+    }
+    (lo + hi) / 2.0
+}
+
+fn segment_dist(p: Vec2, a: Vec2, b: Vec2) -> f64 {
+    let (dx, dy) = (b.x - a.x, b.y - a.y);
+    let len2 = dx * dx + dy * dy;
+    let t = if len2 > 0.0 {
+        (((p.x - a.x) * dx + (p.y - a.y) * dy) / len2).clamp(0.0, 1.0)
+    } else {
+        0.0
+    };
+    Vec2::dist(p, Vec2::new(a.x + dx * t, a.y + dy * t))
+}
+
+/// Turn the passes into one toolpath, linking chains with feed moves when
+/// that is safe and retracting otherwise.
+fn emit_passes(clearing: &Clearing, rings: &[Polyline], step: f64, params: &CutParams) -> Toolpath {
+    let mut tp = Toolpath::new();
+    if clearing.passes.is_empty() {
+        return tp;
+    }
+    let start = clearing.start;
+    let mut entered = false;
+    let mut pos = start;
+    let mut cleared: Vec<Polyline> = Vec::new();
+    for pass in &clearing.passes {
+        let mut remaining: Vec<&Chain> = pass.chains.iter().collect();
+        while !remaining.is_empty() {
+            // Nearest chain next; closed chains can start anywhere.
+            let (idx, mut points) = remaining
+                .iter()
+                .enumerate()
+                .map(|(i, c)| (i, oriented_chain(c, pos, params.climb_cut)))
+                .min_by(|a, b| {
+                    Vec2::dist(a.1[0], pos)
+                        .partial_cmp(&Vec2::dist(b.1[0], pos))
+                        .unwrap()
+                })
+                .unwrap();
+            let chain = remaining.remove(idx);
+            if chain.closed {
+                points.push(points[0]);
+            }
+            if entered {
+                link(&mut tp, pos, &points, &cleared, rings, step, params);
+            } else {
+                // Enter at the start point, heading for the first chain.
+                let mut path = vec![start];
+                path.extend_from_slice(&points);
+                enter(&mut tp, &path, false, EntryArea::Inside(rings), params);
+                tp.cut(points[0].x, points[0].y, params.cut_z);
+                entered = true;
+            }
+            for p in &points[1..] {
+                tp.cut(p.x, p.y, params.cut_z);
+            }
+            pos = *points.last().unwrap();
+        }
+        cleared = pass.cleared.clone();
+    }
+    tp.rapid(pos.x, pos.y, params.safe_z);
+    tp
+}
+
+/// Chain points in cutting order. The ring direction (area on the left,
+/// material on the right) is climb milling; conventional reverses it.
+/// Closed chains are rotated to start at the vertex nearest `pos`.
+fn oriented_chain(chain: &Chain, pos: Vec2, climb: bool) -> Vec<Vec2> {
+    let mut pts = chain.points.clone();
+    if !climb {
+        pts.reverse();
+    }
+    if chain.closed {
+        let nearest = (0..pts.len())
+            .min_by(|&a, &b| {
+                Vec2::dist(pts[a], pos)
+                    .partial_cmp(&Vec2::dist(pts[b], pos))
+                    .unwrap()
+            })
+            .unwrap_or(0);
+        pts.rotate_left(nearest);
+    }
+    pts
+}
+
+/// Move from `from` to the start of `path` at cut depth. Stay down when
+/// the straight line runs through cleared area, or is a short step inside
+/// the reachable area; otherwise lift to safe Z and enter again.
+fn link(
+    tp: &mut Toolpath,
+    from: Vec2,
+    path: &[Vec2],
+    cleared: &[Polyline],
+    rings: &[Polyline],
+    step: f64,
+    params: &CutParams,
+) {
+    let to = path[0];
+    let dist = Vec2::dist(from, to);
+    if dist < 1e-9 {
+        return;
+    }
+    let through_cleared = !cleared.is_empty() && segment_inside(cleared, from, to);
+    let short_step = dist <= step * 1.5 && segment_inside(rings, from, to);
+    if through_cleared || short_step {
+        tp.cut(to.x, to.y, params.cut_z);
+    } else {
+        tp.rapid(from.x, from.y, params.safe_z);
+        enter(tp, path, false, EntryArea::Inside(rings), params);
+    }
+}
+
+/// Entry point: roughly the point farthest from every wall, found by
+/// shrinking the area until it disappears.
+fn start_point(rings: &[Polyline], step: f64) -> Vec2 {
+    let mut last = rings.to_vec();
+    let mut depth = step;
+    loop {
+        let shrunk = offset_region(rings, -depth, JoinType::Round);
+        if shrunk.is_empty() {
+            break;
+        }
+        last = shrunk;
+        depth += step;
+    }
+    let ring = last
+        .iter()
+        .max_by(|a, b| {
+            signed_area(&a.points)
+                .partial_cmp(&signed_area(&b.points))
+                .unwrap()
+        })
+        .unwrap_or(&rings[0]);
+    let n = ring.points.len() as f64;
+    let centroid = Vec2::new(
+        ring.points.iter().map(|p| p.x).sum::<f64>() / n,
+        ring.points.iter().map(|p| p.y).sum::<f64>() / n,
+    );
+    let inside: i32 = rings
+        .iter()
+        .map(|r| winding_number(&r.points, centroid))
+        .sum();
+    if inside > 0 {
+        centroid
+    } else {
+        ring.points[0]
+    }
+}
+
+/// Closed ring with extra points so no edge is longer than `max_len`.
+fn subdivide(ring: &[Vec2], max_len: f64) -> Vec<Vec2> {
+    let n = ring.len();
+    let mut out = Vec::with_capacity(n);
+    for i in 0..n {
+        let (a, b) = (ring[i], ring[(i + 1) % n]);
+        let pieces = (Vec2::dist(a, b) / max_len).ceil().max(1.0) as usize;
+        for k in 0..pieces {
+            let t = k as f64 / pieces as f64;
+            out.push(Vec2::new(a.x + (b.x - a.x) * t, a.y + (b.y - a.y) * t));
+        }
+    }
+    out
+}
+
+/// Remove the points `subdivide` added where they lie on a straight run.
+fn drop_collinear(points: Vec<Vec2>) -> Vec<Vec2> {
+    let mut out: Vec<Vec2> = Vec::with_capacity(points.len());
+    for p in points {
+        if out.len() >= 2 {
+            let (a, b) = (out[out.len() - 2], out[out.len() - 1]);
+            let cross = (b.x - a.x) * (p.y - a.y) - (b.y - a.y) * (p.x - a.x);
+            let dot = (b.x - a.x) * (p.x - b.x) + (b.y - a.y) * (p.y - b.y);
+            if cross.abs() <= 1e-9 * Vec2::dist(a, p).max(1.0) && dot > 0.0 {
+                out.pop();
+            }
+        }
+        out.push(p);
+    }
+    out
+}
+
+fn circle(c: Vec2, r: f64) -> Polyline {
+    let pts = (0..16)
+        .map(|i| {
+            let a = std::f64::consts::TAU * i as f64 / 16.0;
+            Vec2::new(c.x + r * a.cos(), c.y + r * a.sin())
+        })
+        .collect();
+    Polyline::new(pts, true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geometry::offset::ARC_TOLERANCE;
+
+    fn rect(x0: f64, y0: f64, x1: f64, y1: f64) -> Polyline {
+        Polyline::new(
+            vec![
+                Vec2::new(x0, y0),
+                Vec2::new(x1, y0),
+                Vec2::new(x1, y1),
+                Vec2::new(x0, y1),
+            ],
+            true,
+        )
+    }
+
+    fn params() -> CutParams {
+        CutParams {
+            tool_diameter: 4.0,
+            ..CutParams::default()
+        }
+    }
+
+    fn dist_to_rings(rings: &[Polyline], p: Vec2) -> f64 {
+        let mut best = f64::INFINITY;
+        for r in rings {
+            let n = r.points.len();
+            for i in 0..n {
+                let (a, b) = (r.points[i], r.points[(i + 1) % n]);
+                let (dx, dy) = (b.x - a.x, b.y - a.y);
+                let t =
+                    (((p.x - a.x) * dx + (p.y - a.y) * dy) / (dx * dx + dy * dy)).clamp(0.0, 1.0);
+                best = best.min(Vec2::dist(p, Vec2::new(a.x + t * dx, a.y + t * dy)));
+            }
+        }
+        best
+    }
+
+    /// Every new cut point is within one step of the previous cleared area,
+    /// and a 4 mm tool never engages more than `step` there.
+    fn assert_engagement_bounded(rings: &[Polyline], step: f64) -> Clearing {
+        let clearing = clearing_passes(rings, step, 2.0);
+        assert!(!clearing.passes.is_empty());
+        for k in 1..clearing.passes.len() {
+            let before = &clearing.passes[k - 1].cleared;
+            for chain in &clearing.passes[k].chains {
+                for p in sample_chain(chain, step * 0.5) {
+                    let d = dist_to_rings(before, p);
+                    assert!(
+                        d <= step + ARC_TOLERANCE + 1e-6,
+                        "pass {} point {:?} is {} away",
+                        k,
+                        p,
+                        d
+                    );
+                    let e = engagement(p, before, 2.0);
+                    assert!(e <= step, "pass {} point {:?} engages {}", k, p, e);
+                }
+            }
+        }
+        clearing
+    }
+
+    #[test]
+    fn clears_square_pocket_completely() {
+        let reachable = offset_region(&[rect(0.0, 0.0, 20.0, 20.0)], -2.0, JoinType::Round);
+        let clearing = assert_engagement_bounded(&reachable, 0.8);
+        let last = &clearing.passes.last().unwrap().cleared;
+        assert!((boolean::area(last) - boolean::area(&reachable)).abs() < 1e-3);
+    }
+
+    #[test]
+    fn start_point_is_pocket_centre() {
+        let reachable = offset_region(&[rect(0.0, 0.0, 20.0, 10.0)], -2.0, JoinType::Round);
+        let p = start_point(&reachable, 0.5);
+        assert!((p.y - 5.0).abs() < 0.5, "{:?}", p);
+    }
+
+    #[test]
+    fn narrow_slot_is_cleared_with_bounded_engagement() {
+        // Slot 1.5 tool diameters wide: the tool cannot orbit freely.
+        let slot = rect(0.0, 0.0, 40.0, 6.0);
+        let reachable = offset_region(&[slot], -2.0, JoinType::Round);
+        let clearing = assert_engagement_bounded(&reachable, 0.6);
+        assert!(clearing.passes.len() > 20, "slot needs many short loops");
+        let last = &clearing.passes.last().unwrap().cleared;
+        assert!((boolean::area(last) - boolean::area(&reachable)).abs() < 1e-3);
+    }
+
+    #[test]
+    fn sharp_inside_corner_keeps_engagement_bounded() {
+        // A 30° wedge: the tool is driven into its point.
+        let wedge = Polyline::new(
+            vec![
+                Vec2::new(0.0, 0.0),
+                Vec2::new(40.0, -40.0 * 15f64.to_radians().tan()),
+                Vec2::new(40.0, 40.0 * 15f64.to_radians().tan()),
+            ],
+            true,
+        );
+        let reachable = offset_region(&[wedge], -2.0, JoinType::Round);
+        let clearing = assert_engagement_bounded(&reachable, 0.8);
+        let last = &clearing.passes.last().unwrap().cleared;
+        assert!((boolean::area(last) - boolean::area(&reachable)).abs() < 1e-3);
+    }
+
+    #[test]
+    fn engagement_of_a_straight_cut_is_its_stepover() {
+        let cleared = [rect(-20.0, -20.0, 20.0, 0.0)];
+        for stepover in [0.2, 0.8, 2.0, 3.0] {
+            let e = engagement(Vec2::new(0.0, stepover), &cleared, 2.0);
+            assert!((e - stepover).abs() < 0.03, "{} vs {}", e, stepover);
+        }
+        // Slotting from nothing cut at all engages the full diameter.
+        assert!((engagement(Vec2::new(50.0, 50.0), &cleared, 2.0) - 4.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn generate_stays_inside_wall_and_island() {
+        let outer = rect(0.0, 0.0, 30.0, 30.0);
+        let island = rect(12.0, 12.0, 18.0, 18.0);
+        let toolpaths = AdaptiveStrategy::new(0.25).generate(&[outer, island], &params());
+        assert_eq!(toolpaths.len(), 1);
+        let moves = &toolpaths[0].moves;
+        for (prev, m) in moves.iter().zip(moves.iter().skip(1)) {
+            if m.rapid {
+                continue;
+            }
+            for (x, y) in [(m.x, m.y), ((m.x + prev.x) / 2.0, (m.y + prev.y) / 2.0)] {
+                assert!((2.0 - 1e-6..=28.0 + 1e-6).contains(&x), "x {}", x);
+                assert!((2.0 - 1e-6..=28.0 + 1e-6).contains(&y), "y {}", y);
+                let dx = (x - x.clamp(12.0, 18.0)).abs();
+                let dy = (y - y.clamp(12.0, 18.0)).abs();
+                assert!(
+                    (dx * dx + dy * dy).sqrt() >= 2.0 - 1e-6,
+                    "({}, {}) hits island",
+                    x,
+                    y
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn climb_and_conventional_run_opposite_ways() {
+        let square = rect(0.0, 0.0, 20.0, 20.0);
+        let climb = CutParams {
+            climb_cut: true,
+            ..params()
+        };
+        let a = AdaptiveStrategy::new(0.3).generate(std::slice::from_ref(&square), &climb);
+        let b = AdaptiveStrategy::new(0.3).generate(&[square], &params());
+        let turn = |tp: &Toolpath| {
+            let pts: Vec<Vec2> = tp
+                .moves
+                .iter()
+                .filter(|m| !m.rapid)
+                .map(|m| Vec2::new(m.x, m.y))
+                .collect();
+            signed_area(&pts)
+        };
+        assert!(turn(&a[0]) > 0.0);
+        assert!(turn(&b[0]) < 0.0);
+    }
+
+    #[test]
+    fn pocket_smaller_than_tool_is_skipped() {
+        let tiny = rect(0.0, 0.0, 3.0, 3.0);
+        assert!(AdaptiveStrategy::new(0.2)
+            .generate(&[tiny], &params())
+            .is_empty());
+    }
+
+    #[test]
+    fn engagement_fraction_is_clamped() {
+        assert!((AdaptiveStrategy::new(5.0).step(4.0) - 3.6).abs() < 1e-9);
+        assert!((AdaptiveStrategy::new(0.0).step(4.0) - 0.08).abs() < 1e-9);
+    }
+}
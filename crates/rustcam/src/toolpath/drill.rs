@@ -0,0 +1,289 @@
+//! Drilling holes at circles and points.
+//!
+//! Swiss-cheese layer: **Strategy selection**
+//! Extension point: replace `order_holes` with a better tour, or teach
+//! `find_holes` other hole markers.
+//!
+//! Small circles in the input are holes to drill rather than outlines to
+//! profile: every closed contour whose points lie on a circle no larger
+//! than the threshold becomes a hole at its centre, as does any contour
+//! that collapses to a single point (a sketch point). Holes are visited in
+//! nearest-neighbour order from the origin. Each hole is one toolpath that
+//! carries its canned cycle and spells the cycle out as plain moves.
+
+use crate::geometry::{DrillCycle, Polyline, Toolpath, Vec2};
+
+use super::{CutParams, ToolpathStrategy};
+
+/// Fewest points a contour needs to count as a circle; a square's four
+/// corners also lie on one.
+const MIN_CIRCLE_POINTS: usize = 12;
+
+/// Largest spread of the points' distances from the centre, relative to
+/// the radius.
+const ROUNDNESS: f64 = 0.02;
+
+/// Holes closer than this are the same hole (concentric circles, a point
+/// at a circle's centre).
+const SAME_HOLE: f64 = 0.01;
+
+/// How far above the previous peck depth the drill slows back to feed.
+const PECK_CLEARANCE: f64 = 0.5;
+
+/// Hole selection and cycle settings.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DrillParams {
+    /// Largest circle diameter treated as a hole.
+    pub max_diameter: f64,
+    /// Z the drill rapids down to above each hole (R plane).
+    pub retract_z: f64,
+    /// Depth per peck; 0 drills in one feed.
+    pub peck: f64,
+    /// Dwell at the bottom in seconds.
+    pub dwell: f64,
+}
+
+impl Default for DrillParams {
+    fn default() -> Self {
+        Self {
+            max_diameter: 10.0,
+            retract_z: 1.0,
+            peck: 0.0,
+            dwell: 0.0,
+        }
+    }
+}
+
+/// Drills every hole in the input to `cut_z`, deepened by the drill's tip
+/// compensation. Contours that are not holes are ignored.
+pub struct DrillStrategy {
+    pub params: DrillParams,
+}
+
+impl DrillStrategy {
+    pub fn new(params: DrillParams) -> Self {
+        Self { params }
+    }
+}
+
+impl ToolpathStrategy for DrillStrategy {
+    fn generate(&self, contours: &[Polyline], params: &CutParams) -> Vec<Toolpath> {
+        let holes = order_holes(
+            &find_holes(contours, self.params.max_diameter),
+            Vec2::new(0.0, 0.0),
+        );
+        let z = params.cut_z - params.tool.tip_compensation();
+        let retract_z = self.params.retract_z.min(params.safe_z);
+        holes
+            .into_iter()
+            .map(|h| {
+                drill_hole(
+                    DrillCycle {
+                        x: h.x,
+                        y: h.y,
+                        z,
+                        retract_z,
+                        peck: self.params.peck.max(0.0),
+                        dwell: self.params.dwell.max(0.0),
+                    },
+                    params.safe_z,
+                )
+            })
+            .collect()
+    }
+}
+
+/// Centres of the holes in `contours`: circles up to `max_diameter` across
+/// and single points, without duplicates.
+pub fn find_holes(contours: &[Polyline], max_diameter: f64) -> Vec<Vec2> {
+    let mut holes: Vec<Vec2> = Vec::new();
+    for c in contours {
+        let Some((centre, radius)) = circle_of(c) else {
+            continue;
+        };
+        if 2.0 * radius > max_diameter + 1e-9 {
+            continue;
+        }
+        if holes.iter().all(|h| Vec2::dist(*h, centre) > SAME_HOLE) {
+            holes.push(centre);
+        }
+    }
+    holes
+}
+
+/// Centre and radius of a contour that is a circle or a single point.
+fn circle_of(c: &Polyline) -> Option<(Vec2, f64)> {
+    let b = c.bounds()?;
+    let centre = Vec2::new((b.min.x + b.max.x) / 2.0, (b.min.y + b.max.y) / 2.0);
+    if Vec2::dist(b.min, b.max) < SAME_HOLE {
+        return Some((centre, 0.0));
+    }
+    if !c.closed || c.points.len() < MIN_CIRCLE_POINTS {
+        return None;
+    }
+    let dists: Vec<f64> = c.points.iter().map(|p| Vec2::dist(*p, centre)).collect();
+    let radius = dists.iter().sum::<f64>() / dists.len() as f64;
+    let round = dists
+        .iter()
+        .all(|d| (d - radius).abs() <= ROUNDNESS * radius);
+    round.then_some((centre, radius))
+}
+
+/// Visit `holes` in nearest-neighbour order starting from `start`.
+pub fn order_holes(holes: &[Vec2], start: Vec2) -> Vec<Vec2> {
+    let mut left = holes.to_vec();
+    let mut out = Vec::with_capacity(holes.len());
+    let mut at = start;
+    while !left.is_empty() {
+        let (i, _) = left
+            .iter()
+            .enumerate()
+            .map(|(i, h)| (i, Vec2::dist(at, *h)))
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .unwrap();
+        at = left.swap_remove(i);
+        out.push(at);
+    }
+    out
+}
+
+/// The moves of one canned cycle, starting and ending at `safe_z`.
+fn drill_hole(cycle: DrillCycle, safe_z: f64) -> Toolpath {
+    let DrillCycle { x, y, z, .. } = cycle;
+    let r = cycle.retract_z;
+    let mut tp = Toolpath::new();
+    tp.rapid(x, y, safe_z);
+    tp.rapid(x, y, r);
+    if cycle.peck > 0.0 {
+        let mut depth = r;
+        while depth > z {
+            if depth < r {
+                tp.rapid(x, y, (depth + PECK_CLEARANCE).min(r));
+            }
+            depth = (depth - cycle.peck).max(z);
+            tp.cut(x, y, depth);
+            if depth > z {
+                tp.rapid(x, y, r);
+            }
+        }
+    } else {
+        tp.cut(x, y, z);
+    }
+    tp.rapid(x, y, safe_z);
+    tp.drill = Some(cycle);
+    tp
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tool::Tool;
+
+    fn circle(cx: f64, cy: f64, r: f64, n: usize) -> Polyline {
+        let pts = (0..n)
+            .map(|i| {
+                let a = 2.0 * std::f64::consts::PI * i as f64 / n as f64;
+                Vec2::new(cx + r * a.cos(), cy + r * a.sin())
+            })
+            .collect();
+        Polyline::new(pts, true)
+    }
+
+    fn rect(x0: f64, y0: f64, x1: f64, y1: f64) -> Polyline {
+        Polyline::new(
+            vec![
+                Vec2::new(x0, y0),
+                Vec2::new(x1, y0),
+                Vec2::new(x1, y1),
+                Vec2::new(x0, y1),
+            ],
+            true,
+        )
+    }
+
+    #[test]
+    fn finds_small_circles_and_points() {
+        let contours = vec![
+            rect(0.0, 0.0, 100.0, 50.0),
+            circle(10.0, 10.0, 2.5, 64),
+            circle(50.0, 25.0, 20.0, 64),
+            Polyline::new(vec![Vec2::new(90.0, 40.0)], false),
+            // Concentric counterbore outline: same hole.
+            circle(10.0, 10.0, 4.0, 64),
+        ];
+        let holes = find_holes(&contours, 10.0);
+        assert_eq!(holes.len(), 2, "{:?}", holes);
+        assert!(Vec2::dist(holes[0], Vec2::new(10.0, 10.0)) < 1e-9);
+        assert!(Vec2::dist(holes[1], Vec2::new(90.0, 40.0)) < 1e-9);
+    }
+
+    #[test]
+    fn polygons_are_not_holes() {
+        // A square and an irregular many-sided shape.
+        let mut blob = circle(0.0, 0.0, 3.0, 32);
+        blob.points[5].x *= 1.3;
+        let holes = find_holes(&[rect(0.0, 0.0, 4.0, 4.0), blob], 10.0);
+        assert!(holes.is_empty());
+    }
+
+    #[test]
+    fn orders_holes_by_nearest_neighbour() {
+        let holes = vec![
+            Vec2::new(50.0, 0.0),
+            Vec2::new(10.0, 0.0),
+            Vec2::new(30.0, 0.0),
+            Vec2::new(20.0, 0.0),
+        ];
+        let xs: Vec<f64> = order_holes(&holes, Vec2::new(0.0, 0.0))
+            .iter()
+            .map(|h| h.x)
+            .collect();
+        assert_eq!(xs, [10.0, 20.0, 30.0, 50.0]);
+    }
+
+    #[test]
+    fn peck_cycle_moves() {
+        let strategy = DrillStrategy::new(DrillParams {
+            peck: 2.0,
+            ..DrillParams::default()
+        });
+        let params = CutParams {
+            cut_z: -5.0,
+            ..CutParams::default()
+        };
+        let tps = strategy.generate(&[circle(5.0, 5.0, 1.5, 64)], &params);
+        assert_eq!(tps.len(), 1);
+        let cycle = tps[0].drill.unwrap();
+        assert_eq!(cycle.code(), "G83");
+        assert_eq!((cycle.z, cycle.retract_z), (-5.0, 1.0));
+
+        let cuts: Vec<f64> = tps[0]
+            .moves
+            .iter()
+            .filter(|m| !m.rapid)
+            .map(|m| m.z)
+            .collect();
+        assert_eq!(cuts, [-1.0, -3.0, -5.0]);
+        let m = &tps[0].moves;
+        assert!(m.iter().all(|m| m.x == 5.0 && m.y == 5.0));
+        assert_eq!(m.first().unwrap().z, params.safe_z);
+        assert_eq!(m.last().unwrap().z, params.safe_z);
+        // Back down to just above the last peck before feeding again.
+        assert!(m.iter().any(|m| m.rapid && m.z == -0.5));
+    }
+
+    #[test]
+    fn tip_compensation_deepens_holes() {
+        let strategy = DrillStrategy::new(DrillParams::default());
+        let params = CutParams {
+            tool: Tool::drill(4.0, 90.0, true, 20.0),
+            cut_z: -6.0,
+            ..CutParams::default()
+        };
+        let tps = strategy.generate(&[circle(0.0, 0.0, 2.0, 64)], &params);
+        let cycle = tps[0].drill.unwrap();
+        assert!((cycle.z + 8.0).abs() < 1e-9);
+        assert_eq!(cycle.code(), "G81");
+        assert_eq!(tps[0].moves.iter().filter(|m| !m.rapid).count(), 1);
+    }
+}
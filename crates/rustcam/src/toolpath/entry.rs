@@ -0,0 +1,463 @@
+//! Entry moves: how the tool gets from safe Z down to cutting depth.
+//!
+//! Swiss-cheese layer: **Strategy selection**
+//! Extension point: add an `EntryMode` variant and build its moves in
+//! `enter`.
+//!
+//! A straight plunge needs a centre-cutting tool. A ramp descends along
+//! the path that is about to be cut, and a helix spirals down on a small
+//! circle, so the flutes always cut sideways. Entries start
+//! `CutParams::step_down` above the cut depth (the floor of the previous
+//! layer) and never descend steeper than `CutParams::ramp_angle`.
+
+use crate::geometry::nesting::segment_inside;
+use crate::geometry::offset::{signed_area, ARC_TOLERANCE};
+use crate::geometry::{Polyline, Toolpath, Vec2};
+
+use super::CutParams;
+
+/// How the tool descends into the material at the start of a cut.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum EntryMode {
+    /// Straight down at the start point (default).
+    #[default]
+    Plunge,
+    /// Zig-zag or wind down along the path itself.
+    Ramp,
+    /// Spiral down on a circle of `diameter` (tool-centre path) that
+    /// touches the start point. Falls back to a ramp when the circle does
+    /// not fit.
+    Helix { diameter: f64 },
+}
+
+/// Where the tool centre may go while entering, used to check that a
+/// helix fits.
+pub(super) enum EntryArea<'a> {
+    /// Nothing known: no helix is attempted.
+    Unknown,
+    /// Inside these rings (non-zero rule).
+    Inside(&'a [Polyline]),
+    /// Outside this closed loop, e.g. around an outside profile.
+    Outside(&'a [Vec2]),
+}
+
+/// Rapid to safe Z and descend to `params.cut_z` at `path[0]` using
+/// `params.entry`.
+///
+/// `path` is the cut that follows (a closed loop without its repeated
+/// first point when `closed`). A helix that does not fit `area` falls back
+/// to a ramp, and a ramp falls back to a plunge when the path has no
+/// length.
+pub(super) fn enter(
+    tp: &mut Toolpath,
+    path: &[Vec2],
+    closed: bool,
+    area: EntryArea,
+    params: &CutParams,
+) {
+    let Some(&start) = path.first() else {
+        return;
+    };
+    let moves = descent(params).and_then(|(drop, run)| match params.entry {
+        EntryMode::Plunge => None,
+        EntryMode::Ramp => ramp(path, closed, drop, run),
+        EntryMode::Helix { diameter } => {
+            helix(path, &area, diameter / 2.0, drop, run).or_else(|| ramp(path, closed, drop, run))
+        }
+    });
+
+    match moves {
+        Some(moves) => {
+            let first = moves[0].0;
+            tp.rapid(first.x, first.y, params.safe_z);
+            for (p, h) in moves {
+                tp.cut(p.x, p.y, params.cut_z + h);
+            }
+        }
+        None => {
+            tp.rapid(start.x, start.y, params.safe_z);
+            tp.cut(start.x, start.y, params.cut_z);
+        }
+    }
+}
+
+/// Horizontal length of path a ramp entry may use, or 0 for a plunge.
+pub(super) fn ramp_length(params: &CutParams) -> f64 {
+    match (params.entry, descent(params)) {
+        (EntryMode::Plunge, _) | (_, None) => 0.0,
+        (_, Some((_, run))) => run,
+    }
+}
+
+/// Height to descend and the horizontal run needed to do it at
+/// `ramp_angle`, or `None` when there is nothing to ramp.
+fn descent(params: &CutParams) -> Option<(f64, f64)> {
+    let drop = (params.cut_z + params.step_down).min(params.safe_z) - params.cut_z;
+    let angle = params.ramp_angle;
+    if drop <= 1e-9 || !(angle > 0.0 && angle < 90.0) {
+        return None;
+    }
+    Some((drop, drop / angle.to_radians().tan()))
+}
+
+/// Ramp of horizontal length `run` ending at `path[0]`, as points with
+/// their height above the cut depth.
+///
+/// Closed loops are entered along the stretch that precedes the start, so
+/// the ramp runs in the cutting direction and is recut at full depth when
+/// the loop closes. Open paths zig-zag over their first stretch.
+fn ramp(path: &[Vec2], closed: bool, drop: f64, run: f64) -> Option<Vec<(Vec2, f64)>> {
+    let pts: Vec<Vec2> = if closed {
+        let n = path.len();
+        let perimeter: f64 = (0..n).map(|i| Vec2::dist(path[i], path[(i + 1) % n])).sum();
+        if n < 2 || perimeter < 1e-9 {
+            return None;
+        }
+        // Walk backwards from the start, wrapping as often as needed.
+        let mut back = vec![path[0]];
+        let mut remaining = run;
+        let mut i = 0;
+        while remaining > 1e-12 {
+            let j = (i + n - 1) % n;
+            let seg = Vec2::dist(path[i], path[j]);
+            if seg >= remaining {
+                back.push(lerp(path[i], path[j], remaining / seg));
+                break;
+            }
+            if seg > 1e-12 {
+                back.push(path[j]);
+            }
+            remaining -= seg;
+            i = j;
+        }
+        back.reverse();
+        back
+    } else {
+        let total: f64 = path.windows(2).map(|w| Vec2::dist(w[0], w[1])).sum();
+        if total < 1e-9 {
+            return None;
+        }
+        let stretch = truncate(path, total.min(run / 2.0));
+        let len: f64 = stretch.windows(2).map(|w| Vec2::dist(w[0], w[1])).sum();
+        let mut legs = (run / len).ceil() as usize;
+        legs += legs % 2;
+        let mut pts = vec![stretch[0]];
+        for leg in 0..legs {
+            if leg % 2 == 0 {
+                pts.extend_from_slice(&stretch[1..]);
+            } else {
+                pts.extend(stretch[..stretch.len() - 1].iter().rev());
+            }
+        }
+        pts
+    };
+    Some(descend(&pts, drop))
+}
+
+/// Helix touching `path[0]` and tangent to the path there, on whichever
+/// side fits inside `area`. Descends over whole turns, then makes one
+/// more turn at depth to flatten the floor.
+fn helix(
+    path: &[Vec2],
+    area: &EntryArea,
+    radius: f64,
+    drop: f64,
+    run: f64,
+) -> Option<Vec<(Vec2, f64)>> {
+    if radius <= 1e-6 {
+        return None;
+    }
+    let start = path[0];
+    let next = path.iter().find(|p| Vec2::dist(**p, start) > 1e-9)?;
+    let len = Vec2::dist(start, *next);
+    let dir = Vec2::new((next.x - start.x) / len, (next.y - start.y) / len);
+
+    let frame;
+    let rings: &[Polyline] = match area {
+        EntryArea::Unknown => return None,
+        EntryArea::Inside(rings) => rings,
+        EntryArea::Outside(outline) => {
+            frame = surround(outline, 2.0 * radius + 1.0);
+            &frame
+        }
+    };
+
+    let per_turn = (std::f64::consts::TAU / (2.0 * (1.0 - ARC_TOLERANCE / radius).max(-1.0).acos()))
+        .ceil()
+        .max(8.0) as usize;
+    // Left of the path direction turns counter-clockwise, right clockwise,
+    // so the helix leaves the start point heading along the path.
+    for side in [1.0, -1.0] {
+        let centre = Vec2::new(
+            start.x - dir.y * side * radius,
+            start.y + dir.x * side * radius,
+        );
+        let a0 = (start.y - centre.y).atan2(start.x - centre.x);
+        let at = |k: usize| {
+            let a = a0 + side * std::f64::consts::TAU * k as f64 / per_turn as f64;
+            Vec2::new(centre.x + radius * a.cos(), centre.y + radius * a.sin())
+        };
+        let fits = (0..per_turn).all(|k| segment_inside(rings, at(k), at(k + 1)));
+        if !fits {
+            continue;
+        }
+        let circumference = std::f64::consts::TAU * radius;
+        let turns = (run / circumference).ceil().max(1.0) as usize;
+        let mut pts: Vec<Vec2> = (0..=turns * per_turn).map(&at).collect();
+        let mut moves = descend(&pts, drop);
+        pts = (1..=per_turn).map(&at).collect();
+        moves.extend(pts.into_iter().map(|p| (p, 0.0)));
+        moves.last_mut().unwrap().0 = start;
+        return Some(moves);
+    }
+    None
+}
+
+/// Attach heights that fall linearly with distance from `drop` at the
+/// first point to zero at the last.
+fn descend(pts: &[Vec2], drop: f64) -> Vec<(Vec2, f64)> {
+    let total: f64 = pts.windows(2).map(|w| Vec2::dist(w[0], w[1])).sum();
+    let mut travelled = 0.0;
+    let mut out = Vec::with_capacity(pts.len());
+    for (i, &p) in pts.iter().enumerate() {
+        if i > 0 {
+            travelled += Vec2::dist(pts[i - 1], p);
+        }
+        let h = if total > 0.0 {
+            drop * (1.0 - travelled / total)
+        } else {
+            0.0
+        };
+        out.push((p, h.max(0.0)));
+    }
+    if let Some(last) = out.last_mut() {
+        last.1 = 0.0;
+    }
+    out
+}
+
+/// The first `length` of an open path.
+fn truncate(path: &[Vec2], length: f64) -> Vec<Vec2> {
+    let mut out = vec![path[0]];
+    let mut remaining = length;
+    for w in path.windows(2) {
+        let seg = Vec2::dist(w[0], w[1]);
+        if seg >= remaining {
+            out.push(lerp(w[0], w[1], remaining / seg));
+            break;
+        }
+        out.push(w[1]);
+        remaining -= seg;
+    }
+    out
+}
+
+fn lerp(a: Vec2, b: Vec2, t: f64) -> Vec2 {
+    Vec2::new(a.x + (b.x - a.x) * t, a.y + (b.y - a.y) * t)
+}
+
+/// Rings for "outside `outline`": a frame `margin` beyond its bounds,
+/// counter-clockwise, with the outline as a clockwise hole.
+fn surround(outline: &[Vec2], margin: f64) -> Vec<Polyline> {
+    let (mut lo, mut hi) = (outline[0], outline[0]);
+    for p in outline {
+        lo = Vec2::new(lo.x.min(p.x), lo.y.min(p.y));
+        hi = Vec2::new(hi.x.max(p.x), hi.y.max(p.y));
+    }
+    let frame = vec![
+        Vec2::new(lo.x - margin, lo.y - margin),
+        Vec2::new(hi.x + margin, lo.y - margin),
+        Vec2::new(hi.x + margin, hi.y + margin),
+        Vec2::new(lo.x - margin, hi.y + margin),
+    ];
+    let mut hole = outline.to_vec();
+    if signed_area(&hole) > 0.0 {
+        hole.reverse();
+    }
+    vec![Polyline::new(frame, true), Polyline::new(hole, true)]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn square(x0: f64, y0: f64, size: f64) -> Vec<Vec2> {
+        vec![
+            Vec2::new(x0, y0),
+            Vec2::new(x0 + size, y0),
+            Vec2::new(x0 + size, y0 + size),
+            Vec2::new(x0, y0 + size),
+        ]
+    }
+
+    fn params(entry: EntryMode) -> CutParams {
+        CutParams {
+            entry,
+            ramp_angle: 5.0,
+            step_down: 1.0,
+            cut_z: -1.0,
+            ..CutParams::default()
+        }
+    }
+
+    /// Steepest descent over the cutting moves after the first feed down.
+    fn max_angle(tp: &Toolpath) -> f64 {
+        let cuts: Vec<_> = tp.moves.iter().filter(|m| !m.rapid).collect();
+        cuts.windows(2)
+            .skip(1)
+            .map(|w| {
+                let run = (w[1].x - w[0].x).hypot(w[1].y - w[0].y);
+                (w[0].z - w[1].z).atan2(run).to_degrees()
+            })
+            .fold(0.0, f64::max)
+    }
+
+    #[test]
+    fn plunge_goes_straight_down() {
+        let mut tp = Toolpath::new();
+        let path = square(0.0, 0.0, 10.0);
+        enter(
+            &mut tp,
+            &path,
+            true,
+            EntryArea::Unknown,
+            &params(EntryMode::Plunge),
+        );
+        assert_eq!(tp.moves.len(), 2);
+        assert!(tp.moves[0].rapid);
+        assert_eq!(
+            (tp.moves[1].x, tp.moves[1].y, tp.moves[1].z),
+            (0.0, 0.0, -1.0)
+        );
+    }
+
+    #[test]
+    fn ramp_on_closed_loop_follows_path_into_start() {
+        let mut tp = Toolpath::new();
+        let path = square(0.0, 0.0, 10.0);
+        enter(
+            &mut tp,
+            &path,
+            true,
+            EntryArea::Unknown,
+            &params(EntryMode::Ramp),
+        );
+        let last = tp.moves.last().unwrap();
+        assert_eq!((last.x, last.y, last.z), (0.0, 0.0, -1.0));
+        assert!(
+            (tp.moves[1].z - 0.0).abs() < 1e-9,
+            "ramp starts at previous floor"
+        );
+        assert!(max_angle(&tp) <= 5.0 + 1e-6);
+        // Ramp length 1/tan(5°) ≈ 11.4 mm wraps back past the corner at (0, 10).
+        assert!(tp.moves.iter().any(|m| m.x == 0.0 && m.y == 10.0));
+        for m in &tp.moves {
+            assert!(
+                m.x.abs() < 1e-9
+                    || m.y.abs() < 1e-9
+                    || (m.x - 10.0).abs() < 1e-9
+                    || (m.y - 10.0).abs() < 1e-9
+            );
+        }
+    }
+
+    #[test]
+    fn ramp_on_short_open_path_zig_zags() {
+        let mut tp = Toolpath::new();
+        let path = vec![Vec2::new(0.0, 0.0), Vec2::new(2.0, 0.0)];
+        enter(
+            &mut tp,
+            &path,
+            false,
+            EntryArea::Unknown,
+            &params(EntryMode::Ramp),
+        );
+        let last = tp.moves.last().unwrap();
+        assert_eq!((last.x, last.y, last.z), (0.0, 0.0, -1.0));
+        assert!(max_angle(&tp) <= 5.0 + 1e-6);
+        let turns = tp.moves.iter().filter(|m| m.x == 2.0).count();
+        assert!(turns >= 3, "expected several legs, got {}", turns);
+        assert!(tp
+            .moves
+            .iter()
+            .all(|m| (0.0..=2.0).contains(&m.x) && m.y == 0.0));
+    }
+
+    #[test]
+    fn helix_fits_inside_pocket() {
+        let rings = vec![Polyline::new(square(0.0, 0.0, 20.0), true)];
+        let path = vec![Vec2::new(10.0, 10.0), Vec2::new(15.0, 10.0)];
+        let mut tp = Toolpath::new();
+        let p = params(EntryMode::Helix { diameter: 4.0 });
+        enter(&mut tp, &path, false, EntryArea::Inside(&rings), &p);
+        assert!(max_angle(&tp) <= 5.0 + 1e-6);
+        for m in &tp.moves {
+            let d = (m.x - 10.0)
+                .hypot(m.y - 12.0)
+                .min((m.x - 10.0).hypot(m.y - 8.0));
+            assert!(d <= 2.0 + 1e-9);
+        }
+        let last = tp.moves.last().unwrap();
+        assert_eq!((last.x, last.y, last.z), (10.0, 10.0, -1.0));
+        // Final turn at full depth.
+        let at_depth = tp.moves.iter().filter(|m| m.z == -1.0).count();
+        assert!(at_depth >= 8);
+    }
+
+    #[test]
+    fn helix_picks_the_side_that_fits() {
+        // Start near the bottom wall heading -X: only the right (upper)
+        // side fits.
+        let rings = vec![Polyline::new(square(0.0, 0.0, 20.0), true)];
+        let path = vec![Vec2::new(10.0, 1.0), Vec2::new(5.0, 1.0)];
+        let mut tp = Toolpath::new();
+        let p = params(EntryMode::Helix { diameter: 4.0 });
+        enter(&mut tp, &path, false, EntryArea::Inside(&rings), &p);
+        assert!(tp.moves.iter().all(|m| m.y >= 1.0 - 1e-9));
+        assert!(tp.moves.iter().any(|m| m.y > 4.0));
+    }
+
+    #[test]
+    fn helix_outside_profile_stays_off_the_part() {
+        let outline = square(0.0, 0.0, 10.0);
+        let mut tp = Toolpath::new();
+        let p = params(EntryMode::Helix { diameter: 3.0 });
+        enter(&mut tp, &outline, true, EntryArea::Outside(&outline), &p);
+        assert!(tp.moves.len() > 10);
+        for m in &tp.moves {
+            let inside = m.x > 1e-9 && m.x < 10.0 - 1e-9 && m.y > 1e-9 && m.y < 10.0 - 1e-9;
+            assert!(!inside, "({}, {}) is inside the part", m.x, m.y);
+        }
+    }
+
+    #[test]
+    fn helix_that_does_not_fit_falls_back_to_ramp() {
+        let rings = vec![Polyline::new(square(0.0, 0.0, 3.0), true)];
+        let path = square(0.0, 0.0, 3.0);
+        let mut tp = Toolpath::new();
+        let p = params(EntryMode::Helix { diameter: 5.0 });
+        enter(&mut tp, &path, true, EntryArea::Inside(&rings), &p);
+        assert!(tp.moves.len() > 2);
+        for m in &tp.moves {
+            assert!((-1e-9..=3.0 + 1e-9).contains(&m.x) && (-1e-9..=3.0 + 1e-9).contains(&m.y));
+        }
+        assert!(max_angle(&tp) <= 5.0 + 1e-6);
+    }
+
+    #[test]
+    fn no_entry_needed_when_already_at_top() {
+        let mut tp = Toolpath::new();
+        let p = CutParams {
+            step_down: 0.0,
+            ..params(EntryMode::Ramp)
+        };
+        enter(
+            &mut tp,
+            &square(0.0, 0.0, 5.0),
+            true,
+            EntryArea::Unknown,
+            &p,
+        );
+        assert_eq!(tp.moves.len(), 2);
+    }
+}
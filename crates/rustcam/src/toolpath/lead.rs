@@ -0,0 +1,341 @@
+//! Lead-in and lead-out moves, and controller cutter compensation.
+//!
+//! Swiss-cheese layer: **Strategy selection**
+//! Extension point: add a `Lead` variant and build its points in
+//! `approach` and `depart`.
+//!
+//! A profile that starts and ends on the wall leaves a witness mark where
+//! the tool stops and turns. Leads join closed profiles from the waste
+//! side instead: a line square to the wall, or a quarter arc tangent to
+//! it, and the tool descends at the far end of the lead-in, clear of the
+//! part. Leads are not checked against the rest of the part, so keep them
+//! shorter than the gaps around it.
+//!
+//! With controller compensation the profile is the nominal outline. G41
+//! or G42 comes on over a straight move, one tool diameter long, ahead of
+//! the lead-in and goes off (G40) over another after the lead-out, so the
+//! operator can trim the radius in the tool table for wear.
+
+use crate::geometry::offset::{signed_area, ARC_TOLERANCE};
+use crate::geometry::{CutterComp, Toolpath, Vec2};
+
+use super::entry::{enter, EntryArea};
+use super::CutParams;
+
+/// How the tool joins or leaves a closed profile.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Lead {
+    /// Straight down onto the profile and straight up off it (default).
+    #[default]
+    None,
+    /// Straight, square to the profile, `length` long.
+    Line { length: f64 },
+    /// Quarter circle of `radius` tangent to the profile.
+    Arc { radius: f64 },
+}
+
+/// Leads on closed profiles, and who compensates for the tool radius.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct LeadParams {
+    pub lead_in: Lead,
+    pub lead_out: Lead,
+    /// Cut the nominal outline and let the controller offset the tool
+    /// (G41/G42).
+    pub controller_comp: bool,
+}
+
+impl LeadParams {
+    /// Whether closed profiles get anything beyond a plain entry and
+    /// retract.
+    pub fn active(&self) -> bool {
+        self.lead_in != Lead::None || self.lead_out != Lead::None || self.controller_comp
+    }
+}
+
+/// Lead moves planned for one closed profile.
+pub(super) struct Leads {
+    /// The profile, restarted where the leads join it.
+    pub(super) path: Vec<Vec2>,
+    /// From where the tool descends to the profile start, inclusive.
+    lead_in: Vec<Vec2>,
+    /// After the profile closes at its start.
+    lead_out: Vec<Vec2>,
+    comp: Option<CutterComp>,
+}
+
+impl Leads {
+    /// Leads for the closed loop `pts` (without its repeated first point)
+    /// on the side `area` says is waste, or `None` when there is nothing
+    /// to add. Arcs are cut down to half the longest edge, where the
+    /// leads join.
+    pub(super) fn plan(pts: &[Vec2], area: &EntryArea, params: &CutParams) -> Option<Self> {
+        let leads = &params.leads;
+        if !leads.active() || pts.len() < 3 {
+            return None;
+        }
+        let waste_inside = match area {
+            EntryArea::Inside(_) => true,
+            EntryArea::Outside(_) => false,
+            EntryArea::Unknown => return None,
+        };
+        // Left of travel is inside a counter-clockwise loop.
+        let left = (signed_area(pts) > 0.0) == waste_inside;
+        // Join halfway along the longest edge, clear of any corner.
+        let n = pts.len();
+        let (i, len) = (0..n)
+            .map(|i| (i, Vec2::dist(pts[i], pts[(i + 1) % n])))
+            .max_by(|a, b| a.1.total_cmp(&b.1))?;
+        if len < 1e-9 {
+            return None;
+        }
+        let (a, b) = (pts[i], pts[(i + 1) % n]);
+        let start = Vec2::new((a.x + b.x) / 2.0, (a.y + b.y) / 2.0);
+        let path: Vec<Vec2> = std::iter::once(start)
+            .chain(pts[i + 1..].iter().chain(&pts[..=i]).copied())
+            .collect();
+        let d_in = unit(a, b);
+        let d_out = d_in;
+        let fit = |lead: Lead| match lead {
+            Lead::Arc { radius } => Lead::Arc {
+                radius: radius.min(len / 2.0),
+            },
+            other => other,
+        };
+        let waste = |d: Vec2| {
+            if left {
+                Vec2::new(-d.y, d.x)
+            } else {
+                Vec2::new(d.y, -d.x)
+            }
+        };
+
+        let mut lead_in = approach(start, d_in, waste(d_in), fit(leads.lead_in));
+        let mut lead_out = depart(start, d_out, waste(d_out), fit(leads.lead_out));
+        let comp = leads.controller_comp.then_some(if left {
+            CutterComp::Left
+        } else {
+            CutterComp::Right
+        });
+        if comp.is_some() {
+            let reach = params.tool_diameter;
+            let first = lead_in[0];
+            let dir = match lead_in.get(1) {
+                Some(&p) => unit(first, p),
+                None => scale(waste(d_in), -1.0),
+            };
+            lead_in.insert(0, along(first, dir, -reach));
+            let (last, dir) = match lead_out.len() {
+                0 => (start, waste(d_out)),
+                1 => (lead_out[0], unit(start, lead_out[0])),
+                n => (lead_out[n - 1], unit(lead_out[n - 2], lead_out[n - 1])),
+            };
+            lead_out.push(along(last, dir, reach));
+        }
+        Some(Self {
+            path,
+            lead_in,
+            lead_out,
+            comp,
+        })
+    }
+
+    /// Descend at the start of the lead-in and cut it to the start of
+    /// `path`. Without a lead-in, enter the loop as usual. Returns the
+    /// index of the first move after the descent.
+    pub(super) fn enter(&self, tp: &mut Toolpath, area: EntryArea, params: &CutParams) -> usize {
+        if self.lead_in.len() < 2 {
+            enter(tp, &self.path, true, area, params);
+            return tp.moves.len();
+        }
+        let path: Vec<Vec2> = self
+            .lead_in
+            .iter()
+            .chain(&self.path[1..])
+            .copied()
+            .collect();
+        enter(tp, &path, false, EntryArea::Unknown, params);
+        let on = tp.moves.len();
+        for p in &self.lead_in[1..] {
+            tp.cut(p.x, p.y, params.cut_z);
+        }
+        on
+    }
+
+    /// Cut the lead-out from the closed profile and retract. With
+    /// compensation, it is in force from move `on` until the last lead
+    /// move, which cancels it.
+    pub(super) fn exit(&self, tp: &mut Toolpath, on: usize, params: &CutParams) {
+        let Some(last) = tp.moves.last() else {
+            return;
+        };
+        let (z, mut end) = (last.z, Vec2::new(last.x, last.y));
+        for p in &self.lead_out {
+            tp.cut(p.x, p.y, z);
+            end = *p;
+        }
+        if let Some(side) = self.comp {
+            let off = tp.moves.len() - 1;
+            for mv in &mut tp.moves[on..off] {
+                mv.comp = Some(side);
+            }
+        }
+        tp.rapid(end.x, end.y, params.safe_z);
+    }
+}
+
+/// Lead-in points ending at `start`, where the profile heads along `dir`;
+/// `waste` points away from the part.
+fn approach(start: Vec2, dir: Vec2, waste: Vec2, lead: Lead) -> Vec<Vec2> {
+    match lead {
+        Lead::None => vec![start],
+        Lead::Line { length } => vec![along(start, waste, length), start],
+        Lead::Arc { radius } => {
+            // Around a centre on the waste side, from the point behind it
+            // to `start`, arriving along `dir`.
+            let centre = along(start, waste, radius);
+            let n = arc_steps(radius);
+            (0..=n)
+                .map(|k| {
+                    let a = std::f64::consts::FRAC_PI_2 * (n - k) as f64 / n as f64;
+                    on_circle(centre, radius, waste, dir, a, -1.0)
+                })
+                .collect()
+        }
+    }
+}
+
+/// Lead-out points after `start`, where the profile arrives along `dir`.
+fn depart(start: Vec2, dir: Vec2, waste: Vec2, lead: Lead) -> Vec<Vec2> {
+    match lead {
+        Lead::None => Vec::new(),
+        Lead::Line { length } => vec![along(start, waste, length)],
+        Lead::Arc { radius } => {
+            let centre = along(start, waste, radius);
+            let n = arc_steps(radius);
+            (1..=n)
+                .map(|k| {
+                    let a = std::f64::consts::FRAC_PI_2 * k as f64 / n as f64;
+                    on_circle(centre, radius, waste, dir, a, 1.0)
+                })
+                .collect()
+        }
+    }
+}
+
+/// `centre - waste·r·cos a + sign·dir·r·sin a`: at `a = 0` the point on
+/// the profile, at a quarter turn the point ahead of (`sign` 1) or behind
+/// (`sign` -1) the centre.
+fn on_circle(centre: Vec2, r: f64, waste: Vec2, dir: Vec2, a: f64, sign: f64) -> Vec2 {
+    let (s, c) = a.sin_cos();
+    Vec2::new(
+        centre.x - r * (waste.x * c - sign * dir.x * s),
+        centre.y - r * (waste.y * c - sign * dir.y * s),
+    )
+}
+
+/// Chords in a quarter circle of `radius` within `ARC_TOLERANCE`, at least
+/// the four the arc fitter needs to turn it back into one G2/G3.
+fn arc_steps(radius: f64) -> usize {
+    let step = 2.0 * (1.0 - ARC_TOLERANCE / radius).max(-1.0).acos();
+    ((std::f64::consts::FRAC_PI_2 / step).ceil() as usize).max(4)
+}
+
+fn unit(a: Vec2, b: Vec2) -> Vec2 {
+    let len = Vec2::dist(a, b);
+    Vec2::new((b.x - a.x) / len, (b.y - a.y) / len)
+}
+
+fn along(p: Vec2, dir: Vec2, t: f64) -> Vec2 {
+    Vec2::new(p.x + dir.x * t, p.y + dir.y * t)
+}
+
+fn scale(v: Vec2, k: f64) -> Vec2 {
+    Vec2::new(v.x * k, v.y * k)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{ContourStrategy, PerimeterStrategy, ToolpathStrategy};
+    use super::*;
+    use crate::geometry::Polyline;
+
+    fn square() -> Polyline {
+        Polyline::new(
+            vec![
+                Vec2::new(0.0, 0.0),
+                Vec2::new(20.0, 0.0),
+                Vec2::new(20.0, 20.0),
+                Vec2::new(0.0, 20.0),
+            ],
+            true,
+        )
+    }
+
+    fn params(leads: LeadParams) -> CutParams {
+        CutParams {
+            tool_diameter: 4.0,
+            cut_z: -1.0,
+            leads,
+            ..CutParams::default()
+        }
+    }
+
+    fn inside_square(m: &crate::geometry::ToolpathMove) -> bool {
+        m.x > 1e-6 && m.x < 20.0 - 1e-6 && m.y > 1e-6 && m.y < 20.0 - 1e-6
+    }
+
+    #[test]
+    fn arc_leads_stay_on_the_waste_side() {
+        let arc = Lead::Arc { radius: 3.0 };
+        let leads = LeadParams {
+            lead_in: arc,
+            lead_out: arc,
+            controller_comp: false,
+        };
+        let outside = ContourStrategy.generate(&[square()], &params(leads));
+        let tp = &outside[0];
+        // Descends 2 + 3 mm off the offset profile, outside the part.
+        let plunge = tp.moves.iter().find(|m| !m.rapid).unwrap();
+        assert!(!inside_square(plunge));
+        assert!(tp.moves.iter().all(|m| !inside_square(m)));
+        let depth = tp.moves.iter().filter(|m| m.z == -1.0).count();
+        let plain = &ContourStrategy.generate(&[square()], &params(LeadParams::default()))[0];
+        assert!(depth > plain.moves.iter().filter(|m| m.z == -1.0).count() + 8);
+
+        // A perimeter's waste is inside it.
+        let inside = PerimeterStrategy.generate(&[square()], &params(leads));
+        // Every move stays within the loop a radius in from the wall.
+        let within = |m: &crate::geometry::ToolpathMove| {
+            (2.0 - 1e-9..=18.0 + 1e-9).contains(&m.x) && (2.0 - 1e-9..=18.0 + 1e-9).contains(&m.y)
+        };
+        assert!(inside[0].moves.iter().all(within), "{:?}", inside[0].moves);
+        let plunge = inside[0].moves.iter().find(|m| !m.rapid).unwrap();
+        assert!(plunge.x > 2.0 + 1.0 && plunge.y > 2.0 + 1.0);
+    }
+
+    #[test]
+    fn controller_comp_cuts_the_nominal_outline() {
+        let leads = LeadParams {
+            lead_in: Lead::Line { length: 2.0 },
+            lead_out: Lead::None,
+            controller_comp: true,
+        };
+        let tp = &ContourStrategy.generate(&[square()], &params(leads))[0];
+        let on = tp.moves.iter().position(|m| m.comp.is_some()).unwrap();
+        let off = tp.moves.iter().rposition(|m| m.comp.is_some()).unwrap();
+        // On over the straight move onto the line lead, off over the move
+        // after the loop closes.
+        assert!(!tp.moves[on - 1].rapid && tp.moves[on - 1].comp.is_none());
+        assert!(tp.moves[on + 1..=off].iter().all(|m| m.comp.is_some()));
+        assert!(!tp.moves[off + 1].rapid && tp.moves[off + 1].comp.is_none());
+        assert!(tp.moves[off + 2].rapid);
+        // The loop runs on the outline itself, not a radius outside it.
+        let corners = tp.moves[on..=off]
+            .iter()
+            .filter(|m| (m.x == 0.0 || m.x == 20.0) && (m.y == 0.0 || m.y == 20.0))
+            .count();
+        assert!(corners >= 4, "{:?}", tp.moves);
+        // Counter-clockwise outline, tool outside: right of travel.
+        assert_eq!(tp.moves[on].comp, Some(CutterComp::Right));
+    }
+}
@@ -0,0 +1,650 @@
+//! Cut ordering: sequencing toolpaths to shorten rapid travel.
+//!
+//! Swiss-cheese layer: **Strategy selection**
+//! Extension point: add a tour move next to `two_opt` (or-opt, say), or
+//! another cutting-order rule to `precedence`.
+//!
+//! Strategies emit toolpaths in the order their input contours arrived,
+//! which on a nested sheet sends the tool back and forth across it. One
+//! pass worth of toolpaths is re-sequenced as an open travelling-salesman
+//! tour from the current tool position: nearest neighbour builds the tour,
+//! then 2-opt reverses stretches of it while that shortens the rapids.
+//! Open paths that are a single cut may run from either end, and closed
+//! loops may start at the point nearest the tool. Anything inside a closed
+//! loop is cut before the loop, so parts do not drop out of the sheet
+//! before their holes are cut.
+
+use serde::Serialize;
+
+use crate::geometry::nesting::winding_number;
+use crate::geometry::{BoundingBox2, Toolpath, ToolpathMove, Vec2};
+
+/// Passes with more toolpaths keep the nearest-neighbour tour, since each
+/// 2-opt sweep is quadratic.
+pub const MAX_TWO_OPT_PATHS: usize = 2000;
+
+/// 2-opt sweeps before settling for the tour so far.
+const MAX_SWEEPS: usize = 50;
+
+const EPS: f64 = 1e-9;
+
+/// What ordering may change about a toolpath besides its place.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct OrderParams {
+    /// Open paths made of one cut may run backwards.
+    pub reverse: bool,
+    /// Closed loops made of one cut may start anywhere along them.
+    pub start_points: bool,
+}
+
+/// Rapid travel (mm) as the toolpaths were planned and after ordering.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct RapidReport {
+    pub before: f64,
+    pub after: f64,
+}
+
+/// Length of every rapid move, starting over `start` at the height of the
+/// first move.
+pub fn rapid_distance(toolpaths: &[Toolpath], start: Vec2) -> f64 {
+    let mut pos: Option<(f64, f64, f64)> = None;
+    let mut total = 0.0;
+    for mv in toolpaths.iter().flat_map(|tp| &tp.moves) {
+        let (x, y, z) = pos.unwrap_or((start.x, start.y, mv.z));
+        if mv.rapid {
+            total += ((mv.x - x).powi(2) + (mv.y - y).powi(2) + (mv.z - z).powi(2)).sqrt();
+        }
+        pos = Some((mv.x, mv.y, mv.z));
+    }
+    total
+}
+
+/// Re-sequence one pass of toolpaths to shorten the rapids between them,
+/// starting with the tool over `start`. Empty toolpaths are dropped.
+pub fn order_toolpaths(
+    toolpaths: Vec<Toolpath>,
+    start: Vec2,
+    params: &OrderParams,
+) -> Vec<Toolpath> {
+    let mut items: Vec<Item> = toolpaths
+        .into_iter()
+        .filter(|tp| !tp.moves.is_empty())
+        .map(|tp| Item::new(tp, params))
+        .collect();
+    let before = precedence(&items);
+    let mut order = nearest_neighbour(&mut items, &before, start);
+    if items.len() <= MAX_TWO_OPT_PATHS {
+        two_opt(&mut order, &mut items, &before, start);
+    }
+    if params.start_points {
+        place_loop_starts(&order, &mut items, start);
+    }
+
+    let mut slots: Vec<Option<Item>> = items.into_iter().map(Some).collect();
+    order
+        .into_iter()
+        .filter_map(|i| slots[i].take())
+        .map(Item::into_toolpath)
+        .collect()
+}
+
+// ── Toolpaths as tour stops ──────────────────────────────────────────
+
+/// A toolpath that is one uninterrupted cut at one height: an approach
+/// rapid, maybe a plunge, feed moves, maybe a retract. Only these are
+/// reversed or restarted; anything with tabs, ramps or several cuts keeps
+/// its moves as planned.
+struct Chain {
+    approach: ToolpathMove,
+    plunge: Option<ToolpathMove>,
+    /// Where the cut starts, then where each of `cuts` ends.
+    points: Vec<Vec2>,
+    cuts: Vec<ToolpathMove>,
+    retract: Option<ToolpathMove>,
+}
+
+fn xy(mv: &ToolpathMove) -> Vec2 {
+    Vec2::new(mv.x, mv.y)
+}
+
+impl Chain {
+    fn parse(tp: &Toolpath) -> Option<Self> {
+        if tp.drill.is_some() || !tp.tabs.is_empty() || tp.moves.iter().any(|m| m.tab) {
+            return None;
+        }
+        let (approach, mut rest) = tp.moves.split_first()?;
+        if !approach.rapid {
+            return None;
+        }
+        let start = xy(approach);
+        let mut plunge = None;
+        if let Some((mv, tail)) = rest.split_first() {
+            if !mv.rapid && Vec2::dist(xy(mv), start) < EPS && (mv.z - approach.z).abs() > EPS {
+                plunge = Some(mv.clone());
+                rest = tail;
+            }
+        }
+        let mut retract = None;
+        if let Some((mv, head)) = rest.split_last() {
+            if mv.rapid {
+                retract = Some(mv.clone());
+                rest = head;
+            }
+        }
+        let z = rest.first()?.z;
+        let level = |mv: &ToolpathMove| !mv.rapid && (mv.z - z).abs() < EPS;
+        if !rest.iter().all(level) || plunge.as_ref().is_some_and(|p| !level(p)) {
+            return None;
+        }
+        let mut points = vec![start];
+        points.extend(rest.iter().map(xy));
+        if retract
+            .as_ref()
+            .is_some_and(|r| Vec2::dist(xy(r), points[points.len() - 1]) > EPS)
+        {
+            return None;
+        }
+        Some(Self {
+            approach: approach.clone(),
+            plunge,
+            points,
+            cuts: rest.to_vec(),
+            retract,
+        })
+    }
+
+    fn is_loop(&self) -> bool {
+        self.points.len() >= 4
+            && Vec2::dist(self.points[0], self.points[self.points.len() - 1]) < EPS
+    }
+
+    /// The same cut from the other end; each segment keeps its feed move
+    /// (and so its laser power).
+    fn reversed(mut self) -> Self {
+        self.points.reverse();
+        self.cuts.reverse();
+        self
+    }
+
+    /// The same loop starting at `at`, a point on segment `seg`.
+    fn restarted(self, seg: usize, at: Vec2) -> Self {
+        let m = self.points.len() - 1;
+        let mut points = vec![at];
+        let mut cuts = Vec::with_capacity(m + 1);
+        let mut push = |p: Vec2, cut: &ToolpathMove| {
+            if Vec2::dist(points[points.len() - 1], p) > EPS {
+                points.push(p);
+                cuts.push(cut.clone());
+            }
+        };
+        for k in 0..m {
+            let s = (seg + k) % m;
+            push(self.points[s + 1], &self.cuts[s]);
+        }
+        push(at, &self.cuts[seg]);
+        Self {
+            points,
+            cuts,
+            ..self
+        }
+    }
+
+    fn into_toolpath(self) -> Toolpath {
+        let at = |mut mv: ToolpathMove, p: Vec2| {
+            mv.x = p.x;
+            mv.y = p.y;
+            mv
+        };
+        let (start, end) = (self.points[0], self.points[self.points.len() - 1]);
+        let mut tp = Toolpath::new();
+        tp.moves.push(at(self.approach, start));
+        tp.moves.extend(self.plunge.map(|mv| at(mv, start)));
+        for (mv, &p) in self.cuts.into_iter().zip(&self.points[1..]) {
+            tp.moves.push(at(mv, p));
+        }
+        tp.moves.extend(self.retract.map(|mv| at(mv, end)));
+        tp
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Kind {
+    /// Cut exactly as planned.
+    Fixed,
+    /// An open chain that may run backwards.
+    Reversible,
+    /// A closed chain that may start anywhere.
+    Loop,
+}
+
+struct Item {
+    tp: Toolpath,
+    chain: Option<Chain>,
+    kind: Kind,
+    /// Where the tool arrives and leaves, as the toolpath will be cut.
+    entry: Vec2,
+    exit: Vec2,
+    reversed: bool,
+    /// Segment and point a loop now starts at.
+    start: Option<(usize, Vec2)>,
+    /// XY of the moves up to the last cut.
+    footprint: Vec<Vec2>,
+    bounds: Option<BoundingBox2>,
+}
+
+impl Item {
+    fn new(tp: Toolpath, params: &OrderParams) -> Self {
+        let chain = Chain::parse(&tp);
+        let kind = match &chain {
+            Some(c) if c.is_loop() && params.start_points => Kind::Loop,
+            Some(c) if !c.is_loop() && params.reverse => Kind::Reversible,
+            _ => Kind::Fixed,
+        };
+        let last_cut = tp.moves.iter().rposition(|m| !m.rapid).unwrap_or(0);
+        let footprint: Vec<Vec2> = tp.moves[..=last_cut].iter().map(xy).collect();
+        Self {
+            entry: xy(&tp.moves[0]),
+            exit: xy(&tp.moves[tp.moves.len() - 1]),
+            bounds: BoundingBox2::from_points(&footprint),
+            footprint,
+            chain: (kind != Kind::Fixed).then_some(chain).flatten(),
+            kind,
+            reversed: false,
+            start: None,
+            tp,
+        }
+    }
+
+    /// The footprint as a ring, when the toolpath cuts a closed loop.
+    fn ring(&self) -> Option<&[Vec2]> {
+        let pts = &self.footprint;
+        (pts.len() >= 4 && Vec2::dist(pts[0], pts[pts.len() - 1]) < EPS).then_some(pts)
+    }
+
+    /// How the toolpath is best entered from `pos`, unless that is no
+    /// nearer than `best`.
+    fn approach(&self, pos: Vec2, best: f64) -> Option<Approach> {
+        let direct = Approach {
+            dist: Vec2::dist(pos, self.entry),
+            reversed: false,
+            start: None,
+        };
+        let approach = match (self.kind, &self.chain) {
+            (Kind::Reversible, _) => {
+                let back = Vec2::dist(pos, self.exit);
+                if back < direct.dist {
+                    Approach {
+                        dist: back,
+                        reversed: true,
+                        start: None,
+                    }
+                } else {
+                    direct
+                }
+            }
+            (Kind::Loop, Some(chain)) => {
+                // No point on the loop beats its bounding box.
+                if self
+                    .bounds
+                    .as_ref()
+                    .is_some_and(|b| box_dist(b, pos) >= best)
+                {
+                    return None;
+                }
+                let (seg, at) = nearest_on_loop(&chain.points, pos, None);
+                Approach {
+                    dist: Vec2::dist(pos, at),
+                    reversed: false,
+                    start: Some((seg, at)),
+                }
+            }
+            _ => direct,
+        };
+        (approach.dist < best).then_some(approach)
+    }
+
+    fn enter(&mut self, approach: &Approach) {
+        if approach.reversed {
+            self.flip();
+        }
+        if let Some((seg, at)) = approach.start {
+            self.restart(seg, at);
+        }
+    }
+
+    fn restart(&mut self, seg: usize, at: Vec2) {
+        self.start = Some((seg, at));
+        self.entry = at;
+        self.exit = at;
+    }
+
+    /// Whether swapping entry and exit is allowed, as 2-opt needs.
+    fn flippable(&self) -> bool {
+        self.kind == Kind::Reversible || Vec2::dist(self.entry, self.exit) < EPS
+    }
+
+    fn flip(&mut self) {
+        if self.kind == Kind::Reversible {
+            self.reversed = !self.reversed;
+            std::mem::swap(&mut self.entry, &mut self.exit);
+        }
+    }
+
+    fn into_toolpath(self) -> Toolpath {
+        match (self.chain, self.start) {
+            (Some(chain), _) if self.reversed => chain.reversed().into_toolpath(),
+            (Some(chain), Some((seg, at))) => chain.restarted(seg, at).into_toolpath(),
+            _ => self.tp,
+        }
+    }
+}
+
+/// Distance to a toolpath's entry, and how it is entered.
+struct Approach {
+    dist: f64,
+    reversed: bool,
+    /// Segment and point a loop is started at.
+    start: Option<(usize, Vec2)>,
+}
+
+fn box_dist(b: &BoundingBox2, p: Vec2) -> f64 {
+    let dx = (b.min.x - p.x).max(p.x - b.max.x).max(0.0);
+    let dy = (b.min.y - p.y).max(p.y - b.max.y).max(0.0);
+    dx.hypot(dy)
+}
+
+fn nearest_on_segment(a: Vec2, b: Vec2, p: Vec2) -> Vec2 {
+    let (dx, dy) = (b.x - a.x, b.y - a.y);
+    let len2 = dx * dx + dy * dy;
+    if len2 < EPS * EPS {
+        return a;
+    }
+    let t = (((p.x - a.x) * dx + (p.y - a.y) * dy) / len2).clamp(0.0, 1.0);
+    Vec2::new(a.x + t * dx, a.y + t * dy)
+}
+
+/// The point of the loop `points` (closed, last = first) nearest `from`,
+/// or with `to` the point minimizing the way from `from` to `to` through
+/// it, with its segment.
+fn nearest_on_loop(points: &[Vec2], from: Vec2, to: Option<Vec2>) -> (usize, Vec2) {
+    let cost = |p: Vec2| Vec2::dist(from, p) + to.map_or(0.0, |t| Vec2::dist(p, t));
+    let mut best = (f64::INFINITY, 0, points[0]);
+    for (seg, w) in points.windows(2).enumerate() {
+        let candidates = [
+            w[0],
+            nearest_on_segment(w[0], w[1], from),
+            to.map_or(w[0], |t| nearest_on_segment(w[0], w[1], t)),
+        ];
+        for p in candidates {
+            let c = cost(p);
+            if c < best.0 - EPS {
+                best = (c, seg, p);
+            }
+        }
+    }
+    (best.1, best.2)
+}
+
+// ── Constraints and tour ─────────────────────────────────────────────
+
+/// For each toolpath, the ones that must be cut before it: everything
+/// inside it when it cuts a closed loop.
+fn precedence(items: &[Item]) -> Vec<Vec<usize>> {
+    let area = |b: &BoundingBox2| (b.max.x - b.min.x) * (b.max.y - b.min.y);
+    let within = |a: &BoundingBox2, b: &BoundingBox2| {
+        a.min.x >= b.min.x - EPS
+            && a.min.y >= b.min.y - EPS
+            && a.max.x <= b.max.x + EPS
+            && a.max.y <= b.max.y + EPS
+    };
+    items
+        .iter()
+        .enumerate()
+        .map(|(o, outer)| {
+            let (Some(ring), Some(ob)) = (outer.ring(), &outer.bounds) else {
+                return Vec::new();
+            };
+            items
+                .iter()
+                .enumerate()
+                .filter(|&(i, inner)| {
+                    i != o
+                        && inner
+                            .bounds
+                            .as_ref()
+                            .is_some_and(|ib| within(ib, ob) && area(ib) < area(ob) - EPS)
+                        && winding_number(ring, inner.footprint[0]) != 0
+                })
+                .map(|(i, _)| i)
+                .collect()
+        })
+        .collect()
+}
+
+/// Greedy tour: always the nearest toolpath whose predecessors are cut.
+fn nearest_neighbour(items: &mut [Item], before: &[Vec<usize>], start: Vec2) -> Vec<usize> {
+    let n = items.len();
+    let mut waiting: Vec<usize> = before.iter().map(Vec::len).collect();
+    let mut after = vec![Vec::new(); n];
+    for (b, preds) in before.iter().enumerate() {
+        for &a in preds {
+            after[a].push(b);
+        }
+    }
+    let mut done = vec![false; n];
+    let mut order = Vec::with_capacity(n);
+    let mut pos = start;
+    for _ in 0..n {
+        let mut best: Option<(usize, Approach)> = None;
+        for i in (0..n).filter(|&i| !done[i] && waiting[i] == 0) {
+            let limit = best.as_ref().map_or(f64::INFINITY, |(_, a)| a.dist);
+            if let Some(approach) = items[i].approach(pos, limit) {
+                best = Some((i, approach));
+            }
+        }
+        let Some((i, approach)) = best else {
+            break;
+        };
+        let item = &mut items[i];
+        item.enter(&approach);
+        pos = item.exit;
+        done[i] = true;
+        order.push(i);
+        for &b in &after[i] {
+            waiting[b] -= 1;
+        }
+    }
+    order
+}
+
+/// Reverse stretches of the tour (flipping each toolpath in them) while
+/// that shortens it and keeps every toolpath after its predecessors.
+fn two_opt(order: &mut [usize], items: &mut [Item], before: &[Vec<usize>], start: Vec2) {
+    let n = order.len();
+    let mut rank = vec![0; items.len()];
+    for (r, &i) in order.iter().enumerate() {
+        rank[i] = r;
+    }
+    for _ in 0..MAX_SWEEPS {
+        let mut improved = false;
+        for i in 0..n {
+            let prev = if i == 0 {
+                start
+            } else {
+                items[order[i - 1]].exit
+            };
+            for j in i..n {
+                if !items[order[j]].flippable() {
+                    break;
+                }
+                let (a, b) = (items[order[i]].entry, items[order[j]].exit);
+                let next = order.get(j + 1).map(|&k| items[k].entry);
+                let old = Vec2::dist(prev, a) + next.map_or(0.0, |x| Vec2::dist(b, x));
+                let new = Vec2::dist(prev, b) + next.map_or(0.0, |x| Vec2::dist(a, x));
+                let ordered = || {
+                    order[i..=j]
+                        .iter()
+                        .all(|&k| before[k].iter().all(|&p| !(i..=j).contains(&rank[p])))
+                };
+                if new < old - EPS && ordered() {
+                    order[i..=j].reverse();
+                    for r in i..=j {
+                        rank[order[r]] = r;
+                        items[order[r]].flip();
+                    }
+                    improved = true;
+                }
+            }
+        }
+        if !improved {
+            break;
+        }
+    }
+}
+
+/// Move each loop's start to where the way in and out is shortest, now
+/// that its neighbours are settled.
+fn place_loop_starts(order: &[usize], items: &mut [Item], start: Vec2) {
+    let mut pos = start;
+    for (r, &i) in order.iter().enumerate() {
+        let next = order.get(r + 1).map(|&k| items[k].entry);
+        let item = &mut items[i];
+        if let (Kind::Loop, Some(chain)) = (item.kind, &item.chain) {
+            let (seg, at) = nearest_on_loop(&chain.points, pos, next);
+            let cost = |p: Vec2| Vec2::dist(pos, p) + next.map_or(0.0, |x| Vec2::dist(p, x));
+            if cost(at) < cost(item.entry) - EPS {
+                item.restart(seg, at);
+            }
+        }
+        pos = item.exit;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geometry::Polyline;
+    use crate::toolpath::{ContourStrategy, CutParams, LaserCutStrategy, ToolpathStrategy};
+
+    fn square(x: f64, y: f64, size: f64) -> Polyline {
+        Polyline::new(
+            vec![
+                Vec2::new(x, y),
+                Vec2::new(x + size, y),
+                Vec2::new(x + size, y + size),
+                Vec2::new(x, y + size),
+            ],
+            true,
+        )
+    }
+
+    fn line(a: Vec2, b: Vec2) -> Polyline {
+        Polyline::new(vec![a, b], false)
+    }
+
+    fn cut_length(tps: &[Toolpath]) -> f64 {
+        let moves: Vec<&ToolpathMove> = tps.iter().flat_map(|tp| &tp.moves).collect();
+        moves
+            .windows(2)
+            .filter(|w| !w[1].rapid)
+            .map(|w| Vec2::dist(xy(w[0]), xy(w[1])))
+            .sum()
+    }
+
+    #[test]
+    fn test_scattered_parts_are_toured_not_zigzagged() {
+        // A row of parts listed far-near-far-near along X.
+        let parts: Vec<Polyline> = [9, 0, 8, 1, 7, 2, 6, 3, 5, 4]
+            .iter()
+            .map(|&i| square(i as f64 * 20.0, 0.0, 10.0))
+            .collect();
+        let tps = LaserCutStrategy::new(80.0).generate(&parts, &CutParams::default());
+        let start = Vec2::new(0.0, 0.0);
+        let before = rapid_distance(&tps, start);
+        let ordered = order_toolpaths(tps.clone(), start, &OrderParams::default());
+        let after = rapid_distance(&ordered, start);
+
+        assert_eq!(ordered.len(), 10);
+        assert!(after < before / 3.0, "{} -> {}", before, after);
+        // Left to right, each part cut exactly as planned.
+        let xs: Vec<f64> = ordered.iter().map(|tp| tp.moves[0].x).collect();
+        assert!(xs.windows(2).all(|w| w[0] < w[1]), "{:?}", xs);
+        assert!((cut_length(&ordered) - cut_length(&tps)).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_holes_are_cut_before_their_part() {
+        // The part outline is listed first and is nearest the origin.
+        let contours = vec![
+            square(0.0, 0.0, 50.0),
+            square(30.0, 30.0, 10.0),
+            square(100.0, 0.0, 10.0),
+        ];
+        let params = CutParams {
+            cut_z: -1.0,
+            ..CutParams::default()
+        };
+        let tps = ContourStrategy.generate(&contours, &params);
+        let ordered = order_toolpaths(
+            tps,
+            Vec2::new(-5.0, -5.0),
+            &OrderParams {
+                reverse: true,
+                start_points: true,
+            },
+        );
+        let outline = ordered
+            .iter()
+            .position(|tp| tp.moves.iter().any(|m| m.x < 0.0))
+            .unwrap();
+        let hole = ordered
+            .iter()
+            .position(|tp| tp.moves.iter().all(|m| (25.0..45.0).contains(&m.x)))
+            .unwrap();
+        assert!(hole < outline, "hole {} outline {}", hole, outline);
+    }
+
+    #[test]
+    fn test_open_paths_reverse_and_loops_restart_nearby() {
+        let tps = LaserCutStrategy::new(50.0).generate(
+            &[
+                line(Vec2::new(10.0, 5.0), Vec2::new(0.0, 0.0)),
+                square(20.0, 0.0, 10.0),
+            ],
+            &CutParams::default(),
+        );
+        let start = Vec2::new(0.0, 0.0);
+        let fixed = order_toolpaths(tps.clone(), start, &OrderParams::default());
+        let free = order_toolpaths(
+            tps.clone(),
+            start,
+            &OrderParams {
+                reverse: true,
+                start_points: true,
+            },
+        );
+
+        // The line now starts at the origin and ends next to the square,
+        // which starts on its left edge rather than its corner.
+        assert!(rapid_distance(&free, start) < rapid_distance(&fixed, start));
+        assert_eq!(
+            (free[0].moves[0].x, free[0].moves.last().unwrap().x),
+            (0.0, 10.0)
+        );
+        let square_start = xy(&free[1].moves[0]);
+        assert!(Vec2::dist(square_start, Vec2::new(20.0, 5.0)) < 1e-9);
+        assert!((cut_length(&free) - cut_length(&tps)).abs() < 1e-9);
+        assert!(free
+            .iter()
+            .flat_map(|tp| &tp.moves)
+            .all(|m| m.rapid || m.power == Some(50.0)));
+
+        // A loop restarted mid-edge still closes and keeps its length.
+        let lp = Chain::parse(&tps[1])
+            .unwrap()
+            .restarted(1, Vec2::new(30.0, 4.0))
+            .into_toolpath();
+        assert_eq!(xy(&lp.moves[0]), xy(lp.moves.last().unwrap()));
+        assert!((cut_length(&[lp]) - 40.0).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,409 @@
+//! Raster engraving of bitmaps on a laser.
+//!
+//! Swiss-cheese layer: **Strategy selection**
+//! Extension point: add a `Dither` mode, with its error-diffusion kernel
+//! if it has one.
+//!
+//! The image is resampled so that one pixel is one dot: as wide as the
+//! line interval and one scanline tall. Each pixel's darkness becomes a
+//! power level, either on/off by a threshold or error diffusion
+//! (Floyd-Steinberg, Jarvis-Judice-Ninke) or modulated in grayscale
+//! between `min_power` and the laser's power. Scanlines run along X from
+//! the top of the image down, trimmed to their first and last burning dot
+//! and skipped when blank; neighbouring dots at the same power merge into
+//! one move. Every line is overscanned at zero power on both ends so the
+//! head is at feed when the beam fires, and lines alternate direction
+//! unless `bidirectional` is off.
+
+use serde::{Deserialize, Serialize};
+
+use crate::geometry::{Polyline, Toolpath, Vec2};
+use crate::image::GrayImage;
+
+/// Most dots in one engraving, e.g. 500 × 500 mm at 0.1 mm.
+const MAX_DOTS: f64 = 32_000_000.0;
+
+/// Floyd-Steinberg error diffusion: (dx, dy, weight) of 16.
+const FLOYD_STEINBERG: [(isize, usize, f64); 4] =
+    [(1, 0, 7.0), (-1, 1, 3.0), (0, 1, 5.0), (1, 1, 1.0)];
+
+/// Jarvis-Judice-Ninke error diffusion: (dx, dy, weight) of 48.
+const JARVIS: [(isize, usize, f64); 12] = [
+    (1, 0, 7.0),
+    (2, 0, 5.0),
+    (-2, 1, 3.0),
+    (-1, 1, 5.0),
+    (0, 1, 7.0),
+    (1, 1, 5.0),
+    (2, 1, 3.0),
+    (-2, 2, 1.0),
+    (-1, 2, 3.0),
+    (0, 2, 5.0),
+    (1, 2, 3.0),
+    (2, 2, 1.0),
+];
+
+/// How gray becomes laser power.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Dither {
+    /// Burn every pixel at least `threshold` dark.
+    Threshold,
+    #[default]
+    FloydSteinberg,
+    Jarvis,
+    /// Power proportional to darkness.
+    Grayscale,
+}
+
+impl Dither {
+    /// Power levels (0..1) for a darkness map `cols` wide.
+    fn levels(self, darkness: &[f64], cols: usize, threshold: f64) -> Vec<f64> {
+        match self {
+            Dither::Threshold => darkness
+                .iter()
+                .map(|&d| if d >= threshold { 1.0 } else { 0.0 })
+                .collect(),
+            // Whole 8-bit steps, so a flat area is one move.
+            Dither::Grayscale => darkness
+                .iter()
+                .map(|&d| (d.clamp(0.0, 1.0) * 255.0).round() / 255.0)
+                .collect(),
+            Dither::FloydSteinberg => diffuse(darkness, cols, threshold, &FLOYD_STEINBERG, 16.0),
+            Dither::Jarvis => diffuse(darkness, cols, threshold, &JARVIS, 48.0),
+        }
+    }
+}
+
+/// Threshold each pixel in reading order, pushing the rounding error onto
+/// the pixels not yet visited.
+fn diffuse(
+    darkness: &[f64],
+    cols: usize,
+    threshold: f64,
+    kernel: &[(isize, usize, f64)],
+    total: f64,
+) -> Vec<f64> {
+    let rows = darkness.len() / cols;
+    let mut d = darkness.to_vec();
+    let mut out = vec![0.0; d.len()];
+    for y in 0..rows {
+        for x in 0..cols {
+            let i = y * cols + x;
+            out[i] = if d[i] >= threshold { 1.0 } else { 0.0 };
+            let error = d[i] - out[i];
+            for &(dx, dy, weight) in kernel {
+                let nx = x as isize + dx;
+                if nx >= 0 && (nx as usize) < cols && y + dy < rows {
+                    d[(y + dy) * cols + nx as usize] += error * weight / total;
+                }
+            }
+        }
+    }
+    out
+}
+
+/// Size, placement and tone of an engraved image.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RasterParams {
+    /// Engraved width (mm).
+    #[serde(default = "default_width")]
+    pub width: f64,
+    /// Engraved height (mm); by default the image keeps its aspect ratio.
+    #[serde(default)]
+    pub height: Option<f64>,
+    /// Lower-left corner of the image (mm).
+    #[serde(default)]
+    pub x: f64,
+    #[serde(default)]
+    pub y: f64,
+    /// Scanline spacing, which is also the dot width (mm).
+    #[serde(default = "default_line_interval")]
+    pub line_interval: f64,
+    #[serde(default)]
+    pub dither: Dither,
+    /// Darkness (0..1) from which a dot burns in the on/off modes.
+    #[serde(default = "default_threshold")]
+    pub threshold: f64,
+    /// Burn the light parts instead of the dark ones.
+    #[serde(default)]
+    pub invert: bool,
+    /// Power (%) for the lightest gray that burns in grayscale mode.
+    #[serde(default)]
+    pub min_power: f64,
+    /// Travel at zero power past both ends of each line (mm).
+    #[serde(default = "default_overscan")]
+    pub overscan: f64,
+    /// Engrave every other line right to left.
+    #[serde(default = "default_bidirectional")]
+    pub bidirectional: bool,
+}
+
+fn default_width() -> f64 {
+    50.0
+}
+fn default_line_interval() -> f64 {
+    0.1
+}
+fn default_threshold() -> f64 {
+    0.5
+}
+fn default_overscan() -> f64 {
+    2.0
+}
+fn default_bidirectional() -> bool {
+    true
+}
+
+impl Default for RasterParams {
+    fn default() -> Self {
+        Self {
+            width: default_width(),
+            height: None,
+            x: 0.0,
+            y: 0.0,
+            line_interval: default_line_interval(),
+            dither: Dither::default(),
+            threshold: default_threshold(),
+            invert: false,
+            min_power: 0.0,
+            overscan: default_overscan(),
+            bidirectional: default_bidirectional(),
+        }
+    }
+}
+
+impl RasterParams {
+    pub fn from_json(json: &str) -> Result<Self, String> {
+        let params: Self = serde_json::from_str(json).map_err(|e| e.to_string())?;
+        let size_ok = |v: f64| v.is_finite() && v > 0.0;
+        if !(size_ok(params.width) && params.height.is_none_or(size_ok)) {
+            return Err("Image width and height must be positive".into());
+        }
+        if !size_ok(params.line_interval) {
+            return Err("Line interval must be positive".into());
+        }
+        if !(params.x.is_finite() && params.y.is_finite()) {
+            return Err("Image position must be finite".into());
+        }
+        Ok(params)
+    }
+
+    /// Engraved width and height (mm).
+    pub fn size(&self, image: &GrayImage) -> (f64, f64) {
+        let height = self
+            .height
+            .unwrap_or(self.width * image.height as f64 / image.width as f64);
+        (self.width, height)
+    }
+
+    /// The rectangle the image covers.
+    pub fn frame(&self, image: &GrayImage) -> Polyline {
+        let (w, h) = self.size(image);
+        let (x, y) = (self.x, self.y);
+        Polyline::new(
+            vec![
+                Vec2::new(x, y),
+                Vec2::new(x + w, y),
+                Vec2::new(x + w, y + h),
+                Vec2::new(x, y + h),
+            ],
+            true,
+        )
+    }
+}
+
+/// Scanlines engraving `image`, with `power` (%) for the darkest dots.
+pub fn raster_engrave(
+    image: &GrayImage,
+    params: &RasterParams,
+    power: f64,
+) -> Result<Vec<Toolpath>, String> {
+    let (width, height) = params.size(image);
+    let cols = (width / params.line_interval).round().max(1.0);
+    let rows = (height / params.line_interval).round().max(1.0);
+    if cols * rows > MAX_DOTS {
+        return Err(format!(
+            "Image of {:.0} x {:.0} dots is too large; use a coarser line interval",
+            cols, rows
+        ));
+    }
+    let (cols, rows) = (cols as usize, rows as usize);
+    let darkness: Vec<f64> = image
+        .resample(cols, rows)
+        .pixels
+        .iter()
+        .map(|&l| if params.invert { l } else { 1.0 - l })
+        .collect();
+    let levels = params.dither.levels(&darkness, cols, params.threshold);
+    let min_power = params.min_power.clamp(0.0, power);
+    let power_at = |level: f64| {
+        if level > 0.0 {
+            min_power + level * (power - min_power)
+        } else {
+            0.0
+        }
+    };
+
+    let dot = width / cols as f64;
+    let pitch = height / rows as f64;
+    let edge = |col: usize| params.x + col as f64 * dot;
+    let overscan = params.overscan.max(0.0);
+    let mut tp = Toolpath::new();
+    let mut forward = true;
+    for (row, line) in levels.chunks(cols).enumerate() {
+        let Some(first) = line.iter().position(|&l| l > 0.0) else {
+            continue;
+        };
+        let last = line.iter().rposition(|&l| l > 0.0).unwrap_or(first);
+        // Runs of equal level as (first col, end col, level).
+        let mut runs: Vec<(usize, usize, f64)> = Vec::new();
+        for (col, &level) in line.iter().enumerate().take(last + 1).skip(first) {
+            match runs.last_mut() {
+                Some(run) if run.2 == level => run.1 = col + 1,
+                _ => runs.push((col, col + 1, level)),
+            }
+        }
+        let y = params.y + height - (row as f64 + 0.5) * pitch;
+        let (start, end, dir) = if forward {
+            (edge(first), edge(last + 1), 1.0)
+        } else {
+            runs.reverse();
+            (edge(last + 1), edge(first), -1.0)
+        };
+        tp.rapid(start - dir * overscan, y, 0.0);
+        if overscan > 0.0 {
+            tp.cut_with_power(start, y, 0.0, 0.0);
+        }
+        for (c0, c1, level) in runs {
+            let x = if forward { edge(c1) } else { edge(c0) };
+            tp.cut_with_power(x, y, 0.0, power_at(level));
+        }
+        if overscan > 0.0 {
+            tp.cut_with_power(end + dir * overscan, y, 0.0, 0.0);
+        }
+        if params.bidirectional {
+            forward = !forward;
+        }
+    }
+    if tp.moves.is_empty() {
+        Ok(Vec::new())
+    } else {
+        Ok(vec![tp])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn image(width: usize, height: usize, pixels: Vec<f64>) -> GrayImage {
+        GrayImage {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    #[test]
+    fn scanlines_skip_blank_lines_and_overscan() {
+        // Top row: white, black, black, white; the rest white but a dot.
+        let mut pixels = vec![1.0; 16];
+        pixels[1] = 0.0;
+        pixels[2] = 0.0;
+        pixels[14] = 0.0;
+        let params = RasterParams {
+            width: 4.0,
+            line_interval: 1.0,
+            dither: Dither::Threshold,
+            overscan: 1.0,
+            ..RasterParams::default()
+        };
+        let tps = raster_engrave(&image(4, 4, pixels), &params, 80.0).unwrap();
+        let moves = &tps[0].moves;
+        let rapids: Vec<_> = moves.iter().filter(|m| m.rapid).collect();
+        // Two lines engraved, the middle two skipped.
+        assert_eq!(rapids.len(), 2);
+        assert_eq!((rapids[0].x, rapids[0].y), (0.0, 3.5));
+        // The second line runs right to left from its overscan.
+        assert_eq!((rapids[1].x, rapids[1].y), (4.0, 0.5));
+        let burns: Vec<_> = moves
+            .iter()
+            .filter(|m| m.power.is_some_and(|p| p > 0.0))
+            .map(|m| (m.x, m.y))
+            .collect();
+        assert_eq!(burns, vec![(3.0, 3.5), (2.0, 0.5)]);
+        assert!(moves
+            .iter()
+            .all(|m| m.z == 0.0 && (-1.0..=5.0).contains(&m.x)));
+    }
+
+    #[test]
+    fn grayscale_modulates_power_and_merges_runs() {
+        let params = RasterParams {
+            width: 4.0,
+            line_interval: 1.0,
+            dither: Dither::Grayscale,
+            min_power: 20.0,
+            overscan: 0.0,
+            ..RasterParams::default()
+        };
+        let img = image(4, 1, vec![0.5, 0.5, 0.0, 1.0]);
+        let moves = &raster_engrave(&img, &params, 100.0).unwrap()[0].moves;
+        let powers: Vec<_> = moves.iter().filter_map(|m| m.power).collect();
+        assert_eq!(powers.len(), 2);
+        assert!((powers[0] - (20.0 + 80.0 * 128.0 / 255.0)).abs() < 1e-9);
+        assert_eq!(powers[1], 100.0);
+        assert_eq!(moves.last().unwrap().x, 3.0);
+    }
+
+    #[test]
+    fn error_diffusion_keeps_the_tone() {
+        let gray = image(20, 20, vec![0.75; 400]);
+        for dither in [Dither::FloydSteinberg, Dither::Jarvis] {
+            let levels = dither.levels(&[0.25; 400], 20, 0.5);
+            let burnt = levels.iter().filter(|&&l| l == 1.0).count();
+            assert!((80..=120).contains(&burnt), "{:?}: {}", dither, burnt);
+            let params = RasterParams {
+                width: 20.0,
+                line_interval: 1.0,
+                dither,
+                ..RasterParams::default()
+            };
+            assert!(!raster_engrave(&gray, &params, 50.0).unwrap().is_empty());
+        }
+        // Threshold mode burns nothing of a light gray.
+        let params = RasterParams {
+            width: 20.0,
+            line_interval: 1.0,
+            dither: Dither::Threshold,
+            ..RasterParams::default()
+        };
+        assert!(raster_engrave(&gray, &params, 50.0).unwrap().is_empty());
+        assert!(RasterParams::from_json(r#"{"line_interval": 0}"#).is_err());
+        let json = r#"{"dither": "floyd_steinberg", "width": 30}"#;
+        assert_eq!(RasterParams::from_json(json).unwrap().width, 30.0);
+    }
+
+    #[test]
+    fn oversized_and_non_finite_rasters_are_rejected() {
+        // serde_json reads an out-of-range literal as infinity.
+        for json in [
+            r#"{"width": 1e999}"#,
+            r#"{"height": 1e999}"#,
+            r#"{"line_interval": 1e999}"#,
+            r#"{"x": -1e999}"#,
+        ] {
+            assert!(RasterParams::from_json(json).is_err(), "{}", json);
+        }
+
+        let params = RasterParams::from_json(r#"{"width": 1000, "line_interval": 1e-6}"#).unwrap();
+        let err = raster_engrave(&image(1, 1, vec![0.0]), &params, 50.0).unwrap_err();
+        assert!(err.contains("too large"), "{}", err);
+        let params = RasterParams {
+            width: 100.0,
+            ..RasterParams::default()
+        };
+        assert!(raster_engrave(&image(1, 1, vec![1.0]), &params, 50.0).is_ok());
+    }
+}
@@ -0,0 +1,480 @@
+//! Rest machining: cut only where earlier operations left material.
+//!
+//! Swiss-cheese layer: **Strategy selection**
+//! Extension point: swap the heightmap in `RestStock` for a dexel model,
+//! or trim 2-D passes to the rest area instead of re-planning them.
+//!
+//! The earlier operations are replayed with their own tools on a
+//! heightmap stock (see `simulation::Stock`). For 2-D area strategies the
+//! stock still standing above a pass depth is contoured into a region,
+//! grown by the new tool's diameter and clipped to the pocket, and the
+//! strategy plans inside that instead of the whole pocket. For 3-D surface
+//! strategies the stock is compared with the model, and the finishing
+//! passes are trimmed to where the new tool reaches leftover material.
+
+use crate::geometry::boolean;
+use crate::geometry::height_grid::HeightGrid;
+use crate::geometry::nesting::{build_regions, FillRule};
+use crate::geometry::offset::{offset_region, JoinType};
+use crate::geometry::{Mesh, Polyline, Toolpath, Vec2};
+use crate::simulation::Stock;
+use crate::slicer::mesh_height_at;
+use crate::tool::Tool;
+
+/// Material thinner than this is not worth another pass (mm).
+pub const REST_TOLERANCE: f64 = 0.05;
+
+/// Stock cells across the new tool.
+const CELLS_PER_DIAMETER: f64 = 8.0;
+
+/// Finest stock cell (mm).
+const MIN_CELL: f64 = 0.02;
+
+/// Most stock cells; coarser cells are used beyond it.
+const MAX_CELLS: f64 = 500_000.0;
+
+/// An operation that ran before the rest pass.
+#[derive(Debug, Clone, Copy)]
+pub struct PriorCut<'a> {
+    pub tool: &'a Tool,
+    pub toolpaths: &'a [Toolpath],
+}
+
+/// The stock the earlier operations left behind.
+#[derive(Debug, Clone)]
+pub struct RestStock {
+    stock: Stock,
+}
+
+impl RestStock {
+    /// Stock over `min`..`max` (plus room for every tool) with its top at
+    /// `top`, cut by `prior`, fine enough to resolve what `tool` can reach.
+    pub fn new(min: Vec2, max: Vec2, top: f64, tool: &Tool, prior: &[PriorCut]) -> Self {
+        let pad = prior
+            .iter()
+            .map(|c| c.tool.diameter)
+            .fold(tool.diameter, f64::max);
+        let min = Vec2::new(min.x - pad, min.y - pad);
+        let max = Vec2::new(max.x + pad, max.y + pad);
+        let area = (max.x - min.x) * (max.y - min.y);
+        let cell = (tool.diameter / CELLS_PER_DIAMETER)
+            .max(MIN_CELL)
+            .max((area / MAX_CELLS).sqrt());
+        let mut stock = Stock::new(min, max, top, cell);
+        for c in prior {
+            stock.cut(c.toolpaths, c.tool);
+        }
+        Self { stock }
+    }
+
+    /// Stock around 2-D outlines, whose top is Z 0.
+    pub fn for_contours(contours: &[Polyline], tool: &Tool, prior: &[PriorCut]) -> Self {
+        let points: Vec<Vec2> = contours.iter().flat_map(|c| c.points.clone()).collect();
+        let (min, max) = points.iter().fold(
+            (Vec2::new(f64::MAX, f64::MAX), Vec2::new(f64::MIN, f64::MIN)),
+            |(lo, hi), p| {
+                (
+                    Vec2::new(lo.x.min(p.x), lo.y.min(p.y)),
+                    Vec2::new(hi.x.max(p.x), hi.y.max(p.y)),
+                )
+            },
+        );
+        if points.is_empty() {
+            return Self::new(Vec2::new(0.0, 0.0), Vec2::new(0.0, 0.0), 0.0, tool, prior);
+        }
+        Self::new(min, max, 0.0, tool, prior)
+    }
+
+    /// Stock filling the bounding box of `mesh`.
+    pub fn for_mesh(mesh: &Mesh, tool: &Tool, prior: &[PriorCut]) -> Self {
+        let (min, max, top) = match &mesh.bounds {
+            Some(b) => (
+                Vec2::new(b.min.x, b.min.y),
+                Vec2::new(b.max.x, b.max.y),
+                b.max.z,
+            ),
+            None => (Vec2::new(0.0, 0.0), Vec2::new(0.0, 0.0), 0.0),
+        };
+        Self::new(min, max, top, tool, prior)
+    }
+
+    /// Outlines for a 2-D area strategy cutting at `z`: the part of the
+    /// pocket bounded by `contours` within one `tool` diameter of stock
+    /// still standing above `z`. Empty when the pocket is clear.
+    pub fn region_at(&self, contours: &[Polyline], z: f64, tool: &Tool) -> Vec<Polyline> {
+        let pocket: Vec<Polyline> = build_regions(contours, FillRule::EvenOdd)
+            .iter()
+            .flat_map(|r| r.rings())
+            .collect();
+        // Level loops keep the high side on their right; reversed they
+        // follow the region convention (area on the left).
+        let level = z + REST_TOLERANCE;
+        let material: Vec<Polyline> = self
+            .grid(level - 1.0)
+            .level_loops(level)
+            .into_iter()
+            .map(|mut ring| {
+                ring.reverse();
+                Polyline::new(simplify(&ring, self.stock.cell / 4.0), true)
+            })
+            .collect();
+        let left = boolean::intersection(&pocket, &material);
+        // The contour runs between cell centres, so it leaves a sliver
+        // up to a cell wide along every wall the earlier tool finished.
+        // Opening by one cell drops it.
+        let cell = self.stock.cell;
+        let shrunk = offset_region(&left, -cell, JoinType::Round);
+        if shrunk.is_empty() {
+            return Vec::new();
+        }
+        let left = offset_region(&shrunk, cell, JoinType::Round);
+        let reach = offset_region(&left, tool.diameter + cell, JoinType::Round);
+        boolean::intersection(&pocket, &reach)
+    }
+
+    /// Trim surface passes for `tool` to the stretches that come within
+    /// reach of stock standing more than `REST_TOLERANCE` above `mesh`.
+    /// Each stretch becomes its own toolpath, entered and left at
+    /// `safe_z`.
+    pub fn trim_surface(
+        &self,
+        toolpaths: &[Toolpath],
+        mesh: &Mesh,
+        tool: &Tool,
+        safe_z: f64,
+    ) -> Vec<Toolpath> {
+        let mask = self.leftover_mask(mesh, tool.diameter / 2.0 + self.stock.cell);
+        let near = |x: f64, y: f64| mask.contains(&self.stock, x, y);
+        let mut out = Vec::new();
+        for tp in toolpaths {
+            let mut run: Option<Toolpath> = None;
+            for pair in tp.moves.windows(2) {
+                let (a, b) = (&pair[0], &pair[1]);
+                let keep = !b.rapid && (near(a.x, a.y) || near(b.x, b.y));
+                if !keep {
+                    if let Some(done) = run.take() {
+                        out.push(finish(done, safe_z));
+                    }
+                    continue;
+                }
+                let run = run.get_or_insert_with(|| {
+                    let mut start = Toolpath::new();
+                    start.rapid(a.x, a.y, safe_z);
+                    if a.z < safe_z {
+                        start.cut(a.x, a.y, a.z);
+                    }
+                    start
+                });
+                run.cut(b.x, b.y, b.z);
+            }
+            if let Some(done) = run {
+                out.push(finish(done, safe_z));
+            }
+        }
+        out
+    }
+
+    /// The stock as a height grid through its cell centres, with a border
+    /// at `floor` so every loop above it closes.
+    fn grid(&self, floor: f64) -> HeightGrid {
+        let s = &self.stock;
+        let (nx, ny) = (s.nx + 2, s.ny + 2);
+        let mut z = vec![floor; nx * ny];
+        for j in 0..s.ny {
+            for i in 0..s.nx {
+                z[(j + 1) * nx + i + 1] = s.height(i, j);
+            }
+        }
+        let first = s.cell_centre(0, 0);
+        HeightGrid {
+            origin: Vec2::new(first.x - s.cell, first.y - s.cell),
+            spacing: s.cell,
+            nx,
+            ny,
+            z,
+        }
+    }
+
+    /// Cells within `reach` (measured square) of leftover stock on the
+    /// model.
+    fn leftover_mask(&self, mesh: &Mesh, reach: f64) -> Mask {
+        let s = &self.stock;
+        let mut cells = vec![false; s.nx * s.ny];
+        for j in 0..s.ny {
+            for i in 0..s.nx {
+                let c = s.cell_centre(i, j);
+                if let Some(model) = mesh_height_at(mesh, c.x, c.y) {
+                    cells[j * s.nx + i] = s.height(i, j) > model + REST_TOLERANCE;
+                }
+            }
+        }
+        let k = (reach / s.cell).ceil() as usize;
+        Mask {
+            cells: dilate(&cells, s.nx, s.ny, k),
+        }
+    }
+}
+
+/// Cells of a stock grid.
+struct Mask {
+    cells: Vec<bool>,
+}
+
+impl Mask {
+    fn contains(&self, stock: &Stock, x: f64, y: f64) -> bool {
+        let i = ((x - stock.min.x) / stock.cell).floor();
+        let j = ((y - stock.min.y) / stock.cell).floor();
+        if i < 0.0 || j < 0.0 || i >= stock.nx as f64 || j >= stock.ny as f64 {
+            return false;
+        }
+        self.cells[j as usize * stock.nx + i as usize]
+    }
+}
+
+/// Grow the set cells of an `nx` × `ny` grid by `k` cells each way (a
+/// square neighbourhood), one row pass and one column pass.
+fn dilate(cells: &[bool], nx: usize, ny: usize, k: usize) -> Vec<bool> {
+    let pass = |src: &[bool], len: usize, lines: usize, at: &dyn Fn(usize, usize) -> usize| {
+        let mut dst = vec![false; src.len()];
+        for line in 0..lines {
+            // Distance back to the last set cell, then forward.
+            let mut since = usize::MAX;
+            for p in 0..len {
+                since = if src[at(line, p)] {
+                    0
+                } else {
+                    since.saturating_add(1)
+                };
+                dst[at(line, p)] = since <= k;
+            }
+            since = usize::MAX;
+            for p in (0..len).rev() {
+                since = if src[at(line, p)] {
+                    0
+                } else {
+                    since.saturating_add(1)
+                };
+                if since <= k {
+                    dst[at(line, p)] = true;
+                }
+            }
+        }
+        dst
+    };
+    let rows = pass(cells, nx, ny, &|j, i| j * nx + i);
+    pass(&rows, ny, nx, &|i, j| j * nx + i)
+}
+
+/// Drop ring points within `tolerance` of the chord between their
+/// neighbours, mostly the runs of cell crossings along straight walls.
+fn simplify(ring: &[Vec2], tolerance: f64) -> Vec<Vec2> {
+    let mut out: Vec<Vec2> = Vec::with_capacity(ring.len());
+    for (k, &b) in ring.iter().enumerate() {
+        let Some(&a) = out.last() else {
+            out.push(b);
+            continue;
+        };
+        let c = ring[(k + 1) % ring.len()];
+        let (ab, ac) = (
+            Vec2::new(b.x - a.x, b.y - a.y),
+            Vec2::new(c.x - a.x, c.y - a.y),
+        );
+        let len = Vec2::dist(a, c);
+        let off = if len > 0.0 {
+            (ab.x * ac.y - ab.y * ac.x).abs() / len
+        } else {
+            Vec2::dist(a, b)
+        };
+        if off > tolerance {
+            out.push(b);
+        }
+    }
+    out
+}
+
+fn finish(mut tp: Toolpath, safe_z: f64) -> Toolpath {
+    if let Some((x, y, z)) = tp.moves.last().map(|m| (m.x, m.y, m.z)) {
+        if z < safe_z {
+            tp.rapid(x, y, safe_z);
+        }
+    }
+    tp
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geometry::{Triangle, Vec3};
+
+    fn rect(x0: f64, y0: f64, x1: f64, y1: f64) -> Polyline {
+        let p = Vec2::new;
+        Polyline::new(vec![p(x0, y0), p(x1, y0), p(x1, y1), p(x0, y1)], true)
+    }
+
+    fn flat(diameter: f64) -> Tool {
+        Tool {
+            diameter,
+            ..Tool::default()
+        }
+    }
+
+    /// Pocket a 20 mm square to `z` with a tool of `diameter`: a wall pass
+    /// and rows, so the corners keep a fillet of the tool radius.
+    fn rough(diameter: f64, z: f64) -> Toolpath {
+        let r = diameter / 2.0;
+        let (lo, hi) = (r, 20.0 - r);
+        let mut tp = Toolpath::new();
+        tp.rapid(lo, lo, 5.0);
+        for (x, y) in [(lo, lo), (hi, lo), (hi, hi), (lo, hi), (lo, lo)] {
+            tp.cut(x, y, z);
+        }
+        let mut y = lo;
+        while y < hi {
+            tp.cut(lo, y, z);
+            tp.cut(hi, y, z);
+            y += r;
+        }
+        tp.rapid(hi, y, 5.0);
+        tp
+    }
+
+    #[test]
+    fn corners_of_a_large_tool_pocket_are_left() {
+        let big = flat(10.0);
+        let rough = [rough(10.0, -2.0)];
+        let prior = [PriorCut {
+            tool: &big,
+            toolpaths: &rough,
+        }];
+        let pocket = [rect(0.0, 0.0, 20.0, 20.0)];
+        let small = flat(2.0);
+        let rest = RestStock::for_contours(&pocket, &small, &prior);
+
+        let region = rest.region_at(&pocket, -2.0, &small);
+        let corners = build_regions(&region, FillRule::EvenOdd);
+        assert_eq!(corners.len(), 4, "{:?}", region);
+        for c in &corners {
+            let b = c.outer.bounds().unwrap();
+            // A 5 mm fillet, grown by the 2 mm tool, in one corner.
+            assert!(
+                b.max.x - b.min.x < 7.5 && b.max.y - b.min.y < 7.5,
+                "{:?}",
+                b
+            );
+        }
+        assert!(boolean::area(&region) < 0.5 * 400.0);
+        // Above the roughing depth there was nothing to cut.
+        assert!(rest.region_at(&pocket, 1.0, &small).is_empty());
+    }
+
+    #[test]
+    fn cleared_pocket_leaves_nothing() {
+        let tool = flat(4.0);
+        // A pocket the roughing tool fits exactly: its corners are round.
+        let round = Polyline::new(
+            (0..64)
+                .map(|k| {
+                    let a = k as f64 / 64.0 * std::f64::consts::TAU;
+                    Vec2::new(10.0 + 8.0 * a.cos(), 10.0 + 8.0 * a.sin())
+                })
+                .collect(),
+            true,
+        );
+        let mut tp = Toolpath::new();
+        tp.rapid(10.0, 10.0, 5.0);
+        for ring in [2.0, 4.0, 6.0] {
+            for k in 0..=64 {
+                let a = k as f64 / 64.0 * std::f64::consts::TAU;
+                tp.cut(10.0 + ring * a.cos(), 10.0 + ring * a.sin(), -1.0);
+            }
+        }
+        let rough = [tp];
+        let prior = [PriorCut {
+            tool: &tool,
+            toolpaths: &rough,
+        }];
+        let pocket = [round];
+        let rest = RestStock::for_contours(&pocket, &flat(2.0), &prior);
+        assert!(rest.region_at(&pocket, -1.0, &flat(2.0)).is_empty());
+    }
+
+    #[test]
+    fn surface_passes_are_trimmed_to_leftover() {
+        // A flat 20 x 20 model at Z 0, stock top at Z 0 too; the earlier
+        // tool has cut a trench nowhere, so only a raised strip counts.
+        let n = Vec3::new(0.0, 0.0, 1.0);
+        let v = |x: f64, y: f64| Vec3::new(x, y, if x > 10.0 { -2.0 } else { 0.0 });
+        let mesh = Mesh::new(vec![
+            Triangle {
+                normal: n,
+                v0: v(0.0, 0.0),
+                v1: v(10.0, 0.0),
+                v2: v(10.0, 20.0),
+            },
+            Triangle {
+                normal: n,
+                v0: v(0.0, 0.0),
+                v1: v(10.0, 20.0),
+                v2: v(0.0, 20.0),
+            },
+            Triangle {
+                normal: n,
+                v0: v(10.01, 0.0),
+                v1: v(20.0, 0.0),
+                v2: v(20.0, 20.0),
+            },
+            Triangle {
+                normal: n,
+                v0: v(10.01, 0.0),
+                v1: v(20.0, 20.0),
+                v2: v(10.01, 20.0),
+            },
+        ]);
+        // Roughing took the right half down to -1.5, leaving 0.5 mm.
+        let big = flat(6.0);
+        let mut rough = Toolpath::new();
+        rough.rapid(14.0, -3.0, 5.0);
+        let mut x = 14.0;
+        while x < 23.5 {
+            rough.cut(x, -3.0, -1.5);
+            rough.cut(x, 23.0, -1.5);
+            x += 2.0;
+        }
+        let rough = [rough];
+        let prior = [PriorCut {
+            tool: &big,
+            toolpaths: &rough,
+        }];
+        let small = flat(2.0);
+        let rest = RestStock::for_mesh(&mesh, &small, &prior);
+
+        // A finishing row along X across the whole part.
+        let mut finish = Toolpath::new();
+        finish.rapid(0.0, 10.0, 5.0);
+        finish.cut(0.0, 10.0, 0.0);
+        for k in 1..=40 {
+            let x = k as f64 * 0.5;
+            finish.cut(x, 10.0, if x > 10.0 { -2.0 } else { 0.0 });
+        }
+        finish.rapid(20.0, 10.0, 5.0);
+        let trimmed = rest.trim_surface(&[finish], &mesh, &small, 5.0);
+        assert!(!trimmed.is_empty());
+        for tp in &trimmed {
+            assert!(tp.moves.first().unwrap().rapid && tp.moves.last().unwrap().rapid);
+            for m in tp.moves.iter().filter(|m| !m.rapid) {
+                // The left half was never cut but sits on the model.
+                assert!(m.x > 10.0 - 1.0 - 1.0, "{:?}", m);
+            }
+        }
+    }
+
+    #[test]
+    fn dilate_grows_a_square() {
+        let mut cells = vec![false; 49];
+        cells[3 * 7 + 3] = true;
+        let grown = dilate(&cells, 7, 7, 2);
+        let count = grown.iter().filter(|&&c| c).count();
+        assert_eq!(count, 25);
+        assert!(grown[7 + 1] && !grown[0]);
+    }
+}
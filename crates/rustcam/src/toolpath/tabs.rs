@@ -0,0 +1,282 @@
+//! Holding tabs for through-cut profiles.
+//!
+//! Swiss-cheese layer: **Strategy selection**
+//! Extension point: change `place` to honour user-picked tab positions.
+//!
+//! Tabs are short stretches of a closed profile where the final passes
+//! leave material standing, so the part stays attached to the sheet. CNC
+//! passes below the tab top lift over them; laser passes keep moving with
+//! the beam off. Auto-placed tabs are spread evenly and slid along the
+//! profile away from corners.
+
+use crate::geometry::{Tab, Vec2};
+
+/// Tab layout for closed profiles. No tabs unless `width` is positive and
+/// either `count` or `spacing` is set.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct TabParams {
+    /// Tabs per profile. Takes precedence over `spacing`.
+    pub count: u32,
+    /// Distance between tabs along the profile when `count` is 0.
+    pub spacing: f64,
+    /// Length of each tab along the profile.
+    pub width: f64,
+    /// Height of each tab above the floor of the cut.
+    pub height: f64,
+}
+
+impl TabParams {
+    pub fn enabled(&self) -> bool {
+        self.width > 0.0 && (self.count > 0 || self.spacing > 0.0)
+    }
+}
+
+/// Largest total change of direction (degrees) around a tab before the
+/// stretch counts as a corner.
+const CORNER_TURN_DEG: f64 = 15.0;
+
+/// A closed loop split at tab boundaries.
+pub(super) struct TabbedLoop {
+    /// Points from `pts[0]` round to `pts[0]` again; the flag is true when
+    /// the move ending at that point runs over a tab.
+    pub points: Vec<(Vec2, bool)>,
+    pub tabs: Vec<Tab>,
+}
+
+/// Place tabs on the closed tool path `pts` and split it at their ends.
+///
+/// `clearance` is added to the tab width to get the stretch the tool must
+/// skip (the tool diameter for a mill, zero for a laser). The last
+/// `reserved` mm before the start are kept free of tabs because a ramp
+/// entry descends there.
+pub(super) fn tabbed_loop(
+    pts: &[Vec2],
+    params: &TabParams,
+    clearance: f64,
+    reserved: f64,
+) -> TabbedLoop {
+    let n = pts.len();
+    let mut cum = vec![0.0];
+    for i in 0..n {
+        let d = Vec2::dist(pts[i], pts[(i + 1) % n]);
+        cum.push(cum[i] + d);
+    }
+    let spans = place(pts, &cum, params, params.width + clearance, reserved);
+
+    let tabs = spans
+        .iter()
+        .map(|&(a, b)| {
+            let c = point_at(pts, &cum, (a + b) / 2.0);
+            Tab {
+                x: c.x,
+                y: c.y,
+                width: params.width,
+                height: params.height,
+            }
+        })
+        .collect();
+
+    let mut points = Vec::with_capacity(n + 2 * spans.len() + 1);
+    let mut k = 0;
+    for i in 0..n {
+        let (s0, s1) = (cum[i], cum[i + 1]);
+        // Break the edge at every tab boundary it contains.
+        while k < 2 * spans.len() {
+            let s = if k % 2 == 0 {
+                spans[k / 2].0
+            } else {
+                spans[k / 2].1
+            };
+            if s > s1 {
+                break;
+            }
+            if s > s0 {
+                points.push((point_at(pts, &cum, s), k % 2 == 1));
+            }
+            k += 1;
+        }
+        let inside = k % 2 == 1;
+        points.push((pts[(i + 1) % n], inside));
+    }
+    TabbedLoop { points, tabs }
+}
+
+/// Arc-length spans `(start, end)` of the tabs, in order, all strictly
+/// inside `(0, perimeter - reserved)`.
+fn place(
+    pts: &[Vec2],
+    cum: &[f64],
+    params: &TabParams,
+    span: f64,
+    reserved: f64,
+) -> Vec<(f64, f64)> {
+    let perimeter = *cum.last().unwrap();
+    let usable = perimeter - reserved.clamp(0.0, perimeter / 2.0);
+    let wanted = if params.count > 0 {
+        params.count as usize
+    } else {
+        (perimeter / params.spacing).round() as usize
+    };
+    // Each tab needs its own slot at least twice its length.
+    let n = wanted.min((usable / (2.0 * span)).floor() as usize);
+    if n == 0 || span <= 0.0 {
+        return Vec::new();
+    }
+
+    let slot = usable / n as f64;
+    let slack = (slot - span) / 2.0;
+    let tries = 32;
+    (0..n)
+        .map(|k| {
+            let ideal = slot * (k as f64 + 0.5);
+            // Slide outward from the ideal spot until the tab is clear of
+            // corners; keep the ideal spot if nothing straight is in reach.
+            let centre = (0..=tries)
+                .flat_map(|j| {
+                    let d = slack * j as f64 / tries as f64;
+                    [ideal + d, ideal - d]
+                })
+                .find(|&c| turning(pts, cum, c - span, c + span) <= CORNER_TURN_DEG)
+                .unwrap_or(ideal);
+            (centre - span / 2.0, centre + span / 2.0)
+        })
+        .collect()
+}
+
+/// Total change of direction (degrees) at the vertices that lie strictly
+/// between arc lengths `a` and `b` (wrapping round the loop).
+fn turning(pts: &[Vec2], cum: &[f64], a: f64, b: f64) -> f64 {
+    let n = pts.len();
+    let perimeter = cum[n];
+    let mut total = 0.0;
+    for i in 0..n {
+        let s = cum[i];
+        let inside = [s - perimeter, s, s + perimeter]
+            .iter()
+            .any(|&t| t > a && t < b);
+        if !inside {
+            continue;
+        }
+        let prev = pts[(i + n - 1) % n];
+        let next = pts[(i + 1) % n];
+        let (ux, uy) = (pts[i].x - prev.x, pts[i].y - prev.y);
+        let (vx, vy) = (next.x - pts[i].x, next.y - pts[i].y);
+        total += (ux * vy - uy * vx).atan2(ux * vx + uy * vy).abs();
+    }
+    total.to_degrees()
+}
+
+/// Point at arc length `s` from `pts[0]` along the closed loop.
+fn point_at(pts: &[Vec2], cum: &[f64], s: f64) -> Vec2 {
+    let n = pts.len();
+    let i = cum
+        .partition_point(|&c| c <= s)
+        .saturating_sub(1)
+        .min(n - 1);
+    let len = cum[i + 1] - cum[i];
+    let t = if len > 0.0 { (s - cum[i]) / len } else { 0.0 };
+    let (a, b) = (pts[i], pts[(i + 1) % n]);
+    Vec2::new(a.x + (b.x - a.x) * t, a.y + (b.y - a.y) * t)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rect(w: f64, h: f64) -> Vec<Vec2> {
+        vec![
+            Vec2::new(0.0, 0.0),
+            Vec2::new(w, 0.0),
+            Vec2::new(w, h),
+            Vec2::new(0.0, h),
+        ]
+    }
+
+    fn tabs(count: u32, spacing: f64) -> TabParams {
+        TabParams {
+            count,
+            spacing,
+            width: 4.0,
+            height: 1.0,
+        }
+    }
+
+    fn dist_to_corner(t: &Tab, corners: &[Vec2]) -> f64 {
+        corners
+            .iter()
+            .map(|c| Vec2::dist(*c, Vec2::new(t.x, t.y)))
+            .fold(f64::INFINITY, f64::min)
+    }
+
+    #[test]
+    fn disabled_without_width_or_layout() {
+        assert!(!TabParams::default().enabled());
+        assert!(!TabParams {
+            width: 3.0,
+            ..TabParams::default()
+        }
+        .enabled());
+        assert!(tabs(2, 0.0).enabled());
+        assert!(tabs(0, 30.0).enabled());
+    }
+
+    #[test]
+    fn count_places_that_many_tabs_off_the_corners() {
+        let pts = rect(40.0, 20.0);
+        let lp = tabbed_loop(&pts, &tabs(4, 0.0), 3.0, 0.0);
+        assert_eq!(lp.tabs.len(), 4);
+        for t in &lp.tabs {
+            assert!(dist_to_corner(t, &pts) >= 7.0 - 1e-9, "{:?}", t);
+        }
+    }
+
+    #[test]
+    fn slides_tab_off_a_corner() {
+        // One tab on a 10x10 square ideally sits at the far corner (20 mm
+        // along); it must move onto a straight edge.
+        let pts = rect(10.0, 10.0);
+        let lp = tabbed_loop(&pts, &tabs(1, 0.0), 0.0, 0.0);
+        assert_eq!(lp.tabs.len(), 1);
+        assert!(dist_to_corner(&lp.tabs[0], &pts) >= 4.0 - 1e-9);
+    }
+
+    #[test]
+    fn spacing_sets_tab_count() {
+        let lp = tabbed_loop(&rect(40.0, 20.0), &tabs(0, 30.0), 0.0, 0.0);
+        assert_eq!(lp.tabs.len(), 4);
+    }
+
+    #[test]
+    fn small_profile_gets_fewer_tabs() {
+        let lp = tabbed_loop(&rect(6.0, 6.0), &tabs(8, 0.0), 3.0, 0.0);
+        assert_eq!(lp.tabs.len(), 1);
+    }
+
+    #[test]
+    fn split_points_cover_tabs_exactly() {
+        let pts = rect(40.0, 20.0);
+        let lp = tabbed_loop(&pts, &tabs(2, 0.0), 2.0, 0.0);
+        let mut over_tab = 0.0;
+        let mut prev = pts[0];
+        for &(p, tab) in &lp.points {
+            if tab {
+                over_tab += Vec2::dist(prev, p);
+            }
+            prev = p;
+        }
+        assert!((over_tab - 2.0 * 6.0).abs() < 1e-9);
+        assert_eq!(lp.points.last().unwrap().0, pts[0]);
+        assert!(!lp.points.last().unwrap().1);
+    }
+
+    #[test]
+    fn reserved_stretch_before_start_stays_clear() {
+        let pts = rect(40.0, 20.0);
+        let lp = tabbed_loop(&pts, &tabs(3, 0.0), 0.0, 30.0);
+        // The last 30 mm before the start run down the left edge and
+        // along the top from x = 10 to x = 0.
+        for t in &lp.tabs {
+            assert!(!(t.x < 1e-9 || (t.y == 20.0 && t.x < 10.0)), "{:?}", t);
+        }
+    }
+}
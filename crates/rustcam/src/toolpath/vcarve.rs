@@ -0,0 +1,378 @@
+//! V-carving along the medial axis of closed regions.
+//!
+//! Swiss-cheese layer: **Strategy selection**
+//! Extension point: swap `inscribed_radius` for an exact Voronoi medial
+//! axis, or clear the flat bottom with a second tool.
+//!
+//! A V-bit cuts a groove whose width grows with depth, so a region is
+//! carved by running the tip along its medial axis at the depth where the
+//! cone just touches both walls. The axis is traced from the boundary:
+//! at every sample point the largest inscribed circle tangent there is
+//! found by bisection, and its centre and radius give the tool position
+//! and depth. Reflex corners fan out over the normals between their two
+//! edges. Where the region is wider than the bit reaches at the depth cap,
+//! the tool runs at the cap one cutting radius in from the walls, and an
+//! optional pocketing pass clears the flat bottom left in the middle.
+
+use crate::geometry::nesting::{build_regions, FillRule};
+use crate::geometry::offset::{offset_region, JoinType};
+use crate::geometry::{Polyline, Toolpath, Vec2};
+
+use super::{link_loops, offset_loops_inside_out, CutParams, ToolpathStrategy};
+
+/// Spacing of boundary samples along each edge (mm).
+const SAMPLE_STEP: f64 = 0.1;
+
+/// Largest angle between fan normals at a reflex corner.
+const FAN_STEP: f64 = 10.0 * std::f64::consts::PI / 180.0;
+
+/// Bisection steps for the inscribed radius.
+const BISECTIONS: usize = 30;
+
+/// Points closer than this to the line through their neighbours are
+/// dropped from the output (mm).
+const SIMPLIFY: f64 = 0.001;
+
+/// V-carve strategy. Depth follows the local half-width of each region,
+/// down to `cut_z`; the tool's cutting profile (normally a V-bit) sets
+/// the depth for a given width.
+pub struct VCarveStrategy {
+    /// Pocket out the flat bottom where the region is wider than the bit
+    /// cuts at the depth cap.
+    pub flat_bottom: bool,
+}
+
+impl VCarveStrategy {
+    pub fn new(flat_bottom: bool) -> Self {
+        Self { flat_bottom }
+    }
+}
+
+impl ToolpathStrategy for VCarveStrategy {
+    fn generate(&self, contours: &[Polyline], params: &CutParams) -> Vec<Toolpath> {
+        let tool = &params.tool;
+        let full_depth = tool.height_at_radius(tool.diameter / 2.0).unwrap_or(0.0);
+        let max_depth = (-params.cut_z).max(0.0).min(full_depth);
+        let max_radius = tool.radius_at_height(max_depth);
+        let levels = depth_levels(max_depth, params.step_down);
+
+        let mut toolpaths = Vec::new();
+        for region in build_regions(contours, FillRule::EvenOdd) {
+            let rings = region.rings();
+            if self.flat_bottom && max_radius > 0.0 {
+                toolpaths.extend(clear_floor(&rings, max_radius, &levels, params));
+            }
+            for ring in &rings {
+                let path = medial_path(&ring.points, &rings, max_radius, |r| {
+                    tool.height_at_radius(r).unwrap_or(max_depth).min(max_depth)
+                });
+                let tp = emit_levels(&path, &levels, params);
+                if !tp.moves.is_empty() {
+                    toolpaths.push(tp);
+                }
+            }
+        }
+        toolpaths
+    }
+}
+
+/// Depths of the passes: every `step_down` and finally `max_depth`.
+fn depth_levels(max_depth: f64, step_down: f64) -> Vec<f64> {
+    let mut levels = Vec::new();
+    if step_down > 0.0 {
+        let mut d = step_down;
+        while d < max_depth - 1e-9 {
+            levels.push(d);
+            d += step_down;
+        }
+    }
+    levels.push(max_depth);
+    levels
+}
+
+/// Tool-tip points `(x, y, depth)` tracing the medial axis from `ring`.
+/// `depth_of` maps an inscribed radius to the carving depth.
+fn medial_path(
+    ring: &[Vec2],
+    rings: &[Polyline],
+    max_radius: f64,
+    depth_of: impl Fn(f64) -> f64,
+) -> Vec<(f64, f64, f64)> {
+    let n = ring.len();
+    let mut out: Vec<(f64, f64, f64)> = Vec::new();
+    let mut push = |p: Vec2, normal: Vec2| {
+        let r = inscribed_radius(p, normal, rings, max_radius);
+        let c = (p.x + normal.x * r, p.y + normal.y * r, depth_of(r));
+        if out.last() != Some(&c) {
+            out.push(c);
+        }
+    };
+    for i in 0..n {
+        let (prev, a, b) = (ring[(i + n - 1) % n], ring[i], ring[(i + 1) % n]);
+        let len = Vec2::dist(a, b);
+        if len < 1e-12 {
+            continue;
+        }
+        let normal = left_normal(a, b);
+        // The region is on the left; turning right opens a reflex corner.
+        let before = left_normal(prev, a);
+        let turn = before.x * normal.y - before.y * normal.x;
+        if turn < 0.0 && Vec2::dist(prev, a) > 1e-12 {
+            let sweep = (before.x * normal.x + before.y * normal.y)
+                .clamp(-1.0, 1.0)
+                .acos();
+            let steps = (sweep / FAN_STEP).ceil() as usize;
+            let start = before.y.atan2(before.x);
+            for k in 1..steps {
+                let t = start - sweep * k as f64 / steps as f64;
+                push(a, Vec2::new(t.cos(), t.sin()));
+            }
+        }
+        let steps = (len / SAMPLE_STEP).ceil() as usize;
+        for k in 0..steps {
+            let t = k as f64 / steps as f64;
+            push(
+                Vec2::new(a.x + (b.x - a.x) * t, a.y + (b.y - a.y) * t),
+                normal,
+            );
+        }
+    }
+    if let Some(&first) = out.first() {
+        out.push(first);
+    }
+    simplify(&out)
+}
+
+/// Unit normal on the left of `a`→`b`.
+fn left_normal(a: Vec2, b: Vec2) -> Vec2 {
+    let len = Vec2::dist(a, b).max(1e-12);
+    Vec2::new(-(b.y - a.y) / len, (b.x - a.x) / len)
+}
+
+/// Radius of the largest circle, up to `max_radius`, that touches the
+/// boundary at `p` from the `normal` side and stays inside `rings`.
+fn inscribed_radius(p: Vec2, normal: Vec2, rings: &[Polyline], max_radius: f64) -> f64 {
+    let fits = |r: f64| {
+        let c = Vec2::new(p.x + normal.x * r, p.y + normal.y * r);
+        let limit = r * (1.0 - 1e-9) - 1e-9;
+        rings.iter().all(|ring| {
+            let m = ring.points.len();
+            (0..m).all(|j| segment_dist(c, ring.points[j], ring.points[(j + 1) % m]) >= limit)
+        })
+    };
+    if fits(max_radius) {
+        return max_radius;
+    }
+    let (mut lo, mut hi) = (0.0, max_radius);
+    for _ in 0..BISECTIONS {
+        let mid = (lo + hi) / 2.0;
+        if fits(mid) {
+            lo = mid;
+        } else {
+            hi = mid;
+        }
+    }
+    lo
+}
+
+fn segment_dist(p: Vec2, a: Vec2, b: Vec2) -> f64 {
+    let (dx, dy) = (b.x - a.x, b.y - a.y);
+    let len2 = dx * dx + dy * dy;
+    let t = if len2 > 0.0 {
+        (((p.x - a.x) * dx + (p.y - a.y) * dy) / len2).clamp(0.0, 1.0)
+    } else {
+        0.0
+    };
+    Vec2::dist(p, Vec2::new(a.x + dx * t, a.y + dy * t))
+}
+
+/// Drop points that lie on the straight line between their neighbours.
+fn simplify(pts: &[(f64, f64, f64)]) -> Vec<(f64, f64, f64)> {
+    if pts.len() < 3 {
+        return pts.to_vec();
+    }
+    let mut out = vec![pts[0]];
+    for i in 1..pts.len() - 1 {
+        let a = *out.last().unwrap();
+        let (b, c) = (pts[i], pts[i + 1]);
+        let ac = (c.0 - a.0, c.1 - a.1, c.2 - a.2);
+        let ab = (b.0 - a.0, b.1 - a.1, b.2 - a.2);
+        let len2 = ac.0 * ac.0 + ac.1 * ac.1 + ac.2 * ac.2;
+        let t = if len2 > 0.0 {
+            ((ab.0 * ac.0 + ab.1 * ac.1 + ab.2 * ac.2) / len2).clamp(0.0, 1.0)
+        } else {
+            0.0
+        };
+        let d = (ab.0 - ac.0 * t, ab.1 - ac.1 * t, ab.2 - ac.2 * t);
+        if (d.0 * d.0 + d.1 * d.1 + d.2 * d.2).sqrt() > SIMPLIFY {
+            out.push(b);
+        }
+    }
+    out.push(pts[pts.len() - 1]);
+    out
+}
+
+/// One pass of `path` per depth level, each no deeper than its level.
+fn emit_levels(path: &[(f64, f64, f64)], levels: &[f64], params: &CutParams) -> Toolpath {
+    let mut tp = Toolpath::new();
+    let Some(&(x0, y0, _)) = path.first() else {
+        return tp;
+    };
+    let mut above = 0.0;
+    for &level in levels {
+        // Skip passes that would retrace the previous one.
+        if !path.iter().any(|p| p.2 > above + 1e-9) {
+            break;
+        }
+        let z = |d: f64| -d.min(level);
+        tp.rapid(x0, y0, params.safe_z);
+        for &(x, y, d) in path {
+            tp.cut(x, y, z(d));
+        }
+        tp.rapid(x0, y0, params.safe_z);
+        above = level;
+    }
+    tp
+}
+
+/// Pocket the area more than `radius` from the walls at every level.
+fn clear_floor(
+    rings: &[Polyline],
+    radius: f64,
+    levels: &[f64],
+    params: &CutParams,
+) -> Vec<Toolpath> {
+    let boundary = offset_region(rings, -radius, JoinType::Round);
+    if boundary.is_empty() {
+        return Vec::new();
+    }
+    let step = params.step_over.min(2.0 * radius).max(0.05);
+    let loops = offset_loops_inside_out(&boundary, step);
+    levels
+        .iter()
+        .map(|&d| {
+            let p = CutParams {
+                tool_diameter: 2.0 * radius,
+                cut_z: -d,
+                ..params.clone()
+            };
+            link_loops(&loops, &boundary, &p)
+        })
+        .filter(|tp| !tp.moves.is_empty())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tool::Tool;
+
+    fn rect(x0: f64, y0: f64, x1: f64, y1: f64) -> Polyline {
+        Polyline::new(
+            vec![
+                Vec2::new(x0, y0),
+                Vec2::new(x1, y0),
+                Vec2::new(x1, y1),
+                Vec2::new(x0, y1),
+            ],
+            true,
+        )
+    }
+
+    fn params(cut_z: f64) -> CutParams {
+        CutParams {
+            tool: Tool::vbit(20.0, 90.0, 0.0, 10.0),
+            tool_diameter: 20.0,
+            cut_z,
+            step_down: 0.0,
+            ..CutParams::default()
+        }
+    }
+
+    fn cuts(tps: &[Toolpath]) -> Vec<(f64, f64, f64)> {
+        tps.iter()
+            .flat_map(|tp| tp.moves.iter())
+            .filter(|m| !m.rapid)
+            .map(|m| (m.x, m.y, m.z))
+            .collect()
+    }
+
+    #[test]
+    fn strip_is_carved_along_its_centre_line() {
+        // A 4 mm wide strip: a 90 degree bit reaches both walls 2 mm down.
+        let tps = VCarveStrategy::new(false).generate(&[rect(0.0, 0.0, 40.0, 4.0)], &params(-10.0));
+        let pts = cuts(&tps);
+        let deepest = pts.iter().map(|p| p.2).fold(0.0, f64::min);
+        assert!((deepest + 2.0).abs() < 1e-6, "{deepest}");
+        // At full depth the tip is on the centre line.
+        for p in pts.iter().filter(|p| p.2 < -2.0 + 1e-6) {
+            assert!((p.1 - 2.0).abs() < 1e-6, "{:?}", p);
+        }
+        // Corners come up to the surface.
+        assert!(pts.iter().any(|p| p.2.abs() < 1e-6));
+    }
+
+    #[test]
+    fn never_cuts_outside_the_walls() {
+        let l_shape = Polyline::new(
+            vec![
+                Vec2::new(0.0, 0.0),
+                Vec2::new(20.0, 0.0),
+                Vec2::new(20.0, 6.0),
+                Vec2::new(6.0, 6.0),
+                Vec2::new(6.0, 20.0),
+                Vec2::new(0.0, 20.0),
+            ],
+            true,
+        );
+        let rings = [l_shape.clone()];
+        let tps = VCarveStrategy::new(false).generate(&rings, &params(-10.0));
+        let pts = cuts(&tps);
+        assert!(!pts.is_empty());
+        for &(x, y, z) in &pts {
+            // Cutting radius at this depth equals the depth for 90 degrees.
+            let wall = (0..6)
+                .map(|j| {
+                    segment_dist(
+                        Vec2::new(x, y),
+                        rings[0].points[j],
+                        rings[0].points[(j + 1) % 6],
+                    )
+                })
+                .fold(f64::INFINITY, f64::min);
+            assert!(-z <= wall + 1e-6, "({x}, {y}, {z}) wall {wall}");
+        }
+        // The reflex corner fans out, so some cuts sit on its bisector.
+        assert!(pts
+            .iter()
+            .any(|p| (p.0 - p.1).abs() < 1e-6 && p.0 > 3.0 && p.0 < 6.0));
+    }
+
+    #[test]
+    fn depth_cap_and_flat_bottom() {
+        let square = [rect(0.0, 0.0, 20.0, 20.0)];
+        let plain = VCarveStrategy::new(false).generate(&square, &params(-3.0));
+        assert_eq!(plain.len(), 1);
+        let deepest = cuts(&plain).iter().map(|p| p.2).fold(0.0, f64::min);
+        assert!((deepest + 3.0).abs() < 1e-9);
+
+        let cleared = VCarveStrategy::new(true).generate(&square, &params(-3.0));
+        assert!(cleared.len() > 1);
+        let floor = cuts(&cleared[..cleared.len() - 1]);
+        assert!(floor.iter().all(|p| (p.2 + 3.0).abs() < 1e-9));
+        // The floor reaches the middle of the square.
+        assert!(floor
+            .iter()
+            .any(|p| Vec2::dist(Vec2::new(p.0, p.1), Vec2::new(10.0, 10.0)) < 3.0));
+    }
+
+    #[test]
+    fn step_down_splits_deep_carves() {
+        let mut p = params(-10.0);
+        p.step_down = 0.75;
+        let tps = VCarveStrategy::new(false).generate(&[rect(0.0, 0.0, 40.0, 4.0)], &p);
+        let rapids_to_safe = tps[0].moves.iter().filter(|m| m.rapid).count();
+        // Passes at 0.75, 1.5 and 2.0 (the deepest the strip needs).
+        assert_eq!(rapids_to_safe, 6);
+    }
+}
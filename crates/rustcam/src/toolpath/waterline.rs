@@ -0,0 +1,440 @@
+//! Waterline (Z-level) finishing for steep walls.
+//!
+//! Swiss-cheese layer: **Strategy selection**
+//! Extension point: swap the grid contouring in `level_loops` for exact
+//! triangle-against-tool intersection, or give `order_paths` a smarter
+//! linking order.
+//!
+//! Raster finishing steps over in XY, so on near-vertical walls the passes
+//! land far apart in Z and leave tall scallops. Waterline passes step down
+//! in Z instead. The tool-centre height over an XY grid is found with an
+//! exact drop-cutter query (flat for end mills, spherical for ball ends),
+//! which is the surface offset by the tool shape. Each level contours
+//! that surface with marching squares, giving closed loops the tool can
+//! follow at constant Z. Where a vertical wall makes the surface step
+//! between grid nodes, each crossing is bisected with the same query and
+//! kept on the low side, so the loops do not gouge. A slope limit
+//! keeps only the parts of each loop on walls at least that steep, so the
+//! shallow areas can be left to a raster pass.
+
+use crate::geometry::height_grid::HeightGrid;
+use crate::geometry::{Toolpath, Vec2};
+
+use crate::slicer::{drop_ball_tool, drop_flat_tool};
+use crate::tool::ToolType;
+
+use super::SurfaceParams;
+
+/// Most grid nodes sampled for the tool-centre surface.
+const MAX_NODES: f64 = 250_000.0;
+
+/// Finest grid spacing (mm).
+const MIN_SPACING: f64 = 0.05;
+
+/// How closely a loop crossing is bisected between grid nodes (mm).
+const CROSSING_TOLERANCE: f64 = 0.005;
+
+/// Waterline strategy.
+///
+/// `slope_limit` is the shallowest wall, in degrees from horizontal, that
+/// gets waterline passes (0 machines everything).
+#[derive(Debug, Clone, Copy, Default)]
+pub struct WaterlineStrategy {
+    pub slope_limit: f64,
+}
+
+impl WaterlineStrategy {
+    pub fn new(slope_limit: f64) -> Self {
+        Self { slope_limit }
+    }
+
+    /// Waterline passes from the top of the mesh down to its floor, one
+    /// toolpath per loop or open wall section.
+    pub fn generate_surface(&self, params: &SurfaceParams) -> Vec<Toolpath> {
+        let Some(bounds) = &params.mesh.bounds else {
+            return Vec::new();
+        };
+        let cut = &params.cut_params;
+        let radius = cut.tool.diameter / 2.0;
+        let grid = sample_cl(params, radius);
+
+        // Ball ends are placed by their centre, other tools by the tip.
+        let lift = match cut.tool.tool_type {
+            ToolType::BallEnd => radius,
+            _ => 0.0,
+        };
+        let step = cut.step_down.max(0.01);
+        let min_slope = self.slope_limit.clamp(0.0, 90.0).to_radians().tan();
+
+        let mut toolpaths = Vec::new();
+        let mut pos: Option<Vec2> = None;
+        let mut k = 1;
+        loop {
+            let tip = (bounds.max.z - k as f64 * step).max(bounds.min.z);
+            let z = tip + lift;
+            let mut paths = Vec::new();
+            let height = |p: Vec2| drop_tool(params, p, radius);
+            for ring in grid.level_loops_below(z, CROSSING_TOLERANCE, height) {
+                paths.extend(steep_runs(&grid, &ring, min_slope));
+            }
+            for path in order_paths(paths, &mut pos) {
+                let mut points = path.points;
+                if !cut.climb_cut {
+                    points.reverse();
+                }
+                if path.closed {
+                    points.push(points[0]);
+                }
+                let mut tp = Toolpath::new();
+                let first = points[0];
+                tp.rapid(first.x, first.y, cut.safe_z);
+                tp.cut(first.x, first.y, z);
+                for p in &points[1..] {
+                    tp.cut(p.x, p.y, z);
+                }
+                let end = *points.last().unwrap();
+                tp.rapid(end.x, end.y, cut.safe_z);
+                pos = Some(end);
+                toolpaths.push(tp);
+            }
+            if tip <= bounds.min.z {
+                break;
+            }
+            k += 1;
+        }
+        toolpaths
+    }
+}
+
+/// Tool-centre heights over the mesh on a regular XY grid. Points where
+/// the tool misses the mesh sit below every level.
+fn sample_cl(params: &SurfaceParams, radius: f64) -> HeightGrid {
+    let b = params.mesh.bounds.as_ref().unwrap();
+    let mut spacing = (params.cut_params.step_over / 2.0).max(MIN_SPACING);
+    // A free border all round closes every loop.
+    let pad = radius + 2.0 * spacing;
+    let (w, h) = (b.max.x - b.min.x + 2.0 * pad, b.max.y - b.min.y + 2.0 * pad);
+    spacing = spacing.max((w * h / MAX_NODES).sqrt());
+    let pad = radius + 2.0 * spacing;
+    let origin = Vec2::new(b.min.x - pad, b.min.y - pad);
+    let nx = ((b.max.x + pad - origin.x) / spacing).ceil() as usize + 1;
+    let ny = ((b.max.y + pad - origin.y) / spacing).ceil() as usize + 1;
+
+    let mut z = Vec::with_capacity(nx * ny);
+    for j in 0..ny {
+        for i in 0..nx {
+            let p = Vec2::new(origin.x + i as f64 * spacing, origin.y + j as f64 * spacing);
+            z.push(drop_tool(params, p, radius));
+        }
+    }
+    let floor = b.min.z - params.cut_params.tool.diameter - 1.0;
+    for v in &mut z {
+        if *v == f64::NEG_INFINITY {
+            *v = floor;
+        }
+    }
+    HeightGrid {
+        origin,
+        spacing,
+        nx,
+        ny,
+        z,
+    }
+}
+
+/// Tool-centre Z at `p` (the tip for flat tools, the centre for ball
+/// ends), or minus infinity off the mesh. Drill and V-bit points fit
+/// inside the flat disc, so the disc never lets them gouge.
+fn drop_tool(params: &SurfaceParams, p: Vec2, radius: f64) -> f64 {
+    let z = match params.cut_params.tool.tool_type {
+        ToolType::BallEnd => drop_ball_tool(params.mesh, p.x, p.y, radius),
+        _ => drop_flat_tool(params.mesh, p.x, p.y, radius),
+    };
+    z.unwrap_or(f64::NEG_INFINITY)
+}
+
+/// The parts of `ring` on walls at least `min_slope` steep (as a
+/// gradient): the whole loop, or the open runs between shallow spots.
+fn steep_runs(grid: &HeightGrid, ring: &[Vec2], min_slope: f64) -> Vec<Path> {
+    if min_slope <= 0.0 {
+        return vec![Path {
+            points: ring.to_vec(),
+            closed: true,
+        }];
+    }
+    let steep: Vec<bool> = ring.iter().map(|&p| grid.slope(p) >= min_slope).collect();
+    let Some(first_shallow) = steep.iter().position(|s| !s) else {
+        return vec![Path {
+            points: ring.to_vec(),
+            closed: true,
+        }];
+    };
+    // Walk once round the loop from a shallow point.
+    let n = ring.len();
+    let mut runs = Vec::new();
+    let mut run: Vec<Vec2> = Vec::new();
+    for k in 1..=n {
+        let idx = (first_shallow + k) % n;
+        if steep[idx] {
+            run.push(ring[idx]);
+        } else if run.len() > 1 {
+            runs.push(Path {
+                points: std::mem::take(&mut run),
+                closed: false,
+            });
+        } else {
+            run.clear();
+        }
+    }
+    runs
+}
+
+/// A pass to cut at one level.
+struct Path {
+    points: Vec<Vec2>,
+    closed: bool,
+}
+
+/// Nearest-first order from `pos`; closed loops start at the vertex
+/// nearest the tool.
+fn order_paths(mut paths: Vec<Path>, pos: &mut Option<Vec2>) -> Vec<Path> {
+    let mut out = Vec::with_capacity(paths.len());
+    let mut at = *pos;
+    while !paths.is_empty() {
+        let best = match at {
+            Some(p) => (0..paths.len())
+                .min_by(|&a, &b| {
+                    nearest_distance(&paths[a], p).total_cmp(&nearest_distance(&paths[b], p))
+                })
+                .unwrap(),
+            None => 0,
+        };
+        let mut path = paths.swap_remove(best);
+        if let (Some(p), true) = (at, path.closed) {
+            let start = (0..path.points.len())
+                .min_by(|&a, &b| {
+                    Vec2::dist(path.points[a], p).total_cmp(&Vec2::dist(path.points[b], p))
+                })
+                .unwrap();
+            path.points.rotate_left(start);
+        }
+        at = Some(if path.closed {
+            path.points[0]
+        } else {
+            *path.points.last().unwrap()
+        });
+        out.push(path);
+    }
+    out
+}
+
+fn nearest_distance(path: &Path, p: Vec2) -> f64 {
+    if path.closed {
+        path.points
+            .iter()
+            .map(|&q| Vec2::dist(p, q))
+            .fold(f64::INFINITY, f64::min)
+    } else {
+        Vec2::dist(p, path.points[0])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geometry::{Mesh, Triangle, Vec3};
+    use crate::tool::Tool;
+    use crate::toolpath::{CutParams, ScanDirection};
+
+    fn quad(tris: &mut Vec<Triangle>, a: Vec3, b: Vec3, c: Vec3, d: Vec3) {
+        let n = Vec3::new(0.0, 0.0, 1.0);
+        tris.push(Triangle {
+            normal: n,
+            v0: a,
+            v1: b,
+            v2: c,
+        });
+        tris.push(Triangle {
+            normal: n,
+            v0: a,
+            v1: c,
+            v2: d,
+        });
+    }
+
+    /// A 10 mm square boss, 10 mm tall, on a 30 mm square floor at Z 0.
+    /// With `chamfer` the boss top is 4 mm smaller, so its walls slope.
+    fn boss(chamfer: f64) -> Mesh {
+        let v = Vec3::new;
+        let mut t = Vec::new();
+        quad(
+            &mut t,
+            v(0.0, 0.0, 0.0),
+            v(30.0, 0.0, 0.0),
+            v(30.0, 30.0, 0.0),
+            v(0.0, 30.0, 0.0),
+        );
+        let (lo, hi) = (10.0, 20.0);
+        let (tl, th) = (lo + chamfer, hi - chamfer);
+        quad(
+            &mut t,
+            v(tl, tl, 10.0),
+            v(th, tl, 10.0),
+            v(th, th, 10.0),
+            v(tl, th, 10.0),
+        );
+        quad(
+            &mut t,
+            v(lo, lo, 0.0),
+            v(hi, lo, 0.0),
+            v(th, tl, 10.0),
+            v(tl, tl, 10.0),
+        );
+        quad(
+            &mut t,
+            v(hi, lo, 0.0),
+            v(hi, hi, 0.0),
+            v(th, th, 10.0),
+            v(th, tl, 10.0),
+        );
+        quad(
+            &mut t,
+            v(hi, hi, 0.0),
+            v(lo, hi, 0.0),
+            v(tl, th, 10.0),
+            v(th, th, 10.0),
+        );
+        quad(
+            &mut t,
+            v(lo, hi, 0.0),
+            v(lo, lo, 0.0),
+            v(tl, tl, 10.0),
+            v(tl, th, 10.0),
+        );
+        Mesh::new(t)
+    }
+
+    fn params(mesh: &Mesh, tool: Tool) -> SurfaceParams<'_> {
+        let cut = CutParams {
+            tool_diameter: tool.diameter,
+            tool,
+            step_over: 0.5,
+            step_down: 2.5,
+            safe_z: 15.0,
+            ..CutParams::default()
+        };
+        SurfaceParams::new(mesh, cut, ScanDirection::X)
+    }
+
+    fn end_mill(diameter: f64) -> Tool {
+        Tool {
+            diameter,
+            ..Tool::default()
+        }
+    }
+
+    fn cut_levels(tps: &[Toolpath]) -> Vec<f64> {
+        let mut zs: Vec<f64> = tps
+            .iter()
+            .flat_map(|tp| tp.moves.iter().filter(|m| !m.rapid).map(|m| m.z))
+            .collect();
+        zs.sort_by(|a, b| b.total_cmp(a));
+        zs.dedup();
+        zs
+    }
+
+    #[test]
+    fn flat_tool_rings_the_boss_one_radius_out() {
+        let mesh = boss(0.0);
+        let tps = WaterlineStrategy::default().generate_surface(&params(&mesh, end_mill(4.0)));
+        assert_eq!(cut_levels(&tps), [7.5, 5.0, 2.5, 0.0]);
+        // One closed loop per level around the boss.
+        assert_eq!(tps.len(), 4);
+        for tp in &tps {
+            let cuts: Vec<_> = tp.moves.iter().filter(|m| !m.rapid).collect();
+            let (first, last) = (cuts[0], cuts[cuts.len() - 1]);
+            assert!((first.x - last.x).abs() < 1e-9 && (first.y - last.y).abs() < 1e-9);
+            for m in &cuts {
+                // Outside the boss by at least the tool radius, and by no
+                // more than a grid cell.
+                let gap = |v: f64| ((v - 15.0).abs() - 5.0).max(0.0);
+                let d = gap(m.x).hypot(gap(m.y));
+                assert!((2.0 - 0.01..2.0 + 0.4).contains(&d), "{d} at {:?}", m);
+            }
+        }
+    }
+
+    #[test]
+    fn flat_tool_never_gouges_the_wall() {
+        let mesh = boss(0.0);
+        for step_over in [0.5, 1.5, 3.0] {
+            let mut p = params(&mesh, end_mill(6.0));
+            p.cut_params.step_over = step_over;
+            let tps = WaterlineStrategy::default().generate_surface(&p);
+            assert!(!tps.is_empty());
+            for m in tps.iter().flat_map(|tp| &tp.moves).filter(|m| !m.rapid) {
+                // Distance from the boss: at least the radius, and not
+                // much more than a corner chord beyond it.
+                let gap = |v: f64| ((v - 15.0).abs() - 5.0).max(0.0);
+                let d = gap(m.x).hypot(gap(m.y));
+                assert!(
+                    d >= 3.0 - 0.01 && d < 3.0 + step_over.max(0.4),
+                    "step_over {step_over}: {d} at {:?}",
+                    m
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn ball_tool_levels_are_lifted_by_its_radius() {
+        let mesh = boss(2.0);
+        let tps = WaterlineStrategy::default()
+            .generate_surface(&params(&mesh, Tool::ball_end(4.0, 20.0)));
+        assert_eq!(cut_levels(&tps), [9.5, 7.0, 4.5, 2.0]);
+        // The sloped wall pushes lower levels further out.
+        let reach = |z: f64| {
+            tps.iter()
+                .flat_map(|tp| &tp.moves)
+                .filter(|m| !m.rapid && m.z == z)
+                .map(|m| (m.x - 15.0).abs().max((m.y - 15.0).abs()))
+                .fold(0.0, f64::max)
+        };
+        assert!(reach(4.5) > reach(9.5));
+    }
+
+    #[test]
+    fn climb_and_conventional_run_opposite_ways() {
+        let mesh = boss(0.0);
+        let mut p = params(&mesh, end_mill(4.0));
+        let turn = |tps: &[Toolpath]| {
+            // Shoelace area of the first loop: positive when counter-clockwise.
+            let pts: Vec<_> = tps[0].moves.iter().filter(|m| !m.rapid).collect();
+            pts.windows(2)
+                .map(|w| w[0].x * w[1].y - w[1].x * w[0].y)
+                .sum::<f64>()
+        };
+        p.cut_params.climb_cut = true;
+        let climb = turn(&WaterlineStrategy::default().generate_surface(&p));
+        p.cut_params.climb_cut = false;
+        let conventional = turn(&WaterlineStrategy::default().generate_surface(&p));
+        // Climb keeps the boss on the right: clockwise round the outside.
+        assert!(climb < 0.0 && conventional > 0.0, "{climb} {conventional}");
+    }
+
+    #[test]
+    fn slope_limit_drops_shallow_walls() {
+        // Walls of the chamfered boss rise 10 in 2: about 79 degrees.
+        let mesh = boss(2.0);
+        let p = params(&mesh, end_mill(2.0));
+        let all = WaterlineStrategy::new(60.0).generate_surface(&p);
+        assert!(!all.is_empty());
+        let none = WaterlineStrategy::new(85.0).generate_surface(&p);
+        assert!(
+            none.iter()
+                .all(|tp| tp.moves.iter().all(|m| m.rapid || m.z == 0.0)),
+            "{}",
+            none.len()
+        );
+    }
+}
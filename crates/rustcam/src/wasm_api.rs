@@ -14,6 +14,16 @@ pub fn available_profiles() -> String {
     super::available_profiles()
 }
 
+#[wasm_bindgen]
+pub fn available_post_processors() -> String {
+    super::available_post_processors()
+}
+
+#[wasm_bindgen]
+pub fn available_fonts() -> String {
+    super::available_fonts()
+}
+
 #[wasm_bindgen]
 pub fn default_config(machine_type: &str) -> String {
     super::default_config(machine_type)
@@ -26,11 +36,51 @@ pub fn process_stl(data: &[u8], config_json: &str) -> Result<String, JsValue> {
     super::process_stl_impl(data, config_json).map_err(|e| JsValue::from_str(&e))
 }
 
+#[wasm_bindgen]
+pub fn process_obj(data: &[u8], config_json: &str) -> Result<String, JsValue> {
+    super::process_obj_impl(data, config_json).map_err(|e| JsValue::from_str(&e))
+}
+
+#[wasm_bindgen]
+pub fn process_3mf(data: &[u8], config_json: &str) -> Result<String, JsValue> {
+    super::process_3mf_impl(data, config_json).map_err(|e| JsValue::from_str(&e))
+}
+
+#[wasm_bindgen]
+pub fn process_ply(data: &[u8], config_json: &str) -> Result<String, JsValue> {
+    super::process_ply_impl(data, config_json).map_err(|e| JsValue::from_str(&e))
+}
+
 #[wasm_bindgen]
 pub fn process_svg(svg_text: &str, config_json: &str) -> Result<String, JsValue> {
     super::process_svg_impl(svg_text, config_json).map_err(|e| JsValue::from_str(&e))
 }
 
+#[wasm_bindgen]
+pub fn process_text(text_json: &str, config_json: &str) -> Result<String, JsValue> {
+    super::process_text_impl(text_json, config_json).map_err(|e| JsValue::from_str(&e))
+}
+
+#[wasm_bindgen]
+pub fn process_text_serials(text_json: &str, config_json: &str) -> Result<String, JsValue> {
+    super::process_text_serials_impl(text_json, config_json).map_err(|e| JsValue::from_str(&e))
+}
+
+#[wasm_bindgen]
+pub fn process_image(data: &[u8], image_json: &str, config_json: &str) -> Result<String, JsValue> {
+    super::process_image_impl(data, image_json, config_json).map_err(|e| JsValue::from_str(&e))
+}
+
+#[wasm_bindgen]
+pub fn process_job_stl(data: &[u8], job_json: &str) -> Result<String, JsValue> {
+    super::process_job_stl_impl(data, job_json).map_err(|e| JsValue::from_str(&e))
+}
+
+#[wasm_bindgen]
+pub fn process_job_svg(svg_text: &str, job_json: &str) -> Result<String, JsValue> {
+    super::process_job_svg_impl(svg_text, job_json).map_err(|e| JsValue::from_str(&e))
+}
+
 #[wasm_bindgen]
 pub fn process_stl_progress(
     data: &[u8],
@@ -42,7 +92,9 @@ pub fn process_stl_progress(
     let config: CamConfig =
         serde_json::from_str(config_json).map_err(|e| JsValue::from_str(&e.to_string()))?;
 
-    let mesh = stl::parse_stl(data).map_err(|e| JsValue::from_str(&e))?;
+    let profile = output_profile_from_config(&config).map_err(|e| JsValue::from_str(&e))?;
+    crate::require_2d_input(&config.strategy).map_err(|e| JsValue::from_str(&e))?;
+    let (mesh, placement) = crate::load_mesh(data, &config).map_err(|e| JsValue::from_str(&e))?;
 
     let cut_params = toolpath::CutParams {
         tool: tool_from_config(&config),
@@ -55,14 +107,11 @@ pub fn process_stl_progress(
         cut_z: config.cut_depth,
         climb_cut: config.climb_cut,
         perimeter_passes: config.perimeter_passes,
-    };
-
-    let gcode_params = gcode::GcodeParams {
-        feed_rate: config.feed_rate,
-        plunge_rate: config.plunge_rate,
-        spindle_speed: config.spindle_speed,
-        safe_z: config.safe_z,
-        unit_mm: true,
+        entry: crate::entry_mode_from_config(&config),
+        ramp_angle: config.ramp_angle,
+        tabs: crate::tabs_from_config(&config),
+        final_z: mesh.bounds.as_ref().map(|b| b.min.z),
+        leads: crate::leads_from_config(&config),
     };
 
     let toolpaths: Vec<geometry::Toolpath> = match config.strategy.as_str() {
@@ -78,12 +127,26 @@ pub fn process_stl_progress(
             report_progress(on_progress, 1, 1);
             result
         }
+        "waterline" => {
+            report_progress(on_progress, 0, 1);
+            let surface_params = toolpath::SurfaceParams::new(
+                &mesh,
+                cut_params,
+                scan_direction_from_config(&config),
+            );
+            let result = toolpath::WaterlineStrategy::new(config.slope_limit)
+                .generate_surface(&surface_params);
+            report_progress(on_progress, 1, 1);
+            result
+        }
         other => {
             let layers = slicer::slice_mesh(&mesh, config.step_down);
             let total = layers.len() as u32;
             report_progress(on_progress, 0, total);
             let strategy: Box<dyn toolpath::ToolpathStrategy> = match other {
                 "pocket" => Box::new(toolpath::PocketStrategy),
+                "offset_pocket" => Box::new(toolpath::OffsetPocketStrategy),
+                "adaptive" => Box::new(toolpath::AdaptiveStrategy::new(config.max_engagement)),
                 "perimeter" => Box::new(toolpath::PerimeterStrategy),
                 "slice" => Box::new(toolpath::ContourStrategy),
                 _ => Box::new(toolpath::ContourStrategy),
@@ -95,7 +158,9 @@ pub fn process_stl_progress(
                 all.extend(strategy.generate(contours, &p));
                 report_progress(on_progress, (i + 1) as u32, total);
             }
-            if all.is_empty() && other != "pocket" && other != "perimeter" {
+            if all.is_empty()
+                && !matches!(other, "pocket" | "offset_pocket" | "adaptive" | "perimeter")
+            {
                 let contours =
                     slicer::slice_at_z(&mesh, mesh.bounds.as_ref().map_or(0.0, |b| b.min.z + 0.01));
                 all.extend(strategy.generate(&contours, &cut_params));
@@ -104,7 +169,12 @@ pub fn process_stl_progress(
         }
     };
 
-    Ok(gcode::emit_gcode(&toolpaths, &gcode_params))
+    Ok(crate::emit_program(
+        toolpaths,
+        &config,
+        &profile,
+        placement.as_ref(),
+    ))
 }
 
 #[wasm_bindgen]
@@ -118,7 +188,9 @@ pub fn process_svg_progress(
     let config: CamConfig =
         serde_json::from_str(config_json).map_err(|e| JsValue::from_str(&e.to_string()))?;
 
-    let polylines = svg::parse_svg(svg_text).map_err(|e| JsValue::from_str(&e))?;
+    let profile = output_profile_from_config(&config).map_err(|e| JsValue::from_str(&e))?;
+    let (polylines, placement) =
+        crate::load_2d_input(svg_text, &config).map_err(|e| JsValue::from_str(&e))?;
 
     let cut_params = toolpath::CutParams {
         tool: tool_from_config(&config),
@@ -131,25 +203,31 @@ pub fn process_svg_progress(
         cut_z: config.cut_depth,
         climb_cut: config.climb_cut,
         perimeter_passes: config.perimeter_passes,
-    };
-
-    let gcode_params = gcode::GcodeParams {
-        feed_rate: config.feed_rate,
-        plunge_rate: config.plunge_rate,
-        spindle_speed: config.spindle_speed,
-        safe_z: config.safe_z,
-        unit_mm: true,
+        entry: crate::entry_mode_from_config(&config),
+        ramp_angle: config.ramp_angle,
+        tabs: crate::tabs_from_config(&config),
+        final_z: Some(config.cut_depth),
+        leads: crate::leads_from_config(&config),
     };
 
     let strategy: Box<dyn toolpath::ToolpathStrategy> = match config.strategy.as_str() {
         "pocket" => Box::new(toolpath::PocketStrategy),
+        "offset_pocket" => Box::new(toolpath::OffsetPocketStrategy),
+        "adaptive" => Box::new(toolpath::AdaptiveStrategy::new(config.max_engagement)),
+        "drill" => Box::new(toolpath::DrillStrategy::new(
+            crate::drill_params_from_config(&config),
+        )),
+        "vcarve" => Box::new(toolpath::VCarveStrategy::new(config.flat_bottom)),
         "perimeter" => Box::new(toolpath::PerimeterStrategy),
         _ => Box::new(toolpath::ContourStrategy),
     };
+    let single_pass = crate::full_depth_in_one_pass(&config.strategy);
 
     // Count total layers
     let mut total_layers = 0u32;
-    {
+    if single_pass {
+        total_layers = 1;
+    } else {
         let mut z = 0.0;
         while z > config.cut_depth - 0.001 {
             z -= config.step_down;
@@ -167,7 +245,11 @@ pub fn process_svg_progress(
     let mut all_toolpaths = Vec::new();
     let mut z = 0.0;
     let mut layer_num = 0u32;
-    while z > config.cut_depth - 0.001 {
+    if single_pass {
+        all_toolpaths.extend(strategy.generate(&polylines, &cut_params));
+        report_progress(on_progress, 1, 1);
+    }
+    while !single_pass && z > config.cut_depth - 0.001 {
         z -= config.step_down;
         if z < config.cut_depth {
             z = config.cut_depth;
@@ -182,7 +264,12 @@ pub fn process_svg_progress(
         }
     }
 
-    Ok(gcode::emit_gcode(&all_toolpaths, &gcode_params))
+    Ok(crate::emit_program(
+        all_toolpaths,
+        &config,
+        &profile,
+        placement.as_ref(),
+    ))
 }
 
 /// Helper: call a JS progress callback with (completed, total).
@@ -202,8 +289,23 @@ pub fn preview_stl(data: &[u8], config_json: &str) -> Result<String, JsValue> {
 }
 
 #[wasm_bindgen]
-pub fn preview_svg(svg_text: &str) -> Result<String, JsValue> {
-    super::preview_svg_impl(svg_text).map_err(|e| JsValue::from_str(&e))
+pub fn preview_svg(svg_text: &str, config_json: &str) -> Result<String, JsValue> {
+    super::preview_svg_impl(svg_text, config_json).map_err(|e| JsValue::from_str(&e))
+}
+
+#[wasm_bindgen]
+pub fn import_dxf(text: &str, layers_json: &str) -> Result<String, JsValue> {
+    super::import_dxf_impl(text, layers_json).map_err(|e| JsValue::from_str(&e))
+}
+
+#[wasm_bindgen]
+pub fn text_outlines(text_json: &str) -> Result<String, JsValue> {
+    super::text_outlines_impl(text_json).map_err(|e| JsValue::from_str(&e))
+}
+
+#[wasm_bindgen]
+pub fn svg_colors(svg_text: &str) -> Result<String, JsValue> {
+    super::svg_colors_impl(svg_text).map_err(|e| JsValue::from_str(&e))
 }
 
 // ── Simulation data ────────────────────────────────────────────────────
@@ -218,6 +320,65 @@ pub fn sim_moves_svg(svg_text: &str, config_json: &str) -> Result<String, JsValu
     super::sim_moves_svg_impl(svg_text, config_json).map_err(|e| JsValue::from_str(&e))
 }
 
+#[wasm_bindgen]
+pub fn sim_moves_image(
+    data: &[u8],
+    image_json: &str,
+    config_json: &str,
+) -> Result<String, JsValue> {
+    super::sim_moves_image_impl(data, image_json, config_json).map_err(|e| JsValue::from_str(&e))
+}
+
+#[wasm_bindgen]
+pub fn simulate_stock_stl(data: &[u8], config_json: &str) -> Result<String, JsValue> {
+    super::simulate_stock_stl_impl(data, config_json).map_err(|e| JsValue::from_str(&e))
+}
+
+#[wasm_bindgen]
+pub fn simulate_stock_svg(svg_text: &str, config_json: &str) -> Result<String, JsValue> {
+    super::simulate_stock_svg_impl(svg_text, config_json).map_err(|e| JsValue::from_str(&e))
+}
+
+#[wasm_bindgen]
+pub fn interpret_gcode(gcode: &str, config_json: &str) -> Result<String, JsValue> {
+    super::interpret_gcode_impl(gcode, config_json).map_err(|e| JsValue::from_str(&e))
+}
+
+#[wasm_bindgen]
+pub fn cycle_time_stl(data: &[u8], config_json: &str) -> Result<String, JsValue> {
+    super::cycle_time_stl_impl(data, config_json).map_err(|e| JsValue::from_str(&e))
+}
+
+#[wasm_bindgen]
+pub fn cycle_time_svg(svg_text: &str, config_json: &str) -> Result<String, JsValue> {
+    super::cycle_time_svg_impl(svg_text, config_json).map_err(|e| JsValue::from_str(&e))
+}
+
+#[wasm_bindgen]
+pub fn cycle_time_job_stl(data: &[u8], job_json: &str) -> Result<String, JsValue> {
+    super::cycle_time_job_stl_impl(data, job_json).map_err(|e| JsValue::from_str(&e))
+}
+
+#[wasm_bindgen]
+pub fn cycle_time_job_svg(svg_text: &str, job_json: &str) -> Result<String, JsValue> {
+    super::cycle_time_job_svg_impl(svg_text, job_json).map_err(|e| JsValue::from_str(&e))
+}
+
+#[wasm_bindgen]
+pub fn cycle_time_gcode(gcode: &str, config_json: &str) -> Result<String, JsValue> {
+    super::cycle_time_gcode_impl(gcode, config_json).map_err(|e| JsValue::from_str(&e))
+}
+
+#[wasm_bindgen]
+pub fn rapid_report_stl(data: &[u8], config_json: &str) -> Result<String, JsValue> {
+    super::rapid_report_stl_impl(data, config_json).map_err(|e| JsValue::from_str(&e))
+}
+
+#[wasm_bindgen]
+pub fn rapid_report_svg(svg_text: &str, config_json: &str) -> Result<String, JsValue> {
+    super::rapid_report_svg_impl(svg_text, config_json).map_err(|e| JsValue::from_str(&e))
+}
+
 // ── Sketch actor ───────────────────────────────────────────────────────
 
 #[wasm_bindgen]
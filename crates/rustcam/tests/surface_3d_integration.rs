@@ -65,6 +65,7 @@ fn run_strategy(tool: Tool, pattern: Pattern) -> (Vec<Toolpath>, String) {
         spindle_speed: 12000.0,
         safe_z: cut_params.safe_z,
         unit_mm: true,
+        ..GcodeParams::default()
     };
     let gcode = emit_gcode(&toolpaths, &gcode_params);
     (toolpaths, gcode)
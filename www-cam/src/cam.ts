@@ -2,10 +2,10 @@
 
 import {
   process_stl, process_svg,
-  preview_stl, preview_svg,
+  preview_stl, preview_svg, import_dxf, svg_colors,
 } from '../pkg/rustcam.js';
 import { $, $input, $select, $canvas, $textarea, $btn } from './dom.js';
-import type { CamConfig, WorkerOutMsg } from './types.js';
+import type { CamConfig, PreviewTab, Setup, StockShape, WorkOffset, WorkerOutMsg } from './types.js';
 import { theme } from './theme.js';
 
 // ── State ────────────────────────────────────────────────────────────
@@ -18,6 +18,8 @@ export function setWasmReady(v: boolean): void { wasmReady = v; }
 export function setFileData(d: Uint8Array | string | null, t: 'stl' | 'svg' | null): void {
   fileData = d;
   fileType = t;
+  showDxfLayers(null);
+  showSvgColors(null);
 }
 
 // ── DOM refs ─────────────────────────────────────────────────────────
@@ -40,9 +42,11 @@ const laserParamsSection    = $('laser-params');
 const strategySelect        = $select('strategy');
 const perimeterOptions      = $('perimeter-options');
 const zigzagOptions         = $('zigzag-options');
+const adaptiveOptions       = $('adaptive-options');
+const drillOptions          = $('drill-options');
 const fileInput             = $input('file-input');
 
-const cncStrategies   = ['contour', 'pocket', 'slice', 'zigzag', 'perimeter'];
+const cncStrategies   = ['contour', 'pocket', 'offset_pocket', 'adaptive', 'slice', 'zigzag', 'waterline', 'perimeter', 'drill', 'vcarve'];
 const laserStrategies = ['contour', 'pocket', 'perimeter', 'laser_cut', 'laser_engrave'];
 
 // ── Tool type UI ─────────────────────────────────────────────────────
@@ -51,6 +55,8 @@ function updateToolTypeUI(): void {
   const toolType = toolTypeSelect.value;
   effectiveDiameterRow.classList.toggle('hidden', toolType !== 'face_mill');
   cornerRadiusRow.classList.toggle('hidden', toolType !== 'ball_end');
+  $('drill-tip-row').classList.toggle('hidden', toolType !== 'drill');
+  $('vbit-row').classList.toggle('hidden', toolType !== 'vbit');
   if (toolType === 'ball_end') {
     const diameter = parseFloat($input('tool-diameter').value);
     $input('corner-radius').value = (diameter / 2).toFixed(2);
@@ -58,6 +64,25 @@ function updateToolTypeUI(): void {
 }
 
 toolTypeSelect.addEventListener('change', updateToolTypeUI);
+
+function updateEntryUI(): void {
+  const mode = $select('entry-mode').value;
+  $('entry-options').classList.toggle('hidden', mode === 'plunge');
+  $('helix-diameter-row').classList.toggle('hidden', mode !== 'helix');
+}
+
+$select('entry-mode').addEventListener('change', updateEntryUI);
+
+function updateLeadUI(): void {
+  const none = $select('lead-in').value === 'none' && $select('lead-out').value === 'none';
+  $('lead-radius-row').classList.toggle('hidden', none);
+}
+
+$select('lead-in').addEventListener('change', updateLeadUI);
+$select('lead-out').addEventListener('change', updateLeadUI);
+$select('post-processor').addEventListener('change', () => {
+  $('custom-post').classList.toggle('hidden', $select('post-processor').value !== 'custom');
+});
 $input('tool-diameter').addEventListener('change', updateToolTypeUI);
 
 // ── Machine type UI ──────────────────────────────────────────────────
@@ -66,12 +91,18 @@ function updateStrategyUI(): void {
   const strategy = strategySelect.value;
   perimeterOptions.classList.toggle('hidden', strategy !== 'perimeter');
   zigzagOptions.classList.toggle('hidden', strategy !== 'zigzag');
+  adaptiveOptions.classList.toggle('hidden', strategy !== 'adaptive');
+  $('waterline-options').classList.toggle('hidden', strategy !== 'waterline');
+  drillOptions.classList.toggle('hidden', strategy !== 'drill');
+  $('vcarve-options').classList.toggle('hidden', strategy !== 'vcarve');
 }
 
 function updateMachineTypeUI(): void {
   const isLaser = machineTypeSelect.value === 'laser_cutter';
   cncParamsSection.classList.toggle('hidden', isLaser);
   laserParamsSection.classList.toggle('hidden', !isLaser);
+  // Laser tabs are uncut gaps; only a mill leaves them at a height.
+  $('tab-height-row').classList.toggle('hidden', isLaser);
   const current = strategySelect.value;
   const allowed = isLaser ? laserStrategies : cncStrategies;
   for (const opt of Array.from(strategySelect.options)) {
@@ -86,6 +117,10 @@ function updateMachineTypeUI(): void {
 machineTypeSelect.addEventListener('change', () => { updateMachineTypeUI(); tryPreview(); });
 strategySelect.addEventListener('change', () => { updateStrategyUI(); tryPreview(); });
 $select('scan-direction').addEventListener('change', tryPreview);
+$input('drill-max-diameter').addEventListener('change', tryPreview);
+for (const id of ['tab-count', 'tab-spacing', 'tab-width']) $input(id).addEventListener('change', tryPreview);
+$input('dxf-layers').addEventListener('change', tryPreview);
+$input('svg-colors').addEventListener('change', tryPreview);
 updateMachineTypeUI();
 
 // ── Tabs ─────────────────────────────────────────────────────────────
@@ -115,21 +150,52 @@ dropZone.addEventListener('drop', e => {
 });
 fileInput.addEventListener('change', () => { if (fileInput.files?.length) handleFile(fileInput.files[0]); });
 
+/** Show the layer filter for a DXF drawing, listing its layers; hide it otherwise. */
+function showDxfLayers(dxfText: string | null): void {
+  $('dxf-options').classList.toggle('hidden', dxfText === null);
+  if (dxfText === null) { $input('dxf-layers').value = ''; return; }
+  try {
+    const { layers } = JSON.parse(import_dxf(dxfText, '[]')) as { layers: string[] };
+    $('dxf-layer-list').textContent = 'Layers: ' + layers.join(', ');
+  } catch (err) {
+    $('dxf-layer-list').textContent = String(err);
+  }
+}
+
+/** Show the color filter for an SVG, listing its colors; hide it otherwise. */
+function showSvgColors(svgText: string | null): void {
+  $('svg-options').classList.toggle('hidden', svgText === null);
+  if (svgText === null) { $input('svg-colors').value = ''; return; }
+  try {
+    const colors = JSON.parse(svg_colors(svgText)) as string[];
+    $('svg-color-list').textContent = 'Colors: ' + colors.join(', ');
+  } catch (err) {
+    $('svg-color-list').textContent = String(err);
+  }
+}
+
 function handleFile(file: File): void {
   const ext = file.name.split('.').pop()?.toLowerCase();
-  if (ext === 'stl') {
+  // Every mesh format goes through the STL entry points, which detect
+  // OBJ, 3MF and PLY from the file content.
+  if (ext === 'stl' || ext === 'obj' || ext === '3mf' || ext === 'ply') {
     fileType = 'stl';
+    showDxfLayers(null);
+    showSvgColors(null);
     file.arrayBuffer().then(buf => {
       fileData = new Uint8Array(buf);
       filenameEl.textContent = file.name;
       generateBtn.disabled = !wasmReady;
       tryPreview();
     });
-  } else if (ext === 'svg') {
+  } else if (ext === 'svg' || ext === 'dxf') {
+    // DXF is text like SVG; the 2D entry points detect it from the content.
     fileType = 'svg';
     file.text().then(txt => {
       fileData = txt;
       filenameEl.textContent = file.name;
+      showDxfLayers(ext === 'dxf' ? txt : null);
+      showSvgColors(ext === 'svg' ? txt : null);
       generateBtn.disabled = !wasmReady;
       tryPreview();
     });
@@ -169,19 +235,88 @@ export function getConfig(): string {
     config.spindle_speed = parseFloat($input('spindle-speed').value);
     config.safe_z = parseFloat($input('safe-z').value);
     config.cut_depth = parseFloat($input('cut-depth').value);
+    config.entry_mode = $select('entry-mode').value;
+    if (config.entry_mode !== 'plunge') {
+      config.ramp_angle = parseFloat($input('ramp-angle').value) || 3;
+    }
+    if (config.entry_mode === 'helix') {
+      config.helix_diameter = parseFloat($input('helix-diameter').value) || config.tool_diameter;
+    }
+    config.lead_in = $select('lead-in').value;
+    config.lead_out = $select('lead-out').value;
+    const leadRadius = parseFloat($input('lead-radius').value);
+    if (leadRadius > 0) {
+      config.lead_radius = leadRadius;
+    }
+    config.compensation = $select('compensation').value;
   }
   if (toolType === 'ball_end') {
     config.corner_radius = parseFloat($input('corner-radius').value) || 0;
   } else if (toolType === 'face_mill') {
     config.effective_diameter = parseFloat($input('effective-diameter').value) || config.tool_diameter;
+  } else if (toolType === 'drill') {
+    config.tip_angle = parseFloat($input('tip-angle').value) || 118;
+    config.tip_compensation = ($input('tip-compensation') as HTMLInputElement).checked;
+  } else if (toolType === 'vbit') {
+    config.vbit_angle = parseFloat($input('vbit-angle').value) || 60;
+    config.tip_diameter = parseFloat($input('tip-diameter').value) || 0;
   }
   if (config.strategy === 'zigzag') {
     config.scan_direction = $select('scan-direction').value;
   }
+  if (config.strategy === 'waterline') {
+    config.climb_cut = ($input('waterline-climb-cut') as HTMLInputElement).checked;
+    config.slope_limit = parseFloat($input('slope-limit').value) || 0;
+  }
+  if (config.strategy === 'adaptive') {
+    config.climb_cut = ($input('adaptive-climb-cut') as HTMLInputElement).checked;
+    config.max_engagement = (parseFloat($input('max-engagement').value) || 20) / 100;
+  }
+  if (config.strategy === 'drill') {
+    config.drill_max_diameter = parseFloat($input('drill-max-diameter').value) || 0;
+    config.drill_retract = parseFloat($input('drill-retract').value) || 0;
+    config.peck_depth = parseFloat($input('peck-depth').value) || 0;
+    config.dwell = parseFloat($input('dwell').value) || 0;
+  }
+  if (config.strategy === 'vcarve') {
+    config.flat_bottom = ($input('flat-bottom') as HTMLInputElement).checked;
+  }
   if (config.strategy === 'perimeter') {
     config.climb_cut = ($input('climb-cut') as HTMLInputElement).checked;
     config.perimeter_passes = parseInt($input('perimeter-passes').value) || 1;
   }
+  config.tab_count = parseInt($input('tab-count').value) || 0;
+  config.tab_spacing = parseFloat($input('tab-spacing').value) || 0;
+  config.tab_width = parseFloat($input('tab-width').value) || 0;
+  config.tab_height = parseFloat($input('tab-height').value) || 0;
+  config.arc_tolerance = parseFloat($input('arc-tolerance').value) || 0;
+  config.optimize_order = ($input('optimize-order') as HTMLInputElement).checked;
+  config.optimize_start_points = ($input('optimize-start-points') as HTMLInputElement).checked;
+  if (($input('use-setup') as HTMLInputElement).checked) {
+    const num = (id: string) => parseFloat($input(id).value) || 0;
+    config.setup = {
+      stock: {
+        shape: $select('stock-shape').value as StockShape,
+        size: [num('stock-x'), num('stock-y'), num('stock-z')],
+        margin: num('stock-margin'),
+      },
+      model: {
+        scale: num('model-scale') || 1,
+        mirror: [($input('model-mirror') as HTMLInputElement).checked, false, false],
+        rotate: [0, 0, num('model-rotate')],
+      },
+      origin: $select('origin-xy').value as Setup['origin'],
+      origin_z: $select('origin-z').value as Setup['origin_z'],
+      work_offset: $select('work-offset').value as WorkOffset,
+    };
+  }
+  const layers = $input('dxf-layers').value.split(',').map(l => l.trim()).filter(l => l);
+  if (layers.length) config.layers = layers;
+  const colors = $input('svg-colors').value.split(',').map(c => c.trim()).filter(c => c);
+  if (colors.length) config.colors = colors;
+  const post = $select('post-processor').value;
+  if (post === 'custom') config.custom_post = $textarea('custom-post').value;
+  else config.post_processor = post;
   return JSON.stringify(config);
 }
 
@@ -190,14 +325,16 @@ export function getConfig(): string {
 export function tryPreview(): void {
   if (!wasmReady || !fileData) return;
   try {
-    let json: string;
-    if (fileType === 'stl') json = preview_stl(fileData as Uint8Array, getConfig());
-    else json = preview_svg(fileData as string);
-    drawPreview(JSON.parse(json));
+    if (fileType === 'stl') {
+      drawPreview(JSON.parse(preview_stl(fileData as Uint8Array, getConfig())));
+    } else {
+      const preview = JSON.parse(preview_svg(fileData as string, getConfig()));
+      drawPreview(preview.paths, preview.tabs);
+    }
   } catch (e) { console.warn('Preview error:', e); }
 }
 
-function drawPreview(paths: number[][][]): void {
+function drawPreview(paths: number[][][], tabs: PreviewTab[] = []): void {
   const ctx = canvas.getContext('2d')!;
   const dpr = window.devicePixelRatio || 1;
   const rect = canvas.getBoundingClientRect();
@@ -271,6 +408,12 @@ function drawPreview(paths: number[][][]): void {
       ctx.stroke();
     }
   }
+
+  ctx.fillStyle = theme.colors.camTab;
+  for (const t of tabs) {
+    const r = Math.max(3, (t.width * scale) / 2);
+    ctx.fillRect(tx(t.x) - r, ty(t.y) - r, r * 2, r * 2);
+  }
 }
 
 // ── Generate (Web Worker) ────────────────────────────────────────────
@@ -80,6 +80,8 @@ function syncShapesToActor(): void {
       for (const p of s.points) {
         ids.push(JSON.parse(sketch_add_point(p.x, p.y)).id);
       }
+    } else if (s.type === 'point') {
+      ids.push(JSON.parse(sketch_add_point(s.x, s.y)).id);
     }
     shapePointIds.push({ shapeIdx: i, pointIds: ids });
   }
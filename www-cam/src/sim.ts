@@ -1,9 +1,12 @@
 /** Tool simulation module. */
 
-import { sim_moves_stl, sim_moves_svg } from '../pkg/rustcam.js';
+import {
+  sim_moves_stl, sim_moves_svg, simulate_stock_stl, simulate_stock_svg, interpret_gcode,
+  cycle_time_stl, cycle_time_svg, cycle_time_gcode, rapid_report_stl, rapid_report_svg,
+} from '../pkg/rustcam.js';
 import { $, $input, $canvas, $btn } from './dom.js';
 import { fileData, fileType, getConfig } from './cam.js';
-import type { SimMove, SimBounds } from './types.js';
+import type { SimMove, SimBounds, SimReport, GcodeProgram, CycleTime, RapidReport } from './types.js';
 import { theme } from './theme.js';
 
 const simCanvas = $canvas('sim-canvas');
@@ -12,6 +15,9 @@ const simReset  = $btn('sim-reset');
 const simSpeed  = $input('sim-speed');
 const simScrub  = $input('sim-scrub');
 const simInfo   = $('sim-info');
+const simStock  = $btn('sim-stock');
+const simGcode  = $input('sim-gcode-file');
+const simVel    = $canvas('sim-velocity');
 
 let simMoves: SimMove[] = [];
 let simIdx = 0;
@@ -25,6 +31,12 @@ let simOffY = 0;
 let matCanvas: HTMLCanvasElement | null = null;
 let matCtx: CanvasRenderingContext2D | null = null;
 
+let cycleTime: CycleTime | null = null;
+let rapids: RapidReport | null = null;
+
+let stockReport: SimReport | null = null;
+let stockCanvas: HTMLCanvasElement | null = null;
+
 export function loadSim(): void {
   try {
     const cfg = getConfig();
@@ -33,12 +45,30 @@ export function loadSim(): void {
     else json = sim_moves_svg(fileData as string, cfg);
     simMoves = JSON.parse(json);
   } catch (e) { simMoves = []; console.warn('sim_moves error:', e); }
+  try {
+    const cfg = getConfig();
+    cycleTime = JSON.parse(fileType === 'stl'
+      ? cycle_time_stl(fileData as Uint8Array, cfg)
+      : cycle_time_svg(fileData as string, cfg));
+  } catch (e) { cycleTime = null; console.warn('cycle_time error:', e); }
+  try {
+    const cfg = getConfig();
+    rapids = JSON.parse(fileType === 'stl'
+      ? rapid_report_stl(fileData as Uint8Array, cfg)
+      : rapid_report_svg(fileData as string, cfg));
+  } catch (e) { rapids = null; console.warn('rapid_report error:', e); }
+  resetSim();
+}
+
+function resetSim(): void {
   simIdx = 0;
   (simScrub as HTMLInputElement).max = String(Math.max(simMoves.length - 1, 1));
   simScrub.value = '0';
   simInfo.textContent = `0 / ${simMoves.length}`;
   simRunning = false;
   simPlay.textContent = 'Play';
+  stockReport = null;
+  stockCanvas = null;
   computeSimBounds();
   initMatCanvas();
   drawSimFrame();
@@ -136,7 +166,8 @@ function drawSimFrame(): void {
     );
   }
 
-  if (matCanvas) ctx.drawImage(matCanvas, 0, 0, matCanvas.width / dpr, matCanvas.height / dpr);
+  if (stockReport && stockCanvas) drawStock(ctx);
+  else if (matCanvas) ctx.drawImage(matCanvas, 0, 0, matCanvas.width / dpr, matCanvas.height / dpr);
 
   if (simMoves.length > 1 && simIdx > 0) {
     ctx.lineWidth = 0.8;
@@ -172,8 +203,113 @@ function drawSimFrame(): void {
 
   ctx.restore();
   simInfo.textContent = `${simIdx} / ${simMoves.length}`;
+  if (stockReport) {
+    const r = stockReport;
+    simInfo.textContent += ` · ${r.gouges.count} gouge, ${r.leftover.count} leftover,`
+      + ` ${r.rapid_collisions.length} rapid hit`;
+  }
+  if (cycleTime) simInfo.textContent += ` · ${formatDuration(cycleTime.total)}`;
+  if (rapids) {
+    simInfo.textContent += ` · rapids ${rapids.after.toFixed(0)} mm`;
+    if (rapids.after < rapids.before - 0.5) simInfo.textContent += ` (was ${rapids.before.toFixed(0)})`;
+  }
+  drawVelocity();
+}
+
+// ── Cycle time ───────────────────────────────────────────────────────
+
+function formatDuration(s: number): string {
+  const m = Math.floor(s / 60);
+  return m > 0 ? `${m}m ${Math.round(s % 60)}s` : `${s.toFixed(1)}s`;
+}
+
+/** Planned feed over time, with the current move marked. */
+function drawVelocity(): void {
+  const ctx = simVel.getContext('2d')!;
+  const rect = simVel.getBoundingClientRect();
+  if (rect.width < 1) return;
+  const dpr = window.devicePixelRatio || 1;
+  simVel.width = rect.width * dpr;
+  simVel.height = rect.height * dpr;
+  ctx.setTransform(dpr, 0, 0, dpr, 0, 0);
+  ctx.clearRect(0, 0, rect.width, rect.height);
+  const profile = cycleTime?.profile ?? [];
+  if (profile.length < 2 || cycleTime!.total <= 0) return;
+  const vMax = profile.reduce((m, s) => Math.max(m, s.v), 1);
+  const px = (t: number) => t / cycleTime!.total * rect.width;
+  const py = (v: number) => rect.height - 2 - v / vMax * (rect.height - 4);
+  ctx.strokeStyle = theme.colors.simCutting;
+  ctx.lineWidth = 1;
+  ctx.beginPath();
+  profile.forEach((s, i) => i ? ctx.lineTo(px(s.t), py(s.v)) : ctx.moveTo(px(s.t), py(s.v)));
+  ctx.stroke();
+  const at = profile.find(s => s.move >= simIdx);
+  if (at) {
+    ctx.strokeStyle = theme.colors.textDim;
+    ctx.beginPath(); ctx.moveTo(px(at.t), 0); ctx.lineTo(px(at.t), rect.height); ctx.stroke();
+  }
+  ctx.fillStyle = theme.colors.textDim; ctx.font = '10px monospace';
+  ctx.fillText(`${Math.round(vMax)} mm/min`, 4, 10);
 }
 
+// ── Stock check ──────────────────────────────────────────────────────
+
+/** Draw the stock depth image with gouges and rapid collisions marked. */
+function drawStock(ctx: CanvasRenderingContext2D): void {
+  const img = stockReport!.image;
+  const x0 = img.origin[0], y1 = img.origin[1] + img.height * img.cell_size;
+  ctx.imageSmoothingEnabled = false;
+  ctx.drawImage(
+    stockCanvas!, simTx(x0), simTy(y1),
+    img.width * img.cell_size * simScale, img.height * img.cell_size * simScale,
+  );
+  const mark = (x: number, y: number, color: string): void => {
+    ctx.beginPath(); ctx.arc(simTx(x), simTy(y), 3, 0, Math.PI * 2);
+    ctx.fillStyle = color; ctx.fill();
+  };
+  for (const g of stockReport!.gouges.samples) mark(g.x, g.y, theme.colors.danger);
+  for (const c of stockReport!.rapid_collisions) mark(c.x, c.y, theme.colors.warning);
+}
+
+simStock.addEventListener('click', () => {
+  try {
+    const cfg = getConfig();
+    const json = fileType === 'stl'
+      ? simulate_stock_stl(fileData as Uint8Array, cfg)
+      : simulate_stock_svg(fileData as string, cfg);
+    stockReport = JSON.parse(json) as SimReport;
+  } catch (e) { stockReport = null; console.warn('simulate_stock error:', e); return; }
+  const img = stockReport.image;
+  stockCanvas = document.createElement('canvas');
+  stockCanvas.width = img.width;
+  stockCanvas.height = img.height;
+  const sctx = stockCanvas.getContext('2d')!;
+  const data = sctx.createImageData(img.width, img.height);
+  img.pixels.forEach((v, i) => {
+    data.data.set([v, v, v, 255], i * 4);
+  });
+  sctx.putImageData(data, 0, 0);
+  drawSimFrame();
+});
+
+// ── G-code back-plot ─────────────────────────────────────────────────
+
+simGcode.addEventListener('change', async () => {
+  const file = simGcode.files?.[0];
+  if (!file) return;
+  try {
+    const text = await file.text();
+    const program: GcodeProgram = JSON.parse(interpret_gcode(text, '{}'));
+    simMoves = program.moves;
+    cycleTime = JSON.parse(cycle_time_gcode(text, getConfig()));
+    rapids = null;
+    for (const w of program.warnings) console.warn(`${file.name}:${w.line_number}: ${w.message}`);
+    resetSim();
+    if (program.warnings.length) simInfo.textContent += ` (${program.warnings.length} warnings)`;
+  } catch (e) { console.warn('interpret_gcode error:', e); }
+  simGcode.value = '';
+});
+
 // ── Transport controls ───────────────────────────────────────────────
 
 simPlay.addEventListener('click', () => {
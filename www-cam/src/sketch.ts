@@ -1,5 +1,6 @@
 /** 2D sketch drawing engine. */
 
+import { import_dxf } from '../pkg/rustcam.js';
 import { $, $input, $canvas } from './dom.js';
 import type { SketchShape, DraftShape } from './types.js';
 import { theme } from './theme.js';
@@ -170,6 +171,14 @@ function drawShape(
       ctx.arc(tx(shape.cx), ty(shape.cy), shape.r * scale, 0, Math.PI * 2);
       ctx.stroke();
       break;
+    case 'point': {
+      const px = tx(shape.x), py = ty(shape.y);
+      ctx.beginPath();
+      ctx.moveTo(px - 4, py); ctx.lineTo(px + 4, py);
+      ctx.moveTo(px, py - 4); ctx.lineTo(px, py + 4);
+      ctx.stroke();
+      break;
+    }
     case 'polyline':
       if (shape.points.length < 2) break;
       ctx.beginPath();
@@ -188,7 +197,7 @@ let sketchStart: { x: number; y: number } | null = null;
 
 sketchCvs.addEventListener('mousedown', (e: MouseEvent) => {
   if (getCurrentMode() !== 'sketch') return;
-  if (sketchTool === 'polyline') return;
+  if (sketchTool === 'polyline' || sketchTool === 'point') return;
   const p = sketchScreenToWorld(e.clientX, e.clientY);
   sketchMouseDown = true;
   sketchStart = p;
@@ -237,6 +246,15 @@ sketchCvs.addEventListener('mouseup', () => {
   redrawSketch();
 });
 
+// Point tool
+sketchCvs.addEventListener('click', (e: MouseEvent) => {
+  if (getCurrentMode() !== 'sketch' || sketchTool !== 'point') return;
+  const p = sketchScreenToWorld(e.clientX, e.clientY);
+  sketchShapes.push({ type: 'point', x: p.x, y: p.y });
+  updateShapeList();
+  redrawSketch();
+});
+
 // Polyline tool
 sketchCvs.addEventListener('click', (e: MouseEvent) => {
   if (getCurrentMode() !== 'sketch' || sketchTool !== 'polyline') return;
@@ -283,6 +301,7 @@ export function updateShapeList(): void {
     else if (s.type === 'rect') desc = `Rect ${Math.abs(s.w)}×${Math.abs(s.h)} at (${Math.min(s.x, s.x + s.w)},${Math.min(s.y, s.y + s.h)})`;
     else if (s.type === 'circle') desc = `Circle r=${s.r} at (${s.cx},${s.cy})`;
     else if (s.type === 'polyline') desc = `Polyline ${s.points.length} pts`;
+    else if (s.type === 'point') desc = `Point at (${s.x},${s.y})`;
     return `<div>${i + 1}. ${desc}</div>`;
   }).join('');
   if (onShapeListUpdate) onShapeListUpdate();
@@ -301,6 +320,34 @@ $('sketch-clear').addEventListener('click', () => {
   redrawSketch();
 });
 
+// ── DXF import ───────────────────────────────────────────────────────
+
+$input('sketch-dxf-file').addEventListener('change', () => {
+  const file = $input('sketch-dxf-file').files?.[0];
+  if (!file) return;
+  file.text().then(txt => {
+    const status = $('sketch-status');
+    try {
+      const drawing = JSON.parse(import_dxf(txt, '[]')) as {
+        polylines: Array<{ points: Array<{ x: number; y: number }>; closed: boolean }>;
+      };
+      for (const pl of drawing.polylines) {
+        const points = pl.points.map(p => ({ x: p.x, y: p.y }));
+        if (pl.closed) points.push(points[0]);
+        sketchShapes.push({ type: 'polyline', points });
+      }
+      status.textContent = `Imported ${drawing.polylines.length} contours from ${file.name}`;
+      status.className = 'text-xs mt-2 min-h-4 text-text-dim';
+    } catch (err) {
+      status.textContent = String(err);
+      status.className = 'text-xs mt-2 min-h-4 text-danger';
+    }
+    $input('sketch-dxf-file').value = '';
+    updateShapeList();
+    redrawSketch();
+  });
+});
+
 // ── Canvas size change ───────────────────────────────────────────────
 
 $input('canvas-size').addEventListener('change', redrawSketch);
@@ -329,6 +376,10 @@ export function sketchToSvg(): string {
         elements += `<polyline points="${pts}"/>`;
         break;
       }
+      case 'point':
+        // A move with no drawing: a single-point path, drilled as a hole.
+        elements += `<path d="M ${s.x} ${s.y}"/>`;
+        break;
     }
   }
   return `<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 ${size} ${size}">${elements}</svg>`;
@@ -45,6 +45,7 @@ export const theme = {
     // CAM preview colors
     camLaser: 'rgba(255, 60, 40, 0.85)',
     camZDefault: '#4f8cff',
+    camTab: '#ffb020',
 
     // Hover state
     btnSecondaryHover: '#3a3d4a',
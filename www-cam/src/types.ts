@@ -17,16 +17,227 @@ export interface CamConfig {
   scan_direction?: string;
   climb_cut?: boolean;
   perimeter_passes?: number;
+  max_engagement?: number;
+  slope_limit?: number;
+  entry_mode?: string;
+  ramp_angle?: number;
+  helix_diameter?: number;
+  lead_in?: string;
+  lead_out?: string;
+  lead_radius?: number;
+  compensation?: string;
+  tab_count?: number;
+  tab_spacing?: number;
+  tab_width?: number;
+  tab_height?: number;
+  drill_max_diameter?: number;
+  drill_retract?: number;
+  peck_depth?: number;
+  dwell?: number;
+  tip_angle?: number;
+  tip_compensation?: boolean;
+  vbit_angle?: number;
+  tip_diameter?: number;
+  flat_bottom?: boolean;
+  arc_tolerance?: number;
+  optimize_order?: boolean;
+  optimize_start_points?: boolean;
+  setup?: Setup;
+  layers?: string[];
+  colors?: string[];
+  post_processor?: string;
+  custom_post?: string;
   laser_power?: number;
   passes?: number;
   air_assist?: boolean;
 }
 
+export type StockShape = 'box' | 'cylinder';
+export type WorkOffset = 'G54' | 'G55' | 'G56' | 'G57' | 'G58' | 'G59';
+
+/** Stock, model placement and work origin (`rustcam::setup::Setup`). */
+export interface Setup {
+  stock?: {
+    shape?: StockShape;
+    /** X, Y, Z in mm; 0 fits the model plus `margin`. */
+    size?: [number, number, number];
+    margin?: number;
+    /** Model bounding-box corner from the stock's; omitted centres it. */
+    offset?: [number, number, number];
+  };
+  model?: {
+    scale?: number;
+    mirror?: [boolean, boolean, boolean];
+    /** Degrees about X, then Y, then Z. */
+    rotate?: [number, number, number];
+    translate?: [number, number, number];
+  };
+  origin?: 'front_left' | 'front_right' | 'back_left' | 'back_right' | 'center';
+  origin_z?: 'top' | 'bottom';
+  work_offset?: WorkOffset;
+}
+
+/** A library tool, as serialized by `rustcam::tool::Tool`. */
+export interface ToolEntry {
+  name?: string;
+  tool: {
+    tool_type:
+      | { type: 'end_mill' | 'ball_end' }
+      | { type: 'face_mill'; effective_diameter: number }
+      | { type: 'drill'; tip_angle: number; tip_compensation?: boolean }
+      | { type: 'vbit'; angle: number; tip_diameter?: number };
+    diameter: number;
+    flute_length: number;
+    corner_radius: number;
+  };
+}
+
+/** Tools keyed by T number. */
+export type ToolLibrary = Record<string, ToolEntry>;
+
+/** Engraved text, laid out by the wasm `process_text` / `text_outlines`. */
+export interface TextOptions {
+  text: string;
+  /** Built-in single-stroke font (`available_fonts`). */
+  font?: string;
+  /** SVG font document; overrides `font`. */
+  svg_font?: string;
+  /** Cap height in mm. */
+  size?: number;
+  spacing?: number;
+  line_spacing?: number;
+  align?: 'left' | 'center' | 'right';
+  x?: number;
+  y?: number;
+  /** Italic slant in degrees. */
+  slant?: number;
+  /** Bend the baseline round a circle; negative runs along the bottom. */
+  arc_radius?: number;
+  arc_angle?: number;
+  /** `{serial}` in `text` is numbered one program per part. */
+  serial?: { start?: number; count?: number; step?: number; digits?: number };
+}
+
+/** A PNG/BMP raster engraving, for the wasm `process_image` / `sim_moves_image`. */
+export interface RasterParams {
+  /** Engraved size in mm; `height` defaults to the image's aspect ratio. */
+  width?: number;
+  height?: number;
+  /** Lower-left corner in mm. */
+  x?: number;
+  y?: number;
+  /** Scanline spacing and dot width in mm. */
+  line_interval?: number;
+  dither?: 'threshold' | 'floyd_steinberg' | 'jarvis' | 'grayscale';
+  /** Darkness (0-1) from which a dot burns. */
+  threshold?: number;
+  invert?: boolean;
+  /** Power (%) for the lightest gray in grayscale mode. */
+  min_power?: number;
+  /** Laser-off travel past each end of a line, in mm. */
+  overscan?: number;
+  bidirectional?: boolean;
+}
+
+/** One job step; tool fields of the config come from the library. */
+export interface Operation extends Partial<CamConfig> {
+  name?: string;
+  tool: number;
+  /** Cut only where the earlier operations left material. */
+  rest_machining?: boolean;
+}
+
+export interface Job {
+  name?: string;
+  tools: ToolLibrary;
+  operations: Operation[];
+  post_processor?: string;
+  custom_post?: string;
+}
+
 export interface SimMove {
   x: number;
   y: number;
   z: number;
   rapid: boolean;
+  tab?: boolean;
+}
+
+export interface PreviewTab {
+  x: number;
+  y: number;
+  width: number;
+  height: number;
+}
+
+export interface StockDefect {
+  x: number;
+  y: number;
+  depth: number;
+}
+
+export interface DefectSummary {
+  count: number;
+  max_depth: number;
+  samples: StockDefect[];
+}
+
+/** Result of `simulate_stock_*`. */
+export interface SimReport {
+  removed_volume: number;
+  gouges: DefectSummary;
+  leftover: DefectSummary;
+  rapid_collisions: { move_index: number; x: number; y: number; z: number; depth: number }[];
+  image: {
+    width: number;
+    height: number;
+    origin: [number, number];
+    cell_size: number;
+    min_z: number;
+    max_z: number;
+    pixels: number[];
+  };
+}
+
+/** Result of interpreting a G-code program for back-plotting. */
+export interface GcodeProgram {
+  moves: (SimMove & { feed: number; line: number; tool: number; dwell: number })[];
+  stats: {
+    bounds: [[number, number, number], [number, number, number]] | null;
+    cut_bounds: [[number, number, number], [number, number, number]] | null;
+    cut_distance: number;
+    rapid_distance: number;
+    feed_time: number;
+    dwell_time: number;
+    tool_changes: { line: number; tool: number }[];
+    lines: number;
+  };
+  warnings: { line_number: number; message: string }[];
+}
+
+/** Rapid travel (mm) as planned and with cut ordering. */
+export interface RapidReport {
+  before: number;
+  after: number;
+}
+
+/** Acceleration-aware run time from the motion planner. */
+export interface CycleTime {
+  total: number;
+  naive_total: number;
+  operations: {
+    name: string;
+    time: number;
+    naive_time: number;
+    feed_time: number;
+    rapid_time: number;
+    dwell_time: number;
+    cut_distance: number;
+    rapid_distance: number;
+    moves: number;
+  }[];
+  /** Corners of the velocity profile: seconds, mm/min, move index. */
+  profile: { t: number; v: number; move: number }[];
 }
 
 export interface SimBounds {
@@ -65,7 +276,14 @@ export interface PolylineShape {
   points: Array<{ x: number; y: number }>;
 }
 
-export type SketchShape = LineShape | RectShape | CircleShape | PolylineShape;
+/** A lone point, e.g. a hole position for drilling. */
+export interface PointShape {
+  type: 'point';
+  x: number;
+  y: number;
+}
+
+export type SketchShape = LineShape | RectShape | CircleShape | PolylineShape | PointShape;
 
 export interface DraftShape {
   type?: string;
@@ -20,7 +20,7 @@ export function preview_stl(data: Uint8Array, config_json: string): string;
 /**
  * Return toolpath data from SVG as JSON (for the 2-D preview canvas).
  */
-export function preview_svg(svg_text: string): string;
+export function preview_svg(svg_text: string, config_json: string): string;
 
 /**
  * Process an STL file (binary bytes) and return G-code.
@@ -124,7 +124,7 @@ export interface InitOutput {
     readonly available_profiles: () => [number, number];
     readonly default_config: (a: number, b: number) => [number, number];
     readonly preview_stl: (a: number, b: number, c: number, d: number) => [number, number, number, number];
-    readonly preview_svg: (a: number, b: number) => [number, number, number, number];
+    readonly preview_svg: (a: number, b: number, c: number, d: number) => [number, number, number, number];
     readonly process_stl: (a: number, b: number, c: number, d: number) => [number, number, number, number];
     readonly process_stl_progress: (a: number, b: number, c: number, d: number, e: any) => [number, number, number, number];
     readonly process_svg: (a: number, b: number, c: number, d: number) => [number, number, number, number];
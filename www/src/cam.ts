@@ -192,8 +192,9 @@ export function tryPreview(): void {
   try {
     let json: string;
     if (fileType === 'stl') json = preview_stl(fileData as Uint8Array, getConfig());
-    else json = preview_svg(fileData as string);
-    drawPreview(JSON.parse(json));
+    else json = preview_svg(fileData as string, getConfig());
+    const parsed = JSON.parse(json);
+    drawPreview(Array.isArray(parsed) ? parsed : parsed.paths);
   } catch (e) { console.warn('Preview error:', e); }
 }
 